@@ -0,0 +1,497 @@
+//! Portable checkpoint archives for handing a session over to another machine
+//!
+//! An archive is a zstd-compressed JSON document holding the timeline, the
+//! manifest of every exported checkpoint and each referenced blob once.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::storage::CheckpointStorage;
+use super::{
+    is_safe_relative_path, is_valid_id, Checkpoint, CheckpointManifest, CheckpointPaths,
+    FileSnapshot, ManifestEntry, SessionTimeline, TimelineNode,
+};
+
+/// Bumped whenever the archive layout changes incompatibly
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckpointArchive {
+    format_version: u32,
+    exported_at: DateTime<Utc>,
+    /// Timeline restricted to the exported checkpoints
+    timeline: SessionTimeline,
+    /// Exported checkpoints, parents before their children
+    checkpoints: Vec<ArchivedCheckpoint>,
+    /// Content hash -> content of every file and messages blob
+    objects: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedCheckpoint {
+    checkpoint: Checkpoint,
+    manifest: CheckpointManifest,
+}
+
+/// Result of exporting or importing an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSummary {
+    /// Archive file written or read
+    pub archive_path: String,
+    /// Session the checkpoints belong to (the new session on import)
+    pub session_id: String,
+    pub checkpoint_count: usize,
+    pub object_count: usize,
+    /// Size of the archive file in bytes
+    pub archive_size: u64,
+    /// Problems that did not abort the operation
+    pub warnings: Vec<String>,
+}
+
+/// Export one checkpoint, or the whole timeline when `checkpoint_id` is None
+pub fn export_archive(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    session_id: &str,
+    checkpoint_id: Option<&str>,
+    archive_path: &Path,
+) -> Result<ArchiveSummary> {
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+    let mut timeline = storage.load_timeline(&paths.timeline_file)?;
+
+    if let Some(checkpoint_id) = checkpoint_id {
+        let node = timeline
+            .find_checkpoint(checkpoint_id)
+            .ok_or_else(|| anyhow::anyhow!("Checkpoint not found: {}", checkpoint_id))?;
+        let mut checkpoint = node.checkpoint.clone();
+        checkpoint.parent_checkpoint_id = None;
+        timeline.root_node = Some(TimelineNode {
+            checkpoint,
+            children: Vec::new(),
+            file_snapshot_ids: node.file_snapshot_ids.clone(),
+        });
+        timeline.current_checkpoint_id = Some(checkpoint_id.to_string());
+        timeline.branches.clear();
+        timeline.current_branch_id = None;
+        timeline.total_checkpoints = 1;
+    }
+
+    let mut ordered = Vec::new();
+    if let Some(root) = &timeline.root_node {
+        collect_preorder(root, &mut ordered);
+    }
+    if ordered.is_empty() {
+        anyhow::bail!("Session {} has no checkpoints to export", session_id);
+    }
+
+    let mut objects = HashMap::new();
+    let mut checkpoints = Vec::with_capacity(ordered.len());
+    for checkpoint in ordered {
+        let (_, snapshots, messages) =
+            storage.load_checkpoint(project_id, session_id, &checkpoint.id)?;
+
+        let messages_hash = CheckpointStorage::calculate_file_hash(&messages);
+        objects.entry(messages_hash.clone()).or_insert(messages);
+
+        let mut files = Vec::with_capacity(snapshots.len());
+        for snapshot in snapshots {
+            files.push(ManifestEntry {
+                path: snapshot.file_path,
+                hash: snapshot.hash.clone(),
+                is_deleted: snapshot.is_deleted,
+                permissions: snapshot.permissions,
+                size: snapshot.size,
+            });
+            if !snapshot.is_deleted {
+                objects.entry(snapshot.hash).or_insert(snapshot.content);
+            }
+        }
+
+        checkpoints.push(ArchivedCheckpoint {
+            checkpoint,
+            manifest: CheckpointManifest {
+                messages_hash,
+                files,
+            },
+        });
+    }
+
+    let archive = CheckpointArchive {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        timeline,
+        checkpoints,
+        objects,
+    };
+
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent).context("Failed to create archive directory")?;
+    }
+    let file = fs::File::create(archive_path).context("Failed to create archive")?;
+    let mut encoder =
+        zstd::stream::Encoder::new(file, 3).context("Failed to start archive compression")?;
+    serde_json::to_writer(&mut encoder, &archive).context("Failed to write archive")?;
+    encoder.finish().context("Failed to finish archive")?;
+
+    Ok(ArchiveSummary {
+        archive_path: archive_path.to_string_lossy().to_string(),
+        session_id: session_id.to_string(),
+        checkpoint_count: archive.checkpoints.len(),
+        object_count: archive.objects.len(),
+        archive_size: fs::metadata(archive_path).map(|m| m.len()).unwrap_or(0),
+        warnings: Vec::new(),
+    })
+}
+
+/// Import an archive into `project_id` as a new session
+///
+/// The session keeps its original ID unless `session_id` is given. The
+/// conversation at each branch head is written as a session transcript so
+/// the imported work can be resumed; project files are left untouched until
+/// a checkpoint is restored.
+pub fn import_archive(
+    storage: &CheckpointStorage,
+    archive_path: &Path,
+    project_id: &str,
+    session_id: Option<String>,
+) -> Result<ArchiveSummary> {
+    let file = fs::File::open(archive_path).context("Failed to open archive")?;
+    let decoder =
+        zstd::stream::Decoder::new(file).context("Failed to start archive decompression")?;
+    let archive: CheckpointArchive =
+        serde_json::from_reader(decoder).context("Failed to parse archive")?;
+
+    if archive.format_version != ARCHIVE_FORMAT_VERSION {
+        anyhow::bail!(
+            "Unsupported archive format version {}",
+            archive.format_version
+        );
+    }
+    for (hash, content) in &archive.objects {
+        if &CheckpointStorage::calculate_file_hash(content) != hash {
+            anyhow::bail!("Archive is corrupted: content does not match hash {}", hash);
+        }
+    }
+
+    let source_session_id = archive.timeline.session_id.clone();
+    let session_id = session_id.unwrap_or_else(|| source_session_id.clone());
+    validate_archive(&archive, &session_id)?;
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, &session_id);
+    if paths.timeline_file.exists()
+        && storage
+            .load_timeline(&paths.timeline_file)
+            .map(|t| t.root_node.is_some())
+            .unwrap_or(true)
+    {
+        anyhow::bail!(
+            "Session {} already has checkpoints in this project",
+            session_id
+        );
+    }
+    storage.init_storage(project_id, &session_id)?;
+
+    let object = |hash: &str| -> Result<String> {
+        archive
+            .objects
+            .get(hash)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Archive is missing content {}", hash))
+    };
+
+    let mut warnings = Vec::new();
+    for archived in &archive.checkpoints {
+        let mut checkpoint = archived.checkpoint.clone();
+        checkpoint.project_id = project_id.to_string();
+        checkpoint.session_id = session_id.clone();
+
+        let mut snapshots = Vec::with_capacity(archived.manifest.files.len());
+        for entry in &archived.manifest.files {
+            snapshots.push(FileSnapshot {
+                checkpoint_id: checkpoint.id.clone(),
+                file_path: entry.path.clone(),
+                content: if entry.is_deleted {
+                    String::new()
+                } else {
+                    object(&entry.hash)?
+                },
+                hash: entry.hash.clone(),
+                is_deleted: entry.is_deleted,
+                permissions: entry.permissions,
+                size: entry.size,
+            });
+        }
+        let messages = object(&archived.manifest.messages_hash)?;
+
+        let result =
+            storage.save_checkpoint(project_id, &session_id, &checkpoint, snapshots, &messages)?;
+        warnings.extend(result.warnings);
+    }
+
+    // Carry over branches and settings the per-checkpoint saves do not restore
+    let mut timeline = storage.load_timeline(&paths.timeline_file)?;
+    timeline.current_checkpoint_id = archive.timeline.current_checkpoint_id.clone();
+    timeline.auto_checkpoint_enabled = archive.timeline.auto_checkpoint_enabled;
+    timeline.checkpoint_strategy = archive.timeline.checkpoint_strategy.clone();
+    timeline.auto_checkpoint_min_interval_secs = archive.timeline.auto_checkpoint_min_interval_secs;
+    timeline.current_branch_id = archive.timeline.current_branch_id.clone();
+    timeline.branches = archive.timeline.branches.clone();
+    for branch in &mut timeline.branches {
+        if branch.session_id == source_session_id {
+            branch.session_id = session_id.clone();
+        }
+    }
+    storage.save_timeline(&paths.timeline_file, &timeline)?;
+
+    // Write a transcript for every line of work so it can be resumed
+    let mut transcripts = vec![(session_id.clone(), timeline.current_checkpoint_id.clone())];
+    if let Some(main) = timeline.find_branch(super::MAIN_BRANCH_ID) {
+        transcripts[0].1 = main.head_checkpoint_id.clone();
+    }
+    for branch in &timeline.branches {
+        if branch.session_id != session_id {
+            transcripts.push((branch.session_id.clone(), branch.head_checkpoint_id.clone()));
+        }
+    }
+    let project_dir = storage.claude_dir.join("projects").join(project_id);
+    for (transcript_session_id, head) in transcripts {
+        let Some(archived) = head
+            .as_deref()
+            .and_then(|id| archive.checkpoints.iter().find(|c| c.checkpoint.id == id))
+        else {
+            continue;
+        };
+        let transcript_path = project_dir.join(format!("{}.jsonl", transcript_session_id));
+        if transcript_path.exists() {
+            warnings.push(format!(
+                "Kept existing transcript for session {}",
+                transcript_session_id
+            ));
+            continue;
+        }
+        fs::write(&transcript_path, object(&archived.manifest.messages_hash)?)
+            .context("Failed to write session transcript")?;
+    }
+
+    Ok(ArchiveSummary {
+        archive_path: archive_path.to_string_lossy().to_string(),
+        session_id,
+        checkpoint_count: archive.checkpoints.len(),
+        object_count: archive.objects.len(),
+        archive_size: fs::metadata(archive_path).map(|m| m.len()).unwrap_or(0),
+        warnings,
+    })
+}
+
+/// Refuse archives whose IDs or file paths would be written outside the
+/// session and project directories
+fn validate_archive(archive: &CheckpointArchive, session_id: &str) -> Result<()> {
+    let check_id = |kind: &str, id: &str| -> Result<()> {
+        if !is_valid_id(id) {
+            anyhow::bail!("Archive contains an invalid {} ID: {:?}", kind, id);
+        }
+        Ok(())
+    };
+    check_id("session", session_id)?;
+    check_id("session", &archive.timeline.session_id)?;
+    for branch in &archive.timeline.branches {
+        check_id("session", &branch.session_id)?;
+    }
+    for archived in &archive.checkpoints {
+        check_id("checkpoint", &archived.checkpoint.id)?;
+        if let Some(parent) = &archived.checkpoint.parent_checkpoint_id {
+            check_id("checkpoint", parent)?;
+        }
+        for entry in &archived.manifest.files {
+            if !is_safe_relative_path(&entry.path) {
+                anyhow::bail!(
+                    "Archive contains a file outside the project: {}",
+                    entry.path.display()
+                );
+            }
+            if !entry.hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                anyhow::bail!("Archive contains an invalid content hash: {:?}", entry.hash);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collect checkpoints so that every parent comes before its children
+fn collect_preorder(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
+    checkpoints.push(node.checkpoint.clone());
+    for child in &node.children {
+        collect_preorder(child, checkpoints);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let checkpoint = Checkpoint {
+            id: CheckpointStorage::generate_checkpoint_id(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: Some("before refactor".to_string()),
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "unknown".to_string(),
+                user_prompt: String::new(),
+                file_changes: 1,
+                snapshot_size: 0,
+            },
+        };
+        let snapshot = FileSnapshot {
+            checkpoint_id: checkpoint.id.clone(),
+            file_path: PathBuf::from("src/main.rs"),
+            content: "fn main() {}".to_string(),
+            hash: CheckpointStorage::calculate_file_hash("fn main() {}"),
+            is_deleted: false,
+            permissions: None,
+            size: 12,
+        };
+        storage
+            .save_checkpoint(
+                "project",
+                "session",
+                &checkpoint,
+                vec![snapshot],
+                "{\"type\":\"user\"}",
+            )
+            .unwrap();
+
+        let archive_path = temp_dir.path().join("handoff.cwarchive");
+        let exported = export_archive(&storage, "project", "session", None, &archive_path).unwrap();
+        assert_eq!(exported.checkpoint_count, 1);
+
+        let imported = import_archive(
+            &storage,
+            &archive_path,
+            "other-project",
+            Some("imported".to_string()),
+        )
+        .unwrap();
+        assert_eq!(imported.session_id, "imported");
+
+        let (restored, files, messages) = storage
+            .load_checkpoint("other-project", "imported", &checkpoint.id)
+            .unwrap();
+        assert_eq!(restored.project_id, "other-project");
+        assert_eq!(restored.description.as_deref(), Some("before refactor"));
+        assert_eq!(files[0].content, "fn main() {}");
+        assert_eq!(messages, "{\"type\":\"user\"}");
+        assert!(temp_dir
+            .path()
+            .join("projects/other-project/imported.jsonl")
+            .exists());
+
+        // Importing into the same session twice is refused
+        assert!(import_archive(
+            &storage,
+            &archive_path,
+            "other-project",
+            Some("imported".to_string())
+        )
+        .is_err());
+    }
+
+    /// Rewrite an exported archive through `tamper` and write it back
+    fn tamper_archive(path: &Path, tamper: impl FnOnce(&mut serde_json::Value)) {
+        let decoder = zstd::stream::Decoder::new(fs::File::open(path).unwrap()).unwrap();
+        let mut archive: serde_json::Value = serde_json::from_reader(decoder).unwrap();
+        tamper(&mut archive);
+        let mut encoder = zstd::stream::Encoder::new(fs::File::create(path).unwrap(), 3).unwrap();
+        serde_json::to_writer(&mut encoder, &archive).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_import_rejects_path_traversal() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let checkpoint = Checkpoint {
+            id: CheckpointStorage::generate_checkpoint_id(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: None,
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "unknown".to_string(),
+                user_prompt: String::new(),
+                file_changes: 1,
+                snapshot_size: 0,
+            },
+        };
+        let snapshot = FileSnapshot {
+            checkpoint_id: checkpoint.id.clone(),
+            file_path: PathBuf::from("notes.txt"),
+            content: "hello".to_string(),
+            hash: CheckpointStorage::calculate_file_hash("hello"),
+            is_deleted: false,
+            permissions: None,
+            size: 5,
+        };
+        storage
+            .save_checkpoint("project", "session", &checkpoint, vec![snapshot], "{}")
+            .unwrap();
+
+        let archive_path = temp_dir.path().join("evil.cwarchive");
+        let import = |session: Option<&str>| {
+            import_archive(
+                &storage,
+                &archive_path,
+                "victim",
+                session.map(str::to_string),
+            )
+        };
+
+        for evil_path in ["../../escape.txt", "/etc/escape.txt"] {
+            export_archive(&storage, "project", "session", None, &archive_path).unwrap();
+            tamper_archive(&archive_path, |archive| {
+                archive["checkpoints"][0]["manifest"]["files"][0]["path"] =
+                    serde_json::json!(evil_path);
+            });
+            assert!(import(Some("fresh")).is_err(), "accepted {}", evil_path);
+        }
+
+        export_archive(&storage, "project", "session", None, &archive_path).unwrap();
+        tamper_archive(&archive_path, |archive| {
+            archive["timeline"]["sessionId"] = serde_json::json!("../../outside");
+        });
+        assert!(import(None).is_err());
+
+        export_archive(&storage, "project", "session", None, &archive_path).unwrap();
+        tamper_archive(&archive_path, |archive| {
+            archive["checkpoints"][0]["checkpoint"]["id"] = serde_json::json!("../cp");
+        });
+        assert!(import(Some("fresh")).is_err());
+
+        assert!(import(Some("../fresh")).is_err());
+        assert!(!temp_dir.path().join("escape.txt").exists());
+        assert!(!temp_dir.path().join("outside").exists());
+        assert!(!temp_dir.path().join("projects/victim").exists());
+    }
+}
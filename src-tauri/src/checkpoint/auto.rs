@@ -0,0 +1,124 @@
+//! Detection of completed file edits in Claude's `stream-json` output
+//!
+//! A file counts as modified once the tool result for a file-editing tool use
+//! arrives without an error, so failed or rejected edits do not trigger
+//! automatic checkpoints.
+use std::collections::HashMap;
+
+/// Tools whose successful result means a project file was written
+const FILE_EDIT_TOOLS: &[&str] = &["write", "edit", "multiedit", "notebookedit"];
+
+/// Follows tool uses across stream messages and reports finished file edits
+#[derive(Debug, Default)]
+pub struct FileEditDetector {
+    /// Pending tool_use ID -> path of the file being edited
+    pending: HashMap<String, String>,
+}
+
+impl FileEditDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one output line, returning the files whose edits completed with it
+    pub fn observe(&mut self, line: &str) -> Vec<String> {
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(line) else {
+            return Vec::new();
+        };
+        let Some(content) = msg
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+        else {
+            return Vec::new();
+        };
+
+        let mut completed = Vec::new();
+        match msg.get("type").and_then(|t| t.as_str()) {
+            Some("assistant") => {
+                for item in content {
+                    if item.get("type").and_then(|t| t.as_str()) != Some("tool_use") {
+                        continue;
+                    }
+                    let name = item
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("")
+                        .to_lowercase();
+                    if !FILE_EDIT_TOOLS.contains(&name.as_str()) {
+                        continue;
+                    }
+                    let input = item.get("input");
+                    let path = input
+                        .and_then(|i| i.get("file_path").or_else(|| i.get("notebook_path")))
+                        .and_then(|p| p.as_str());
+                    if let (Some(id), Some(path)) = (item.get("id").and_then(|i| i.as_str()), path)
+                    {
+                        self.pending.insert(id.to_string(), path.to_string());
+                    }
+                }
+            }
+            Some("user") => {
+                for item in content {
+                    if item.get("type").and_then(|t| t.as_str()) != Some("tool_result") {
+                        continue;
+                    }
+                    let Some(path) = item
+                        .get("tool_use_id")
+                        .and_then(|i| i.as_str())
+                        .and_then(|id| self.pending.remove(id))
+                    else {
+                        continue;
+                    };
+                    let is_error = item
+                        .get("is_error")
+                        .and_then(|e| e.as_bool())
+                        .unwrap_or(false);
+                    if !is_error && !completed.contains(&path) {
+                        completed.push(path);
+                    }
+                }
+            }
+            _ => {}
+        }
+        completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_completed_edits() {
+        let mut detector = FileEditDetector::new();
+        let assistant = r#"{"type":"assistant","message":{"content":[
+            {"type":"text","text":"Editing"},
+            {"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"/p/src/main.rs"}},
+            {"type":"tool_use","id":"t2","name":"Write","input":{"file_path":"/p/README.md"}},
+            {"type":"tool_use","id":"t3","name":"NotebookEdit","input":{"notebook_path":"/p/a.ipynb"}},
+            {"type":"tool_use","id":"t4","name":"Read","input":{"file_path":"/p/Cargo.toml"}}
+        ]}}"#
+            .replace('\n', "");
+        assert!(detector.observe(&assistant).is_empty());
+
+        let results = r#"{"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"t1","content":"ok"},
+            {"type":"tool_result","tool_use_id":"t2","content":"denied","is_error":true},
+            {"type":"tool_result","tool_use_id":"t3","content":"ok"},
+            {"type":"tool_result","tool_use_id":"t4","content":"[package]"}
+        ]}}"#
+            .replace('\n', "");
+        assert_eq!(
+            detector.observe(&results),
+            vec!["/p/src/main.rs".to_string(), "/p/a.ipynb".to_string()]
+        );
+
+        // Each edit is reported once, and other output is ignored
+        assert!(detector.observe(&results).is_empty());
+        assert!(detector.observe("not json").is_empty());
+        assert!(detector
+            .observe(r#"{"type":"result","result":"done"}"#)
+            .is_empty());
+    }
+}
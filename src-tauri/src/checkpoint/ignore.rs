@@ -0,0 +1,197 @@
+//! `.checkpointignore` rules deciding which project files are snapshotted
+//!
+//! The file uses a gitignore-like syntax: `#` comments, `!` to re-include,
+//! a trailing `/` for directories only and a `/` inside the pattern to match
+//! against the path from the project root instead of any file name. Rules of
+//! the project file come after the defaults, so `!target/` re-includes a
+//! default-ignored directory. Files that are binary or larger than
+//! `MAX_SNAPSHOT_FILE_SIZE` are never snapshotted.
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Name of the per-project ignore file in the project root
+pub const IGNORE_FILE_NAME: &str = ".checkpointignore";
+
+/// Rules applied to every project before its own ignore file
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] =
+    &[".git/", "node_modules/", "target/", "__pycache__/", "*.pyc"];
+
+/// Files above this size are left out of snapshots
+pub const MAX_SNAPSHOT_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// How many leading bytes are inspected to detect binary files
+const BINARY_SNIFF_LEN: usize = 8000;
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole relative path rather than the file name
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+
+        match Pattern::new(line) {
+            Ok(pattern) => Some(Self {
+                pattern,
+                negated,
+                dir_only,
+                anchored,
+            }),
+            Err(e) => {
+                log::warn!(
+                    "Ignoring invalid {} pattern {:?}: {}",
+                    IGNORE_FILE_NAME,
+                    line,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn matches(&self, rel_path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        if self.anchored {
+            self.pattern.matches_path_with(rel_path, options)
+        } else {
+            rel_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(|name| self.pattern.matches_with(name, options))
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// Compiled ignore rules of a project
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl CheckpointIgnore {
+    /// Build from rule lines, later lines taking precedence
+    pub fn from_patterns<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            rules: lines.into_iter().filter_map(IgnoreRule::parse).collect(),
+        }
+    }
+
+    /// Load the defaults followed by the project's `.checkpointignore`
+    pub fn load(project_path: &Path) -> Self {
+        let project_rules =
+            fs::read_to_string(project_path.join(IGNORE_FILE_NAME)).unwrap_or_default();
+        Self::from_patterns(
+            DEFAULT_IGNORE_PATTERNS
+                .iter()
+                .copied()
+                .chain(project_rules.lines()),
+        )
+    }
+
+    /// Whether a path relative to the project root is ignored, either itself
+    /// or through one of its parent directories
+    pub fn is_ignored(&self, rel_path: &Path, is_dir: bool) -> bool {
+        let mut prefix = std::path::PathBuf::new();
+        let components: Vec<_> = rel_path.components().collect();
+        for (index, component) in components.iter().enumerate() {
+            prefix.push(component);
+            let is_last = index + 1 == components.len();
+            if self.matches(&prefix, !is_last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn matches(&self, rel_path: &Path, is_dir: bool) -> bool {
+        // The last matching rule decides
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(rel_path, is_dir))
+            .map(|rule| !rule.negated)
+            .unwrap_or(false)
+    }
+
+    /// Whether a file is left out of snapshots because of its rules, size or
+    /// binary content
+    pub fn excludes_file(&self, rel_path: &Path, full_path: &Path) -> bool {
+        self.is_ignored(rel_path, false) || is_large_or_binary(full_path)
+    }
+}
+
+/// Whether a file exceeds the snapshot size limit or contains NUL bytes
+fn is_large_or_binary(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if metadata.len() > MAX_SNAPSHOT_FILE_SIZE {
+        return true;
+    }
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    if file
+        .take(BINARY_SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .is_err()
+    {
+        return false;
+    }
+    head.contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let ignore =
+            CheckpointIgnore::from_patterns(DEFAULT_IGNORE_PATTERNS.iter().copied().chain([
+                "*.log",
+                "!keep.log",
+                "/docs/generated",
+                "!target/",
+            ]));
+
+        assert!(ignore.is_ignored(Path::new("web/node_modules/react/index.js"), false));
+        assert!(ignore.is_ignored(Path::new("server.log"), false));
+        assert!(!ignore.is_ignored(Path::new("logs/keep.log"), false));
+        assert!(ignore.is_ignored(Path::new("docs/generated/api.md"), false));
+        assert!(!ignore.is_ignored(Path::new("src/docs/generated/api.md"), false));
+        // The project file re-includes a default-ignored directory
+        assert!(!ignore.is_ignored(Path::new("target/debug/app"), false));
+        // Directory-only rules do not match files of the same name
+        assert!(!ignore.is_ignored(Path::new("node_modules"), false));
+    }
+}
@@ -3,16 +3,111 @@ use chrono::{DateTime, TimeZone, Utc};
 use log;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use super::{
+    ignore::CheckpointIgnore,
     storage::{self, CheckpointStorage},
-    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
-    FileSnapshot, FileState, FileTracker, SessionTimeline,
+    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointProgress, CheckpointResult,
+    CheckpointStrategy, FileSnapshot, FileState, FileTracker, ProgressCallback, SessionTimeline,
+    TimelineBranch,
 };
 
+/// Recursively collect project files relative to `base` that belong in snapshots
+///
+/// Hidden directories like .git are skipped, as is everything excluded by `ignore`.
+fn collect_project_files(
+    dir: &std::path::Path,
+    base: &std::path::Path,
+    ignore: &CheckpointIgnore,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(base) else {
+            continue;
+        };
+        if path.is_dir() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if name.starts_with('.') {
+                    continue;
+                }
+            }
+            if ignore.is_ignored(rel, true) {
+                continue;
+            }
+            collect_project_files(&path, base, ignore, files)?;
+        } else if path.is_file() && !ignore.excludes_file(rel, &path) {
+            files.push(rel.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Report progress after this many files while scanning or snapshotting
+const PROGRESS_INTERVAL: usize = 200;
+
+/// Hash, existence and modification time of a project file on disk
+fn read_file_state(full_path: &Path) -> Result<(String, bool, DateTime<Utc>)> {
+    if !full_path.exists() {
+        return Ok((String::new(), false, Utc::now()));
+    }
+
+    let content = fs::read_to_string(full_path).unwrap_or_default();
+    let metadata = fs::metadata(full_path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| {
+            Utc.timestamp_opt(d.as_secs() as i64, d.subsec_nanos())
+                .unwrap()
+        })
+        .unwrap_or_else(Utc::now);
+
+    Ok((
+        storage::CheckpointStorage::calculate_file_hash(&content),
+        true,
+        modified,
+    ))
+}
+
+/// Record the current state of a file in the tracker
+fn update_tracked_file(
+    tracker: &mut FileTracker,
+    rel_path: PathBuf,
+    hash: String,
+    exists: bool,
+    modified: DateTime<Utc>,
+) {
+    // Check if file has actually changed
+    let is_modified = if let Some(existing_state) = tracker.tracked_files.get(&rel_path) {
+        // File is modified if:
+        // 1. Hash has changed
+        // 2. Existence state has changed
+        // 3. It was already marked as modified
+        existing_state.last_hash != hash
+            || existing_state.exists != exists
+            || existing_state.is_modified
+    } else {
+        // New file is always considered modified
+        true
+    };
+
+    tracker.tracked_files.insert(
+        rel_path,
+        FileState {
+            last_hash: hash,
+            is_modified,
+            last_modified: modified,
+            exists,
+        },
+    );
+}
+
 /// Manages checkpoint operations for a session
 pub struct CheckpointManager {
     project_id: String,
@@ -22,6 +117,7 @@ pub struct CheckpointManager {
     pub storage: Arc<CheckpointStorage>,
     timeline: Arc<RwLock<SessionTimeline>>,
     current_messages: Arc<RwLock<Vec<String>>>, // JSONL messages
+    last_auto_checkpoint: Arc<RwLock<Option<DateTime<Utc>>>>,
 }
 
 impl CheckpointManager {
@@ -57,6 +153,7 @@ impl CheckpointManager {
             storage,
             timeline: Arc::new(RwLock::new(timeline)),
             current_messages: Arc::new(RwLock::new(Vec::new())),
+            last_auto_checkpoint: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -85,6 +182,12 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Replace the tracked messages with a freshly loaded transcript
+    pub async fn replace_messages(&self, messages: Vec<String>) {
+        let mut current = self.current_messages.write().await;
+        *current = messages;
+    }
+
     /// Track file operations from tool usage
     async fn track_tool_operation(&self, tool: &str, input: &serde_json::Value) -> Result<()> {
         match tool.to_lowercase().as_str() {
@@ -106,58 +209,52 @@ impl CheckpointManager {
 
     /// Track a file modification
     pub async fn track_file_modification(&self, file_path: &str) -> Result<()> {
+        let (hash, exists, modified) = read_file_state(&self.project_path.join(file_path))?;
         let mut tracker = self.file_tracker.write().await;
-        let full_path = self.project_path.join(file_path);
-
-        // Read current file state
-        let (hash, exists, _size, modified) = if full_path.exists() {
-            let content = fs::read_to_string(&full_path).unwrap_or_default();
-            let metadata = fs::metadata(&full_path)?;
-            let modified = metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| {
-                    Utc.timestamp_opt(d.as_secs() as i64, d.subsec_nanos())
-                        .unwrap()
-                })
-                .unwrap_or_else(Utc::now);
-
-            (
-                storage::CheckpointStorage::calculate_file_hash(&content),
-                true,
-                metadata.len(),
-                modified,
-            )
-        } else {
-            (String::new(), false, 0, Utc::now())
-        };
-
-        // Check if file has actually changed
-        let is_modified =
-            if let Some(existing_state) = tracker.tracked_files.get(&PathBuf::from(file_path)) {
-                // File is modified if:
-                // 1. Hash has changed
-                // 2. Existence state has changed
-                // 3. It was already marked as modified
-                existing_state.last_hash != hash
-                    || existing_state.exists != exists
-                    || existing_state.is_modified
-            } else {
-                // New file is always considered modified
-                true
-            };
-
-        tracker.tracked_files.insert(
+        update_tracked_file(
+            &mut tracker,
             PathBuf::from(file_path),
-            FileState {
-                last_hash: hash,
-                is_modified,
-                last_modified: modified,
-                exists,
-            },
+            hash,
+            exists,
+            modified,
         );
+        Ok(())
+    }
+
+    /// Walk the project directory and track each file for the next snapshot
+    ///
+    /// The walk and hashing run on the blocking pool so large projects do not
+    /// stall the async runtime.
+    async fn track_all_project_files(&self, progress: Option<&ProgressCallback>) -> Result<()> {
+        let project_dir = self.project_path.clone();
+        let progress = progress.cloned();
+        let states = tokio::task::spawn_blocking(move || {
+            let mut all_files = Vec::new();
+            let ignore = CheckpointIgnore::load(&project_dir);
+            let _ = collect_project_files(&project_dir, &project_dir, &ignore, &mut all_files);
+
+            let mut states = Vec::with_capacity(all_files.len());
+            for (index, rel) in all_files.into_iter().enumerate() {
+                if let Ok((hash, exists, modified)) = read_file_state(&project_dir.join(&rel)) {
+                    states.push((rel, hash, exists, modified));
+                }
+                if let Some(progress) = &progress {
+                    if (index + 1) % PROGRESS_INTERVAL == 0 {
+                        progress(CheckpointProgress::Scanning {
+                            files_scanned: index + 1,
+                        });
+                    }
+                }
+            }
+            states
+        })
+        .await
+        .context("Project scan task failed")?;
 
+        let mut tracker = self.file_tracker.write().await;
+        for (rel, hash, exists, modified) in states {
+            update_tracked_file(&mut tracker, rel, hash, exists, modified);
+        }
         Ok(())
     }
 
@@ -190,7 +287,22 @@ impl CheckpointManager {
         description: Option<String>,
         parent_checkpoint_id: Option<String>,
     ) -> Result<CheckpointResult> {
-        let messages = self.current_messages.read().await;
+        self.create_checkpoint_with_progress(description, parent_checkpoint_id, None)
+            .await
+    }
+
+    /// Create a checkpoint, reporting progress through `progress`
+    ///
+    /// File scanning, reading and writing run on the blocking pool, and the
+    /// message list is copied up front so the session keeps tracking new
+    /// messages while a large project is snapshotted.
+    pub async fn create_checkpoint_with_progress(
+        &self,
+        description: Option<String>,
+        parent_checkpoint_id: Option<String>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<CheckpointResult> {
+        let messages = self.current_messages.read().await.clone();
         let message_index = messages.len().saturating_sub(1);
 
         // Extract metadata from the last user message
@@ -198,99 +310,87 @@ impl CheckpointManager {
             self.extract_checkpoint_metadata(&messages).await?;
 
         // Ensure every file in the project is tracked so new checkpoints include all files
-        // Recursively walk the project directory and track each file
-        fn collect_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-        let mut all_files = Vec::new();
-        let project_dir = &self.project_path;
-        let _ = collect_files(project_dir.as_path(), project_dir.as_path(), &mut all_files);
-        for rel in all_files {
-            if let Some(p) = rel.to_str() {
-                // Track each file for snapshot
-                let _ = self.track_file_modification(p).await;
-            }
-        }
+        self.track_all_project_files(progress.as_ref()).await?;
 
         // Generate checkpoint ID early so snapshots reference it
         let checkpoint_id = storage::CheckpointStorage::generate_checkpoint_id();
 
         // Create file snapshots
-        let file_snapshots = self.create_file_snapshots(&checkpoint_id).await?;
-
-        // Generate checkpoint struct
-        let checkpoint = Checkpoint {
-            id: checkpoint_id.clone(),
-            session_id: self.session_id.clone(),
-            project_id: self.project_id.clone(),
-            message_index,
-            timestamp: Utc::now(),
-            description,
-            parent_checkpoint_id: {
-                if let Some(parent_id) = parent_checkpoint_id {
-                    Some(parent_id)
-                } else {
-                    // Perform an asynchronous read to avoid blocking within the runtime
-                    let timeline = self.timeline.read().await;
-                    timeline.current_checkpoint_id.clone()
-                }
-            },
-            metadata: CheckpointMetadata {
-                total_tokens,
-                model_used,
-                user_prompt,
-                file_changes: file_snapshots.len(),
-                snapshot_size: storage::CheckpointStorage::estimate_checkpoint_size(
-                    &messages.join("\n"),
-                    &file_snapshots,
-                ),
-            },
+        let file_changes = {
+            let tracker = self.file_tracker.read().await;
+            tracker
+                .tracked_files
+                .values()
+                .filter(|state| state.is_modified)
+                .count()
         };
-
-        // Save checkpoint
+        let file_snapshots = self
+            .create_file_snapshots(&checkpoint_id, progress.as_ref())
+            .await?;
         let messages_content = messages.join("\n");
-        let result = self.storage.save_checkpoint(
-            &self.project_id,
-            &self.session_id,
-            &checkpoint,
-            file_snapshots,
-            &messages_content,
-        )?;
-
-        // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
-        let claude_dir = self.storage.claude_dir.clone();
-        let paths = CheckpointPaths::new(&claude_dir, &self.project_id, &self.session_id);
-        let updated_timeline = self.storage.load_timeline(&paths.timeline_file)?;
-        {
-            let mut timeline_lock = self.timeline.write().await;
-            *timeline_lock = updated_timeline;
+
+        let parent_checkpoint_id = match parent_checkpoint_id {
+            Some(parent_id) => Some(parent_id),
+            None => self.timeline.read().await.current_checkpoint_id.clone(),
+        };
+
+        if let Some(progress) = &progress {
+            progress(CheckpointProgress::Saving {
+                files_total: file_snapshots.len(),
+            });
         }
 
+        let storage = Arc::clone(&self.storage);
+        let project_id = self.project_id.clone();
+        let session_id = self.session_id.clone();
+        let id = checkpoint_id.clone();
+        let (result, updated_timeline) = tokio::task::spawn_blocking(move || {
+            let paths = CheckpointPaths::new(&storage.claude_dir, &project_id, &session_id);
+
+            // Generate checkpoint struct
+            let checkpoint = Checkpoint {
+                id,
+                session_id: session_id.clone(),
+                project_id: project_id.clone(),
+                message_index,
+                timestamp: Utc::now(),
+                description,
+                parent_checkpoint_id,
+                metadata: CheckpointMetadata {
+                    total_tokens,
+                    model_used,
+                    user_prompt,
+                    file_changes,
+                    snapshot_size: storage.estimate_new_content_size(
+                        &paths,
+                        &messages_content,
+                        &file_snapshots,
+                    ),
+                },
+            };
+
+            // Save checkpoint
+            let result = storage.save_checkpoint(
+                &project_id,
+                &session_id,
+                &checkpoint,
+                file_snapshots,
+                &messages_content,
+            )?;
+
+            // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
+            let updated_timeline = storage.load_timeline(&paths.timeline_file)?;
+            Ok::<_, anyhow::Error>((result, updated_timeline))
+        })
+        .await
+        .context("Checkpoint save task failed")??;
+
         // Update timeline (current checkpoint only)
-        let mut timeline = self.timeline.write().await;
-        timeline.current_checkpoint_id = Some(checkpoint_id);
+        {
+            let mut timeline = self.timeline.write().await;
+            *timeline = updated_timeline;
+            timeline.current_checkpoint_id = Some(checkpoint_id);
+        }
 
         // Reset file tracker
         let mut tracker = self.file_tracker.write().await;
@@ -397,56 +497,87 @@ impl CheckpointManager {
         Ok((user_prompt, model_used, total_tokens))
     }
 
-    /// Create file snapshots for all tracked modified files
-    async fn create_file_snapshots(&self, checkpoint_id: &str) -> Result<Vec<FileSnapshot>> {
-        let tracker = self.file_tracker.read().await;
-        let mut snapshots = Vec::new();
-
-        for (rel_path, state) in &tracker.tracked_files {
-            // Skip files that haven't been modified
-            if !state.is_modified {
-                continue;
-            }
+    /// Create file snapshots for all tracked files
+    ///
+    /// Every checkpoint records the complete project state so it can be
+    /// restored on its own; unchanged content is deduplicated by storage.
+    async fn create_file_snapshots(
+        &self,
+        checkpoint_id: &str,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<Vec<FileSnapshot>> {
+        let rel_paths: Vec<PathBuf> = {
+            let tracker = self.file_tracker.read().await;
+            tracker
+                .tracked_files
+                .iter()
+                // Files removed before the previous checkpoint are simply absent
+                .filter(|(_, state)| state.exists || state.is_modified)
+                .map(|(rel_path, _)| rel_path.clone())
+                .collect()
+        };
 
-            let full_path = self.project_path.join(rel_path);
+        let project_path = self.project_path.clone();
+        let checkpoint_id = checkpoint_id.to_string();
+        let progress = progress.cloned();
+        tokio::task::spawn_blocking(move || {
+            let ignore = CheckpointIgnore::load(&project_path);
+            let files_total = rel_paths.len();
+            let mut snapshots = Vec::new();
+
+            for (index, rel_path) in rel_paths.into_iter().enumerate() {
+                if let Some(progress) = &progress {
+                    if index % PROGRESS_INTERVAL == 0 {
+                        progress(CheckpointProgress::Snapshotting {
+                            files_done: index,
+                            files_total,
+                        });
+                    }
+                }
 
-            let (content, exists, permissions, size, current_hash) = if full_path.exists() {
-                let content = fs::read_to_string(&full_path).unwrap_or_default();
-                let current_hash = storage::CheckpointStorage::calculate_file_hash(&content);
+                let full_path = project_path.join(&rel_path);
+                // Edits reported by tools may touch files the walk leaves out
+                if ignore.excludes_file(&rel_path, &full_path) {
+                    continue;
+                }
 
-                // Don't skip based on hash - if is_modified is true, we should snapshot it
-                // The hash check in track_file_modification already determined if it changed
+                let (content, exists, permissions, size, current_hash) = if full_path.exists() {
+                    let content = fs::read_to_string(&full_path).unwrap_or_default();
+                    let current_hash = storage::CheckpointStorage::calculate_file_hash(&content);
 
-                let metadata = fs::metadata(&full_path)?;
-                let permissions = {
-                    // Windows doesn't use Unix-style permissions
-                    // File permissions are handled through ACLs and file attributes
-                    #[cfg(target_os = "windows")]
-                    {
-                        None
-                    }
-                    #[cfg(not(target_os = "windows"))]
-                    {
-                        None // Simplified for Windows-only build
-                    }
+                    let metadata = fs::metadata(&full_path)?;
+                    let permissions = {
+                        // Windows doesn't use Unix-style permissions
+                        // File permissions are handled through ACLs and file attributes
+                        #[cfg(target_os = "windows")]
+                        {
+                            None
+                        }
+                        #[cfg(not(target_os = "windows"))]
+                        {
+                            None // Simplified for Windows-only build
+                        }
+                    };
+                    (content, true, permissions, metadata.len(), current_hash)
+                } else {
+                    (String::new(), false, None, 0, String::new())
                 };
-                (content, true, permissions, metadata.len(), current_hash)
-            } else {
-                (String::new(), false, None, 0, String::new())
-            };
 
-            snapshots.push(FileSnapshot {
-                checkpoint_id: checkpoint_id.to_string(),
-                file_path: rel_path.clone(),
-                content,
-                hash: current_hash,
-                is_deleted: !exists,
-                permissions,
-                size,
-            });
-        }
+                snapshots.push(FileSnapshot {
+                    checkpoint_id: checkpoint_id.clone(),
+                    file_path: rel_path,
+                    content,
+                    hash: current_hash,
+                    is_deleted: !exists,
+                    permissions,
+                    size,
+                });
+            }
 
-        Ok(snapshots)
+            Ok(snapshots)
+        })
+        .await
+        .context("File snapshot task failed")?
     }
 
     /// Restore a checkpoint
@@ -456,36 +587,16 @@ impl CheckpointManager {
             self.storage
                 .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
 
-        // First, collect all files currently in the project to handle deletions
-        fn collect_all_project_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_all_project_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-
+        // First, collect all files currently in the project to handle deletions.
+        // Ignored files are not part of checkpoints and must survive the restore.
         let mut current_files = Vec::new();
-        let _ =
-            collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
+        let ignore = CheckpointIgnore::load(&self.project_path);
+        let _ = collect_project_files(
+            &self.project_path,
+            &self.project_path,
+            &ignore,
+            &mut current_files,
+        );
 
         // Create a set of files that should exist after restore
         let mut checkpoint_files = std::collections::HashSet::new();
@@ -601,6 +712,13 @@ impl CheckpointManager {
 
     /// Restore a single file from snapshot
     async fn restore_file_snapshot(&self, snapshot: &FileSnapshot) -> Result<()> {
+        // Snapshots may come from an imported archive; never write outside the project
+        if !super::is_safe_relative_path(&snapshot.file_path) {
+            anyhow::bail!(
+                "Refusing to restore a path outside the project: {}",
+                snapshot.file_path.display()
+            );
+        }
         let full_path = self.project_path.join(&snapshot.file_path);
 
         if snapshot.is_deleted {
@@ -634,6 +752,15 @@ impl CheckpointManager {
         self.timeline.read().await.clone()
     }
 
+    /// Reload the timeline after it was changed on disk, e.g. by pruning
+    pub async fn reload_timeline(&self) -> Result<()> {
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        let timeline = self.storage.load_timeline(&paths.timeline_file)?;
+        *self.timeline.write().await = timeline;
+        Ok(())
+    }
+
     /// List all checkpoints
     pub async fn list_checkpoints(&self) -> Vec<Checkpoint> {
         let timeline = self.timeline.read().await;
@@ -679,6 +806,112 @@ impl CheckpointManager {
             .await
     }
 
+    /// Fork a new branch from a historical checkpoint and switch to it
+    ///
+    /// Uncheckpointed edits on the current branch are saved first. Project files
+    /// and tracked messages are then restored to `checkpoint_id`, and further
+    /// checkpoints grow the new branch while the old one keeps its head.
+    pub async fn create_branch(
+        &self,
+        checkpoint_id: &str,
+        name: Option<String>,
+        branch_session_id: String,
+    ) -> Result<(TimelineBranch, CheckpointResult)> {
+        {
+            let mut timeline = self.timeline.write().await;
+            if timeline.find_checkpoint(checkpoint_id).is_none() {
+                anyhow::bail!("Checkpoint not found: {}", checkpoint_id);
+            }
+            timeline.ensure_main_branch();
+            self.persist_timeline(&timeline)?;
+        }
+
+        let name = name.unwrap_or_else(|| {
+            let short_id: String = checkpoint_id.chars().take(8).collect();
+            format!("Branch from checkpoint {}", short_id)
+        });
+        self.preserve_current_branch(&name).await?;
+        let result = self.restore_checkpoint(checkpoint_id).await?;
+
+        let branch = TimelineBranch {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            forked_from_checkpoint_id: Some(checkpoint_id.to_string()),
+            head_checkpoint_id: Some(checkpoint_id.to_string()),
+            session_id: branch_session_id,
+            created_at: Utc::now(),
+        };
+
+        let mut timeline = self.timeline.write().await;
+        timeline.branches.push(branch.clone());
+        timeline.current_branch_id = Some(branch.id.clone());
+        timeline.current_checkpoint_id = Some(checkpoint_id.to_string());
+        self.persist_timeline(&timeline)?;
+
+        Ok((branch, result))
+    }
+
+    /// List all branches, including the implicit main branch
+    pub async fn list_branches(&self) -> (Vec<TimelineBranch>, Option<String>) {
+        let mut timeline = self.timeline.read().await.clone();
+        timeline.ensure_main_branch();
+        (timeline.branches, timeline.current_branch_id)
+    }
+
+    /// Switch to another branch, restoring project files to its head checkpoint
+    pub async fn switch_branch(&self, branch_id: &str) -> Result<TimelineBranch> {
+        let branch = {
+            let mut timeline = self.timeline.write().await;
+            timeline.ensure_main_branch();
+            self.persist_timeline(&timeline)?;
+            let branch = timeline
+                .find_branch(branch_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Branch not found: {}", branch_id))?;
+            if timeline.current_branch_id.as_deref() == Some(branch_id) {
+                return Ok(branch);
+            }
+            branch
+        };
+
+        self.preserve_current_branch(&branch.name).await?;
+
+        if let Some(head) = &branch.head_checkpoint_id {
+            self.restore_checkpoint(head).await?;
+        }
+
+        let mut timeline = self.timeline.write().await;
+        timeline.current_branch_id = Some(branch.id.clone());
+        timeline.current_checkpoint_id = branch.head_checkpoint_id.clone();
+        self.persist_timeline(&timeline)?;
+
+        Ok(branch)
+    }
+
+    /// Checkpoint the current branch if project files changed since its head
+    async fn preserve_current_branch(&self, target_name: &str) -> Result<()> {
+        self.track_all_project_files(None).await?;
+        let has_changes = {
+            let tracker = self.file_tracker.read().await;
+            tracker
+                .tracked_files
+                .values()
+                .any(|state| state.is_modified)
+        };
+        if has_changes {
+            self.create_checkpoint(Some(format!("Before switching to {}", target_name)), None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Write the in-memory timeline to disk
+    fn persist_timeline(&self, timeline: &SessionTimeline) -> Result<()> {
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        self.storage.save_timeline(&paths.timeline_file, timeline)
+    }
+
     /// Check if auto-checkpoint should be triggered
     pub async fn should_auto_checkpoint(&self, message: &str) -> bool {
         let timeline = self.timeline.read().await;
@@ -742,7 +975,50 @@ impl CheckpointManager {
                     false
                 }
             }
+            // Handled by the backend through `create_auto_checkpoint`
+            CheckpointStrategy::OnFileChange => false,
+        }
+    }
+
+    /// How long a file edit has to wait for its automatic checkpoint
+    ///
+    /// None unless the session checkpoints on file changes; zero once the
+    /// previous automatic checkpoint is older than the configured interval.
+    pub async fn file_change_checkpoint_wait(&self) -> Option<std::time::Duration> {
+        let timeline = self.timeline.read().await;
+        if !timeline.auto_checkpoint_enabled
+            || !matches!(timeline.checkpoint_strategy, CheckpointStrategy::OnFileChange)
+        {
+            return None;
         }
+        let elapsed = match *self.last_auto_checkpoint.read().await {
+            Some(previous) => (Utc::now() - previous).num_milliseconds().max(0) as u64,
+            None => u64::MAX,
+        };
+        let interval = timeline.auto_checkpoint_min_interval_secs.saturating_mul(1000);
+        Some(std::time::Duration::from_millis(interval.saturating_sub(elapsed)))
+    }
+
+    /// Create an automatic checkpoint after Claude edited `files`
+    pub async fn create_auto_checkpoint(&self, files: &[String]) -> Result<CheckpointResult> {
+        *self.last_auto_checkpoint.write().await = Some(Utc::now());
+
+        let names: Vec<&str> = files
+            .iter()
+            .map(|f| {
+                std::path::Path::new(f)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or(f)
+            })
+            .collect();
+        let description = match names.as_slice() {
+            [] => "Auto: file changes".to_string(),
+            [name] => format!("Auto: edited {}", name),
+            [first, rest @ ..] => format!("Auto: edited {} and {} more", first, rest.len()),
+        };
+
+        self.create_checkpoint(Some(description), None).await
     }
 
     /// Update checkpoint settings
@@ -750,10 +1026,14 @@ impl CheckpointManager {
         &self,
         auto_checkpoint_enabled: bool,
         checkpoint_strategy: CheckpointStrategy,
+        auto_checkpoint_min_interval_secs: Option<u64>,
     ) -> Result<()> {
         let mut timeline = self.timeline.write().await;
         timeline.auto_checkpoint_enabled = auto_checkpoint_enabled;
         timeline.checkpoint_strategy = checkpoint_strategy;
+        if let Some(interval) = auto_checkpoint_min_interval_secs {
+            timeline.auto_checkpoint_min_interval_secs = interval;
+        }
 
         // Save updated timeline
         let claude_dir = self.storage.claude_dir.clone();
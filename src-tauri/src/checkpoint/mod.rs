@@ -1,9 +1,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 
+pub mod archive;
+pub mod auto;
+pub mod ignore;
 pub mod manager;
+pub mod project_timeline;
+pub mod retention;
+pub mod search;
 pub mod state;
 pub mod storage;
 
@@ -65,6 +71,32 @@ pub struct FileSnapshot {
     pub size: u64,
 }
 
+/// Lists the blobs that make up a checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointManifest {
+    /// Hash of the session messages blob
+    pub messages_hash: String,
+    /// Every project file at the checkpoint
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A file in a checkpoint manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// Relative path from project root
+    pub path: PathBuf,
+    /// Hash of the content blob (empty for deleted files)
+    pub hash: String,
+    /// Whether this file was deleted at this checkpoint
+    pub is_deleted: bool,
+    /// File permissions (Unix mode)
+    pub permissions: Option<u32>,
+    /// File size in bytes
+    pub size: u64,
+}
+
 /// Represents a node in the timeline tree
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -73,7 +105,7 @@ pub struct TimelineNode {
     pub checkpoint: Checkpoint,
     /// Child nodes (for branches/forks)
     pub children: Vec<TimelineNode>,
-    /// IDs of file snapshots associated with this checkpoint
+    /// Hashes of file contents first stored by this checkpoint
     pub file_snapshot_ids: Vec<String>,
 }
 
@@ -91,8 +123,35 @@ pub struct SessionTimeline {
     pub auto_checkpoint_enabled: bool,
     /// Strategy for automatic checkpoints
     pub checkpoint_strategy: CheckpointStrategy,
+    /// Minimum seconds between two automatic file-change checkpoints
+    #[serde(default = "default_auto_checkpoint_interval")]
+    pub auto_checkpoint_min_interval_secs: u64,
     /// Total number of checkpoints in timeline
     pub total_checkpoints: usize,
+    /// Named branches forked from historical checkpoints
+    #[serde(default)]
+    pub branches: Vec<TimelineBranch>,
+    /// ID of the branch new checkpoints are added to
+    #[serde(default)]
+    pub current_branch_id: Option<String>,
+}
+
+/// A line of work in the timeline, forked from an earlier checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBranch {
+    /// Unique identifier for the branch
+    pub id: String,
+    /// Display name of the branch
+    pub name: String,
+    /// Checkpoint the branch was forked from (None for the main branch)
+    pub forked_from_checkpoint_id: Option<String>,
+    /// Latest checkpoint on this branch
+    pub head_checkpoint_id: Option<String>,
+    /// Claude session to resume when working on this branch
+    pub session_id: String,
+    /// Timestamp when the branch was created
+    pub created_at: DateTime<Utc>,
 }
 
 /// Strategy for automatic checkpoint creation
@@ -107,6 +166,16 @@ pub enum CheckpointStrategy {
     PerToolUse,
     /// Create checkpoint after destructive operations
     Smart,
+    /// Create checkpoint whenever Claude finished editing a file,
+    /// detected by the backend from the session's tool results
+    OnFileChange,
+}
+
+/// ID of the branch holding the session's original line of work
+pub const MAIN_BRANCH_ID: &str = "main";
+
+fn default_auto_checkpoint_interval() -> u64 {
+    30
 }
 
 /// Tracks the state of files for checkpointing
@@ -140,6 +209,21 @@ pub struct CheckpointResult {
     pub warnings: Vec<String>,
 }
 
+/// Progress of a checkpoint being created in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum CheckpointProgress {
+    /// Walking the project and hashing files
+    Scanning { files_scanned: usize },
+    /// Reading the contents of tracked files
+    Snapshotting { files_done: usize, files_total: usize },
+    /// Writing objects, manifest and timeline to disk
+    Saving { files_total: usize },
+}
+
+/// Callback receiving progress updates while a checkpoint is created
+pub type ProgressCallback = std::sync::Arc<dyn Fn(CheckpointProgress) + Send + Sync>;
+
 /// Diff between two checkpoints
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CheckpointDiff {
@@ -185,7 +269,122 @@ impl SessionTimeline {
             current_checkpoint_id: None,
             auto_checkpoint_enabled: false,
             checkpoint_strategy: CheckpointStrategy::default(),
+            auto_checkpoint_min_interval_secs: default_auto_checkpoint_interval(),
             total_checkpoints: 0,
+            branches: Vec::new(),
+            current_branch_id: None,
+        }
+    }
+
+    /// Make the implicit main branch explicit before the first fork
+    ///
+    /// The main branch continues the original session from the current checkpoint.
+    pub fn ensure_main_branch(&mut self) {
+        if !self.branches.is_empty() {
+            return;
+        }
+        let main = TimelineBranch {
+            id: MAIN_BRANCH_ID.to_string(),
+            name: "main".to_string(),
+            forked_from_checkpoint_id: None,
+            head_checkpoint_id: self.current_checkpoint_id.clone(),
+            session_id: self.session_id.clone(),
+            created_at: self
+                .root_node
+                .as_ref()
+                .map(|root| root.checkpoint.timestamp)
+                .unwrap_or_else(Utc::now),
+        };
+        self.branches.push(main);
+        self.current_branch_id = Some(MAIN_BRANCH_ID.to_string());
+    }
+
+    /// Checkpoints that pruning must keep: the current one, branch heads and fork points
+    pub fn protected_checkpoint_ids(&self) -> HashSet<String> {
+        fn collect_fork_points(node: &TimelineNode, ids: &mut HashSet<String>) {
+            if node.children.len() > 1 {
+                ids.insert(node.checkpoint.id.clone());
+            }
+            for child in &node.children {
+                collect_fork_points(child, ids);
+            }
+        }
+
+        let mut ids: HashSet<String> = self.current_checkpoint_id.iter().cloned().collect();
+        for branch in &self.branches {
+            ids.extend(branch.head_checkpoint_id.iter().cloned());
+            ids.extend(branch.forked_from_checkpoint_id.iter().cloned());
+        }
+        if let Some(root) = &self.root_node {
+            collect_fork_points(root, &mut ids);
+        }
+        ids
+    }
+
+    /// Remove a checkpoint from the tree, attaching its children to its parent
+    ///
+    /// A root with several children cannot be removed; returns whether the
+    /// checkpoint was removed.
+    pub fn remove_checkpoint_node(&mut self, checkpoint_id: &str) -> bool {
+        let Some(root) = self.root_node.as_mut() else {
+            return false;
+        };
+
+        let removed = if root.checkpoint.id == checkpoint_id {
+            match root.children.len() {
+                0 => {
+                    self.root_node = None;
+                    true
+                }
+                1 => {
+                    let mut child = root.children.remove(0);
+                    child.checkpoint.parent_checkpoint_id = None;
+                    self.root_node = Some(child);
+                    true
+                }
+                _ => false,
+            }
+        } else {
+            Self::remove_from_tree(root, checkpoint_id)
+        };
+
+        if removed {
+            self.total_checkpoints = self.total_checkpoints.saturating_sub(1);
+        }
+        removed
+    }
+
+    fn remove_from_tree(node: &mut TimelineNode, checkpoint_id: &str) -> bool {
+        if let Some(index) = node
+            .children
+            .iter()
+            .position(|c| c.checkpoint.id == checkpoint_id)
+        {
+            let removed = node.children.remove(index);
+            for (offset, mut child) in removed.children.into_iter().enumerate() {
+                child.checkpoint.parent_checkpoint_id = Some(node.checkpoint.id.clone());
+                node.children.insert(index + offset, child);
+            }
+            return true;
+        }
+
+        node.children
+            .iter_mut()
+            .any(|child| Self::remove_from_tree(child, checkpoint_id))
+    }
+
+    /// Find a branch by ID
+    pub fn find_branch(&self, branch_id: &str) -> Option<&TimelineBranch> {
+        self.branches.iter().find(|b| b.id == branch_id)
+    }
+
+    /// Move the head of the current branch to a newly created checkpoint
+    pub fn advance_current_branch(&mut self, checkpoint_id: &str) {
+        let Some(current) = self.current_branch_id.clone() else {
+            return;
+        };
+        if let Some(branch) = self.branches.iter_mut().find(|b| b.id == current) {
+            branch.head_checkpoint_id = Some(checkpoint_id.to_string());
         }
     }
 
@@ -216,20 +415,23 @@ pub struct CheckpointPaths {
     pub timeline_file: PathBuf,
     pub checkpoints_dir: PathBuf,
     pub files_dir: PathBuf,
+    /// Content-addressable blob store shared by all sessions of the project
+    pub objects_dir: PathBuf,
 }
 
 impl CheckpointPaths {
     pub fn new(claude_dir: &PathBuf, project_id: &str, session_id: &str) -> Self {
-        let base_dir = claude_dir
+        let timelines_dir = claude_dir
             .join("projects")
             .join(project_id)
-            .join(".timelines")
-            .join(session_id);
+            .join(".timelines");
+        let base_dir = timelines_dir.join(session_id);
 
         Self {
             timeline_file: base_dir.join("timeline.json"),
             checkpoints_dir: base_dir.join("checkpoints"),
             files_dir: base_dir.join("files"),
+            objects_dir: timelines_dir.join("objects"),
         }
     }
 
@@ -241,22 +443,52 @@ impl CheckpointPaths {
         self.checkpoint_dir(checkpoint_id).join("metadata.json")
     }
 
+    pub fn checkpoint_manifest_file(&self, checkpoint_id: &str) -> PathBuf {
+        self.checkpoint_dir(checkpoint_id).join("manifest.json")
+    }
+
+    /// Messages file of checkpoints written before the object store existed
     pub fn checkpoint_messages_file(&self, checkpoint_id: &str) -> PathBuf {
         self.checkpoint_dir(checkpoint_id).join("messages.jsonl")
     }
 
-    #[allow(dead_code)]
-    pub fn file_snapshot_path(&self, _checkpoint_id: &str, file_hash: &str) -> PathBuf {
-        // In content-addressable storage, files are stored by hash in the content pool
-        self.files_dir.join("content_pool").join(file_hash)
+    /// Blob path for a content hash, sharded by its first two characters
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        if hash.len() > 2 {
+            self.objects_dir.join(&hash[..2]).join(&hash[2..])
+        } else {
+            self.objects_dir.join(hash)
+        }
+    }
+
+    /// Per-session content pool of checkpoints written before the object store existed
+    pub fn legacy_content_pool_dir(&self) -> PathBuf {
+        self.files_dir.join("content_pool")
     }
 
-    #[allow(dead_code)]
-    pub fn file_reference_path(&self, checkpoint_id: &str, safe_filename: &str) -> PathBuf {
-        // References are stored per checkpoint
-        self.files_dir
-            .join("refs")
-            .join(checkpoint_id)
-            .join(format!("{}.json", safe_filename))
+    /// Per-checkpoint file references of checkpoints written before manifests existed
+    pub fn legacy_refs_dir(&self, checkpoint_id: &str) -> PathBuf {
+        self.files_dir.join("refs").join(checkpoint_id)
     }
 }
+
+/// Whether a session or checkpoint ID is safe to use as a directory name
+///
+/// IDs are UUIDs or similar; anything else, e.g. from an imported archive, is
+/// refused before it ends up in a path.
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether a snapshot path stays inside the project it is joined to: relative,
+/// without `..`, root or drive prefix components
+pub fn is_safe_relative_path(path: &Path) -> bool {
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
@@ -0,0 +1,194 @@
+//! Chronological timeline of all checkpoints in a project
+//!
+//! Each session keeps its own timeline tree; this merges them into one list
+//! ordered by creation time. Entries keep their session ID, so a checkpoint
+//! of any session can be restored through that session.
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, SessionTimeline, TimelineNode};
+
+/// A checkpoint in the project timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTimelineEntry {
+    pub checkpoint: Checkpoint,
+    /// Whether the previous entry belongs to a different session
+    pub session_boundary: bool,
+    /// Whether this is the current checkpoint of its session
+    pub is_current: bool,
+}
+
+/// Checkpoint range of one session in the project timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTimelineSession {
+    pub session_id: String,
+    pub checkpoint_count: usize,
+    pub first_checkpoint_at: DateTime<Utc>,
+    pub last_checkpoint_at: DateTime<Utc>,
+    pub current_checkpoint_id: Option<String>,
+}
+
+/// Checkpoints of all sessions in a project, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTimeline {
+    pub project_id: String,
+    pub entries: Vec<ProjectTimelineEntry>,
+    /// Sessions with checkpoints, ordered by their first checkpoint
+    pub sessions: Vec<ProjectTimelineSession>,
+}
+
+/// Load the timelines of all sessions and merge them
+pub fn load_project_timeline(
+    storage: &CheckpointStorage,
+    project_id: &str,
+) -> Result<ProjectTimeline> {
+    let timelines = storage.load_project_timelines(project_id)?;
+    Ok(merge_timelines(project_id, timelines))
+}
+
+fn merge_timelines(project_id: &str, timelines: Vec<(String, SessionTimeline)>) -> ProjectTimeline {
+    let mut entries = Vec::new();
+    let mut sessions = Vec::new();
+
+    for (session_id, timeline) in timelines {
+        let mut checkpoints = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            collect_checkpoints(root, &mut checkpoints);
+        }
+        let (Some(first), Some(last)) = (
+            checkpoints.iter().map(|c| c.timestamp).min(),
+            checkpoints.iter().map(|c| c.timestamp).max(),
+        ) else {
+            continue;
+        };
+
+        sessions.push(ProjectTimelineSession {
+            session_id,
+            checkpoint_count: checkpoints.len(),
+            first_checkpoint_at: first,
+            last_checkpoint_at: last,
+            current_checkpoint_id: timeline.current_checkpoint_id.clone(),
+        });
+        entries.extend(
+            checkpoints
+                .into_iter()
+                .map(|checkpoint| ProjectTimelineEntry {
+                    is_current: timeline.current_checkpoint_id.as_deref()
+                        == Some(checkpoint.id.as_str()),
+                    checkpoint,
+                    session_boundary: false,
+                }),
+        );
+    }
+
+    entries.sort_by(|a, b| a.checkpoint.timestamp.cmp(&b.checkpoint.timestamp));
+    let mut previous_session: Option<String> = None;
+    for entry in &mut entries {
+        entry.session_boundary =
+            previous_session.as_deref() != Some(entry.checkpoint.session_id.as_str());
+        previous_session = Some(entry.checkpoint.session_id.clone());
+    }
+    sessions.sort_by(|a, b| a.first_checkpoint_at.cmp(&b.first_checkpoint_at));
+
+    ProjectTimeline {
+        project_id: project_id.to_string(),
+        entries,
+        sessions,
+    }
+}
+
+/// Every checkpoint of a timeline tree, depth first
+pub(super) fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
+    checkpoints.push(node.checkpoint.clone());
+    for child in &node.children {
+        collect_checkpoints(child, checkpoints);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+    use chrono::Duration;
+
+    fn node(id: &str, session_id: &str, minutes: i64, children: Vec<TimelineNode>) -> TimelineNode {
+        TimelineNode {
+            checkpoint: Checkpoint {
+                id: id.to_string(),
+                session_id: session_id.to_string(),
+                project_id: "project".to_string(),
+                message_index: 0,
+                timestamp: Utc::now() + Duration::minutes(minutes),
+                description: None,
+                parent_checkpoint_id: None,
+                metadata: CheckpointMetadata {
+                    total_tokens: 0,
+                    model_used: "unknown".to_string(),
+                    user_prompt: String::new(),
+                    file_changes: 0,
+                    snapshot_size: 0,
+                },
+            },
+            children,
+            file_snapshot_ids: Vec::new(),
+        }
+    }
+
+    fn timeline(session_id: &str, root: TimelineNode, current: &str) -> (String, SessionTimeline) {
+        let mut timeline = SessionTimeline::new(session_id.to_string());
+        timeline.root_node = Some(root);
+        timeline.current_checkpoint_id = Some(current.to_string());
+        (session_id.to_string(), timeline)
+    }
+
+    #[test]
+    fn test_merges_sessions_chronologically() {
+        let timelines = vec![
+            timeline(
+                "s2",
+                node("b1", "s2", 2, vec![node("b2", "s2", 3, vec![])]),
+                "b2",
+            ),
+            timeline(
+                "s1",
+                node(
+                    "a1",
+                    "s1",
+                    0,
+                    vec![node("a2", "s1", 1, vec![]), node("a3", "s1", 4, vec![])],
+                ),
+                "a3",
+            ),
+            (
+                "empty".to_string(),
+                SessionTimeline::new("empty".to_string()),
+            ),
+        ];
+
+        let merged = merge_timelines("project", timelines);
+
+        let ids: Vec<&str> = merged
+            .entries
+            .iter()
+            .map(|e| e.checkpoint.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["a1", "a2", "b1", "b2", "a3"]);
+        let boundaries: Vec<bool> = merged.entries.iter().map(|e| e.session_boundary).collect();
+        assert_eq!(boundaries, vec![true, false, true, false, true]);
+        assert!(merged.entries[4].is_current);
+        assert!(!merged.entries[0].is_current);
+
+        let sessions: Vec<&str> = merged
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(sessions, vec!["s1", "s2"]);
+        assert_eq!(merged.sessions[0].checkpoint_count, 3);
+    }
+}
@@ -0,0 +1,214 @@
+//! Retention policy deciding which checkpoints of a project may be pruned
+//!
+//! Selection is a pure function of the checkpoints and the policy, so a dry
+//! run reports exactly what a real prune removes.
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::Checkpoint;
+
+/// How many checkpoints to keep
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    /// Most recent checkpoints always kept per session
+    pub keep_last: usize,
+    /// Keep the newest checkpoint of each day for this many days, 0 to disable
+    pub keep_daily_days: u32,
+    /// Size cap for a project's checkpoints in bytes, `None` for no cap
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: 20,
+            keep_daily_days: 30,
+            max_total_bytes: None,
+        }
+    }
+}
+
+/// Why a checkpoint was selected for pruning
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    /// Neither among the last N nor a daily checkpoint still in the window
+    Expired,
+    /// Removed to bring the project under the size cap
+    SizeCap,
+}
+
+/// A checkpoint considered by the retention policy
+#[derive(Debug, Clone)]
+pub struct RetentionCandidate {
+    pub checkpoint: Checkpoint,
+    /// Estimated storage the checkpoint added
+    pub size: u64,
+    /// Current checkpoint, branch head or fork point that must stay
+    pub protected: bool,
+}
+
+/// A checkpoint removed (or to be removed on a dry run) by pruning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedCheckpoint {
+    pub checkpoint_id: String,
+    pub session_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub description: Option<String>,
+    pub size: u64,
+    pub reason: PruneReason,
+}
+
+/// Outcome of `prune_checkpoints`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub pruned: Vec<PrunedCheckpoint>,
+    pub kept_count: usize,
+    /// Estimated bytes freed by the pruned checkpoints
+    pub estimated_freed_bytes: u64,
+    /// Blobs deleted from the object store (always 0 on a dry run)
+    pub removed_objects: usize,
+}
+
+/// Select the checkpoints the policy allows to remove
+pub fn select_for_pruning(
+    candidates: &[RetentionCandidate],
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> HashMap<String, PruneReason> {
+    let mut by_session: HashMap<&str, Vec<&RetentionCandidate>> = HashMap::new();
+    for candidate in candidates {
+        by_session
+            .entry(candidate.checkpoint.session_id.as_str())
+            .or_default()
+            .push(candidate);
+    }
+
+    let daily_cutoff = now - Duration::days(policy.keep_daily_days as i64);
+    // Only daily checkpoints may be evicted by the size cap; protected and
+    // last-N checkpoints always stay
+    let mut daily = HashSet::new();
+    let mut pruned = HashMap::new();
+
+    for checkpoints in by_session.values_mut() {
+        checkpoints.sort_by(|a, b| b.checkpoint.timestamp.cmp(&a.checkpoint.timestamp));
+        let mut days_seen = HashSet::new();
+        for (index, candidate) in checkpoints.iter().enumerate() {
+            let id = candidate.checkpoint.id.as_str();
+            if candidate.protected || index < policy.keep_last {
+                days_seen.insert(candidate.checkpoint.timestamp.date_naive());
+            } else if policy.keep_daily_days > 0
+                && candidate.checkpoint.timestamp >= daily_cutoff
+                && days_seen.insert(candidate.checkpoint.timestamp.date_naive())
+            {
+                daily.insert(id);
+            } else {
+                pruned.insert(id.to_string(), PruneReason::Expired);
+            }
+        }
+    }
+
+    if let Some(max_total_bytes) = policy.max_total_bytes {
+        let mut total: u64 = candidates
+            .iter()
+            .filter(|c| !pruned.contains_key(&c.checkpoint.id))
+            .map(|c| c.size)
+            .sum();
+
+        let mut evictable: Vec<&RetentionCandidate> = candidates
+            .iter()
+            .filter(|c| daily.contains(c.checkpoint.id.as_str()))
+            .collect();
+        evictable.sort_by(|a, b| a.checkpoint.timestamp.cmp(&b.checkpoint.timestamp));
+
+        for candidate in evictable {
+            if total <= max_total_bytes {
+                break;
+            }
+            total = total.saturating_sub(candidate.size);
+            pruned.insert(candidate.checkpoint.id.clone(), PruneReason::SizeCap);
+        }
+    }
+
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+
+    fn candidate(id: &str, session_id: &str, age_days: i64, size: u64) -> RetentionCandidate {
+        RetentionCandidate {
+            checkpoint: Checkpoint {
+                id: id.to_string(),
+                session_id: session_id.to_string(),
+                project_id: "project".to_string(),
+                message_index: 0,
+                timestamp: Utc::now() - Duration::days(age_days),
+                description: None,
+                parent_checkpoint_id: None,
+                metadata: CheckpointMetadata {
+                    total_tokens: 0,
+                    model_used: "unknown".to_string(),
+                    user_prompt: String::new(),
+                    file_changes: 0,
+                    snapshot_size: size,
+                },
+            },
+            size,
+            protected: false,
+        }
+    }
+
+    #[test]
+    fn test_keeps_last_and_daily_checkpoints() {
+        let policy = RetentionPolicy {
+            keep_last: 1,
+            keep_daily_days: 30,
+            max_total_bytes: None,
+        };
+        let mut old_protected = candidate("protected", "s1", 90, 10);
+        old_protected.protected = true;
+        let candidates = vec![
+            candidate("newest", "s1", 0, 10),
+            candidate("day2-a", "s1", 2, 10),
+            candidate("day2-b", "s1", 2, 10),
+            candidate("old", "s1", 60, 10),
+            old_protected,
+            candidate("other-session", "s2", 60, 10),
+        ];
+
+        let pruned = select_for_pruning(&candidates, &policy, Utc::now());
+
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(pruned.get("old"), Some(&PruneReason::Expired));
+        // Only one checkpoint of the same day survives the daily rule
+        assert!(pruned.contains_key("day2-b") || pruned.contains_key("day2-a"));
+        assert!(!pruned.contains_key("other-session"));
+    }
+
+    #[test]
+    fn test_size_cap_evicts_oldest_daily_checkpoints() {
+        let policy = RetentionPolicy {
+            keep_last: 1,
+            keep_daily_days: 30,
+            max_total_bytes: Some(25),
+        };
+        let candidates = vec![
+            candidate("newest", "s1", 0, 10),
+            candidate("day5", "s1", 5, 10),
+            candidate("day10", "s1", 10, 10),
+        ];
+
+        let pruned = select_for_pruning(&candidates, &policy, Utc::now());
+
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned.get("day10"), Some(&PruneReason::SizeCap));
+    }
+}
@@ -0,0 +1,193 @@
+//! Full-text search over the checkpoints of a project
+//!
+//! Every whitespace-separated term of the query has to appear, case
+//! insensitively, in the description, the user prompt or a changed file path.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::project_timeline::collect_checkpoints;
+use super::storage::CheckpointStorage;
+use super::Checkpoint;
+
+/// Matches in the description weigh more than in the prompt or file paths
+const DESCRIPTION_WEIGHT: u32 = 3;
+const PROMPT_WEIGHT: u32 = 2;
+const FILE_WEIGHT: u32 = 1;
+
+/// Maximum number of matching file paths reported per hit
+const MAX_MATCHED_FILES: usize = 10;
+
+/// Where a query term was found
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Description,
+    UserPrompt,
+    FilePath,
+}
+
+/// A checkpoint matching a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointSearchHit {
+    pub checkpoint: Checkpoint,
+    pub score: u32,
+    pub matched_fields: Vec<SearchField>,
+    /// Changed files whose path matched a term
+    pub matched_files: Vec<PathBuf>,
+}
+
+/// Search all sessions of a project, best matches first
+pub fn search_checkpoints(
+    storage: &CheckpointStorage,
+    project_id: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<CheckpointSearchHit>> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut hits = Vec::new();
+    for (session_id, timeline) in storage.load_project_timelines(project_id)? {
+        let mut checkpoints = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            collect_checkpoints(root, &mut checkpoints);
+        }
+        for checkpoint in checkpoints {
+            let changed_files = storage
+                .changed_files(project_id, &session_id, &checkpoint)
+                .unwrap_or_else(|e| {
+                    log::warn!(
+                        "Failed to read files of checkpoint {}: {}",
+                        checkpoint.id,
+                        e
+                    );
+                    Vec::new()
+                });
+            if let Some(hit) = match_checkpoint(&terms, checkpoint, &changed_files) {
+                hits.push(hit);
+            }
+        }
+    }
+
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.checkpoint.timestamp.cmp(&a.checkpoint.timestamp))
+    });
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+/// Score a checkpoint against lowercase query terms, None unless every term matches
+fn match_checkpoint(
+    terms: &[String],
+    checkpoint: Checkpoint,
+    changed_files: &[PathBuf],
+) -> Option<CheckpointSearchHit> {
+    let description = checkpoint
+        .description
+        .as_deref()
+        .unwrap_or("")
+        .to_lowercase();
+    let prompt = checkpoint.metadata.user_prompt.to_lowercase();
+    let files: Vec<String> = changed_files
+        .iter()
+        .map(|p| p.to_string_lossy().to_lowercase())
+        .collect();
+
+    let mut score = 0;
+    let mut matched_fields = Vec::new();
+    let mut matched_files = Vec::new();
+
+    for term in terms {
+        let mut term_matched = false;
+        if description.contains(term.as_str()) {
+            score += DESCRIPTION_WEIGHT;
+            term_matched = true;
+            if !matched_fields.contains(&SearchField::Description) {
+                matched_fields.push(SearchField::Description);
+            }
+        }
+        if prompt.contains(term.as_str()) {
+            score += PROMPT_WEIGHT;
+            term_matched = true;
+            if !matched_fields.contains(&SearchField::UserPrompt) {
+                matched_fields.push(SearchField::UserPrompt);
+            }
+        }
+        for (path, lowered) in changed_files.iter().zip(&files) {
+            if lowered.contains(term.as_str()) {
+                term_matched = true;
+                if !matched_files.contains(path) {
+                    score += FILE_WEIGHT;
+                    matched_files.push(path.clone());
+                }
+            }
+        }
+        if !term_matched {
+            return None;
+        }
+    }
+
+    if !matched_files.is_empty() {
+        matched_fields.push(SearchField::FilePath);
+    }
+    matched_files.truncate(MAX_MATCHED_FILES);
+
+    Some(CheckpointSearchHit {
+        checkpoint,
+        score,
+        matched_fields,
+        matched_files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+    use chrono::Utc;
+
+    fn checkpoint(description: &str, prompt: &str) -> Checkpoint {
+        Checkpoint {
+            id: "id".to_string(),
+            session_id: "session".to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: Some(description.to_string()),
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "unknown".to_string(),
+                user_prompt: prompt.to_string(),
+                file_changes: 0,
+                snapshot_size: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_all_terms_must_match_across_fields() {
+        let terms = vec!["auth".to_string(), "refactor".to_string()];
+        let files = vec![PathBuf::from("src/Auth/login.rs")];
+
+        let hit = match_checkpoint(
+            &terms,
+            checkpoint("Before refactor", "clean things up"),
+            &files,
+        )
+        .unwrap();
+        assert_eq!(
+            hit.matched_fields,
+            vec![SearchField::Description, SearchField::FilePath]
+        );
+        assert_eq!(hit.matched_files, files);
+
+        assert!(match_checkpoint(&terms, checkpoint("Before refactor", ""), &[]).is_none());
+    }
+}
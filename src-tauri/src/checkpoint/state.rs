@@ -2,7 +2,7 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 
 use super::manager::CheckpointManager;
 
@@ -18,6 +18,8 @@ pub struct CheckpointState {
     managers: Arc<RwLock<HashMap<String, Arc<CheckpointManager>>>>,
     /// The Claude directory path for consistent access
     claude_dir: Arc<RwLock<Option<PathBuf>>>,
+    /// Map of project_id to the lock serializing checkpoint writes
+    project_locks: Arc<RwLock<HashMap<String, Arc<Mutex<()>>>>>,
 }
 
 impl CheckpointState {
@@ -26,6 +28,7 @@ impl CheckpointState {
         Self {
             managers: Arc::new(RwLock::new(HashMap::new())),
             claude_dir: Arc::new(RwLock::new(None)),
+            project_locks: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -84,12 +87,24 @@ impl CheckpointState {
     /// Gets an existing CheckpointManager for a session
     ///
     /// Returns None if no manager exists for the session
-    #[allow(dead_code)]
     pub async fn get_manager(&self, session_id: &str) -> Option<Arc<CheckpointManager>> {
         let managers = self.managers.read().await;
         managers.get(session_id).map(Arc::clone)
     }
 
+    /// Locks checkpoint writes for a project
+    ///
+    /// Sessions of a project share one object store, so creating, restoring
+    /// and pruning checkpoints is serialized per project. The lock is held
+    /// until the returned guard is dropped.
+    pub async fn lock_project(&self, project_id: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.project_locks.write().await;
+            Arc::clone(locks.entry(project_id.to_string()).or_default())
+        };
+        lock.lock_owned().await
+    }
+
     /// Removes a CheckpointManager for a session
     ///
     /// This should be called when a session ends to free resources
@@ -181,4 +196,23 @@ mod tests {
 
         assert!(!Arc::ptr_eq(&manager1, &manager3));
     }
+
+    #[tokio::test]
+    async fn test_project_lock_serializes_writes() {
+        let state = CheckpointState::new();
+        let guard = state.lock_project("project-a").await;
+
+        // Another project is not blocked
+        drop(state.lock_project("project-b").await);
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            state.lock_project("project-a"),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(guard);
+        drop(state.lock_project("project-a").await);
+    }
 }
@@ -1,12 +1,18 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
 use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use zstd::stream::{decode_all, encode_all};
 
+use super::retention::{
+    select_for_pruning, PruneReport, PrunedCheckpoint, RetentionCandidate, RetentionPolicy,
+};
 use super::{
-    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
+    Checkpoint, CheckpointManifest, CheckpointPaths, CheckpointResult, FileSnapshot, ManifestEntry,
+    SessionTimeline, TimelineNode,
 };
 
 /// Manages checkpoint storage operations
@@ -31,7 +37,7 @@ impl CheckpointStorage {
         // Create directory structure
         fs::create_dir_all(&paths.checkpoints_dir)
             .context("Failed to create checkpoints directory")?;
-        fs::create_dir_all(&paths.files_dir).context("Failed to create files directory")?;
+        fs::create_dir_all(&paths.objects_dir).context("Failed to create objects directory")?;
 
         // Initialize empty timeline if it doesn't exist
         if !paths.timeline_file.exists() {
@@ -43,6 +49,10 @@ impl CheckpointStorage {
     }
 
     /// Save a checkpoint to disk
+    ///
+    /// File contents and messages go to the project's object store keyed by
+    /// hash, so content already stored by an earlier checkpoint is not written
+    /// again; the checkpoint itself only keeps a manifest of hashes.
     pub fn save_checkpoint(
         &self,
         project_id: &str,
@@ -63,30 +73,57 @@ impl CheckpointStorage {
             .context("Failed to serialize checkpoint metadata")?;
         fs::write(&metadata_path, metadata_json).context("Failed to write checkpoint metadata")?;
 
-        // Save messages (compressed)
-        let messages_path = paths.checkpoint_messages_file(&checkpoint.id);
-        let compressed_messages = encode_all(messages.as_bytes(), self.compression_level)
-            .context("Failed to compress messages")?;
-        fs::write(&messages_path, compressed_messages)
-            .context("Failed to write compressed messages")?;
+        // Save messages as a blob
+        let messages_hash = Self::calculate_file_hash(messages);
+        self.write_object(&paths, &messages_hash, messages.as_bytes())
+            .context("Failed to write messages")?;
 
         // Save file snapshots
         let mut warnings = Vec::new();
         let mut files_processed = 0;
+        let mut entries = Vec::with_capacity(file_snapshots.len());
+
+        let mut new_hashes = Vec::new();
 
         for snapshot in &file_snapshots {
-            match self.save_file_snapshot(&paths, snapshot) {
-                Ok(_) => files_processed += 1,
-                Err(e) => warnings.push(format!(
-                    "Failed to save {}: {}",
-                    snapshot.file_path.display(),
-                    e
-                )),
+            if !snapshot.is_deleted {
+                match self.write_object(&paths, &snapshot.hash, snapshot.content.as_bytes()) {
+                    Ok(true) => new_hashes.push(snapshot.hash.clone()),
+                    Ok(false) => {}
+                    Err(e) => {
+                        warnings.push(format!(
+                            "Failed to save {}: {}",
+                            snapshot.file_path.display(),
+                            e
+                        ));
+                        continue;
+                    }
+                }
             }
+            files_processed += 1;
+            entries.push(ManifestEntry {
+                path: snapshot.file_path.clone(),
+                hash: snapshot.hash.clone(),
+                is_deleted: snapshot.is_deleted,
+                permissions: snapshot.permissions,
+                size: snapshot.size,
+            });
         }
 
+        // Save the manifest last so it never references missing blobs
+        let manifest = CheckpointManifest {
+            messages_hash,
+            files: entries,
+        };
+        fs::write(
+            paths.checkpoint_manifest_file(&checkpoint.id),
+            serde_json::to_string_pretty(&manifest)
+                .context("Failed to serialize checkpoint manifest")?,
+        )
+        .context("Failed to write checkpoint manifest")?;
+
         // Update timeline
-        self.update_timeline_with_checkpoint(&paths.timeline_file, checkpoint, &file_snapshots)?;
+        self.update_timeline_with_checkpoint(&paths.timeline_file, checkpoint, new_hashes)?;
 
         Ok(CheckpointResult {
             checkpoint: checkpoint.clone(),
@@ -95,52 +132,51 @@ impl CheckpointStorage {
         })
     }
 
-    /// Save a single file snapshot
-    fn save_file_snapshot(&self, paths: &CheckpointPaths, snapshot: &FileSnapshot) -> Result<()> {
-        // Use content-addressable storage: store files by their hash
-        // This prevents duplication of identical file content across checkpoints
-        let content_pool_dir = paths.files_dir.join("content_pool");
-        fs::create_dir_all(&content_pool_dir).context("Failed to create content pool directory")?;
-
-        // Store the actual content in the content pool
-        let content_file = content_pool_dir.join(&snapshot.hash);
-
-        // Only write the content if it doesn't already exist
-        if !content_file.exists() {
-            // Compress and save file content
-            let compressed_content =
-                encode_all(snapshot.content.as_bytes(), self.compression_level)
-                    .context("Failed to compress file content")?;
-            fs::write(&content_file, compressed_content)
-                .context("Failed to write file content to pool")?;
+    /// Store a zstd-compressed blob under its hash unless it already exists
+    ///
+    /// Returns whether a new blob was written.
+    fn write_object(&self, paths: &CheckpointPaths, hash: &str, content: &[u8]) -> Result<bool> {
+        let object_path = paths.object_path(hash);
+        if object_path.exists() {
+            return Ok(false);
         }
 
-        // Create a reference in the checkpoint-specific directory
-        let checkpoint_refs_dir = paths.files_dir.join("refs").join(&snapshot.checkpoint_id);
-        fs::create_dir_all(&checkpoint_refs_dir)
-            .context("Failed to create checkpoint refs directory")?;
-
-        // Save file metadata with reference to content
-        let ref_metadata = serde_json::json!({
-            "path": snapshot.file_path,
-            "hash": snapshot.hash,
-            "is_deleted": snapshot.is_deleted,
-            "permissions": snapshot.permissions,
-            "size": snapshot.size,
-        });
-
-        // Use a sanitized filename for the reference
-        let safe_filename = snapshot
-            .file_path
-            .to_string_lossy()
-            .replace('/', "_")
-            .replace('\\', "_");
-        let ref_path = checkpoint_refs_dir.join(format!("{}.json", safe_filename));
-
-        fs::write(&ref_path, serde_json::to_string_pretty(&ref_metadata)?)
-            .context("Failed to write file reference")?;
+        if let Some(parent) = object_path.parent() {
+            fs::create_dir_all(parent).context("Failed to create object directory")?;
+        }
+        let compressed =
+            encode_all(content, self.compression_level).context("Failed to compress content")?;
+
+        // Write to a temporary file first so a crash never leaves a truncated blob
+        let tmp_path = object_path.with_extension(format!("tmp-{}", Uuid::new_v4()));
+        fs::write(&tmp_path, compressed).context("Failed to write object")?;
+        if let Err(e) = fs::rename(&tmp_path, &object_path) {
+            let _ = fs::remove_file(&tmp_path);
+            // Another checkpoint may have stored the same content concurrently
+            if !object_path.exists() {
+                return Err(e).context("Failed to move object into place");
+            }
+            return Ok(false);
+        }
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Read and decompress a blob, falling back to the session's legacy content pool
+    fn read_object(&self, paths: &CheckpointPaths, hash: &str) -> Result<Vec<u8>> {
+        let object_path = paths.object_path(hash);
+        let path = if object_path.exists() {
+            object_path
+        } else {
+            paths.legacy_content_pool_dir().join(hash)
+        };
+        let compressed = fs::read(&path).context("Failed to read object")?;
+        decode_all(&compressed[..]).context("Failed to decompress object")
+    }
+
+    /// Whether content with this hash is already stored
+    pub fn has_object(&self, paths: &CheckpointPaths, hash: &str) -> bool {
+        paths.object_path(hash).exists() || paths.legacy_content_pool_dir().join(hash).exists()
     }
 
     /// Load a checkpoint from disk
@@ -159,33 +195,79 @@ impl CheckpointStorage {
         let checkpoint: Checkpoint =
             serde_json::from_str(&metadata_json).context("Failed to parse checkpoint metadata")?;
 
+        let manifest_path = paths.checkpoint_manifest_file(checkpoint_id);
+        if !manifest_path.exists() {
+            let (file_snapshots, messages) = self.load_legacy_checkpoint(&paths, checkpoint_id)?;
+            return Ok((checkpoint, file_snapshots, messages));
+        }
+
+        let manifest = self.load_manifest(&manifest_path)?;
+
         // Load messages
-        let messages_path = paths.checkpoint_messages_file(checkpoint_id);
-        let compressed_messages =
-            fs::read(&messages_path).context("Failed to read compressed messages")?;
         let messages = String::from_utf8(
-            decode_all(&compressed_messages[..]).context("Failed to decompress messages")?,
+            self.read_object(&paths, &manifest.messages_hash)
+                .context("Failed to read messages")?,
         )
         .context("Invalid UTF-8 in messages")?;
 
         // Load file snapshots
-        let file_snapshots = self.load_file_snapshots(&paths, checkpoint_id)?;
+        let mut file_snapshots = Vec::with_capacity(manifest.files.len());
+        for entry in manifest.files {
+            let content = if entry.is_deleted {
+                String::new()
+            } else {
+                match self.read_object(&paths, &entry.hash) {
+                    Ok(bytes) => {
+                        String::from_utf8(bytes).context("Invalid UTF-8 in file content")?
+                    }
+                    Err(e) => {
+                        // Handle missing content gracefully
+                        log::warn!("Content missing for hash {}: {}", entry.hash, e);
+                        String::new()
+                    }
+                }
+            };
+            file_snapshots.push(FileSnapshot {
+                checkpoint_id: checkpoint_id.to_string(),
+                file_path: entry.path,
+                content,
+                hash: entry.hash,
+                is_deleted: entry.is_deleted,
+                permissions: entry.permissions,
+                size: entry.size,
+            });
+        }
 
         Ok((checkpoint, file_snapshots, messages))
     }
 
-    /// Load all file snapshots for a checkpoint
-    fn load_file_snapshots(
+    /// Load a checkpoint manifest
+    fn load_manifest(&self, manifest_path: &Path) -> Result<CheckpointManifest> {
+        let manifest_json =
+            fs::read_to_string(manifest_path).context("Failed to read checkpoint manifest")?;
+        serde_json::from_str(&manifest_json).context("Failed to parse checkpoint manifest")
+    }
+
+    /// Load messages and file snapshots of a checkpoint saved with per-file references
+    fn load_legacy_checkpoint(
         &self,
         paths: &CheckpointPaths,
         checkpoint_id: &str,
-    ) -> Result<Vec<FileSnapshot>> {
-        let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
+    ) -> Result<(Vec<FileSnapshot>, String)> {
+        // Load messages
+        let messages_path = paths.checkpoint_messages_file(checkpoint_id);
+        let compressed_messages =
+            fs::read(&messages_path).context("Failed to read compressed messages")?;
+        let messages = String::from_utf8(
+            decode_all(&compressed_messages[..]).context("Failed to decompress messages")?,
+        )
+        .context("Invalid UTF-8 in messages")?;
+
+        let refs_dir = paths.legacy_refs_dir(checkpoint_id);
         if !refs_dir.exists() {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), messages));
         }
 
-        let content_pool_dir = paths.files_dir.join("content_pool");
         let mut snapshots = Vec::new();
 
         // Read all reference files
@@ -208,19 +290,13 @@ impl CheckpointStorage {
                 .ok_or_else(|| anyhow::anyhow!("Missing hash in reference"))?;
 
             // Load content from pool
-            let content_file = content_pool_dir.join(hash);
-            let content = if content_file.exists() {
-                let compressed_content =
-                    fs::read(&content_file).context("Failed to read file content from pool")?;
-                String::from_utf8(
-                    decode_all(&compressed_content[..])
-                        .context("Failed to decompress file content")?,
-                )
-                .context("Invalid UTF-8 in file content")?
-            } else {
-                // Handle missing content gracefully
-                log::warn!("Content file missing for hash: {}", hash);
-                String::new()
+            let content = match self.read_object(paths, hash) {
+                Ok(bytes) => String::from_utf8(bytes).context("Invalid UTF-8 in file content")?,
+                Err(_) => {
+                    // Handle missing content gracefully
+                    log::warn!("Content file missing for hash: {}", hash);
+                    String::new()
+                }
             };
 
             snapshots.push(FileSnapshot {
@@ -234,7 +310,7 @@ impl CheckpointStorage {
             });
         }
 
-        Ok(snapshots)
+        Ok((snapshots, messages))
     }
 
     /// Save timeline to disk
@@ -258,14 +334,14 @@ impl CheckpointStorage {
         &self,
         timeline_path: &Path,
         checkpoint: &Checkpoint,
-        file_snapshots: &[FileSnapshot],
+        new_hashes: Vec<String>,
     ) -> Result<()> {
         let mut timeline = self.load_timeline(timeline_path)?;
 
         let new_node = TimelineNode {
             checkpoint: checkpoint.clone(),
             children: Vec::new(),
-            file_snapshot_ids: file_snapshots.iter().map(|s| s.hash.clone()).collect(),
+            file_snapshot_ids: new_hashes,
         };
 
         // If this is the first checkpoint
@@ -286,6 +362,8 @@ impl CheckpointStorage {
             }
         }
 
+        timeline.advance_current_branch(&checkpoint.id);
+
         timeline.total_checkpoints += 1;
         self.save_timeline(timeline_path, &timeline)?;
 
@@ -333,6 +411,28 @@ impl CheckpointStorage {
         (messages_size + files_size) / 4
     }
 
+    /// Estimate the storage a checkpoint adds, counting only content not stored yet
+    pub fn estimate_new_content_size(
+        &self,
+        paths: &CheckpointPaths,
+        messages: &str,
+        file_snapshots: &[FileSnapshot],
+    ) -> u64 {
+        let messages_size = if self.has_object(paths, &Self::calculate_file_hash(messages)) {
+            0
+        } else {
+            messages.len() as u64
+        };
+        let files_size: u64 = file_snapshots
+            .iter()
+            .filter(|s| !s.is_deleted && !self.has_object(paths, &s.hash))
+            .map(|s| s.content.len() as u64)
+            .sum();
+
+        // Estimate compressed size (typically 20-30% of original for text)
+        (messages_size + files_size) / 4
+    }
+
     /// Clean up old checkpoints based on retention policy
     pub fn cleanup_old_checkpoints(
         &self,
@@ -377,6 +477,174 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 
+    /// Load the timelines of every session in a project as (session ID, timeline)
+    pub fn load_project_timelines(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<(String, SessionTimeline)>> {
+        let timelines_dir = self
+            .claude_dir
+            .join("projects")
+            .join(project_id)
+            .join(".timelines");
+
+        let mut timelines = Vec::new();
+        if timelines_dir.exists() {
+            for entry in fs::read_dir(&timelines_dir)? {
+                let session_dir = entry?.path();
+                let timeline_file = session_dir.join("timeline.json");
+                if !timeline_file.exists() {
+                    continue;
+                }
+                if let Some(session_id) = session_dir.file_name().and_then(|n| n.to_str()) {
+                    timelines.push((session_id.to_string(), self.load_timeline(&timeline_file)?));
+                }
+            }
+        }
+
+        Ok(timelines)
+    }
+
+    /// Paths of the files a checkpoint changed compared to its parent
+    ///
+    /// Only manifests are read, not file contents. Checkpoints saved before
+    /// manifests existed only referenced changed files, so all of them count.
+    pub fn changed_files(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint: &Checkpoint,
+    ) -> Result<Vec<PathBuf>> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let manifest_path = paths.checkpoint_manifest_file(&checkpoint.id);
+
+        if !manifest_path.exists() {
+            let refs_dir = paths.legacy_refs_dir(&checkpoint.id);
+            let mut changed = Vec::new();
+            if refs_dir.exists() {
+                for entry in fs::read_dir(&refs_dir)? {
+                    let ref_json = fs::read_to_string(entry?.path())?;
+                    if let Ok(ref_metadata) = serde_json::from_str::<serde_json::Value>(&ref_json) {
+                        if let Some(path) = ref_metadata["path"].as_str() {
+                            changed.push(PathBuf::from(path));
+                        }
+                    }
+                }
+            }
+            return Ok(changed);
+        }
+
+        let manifest = self.load_manifest(&manifest_path)?;
+        let parent_files: HashMap<PathBuf, String> = match &checkpoint.parent_checkpoint_id {
+            Some(parent_id) if paths.checkpoint_manifest_file(parent_id).exists() => self
+                .load_manifest(&paths.checkpoint_manifest_file(parent_id))?
+                .files
+                .into_iter()
+                .filter(|f| !f.is_deleted)
+                .map(|f| (f.path, f.hash))
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        let mut changed = Vec::new();
+        let mut present = HashSet::new();
+        for entry in manifest.files {
+            if entry.is_deleted {
+                continue;
+            }
+            if parent_files.get(&entry.path) != Some(&entry.hash) {
+                changed.push(entry.path.clone());
+            }
+            present.insert(entry.path);
+        }
+        // Files of the parent that no longer exist were deleted
+        changed.extend(parent_files.into_keys().filter(|p| !present.contains(p)));
+
+        Ok(changed)
+    }
+
+    /// Prune checkpoints of every session in a project according to `policy`
+    ///
+    /// On a dry run nothing is removed and the report lists what would be.
+    pub fn prune_checkpoints(
+        &self,
+        project_id: &str,
+        policy: &RetentionPolicy,
+        dry_run: bool,
+    ) -> Result<PruneReport> {
+        let timelines = self.load_project_timelines(project_id)?;
+
+        let mut candidates = Vec::new();
+        for (_, timeline) in &timelines {
+            let protected = timeline.protected_checkpoint_ids();
+            let mut checkpoints = Vec::new();
+            if let Some(root) = &timeline.root_node {
+                Self::collect_checkpoints(root, &mut checkpoints);
+            }
+            for checkpoint in checkpoints {
+                candidates.push(RetentionCandidate {
+                    size: checkpoint.metadata.snapshot_size,
+                    protected: protected.contains(&checkpoint.id),
+                    checkpoint,
+                });
+            }
+        }
+
+        let selected = select_for_pruning(&candidates, policy, Utc::now());
+        let mut pruned: Vec<PrunedCheckpoint> = candidates
+            .iter()
+            .filter_map(|c| {
+                selected
+                    .get(&c.checkpoint.id)
+                    .map(|reason| PrunedCheckpoint {
+                        checkpoint_id: c.checkpoint.id.clone(),
+                        session_id: c.checkpoint.session_id.clone(),
+                        timestamp: c.checkpoint.timestamp,
+                        description: c.checkpoint.description.clone(),
+                        size: c.size,
+                        reason: *reason,
+                    })
+            })
+            .collect();
+        pruned.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+        let mut report = PruneReport {
+            dry_run,
+            kept_count: candidates.len() - pruned.len(),
+            estimated_freed_bytes: pruned.iter().map(|p| p.size).sum(),
+            pruned,
+            removed_objects: 0,
+        };
+        if dry_run {
+            return Ok(report);
+        }
+
+        for (session_id, mut timeline) in timelines {
+            let paths = CheckpointPaths::new(&self.claude_dir, project_id, &session_id);
+            let mut changed = false;
+            for pruned in report.pruned.iter().filter(|p| p.session_id == session_id) {
+                if !timeline.remove_checkpoint_node(&pruned.checkpoint_id) {
+                    log::warn!("Could not unlink checkpoint {}", pruned.checkpoint_id);
+                    continue;
+                }
+                changed = true;
+                if let Err(e) = self.remove_checkpoint(&paths, &pruned.checkpoint_id) {
+                    log::warn!(
+                        "Failed to remove checkpoint {}: {}",
+                        pruned.checkpoint_id,
+                        e
+                    );
+                }
+            }
+            if changed {
+                self.save_timeline(&paths.timeline_file, &timeline)?;
+                report.removed_objects += self.garbage_collect_content(project_id, &session_id)?;
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Collect all checkpoints from the tree in order
     fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
         checkpoints.push(node.checkpoint.clone());
@@ -393,45 +661,124 @@ impl CheckpointStorage {
             fs::remove_dir_all(&checkpoint_dir).context("Failed to remove checkpoint directory")?;
         }
 
-        // Remove file references for this checkpoint
-        let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
+        // Remove legacy file references for this checkpoint
+        let refs_dir = paths.legacy_refs_dir(checkpoint_id);
         if refs_dir.exists() {
             fs::remove_dir_all(&refs_dir).context("Failed to remove file references")?;
         }
 
-        // Note: We don't remove content from the pool here as it might be
-        // referenced by other checkpoints. Use garbage_collect_content() for that.
+        // Note: We don't remove blobs here as they might be referenced by
+        // other checkpoints. Use garbage_collect_content() for that.
 
         Ok(())
     }
 
-    /// Garbage collect unreferenced content from the content pool
+    /// Garbage collect unreferenced content from the object store
+    ///
+    /// Blobs are shared by every session of the project, so references from
+    /// all of its timelines are considered. The session's legacy content pool
+    /// is collected as well.
     pub fn garbage_collect_content(&self, project_id: &str, session_id: &str) -> Result<usize> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
-        let content_pool_dir = paths.files_dir.join("content_pool");
-        let refs_dir = paths.files_dir.join("refs");
+        let referenced_hashes = self.collect_referenced_hashes(&paths)?;
 
-        if !content_pool_dir.exists() {
-            return Ok(0);
+        let mut removed_count = 0;
+
+        if paths.objects_dir.exists() {
+            for shard_entry in fs::read_dir(&paths.objects_dir)? {
+                let shard_dir = shard_entry?.path();
+                if !shard_dir.is_dir() {
+                    continue;
+                }
+                let Some(prefix) = shard_dir.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                let prefix = prefix.to_string();
+                for object_entry in fs::read_dir(&shard_dir)? {
+                    let object_file = object_entry?.path();
+                    let Some(rest) = object_file.file_name().and_then(|n| n.to_str()) else {
+                        continue;
+                    };
+                    // Blobs written moments ago may belong to a checkpoint
+                    // whose manifest is still being saved
+                    if Self::is_recent(&object_file) {
+                        continue;
+                    }
+                    if !referenced_hashes.contains(&format!("{}{}", prefix, rest))
+                        && fs::remove_file(&object_file).is_ok()
+                    {
+                        removed_count += 1;
+                    }
+                }
+                // Drop the shard directory once it is empty
+                let _ = fs::remove_dir(&shard_dir);
+            }
         }
 
-        // Collect all referenced hashes
-        let mut referenced_hashes = std::collections::HashSet::new();
+        let content_pool_dir = paths.legacy_content_pool_dir();
+        if content_pool_dir.exists() {
+            for entry in fs::read_dir(&content_pool_dir)? {
+                let content_file = entry?.path();
+                if content_file.is_file() {
+                    if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
+                        if !referenced_hashes.contains(hash)
+                            && fs::remove_file(&content_file).is_ok()
+                        {
+                            removed_count += 1;
+                        }
+                    }
+                }
+            }
+        }
 
-        if refs_dir.exists() {
-            for checkpoint_entry in fs::read_dir(&refs_dir)? {
-                let checkpoint_dir = checkpoint_entry?.path();
-                if checkpoint_dir.is_dir() {
+        Ok(removed_count)
+    }
+
+    /// Collect blob hashes referenced by any checkpoint of the project
+    fn collect_referenced_hashes(&self, paths: &CheckpointPaths) -> Result<HashSet<String>> {
+        let mut referenced_hashes = HashSet::new();
+        let Some(timelines_dir) = paths.objects_dir.parent() else {
+            return Ok(referenced_hashes);
+        };
+
+        for session_entry in fs::read_dir(timelines_dir)? {
+            let session_dir = session_entry?.path();
+            if !session_dir.is_dir() || session_dir == paths.objects_dir {
+                continue;
+            }
+
+            let checkpoints_dir = session_dir.join("checkpoints");
+            if checkpoints_dir.exists() {
+                for checkpoint_entry in fs::read_dir(&checkpoints_dir)? {
+                    let manifest_path = checkpoint_entry?.path().join("manifest.json");
+                    if !manifest_path.exists() {
+                        continue;
+                    }
+                    // An unreadable manifest must not let its blobs be collected
+                    let manifest = self.load_manifest(&manifest_path)?;
+                    referenced_hashes.insert(manifest.messages_hash);
+                    referenced_hashes.extend(manifest.files.into_iter().map(|f| f.hash));
+                }
+            }
+
+            let refs_dir = session_dir.join("files").join("refs");
+            if refs_dir.exists() {
+                for checkpoint_entry in fs::read_dir(&refs_dir)? {
+                    let checkpoint_dir = checkpoint_entry?.path();
+                    if !checkpoint_dir.is_dir() {
+                        continue;
+                    }
                     for ref_entry in fs::read_dir(&checkpoint_dir)? {
                         let ref_path = ref_entry?.path();
-                        if ref_path.extension().and_then(|e| e.to_str()) == Some("json") {
-                            if let Ok(ref_json) = fs::read_to_string(&ref_path) {
-                                if let Ok(ref_metadata) =
-                                    serde_json::from_str::<serde_json::Value>(&ref_json)
-                                {
-                                    if let Some(hash) = ref_metadata["hash"].as_str() {
-                                        referenced_hashes.insert(hash.to_string());
-                                    }
+                        if ref_path.extension().and_then(|e| e.to_str()) != Some("json") {
+                            continue;
+                        }
+                        if let Ok(ref_json) = fs::read_to_string(&ref_path) {
+                            if let Ok(ref_metadata) =
+                                serde_json::from_str::<serde_json::Value>(&ref_json)
+                            {
+                                if let Some(hash) = ref_metadata["hash"].as_str() {
+                                    referenced_hashes.insert(hash.to_string());
                                 }
                             }
                         }
@@ -440,21 +787,110 @@ impl CheckpointStorage {
             }
         }
 
-        // Remove unreferenced content
-        let mut removed_count = 0;
-        for entry in fs::read_dir(&content_pool_dir)? {
-            let content_file = entry?.path();
-            if content_file.is_file() {
-                if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
-                    if !referenced_hashes.contains(hash) {
-                        if fs::remove_file(&content_file).is_ok() {
-                            removed_count += 1;
-                        }
-                    }
-                }
-            }
+        Ok(referenced_hashes)
+    }
+
+    /// Whether a file was modified within the last minute
+    fn is_recent(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map(|age| age.as_secs() < 60)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::CheckpointMetadata;
+    use tempfile::TempDir;
+
+    fn checkpoint(session_id: &str, parent: Option<String>) -> Checkpoint {
+        Checkpoint {
+            id: CheckpointStorage::generate_checkpoint_id(),
+            session_id: session_id.to_string(),
+            project_id: "project".to_string(),
+            message_index: 0,
+            timestamp: Utc::now(),
+            description: None,
+            parent_checkpoint_id: parent,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: "unknown".to_string(),
+                user_prompt: String::new(),
+                file_changes: 0,
+                snapshot_size: 0,
+            },
         }
+    }
 
-        Ok(removed_count)
+    fn snapshot(checkpoint_id: &str, path: &str, content: &str) -> FileSnapshot {
+        FileSnapshot {
+            checkpoint_id: checkpoint_id.to_string(),
+            file_path: PathBuf::from(path),
+            content: content.to_string(),
+            hash: CheckpointStorage::calculate_file_hash(content),
+            is_deleted: false,
+            permissions: None,
+            size: content.len() as u64,
+        }
+    }
+
+    fn object_count(paths: &CheckpointPaths) -> usize {
+        fs::read_dir(&paths.objects_dir)
+            .unwrap()
+            .map(|shard| fs::read_dir(shard.unwrap().path()).unwrap().count())
+            .sum()
+    }
+
+    #[test]
+    fn test_identical_content_is_stored_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+        let paths = CheckpointPaths::new(&storage.claude_dir, "project", "session");
+
+        let first = checkpoint("session", None);
+        storage
+            .save_checkpoint(
+                "project",
+                "session",
+                &first,
+                vec![
+                    snapshot(&first.id, "a.txt", "same"),
+                    snapshot(&first.id, "b.txt", "same"),
+                ],
+                "{}",
+            )
+            .unwrap();
+        // One blob for the shared file content, one for the messages
+        assert_eq!(object_count(&paths), 2);
+
+        let second = checkpoint("session", Some(first.id.clone()));
+        storage
+            .save_checkpoint(
+                "project",
+                "session",
+                &second,
+                vec![
+                    snapshot(&second.id, "a.txt", "same"),
+                    snapshot(&second.id, "b.txt", "changed"),
+                ],
+                "{}",
+            )
+            .unwrap();
+        assert_eq!(object_count(&paths), 3);
+
+        let (_, files, messages) = storage
+            .load_checkpoint("project", "session", &second.id)
+            .unwrap();
+        assert_eq!(messages, "{}");
+        let b = files
+            .iter()
+            .find(|f| f.file_path == PathBuf::from("b.txt"))
+            .unwrap();
+        assert_eq!(b.content, "changed");
     }
 }
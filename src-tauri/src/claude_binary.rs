@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 /// Shared module for detecting Claude Code binary installations
 /// Supports NVM installations, aliased paths, version-based selection, and bundled sidecars
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tauri::Manager;
 
@@ -17,6 +17,10 @@ pub enum InstallationType {
     System,
     /// Custom path specified by user
     Custom,
+    /// Version installed by the built-in version manager
+    Managed,
+    /// Installed inside a WSL distribution, launched through wsl.exe
+    Wsl,
 }
 
 /// Represents a Claude installation with metadata
@@ -38,7 +42,7 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
     info!("Searching for system Claude CLI...");
 
     // First check if we have a stored path in the database
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+    if let Ok(app_data_dir) = crate::portable::app_data_dir(app_handle) {
         let db_path = app_data_dir.join("agents.db");
         if db_path.exists() {
             if let Ok(conn) = rusqlite::Connection::open(&db_path) {
@@ -51,7 +55,8 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
                     
                     // Verify the stored path still exists and is accessible
                     let path_buf = PathBuf::from(&stored_path);
-                    if path_buf.exists() && path_buf.is_file() {
+                    let is_wsl = parse_wsl_path(&stored_path).is_some();
+                    if is_wsl || (path_buf.exists() && path_buf.is_file()) {
                         // Test if the binary is actually executable
                         if test_claude_binary(&stored_path) {
                             info!("Using cached Claude CLI path: {}", stored_path);
@@ -77,10 +82,21 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
         }
     }
 
-    // Discover all available system installations
-    let installations = discover_system_installations();
+    // Discover all available system installations (cached between runs)
+    let installations: Vec<ClaudeInstallation> = cached_claude_installations(app_handle)
+        .into_iter()
+        .filter(|installation| installation.installation_type != InstallationType::Wsl)
+        .collect();
 
     if installations.is_empty() {
+        // Fall back to the self-contained runtime shipped with or downloaded by the app
+        if let Some(runtime) = find_runtime_installation(app_handle) {
+            info!(
+                "No system Claude CLI found, using runtime at {}",
+                runtime.path
+            );
+            return Ok(runtime.path);
+        }
         error!("Could not find claude CLI in any location");
         return Err("Claude CLI not found. Please install Claude CLI using 'npm install -g @anthropic/claude' or ensure it's in your PATH".to_string());
     }
@@ -110,7 +126,7 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
 
 /// Store Claude CLI path in database for future use
 fn store_claude_path(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+    if let Ok(app_data_dir) = crate::portable::app_data_dir(app_handle) {
         if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
             return Err(format!("Failed to create app data directory: {}", e));
         }
@@ -152,8 +168,7 @@ pub fn test_claude_binary(path: &str) -> bool {
     debug!("Testing Claude binary at: {}", path);
     
     // Test with a simple --version command 
-    let mut cmd = Command::new(path);
-    cmd.arg("--version");
+    let mut cmd = version_command(path);
     
     // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
     #[cfg(target_os = "windows")]
@@ -184,6 +199,7 @@ pub fn discover_claude_installations() -> Vec<ClaudeInstallation> {
 
     // Only discover system installations - no bundled sidecar
     installations.extend(discover_system_installations());
+    installations.extend(find_wsl_installations());
 
     // Sort by installation type, then by version (highest first), then by source preference
     installations.sort_by(|a, b| {
@@ -214,11 +230,15 @@ fn source_preference(installation: &ClaudeInstallation) -> u8 {
         "homebrew" => 2,
         "system" => 3,
         source if source.starts_with("nvm") => 4,
+        source if source.starts_with("fnm") => 4,
+        "volta" => 4,
         "local-bin" => 5,
         "claude-local" => 6,
-        "npm-global" => 7,
+        "npm-global" | "npm-appdata" | "npm-global-windows" | "npm-prefix" => 7,
         "yarn" | "yarn-global" => 8,
         "bun" => 9,
+        "scoop" => 9,
+        source if source.starts_with("wsl") => 12,
         "node-modules" => 10,
         "home-bin" => 11,
         "PATH" => 12,
@@ -231,22 +251,23 @@ fn discover_system_installations() -> Vec<ClaudeInstallation> {
     let mut installations = Vec::new();
 
     // 1. Try 'where' command on Windows
-    if let Some(installation) = try_where_command() {
-        installations.push(installation);
-    }
+    installations.extend(try_where_command());
 
     // 2. Try 'which' command (fallback, but not really needed on Windows)
     if let Some(installation) = try_which_command() {
         installations.push(installation);
     }
 
-    // 3. Check NVM paths (Windows)
+    // 3. Check Node version managers (nvm, nvm-windows, fnm, volta)
     installations.extend(find_nvm_installations());
 
-    // 4. Check standard paths (Windows)
+    // 4. Check package manager prefixes (bun, scoop, homebrew, npm prefix)
+    installations.extend(find_package_manager_installations());
+
+    // 5. Check standard paths (Windows)
     installations.extend(find_standard_installations());
 
-    // 5. Check Windows-specific paths
+    // 6. Check Windows-specific paths
     installations.extend(find_windows_installations());
 
     // Remove duplicates by path
@@ -266,8 +287,8 @@ fn discover_system_installations() -> Vec<ClaudeInstallation> {
 }
 
 
-/// Try using the 'where' command to find Claude on Windows
-fn try_where_command() -> Option<ClaudeInstallation> {
+/// Try using the 'where' command to find every Claude on the Windows PATH
+fn try_where_command() -> Vec<ClaudeInstallation> {
     debug!("Trying 'where claude' to find binary...");
 
     let mut cmd = Command::new("where");
@@ -282,34 +303,32 @@ fn try_where_command() -> Option<ClaudeInstallation> {
     
     match cmd.output() {
         Ok(output) if output.status.success() => {
-            let output_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-            if output_str.is_empty() {
-                return None;
-            }
-
-            // 'where' can return multiple paths, take the first one
-            let path = output_str.lines().next()?.trim().to_string();
-
-            debug!("'where' found claude at: {}", path);
-
-            // Verify the path exists
-            if !PathBuf::from(&path).exists() {
-                warn!("Path from 'where' does not exist: {}", path);
-                return None;
-            }
-
-            // Get version
-            let version = get_claude_version(&path).ok().flatten();
+            let output_str = String::from_utf8_lossy(&output.stdout);
+
+            // 'where' returns one line per match in PATH order
+            output_str
+                .lines()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .filter_map(|path| {
+                    debug!("'where' found claude at: {}", path);
+
+                    // Verify the path exists
+                    if !PathBuf::from(path).exists() {
+                        warn!("Path from 'where' does not exist: {}", path);
+                        return None;
+                    }
 
-            Some(ClaudeInstallation {
-                path,
-                version,
-                source: "where".to_string(),
-                installation_type: InstallationType::System,
-            })
+                    Some(ClaudeInstallation {
+                        path: path.to_string(),
+                        version: get_claude_version(path).ok().flatten(),
+                        source: "where".to_string(),
+                        installation_type: InstallationType::System,
+                    })
+                })
+                .collect()
         }
-        _ => None,
+        _ => Vec::new(),
     }
 }
 
@@ -371,52 +390,172 @@ fn try_which_command() -> Option<ClaudeInstallation> {
     }
 }
 
-/// Find Claude installations in NVM directories (Windows-only)
+/// File names a Claude binary or shim may have on this platform
+fn claude_binary_names() -> &'static [&'static str] {
+    if cfg!(target_os = "windows") {
+        &["claude.cmd", "claude.exe", "claude"]
+    } else {
+        &["claude"]
+    }
+}
+
+/// The first Claude binary found in `dir`, with its version
+fn installation_in_dir(dir: &Path, source: &str) -> Option<ClaudeInstallation> {
+    let path = claude_binary_names()
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())?;
+    let path_str = path.to_string_lossy().to_string();
+    debug!("Found Claude in {} directory: {}", source, path_str);
+
+    Some(ClaudeInstallation {
+        version: get_claude_version(&path_str).ok().flatten(),
+        path: path_str,
+        source: source.to_string(),
+        installation_type: InstallationType::System,
+    })
+}
+
+/// Directory from an environment variable, or `fallback` when it is unset
+fn env_dir_or(var: &str, fallback: Option<PathBuf>) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or(fallback)
+}
+
+/// Claude installations inside every Node version of a version manager
+///
+/// `versions_dir` holds one directory per Node version; `bin_subdirs` are
+/// the places inside a version directory where global npm binaries live.
+fn find_in_node_versions(
+    versions_dir: &Path,
+    bin_subdirs: &[&str],
+    manager: &str,
+) -> Vec<ClaudeInstallation> {
+    debug!("Checking {} directory: {:?}", manager, versions_dir);
+    let Ok(entries) = std::fs::read_dir(versions_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter_map(|entry| {
+            let node_version = entry.file_name().to_string_lossy().to_string();
+            let source = format!("{} ({})", manager, node_version);
+            // Only add one per node version
+            bin_subdirs
+                .iter()
+                .find_map(|subdir| installation_in_dir(&entry.path().join(subdir), &source))
+        })
+        .collect()
+}
+
+/// Find Claude installed through Node version managers: nvm, nvm-windows,
+/// fnm and volta
 fn find_nvm_installations() -> Vec<ClaudeInstallation> {
     let mut installations = Vec::new();
+    let home = dirs::home_dir();
+    let appdata = std::env::var_os("APPDATA").map(PathBuf::from);
+    let local_appdata = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+
+    // nvm keeps versions/node/<version>/bin
+    if let Some(nvm_dir) = env_dir_or("NVM_DIR", home.as_ref().map(|h| h.join(".nvm"))) {
+        installations.extend(find_in_node_versions(
+            &nvm_dir.join("versions").join("node"),
+            &["bin"],
+            "nvm",
+        ));
+    }
 
-    // Use USERPROFILE for Windows
-    if let Ok(home) = std::env::var("USERPROFILE") {
-        let nvm_dir = PathBuf::from(&home)
-            .join(".nvm")
-            .join("versions")
-            .join("node");
-
-        debug!("Checking NVM directory: {:?}", nvm_dir);
-
-        if let Ok(entries) = std::fs::read_dir(&nvm_dir) {
-            for entry in entries.flatten() {
-                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                    // Check both 'claude' and 'claude.cmd' for Windows
-                    let claude_paths = [
-                        entry.path().join("bin").join("claude"),
-                        entry.path().join("bin").join("claude.cmd"),
-                    ];
-
-                    for claude_path in &claude_paths {
-                        if claude_path.exists() && claude_path.is_file() {
-                            let path_str = claude_path.to_string_lossy().to_string();
-                            let node_version = entry.file_name().to_string_lossy().to_string();
-
-                            debug!("Found Claude in NVM node {}: {}", node_version, path_str);
-
-                            // Get Claude version
-                            let version = get_claude_version(&path_str).ok().flatten();
-
-                            installations.push(ClaudeInstallation {
-                                path: path_str,
-                                version,
-                                source: format!("nvm ({})", node_version),
-                                installation_type: InstallationType::System,
-                            });
-                            break; // Only add one per node version
-                        }
-                    }
-                }
-            }
+    // nvm-windows keeps <version> directly under NVM_HOME, binaries at its root
+    if let Some(nvm_home) = env_dir_or("NVM_HOME", appdata.as_ref().map(|a| a.join("nvm"))) {
+        installations.extend(find_in_node_versions(&nvm_home, &[""], "nvm"));
+    }
+    if let Some(nvm_symlink) = env_dir_or("NVM_SYMLINK", None) {
+        installations.extend(installation_in_dir(&nvm_symlink, "nvm"));
+    }
+
+    // fnm keeps node-versions/<version>/installation, with bin/ outside Windows
+    let fnm_fallbacks = [
+        appdata.as_ref().map(|a| a.join("fnm")),
+        local_appdata.as_ref().map(|a| a.join("fnm")),
+        home.as_ref()
+            .map(|h| h.join(".local").join("share").join("fnm")),
+        home.as_ref().map(|h| h.join(".fnm")),
+    ];
+    let fnm_dirs: Vec<PathBuf> = match env_dir_or("FNM_DIR", None) {
+        Some(dir) => vec![dir],
+        None => fnm_fallbacks.into_iter().flatten().collect(),
+    };
+    for fnm_dir in fnm_dirs {
+        installations.extend(find_in_node_versions(
+            &fnm_dir.join("node-versions"),
+            &["installation", "installation/bin"],
+            "fnm",
+        ));
+    }
+
+    // volta puts shims for global packages into bin
+    let volta_fallback = if cfg!(target_os = "windows") {
+        local_appdata.as_ref().map(|a| a.join("Volta"))
+    } else {
+        home.as_ref().map(|h| h.join(".volta"))
+    };
+    if let Some(volta_home) = env_dir_or("VOLTA_HOME", volta_fallback) {
+        installations.extend(installation_in_dir(&volta_home.join("bin"), "volta"));
+    }
+
+    installations
+}
+
+/// Find Claude installed through bun, scoop, homebrew or a custom npm prefix
+fn find_package_manager_installations() -> Vec<ClaudeInstallation> {
+    let mut installations = Vec::new();
+    let home = dirs::home_dir();
+
+    if let Some(bun_dir) = env_dir_or("BUN_INSTALL", home.as_ref().map(|h| h.join(".bun"))) {
+        installations.extend(installation_in_dir(&bun_dir.join("bin"), "bun"));
+    }
+
+    if let Some(scoop_dir) = env_dir_or("SCOOP", home.as_ref().map(|h| h.join("scoop"))) {
+        let scoop_bins = [
+            scoop_dir.join("shims"),
+            scoop_dir
+                .join("apps")
+                .join("nodejs")
+                .join("current")
+                .join("bin"),
+            scoop_dir
+                .join("apps")
+                .join("nodejs-lts")
+                .join("current")
+                .join("bin"),
+            scoop_dir.join("persist").join("nodejs").join("bin"),
+            scoop_dir.join("persist").join("nodejs-lts").join("bin"),
+        ];
+        for dir in &scoop_bins {
+            installations.extend(installation_in_dir(dir, "scoop"));
         }
     }
 
+    let homebrew_bins = [
+        env_dir_or("HOMEBREW_PREFIX", None).map(|p| p.join("bin")),
+        Some(PathBuf::from("/opt/homebrew/bin")),
+        Some(PathBuf::from("/usr/local/bin")),
+        Some(PathBuf::from("/home/linuxbrew/.linuxbrew/bin")),
+    ];
+    for dir in homebrew_bins.iter().flatten() {
+        installations.extend(installation_in_dir(dir, "homebrew"));
+    }
+
+    // A global npm prefix moved away from the default location
+    if let Some(prefix) = env_dir_or("NPM_CONFIG_PREFIX", None) {
+        installations.extend(installation_in_dir(&prefix, "npm-prefix"));
+        installations.extend(installation_in_dir(&prefix.join("bin"), "npm-prefix"));
+    }
+
     installations
 }
 
@@ -568,11 +707,10 @@ fn find_windows_installations() -> Vec<ClaudeInstallation> {
     installations
 }
 /// Get Claude version by running --version command (Windows-only)
-fn get_claude_version(path: &str) -> Result<Option<String>, String> {
+pub fn get_claude_version(path: &str) -> Result<Option<String>, String> {
     debug!("Getting version for Claude at: {}", path);
     
-    let mut cmd = Command::new(path);
-    cmd.arg("--version");
+    let mut cmd = version_command(path);
     
     // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
     #[cfg(target_os = "windows")]
@@ -700,6 +838,1158 @@ fn compare_versions(a: &str, b: &str) -> Ordering {
     Ordering::Equal
 }
 
+/// npm package managed Claude CLI versions are installed from
+const CLAUDE_NPM_PACKAGE: &str = "@anthropic-ai/claude-code";
+
+/// Per-project file pinning the Claude CLI version, meant to be committed
+pub const VERSION_PIN_FILE: &str = ".claude-version";
+
+/// Directory holding one npm prefix per managed Claude CLI version
+fn managed_versions_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    crate::portable::app_data_dir(app_handle)
+        .map(|dir| dir.join("claude-versions"))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// Path of the Claude binary inside a managed version's npm prefix
+fn managed_binary_path(version_dir: &Path) -> PathBuf {
+    let bin_dir = version_dir.join("node_modules").join(".bin");
+    if cfg!(target_os = "windows") {
+        bin_dir.join("claude.cmd")
+    } else {
+        bin_dir.join("claude")
+    }
+}
+
+/// Whether `version` is an exact semantic version like "1.0.41"
+fn is_exact_version(version: &str) -> bool {
+    regex::Regex::new(r"^\d+\.\d+\.\d+(?:-[a-zA-Z0-9.-]+)?$")
+        .map(|re| re.is_match(version))
+        .unwrap_or(false)
+}
+
+/// Create an npm command with the Windows console window hidden
+fn npm_command() -> Command {
+    let program = if cfg!(target_os = "windows") {
+        "npm.cmd"
+    } else {
+        "npm"
+    };
+    #[allow(unused_mut)]
+    let mut cmd = create_command_with_env(program);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    cmd
+}
+
+/// Lists Claude CLI versions published on npm, newest first
+pub fn list_available_claude_versions() -> Result<Vec<String>, String> {
+    let output = npm_command()
+        .args(["view", CLAUDE_NPM_PACKAGE, "versions", "--json"])
+        .output()
+        .map_err(|e| format!("Failed to run npm: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "npm view failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let mut versions: Vec<String> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse npm versions: {}", e))?;
+    versions.sort_by(|a, b| compare_versions(b, a));
+    Ok(versions)
+}
+
+/// Lists the Claude CLI versions installed by the version manager
+pub fn list_managed_installations(app_handle: &tauri::AppHandle) -> Vec<ClaudeInstallation> {
+    let Ok(versions_dir) = managed_versions_dir(app_handle) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&versions_dir) else {
+        return Vec::new();
+    };
+
+    let mut installations: Vec<ClaudeInstallation> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let version = entry.file_name().to_string_lossy().to_string();
+            let binary = managed_binary_path(&entry.path());
+            if !is_exact_version(&version) || !binary.is_file() {
+                return None;
+            }
+            Some(ClaudeInstallation {
+                path: binary.to_string_lossy().to_string(),
+                version: Some(version),
+                source: "managed".to_string(),
+                installation_type: InstallationType::Managed,
+            })
+        })
+        .collect();
+
+    installations.sort_by(|a, b| {
+        compare_versions(
+            b.version.as_deref().unwrap_or_default(),
+            a.version.as_deref().unwrap_or_default(),
+        )
+    });
+    installations
+}
+
+/// Downloads and installs a Claude CLI version from npm
+///
+/// Each version gets its own npm prefix, so installing one never touches
+/// the global installation or other managed versions. "latest" resolves to
+/// the newest published version.
+pub fn install_claude_version(
+    app_handle: &tauri::AppHandle,
+    version: &str,
+) -> Result<ClaudeInstallation, String> {
+    let version = if version == "latest" {
+        list_available_claude_versions()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "No published Claude CLI versions found".to_string())?
+    } else {
+        version.to_string()
+    };
+    if !is_exact_version(&version) {
+        return Err(format!("Invalid Claude CLI version: {}", version));
+    }
+
+    let version_dir = managed_versions_dir(app_handle)?.join(&version);
+    if managed_binary_path(&version_dir).is_file() {
+        info!("Claude CLI {} is already installed", version);
+    } else {
+        info!("Installing Claude CLI {} into {:?}", version, version_dir);
+        std::fs::create_dir_all(&version_dir)
+            .map_err(|e| format!("Failed to create version directory: {}", e))?;
+
+        let output = npm_command()
+            .arg("install")
+            .arg("--prefix")
+            .arg(&version_dir)
+            .arg("--no-save")
+            .arg(format!("{}@{}", CLAUDE_NPM_PACKAGE, version))
+            .output()
+            .map_err(|e| format!("Failed to run npm: {}", e))?;
+        if !output.status.success() || !managed_binary_path(&version_dir).is_file() {
+            let _ = std::fs::remove_dir_all(&version_dir);
+            return Err(format!(
+                "Failed to install Claude CLI {}: {}",
+                version,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+
+    let path = managed_binary_path(&version_dir)
+        .to_string_lossy()
+        .to_string();
+    Ok(ClaudeInstallation {
+        version: get_claude_version(&path).ok().flatten().or(Some(version)),
+        path,
+        source: "managed".to_string(),
+        installation_type: InstallationType::Managed,
+    })
+}
+
+/// Removes a managed Claude CLI version
+pub fn uninstall_claude_version(
+    app_handle: &tauri::AppHandle,
+    version: &str,
+) -> Result<(), String> {
+    if !is_exact_version(version) {
+        return Err(format!("Invalid Claude CLI version: {}", version));
+    }
+    let version_dir = managed_versions_dir(app_handle)?.join(version);
+    if !version_dir.exists() {
+        return Err(format!("Claude CLI {} is not installed", version));
+    }
+    std::fs::remove_dir_all(&version_dir)
+        .map_err(|e| format!("Failed to remove Claude CLI {}: {}", version, e))?;
+    info!("Uninstalled Claude CLI {}", version);
+    Ok(())
+}
+
+/// Find an installed Claude CLI of exactly `version`, managed ones first
+fn find_installation_with_version(
+    app_handle: &tauri::AppHandle,
+    version: &str,
+) -> Option<ClaudeInstallation> {
+    let has_version =
+        |installation: &ClaudeInstallation| installation.version.as_deref() == Some(version);
+    list_managed_installations(app_handle)
+        .into_iter()
+        .find(has_version)
+        .or_else(|| {
+            cached_claude_installations(app_handle)
+                .into_iter()
+                .find(has_version)
+        })
+}
+
+/// Makes an installed Claude CLI version the one used by default
+pub fn switch_claude_version(
+    app_handle: &tauri::AppHandle,
+    version: &str,
+) -> Result<ClaudeInstallation, String> {
+    let installation = find_installation_with_version(app_handle, version)
+        .ok_or_else(|| format!("Claude CLI {} is not installed", version))?;
+    store_claude_path(app_handle, &installation.path)?;
+    info!(
+        "Switched active Claude CLI to {} at {}",
+        version, installation.path
+    );
+    Ok(installation)
+}
+
+/// Reads the Claude CLI version pinned by a project, if any
+pub fn read_version_pin(project_path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(project_path.join(VERSION_PIN_FILE)).ok()?;
+    let version = content.trim().trim_start_matches('v').to_string();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+/// Pins a project to a Claude CLI version, or removes the pin with `None`
+pub fn write_version_pin(project_path: &Path, version: Option<&str>) -> Result<(), String> {
+    let pin_file = project_path.join(VERSION_PIN_FILE);
+    match version {
+        Some(version) => {
+            if !is_exact_version(version) {
+                return Err(format!("Invalid Claude CLI version: {}", version));
+            }
+            std::fs::write(&pin_file, format!("{}\n", version))
+                .map_err(|e| format!("Failed to write {}: {}", VERSION_PIN_FILE, e))
+        }
+        None if pin_file.exists() => std::fs::remove_file(&pin_file)
+            .map_err(|e| format!("Failed to remove {}: {}", VERSION_PIN_FILE, e)),
+        None => Ok(()),
+    }
+}
+
+/// Find the Claude binary to run in a project
+///
+/// A version pinned through `.claude-version` has to be installed, otherwise
+/// the project would silently run with a different CLI; without a pin the
+/// regular lookup of `find_claude_binary` applies.
+pub fn find_claude_binary_for_project(
+    app_handle: &tauri::AppHandle,
+    project_path: &str,
+) -> Result<String, String> {
+    let Some(version) = read_version_pin(Path::new(project_path)) else {
+        return find_claude_binary(app_handle);
+    };
+
+    match find_installation_with_version(app_handle, &version) {
+        Some(installation) => {
+            info!(
+                "Using Claude CLI {} pinned by {} at {}",
+                version, VERSION_PIN_FILE, installation.path
+            );
+            Ok(installation.path)
+        }
+        None => Err(format!(
+            "This project pins Claude CLI {} in {}, but that version is not installed. Install it from the Claude version manager.",
+            version, VERSION_PIN_FILE
+        )),
+    }
+}
+
+/// Directory of the self-contained Claude runtime, a node distribution in
+/// `node/` next to an npm prefix with the Claude CLI
+///
+/// Release builds can ship one as the `claude-runtime` resource; otherwise it
+/// is downloaded into the app data directory on request.
+const RUNTIME_DIR_NAME: &str = "claude-runtime";
+
+/// Node.js release bundled into downloaded runtimes
+const RUNTIME_NODE_VERSION: &str = "20.18.0";
+
+/// Path of the Claude binary inside a runtime directory
+fn runtime_binary_path(runtime_dir: &Path) -> PathBuf {
+    managed_binary_path(runtime_dir)
+}
+
+/// Directory holding the node executable of the runtime containing `program`
+fn runtime_node_bin_dir(program: &Path) -> Option<PathBuf> {
+    let runtime_dir = program
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == RUNTIME_DIR_NAME))?;
+    let node_dir = runtime_dir.join("node");
+    let bin_dir = if cfg!(target_os = "windows") {
+        node_dir
+    } else {
+        node_dir.join("bin")
+    };
+    bin_dir.is_dir().then_some(bin_dir)
+}
+
+/// Runtime downloaded into the app data directory
+fn downloaded_runtime_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    crate::portable::app_data_dir(app_handle)
+        .map(|dir| dir.join(RUNTIME_DIR_NAME))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// The self-contained Claude runtime, preferring the one shipped with the app
+pub fn find_runtime_installation(app_handle: &tauri::AppHandle) -> Option<ClaudeInstallation> {
+    let shipped = app_handle
+        .path()
+        .resource_dir()
+        .ok()
+        .map(|dir| (dir.join(RUNTIME_DIR_NAME), "runtime-bundled"));
+    let downloaded = downloaded_runtime_dir(app_handle)
+        .ok()
+        .map(|dir| (dir, "runtime-download"));
+
+    shipped
+        .into_iter()
+        .chain(downloaded)
+        .find_map(|(runtime_dir, source)| {
+            let binary = runtime_binary_path(&runtime_dir);
+            if !binary.is_file() || runtime_node_bin_dir(&binary).is_none() {
+                return None;
+            }
+            let path = binary.to_string_lossy().to_string();
+            Some(ClaudeInstallation {
+                version: get_claude_version(&path).ok().flatten(),
+                path,
+                source: source.to_string(),
+                installation_type: InstallationType::Bundled,
+            })
+        })
+}
+
+/// Node.js distribution archive for this platform, e.g. "node-v20.18.0-win-x64.zip"
+fn node_archive_name() -> Result<String, String> {
+    let platform = match std::env::consts::OS {
+        "windows" => "win",
+        "macos" => "darwin",
+        "linux" => "linux",
+        other => return Err(format!("No Node.js runtime available for {}", other)),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => return Err(format!("No Node.js runtime available for {}", other)),
+    };
+    let extension = if platform == "win" { "zip" } else { "tar.gz" };
+    Ok(format!(
+        "node-v{}-{}-{}.{}",
+        RUNTIME_NODE_VERSION, platform, arch, extension
+    ))
+}
+
+/// A downloaded runtime together with the verification of its node archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeInstall {
+    pub installation: ClaudeInstallation,
+    pub verification: crate::integrity::ArtifactVerification,
+}
+
+/// Downloads a node runtime and the Claude CLI into the app data directory,
+/// so the app works on machines without Node.js
+///
+/// `version` pins the Claude CLI version, which is the latest otherwise. The
+/// node archive must match the checksum nodejs.org publishes. The runtime is
+/// assembled in a staging directory and only replaces an existing one once
+/// the Claude CLI runs.
+pub async fn install_claude_runtime(
+    app_handle: &tauri::AppHandle,
+    version: Option<&str>,
+) -> Result<RuntimeInstall, String> {
+    let package = match version {
+        Some(version) if is_exact_version(version) => {
+            format!("{}@{}", CLAUDE_NPM_PACKAGE, version)
+        }
+        Some(version) => return Err(format!("Invalid Claude CLI version: {}", version)),
+        None => CLAUDE_NPM_PACKAGE.to_string(),
+    };
+    let runtime_dir = downloaded_runtime_dir(app_handle)?;
+    let staging_dir = runtime_dir.with_file_name(format!("{}.partial", RUNTIME_DIR_NAME));
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir)
+            .map_err(|e| format!("Failed to clear staging directory: {}", e))?;
+    }
+    std::fs::create_dir_all(&staging_dir)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    let archive_name = node_archive_name()?;
+    let dist_url = format!("https://nodejs.org/dist/v{}", RUNTIME_NODE_VERSION);
+    info!(
+        "Downloading Node.js runtime {} from {}",
+        archive_name, dist_url
+    );
+    let archive = download_bytes(&format!("{}/{}", dist_url, archive_name)).await?;
+
+    // Check the archive against the published, signed checksum list before
+    // anything from it runs
+    let checksums = download_bytes(&format!("{}/SHASUMS256.txt", dist_url)).await?;
+    let checksums_path = staging_dir.join("SHASUMS256.txt");
+    std::fs::write(&checksums_path, &checksums)
+        .map_err(|e| format!("Failed to save Node.js checksums: {}", e))?;
+    let checksum_list = String::from_utf8_lossy(&checksums);
+    let expected = crate::integrity::checksum_from_list(&checksum_list, &archive_name)
+        .ok_or_else(|| format!("No published checksum for {}", archive_name))?;
+    let mut verification =
+        crate::integrity::ArtifactVerification::new(&archive_name, &expected, &archive);
+    match download_bytes(&format!("{}/SHASUMS256.txt.sig", dist_url)).await {
+        Ok(signature) => {
+            let signature_path = staging_dir.join("SHASUMS256.txt.sig");
+            if std::fs::write(&signature_path, &signature).is_ok() {
+                let (status, detail) =
+                    crate::integrity::verify_gpg_signature(&checksums_path, &signature_path);
+                verification.signature = status;
+                verification.signature_detail = detail;
+            }
+        }
+        Err(e) => verification.signature_detail = Some(e),
+    }
+    if let Err(e) = verification.ensure_verified() {
+        let _ = std::fs::remove_dir_all(&staging_dir);
+        return Err(e);
+    }
+
+    let archive_path = staging_dir.join(&archive_name);
+    std::fs::write(&archive_path, &archive)
+        .map_err(|e| format!("Failed to save Node.js runtime: {}", e))?;
+
+    let result = tokio::task::spawn_blocking(move || {
+        assemble_runtime(
+            &staging_dir,
+            &archive_path,
+            &archive_name,
+            &package,
+            &runtime_dir,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to install Claude runtime: {}", e))?;
+    let installation = result?;
+    info!("Installed Claude runtime at {}", installation.path);
+    Ok(RuntimeInstall {
+        installation,
+        verification,
+    })
+}
+
+/// Download `url` completely into memory
+async fn download_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    Ok(bytes.to_vec())
+}
+
+/// Unpack node, install `package` (the Claude CLI, possibly `@version`) with
+/// its npm and move the staging directory into place
+fn assemble_runtime(
+    staging_dir: &Path,
+    archive_path: &Path,
+    archive_name: &str,
+    package: &str,
+    runtime_dir: &Path,
+) -> Result<ClaudeInstallation, String> {
+    let fail = |message: String| {
+        let _ = std::fs::remove_dir_all(staging_dir);
+        Err(message)
+    };
+
+    // tar ships with Windows 10 and later and unpacks zip archives too
+    #[allow(unused_mut)]
+    let mut tar = Command::new("tar");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        tar.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    match tar
+        .arg("-xf")
+        .arg(archive_path)
+        .arg("-C")
+        .arg(staging_dir)
+        .output()
+    {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            return fail(format!(
+                "Failed to unpack Node.js runtime: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+        Err(e) => return fail(format!("Failed to run tar: {}", e)),
+    }
+    let _ = std::fs::remove_file(archive_path);
+
+    let unpacked = staging_dir.join(
+        archive_name
+            .trim_end_matches(".zip")
+            .trim_end_matches(".tar.gz"),
+    );
+    if let Err(e) = std::fs::rename(&unpacked, staging_dir.join("node")) {
+        return fail(format!("Failed to move Node.js runtime: {}", e));
+    }
+
+    let npm = if cfg!(target_os = "windows") {
+        staging_dir.join("node").join("npm.cmd")
+    } else {
+        staging_dir.join("node").join("bin").join("npm")
+    };
+    // The staging directory isn't recognised as a runtime yet, so put its
+    // node on PATH for the npm shim here
+    let node_bin_dir = npm.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut paths = vec![node_bin_dir];
+    paths.extend(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+    ));
+    let mut cmd = create_command_with_env(&npm.to_string_lossy());
+    if let Ok(new_path) = std::env::join_paths(paths) {
+        cmd.env("PATH", new_path);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = match cmd
+        .arg("install")
+        .arg("--prefix")
+        .arg(staging_dir)
+        .arg("--no-save")
+        .arg(package)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => return fail(format!("Failed to run npm: {}", e)),
+    };
+    if !output.status.success() || !runtime_binary_path(staging_dir).is_file() {
+        return fail(format!(
+            "Failed to install Claude CLI into the runtime: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    if runtime_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(runtime_dir) {
+            return fail(format!("Failed to replace existing runtime: {}", e));
+        }
+    }
+    if let Err(e) = std::fs::rename(staging_dir, runtime_dir) {
+        return fail(format!("Failed to move Claude runtime into place: {}", e));
+    }
+
+    let path = runtime_binary_path(runtime_dir)
+        .to_string_lossy()
+        .to_string();
+    Ok(ClaudeInstallation {
+        version: get_claude_version(&path).ok().flatten(),
+        path,
+        source: "runtime-download".to_string(),
+        installation_type: InstallationType::Bundled,
+    })
+}
+
+/// Removes the downloaded Claude runtime
+pub fn remove_claude_runtime(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let runtime_dir = downloaded_runtime_dir(app_handle)?;
+    if !runtime_dir.exists() {
+        return Err("No downloaded Claude runtime to remove".to_string());
+    }
+    std::fs::remove_dir_all(&runtime_dir)
+        .map_err(|e| format!("Failed to remove Claude runtime: {}", e))?;
+    info!("Removed Claude runtime at {:?}", runtime_dir);
+    Ok(())
+}
+
+/// Prefix of binary paths that refer to Claude inside a WSL distribution,
+/// e.g. "wsl://Ubuntu"
+pub const WSL_PATH_PREFIX: &str = "wsl://";
+
+/// The distribution name of a "wsl://<distro>" binary path
+pub fn parse_wsl_path(claude_path: &str) -> Option<&str> {
+    claude_path
+        .strip_prefix(WSL_PATH_PREFIX)
+        .filter(|distro| !distro.is_empty())
+}
+
+/// Translate a Windows path into the path seen from inside WSL
+///
+/// Drive paths map to the /mnt mounts, `\\wsl$\<distro>` and
+/// `\\wsl.localhost\<distro>` shares to the distribution's own root.
+pub fn windows_to_wsl_path(path: &str) -> String {
+    let normalized = path.replace('\\', "/");
+
+    for share in ["//wsl$/", "//wsl.localhost/"] {
+        if let Some(rest) = normalized.strip_prefix(share) {
+            // Skip the distribution name
+            return match rest.find('/') {
+                Some(index) => rest[index..].to_string(),
+                None => "/".to_string(),
+            };
+        }
+    }
+
+    let mut chars = normalized.chars();
+    if let (Some(drive), Some(':')) = (chars.next(), chars.next()) {
+        if drive.is_ascii_alphabetic() {
+            let rest = chars.as_str().trim_start_matches('/');
+            return format!("/mnt/{}/{}", drive.to_ascii_lowercase(), rest)
+                .trim_end_matches('/')
+                .to_string();
+        }
+    }
+
+    normalized
+}
+
+/// Program and arguments running `claude <args>` inside a WSL distribution
+///
+/// Claude runs through an interactive login shell so PATH setup from the
+/// user's profile and .bashrc (nvm, fnm, ...) applies; the arguments are
+/// handed over as positional parameters and never re-parsed by the shell.
+/// CLAUDE_CONFIG_DIR points at the Windows ~/.claude so sessions and
+/// settings stay where the app reads them.
+pub fn wsl_launch_args(
+    distro: &str,
+    args: &[String],
+    project_path: Option<&str>,
+) -> (String, Vec<String>) {
+    let mut wsl_args = vec!["-d".to_string(), distro.to_string()];
+    if let Some(project_path) = project_path {
+        wsl_args.push("--cd".to_string());
+        wsl_args.push(windows_to_wsl_path(project_path));
+    }
+
+    let config_dir = dirs::home_dir()
+        .map(|home| windows_to_wsl_path(&home.join(".claude").to_string_lossy()))
+        .unwrap_or_default();
+    wsl_args.extend(
+        [
+            "--",
+            "bash",
+            "-lic",
+            r#"[ -n "$1" ] && export CLAUDE_CONFIG_DIR="$1"; shift; exec claude "$@""#,
+            "claude",
+        ]
+        .iter()
+        .map(|arg| arg.to_string()),
+    );
+    wsl_args.push(config_dir);
+    wsl_args.extend(args.iter().cloned());
+    ("wsl.exe".to_string(), wsl_args)
+}
+
+/// Command running a Claude binary path with `args`, WSL paths included
+fn cli_command(path: &str, args: &[&str]) -> Command {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    match parse_wsl_path(path) {
+        Some(distro) => {
+            let (program, wsl_args) = wsl_launch_args(distro, &args, None);
+            let mut cmd = Command::new(program);
+            cmd.args(wsl_args);
+            cmd
+        }
+        None => {
+            let mut cmd = create_command_with_env(path);
+            cmd.args(args);
+            cmd
+        }
+    }
+}
+
+/// Command printing the version of a Claude binary path, WSL paths included
+pub fn version_command(path: &str) -> Command {
+    cli_command(path, &["--version"])
+}
+
+/// Names of the installed WSL distributions
+fn list_wsl_distros() -> Vec<String> {
+    if !cfg!(target_os = "windows") {
+        return Vec::new();
+    }
+
+    let mut cmd = Command::new("wsl.exe");
+    cmd.args(["--list", "--quiet"]);
+
+    // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = match cmd.output() {
+        Ok(output) if output.status.success() => output.stdout,
+        _ => return Vec::new(),
+    };
+
+    // wsl.exe writes UTF-16LE
+    let units: Vec<u16> = output
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}'))
+        .filter(|line| !line.is_empty())
+        // Docker Desktop's internal distributions have no user environment
+        .filter(|line| !line.starts_with("docker-desktop"))
+        .map(String::from)
+        .collect()
+}
+
+/// Find Claude installed inside WSL distributions (Windows-only)
+fn find_wsl_installations() -> Vec<ClaudeInstallation> {
+    list_wsl_distros()
+        .into_iter()
+        .filter_map(|distro| {
+            let path = format!("{}{}", WSL_PATH_PREFIX, distro);
+            debug!("Checking for Claude in WSL distribution: {}", distro);
+            // Only distributions where claude actually runs are reported
+            let version = get_claude_version(&path).ok().flatten()?;
+            Some(ClaudeInstallation {
+                path,
+                version: Some(version),
+                source: format!("wsl ({})", distro),
+                installation_type: InstallationType::Wsl,
+            })
+        })
+        .collect()
+}
+
+/// How an installed Claude CLI is updated
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateMethod {
+    /// Global npm package, updated with `npm install -g`
+    Npm,
+    /// Native installer build, updated with `claude update`
+    Native,
+    /// Version manager install, updated by installing the latest version
+    Managed,
+    /// Installation inside WSL, updated with `claude update` in the distribution
+    Wsl,
+}
+
+/// Pick the updater matching where a Claude binary was installed
+pub fn update_method(app_handle: &tauri::AppHandle, path: &str) -> UpdateMethod {
+    if parse_wsl_path(path).is_some() {
+        return UpdateMethod::Wsl;
+    }
+    if let Ok(versions_dir) = managed_versions_dir(app_handle) {
+        if Path::new(path).starts_with(&versions_dir) {
+            return UpdateMethod::Managed;
+        }
+    }
+
+    let normalized = path.replace('\\', "/");
+    if normalized.contains("/.claude/local/") || normalized.contains("/.local/bin/claude") {
+        UpdateMethod::Native
+    } else {
+        UpdateMethod::Npm
+    }
+}
+
+/// Command updating the Claude binary at `path` in place
+///
+/// Managed versions are never updated in place; install a newer version
+/// through the version manager instead.
+pub fn update_command(path: &str, method: UpdateMethod) -> Result<Command, String> {
+    #[allow(unused_mut)]
+    let mut cmd = match method {
+        UpdateMethod::Npm => {
+            let mut cmd = npm_command();
+            cmd.args(["install", "-g", &format!("{}@latest", CLAUDE_NPM_PACKAGE)]);
+            cmd
+        }
+        UpdateMethod::Native => {
+            let mut cmd = create_command_with_env(path);
+            cmd.arg("update");
+            cmd
+        }
+        UpdateMethod::Wsl => {
+            let distro = parse_wsl_path(path).unwrap_or_default();
+            let (program, args) = wsl_launch_args(distro, &["update".to_string()], None);
+            let mut cmd = create_command_with_env(&program);
+            cmd.args(args);
+            cmd
+        }
+        UpdateMethod::Managed => {
+            return Err("Managed versions are updated by installing a new version".to_string())
+        }
+    };
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    Ok(cmd)
+}
+
+/// Features of a Claude CLI build the app relies on
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClaudeCapabilities {
+    pub version: Option<String>,
+    /// `--output-format stream-json`, needed to follow sessions
+    pub output_format: bool,
+    pub resume: bool,
+    pub continue_session: bool,
+    pub session_id: bool,
+    pub skip_permissions: bool,
+    /// `--settings`, used to pass per-run environment overrides
+    pub settings: bool,
+    /// The `claude mcp` subcommand
+    pub mcp: bool,
+    /// Whether the features were read from `--help` rather than inferred
+    /// from the version
+    pub probed: bool,
+}
+
+/// First CLI versions assumed to have a feature when `--help` can't be read
+const OUTPUT_FORMAT_MIN_VERSION: &str = "0.2.0";
+const RESUME_MIN_VERSION: &str = "0.2.0";
+const SKIP_PERMISSIONS_MIN_VERSION: &str = "0.2.0";
+const MCP_MIN_VERSION: &str = "0.2.0";
+const SESSION_ID_MIN_VERSION: &str = "1.0.0";
+const SETTINGS_MIN_VERSION: &str = "1.0.0";
+
+/// Capabilities probed per binary path during this run of the app
+static CAPABILITY_CACHE: std::sync::OnceLock<
+    std::sync::Mutex<std::collections::HashMap<String, ClaudeCapabilities>>,
+> = std::sync::OnceLock::new();
+
+impl ClaudeCapabilities {
+    /// Read the supported flags from `claude --help`
+    fn from_help(version: Option<String>, help: &str) -> Self {
+        let has_flag = |flag: &str| {
+            help.split(|c: char| c.is_whitespace() || c == ',')
+                .any(|word| word == flag)
+        };
+        let has_command = |command: &str| {
+            help.lines()
+                .any(|line| line.trim_start().split_whitespace().next() == Some(command))
+        };
+
+        Self {
+            version,
+            output_format: has_flag("--output-format"),
+            resume: has_flag("--resume"),
+            continue_session: has_flag("--continue"),
+            session_id: has_flag("--session-id"),
+            skip_permissions: has_flag("--dangerously-skip-permissions"),
+            settings: has_flag("--settings"),
+            mcp: has_command("mcp"),
+            probed: true,
+        }
+    }
+
+    /// Infer the supported flags from the version alone; an unknown version
+    /// is assumed to be current
+    fn from_version(version: Option<String>) -> Self {
+        let at_least = |min: &str| {
+            version
+                .as_deref()
+                .map(|v| compare_versions(v, min) != Ordering::Less)
+                .unwrap_or(true)
+        };
+
+        Self {
+            output_format: at_least(OUTPUT_FORMAT_MIN_VERSION),
+            resume: at_least(RESUME_MIN_VERSION),
+            continue_session: at_least(RESUME_MIN_VERSION),
+            session_id: at_least(SESSION_ID_MIN_VERSION),
+            skip_permissions: at_least(SKIP_PERMISSIONS_MIN_VERSION),
+            settings: at_least(SETTINGS_MIN_VERSION),
+            mcp: at_least(MCP_MIN_VERSION),
+            probed: false,
+            version,
+        }
+    }
+
+    /// Adapt CLI arguments to this build
+    ///
+    /// Optional flags the build lacks are dropped with a warning; flags a
+    /// session can't work without fail with an error asking for an update.
+    pub fn adapt_args(&self, args: Vec<String>) -> Result<Vec<String>, String> {
+        let version = self.version.as_deref().unwrap_or("unknown");
+        let mut adapted = Vec::with_capacity(args.len());
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (supported, required, takes_value) = match arg.as_str() {
+                "--output-format" => (self.output_format, true, true),
+                "--resume" | "-r" => (self.resume, true, true),
+                "--continue" | "-c" => (self.continue_session, true, false),
+                "--session-id" => (self.session_id, false, true),
+                "--settings" => (self.settings, false, true),
+                "--dangerously-skip-permissions" => (self.skip_permissions, false, false),
+                _ => (true, false, false),
+            };
+
+            if supported {
+                adapted.push(arg);
+                continue;
+            }
+            if required {
+                return Err(format!(
+                    "Claude CLI {} does not support {}. Please update the Claude CLI.",
+                    version, arg
+                ));
+            }
+            warn!(
+                "Claude CLI {} does not support {}, leaving it out",
+                version, arg
+            );
+            if takes_value {
+                args.next();
+            }
+        }
+
+        Ok(adapted)
+    }
+}
+
+/// `detect_capabilities` on a blocking thread, for launches from async code
+///
+/// A probe that fails to run is treated like an unknown version.
+pub async fn probe_capabilities(path: &str) -> ClaudeCapabilities {
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || detect_capabilities(&path))
+        .await
+        .unwrap_or_else(|_| ClaudeCapabilities::from_version(None))
+}
+
+/// Detect the features of the Claude binary at `path`, cached per path
+pub fn detect_capabilities(path: &str) -> ClaudeCapabilities {
+    let cache = CAPABILITY_CACHE.get_or_init(Default::default);
+    if let Some(capabilities) = cache.lock().ok().and_then(|c| c.get(path).cloned()) {
+        return capabilities;
+    }
+
+    let version = get_claude_version(path).ok().flatten();
+    let mut cmd = cli_command(path, &["--help"]);
+
+    // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let capabilities = match cmd.output() {
+        Ok(output) if output.status.success() && !output.stdout.is_empty() => {
+            ClaudeCapabilities::from_help(version, &String::from_utf8_lossy(&output.stdout))
+        }
+        _ => {
+            debug!("Could not read --help of {}, inferring capabilities", path);
+            ClaudeCapabilities::from_version(version)
+        }
+    };
+    info!("Claude CLI capabilities of {}: {:?}", path, capabilities);
+
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(path.to_string(), capabilities.clone());
+    }
+    capabilities
+}
+
+/// Forget probed capabilities, e.g. after the CLI was updated
+pub fn clear_capability_cache() {
+    if let Some(cache) = CAPABILITY_CACHE.get() {
+        if let Ok(mut cache) = cache.lock() {
+            cache.clear();
+        }
+    }
+}
+
+/// Open agents.db with the table caching discovered installations
+fn discovery_cache_connection(
+    app_handle: &tauri::AppHandle,
+) -> Result<rusqlite::Connection, String> {
+    let app_data_dir = crate::portable::app_data_dir(app_handle)
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    std::fs::create_dir_all(&app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    let conn = rusqlite::Connection::open(app_data_dir.join("agents.db"))
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    init_discovery_cache(&conn)?;
+    Ok(conn)
+}
+
+fn init_discovery_cache(conn: &rusqlite::Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS claude_binary_cache (
+            path TEXT PRIMARY KEY,
+            version TEXT,
+            source TEXT NOT NULL,
+            installation_type TEXT NOT NULL,
+            capabilities TEXT,
+            position INTEGER NOT NULL,
+            discovered_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create binary cache table: {}", e))?;
+    Ok(())
+}
+
+/// Installations and capabilities stored by the last discovery
+fn load_discovery_cache(
+    conn: &rusqlite::Connection,
+) -> rusqlite::Result<Vec<(ClaudeInstallation, Option<ClaudeCapabilities>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, version, source, installation_type, capabilities
+         FROM claude_binary_cache ORDER BY position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let installation_type: String = row.get(3)?;
+        let capabilities: Option<String> = row.get(4)?;
+        Ok((
+            ClaudeInstallation {
+                path: row.get(0)?,
+                version: row.get(1)?,
+                source: row.get(2)?,
+                installation_type: serde_json::from_str(&installation_type)
+                    .unwrap_or(InstallationType::System),
+            },
+            capabilities.and_then(|c| serde_json::from_str(&c).ok()),
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Whether a cached installation can still be launched from its path
+fn cached_installation_exists(installation: &ClaudeInstallation) -> bool {
+    installation.installation_type == InstallationType::Wsl
+        || installation.path == "claude"
+        || Path::new(&installation.path).is_file()
+}
+
+/// Installations found by the last discovery, scanning only when nothing is
+/// cached yet or a cached binary has disappeared
+///
+/// Cached capabilities are loaded so launches don't have to probe again.
+pub fn cached_claude_installations(app_handle: &tauri::AppHandle) -> Vec<ClaudeInstallation> {
+    let cached = match discovery_cache_connection(app_handle)
+        .and_then(|conn| load_discovery_cache(&conn).map_err(|e| e.to_string()))
+    {
+        Ok(cached) => cached,
+        Err(e) => {
+            warn!("Failed to read Claude discovery cache: {}", e);
+            return discover_claude_installations();
+        }
+    };
+
+    if cached.is_empty()
+        || !cached
+            .iter()
+            .all(|(installation, _)| cached_installation_exists(installation))
+    {
+        return refresh_claude_installations(app_handle);
+    }
+
+    debug!("Using {} cached Claude installations", cached.len());
+    let cache = CAPABILITY_CACHE.get_or_init(Default::default);
+    let mut installations = Vec::with_capacity(cached.len());
+    for (installation, capabilities) in cached {
+        if let (Some(capabilities), Ok(mut cache)) = (capabilities, cache.lock()) {
+            cache
+                .entry(installation.path.clone())
+                .or_insert(capabilities);
+        }
+        installations.push(installation);
+    }
+    installations
+}
+
+/// Scan for installations again, probe their capabilities and replace the
+/// cached results
+pub fn refresh_claude_installations(app_handle: &tauri::AppHandle) -> Vec<ClaudeInstallation> {
+    clear_capability_cache();
+    let installations = discover_claude_installations();
+    let entries: Vec<(ClaudeInstallation, ClaudeCapabilities)> = installations
+        .into_iter()
+        .map(|installation| {
+            let capabilities = detect_capabilities(&installation.path);
+            (installation, capabilities)
+        })
+        .collect();
+
+    if let Err(e) = discovery_cache_connection(app_handle)
+        .and_then(|mut conn| store_discovery_cache(&mut conn, &entries))
+    {
+        warn!("Failed to cache Claude discovery results: {}", e);
+    }
+    info!("Cached {} discovered Claude installations", entries.len());
+    entries
+        .into_iter()
+        .map(|(installation, _)| installation)
+        .collect()
+}
+
+fn store_discovery_cache(
+    conn: &mut rusqlite::Connection,
+    entries: &[(ClaudeInstallation, ClaudeCapabilities)],
+) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute("DELETE FROM claude_binary_cache", [])
+        .map_err(|e| format!("Failed to clear binary cache: {}", e))?;
+    for (position, (installation, capabilities)) in entries.iter().enumerate() {
+        let installation_type = serde_json::to_string(&installation.installation_type)
+            .map_err(|e| format!("Failed to serialize installation type: {}", e))?;
+        let capabilities = serde_json::to_string(capabilities)
+            .map_err(|e| format!("Failed to serialize capabilities: {}", e))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO claude_binary_cache
+                (path, version, source, installation_type, capabilities, position)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                installation.path,
+                installation.version,
+                installation.source,
+                installation_type,
+                capabilities,
+                position as i64
+            ],
+        )
+        .map_err(|e| format!("Failed to cache installation: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit binary cache: {}", e))
+}
+
+/// Drop cached discovery results and capabilities, e.g. after the CLI was
+/// updated; the next lookup scans again
+pub fn invalidate_discovery_cache(app_handle: &tauri::AppHandle) {
+    clear_capability_cache();
+    if let Err(e) = discovery_cache_connection(app_handle).and_then(|conn| {
+        conn.execute("DELETE FROM claude_binary_cache", [])
+            .map_err(|e| e.to_string())
+    }) {
+        warn!("Failed to clear Claude discovery cache: {}", e);
+    }
+}
+
 /// Helper function to create a Command with proper Windows environment variables
 pub fn create_command_with_env(program: &str) -> Command {
     let mut cmd = Command::new(program);
@@ -739,6 +2029,181 @@ pub fn create_command_with_env(program: &str) -> Command {
         }
     }
 
+    // A self-contained runtime needs its own node on PATH
+    if let Some(node_bin_dir) = runtime_node_bin_dir(Path::new(program)) {
+        let mut paths = vec![node_bin_dir];
+        paths.extend(std::env::split_paths(
+            &std::env::var_os("PATH").unwrap_or_default(),
+        ));
+        if let Ok(new_path) = std::env::join_paths(paths) {
+            debug!("Adding Claude runtime node directory to PATH");
+            cmd.env("PATH", new_path);
+        }
+    }
+
     cmd
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_to_wsl_path() {
+        assert_eq!(
+            windows_to_wsl_path(r"C:\Users\dev\project"),
+            "/mnt/c/Users/dev/project"
+        );
+        assert_eq!(windows_to_wsl_path(r"D:\"), "/mnt/d");
+        assert_eq!(
+            windows_to_wsl_path(r"\\wsl$\Ubuntu\home\dev\project"),
+            "/home/dev/project"
+        );
+        assert_eq!(
+            windows_to_wsl_path(r"\\wsl.localhost\Debian\srv"),
+            "/srv"
+        );
+        assert_eq!(parse_wsl_path("wsl://Ubuntu"), Some("Ubuntu"));
+        assert_eq!(parse_wsl_path(r"C:\claude.cmd"), None);
+    }
+
+    #[test]
+    fn test_capabilities_adapt_args() {
+        let help = "Usage: claude [options] [command] [prompt]\n\n\
+            Options:\n  -p, --print  Print response and exit\n  \
+            --output-format <format>  Output format\n  \
+            -r, --resume [sessionId]  Resume a conversation\n\n\
+            Commands:\n  mcp  Configure MCP servers\n";
+        let capabilities = ClaudeCapabilities::from_help(Some("0.2.9".to_string()), help);
+        assert!(capabilities.output_format && capabilities.resume && capabilities.mcp);
+        assert!(!capabilities.settings && !capabilities.skip_permissions);
+
+        let args = [
+            "-p",
+            "hi",
+            "--settings",
+            "{}",
+            "--dangerously-skip-permissions",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        assert_eq!(capabilities.adapt_args(args).unwrap(), vec!["-p", "hi"]);
+        assert!(capabilities.adapt_args(vec!["-c".to_string()]).is_err());
+        let resume = vec!["-r".to_string(), "abc".to_string()];
+        assert_eq!(capabilities.adapt_args(resume.clone()).unwrap(), resume);
+        let no_resume = ClaudeCapabilities {
+            resume: false,
+            ..capabilities.clone()
+        };
+        assert!(no_resume.adapt_args(resume).is_err());
+
+        let old = ClaudeCapabilities::from_version(Some("0.9.1".to_string()));
+        assert!(old.output_format && !old.session_id);
+    }
+
+    #[test]
+    fn test_runtime_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime_dir = dir.path().join(RUNTIME_DIR_NAME);
+        let binary = runtime_binary_path(&runtime_dir);
+        assert!(binary.starts_with(runtime_dir.join("node_modules")));
+        // No node next to the CLI yet
+        assert_eq!(runtime_node_bin_dir(&binary), None);
+
+        let node_bin_dir = if cfg!(target_os = "windows") {
+            runtime_dir.join("node")
+        } else {
+            runtime_dir.join("node").join("bin")
+        };
+        std::fs::create_dir_all(&node_bin_dir).unwrap();
+        assert_eq!(runtime_node_bin_dir(&binary), Some(node_bin_dir));
+        // Binaries outside a runtime directory never get its node
+        assert_eq!(
+            runtime_node_bin_dir(&dir.path().join("bin").join("claude")),
+            None
+        );
+
+        if let Ok(archive_name) = node_archive_name() {
+            let prefix = format!("node-v{}-", RUNTIME_NODE_VERSION);
+            assert!(archive_name.starts_with(&prefix));
+            assert!(archive_name.ends_with(".zip") || archive_name.ends_with(".tar.gz"));
+        }
+    }
+
+    #[test]
+    fn test_discovery_cache_round_trip() {
+        let installation = |path: &str, installation_type| ClaudeInstallation {
+            path: path.to_string(),
+            version: Some("1.0.3".to_string()),
+            source: "test".to_string(),
+            installation_type,
+        };
+        let capabilities = ClaudeCapabilities::from_version(Some("1.0.3".to_string()));
+
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        init_discovery_cache(&conn).unwrap();
+        assert!(load_discovery_cache(&conn).unwrap().is_empty());
+
+        let entries = vec![
+            (
+                installation("/usr/bin/claude", InstallationType::System),
+                capabilities.clone(),
+            ),
+            (
+                installation("wsl://Ubuntu", InstallationType::Wsl),
+                capabilities.clone(),
+            ),
+        ];
+        store_discovery_cache(&mut conn, &entries).unwrap();
+        let cached = load_discovery_cache(&conn).unwrap();
+        let paths: Vec<&str> = cached.iter().map(|(i, _)| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["/usr/bin/claude", "wsl://Ubuntu"]);
+        assert_eq!(cached[1].0.installation_type, InstallationType::Wsl);
+        assert_eq!(cached[0].1.as_ref(), Some(&capabilities));
+
+        // A new discovery replaces the previous results
+        let entries = vec![(
+            installation("claude", InstallationType::System),
+            capabilities,
+        )];
+        store_discovery_cache(&mut conn, &entries).unwrap();
+        assert_eq!(load_discovery_cache(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cached_installation_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude");
+        std::fs::write(&binary, "").unwrap();
+        let installation = |path: &Path, installation_type| ClaudeInstallation {
+            path: path.to_string_lossy().into_owned(),
+            version: None,
+            source: "test".to_string(),
+            installation_type,
+        };
+
+        assert!(cached_installation_exists(&installation(
+            &binary,
+            InstallationType::System
+        )));
+        assert!(!cached_installation_exists(&installation(
+            &dir.path().join("missing"),
+            InstallationType::System
+        )));
+        // Directories aren't launchable binaries
+        assert!(!cached_installation_exists(&installation(
+            dir.path(),
+            InstallationType::Custom
+        )));
+        // WSL paths and the bare command on PATH can't be checked on disk
+        assert!(cached_installation_exists(&installation(
+            Path::new("wsl://Ubuntu"),
+            InstallationType::Wsl
+        )));
+        assert!(cached_installation_exists(&installation(
+            Path::new("claude"),
+            InstallationType::System
+        )));
+    }
+}
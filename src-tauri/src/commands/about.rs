@@ -1,5 +1,8 @@
-use serde::Serialize;
-use tauri::command;
+use crate::commands::agents::AgentDb;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Emitter, State};
+use tauri_plugin_updater::UpdaterExt;
 use std::env;
 
 #[derive(Debug, Serialize)]
@@ -8,6 +11,8 @@ pub struct AppInfo {
     pub database_path: String,
     pub latest_version: Option<String>,
     pub update_available: bool,
+    /// Data is kept next to the executable (portable.flag or --portable)
+    pub portable: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,7 +21,11 @@ pub struct UpdateInfo {
     pub current_version: String,
     pub update_available: bool,
     pub download_url: Option<String>,
+    /// SHA-256 GitHub reports for the download, to verify it before running
+    pub sha256: Option<String>,
     pub release_notes: Option<String>,
+    pub channel: UpdateChannel,
+    pub prerelease: bool,
 }
 
 /// Get application version from Cargo.toml
@@ -29,13 +38,11 @@ pub async fn get_app_version() -> Result<String, String> {
 /// Get database location
 #[command]
 pub async fn get_database_path() -> Result<String, String> {
-    // Get the app data directory
-    let app_data_dir = dirs::data_dir()
+    // Get the app data directory (next to the executable in portable mode)
+    let app_data_dir = crate::portable::data_dir()
         .ok_or("Failed to get app data directory")?;
     
-    let db_path = app_data_dir
-        .join("claude.workbench.app")
-        .join("agents.db");
+    let db_path = app_data_dir.join("agents.db");
     
     Ok(db_path.to_string_lossy().to_string())
 }
@@ -51,42 +58,224 @@ pub async fn get_app_info() -> Result<AppInfo, String> {
         database_path,
         latest_version: None,
         update_available: false,
+        portable: crate::portable::is_portable(),
     })
 }
 
-/// Check for updates from GitHub releases
+/// GitHub API endpoint for releases
+const RELEASES_API_URL: &str = "https://api.github.com/repos/xinhai-ai/claude-suite/releases";
+
+/// app_settings key storing the update channel
+pub const UPDATE_CHANNEL_SETTING_KEY: &str = "update_channel";
+
+/// Which releases `check_for_updates` offers
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Published releases only
+    #[default]
+    Stable,
+    /// Pre-releases as well
+    Beta,
+}
+
+/// app_settings key storing the proxy URL used for GitHub requests
+pub const NETWORK_PROXY_SETTING_KEY: &str = "network_proxy";
+
+/// Timeout of GitHub API requests, so an unreachable network fails visibly
+const GITHUB_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+fn read_setting(db: &AgentDb, key: &str) -> Option<String> {
+    let conn = db.0.lock().ok()?;
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+}
+
+fn read_update_channel(db: &AgentDb) -> UpdateChannel {
+    read_setting(db, UPDATE_CHANNEL_SETTING_KEY)
+        .and_then(|value| serde_json::from_value(serde_json::Value::String(value)).ok())
+        .unwrap_or_default()
+}
+
+/// The in-app proxy, if one is configured
+fn read_network_proxy(db: &AgentDb) -> Option<String> {
+    read_setting(db, NETWORK_PROXY_SETTING_KEY).filter(|proxy| !proxy.trim().is_empty())
+}
+
+/// HTTP client for GitHub requests
+///
+/// Uses the in-app proxy when set; otherwise reqwest picks up the proxy from
+/// HTTP(S)_PROXY or, on Windows, the system settings.
+fn github_client(proxy: Option<&str>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent("Claude-Suite")
+        .timeout(GITHUB_REQUEST_TIMEOUT);
+    if let Some(proxy) = proxy {
+        let proxy = reqwest::Proxy::all(proxy).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Get the proxy used for update checks and GitHub requests
 #[command]
-pub async fn check_for_updates() -> Result<UpdateInfo, String> {
-    let current_version = get_app_version().await?;
-    
-    // GitHub API endpoint for releases
-    let url = "https://api.github.com/repos/xinhai-ai/claude-suite/releases/latest";
+pub async fn get_network_proxy(db: State<'_, AgentDb>) -> Result<Option<String>, String> {
+    Ok(read_network_proxy(&db))
+}
+
+/// Set the proxy used for update checks and GitHub requests; `None` falls
+/// back to the system proxy
+#[command]
+pub async fn set_network_proxy(
+    db: State<'_, AgentDb>,
+    proxy: Option<String>,
+) -> Result<(), String> {
+    let proxy = proxy
+        .map(|proxy| proxy.trim().to_string())
+        .filter(|proxy| !proxy.is_empty());
+    if let Some(proxy) = &proxy {
+        reqwest::Proxy::all(proxy.as_str()).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+    }
     
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .header("User-Agent", "Claude-Suite")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch release info: {}", e))?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match &proxy {
+        Some(proxy) => conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![NETWORK_PROXY_SETTING_KEY, proxy],
+        ),
+        None => conn.execute(
+            "DELETE FROM app_settings WHERE key = ?1",
+            params![NETWORK_PROXY_SETTING_KEY],
+        ),
+    }
+    .map_err(|e| format!("Failed to save proxy: {}", e))?;
+    if proxy.is_some() {
+        log::info!("Network proxy configured");
+    } else {
+        log::info!("Network proxy cleared");
+    }
+    Ok(())
+}
+
+/// Get the update channel
+#[command]
+pub async fn get_update_channel(db: State<'_, AgentDb>) -> Result<UpdateChannel, String> {
+    Ok(read_update_channel(&db))
+}
+
+/// Set and persist the update channel
+#[command]
+pub async fn set_update_channel(
+    db: State<'_, AgentDb>,
+    channel: UpdateChannel,
+) -> Result<(), String> {
+    let value = match channel {
+        UpdateChannel::Stable => "stable",
+        UpdateChannel::Beta => "beta",
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![UPDATE_CHANNEL_SETTING_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save update channel: {}", e))?;
+    log::info!("Update channel set to {}", value);
+    Ok(())
+}
+
+/// GET a GitHub API URL and parse the JSON response
+async fn fetch_github_json(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<serde_json::Value, String> {
+    let response = client.get(url).send().await.map_err(|e| {
+        if e.is_timeout() || e.is_connect() {
+            format!(
+                "Failed to reach GitHub ({}); check the network or proxy settings",
+                e
+            )
+        } else {
+            format!("Failed to fetch release info: {}", e)
+        }
+    })?;
     
     if !response.status().is_success() {
         return Err(format!("GitHub API returned status: {}", response.status()));
     }
     
-    let release_data: serde_json::Value = response
+    response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-    
-    let latest_version = release_data
+        .map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+/// Version of a GitHub release, without the leading "v" of its tag
+fn release_version(release: &serde_json::Value) -> &str {
+    release
         .get("tag_name")
         .and_then(|v| v.as_str())
         .unwrap_or("unknown")
         .trim_start_matches('v')
-        .to_string();
+}
+
+/// Newest release of the channel
+///
+/// GitHub's "latest" release never is a pre-release, so the beta channel
+/// picks the highest version among recent releases instead.
+async fn fetch_channel_release(
+    client: &reqwest::Client,
+    channel: UpdateChannel,
+) -> Result<serde_json::Value, String> {
+    match channel {
+        UpdateChannel::Stable => {
+            fetch_github_json(client, &format!("{}/latest", RELEASES_API_URL)).await
+        }
+        UpdateChannel::Beta => {
+            let url = format!("{}?per_page=20", RELEASES_API_URL);
+            let releases = fetch_github_json(client, &url).await?;
+            let mut newest: Option<&serde_json::Value> = None;
+            for release in releases.as_array().into_iter().flatten() {
+                let is_draft = release
+                    .get("draft")
+                    .and_then(|d| d.as_bool())
+                    .unwrap_or(false);
+                let is_newer = match newest {
+                    Some(newest) => {
+                        compare_versions(release_version(newest), release_version(release))
+                    }
+                    None => true,
+                };
+                if !is_draft && is_newer {
+                    newest = Some(release);
+                }
+            }
+            newest.cloned().ok_or_else(|| "No releases found".to_string())
+        }
+    }
+}
+
+/// Check for updates from GitHub releases on the configured channel
+#[command]
+pub async fn check_for_updates(db: State<'_, AgentDb>) -> Result<UpdateInfo, String> {
+    let current_version = get_app_version().await?;
+    let channel = read_update_channel(&db);
+    let client = github_client(read_network_proxy(&db).as_deref())?;
+    
+    let release_data = fetch_channel_release(&client, channel).await?;
     
-    let download_url = release_data
+    let latest_version = release_version(&release_data).to_string();
+    let prerelease = release_data
+        .get("prerelease")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let installer = release_data
         .get("assets")
         .and_then(|assets| assets.as_array())
         .and_then(|assets| assets.iter().find(|asset| {
@@ -94,11 +283,15 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
                 .and_then(|name| name.as_str())
                 .map(|name| name.ends_with(".exe") || name.ends_with(".msi"))
                 .unwrap_or(false)
-        }))
+        }));
+    
+    let download_url = installer
         .and_then(|asset| asset.get("browser_download_url"))
         .and_then(|url| url.as_str())
         .map(|url| url.to_string());
     
+    let sha256 = installer.and_then(asset_sha256);
+    
     let release_notes = release_data
         .get("body")
         .and_then(|body| body.as_str())
@@ -112,20 +305,342 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
         current_version,
         update_available,
         download_url,
+        sha256,
         release_notes,
+        channel,
+        prerelease,
     })
 }
 
+/// SHA-256 of a release asset; GitHub publishes digests as "sha256:<hex>"
+fn asset_sha256(asset: &serde_json::Value) -> Option<String> {
+    asset
+        .get("digest")
+        .and_then(|digest| digest.as_str())
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .map(|digest| digest.to_string())
+}
+
+/// Boolean field of a GitHub API object, false when missing
+fn json_flag(value: &serde_json::Value, key: &str) -> bool {
+    value.get(key).and_then(|flag| flag.as_bool()).unwrap_or(false)
+}
+
+/// Most releases `get_release_history` returns
+const MAX_RELEASE_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ReleaseNoteSection {
+    /// Heading text, `None` for notes before the first heading
+    pub title: Option<String>,
+    /// List items under the heading
+    pub items: Vec<String>,
+    /// Paragraph lines that are not list items
+    pub text: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub download_url: String,
+    pub size: u64,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseHistoryEntry {
+    pub version: String,
+    pub name: Option<String>,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+    pub html_url: Option<String>,
+    /// Raw Markdown body
+    pub body: String,
+    pub sections: Vec<ReleaseNoteSection>,
+    pub assets: Vec<ReleaseAsset>,
+    /// Whether this release is newer than the running version
+    pub newer_than_current: bool,
+}
+
+/// Split a Markdown release body into sections by heading
+///
+/// List markers ("-", "*", "+", "1.") are stripped from items; blank lines
+/// and horizontal rules are dropped.
+fn parse_release_notes(body: &str) -> Vec<ReleaseNoteSection> {
+    let mut sections: Vec<ReleaseNoteSection> = Vec::new();
+    let mut current = ReleaseNoteSection {
+        title: None,
+        items: Vec::new(),
+        text: Vec::new(),
+    };
+    
+    for line in body.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.chars().all(|c| c == '-' || c == '*' || c == '_') {
+            continue;
+        }
+        
+        if line.starts_with('#') {
+            let title = line.trim_start_matches('#').trim().to_string();
+            let previous = std::mem::replace(
+                &mut current,
+                ReleaseNoteSection {
+                    title: Some(title),
+                    items: Vec::new(),
+                    text: Vec::new(),
+                },
+            );
+            if previous.title.is_some() || !previous.items.is_empty() || !previous.text.is_empty() {
+                sections.push(previous);
+            }
+            continue;
+        }
+        
+        let ordered_item = line
+            .split_once(". ")
+            .filter(|(number, _)| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+            .map(|(_, item)| item);
+        let bullet_item = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| line.strip_prefix(marker));
+        match bullet_item.or(ordered_item) {
+            Some(item) => current.items.push(item.trim().to_string()),
+            None => current.text.push(line.to_string()),
+        }
+    }
+    
+    if current.title.is_some() || !current.items.is_empty() || !current.text.is_empty() {
+        sections.push(current);
+    }
+    sections
+}
+
+/// Last `limit` releases with parsed notes and assets, newest first
+///
+/// Pre-releases are only included on the beta channel.
+#[command]
+pub async fn get_release_history(
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+) -> Result<Vec<ReleaseHistoryEntry>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, MAX_RELEASE_HISTORY);
+    let current_version = get_app_version().await?;
+    let channel = read_update_channel(&db);
+    let client = github_client(read_network_proxy(&db).as_deref())?;
+    
+    // Fetch extra releases so filtered-out pre-releases don't shorten the list
+    let url = format!("{}?per_page={}", RELEASES_API_URL, MAX_RELEASE_HISTORY);
+    let releases = fetch_github_json(&client, &url).await?;
+    
+    let entries = releases
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|release| !json_flag(release, "draft"))
+        .filter(|release| channel == UpdateChannel::Beta || !json_flag(release, "prerelease"))
+        .take(limit)
+        .map(|release| {
+            let version = release_version(release).to_string();
+            let str_field = |key: &str| {
+                release
+                    .get(key)
+                    .and_then(|value| value.as_str())
+                    .map(|value| value.to_string())
+            };
+            let body = str_field("body").unwrap_or_default();
+            let assets = release
+                .get("assets")
+                .and_then(|assets| assets.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|asset| {
+                    Some(ReleaseAsset {
+                        name: asset.get("name")?.as_str()?.to_string(),
+                        download_url: asset.get("browser_download_url")?.as_str()?.to_string(),
+                        size: asset.get("size").and_then(|size| size.as_u64())?,
+                        sha256: asset_sha256(asset),
+                    })
+                })
+                .collect();
+            
+            ReleaseHistoryEntry {
+                newer_than_current: compare_versions(&current_version, &version),
+                version,
+                name: str_field("name").filter(|name| !name.is_empty()),
+                published_at: str_field("published_at"),
+                prerelease: json_flag(release, "prerelease"),
+                html_url: str_field("html_url"),
+                sections: parse_release_notes(&body),
+                body,
+                assets,
+            }
+        })
+        .collect();
+    
+    Ok(entries)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateDownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateInstallResult {
+    pub version: String,
+    /// SHA-256 of the installed package; its signature was checked by the updater
+    pub sha256: String,
+    /// Whether the app must be restarted to run the new version
+    pub restart_required: bool,
+}
+
+/// Public key the updater checks release signatures against, if this build has one
+fn updater_pubkey(app: &AppHandle) -> Option<String> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .map(|pubkey| pubkey.trim().to_string())
+        .filter(|pubkey| !pubkey.is_empty())
+}
+
+/// Download, verify and install the latest release
+///
+/// Emits `update-download-progress` while downloading and `update-installing`
+/// before the package is installed. The package signature is verified before
+/// installing; an unsigned or tampered package is rejected. On Windows the
+/// installer takes over and closes the app, elsewhere the frontend should
+/// offer to restart via `restart_app`.
+#[command]
+pub async fn install_update(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<UpdateInstallResult, String> {
+    if updater_pubkey(&app).is_none() {
+        return Err(
+            "This build has no updater signing key; please download the release manually"
+                .to_string(),
+        );
+    }
+    
+    // The configured endpoint follows the stable releases; the beta channel
+    // reads the update manifest of the newest pre-release instead
+    let proxy = read_network_proxy(&db);
+    let mut builder = app.updater_builder();
+    if let Some(proxy) = &proxy {
+        let proxy = tauri::Url::parse(proxy).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    if read_update_channel(&db) == UpdateChannel::Beta {
+        let client = github_client(proxy.as_deref())?;
+        let release = fetch_channel_release(&client, UpdateChannel::Beta).await?;
+        let manifest_url = release
+            .get("assets")
+            .and_then(|assets| assets.as_array())
+            .and_then(|assets| assets.iter().find(|asset| {
+                asset.get("name").and_then(|name| name.as_str()) == Some("latest.json")
+            }))
+            .and_then(|asset| asset.get("browser_download_url"))
+            .and_then(|url| url.as_str())
+            .ok_or_else(|| {
+                format!("Release {} has no update manifest", release_version(&release))
+            })?;
+        let manifest_url = tauri::Url::parse(manifest_url)
+            .map_err(|e| format!("Invalid update manifest URL: {}", e))?;
+        builder = builder
+            .endpoints(vec![manifest_url])
+            .map_err(|e| format!("Failed to configure updater: {}", e))?;
+    }
+    let updater = builder
+        .build()
+        .map_err(|e| format!("Failed to initialize updater: {}", e))?;
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .ok_or_else(|| "No update available".to_string())?;
+    log::info!(
+        "Downloading update {} from {}",
+        update.version, update.download_url
+    );
+    
+    let mut downloaded: u64 = 0;
+    let bytes = update
+        .download(
+            |chunk_length, total| {
+                downloaded += chunk_length as u64;
+                let _ = app.emit(
+                    "update-download-progress",
+                    UpdateDownloadProgress { downloaded, total },
+                );
+            },
+            || log::info!("Update download finished"),
+        )
+        .await
+        .map_err(|e| format!("Failed to download or verify update: {}", e))?;
+    let sha256 = crate::integrity::sha256_hex(&bytes);
+    log::info!("Verified update {} (sha256 {})", update.version, sha256);
+    
+    let result = UpdateInstallResult {
+        version: update.version.clone(),
+        sha256,
+        restart_required: !cfg!(target_os = "windows"),
+    };
+    let _ = app.emit("update-installing", &result.version);
+    update
+        .install(bytes)
+        .map_err(|e| format!("Failed to install update: {}", e))?;
+    
+    Ok(result)
+}
+
+/// Restart the app, e.g. after an update was installed
+#[command]
+pub fn restart_app(app: AppHandle) {
+    log::info!("Restarting app");
+    app.restart()
+}
+
+/// Verify a downloaded update installer against the SHA-256 from `check_for_updates`
+#[command]
+pub async fn verify_update_artifact(
+    path: String,
+    sha256: String,
+) -> Result<crate::integrity::ArtifactVerification, String> {
+    let content = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read downloaded file: {}", e))?;
+    let artifact = std::path::Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or(path);
+    
+    Ok(crate::integrity::ArtifactVerification::new(&artifact, &sha256, &content))
+}
+
 /// Compare two version strings (simple semantic version comparison)
+///
+/// A pre-release like "1.4.0-beta.2" sorts before "1.4.0".
 fn compare_versions(current: &str, latest: &str) -> bool {
+    let split_pre_release = |v: &str| -> (String, Option<String>) {
+        match v.split_once('-') {
+            Some((core, pre)) => (core.to_string(), Some(pre.to_string())),
+            None => (v.to_string(), None),
+        }
+    };
     let parse_version = |v: &str| -> Vec<u32> {
         v.split('.')
             .map(|part| part.parse().unwrap_or(0))
             .collect()
     };
     
-    let current_parts = parse_version(current);
-    let latest_parts = parse_version(latest);
+    let (current_core, current_pre) = split_pre_release(current);
+    let (latest_core, latest_pre) = split_pre_release(latest);
+    let current_parts = parse_version(&current_core);
+    let latest_parts = parse_version(&latest_core);
     
     // Pad with zeros if needed
     let max_len = current_parts.len().max(latest_parts.len());
@@ -144,7 +659,23 @@ fn compare_versions(current: &str, latest: &str) -> bool {
         }
     }
     
-    false // Versions are equal
+    // Same core version: a release is newer than its pre-releases
+    match (current_pre, latest_pre) {
+        (Some(_), None) => true,
+        (Some(c), Some(l)) => {
+            for (c, l) in c.split('.').zip(l.split('.')) {
+                let ordering = match (c.parse::<u32>(), l.parse::<u32>()) {
+                    (Ok(c), Ok(l)) => c.cmp(&l),
+                    _ => c.cmp(l),
+                };
+                if ordering != std::cmp::Ordering::Equal {
+                    return ordering == std::cmp::Ordering::Less;
+                }
+            }
+            l.split('.').count() > c.split('.').count()
+        }
+        _ => false, // Versions are equal
+    }
 }
 
 #[cfg(test)]
@@ -161,4 +692,28 @@ mod tests {
         assert!(!compare_versions("2.0.0", "1.0.0"));
         assert!(!compare_versions("1.0.0", "1.0.0"));
     }
+
+    #[test]
+    fn test_parse_release_notes() {
+        let body = "Highlights of this release\n\n## ✨ Features\n- Update channels\n* Proxy support\n\n---\n### Fixes\n1. Timeline merge\nSee the docs.\n";
+        let sections = parse_release_notes(body);
+        
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].title, None);
+        assert_eq!(sections[0].text, vec!["Highlights of this release"]);
+        assert_eq!(sections[1].title.as_deref(), Some("✨ Features"));
+        assert_eq!(sections[1].items, vec!["Update channels", "Proxy support"]);
+        assert_eq!(sections[2].items, vec!["Timeline merge"]);
+        assert_eq!(sections[2].text, vec!["See the docs."]);
+    }
+
+    #[test]
+    fn test_pre_release_comparison() {
+        assert!(compare_versions("1.4.0-beta.1", "1.4.0"));
+        assert!(compare_versions("1.4.0-beta.1", "1.4.0-beta.2"));
+        assert!(compare_versions("1.4.0-beta.9", "1.4.0-beta.10"));
+        assert!(compare_versions("1.3.5", "1.4.0-beta.1"));
+        assert!(!compare_versions("1.4.0", "1.4.0-beta.1"));
+        assert!(!compare_versions("1.4.0-beta.2", "1.4.0-beta.2"));
+    }
 }
\ No newline at end of file
@@ -15,10 +15,10 @@ use tauri_plugin_shell::ShellExt;
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tokio::process::Command;
 
-/// Finds the full path to the claude binary
+/// Finds the full path to the claude binary for a project, as sessions do
 /// This is necessary because Windows apps may have a limited PATH environment
-fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
-    crate::claude_binary::find_claude_binary(app_handle)
+fn find_claude_binary(app_handle: &AppHandle, project_path: &str) -> Result<String, String> {
+    crate::commands::claude::find_claude_binary_for_project(app_handle, Some(project_path))
 }
 
 /// Represents a CC Agent stored in the database
@@ -165,6 +165,69 @@ impl AgentRunMetrics {
             },
         }
     }
+
+    /// Calculate metrics from captured `--output-format stream-json` stdout
+    ///
+    /// Token and cost totals come from the CLI's final `result` message;
+    /// `elapsed_ms` is used when the stream ends without one.
+    pub fn from_stream_output(output: &str, elapsed_ms: i64) -> Self {
+        let messages: Vec<JsonValue> = output
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let usage = crate::process::summarize_usage(output.lines());
+        let total_tokens = usage.input_tokens.unwrap_or(0) + usage.output_tokens.unwrap_or(0);
+        let message_count = messages
+            .iter()
+            .filter(|msg| matches!(msg["type"].as_str(), Some("user") | Some("assistant")))
+            .count() as i64;
+        let duration_ms = messages
+            .iter()
+            .rev()
+            .find(|msg| msg["type"] == "result")
+            .and_then(|msg| msg["duration_ms"].as_i64())
+            .unwrap_or(elapsed_ms);
+
+        Self {
+            duration_ms: Some(duration_ms),
+            total_tokens: if total_tokens > 0 {
+                Some(total_tokens)
+            } else {
+                None
+            },
+            cost_usd: usage.cost_usd,
+            message_count: if message_count > 0 {
+                Some(message_count)
+            } else {
+                None
+            },
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.duration_ms.is_none()
+            && self.total_tokens.is_none()
+            && self.cost_usd.is_none()
+            && self.message_count.is_none()
+    }
+}
+
+/// Persist the metrics of a finished run so history views don't re-read its JSONL
+fn store_run_metrics(
+    conn: &Connection,
+    run_id: i64,
+    metrics: &AgentRunMetrics,
+) -> SqliteResult<usize> {
+    conn.execute(
+        "UPDATE agent_runs SET duration_ms = ?1, total_tokens = ?2, cost_usd = ?3, message_count = ?4 WHERE id = ?5",
+        params![
+            metrics.duration_ms,
+            metrics.total_tokens,
+            metrics.cost_usd,
+            metrics.message_count,
+            run_id
+        ],
+    )
 }
 
 /// Read JSONL content from a session file
@@ -216,10 +279,7 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
 
 /// Initialize the agents database
 pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
-    let app_dir = app
-        .path()
-        .app_data_dir()
-        .expect("Failed to get app data dir");
+    let app_dir = crate::portable::app_data_dir(app).expect("Failed to get app data dir");
     std::fs::create_dir_all(&app_dir).expect("Failed to create app data dir");
 
     let db_path = app_dir.join("agents.db");
@@ -296,6 +356,14 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    // Metrics of finished runs, see store_run_metrics
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN duration_ms INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN total_tokens INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN cost_usd REAL", []);
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN message_count INTEGER",
+        [],
+    );
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -344,6 +412,135 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create provider_usage_sessions table to track which provider was active when
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS provider_usage_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            provider_id TEXT NOT NULL,
+            provider_name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_provider_usage_sessions_started_at ON provider_usage_sessions(started_at)",
+        [],
+    )?;
+
+    // Create managed_processes table mirroring the in-memory process registry,
+    // used to find orphaned processes after a crash
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS managed_processes (
+            run_id INTEGER PRIMARY KEY,
+            process_type TEXT NOT NULL,
+            pid INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT NOT NULL,
+            model TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Create run_history table recording finished process runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id INTEGER NOT NULL,
+            process_type TEXT NOT NULL,
+            session_id TEXT,
+            agent_id INTEGER,
+            agent_name TEXT,
+            project_path TEXT NOT NULL,
+            task TEXT NOT NULL,
+            model TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            ended_at TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            exit_code INTEGER,
+            status TEXT NOT NULL,
+            input_tokens INTEGER,
+            output_tokens INTEGER,
+            cache_creation_tokens INTEGER,
+            cache_read_tokens INTEGER,
+            cost_usd REAL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_run_history_ended_at ON run_history(ended_at)",
+        [],
+    )?;
+
+    // Create session_index table caching metadata of Claude Code transcripts
+    crate::commands::sessions::create_session_index_table(&conn)?;
+
+    // Create project_meta table holding pins, names and visibility of projects
+    crate::commands::projects::create_project_meta_table(&conn)?;
+
+    // Create proxy_requests table logging requests through the local proxy
+    crate::commands::proxy_log::create_proxy_requests_table(&conn)?;
+
+    // Create station_balance_snapshots table used for spend forecasts
+    crate::commands::relay_forecast::create_balance_snapshots_table(&conn)?;
+
+    // Create cost_alert_rules table for spend and request count alerts
+    crate::commands::cost_alerts::create_cost_alert_rules_table(&conn)?;
+
+    // Create usage_quotas table for local per-model token quotas
+    crate::commands::usage_quotas::create_usage_quotas_table(&conn)?;
+
+    // Create station_log_cache tables mirroring relay station logs
+    crate::commands::relay_log_cache::create_station_log_cache_tables(&conn)?;
+
+    // Create station_renewals and station_token_expiries tables for renewal reminders
+    crate::commands::relay_renewals::create_renewal_tables(&conn)?;
+
+    // Create station_token_quota table for low quota warnings
+    crate::commands::relay_token_quota::create_token_quota_table(&conn)?;
+
+    // Create token_rate_warnings table for request rate limit warnings
+    crate::commands::relay_rate_usage::create_token_rate_warnings_table(&conn)?;
+
+    // Create station_response_cache table for offline mode
+    crate::commands::relay_offline::create_response_cache_table(&conn)?;
+
+    // Create quick_search_picks table ranking quick switcher results
+    crate::commands::quick_search::create_quick_search_picks_table(&conn)?;
+
+    // Create window_states table keeping window sizes and positions
+    crate::windows::create_window_states_table(&conn)?;
+
+    // Create task chain tables for sequenced and scheduled Claude runs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_chains (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            steps TEXT NOT NULL,
+            schedule TEXT,
+            failure_policy TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            last_run_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_chain_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chain_id INTEGER NOT NULL,
+            trigger TEXT NOT NULL,
+            status TEXT NOT NULL,
+            steps TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT
+        )",
+        [],
+    )?;
+
     Ok(conn)
 }
 
@@ -678,6 +875,90 @@ pub async fn list_agent_runs_with_metrics(
     Ok(runs_with_metrics)
 }
 
+/// List agent runs with their stored metrics for the history view, newest first
+///
+/// Unlike `list_agent_runs_with_metrics` this doesn't load run output. Runs
+/// finished before metrics were stored get them computed from their session
+/// JSONL once.
+#[tauri::command]
+pub async fn list_agent_run_history(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    project_path: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<AgentRunWithMetrics>, String> {
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let offset = offset.unwrap_or(0);
+
+    let mut runs = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at,
+                        duration_ms, total_tokens, cost_usd, message_count
+                 FROM agent_runs
+                 WHERE (?1 IS NULL OR agent_id = ?1) AND (?2 IS NULL OR project_path = ?2)
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT ?3 OFFSET ?4",
+            )
+            .map_err(|e| e.to_string())?;
+        let runs = stmt
+            .query_map(params![agent_id, project_path, limit, offset], |row| {
+                let metrics = AgentRunMetrics {
+                    duration_ms: row.get(13)?,
+                    total_tokens: row.get(14)?,
+                    cost_usd: row.get(15)?,
+                    message_count: row.get(16)?,
+                };
+                Ok(AgentRunWithMetrics {
+                    run: AgentRun {
+                        id: Some(row.get(0)?),
+                        agent_id: row.get(1)?,
+                        agent_name: row.get(2)?,
+                        agent_icon: row.get(3)?,
+                        task: row.get(4)?,
+                        model: row.get(5)?,
+                        project_path: row.get(6)?,
+                        session_id: row.get(7)?,
+                        status: row
+                            .get::<_, String>(8)
+                            .unwrap_or_else(|_| "pending".to_string()),
+                        pid: row.get::<_, Option<i64>>(9).ok().flatten().map(|p| p as u32),
+                        process_started_at: row.get(10)?,
+                        created_at: row.get(11)?,
+                        completed_at: row.get(12)?,
+                    },
+                    metrics: (!metrics.is_empty()).then_some(metrics),
+                    output: None,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read agent run history: {}", e))?;
+        runs
+    };
+
+    for entry in runs.iter_mut() {
+        let run = &entry.run;
+        if entry.metrics.is_some() || run.completed_at.is_none() || run.session_id.is_empty() {
+            continue;
+        }
+        let Ok(jsonl_content) = read_session_jsonl(&run.session_id, &run.project_path).await else {
+            continue;
+        };
+        let metrics = AgentRunMetrics::from_jsonl(&jsonl_content);
+        if let (Some(run_id), Ok(conn)) = (run.id, db.0.lock()) {
+            if let Err(e) = store_run_metrics(&conn, run_id, &metrics) {
+                warn!("Failed to store metrics for agent run {}: {}", run_id, e);
+            }
+        }
+        entry.metrics = Some(metrics);
+    }
+
+    Ok(runs)
+}
+
 /// Execute a CC agent with streaming output
 #[tauri::command]
 pub async fn execute_agent(
@@ -694,6 +975,7 @@ pub async fn execute_agent(
     // Get the agent from database
     let agent = get_agent(db.clone(), agent_id).await?;
     let execution_model = model.unwrap_or(agent.model.clone());
+    crate::commands::usage_quotas::ensure_launch_allowed(&app, &execution_model).await?;
     
     // Create .claude/settings.json with agent hooks if it doesn't exist
     if let Some(hooks_json) = &agent.hooks {
@@ -744,7 +1026,7 @@ pub async fn execute_agent(
 
     // Find Claude binary
     info!("Running agent '{}'", agent.name);
-    let claude_path = match find_claude_binary(&app) {
+    let claude_path = match find_claude_binary(&app, &project_path) {
         Ok(path) => path,
         Err(e) => {
             error!("Failed to find claude binary: {}", e);
@@ -770,6 +1052,10 @@ pub async fn execute_agent(
     if should_use_sidecar(&claude_path) {
         spawn_agent_sidecar(app, run_id, agent_id, agent.name.clone(), args, project_path, task, execution_model, db, registry).await
     } else {
+        // Older CLI builds lack some flags; drop optional ones, fail on required ones
+        let args = crate::claude_binary::probe_capabilities(&claude_path)
+            .await
+            .adapt_args(args)?;
         spawn_agent_system(app, run_id, agent_id, agent.name.clone(), claude_path, args, project_path, task, execution_model, db, registry).await
     }
 }
@@ -805,17 +1091,32 @@ fn create_agent_system_command(
     args: Vec<String>,
     project_path: &str,
 ) -> Command {
-    let mut cmd = create_command_with_env(claude_path);
-    
-    // Add all arguments
-    for arg in args {
-        cmd.arg(arg);
-    }
+    let mut cmd = match crate::claude_binary::parse_wsl_path(claude_path) {
+        Some(distro) => {
+            let (program, wsl_args) =
+                crate::claude_binary::wsl_launch_args(distro, &args, Some(project_path));
+            let mut cmd = create_command_with_env(&program);
+            cmd.args(wsl_args);
+            cmd
+        }
+        None => {
+            let mut cmd = create_command_with_env(claude_path);
+
+            // Add all arguments
+            for arg in args {
+                cmd.arg(arg);
+            }
+            cmd
+        }
+    };
     
     cmd.current_dir(project_path)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+
+    // Make the agent's child processes killable as a unit
+    crate::process::tree::configure_command(&mut cmd);
     
     cmd
 }
@@ -862,10 +1163,7 @@ async fn spawn_agent_sidecar(
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
 
     // Create variables we need for the spawned task
-    let app_dir = app
-        .path()
-        .app_data_dir()
-        .expect("Failed to get app data dir");
+    let app_dir = crate::portable::app_data_dir(&app).expect("Failed to get app data dir");
     let db_path = app_dir.join("agents.db");
     let db_path_for_stream = db_path.clone(); // Clone for the streaming task
 
@@ -877,6 +1175,7 @@ async fn spawn_agent_sidecar(
     let registry_clone = registry.0.clone();
     let first_output = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_output_clone = first_output.clone();
+    let start_time = std::time::Instant::now();
 
     let sidecar_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude sidecar events...");
@@ -953,6 +1252,7 @@ async fn spawn_agent_sidecar(
                     let line = String::from_utf8_lossy(&data).trim().to_string();
                     if !line.is_empty() {
                         error!("sidecar stderr: {}", line);
+                        let _ = registry_clone.append_output(run_id, crate::process::OutputStream::Stderr, &line);
                         // Emit error lines to the frontend with run_id for isolation
                         let _ = app_handle.emit(&format!("agent-error:{}", run_id), &line);
                         // Also emit to the generic event for backward compatibility
@@ -1024,6 +1324,12 @@ async fn spawn_agent_sidecar(
             String::new()
         };
 
+        let elapsed_ms = start_time.elapsed().as_millis() as i64;
+        let metrics = live_output
+            .lock()
+            .map(|output| AgentRunMetrics::from_stream_output(&output, elapsed_ms))
+            .ok();
+
         // Update the run record with session ID and mark as completed
         if let Ok(conn) = Connection::open(&db_path) {
             info!("🔄 Updating database with extracted session ID: {}", extracted_session_id);
@@ -1042,6 +1348,11 @@ async fn spawn_agent_sidecar(
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
                 }
             }
+            if let Some(metrics) = &metrics {
+                if let Err(e) = store_run_metrics(&conn, run_id, metrics) {
+                    error!("❌ Failed to store metrics for agent run {}: {}", run_id, e);
+                }
+            }
         } else {
             error!("❌ Failed to open database to update session ID for run {}", run_id);
         }
@@ -1106,10 +1417,7 @@ async fn spawn_agent_system(
     let stderr_reader = TokioBufReader::new(stderr);
 
     // Create variables we need for the spawned tasks
-    let app_dir = app
-        .path()
-        .app_data_dir()
-        .expect("Failed to get app data dir");
+    let app_dir = crate::portable::app_data_dir(&app).expect("Failed to get app data dir");
     let db_path = app_dir.join("agents.db");
 
     // Shared state for collecting session ID and live output
@@ -1204,6 +1512,7 @@ async fn spawn_agent_system(
     });
 
     let app_handle_stderr = app.clone();
+    let registry_clone_stderr = registry.0.clone();
     let first_error = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_error_clone = first_error.clone();
 
@@ -1222,6 +1531,7 @@ async fn spawn_agent_system(
             }
 
             error!("stderr[{}]: {}", error_count, line);
+            let _ = registry_clone_stderr.append_output(run_id, crate::process::OutputStream::Stderr, &line);
             // Emit error lines to the frontend with run_id for isolation
             let _ = app_handle_stderr.emit(&format!("agent-error:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
@@ -1255,6 +1565,7 @@ async fn spawn_agent_system(
     info!("📋 Registered process in registry");
 
     let db_path_for_monitor = db_path.clone(); // Clone for the monitor task
+    let registry_monitor = registry.0.clone();
 
     // Monitor process status and wait for completion
     tokio::spawn(async move {
@@ -1285,43 +1596,18 @@ async fn spawn_agent_system(
                     pid
                 );
                 #[cfg(target_os = "windows")]
-                let kill_result = {
-                    use std::os::windows::process::CommandExt;
-                    std::process::Command::new("taskkill")
-                        .args(["/F", "/PID", &pid.to_string()])
-                        .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                        .output()
-                };
-                
+                let kill_result = crate::process::tree::kill_tree_by_pid(pid);
+
                 #[cfg(not(target_os = "windows"))]
-                let kill_result = std::process::Command::new("kill")
-                    .arg("-TERM")
-                    .arg(pid.to_string())
-                    .output();
+                let kill_result = crate::process::tree::signal_tree(pid, "TERM");
 
                 match kill_result {
-                    Ok(output) if output.status.success() => {
+                    Ok(()) => {
                         warn!("🔍 Successfully sent TERM signal to process");
                     }
-                    Ok(_) => {
-                        warn!("🔍 Failed to kill process with TERM, trying KILL");
-                        #[cfg(target_os = "windows")]
-                        let _ = {
-                            use std::os::windows::process::CommandExt;
-                            std::process::Command::new("taskkill")
-                                .args(["/F", "/PID", &pid.to_string()])
-                                .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                                .output()
-                        };
-                        
-                        #[cfg(not(target_os = "windows"))]
-                        let _ = std::process::Command::new("kill")
-                            .arg("-KILL")
-                            .arg(pid.to_string())
-                            .output();
-                    }
                     Err(e) => {
-                        warn!("🔍 Error killing process: {}", e);
+                        warn!("🔍 Failed to kill process with TERM ({}), trying KILL", e);
+                        let _ = crate::process::tree::kill_tree_by_pid(pid);
                     }
                 }
 
@@ -1332,6 +1618,7 @@ async fn spawn_agent_system(
                         params![run_id],
                     );
                 }
+                let _ = registry_monitor.record_history(run_id, None, "failed");
 
                 let _ = app.emit("agent-complete", false);
                 let _ = app.emit(&format!("agent-complete:{}", run_id), false);
@@ -1346,6 +1633,17 @@ async fn spawn_agent_system(
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
+        // Record the finished run in the persistent history; the entry stays
+        // registered so its live output remains readable
+        let exit_code = registry_monitor.collect_exit_code(run_id).await;
+        let history_status = match exit_code {
+            Some(0) | None => "completed",
+            Some(_) => "failed",
+        };
+        if let Err(e) = registry_monitor.record_history(run_id, exit_code, history_status) {
+            warn!("Failed to record run history for {}: {}", run_id, e);
+        }
+
         let duration_ms = start_time.elapsed().as_millis() as i64;
         info!("⏱️ Process execution took {} ms", duration_ms);
 
@@ -1359,12 +1657,18 @@ async fn spawn_agent_system(
         // Wait for process completion and update status
         info!("✅ Claude process execution monitoring complete");
 
-        // Update the run record with session ID and mark as completed - open a new connection
+        let metrics = live_output
+            .lock()
+            .map(|output| AgentRunMetrics::from_stream_output(&output, duration_ms))
+            .ok();
+
+        // Update the run record with session ID and final status - open a new connection.
+        // A run cancelled through kill_agent_session keeps its status.
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             info!("🔄 Updating database with extracted session ID: {}", extracted_session_id);
             match conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                params![extracted_session_id, run_id],
+                "UPDATE agent_runs SET session_id = ?1, status = CASE WHEN status = 'cancelled' THEN status ELSE ?2 END, completed_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                params![extracted_session_id, history_status, run_id],
             ) {
                 Ok(rows_affected) => {
                     if rows_affected > 0 {
@@ -1377,6 +1681,11 @@ async fn spawn_agent_system(
                     error!("❌ Failed to update agent run {} with session ID: {}", run_id, e);
                 }
             }
+            if let Some(metrics) = &metrics {
+                if let Err(e) = store_run_metrics(&conn, run_id, metrics) {
+                    error!("❌ Failed to store metrics for agent run {}: {}", run_id, e);
+                }
+            }
         } else {
             error!("❌ Failed to open database to update session ID for run {}", run_id);
         }
@@ -1748,8 +2057,7 @@ pub async fn stream_session_output(
             // Check if the session is still running by querying the database
             // If the session is no longer running, stop streaming
             if let Ok(conn) = rusqlite::Connection::open(
-                app.path()
-                    .app_data_dir()
+                crate::portable::app_data_dir(&app)
                     .expect("Failed to get app data dir")
                     .join("agents.db"),
             ) {
@@ -1852,10 +2160,10 @@ pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<Str
 pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    // Special handling for bundled sidecar reference
-    if path == "claude-code" {
-        // For bundled sidecar, we don't need to validate file existence
-        // as it's handled by Tauri's sidecar system
+    // Special handling for bundled sidecar and WSL references
+    if path == "claude-code" || crate::claude_binary::parse_wsl_path(&path).is_some() {
+        // Neither exists as a file: the sidecar is handled by Tauri's
+        // sidecar system, WSL installations are launched through wsl.exe
         conn.execute(
             "INSERT INTO app_settings (key, value) VALUES ('claude_binary_path', ?1)
              ON CONFLICT(key) DO UPDATE SET value = ?1",
@@ -1901,7 +2209,7 @@ pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Res
 pub async fn list_claude_installations(
     app: AppHandle,
 ) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
-    let mut installations = crate::claude_binary::discover_claude_installations();
+    let mut installations = crate::claude_binary::cached_claude_installations(&app);
 
     if installations.is_empty() {
         return Err("No Claude Code installations found on the system".to_string());
@@ -2379,3 +2687,32 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stream-json output of an agent run that read one file
+    const STREAM: &str = r#"{"type":"system","subtype":"init","cwd":"/tmp/app","session_id":"0b6e7a1d-3f2c-4e59-a8d1-7c94e2f05b13","tools":["Read","Grep"],"model":"claude-opus-4-1-20250805","permissionMode":"bypassPermissions","apiKeySource":"ANTHROPIC_API_KEY"}
+{"type":"assistant","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-opus-4-1-20250805","content":[{"type":"tool_use","id":"toolu_01","name":"Read","input":{"file_path":"/tmp/app/README.md"}}],"stop_reason":null,"usage":{"input_tokens":3,"cache_creation_input_tokens":2210,"cache_read_input_tokens":0,"output_tokens":48}},"parent_tool_use_id":null,"session_id":"0b6e7a1d-3f2c-4e59-a8d1-7c94e2f05b13"}
+{"type":"user","message":{"role":"user","content":[{"tool_use_id":"toolu_01","type":"tool_result","content":"Sample app"}]},"parent_tool_use_id":null,"session_id":"0b6e7a1d-3f2c-4e59-a8d1-7c94e2f05b13"}
+{"type":"assistant","message":{"id":"msg_02","type":"message","role":"assistant","model":"claude-opus-4-1-20250805","content":[{"type":"text","text":"The README only has a title."}],"stop_reason":null,"usage":{"input_tokens":3,"cache_creation_input_tokens":40,"cache_read_input_tokens":2210,"output_tokens":12}},"parent_tool_use_id":null,"session_id":"0b6e7a1d-3f2c-4e59-a8d1-7c94e2f05b13"}
+{"type":"result","subtype":"success","is_error":false,"duration_ms":5230,"duration_api_ms":4980,"num_turns":2,"result":"The README only has a title.","session_id":"0b6e7a1d-3f2c-4e59-a8d1-7c94e2f05b13","total_cost_usd":0.0474,"usage":{"input_tokens":6,"cache_creation_input_tokens":2250,"cache_read_input_tokens":2210,"output_tokens":60,"service_tier":"standard"}}"#;
+
+    #[test]
+    fn test_metrics_from_stream_output() {
+        let metrics = AgentRunMetrics::from_stream_output(STREAM, 9000);
+        assert_eq!(metrics.duration_ms, Some(5230));
+        assert_eq!(metrics.total_tokens, Some(66));
+        assert_eq!(metrics.cost_usd, Some(0.0474));
+        assert_eq!(metrics.message_count, Some(3));
+
+        // Killed before the result message: only the wall-clock time is known
+        let partial: String = STREAM.lines().take(2).collect::<Vec<_>>().join("\n");
+        let metrics = AgentRunMetrics::from_stream_output(&partial, 9000);
+        assert_eq!(metrics.duration_ms, Some(9000));
+        assert_eq!(metrics.total_tokens, None);
+        assert_eq!(metrics.cost_usd, None);
+        assert_eq!(metrics.message_count, Some(1));
+    }
+}
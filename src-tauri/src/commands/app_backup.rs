@@ -0,0 +1,1125 @@
+//! Whole-app backup archives and a guided restore
+//!
+//! An archive is a zstd-compressed JSON document holding providers.json, a
+//! snapshot of the app database, Claude Code's settings.json and the
+//! checkpoint index (timelines and manifests, not the file contents they
+//! point to). With a passphrase, the items holding secrets are sealed with
+//! ChaCha20-Poly1305 under a PBKDF2 key, as station shares are; the
+//! checkpoint index stays readable so a restore can be previewed.
+//!
+//! A restore first compares every item with what is on disk, and only
+//! overwrites conflicting items that were chosen explicitly. The current
+//! data is backed up before anything is written, encrypted with the restored
+//! archive's passphrase when it has one; only the newest of these safety
+//! backups are kept.
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::agents::AgentDb;
+use super::app_lock::{self, ProtectedAction};
+use super::provider;
+use super::relay_share::{self, MIN_PASSPHRASE_LEN, NONCE_LEN, SALT_LEN};
+
+/// Bumped whenever the archive layout changes incompatibly
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Sealed into encrypted archives to tell a wrong passphrase early
+const PASSPHRASE_CHECK: &[u8] = b"claude-workbench-backup";
+
+/// Name of the database file inside the database item
+const DATABASE_FILE: &str = "agents.db";
+
+/// Subdirectory of the app data directory for backups made before a restore
+const SAFETY_BACKUPS_DIR: &str = "backups";
+
+/// File name prefix of the backups made before a restore
+const SAFETY_BACKUP_PREFIX: &str = "pre-restore-";
+
+/// Safety backups kept; older ones are deleted after each restore
+const MAX_SAFETY_BACKUPS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupItemKind {
+    Providers,
+    Database,
+    ClaudeSettings,
+    CheckpointIndex,
+}
+
+impl BackupItemKind {
+    fn holds_secrets(self) -> bool {
+        self != Self::CheckpointIndex
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupFile {
+    /// Path relative to `~/.claude`, or the file name for providers and the
+    /// database
+    path: String,
+    /// SHA-256 of the content before sealing
+    sha256: String,
+    /// Base64 of the content, or of the nonce and sealed content
+    data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupItem {
+    /// `providers`, `database`, `claude-settings` or `checkpoints:<project>`
+    id: String,
+    kind: BackupItemKind,
+    encrypted: bool,
+    files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupEncryption {
+    salt: String,
+    /// `PASSPHRASE_CHECK` sealed with the key
+    check: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppBackup {
+    format_version: u32,
+    created_at: i64,
+    app_version: String,
+    encryption: Option<BackupEncryption>,
+    items: Vec<BackupItem>,
+}
+
+/// Where the backed up files live
+#[derive(Debug, Clone)]
+pub struct BackupPaths {
+    pub providers_file: PathBuf,
+    pub claude_dir: PathBuf,
+}
+
+impl BackupPaths {
+    pub fn current() -> Result<Self, String> {
+        Ok(Self {
+            providers_file: provider::get_providers_config_path()?,
+            claude_dir: dirs::home_dir()
+                .ok_or("Failed to get home directory")?
+                .join(".claude"),
+        })
+    }
+
+    /// Where a file of an item is restored to
+    fn target(&self, kind: BackupItemKind, path: &str) -> Result<Option<PathBuf>, String> {
+        match kind {
+            BackupItemKind::Providers => Ok(Some(self.providers_file.clone())),
+            BackupItemKind::Database => Ok(None),
+            BackupItemKind::ClaudeSettings | BackupItemKind::CheckpointIndex => {
+                // Archives may come from anywhere; only write the files the
+                // item kind backs up, so hooks, commands and agents stay out
+                let relative = Path::new(path);
+                if !is_allowed_path(kind, relative) {
+                    return Err(format!("Backup holds an unsafe path: {}", path));
+                }
+                Ok(Some(self.claude_dir.join(relative)))
+            }
+        }
+    }
+}
+
+/// Whether an item of `kind` may hold `path`, relative to `~/.claude`:
+/// `settings.json`, or JSON files under `projects/<project>/.timelines`
+fn is_allowed_path(kind: BackupItemKind, path: &Path) -> bool {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => match part.to_str() {
+                Some(part) => parts.push(part),
+                None => return false,
+            },
+            _ => return false,
+        }
+    }
+    match kind {
+        BackupItemKind::ClaudeSettings => parts == ["settings.json"],
+        BackupItemKind::CheckpointIndex => {
+            parts.len() >= 4
+                && parts[0] == "projects"
+                && parts[2] == ".timelines"
+                && parts.last().is_some_and(|name| name.ends_with(".json"))
+        }
+        BackupItemKind::Providers | BackupItemKind::Database => false,
+    }
+}
+
+/// The cipher of an encrypted archive
+struct Sealer(ChaCha20Poly1305);
+
+impl Sealer {
+    fn new(passphrase: &str, salt: &[u8]) -> Self {
+        Self(ChaCha20Poly1305::new(&relay_share::derive_key(
+            passphrase, salt,
+        )))
+    }
+
+    fn seal(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .0
+            .encrypt(&nonce, data)
+            .map_err(|e| format!("Failed to encrypt the backup: {}", e))?;
+        let mut bytes = Vec::with_capacity(NONCE_LEN + sealed.len());
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&sealed);
+        Ok(bytes)
+    }
+
+    fn open(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if data.len() <= NONCE_LEN {
+            return Err("Backup is damaged: sealed data too short".to_string());
+        }
+        let (nonce, sealed) = data.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| "Wrong passphrase or damaged backup".to_string())
+    }
+}
+
+fn base64_engine() -> &'static base64::engine::GeneralPurpose {
+    &base64::engine::general_purpose::STANDARD
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn backup_file(
+    path: String,
+    content: &[u8],
+    sealer: Option<&Sealer>,
+) -> Result<BackupFile, String> {
+    let data = match sealer {
+        Some(sealer) => sealer.seal(content)?,
+        None => content.to_vec(),
+    };
+    Ok(BackupFile {
+        path,
+        sha256: sha256_hex(content),
+        data: base64_engine().encode(data),
+    })
+}
+
+/// A file's content, checked against its hash
+fn file_content(file: &BackupFile, sealer: Option<&Sealer>) -> Result<Vec<u8>, String> {
+    let data = base64_engine()
+        .decode(&file.data)
+        .map_err(|e| format!("Backup is damaged: {}", e))?;
+    let content = match sealer {
+        Some(sealer) => sealer.open(&data)?,
+        None => data,
+    };
+    if sha256_hex(&content) != file.sha256 {
+        return Err(format!(
+            "Backup is damaged: {} does not match its hash",
+            file.path
+        ));
+    }
+    Ok(content)
+}
+
+/// `path` under `root` with `/` separators
+fn relative_path(path: &Path, root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Timelines, checkpoint metadata and manifests of each project, without
+/// the file and message blobs
+fn checkpoint_index(claude_dir: &Path) -> Vec<(String, Vec<PathBuf>)> {
+    let Ok(projects) = fs::read_dir(claude_dir.join("projects")) else {
+        return Vec::new();
+    };
+    let mut index = Vec::new();
+    for project in projects.flatten() {
+        let timelines = project.path().join(".timelines");
+        if !timelines.is_dir() {
+            continue;
+        }
+        let mut files: Vec<PathBuf> = walkdir::WalkDir::new(&timelines)
+            .into_iter()
+            .filter_entry(|entry| {
+                !(entry.file_type().is_dir()
+                    && matches!(entry.file_name().to_str(), Some("files" | "objects")))
+            })
+            .flatten()
+            .filter(|entry| {
+                entry.file_type().is_file()
+                    && entry.path().extension().is_some_and(|ext| ext == "json")
+            })
+            .map(|entry| entry.into_path())
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+        files.sort();
+        index.push((project.file_name().to_string_lossy().to_string(), files));
+    }
+    index.sort();
+    index
+}
+
+/// Build an archive of the files under `paths` and the `database` snapshot
+pub fn build_backup(
+    paths: &BackupPaths,
+    database: Option<Vec<u8>>,
+    passphrase: Option<&str>,
+    app_version: &str,
+) -> Result<AppBackup, String> {
+    let (encryption, sealer) = match passphrase {
+        Some(passphrase) => {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let sealer = Sealer::new(passphrase, &salt);
+            let encryption = BackupEncryption {
+                salt: base64_engine().encode(salt),
+                check: base64_engine().encode(sealer.seal(PASSPHRASE_CHECK)?),
+            };
+            (Some(encryption), Some(sealer))
+        }
+        None => (None, None),
+    };
+    let mut items = Vec::new();
+    let mut add = |id: String, kind: BackupItemKind, files: Vec<(String, Vec<u8>)>| {
+        let sealer = sealer.as_ref().filter(|_| kind.holds_secrets());
+        let files = files
+            .into_iter()
+            .map(|(path, content)| backup_file(path, &content, sealer))
+            .collect::<Result<Vec<_>, String>>()?;
+        items.push(BackupItem {
+            id,
+            kind,
+            encrypted: sealer.is_some(),
+            files,
+        });
+        Ok::<(), String>(())
+    };
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+
+    if paths.providers_file.exists() {
+        let name = paths
+            .providers_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "providers.json".to_string());
+        add(
+            "providers".to_string(),
+            BackupItemKind::Providers,
+            vec![(name, read(&paths.providers_file)?)],
+        )?;
+    }
+    if let Some(database) = database {
+        add(
+            "database".to_string(),
+            BackupItemKind::Database,
+            vec![(DATABASE_FILE.to_string(), database)],
+        )?;
+    }
+    let settings = paths.claude_dir.join("settings.json");
+    if settings.exists() {
+        add(
+            "claude-settings".to_string(),
+            BackupItemKind::ClaudeSettings,
+            vec![("settings.json".to_string(), read(&settings)?)],
+        )?;
+    }
+    for (project, files) in checkpoint_index(&paths.claude_dir) {
+        let files = files
+            .iter()
+            .filter_map(|file| {
+                let relative = relative_path(file, &paths.claude_dir)?;
+                Some(read(file).map(|content| (relative, content)))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        add(
+            format!("checkpoints:{}", project),
+            BackupItemKind::CheckpointIndex,
+            files,
+        )?;
+    }
+
+    Ok(AppBackup {
+        format_version: BACKUP_FORMAT_VERSION,
+        created_at: chrono::Utc::now().timestamp(),
+        app_version: app_version.to_string(),
+        encryption,
+        items,
+    })
+}
+
+/// Create (or truncate) a file only the current user can read
+fn create_private(path: &Path) -> std::io::Result<fs::File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // `mode` only applies to new files
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    {
+        fs::File::create(path)
+    }
+}
+
+/// Write `backup` to `path`, readable only by the current user
+pub fn write_archive(backup: &AppBackup, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    }
+    let file = create_private(path).map_err(|e| format!("Failed to create backup: {}", e))?;
+    let mut encoder = zstd::stream::Encoder::new(file, 3)
+        .map_err(|e| format!("Failed to start backup compression: {}", e))?;
+    serde_json::to_writer(&mut encoder, backup)
+        .map_err(|e| format!("Failed to write backup: {}", e))?;
+    encoder
+        .finish()
+        .map_err(|e| format!("Failed to finish backup: {}", e))?;
+    Ok(())
+}
+
+pub fn read_archive(path: &Path) -> Result<AppBackup, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open backup: {}", e))?;
+    let decoder = zstd::stream::Decoder::new(file)
+        .map_err(|e| format!("Failed to start backup decompression: {}", e))?;
+    let backup: AppBackup =
+        serde_json::from_reader(decoder).map_err(|e| format!("Failed to parse backup: {}", e))?;
+    if backup.format_version != BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported backup format version {}",
+            backup.format_version
+        ));
+    }
+    Ok(backup)
+}
+
+/// The archive's cipher, once the passphrase is checked
+fn open_sealer(backup: &AppBackup, passphrase: Option<&str>) -> Result<Option<Sealer>, String> {
+    let Some(encryption) = &backup.encryption else {
+        return Ok(None);
+    };
+    let passphrase = passphrase
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or("This backup is encrypted; enter its passphrase")?;
+    let salt = base64_engine()
+        .decode(&encryption.salt)
+        .map_err(|e| format!("Backup is damaged: {}", e))?;
+    let check = base64_engine()
+        .decode(&encryption.check)
+        .map_err(|e| format!("Backup is damaged: {}", e))?;
+    let sealer = Sealer::new(passphrase, &salt);
+    if sealer.open(&check)? != PASSPHRASE_CHECK {
+        return Err("Wrong passphrase or damaged backup".to_string());
+    }
+    Ok(Some(sealer))
+}
+
+/// A copy of the database, consistent even while the app writes to it
+pub fn snapshot_database(conn: &Connection) -> Result<Vec<u8>, String> {
+    let dir = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {}", e))?;
+    let file = dir.path().join(DATABASE_FILE);
+    conn.execute(
+        "VACUUM INTO ?1",
+        params![file.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("Failed to snapshot the database: {}", e))?;
+    fs::read(&file).map_err(|e| format!("Failed to read database snapshot: {}", e))
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1, ?2)")?;
+    let columns = stmt
+        .query_map(params![table, schema], |row| row.get(0))?
+        .collect();
+    columns
+}
+
+/// Replace the rows of every table with those in the attached `backup`
+///
+/// Columns missing on either side are left out, so a backup from an older or
+/// newer version still restores. Virtual tables are skipped: the search index
+/// follows its content table through triggers.
+fn copy_tables(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+    let tables: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT name, COALESCE(sql, '') FROM backup.sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    let virtual_tables: Vec<&String> = tables
+        .iter()
+        .filter(|(_, sql)| sql.to_uppercase().starts_with("CREATE VIRTUAL TABLE"))
+        .map(|(name, _)| name)
+        .collect();
+
+    let mut skipped = Vec::new();
+    let tx = conn.unchecked_transaction()?;
+    for (table, _) in &tables {
+        if virtual_tables
+            .iter()
+            .any(|name| table == *name || table.starts_with(&format!("{}_", name)))
+        {
+            continue;
+        }
+        let current = table_columns(conn, "main", table)?;
+        if current.is_empty() {
+            skipped.push(table.clone());
+            continue;
+        }
+        let backed_up: HashSet<String> =
+            table_columns(conn, "backup", table)?.into_iter().collect();
+        let columns = current
+            .iter()
+            .filter(|column| backed_up.contains(*column))
+            .map(|column| quote(column))
+            .collect::<Vec<_>>();
+        // Nothing in common, e.g. a table renamed and recreated since
+        if columns.is_empty() {
+            skipped.push(table.clone());
+            continue;
+        }
+        let columns = columns.join(", ");
+        tx.execute(&format!("DELETE FROM main.{}", quote(table)), [])?;
+        tx.execute(
+            &format!(
+                "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM backup.{table}",
+                table = quote(table),
+                columns = columns
+            ),
+            [],
+        )?;
+    }
+    tx.commit()?;
+    Ok(skipped)
+}
+
+/// Restore a database snapshot into the open database; returns the tables
+/// of the snapshot this version does not have or cannot fill
+pub fn restore_database(conn: &Connection, snapshot: &[u8]) -> Result<Vec<String>, String> {
+    let dir = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {}", e))?;
+    let file = dir.path().join(DATABASE_FILE);
+    fs::write(&file, snapshot).map_err(|e| format!("Failed to write database snapshot: {}", e))?;
+
+    let foreign_keys: bool = conn
+        .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+        .unwrap_or(false);
+    conn.execute("PRAGMA foreign_keys = OFF", [])
+        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS backup",
+        params![file.to_string_lossy().to_string()],
+    )
+    .map_err(|e| format!("Failed to open database snapshot: {}", e))?;
+    let result = copy_tables(conn).map_err(|e| format!("Failed to restore the database: {}", e));
+    let _ = conn.execute("DETACH DATABASE backup", []);
+    if foreign_keys {
+        let _ = conn.execute("PRAGMA foreign_keys = ON", []);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreStatus {
+    /// Nothing there yet
+    New,
+    /// Already the same
+    Identical,
+    /// Differs from what is there; only restored when chosen
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreItemPlan {
+    pub id: String,
+    pub kind: BackupItemKind,
+    pub encrypted: bool,
+    pub status: RestoreStatus,
+    pub file_count: usize,
+    /// Files that would overwrite different content
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePlan {
+    pub created_at: i64,
+    pub app_version: String,
+    pub encrypted: bool,
+    pub items: Vec<RestoreItemPlan>,
+}
+
+fn plan_item(item: &BackupItem, paths: &BackupPaths) -> RestoreItemPlan {
+    let mut status = RestoreStatus::Identical;
+    let mut conflicts = Vec::new();
+    for file in &item.files {
+        match paths.target(item.kind, &file.path) {
+            // The database is always there and always replaced
+            Ok(None) => {
+                status = RestoreStatus::Conflict;
+                conflicts.push(file.path.clone());
+            }
+            Ok(Some(target)) => match fs::read(&target) {
+                Ok(current) if sha256_hex(&current) == file.sha256 => {}
+                Ok(_) => {
+                    status = RestoreStatus::Conflict;
+                    conflicts.push(file.path.clone());
+                }
+                Err(_) if status == RestoreStatus::Identical => status = RestoreStatus::New,
+                Err(_) => {}
+            },
+            Err(_) => {
+                status = RestoreStatus::Conflict;
+                conflicts.push(file.path.clone());
+            }
+        }
+    }
+    RestoreItemPlan {
+        id: item.id.clone(),
+        kind: item.kind,
+        encrypted: item.encrypted,
+        status,
+        file_count: item.files.len(),
+        conflicts,
+    }
+}
+
+/// What restoring would do to each item; needs no passphrase
+pub fn plan_restore(backup: &AppBackup, paths: &BackupPaths) -> RestorePlan {
+    RestorePlan {
+        created_at: backup.created_at,
+        app_version: backup.app_version.clone(),
+        encrypted: backup.encryption.is_some(),
+        items: backup
+            .items
+            .iter()
+            .map(|item| plan_item(item, paths))
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreAction {
+    Restore,
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreOutcome {
+    Restored,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreItemResult {
+    pub id: String,
+    pub kind: BackupItemKind,
+    pub status: RestoreStatus,
+    pub outcome: RestoreOutcome,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// Backup of the data as it was before the restore
+    pub safety_backup: Option<String>,
+    pub items: Vec<RestoreItemResult>,
+    /// Database tables in the backup this version does not have
+    pub skipped_tables: Vec<String>,
+    /// Something was restored; the app has to restart to drop settings, the
+    /// relay and proxy state and caches it loaded from the old data
+    pub restart_required: bool,
+}
+
+fn restore_item(
+    item: &BackupItem,
+    paths: &BackupPaths,
+    conn: &Connection,
+    sealer: Option<&Sealer>,
+) -> Result<Vec<String>, String> {
+    let sealer = sealer.filter(|_| item.encrypted);
+    if item.encrypted && sealer.is_none() {
+        return Err("This backup is encrypted; enter its passphrase".to_string());
+    }
+    // Decode everything first so a damaged item writes nothing
+    let contents = item
+        .files
+        .iter()
+        .map(|file| file_content(file, sealer))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut skipped_tables = Vec::new();
+    for (file, content) in item.files.iter().zip(contents) {
+        match paths.target(item.kind, &file.path)? {
+            None => skipped_tables = restore_database(conn, &content)?,
+            Some(target) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                }
+                fs::write(&target, content)
+                    .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+            }
+        }
+    }
+    Ok(skipped_tables)
+}
+
+/// Restore the items of `backup`
+///
+/// Only items whose action is `Restore` are restored; even new items are
+/// skipped by default, so nothing is written that was not chosen.
+pub fn restore_backup(
+    backup: &AppBackup,
+    paths: &BackupPaths,
+    conn: &Connection,
+    passphrase: Option<&str>,
+    actions: &HashMap<String, RestoreAction>,
+) -> Result<RestoreReport, String> {
+    let sealer = open_sealer(backup, passphrase)?;
+    let mut report = RestoreReport::default();
+    for item in &backup.items {
+        let status = plan_item(item, paths).status;
+        let action = actions
+            .get(&item.id)
+            .copied()
+            .unwrap_or(RestoreAction::Skip);
+        let (outcome, message) = match action {
+            RestoreAction::Skip => (RestoreOutcome::Skipped, None),
+            RestoreAction::Restore => match restore_item(item, paths, conn, sealer.as_ref()) {
+                Ok(skipped_tables) => {
+                    report.skipped_tables.extend(skipped_tables);
+                    report.restart_required = true;
+                    (RestoreOutcome::Restored, None)
+                }
+                Err(e) => (RestoreOutcome::Failed, Some(e)),
+            },
+        };
+        report.items.push(RestoreItemResult {
+            id: item.id.clone(),
+            kind: item.kind,
+            status,
+            outcome,
+            message,
+        });
+    }
+    Ok(report)
+}
+
+/// Delete all but the newest `keep` safety backups in `dir`
+///
+/// Their names embed the time they were made, so they sort oldest first.
+fn prune_safety_backups(dir: &Path, keep: usize) -> Result<(), String> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to list {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(SAFETY_BACKUP_PREFIX) && name.ends_with(".cwbackup")
+                })
+        })
+        .collect();
+    backups.sort();
+    let stale = backups.len().saturating_sub(keep);
+    for path in &backups[..stale] {
+        fs::remove_file(path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub path: String,
+    pub created_at: i64,
+    pub encrypted: bool,
+    pub items: Vec<String>,
+    /// Size of the archive file in bytes
+    pub size: u64,
+}
+
+/// Back up the app's data to `path` as the running app has it
+fn backup_to(
+    app: &AppHandle,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<BackupSummary, String> {
+    let database = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        snapshot_database(&conn)?
+    };
+    let backup = build_backup(
+        &BackupPaths::current()?,
+        Some(database),
+        passphrase,
+        env!("CARGO_PKG_VERSION"),
+    )?;
+    write_archive(&backup, path)?;
+    Ok(BackupSummary {
+        path: path.to_string_lossy().to_string(),
+        created_at: backup.created_at,
+        encrypted: backup.encryption.is_some(),
+        items: backup.items.iter().map(|item| item.id.clone()).collect(),
+        size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Back up providers, the database, Claude Code's settings and the checkpoint
+/// index to one archive; with a passphrase the secrets in it are encrypted
+#[tauri::command]
+pub async fn backup_app_data(
+    app: AppHandle,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<BackupSummary, String> {
+    app_lock::require_unlocked(&app, ProtectedAction::ExportStations).await?;
+    let passphrase = passphrase.filter(|passphrase| !passphrase.is_empty());
+    if passphrase
+        .as_ref()
+        .is_some_and(|passphrase| passphrase.chars().count() < MIN_PASSPHRASE_LEN)
+    {
+        return Err(format!(
+            "The passphrase needs at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let summary = backup_to(&app, Path::new(&path), passphrase.as_deref())?;
+    log::info!(
+        "Backed up app data to {} ({} items)",
+        summary.path,
+        summary.items.len()
+    );
+    Ok(summary)
+}
+
+/// What restoring a backup would do, item by item
+#[tauri::command]
+pub async fn preview_app_restore(path: String) -> Result<RestorePlan, String> {
+    let backup = read_archive(Path::new(&path))?;
+    Ok(plan_restore(&backup, &BackupPaths::current()?))
+}
+
+/// Restore a backup, after backing up the current data next to the database
+///
+/// Only the item ids `actions` marks `Restore` are restored. When the report
+/// says `restart_required`, the window restarts the app (`restart_app`)
+/// before going on, as settings and caches still hold the old data.
+#[tauri::command]
+pub async fn restore_app_data(
+    app: AppHandle,
+    path: String,
+    passphrase: Option<String>,
+    actions: Option<HashMap<String, RestoreAction>>,
+) -> Result<RestoreReport, String> {
+    app_lock::require_unlocked(&app, ProtectedAction::SwitchConfig).await?;
+    let backup = read_archive(Path::new(&path))?;
+    // Check the passphrase before making the safety backup, which is sealed
+    // with it so the restore leaves no secrets readable that weren't before
+    let safety_passphrase = open_sealer(&backup, passphrase.as_deref())?.and(passphrase.as_deref());
+
+    let safety_dir = crate::portable::data_dir()
+        .ok_or("Failed to get app data directory")?
+        .join(SAFETY_BACKUPS_DIR);
+    let safety_path = safety_dir.join(format!(
+        "{}{}.cwbackup",
+        SAFETY_BACKUP_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let safety = backup_to(&app, &safety_path, safety_passphrase)?;
+    if let Err(e) = prune_safety_backups(&safety_dir, MAX_SAFETY_BACKUPS) {
+        log::warn!("Failed to prune safety backups: {}", e);
+    }
+
+    let mut report = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        restore_backup(
+            &backup,
+            &BackupPaths::current()?,
+            &conn,
+            passphrase.as_deref(),
+            &actions.unwrap_or_default(),
+        )?
+    };
+    report.safety_backup = Some(safety.path);
+    if report.restart_required {
+        crate::tray::refresh_menu(&app);
+    }
+    log::info!(
+        "Restored app data from {}: {} of {} items",
+        path,
+        report
+            .items
+            .iter()
+            .filter(|item| item.outcome == RestoreOutcome::Restored)
+            .count(),
+        report.items.len()
+    );
+    let _ = app.emit("app-data-restored", &report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(rows: &[(&str, &str)]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        for (key, value) in rows {
+            conn.execute(
+                "INSERT INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn paths(root: &Path) -> BackupPaths {
+        BackupPaths {
+            providers_file: root.join(".claude").join("providers.json"),
+            claude_dir: root.join(".claude"),
+        }
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let source = tempfile::tempdir().unwrap();
+        let source_paths = paths(source.path());
+        let timeline = source_paths
+            .claude_dir
+            .join("projects/-work-app/.timelines/s1");
+        fs::create_dir_all(timeline.join("files")).unwrap();
+        fs::write(timeline.join("timeline.json"), "{}").unwrap();
+        fs::write(timeline.join("files").join("blob.json"), "{}").unwrap();
+        fs::write(&source_paths.providers_file, r#"[{"api_key":"sk-secret"}]"#).unwrap();
+        fs::write(
+            source_paths.claude_dir.join("settings.json"),
+            "{\"env\":{}}",
+        )
+        .unwrap();
+        let snapshot = snapshot_database(&database(&[("theme", "dark")])).unwrap();
+
+        let backup =
+            build_backup(&source_paths, Some(snapshot), Some("passphrase"), "1.0").unwrap();
+        let archive = source.path().join("app.cwbackup");
+        write_archive(&backup, &archive).unwrap();
+        let backup = read_archive(&archive).unwrap();
+        let ids: Vec<&str> = backup.items.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "providers",
+                "database",
+                "claude-settings",
+                "checkpoints:-work-app"
+            ]
+        );
+        assert_eq!(backup.items[3].files.len(), 1);
+        assert!(!backup.items[3].encrypted);
+        assert!(!serde_json::to_string(&backup)
+            .unwrap()
+            .contains("sk-secret"));
+
+        let target = tempfile::tempdir().unwrap();
+        let target_paths = paths(target.path());
+        fs::create_dir_all(&target_paths.claude_dir).unwrap();
+        fs::write(target_paths.claude_dir.join("settings.json"), "{}").unwrap();
+        let plan = plan_restore(&backup, &target_paths);
+        let statuses: Vec<RestoreStatus> = plan.items.iter().map(|item| item.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RestoreStatus::New,
+                RestoreStatus::Conflict,
+                RestoreStatus::Conflict,
+                RestoreStatus::New
+            ]
+        );
+
+        let conn = database(&[("theme", "light"), ("locale", "en")]);
+        // Nothing is restored unless chosen, new items included
+        let report = restore_backup(
+            &backup,
+            &target_paths,
+            &conn,
+            Some("passphrase"),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert!(report
+            .items
+            .iter()
+            .all(|item| item.outcome == RestoreOutcome::Skipped));
+        assert!(!report.restart_required);
+        assert!(!target_paths.providers_file.exists());
+
+        let actions = HashMap::from([
+            ("providers".to_string(), RestoreAction::Restore),
+            ("database".to_string(), RestoreAction::Restore),
+            ("checkpoints:-work-app".to_string(), RestoreAction::Restore),
+        ]);
+        assert!(restore_backup(
+            &backup,
+            &target_paths,
+            &conn,
+            Some("wrong passphrase"),
+            &actions
+        )
+        .is_err());
+        let report =
+            restore_backup(&backup, &target_paths, &conn, Some("passphrase"), &actions).unwrap();
+        let outcomes: Vec<RestoreOutcome> = report.items.iter().map(|item| item.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                RestoreOutcome::Restored,
+                RestoreOutcome::Restored,
+                RestoreOutcome::Skipped,
+                RestoreOutcome::Restored
+            ]
+        );
+        assert!(fs::read_to_string(&target_paths.providers_file)
+            .unwrap()
+            .contains("sk-secret"));
+        assert_eq!(
+            fs::read_to_string(target_paths.claude_dir.join("settings.json")).unwrap(),
+            "{}"
+        );
+        let rows: Vec<(String, String)> = conn
+            .prepare("SELECT key, value FROM app_settings")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(rows, vec![("theme".to_string(), "dark".to_string())]);
+
+        assert!(report.restart_required);
+
+        assert!(target_paths
+            .target(BackupItemKind::ClaudeSettings, "../outside")
+            .is_err());
+        // Only the files an item kind backs up are written
+        for (kind, path) in [
+            (BackupItemKind::ClaudeSettings, "hooks/pre.sh"),
+            (BackupItemKind::ClaudeSettings, "commands/deploy.md"),
+            (BackupItemKind::CheckpointIndex, "agents/reviewer.md"),
+            (
+                BackupItemKind::CheckpointIndex,
+                "projects/p/.timelines/s1/run.sh",
+            ),
+            (BackupItemKind::CheckpointIndex, "projects/p/notes.json"),
+        ] {
+            assert!(target_paths.target(kind, path).is_err(), "{}", path);
+        }
+        assert!(target_paths
+            .target(
+                BackupItemKind::CheckpointIndex,
+                "projects/p/.timelines/s1/timeline.json"
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_restore_skips_tables_without_shared_columns() {
+        let backup = Connection::open_in_memory().unwrap();
+        backup
+            .execute("CREATE TABLE renamed (old_column TEXT)", [])
+            .unwrap();
+        backup
+            .execute("INSERT INTO renamed (old_column) VALUES ('x')", [])
+            .unwrap();
+        let snapshot = snapshot_database(&backup).unwrap();
+
+        let conn = database(&[("theme", "dark")]);
+        conn.execute("CREATE TABLE renamed (new_column TEXT)", [])
+            .unwrap();
+        let skipped = restore_database(&conn, &snapshot).unwrap();
+        assert_eq!(skipped, vec!["renamed".to_string()]);
+    }
+
+    #[test]
+    fn test_prune_safety_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let names = [
+            "pre-restore-20250101-090000.cwbackup",
+            "pre-restore-20250102-090000.cwbackup",
+            "pre-restore-20250103-090000.cwbackup",
+            "manual.cwbackup",
+        ];
+        for name in names {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        prune_safety_backups(dir.path(), 2).unwrap();
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, [names[3], names[1], names[2]]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archives_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backups").join("app.cwbackup");
+        let backup = build_backup(&paths(dir.path()), None, None, "1.0.0").unwrap();
+        write_archive(&backup, &path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(read_archive(&path).is_ok());
+    }
+}
@@ -0,0 +1,421 @@
+//! App lock for shared machines
+//!
+//! When enabled, revealing secrets, exporting or sharing stations and
+//! switching configurations first need a passcode or the OS verification
+//! (Windows Hello, Touch ID or the administrator prompt, polkit). An unlock
+//! holds for a few minutes. Commands refused while locked emit
+//! `app-lock-required` so the window can ask for the passcode and retry.
+//!
+//! The checks sit in the shared paths rather than in single commands: every
+//! write of the Claude or provider config goes through the provider module's
+//! guard, and commands listing providers, stations and tokens mask their
+//! secrets while locked.
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::t;
+
+/// app_settings key storing the lock settings (JSON)
+pub const APP_LOCK_SETTINGS_KEY: &str = "app_lock_settings";
+
+/// app_settings key storing the passcode hash, kept apart from the settings
+/// the window reads
+const APP_LOCK_PASSCODE_KEY: &str = "app_lock_passcode";
+
+const PBKDF2_ROUNDS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const MIN_PASSCODE_LEN: usize = 4;
+
+/// Wrong passcodes allowed before unlocking pauses
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnlockMethod {
+    #[default]
+    Passcode,
+    /// Windows Hello, Touch ID or the administrator prompt, polkit
+    System,
+}
+
+/// What the lock guards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtectedAction {
+    RevealSecret,
+    ExportStations,
+    SwitchConfig,
+    /// Changing or turning off the lock itself; always guarded
+    ChangeLock,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppLockSettings {
+    pub enabled: bool,
+    pub method: UnlockMethod,
+    /// An unlock holds this long
+    pub relock_minutes: u64,
+    pub protect_secrets: bool,
+    pub protect_export: bool,
+    pub protect_switch: bool,
+}
+
+impl Default for AppLockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            method: UnlockMethod::default(),
+            relock_minutes: 5,
+            protect_secrets: true,
+            protect_export: true,
+            protect_switch: true,
+        }
+    }
+}
+
+impl AppLockSettings {
+    pub fn protects(&self, action: ProtectedAction) -> bool {
+        self.enabled
+            && match action {
+                ProtectedAction::RevealSecret => self.protect_secrets,
+                ProtectedAction::ExportStations => self.protect_export,
+                ProtectedAction::SwitchConfig => self.protect_switch,
+                ProtectedAction::ChangeLock => true,
+            }
+    }
+
+    fn relock_after(&self) -> Duration {
+        Duration::from_secs(self.relock_minutes * 60)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLockStatus {
+    pub settings: AppLockSettings,
+    pub has_passcode: bool,
+    /// Guarded actions need unlocking first
+    pub locked: bool,
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+    unlocked_at: Option<Instant>,
+    failed_attempts: u32,
+    retry_at: Option<Instant>,
+}
+
+impl LockState {
+    fn is_unlocked(&self, now: Instant, relock_after: Duration) -> bool {
+        self.unlocked_at
+            .is_some_and(|unlocked_at| now.duration_since(unlocked_at) < relock_after)
+    }
+
+    fn unlock(&mut self, now: Instant) {
+        self.unlocked_at = Some(now);
+        self.failed_attempts = 0;
+        self.retry_at = None;
+    }
+
+    /// Seconds until another passcode may be tried
+    fn wait_secs(&self, now: Instant) -> Option<u64> {
+        self.retry_at
+            .filter(|retry_at| *retry_at > now)
+            .map(|retry_at| (retry_at - now).as_secs().max(1))
+    }
+
+    fn fail(&mut self, now: Instant) {
+        self.failed_attempts += 1;
+        if self.failed_attempts >= MAX_ATTEMPTS {
+            self.failed_attempts = 0;
+            self.retry_at = Some(now + RETRY_DELAY);
+        }
+    }
+}
+
+/// When the app was last unlocked, and wrong passcodes since
+#[derive(Default)]
+pub struct AppLockState(Mutex<LockState>);
+
+/// `salt:hash`, both base64
+pub fn hash_passcode(passcode: &str) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    format!(
+        "{}:{}",
+        STANDARD.encode(salt),
+        STANDARD.encode(derive(passcode, &salt))
+    )
+}
+
+pub fn verify_passcode(passcode: &str, stored: &str) -> bool {
+    let Some((salt, hash)) = stored.split_once(':') else {
+        return false;
+    };
+    let (Ok(salt), Ok(hash)) = (STANDARD.decode(salt), STANDARD.decode(hash)) else {
+        return false;
+    };
+    let derived = derive(passcode, &salt);
+    // Compared in full so the time taken tells nothing
+    derived.len() == hash.len()
+        && derived
+            .iter()
+            .zip(&hash)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn derive(passcode: &str, salt: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passcode.as_bytes(), salt, PBKDF2_ROUNDS, &mut hash);
+    hash
+}
+
+pub fn load_app_lock_settings(conn: &Connection) -> AppLockSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![APP_LOCK_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn load_passcode(conn: &Connection) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![APP_LOCK_PASSCODE_KEY],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+fn lock_state(app: &AppHandle) -> Result<std::sync::MutexGuard<'_, LockState>, String> {
+    app.state::<AppLockState>()
+        .inner()
+        .0
+        .lock()
+        .map_err(|e| e.to_string())
+}
+
+/// Ask the OS to verify the user; blocks until the prompt closes
+async fn verify_with_system() -> Result<(), String> {
+    let reason = t!("app_lock.system_prompt");
+    tokio::task::spawn_blocking(move || crate::os_auth::require_user_verification(&reason))
+        .await
+        .map_err(|e| format!("Verification task failed: {}", e))?
+}
+
+/// Check the lock before a guarded action
+///
+/// Returns whether the lock guards `action`; it is then unlocked, after the
+/// OS verification when that is the method. Locked with a passcode, the
+/// action is refused and `app-lock-required` emitted.
+pub async fn require_unlocked(app: &AppHandle, action: ProtectedAction) -> Result<bool, String> {
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_app_lock_settings(&conn)
+    };
+    if !settings.protects(action) {
+        return Ok(false);
+    }
+    if lock_state(app)?.is_unlocked(Instant::now(), settings.relock_after()) {
+        return Ok(true);
+    }
+    match settings.method {
+        UnlockMethod::System => {
+            verify_with_system().await?;
+            lock_state(app)?.unlock(Instant::now());
+            Ok(true)
+        }
+        UnlockMethod::Passcode => {
+            let _ = app.emit("app-lock-required", action);
+            Err(t!("app_lock.locked"))
+        }
+    }
+}
+
+/// Whether commands should return secrets masked: the lock guards revealing
+/// them and is not unlocked. Never prompts; unlocking reveals them again.
+pub fn hides_secrets(app: &AppHandle) -> bool {
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let Ok(conn) = db.0.lock() else {
+            return true;
+        };
+        load_app_lock_settings(&conn)
+    };
+    settings.protects(ProtectedAction::RevealSecret)
+        && !lock_state(app)
+            .map(|state| state.is_unlocked(Instant::now(), settings.relock_after()))
+            .unwrap_or(false)
+}
+
+#[tauri::command]
+pub async fn get_app_lock_status(app: AppHandle) -> Result<AppLockStatus, String> {
+    let (settings, has_passcode) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            load_app_lock_settings(&conn),
+            load_passcode(&conn).is_some(),
+        )
+    };
+    let locked =
+        settings.enabled && !lock_state(&app)?.is_unlocked(Instant::now(), settings.relock_after());
+    Ok(AppLockStatus {
+        settings,
+        has_passcode,
+        locked,
+    })
+}
+
+/// Change the lock settings; `passcode` sets a new passcode
+///
+/// Needs unlocking first while the lock is on. Turning on the OS
+/// verification prompts for it once, so a machine without it cannot lock
+/// the user out.
+#[tauri::command]
+pub async fn set_app_lock_settings(
+    app: AppHandle,
+    settings: AppLockSettings,
+    passcode: Option<String>,
+) -> Result<AppLockStatus, String> {
+    require_unlocked(&app, ProtectedAction::ChangeLock).await?;
+    if !(1..=24 * 60).contains(&settings.relock_minutes) {
+        return Err("Relock after between 1 minute and 24 hours".to_string());
+    }
+    let passcode = passcode.filter(|passcode| !passcode.is_empty());
+    if passcode
+        .as_ref()
+        .is_some_and(|passcode| passcode.chars().count() < MIN_PASSCODE_LEN)
+    {
+        return Err(t!("app_lock.passcode_too_short", "min" => &MIN_PASSCODE_LEN.to_string()));
+    }
+    if settings.enabled && settings.method == UnlockMethod::System {
+        verify_with_system().await?;
+    }
+
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let has_passcode = passcode.is_some() || load_passcode(&conn).is_some();
+        if settings.enabled && settings.method == UnlockMethod::Passcode && !has_passcode {
+            return Err(t!("app_lock.passcode_required"));
+        }
+        if let Some(passcode) = &passcode {
+            conn.execute(
+                "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+                params![APP_LOCK_PASSCODE_KEY, hash_passcode(passcode)],
+            )
+            .map_err(|e| format!("Failed to save the passcode: {}", e))?;
+        }
+        let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![APP_LOCK_SETTINGS_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save app lock settings: {}", e))?;
+    }
+    // Whoever just set the lock stays unlocked for now
+    lock_state(&app)?.unlock(Instant::now());
+    log::info!(
+        "App lock {} ({:?})",
+        if settings.enabled { "on" } else { "off" },
+        settings.method
+    );
+    get_app_lock_status(app).await
+}
+
+/// Unlock with the passcode, or the OS verification when that is the method
+#[tauri::command]
+pub async fn unlock_app(app: AppHandle, passcode: Option<String>) -> Result<(), String> {
+    let (settings, stored) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (load_app_lock_settings(&conn), load_passcode(&conn))
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    if settings.method == UnlockMethod::System {
+        verify_with_system().await?;
+        lock_state(&app)?.unlock(Instant::now());
+        return Ok(());
+    }
+
+    if let Some(secs) = lock_state(&app)?.wait_secs(Instant::now()) {
+        return Err(t!("app_lock.too_many_attempts", "seconds" => &secs.to_string()));
+    }
+    let passcode = passcode.unwrap_or_default();
+    let stored = stored.ok_or_else(|| t!("app_lock.passcode_required"))?;
+    let valid = tokio::task::spawn_blocking(move || verify_passcode(&passcode, &stored))
+        .await
+        .map_err(|e| format!("Verification task failed: {}", e))?;
+    let mut state = lock_state(&app)?;
+    if valid {
+        state.unlock(Instant::now());
+        Ok(())
+    } else {
+        log::warn!("Wrong app lock passcode");
+        state.fail(Instant::now());
+        Err(t!("app_lock.wrong_passcode"))
+    }
+}
+
+/// Lock again before the unlock runs out
+#[tauri::command]
+pub async fn lock_app(state: State<'_, AppLockState>) -> Result<(), String> {
+    state.0.lock().map_err(|e| e.to_string())?.unlocked_at = None;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passcode_and_lock_state() {
+        let stored = hash_passcode("2468");
+        assert!(verify_passcode("2468", &stored));
+        assert!(!verify_passcode("2469", &stored));
+        assert!(!verify_passcode("2468", "not a hash"));
+        assert_ne!(stored, hash_passcode("2468"));
+
+        let settings = AppLockSettings {
+            enabled: true,
+            protect_export: false,
+            ..AppLockSettings::default()
+        };
+        assert!(settings.protects(ProtectedAction::RevealSecret));
+        assert!(!settings.protects(ProtectedAction::ExportStations));
+        assert!(!AppLockSettings::default().protects(ProtectedAction::ChangeLock));
+
+        let now = Instant::now();
+        let mut state = LockState::default();
+        assert!(!state.is_unlocked(now, settings.relock_after()));
+        state.unlock(now);
+        assert!(state.is_unlocked(now + Duration::from_secs(299), settings.relock_after()));
+        assert!(!state.is_unlocked(now + Duration::from_secs(300), settings.relock_after()));
+
+        for _ in 0..MAX_ATTEMPTS - 1 {
+            state.fail(now);
+        }
+        assert_eq!(state.wait_secs(now), None);
+        state.fail(now);
+        assert_eq!(state.wait_secs(now), Some(60));
+        assert_eq!(state.wait_secs(now + RETRY_DELAY), None);
+    }
+}
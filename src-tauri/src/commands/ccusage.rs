@@ -0,0 +1,278 @@
+//! Usage export in the JSON shape of ccusage
+//!
+//! `ccusage daily --json` and `ccusage session --json` print per-day and
+//! per-session token and cost records with model breakdowns and totals.
+//! The same shape is produced here from the usage the workbench reads, so
+//! scripts written against ccusage keep working. Costs are in USD, as there.
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use super::usage::{get_all_usage_entries, local_date, UsageEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CcusageReport {
+    Daily,
+    Session,
+}
+
+impl std::str::FromStr for CcusageReport {
+    type Err = String;
+
+    fn from_str(report: &str) -> Result<Self, Self::Err> {
+        match report {
+            "daily" => Ok(Self::Daily),
+            "session" => Ok(Self::Session),
+            _ => Err(format!(
+                "Unknown report {:?}, expected daily or session",
+                report
+            )),
+        }
+    }
+}
+
+/// Token counts and cost, as ccusage names them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Totals {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    total_tokens: u64,
+    total_cost: f64,
+}
+
+impl Totals {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.input_tokens += entry.input_tokens;
+        self.output_tokens += entry.output_tokens;
+        self.cache_creation_tokens += entry.cache_creation_tokens;
+        self.cache_read_tokens += entry.cache_read_tokens;
+        self.total_tokens += entry.input_tokens
+            + entry.output_tokens
+            + entry.cache_creation_tokens
+            + entry.cache_read_tokens;
+        self.total_cost += entry.cost;
+    }
+}
+
+/// A day or session with its usage per model
+#[derive(Debug, Default)]
+struct Group {
+    totals: Totals,
+    models: BTreeMap<String, Totals>,
+    project_path: String,
+    last_activity: Option<NaiveDate>,
+}
+
+impl Group {
+    fn add(&mut self, entry: &UsageEntry, date: NaiveDate) {
+        self.totals.add(entry);
+        self.models
+            .entry(entry.model.clone())
+            .or_default()
+            .add(entry);
+        self.project_path = entry.project_path.clone();
+        self.last_activity = self.last_activity.max(Some(date));
+    }
+
+    /// ccusage's record fields other than the day or session id
+    fn fields(&self) -> serde_json::Map<String, Value> {
+        let Value::Object(mut fields) = json!(self.totals) else {
+            unreachable!("totals serialize to an object");
+        };
+        let models_used: BTreeSet<&String> = self.models.keys().collect();
+        fields.insert("modelsUsed".to_string(), json!(models_used));
+        let breakdowns: Vec<Value> = self
+            .models
+            .iter()
+            .map(|(model, totals)| {
+                json!({
+                    "modelName": model,
+                    "inputTokens": totals.input_tokens,
+                    "outputTokens": totals.output_tokens,
+                    "cacheCreationTokens": totals.cache_creation_tokens,
+                    "cacheReadTokens": totals.cache_read_tokens,
+                    "cost": totals.total_cost,
+                })
+            })
+            .collect();
+        fields.insert("modelBreakdowns".to_string(), json!(breakdowns));
+        fields
+    }
+}
+
+/// A date as ccusage's `--since`/`--until` take it (YYYYMMDD) or YYYY-MM-DD
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+        .map_err(|_| format!("Invalid date {:?}, expected YYYYMMDD", date))
+}
+
+/// The report for entries between `since` and `until` (local days, inclusive)
+pub fn build_report(
+    entries: &[UsageEntry],
+    report: CcusageReport,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Value {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+    let mut totals = Totals::default();
+    for entry in entries {
+        let Some(date) = local_date(&entry.timestamp) else {
+            continue;
+        };
+        if since.is_some_and(|since| date < since) || until.is_some_and(|until| date > until) {
+            continue;
+        }
+        let key = match report {
+            CcusageReport::Daily => date.format("%Y-%m-%d").to_string(),
+            CcusageReport::Session => entry.session_id.clone(),
+        };
+        groups.entry(key).or_default().add(entry, date);
+        totals.add(entry);
+    }
+
+    match report {
+        CcusageReport::Daily => {
+            let daily: Vec<Value> = groups
+                .iter()
+                .map(|(date, group)| {
+                    let mut fields = group.fields();
+                    fields.insert("date".to_string(), json!(date));
+                    Value::Object(fields)
+                })
+                .collect();
+            json!({ "daily": daily, "totals": totals })
+        }
+        CcusageReport::Session => {
+            let mut sessions: Vec<(&String, &Group)> = groups.iter().collect();
+            // Most recent last, as ccusage lists them
+            sessions.sort_by_key(|(id, group)| (group.last_activity, *id));
+            let sessions: Vec<Value> = sessions
+                .into_iter()
+                .map(|(session_id, group)| {
+                    let mut fields = group.fields();
+                    fields.insert("sessionId".to_string(), json!(session_id));
+                    fields.insert("projectPath".to_string(), json!(group.project_path));
+                    let last_activity = group
+                        .last_activity
+                        .map(|date| date.format("%Y-%m-%d").to_string());
+                    fields.insert("lastActivity".to_string(), json!(last_activity));
+                    Value::Object(fields)
+                })
+                .collect();
+            json!({ "sessions": sessions, "totals": totals })
+        }
+    }
+}
+
+/// The report as pretty JSON, from the usage under `~/.claude`
+pub fn export_json(
+    report: CcusageReport,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<String, String> {
+    let since = since.map(parse_date).transpose()?;
+    let until = until.map(parse_date).transpose()?;
+    let claude_path = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+    let entries = get_all_usage_entries(&claude_path);
+    serde_json::to_string_pretty(&build_report(&entries, report, since, until))
+        .map_err(|e| e.to_string())
+}
+
+/// Usage as `ccusage daily --json` or `ccusage session --json` prints it;
+/// also written to `output_path` when given
+#[tauri::command]
+pub async fn export_ccusage_json(
+    report: CcusageReport,
+    since: Option<String>,
+    until: Option<String>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let json = tokio::task::spawn_blocking(move || {
+        export_json(report, since.as_deref(), until.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to export usage: {}", e))??;
+    if let Some(path) = output_path {
+        std::fs::write(Path::new(&path), &json)
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    fn entry(date: &str, session_id: &str, model: &str, output_tokens: u64) -> UsageEntry {
+        let timestamp = parse_date(date)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .to_rfc3339();
+        UsageEntry {
+            timestamp,
+            model: model.to_string(),
+            input_tokens: 10,
+            output_tokens,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 5,
+            cost: output_tokens as f64 / 100.0,
+            session_id: session_id.to_string(),
+            project_path: "/work/app".to_string(),
+            api_base_url: "https://api.anthropic.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_report() {
+        let entries = vec![
+            entry("20250601", "a", "claude-sonnet-4", 100),
+            entry("20250601", "a", "claude-opus-4", 50),
+            entry("20250602", "b", "claude-sonnet-4", 200),
+            entry("20250520", "c", "claude-sonnet-4", 300),
+        ];
+        let since = parse_date("20250601").ok();
+
+        let daily = build_report(&entries, CcusageReport::Daily, since, None);
+        assert_eq!(daily["daily"].as_array().unwrap().len(), 2);
+        let day = &daily["daily"][0];
+        assert_eq!(day["date"], "2025-06-01");
+        assert_eq!(day["totalTokens"], 180);
+        assert_eq!(
+            day["modelsUsed"],
+            json!(["claude-opus-4", "claude-sonnet-4"])
+        );
+        assert_eq!(day["modelBreakdowns"][1]["outputTokens"], 100);
+        assert_eq!(daily["totals"]["outputTokens"], 350);
+        assert_eq!(daily["totals"]["totalCost"], 3.5);
+
+        let sessions = build_report(
+            &entries,
+            CcusageReport::Session,
+            None,
+            parse_date("2025-06-01").ok(),
+        );
+        let ids: Vec<&str> = sessions["sessions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|session| session["sessionId"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["c", "a"]);
+        assert_eq!(sessions["sessions"][1]["lastActivity"], "2025-06-01");
+        assert_eq!(sessions["sessions"][1]["projectPath"], "/work/app");
+        assert!(parse_date("June 1").is_err());
+    }
+}
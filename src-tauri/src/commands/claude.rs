@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
@@ -9,26 +10,14 @@ use std::sync::Arc;
 use std::time::SystemTime;
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_shell::ShellExt;
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use tokio::process::Command;
+
+use super::notifications::{notify_session_event, SessionEvent};
 
 // Windows-specific imports
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
-/// Global state to track current Claude process
-pub struct ClaudeProcessState {
-    pub current_process: Arc<Mutex<Option<Child>>>,
-}
-
-impl Default for ClaudeProcessState {
-    fn default() -> Self {
-        Self {
-            current_process: Arc::new(Mutex::new(None)),
-        }
-    }
-}
-
 /// Represents a project in the ~/.claude/projects directory
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -138,6 +127,37 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
     crate::claude_binary::find_claude_binary(app_handle)
 }
 
+/// Finds the claude binary for a project, honouring its pinned CLI version
+///
+/// A binary set on the active provider takes precedence over both the
+/// version pin and global discovery. Commands run outside a project, like
+/// `claude mcp`, pass no project and skip the pin.
+pub(crate) fn find_claude_binary_for_project(
+    app_handle: &AppHandle,
+    project_path: Option<&str>,
+) -> Result<String, String> {
+    if let Some((Some(path), _)) = crate::commands::provider::current_provider_launch_overrides() {
+        log::info!(
+            "Using Claude binary configured on the active provider: {}",
+            path
+        );
+        return Ok(path);
+    }
+    match project_path {
+        Some(project_path) => {
+            crate::claude_binary::find_claude_binary_for_project(app_handle, project_path)
+        }
+        None => find_claude_binary(app_handle),
+    }
+}
+
+/// Extra arguments configured on the active provider
+fn provider_default_args() -> Vec<String> {
+    crate::commands::provider::current_provider_launch_overrides()
+        .map(|(_, args)| args)
+        .unwrap_or_default()
+}
+
 /// Gets the path to the ~/.claude directory
 fn get_claude_dir() -> Result<PathBuf> {
     let claude_dir = dirs::home_dir()
@@ -184,7 +204,7 @@ fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, Strin
 /// Decodes a project directory name back to its original path
 /// The directory names in ~/.claude/projects are encoded paths
 /// DEPRECATED: Use get_project_path_from_sessions instead when possible
-fn decode_project_path(encoded: &str) -> String {
+pub(crate) fn decode_project_path(encoded: &str) -> String {
     // This is a fallback - the encoding isn't reversible when paths contain hyphens
     // For example: -Users-mufeedvh-dev-jsonl-viewer could be /Users/mufeedvh/dev/jsonl-viewer
     // or /Users/mufeedvh/dev/jsonl/viewer
@@ -322,13 +342,19 @@ fn create_command_with_env(program: &str) -> Command {
     tokio_cmd
 }
 
-/// Helper function to spawn Claude process and handle streaming
+/// Build the command that runs Claude
 /// Enhanced for Windows compatibility with router support
-fn create_system_command(
+pub(crate) async fn create_system_command(
     claude_path: &str,
     args: Vec<String>,
     project_path: &str,
 ) -> Result<Command, String> {
+    let mut args = args;
+    args.extend(provider_default_args());
+    // Older CLI builds lack some flags; drop optional ones, fail on required ones
+    let args = crate::claude_binary::probe_capabilities(claude_path)
+        .await
+        .adapt_args(args)?;
     create_windows_command(claude_path, args, project_path)
 }
 
@@ -338,10 +364,24 @@ fn create_windows_command(
     args: Vec<String>,
     project_path: &str,
 ) -> Result<Command, String> {
-    let mut cmd = create_command_with_env(claude_path);
 
-    // Add all arguments
-    cmd.args(&args);
+    let mut cmd = match crate::claude_binary::parse_wsl_path(claude_path) {
+        // Claude inside WSL runs through wsl.exe in the translated project path
+        Some(distro) => {
+            let (program, wsl_args) =
+                crate::claude_binary::wsl_launch_args(distro, &args, Some(project_path));
+            let mut cmd = create_command_with_env(&program);
+            cmd.args(&wsl_args);
+            cmd
+        }
+        None => {
+            let mut cmd = create_command_with_env(claude_path);
+
+            // Add all arguments
+            cmd.args(&args);
+            cmd
+        }
+    };
 
     // Set working directory
     cmd.current_dir(project_path);
@@ -357,6 +397,9 @@ fn create_windows_command(
         cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
     }
 
+    // Make the session's child processes killable as a unit
+    crate::process::tree::configure_command(&mut cmd);
+
     Ok(cmd)
 }
 
@@ -860,8 +903,7 @@ pub async fn check_claude_version(app: AppHandle) -> Result<ClaudeVersionStatus,
     debug!("Claude path: {}", claude_path);
 
     // For system installations, try to check version
-    let mut cmd = std::process::Command::new(&claude_path);
-    cmd.arg("--version");
+    let mut cmd = crate::claude_binary::version_command(&claude_path);
 
     // On Windows, ensure the command runs without creating a console window
     #[cfg(target_os = "windows")]
@@ -923,7 +965,8 @@ pub async fn save_system_prompt(content: String) -> Result<String, String> {
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
     let claude_md_path = claude_dir.join("CLAUDE.md");
 
-    fs::write(&claude_md_path, content).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
+    super::memory::save_with_backup(&claude_md_path, &content)
+        .map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
 
     Ok("System prompt saved successfully".to_string())
 }
@@ -1110,13 +1153,9 @@ pub async fn save_claude_md_file(file_path: String, content: String) -> Result<S
 
     let path = PathBuf::from(&file_path);
 
-    // Ensure the parent directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-    }
-
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+    // Creates the parent directory and keeps the previous version as a backup
+    super::memory::save_with_backup(&path, &content)
+        .map_err(|e| format!("Failed to write file: {}", e))?;
 
     Ok("File saved successfully".to_string())
 }
@@ -1169,6 +1208,31 @@ pub async fn execute_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    env: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let env = env.unwrap_or_default();
+    super::usage_quotas::ensure_launch_allowed(&app, &model).await?;
+    let Some(slot) = reserve_slot_or_queue(
+        &app,
+        crate::process::QueuedRunKind::Execute,
+        &project_path,
+        &prompt,
+        &model,
+        &env,
+    )?
+    else {
+        return Ok(());
+    };
+    start_execute_claude_code(app, project_path, prompt, model, env, slot).await
+}
+
+async fn start_execute_claude_code(
+    app: AppHandle,
+    project_path: String,
+    prompt: String,
+    model: String,
+    env: HashMap<String, String>,
+    slot: crate::process::SessionSlot,
 ) -> Result<(), String> {
     log::info!(
         "Starting Claude Code session with project context resume in: {} with model: {}",
@@ -1176,7 +1240,7 @@ pub async fn execute_claude_code(
         model
     );
 
-    let claude_path = find_claude_binary(&app)?;
+    let claude_path = find_claude_binary_for_project(&app, Some(&project_path))?;
 
     // Escape prompt for command line - handle multiline content properly
     let escaped_prompt = escape_prompt_for_cli(&prompt);
@@ -1192,8 +1256,8 @@ pub async fn execute_claude_code(
     ];
 
     // Create command
-    let cmd = create_system_command(&claude_path, args, &project_path)?;
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let cmd = create_system_command(&claude_path, args, &project_path).await?;
+    spawn_claude_process(app, cmd, prompt, model, project_path, env, None, slot).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
@@ -1204,6 +1268,31 @@ pub async fn continue_claude_code(
     project_path: String,
     prompt: String,
     model: String,
+    env: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let env = env.unwrap_or_default();
+    super::usage_quotas::ensure_launch_allowed(&app, &model).await?;
+    let Some(slot) = reserve_slot_or_queue(
+        &app,
+        crate::process::QueuedRunKind::Continue,
+        &project_path,
+        &prompt,
+        &model,
+        &env,
+    )?
+    else {
+        return Ok(());
+    };
+    start_continue_claude_code(app, project_path, prompt, model, env, slot).await
+}
+
+async fn start_continue_claude_code(
+    app: AppHandle,
+    project_path: String,
+    prompt: String,
+    model: String,
+    env: HashMap<String, String>,
+    slot: crate::process::SessionSlot,
 ) -> Result<(), String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
@@ -1211,7 +1300,7 @@ pub async fn continue_claude_code(
         model
     );
 
-    let claude_path = find_claude_binary(&app)?;
+    let claude_path = find_claude_binary_for_project(&app, Some(&project_path))?;
 
     // Escape prompt for command line - handle multiline content properly
     let escaped_prompt = escape_prompt_for_cli(&prompt);
@@ -1228,8 +1317,8 @@ pub async fn continue_claude_code(
     ];
 
     // Create command
-    let cmd = create_system_command(&claude_path, args, &project_path)?;
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let cmd = create_system_command(&claude_path, args, &project_path).await?;
+    spawn_claude_process(app, cmd, prompt, model, project_path, env, None, slot).await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
@@ -1241,6 +1330,34 @@ pub async fn resume_claude_code(
     session_id: String,
     prompt: String,
     model: String,
+    env: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let env = env.unwrap_or_default();
+    super::usage_quotas::ensure_launch_allowed(&app, &model).await?;
+    let Some(slot) = reserve_slot_or_queue(
+        &app,
+        crate::process::QueuedRunKind::Resume {
+            session_id: session_id.clone(),
+        },
+        &project_path,
+        &prompt,
+        &model,
+        &env,
+    )?
+    else {
+        return Ok(());
+    };
+    start_resume_claude_code(app, project_path, session_id, prompt, model, env, slot).await
+}
+
+async fn start_resume_claude_code(
+    app: AppHandle,
+    project_path: String,
+    session_id: String,
+    prompt: String,
+    model: String,
+    env: HashMap<String, String>,
+    slot: crate::process::SessionSlot,
 ) -> Result<(), String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
@@ -1263,7 +1380,7 @@ pub async fn resume_claude_code(
     log::info!("Expected session file directory: {}", session_dir);
     log::info!("Session ID to resume: {}", session_id);
 
-    let claude_path = find_claude_binary(&app)?;
+    let claude_path = find_claude_binary_for_project(&app, Some(&project_path))?;
 
     // Escape prompt for command line - handle multiline content properly
     let escaped_prompt = escape_prompt_for_cli(&prompt);
@@ -1281,10 +1398,10 @@ pub async fn resume_claude_code(
         "--dangerously-skip-permissions".to_string(),
     ];
 
-    log::info!("Resume command: claude {}", args.join(" "));
+    log::info!("Resume command: claude --resume {} --model {}", session_id, model);
 
     // Create command
-    let cmd = create_system_command(&claude_path, args, &project_path)?;
+    let cmd = create_system_command(&claude_path, args, &project_path).await?;
 
     // Try to spawn the process - if it fails, fall back to continue mode
     match spawn_claude_process(
@@ -1293,6 +1410,9 @@ pub async fn resume_claude_code(
         prompt.clone(),
         model.clone(),
         project_path.clone(),
+        env.clone(),
+        None,
+        slot,
     )
     .await
     {
@@ -1302,10 +1422,238 @@ pub async fn resume_claude_code(
                 "Resume failed: {}, trying continue mode as fallback",
                 resume_error
             );
+            // The failed spawn released its slot, so the fallback needs a new one
+            let registry = app.state::<crate::process::ProcessRegistryState>();
+            let Some(slot) = registry.0.try_reserve_session_slot(&project_path) else {
+                return Err(resume_error);
+            };
             // Fallback to continue mode
-            continue_claude_code(app, project_path, prompt, model).await
+            start_continue_claude_code(app, project_path, prompt, model, env, slot).await
+        }
+    }
+}
+
+/// Error of launches that cannot wait in the run queue
+const SESSION_LIMIT_REACHED: &str =
+    "The concurrency limit for Claude sessions is reached; wait for a session to finish";
+
+/// Reserve a session slot for the run, or queue it and return `None` when the
+/// concurrency limits are reached
+///
+/// Runs also queue behind earlier queued runs for the same project so they start in order.
+fn reserve_slot_or_queue(
+    app: &AppHandle,
+    kind: crate::process::QueuedRunKind,
+    project_path: &str,
+    prompt: &str,
+    model: &str,
+    env: &HashMap<String, String>,
+) -> Result<Option<crate::process::SessionSlot>, String> {
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    if !registry.0.run_queue().has_queued_for_project(project_path) {
+        if let Some(slot) = registry.0.try_reserve_session_slot(project_path) {
+            return Ok(Some(slot));
+        }
+    }
+
+    let run = crate::process::QueuedRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        project_path: project_path.to_string(),
+        prompt: prompt.to_string(),
+        model: model.to_string(),
+        env: env.clone(),
+        queued_at: chrono::Utc::now(),
+    };
+    let position = registry.0.run_queue().push(run.clone())?;
+    log::info!(
+        "Concurrency limit reached, queued run {} for {} at position {}",
+        run.id,
+        project_path,
+        position
+    );
+
+    let _ = app.emit("claude-run-queued", &run);
+    crate::commands::processes::emit_run_queue_changed(app, &registry.0);
+    Ok(None)
+}
+
+/// Start queued runs while session slots are available
+///
+/// Returns a boxed future because it is spawned from within `spawn_claude_process`.
+pub(crate) fn start_queued_runs(
+    app: AppHandle,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let registry = app.state::<crate::process::ProcessRegistryState>().0.clone();
+        while let Some((run, slot)) = registry.take_next_queued_run() {
+            crate::commands::processes::emit_run_queue_changed(&app, &registry);
+            log::info!("Starting queued run {} for {}", run.id, run.project_path);
+
+            // The quota may have been used up while the run waited
+            let result = match super::usage_quotas::ensure_launch_allowed(&app, &run.model).await {
+                Err(e) => Err(e),
+                Ok(()) => match run.kind.clone() {
+                    crate::process::QueuedRunKind::Execute => {
+                        start_execute_claude_code(
+                            app.clone(),
+                            run.project_path.clone(),
+                            run.prompt.clone(),
+                            run.model.clone(),
+                            run.env.clone(),
+                            slot,
+                        )
+                        .await
+                    }
+                    crate::process::QueuedRunKind::Continue => {
+                        start_continue_claude_code(
+                            app.clone(),
+                            run.project_path.clone(),
+                            run.prompt.clone(),
+                            run.model.clone(),
+                            run.env.clone(),
+                            slot,
+                        )
+                        .await
+                    }
+                    crate::process::QueuedRunKind::Resume { session_id } => {
+                        start_resume_claude_code(
+                            app.clone(),
+                            run.project_path.clone(),
+                            session_id,
+                            run.prompt.clone(),
+                            run.model.clone(),
+                            run.env.clone(),
+                            slot,
+                        )
+                        .await
+                    }
+                },
+            };
+
+            let _ = app.emit(
+                "claude-run-dequeued",
+                serde_json::json!({
+                    "id": run.id,
+                    "success": result.is_ok(),
+                    "error": result.err(),
+                }),
+            );
+        }
+    })
+}
+
+/// Identifiers of an interactive (PTY-backed) Claude session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractiveSession {
+    pub run_id: i64,
+    pub session_id: String,
+}
+
+/// Start Claude interactively inside a pseudo-terminal for the terminal view
+///
+/// Output streams as `pty-output:{run_id}` and the exit as `pty-exit:{run_id}`.
+/// A new session gets a fixed `--session-id` so it can be resumed later.
+#[tauri::command]
+pub async fn start_interactive_claude_session(
+    app: AppHandle,
+    ptys: tauri::State<'_, Arc<crate::process::pty::PtySessions>>,
+    registry: tauri::State<'_, crate::process::ProcessRegistryState>,
+    project_path: String,
+    model: Option<String>,
+    resume_session_id: Option<String>,
+    rows: u16,
+    cols: u16,
+    env: Option<HashMap<String, String>>,
+) -> Result<InteractiveSession, String> {
+    super::usage_quotas::ensure_launch_allowed(&app, model.as_deref().unwrap_or_default())
+        .await?;
+    // Interactive sessions count against the concurrency limits but can't wait in the queue
+    let slot = registry
+        .0
+        .try_reserve_session_slot(&project_path)
+        .ok_or_else(|| SESSION_LIMIT_REACHED.to_string())?;
+    let claude_path = find_claude_binary_for_project(&app, Some(&project_path))?;
+    let env = env.unwrap_or_default();
+
+    let (session_id, mut args) = match resume_session_id {
+        Some(session_id) => (session_id.clone(), vec!["--resume".to_string(), session_id]),
+        None => {
+            let session_id = uuid::Uuid::new_v4().to_string();
+            (session_id.clone(), vec!["--session-id".to_string(), session_id])
         }
+    };
+    if let Some(model) = &model {
+        args.push("--model".to_string());
+        args.push(model.clone());
     }
+    // Overrides go on the PTY's environment, never on the command line
+    args.extend(provider_default_args());
+    let args = crate::claude_binary::probe_capabilities(&claude_path)
+        .await
+        .adapt_args(args)?;
+
+    let (program, args) = match crate::claude_binary::parse_wsl_path(&claude_path) {
+        Some(distro) => crate::claude_binary::wsl_launch_args(distro, &args, Some(&project_path)),
+        None => (claude_path, args),
+    };
+
+    let run_id = ptys.inner().spawn(
+        app.clone(),
+        registry.0.clone(),
+        crate::process::pty::PtyLaunch {
+            program,
+            args,
+            cwd: project_path,
+            env,
+            rows,
+            cols,
+            session_id: session_id.clone(),
+            task: "Interactive session".to_string(),
+            model: model.unwrap_or_default(),
+        },
+        slot,
+    )?;
+    Ok(InteractiveSession { run_id, session_id })
+}
+
+/// Send keystrokes or pasted text to an interactive session
+#[tauri::command]
+pub async fn write_claude_pty(
+    ptys: tauri::State<'_, Arc<crate::process::pty::PtySessions>>,
+    run_id: i64,
+    data: String,
+) -> Result<(), String> {
+    ptys.write(run_id, &data)
+}
+
+/// Resize an interactive session's terminal
+#[tauri::command]
+pub async fn resize_claude_pty(
+    ptys: tauri::State<'_, Arc<crate::process::pty::PtySessions>>,
+    run_id: i64,
+    rows: u16,
+    cols: u16,
+) -> Result<(), String> {
+    ptys.resize(run_id, rows, cols)
+}
+
+/// Raw terminal output captured so far, used to repaint a re-opened view
+#[tauri::command]
+pub async fn get_claude_pty_scrollback(
+    ptys: tauri::State<'_, Arc<crate::process::pty::PtySessions>>,
+    run_id: i64,
+) -> Result<String, String> {
+    ptys.scrollback(run_id)
+}
+
+/// Terminate an interactive session
+#[tauri::command]
+pub async fn close_claude_pty(
+    ptys: tauri::State<'_, Arc<crate::process::pty::PtySessions>>,
+    run_id: i64,
+) -> Result<(), String> {
+    ptys.kill(run_id)
 }
 
 /// Cancel the currently running Claude Code execution
@@ -1357,65 +1705,17 @@ pub async fn cancel_claude_execution(
         }
     }
 
-    // Method 2: Try the legacy approach via ClaudeProcessState
-    if !killed {
-        let claude_state = app.state::<ClaudeProcessState>();
-        let mut current_process = claude_state.current_process.lock().await;
-
-        if let Some(mut child) = current_process.take() {
-            // Try to get the PID before killing
-            let pid = child.id();
-            log::info!(
-                "Attempting to kill Claude process via ClaudeProcessState with PID: {:?}",
-                pid
-            );
-
-            // Kill the process
-            match child.kill().await {
-                Ok(_) => {
-                    log::info!("Successfully killed Claude process via ClaudeProcessState");
-                    killed = true;
-                }
-                Err(e) => {
-                    log::error!(
-                        "Failed to kill Claude process via ClaudeProcessState: {}",
-                        e
-                    );
-
-                    // Method 3: If we have a PID, try system kill as last resort
-                    if let Some(pid) = pid {
-                        log::info!("Attempting system kill as last resort for PID: {}", pid);
-                        let kill_result = if cfg!(target_os = "windows") {
-                            std::process::Command::new("taskkill")
-                                .args(["/F", "/PID", &pid.to_string()])
-                                .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                                .output()
-                        } else {
-                            std::process::Command::new("kill")
-                                .args(["-KILL", &pid.to_string()])
-                                .output()
-                        };
-
-                        match kill_result {
-                            Ok(output) if output.status.success() => {
-                                log::info!("Successfully killed process via system command");
-                                killed = true;
-                            }
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                log::error!("System kill failed: {}", stderr);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to execute system kill command: {}", e);
-                            }
-                        }
-                    }
-                }
-            }
-            attempted_methods.push("claude_state");
+    // Method 2: The run has not reported its session yet, stop the ones still launching
+    if !killed && session_id.is_none() {
+        let registry = app.state::<crate::process::ProcessRegistryState>();
+        let launching = registry.0.kill_launching_sessions();
+        if launching > 0 {
+            log::info!("Killed {} Claude process(es) still launching", launching);
+            killed = true;
         } else {
-            log::warn!("No active Claude process in ClaudeProcessState");
+            log::warn!("No launching Claude process to cancel");
         }
+        attempted_methods.push("launching");
     }
 
     if !killed && attempted_methods.is_empty() {
@@ -1465,17 +1765,330 @@ pub async fn get_claude_session_output(
     }
 }
 
+/// Prompt sent to a session relaunched after a crash
+const RECOVERY_PROMPT: &str =
+    "The previous run exited unexpectedly. Continue the task from where it left off.";
+
+/// Links a relaunched process back to the session that crashed
+#[derive(Debug, Clone)]
+struct SessionRecovery {
+    previous_session_id: String,
+    attempt: u32,
+}
+
+/// Progress of an auto-restart, emitted as `claude-session-restart:{session_id}`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRestartEvent {
+    pub session_id: String,
+    /// "restarting", "recovered" or "failed"
+    pub status: String,
+    pub attempt: u32,
+    pub max_restarts: u32,
+    pub delay_ms: u64,
+    pub new_session_id: Option<String>,
+    pub error: Option<String>,
+}
+
+fn emit_session_restart(app: &AppHandle, event: SessionRestartEvent) {
+    let _ = app.emit(
+        &format!("claude-session-restart:{}", event.session_id),
+        &event,
+    );
+    let _ = app.emit("claude-session-restart", &event);
+}
+
+/// Relaunch a crashed session with `--resume` once the policy's backoff has elapsed
+///
+/// Returns a boxed future because it is spawned from within `spawn_claude_process`.
+fn restart_crashed_session(
+    app: AppHandle,
+    session_id: String,
+    project_path: String,
+    model: String,
+    env: HashMap<String, String>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let registry = app.state::<crate::process::ProcessRegistryState>().0.clone();
+        let Some(policy) = registry.get_restart_policy(&session_id) else {
+            return;
+        };
+
+        let mut event = SessionRestartEvent {
+            session_id: session_id.clone(),
+            status: "failed".to_string(),
+            attempt: policy.max_restarts,
+            max_restarts: policy.max_restarts,
+            delay_ms: 0,
+            new_session_id: None,
+            error: None,
+        };
+
+        let Some(attempt) = registry.next_restart_attempt(&session_id) else {
+            log::warn!(
+                "Session {} crashed again, giving up after {} restarts",
+                session_id,
+                policy.max_restarts
+            );
+            event.error = Some("Restart limit reached".to_string());
+            emit_session_restart(&app, event);
+            return;
+        };
+
+        event.attempt = attempt;
+        event.delay_ms = policy.backoff_ms(attempt);
+        event.status = "restarting".to_string();
+        log::info!(
+            "Restarting crashed session {} (attempt {}/{}) in {}ms",
+            session_id,
+            attempt,
+            policy.max_restarts,
+            event.delay_ms
+        );
+        emit_session_restart(&app, event.clone());
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(event.delay_ms)).await;
+
+        let result = relaunch_session(
+            &app,
+            &session_id,
+            &project_path,
+            &model,
+            RECOVERY_PROMPT,
+            env,
+            Some(SessionRecovery {
+                previous_session_id: session_id.clone(),
+                attempt,
+            }),
+        )
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Failed to restart session {}: {}", session_id, e);
+            event.status = "failed".to_string();
+            event.error = Some(e);
+            emit_session_restart(&app, event);
+        }
+    })
+}
+
+/// Start `claude --resume <session_id>` with the given prompt under the current configuration
+async fn relaunch_session(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    model: &str,
+    prompt: &str,
+    env: HashMap<String, String>,
+    recovery: Option<SessionRecovery>,
+) -> Result<(), String> {
+    super::usage_quotas::ensure_launch_allowed(app, model).await?;
+    let slot = app
+        .state::<crate::process::ProcessRegistryState>()
+        .0
+        .try_reserve_session_slot(project_path)
+        .ok_or_else(|| SESSION_LIMIT_REACHED.to_string())?;
+    let claude_path = find_claude_binary_for_project(app, Some(project_path))?;
+    let args = vec![
+        "--resume".to_string(),
+        session_id.to_string(),
+        escape_prompt_for_cli(prompt),
+        "--model".to_string(),
+        model.to_string(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ];
+    let cmd = create_system_command(&claude_path, args, project_path).await?;
+    spawn_claude_process(
+        app.clone(),
+        cmd,
+        prompt.to_string(),
+        model.to_string(),
+        project_path.to_string(),
+        env,
+        recovery,
+        slot,
+    )
+    .await
+}
+
+/// Resume a session that was stopped by the app (e.g. to apply a provider switch)
+pub(crate) async fn resume_stopped_session(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    model: &str,
+    prompt: &str,
+    env: HashMap<String, String>,
+) -> Result<(), String> {
+    relaunch_session(app, session_id, project_path, model, prompt, env, None).await
+}
+
+/// Locate the transcript Claude writes for a session, returning its project ID and path
+///
+/// The CLI's directory naming differs from ours, so every project directory is
+/// searched and the largest matching file wins over empty placeholders.
+fn find_session_transcript(session_id: &str) -> Option<(String, PathBuf)> {
+    let projects_dir = get_claude_dir().ok()?.join("projects");
+    let file_name = format!("{}.jsonl", session_id);
+
+    fs::read_dir(projects_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path().join(&file_name);
+            let size = fs::metadata(&path).ok()?.len();
+            Some((size, entry.file_name().to_string_lossy().to_string(), path))
+        })
+        .max_by_key(|(size, _, _)| *size)
+        .map(|(_, project_id, path)| (project_id, path))
+}
+
+/// Outcome of an automatic checkpoint attempt
+enum AutoCheckpoint {
+    Created(crate::checkpoint::CheckpointResult),
+    Skipped,
+    /// The previous automatic checkpoint is too recent; retry after this long
+    Throttled(std::time::Duration),
+}
+
+/// Create an automatic checkpoint for a session after Claude edited `files`
+async fn auto_checkpoint_after_edit(
+    app: &AppHandle,
+    session_id: &str,
+    project_path: &str,
+    files: &[String],
+) -> Result<AutoCheckpoint, String> {
+    let Some((project_id, transcript)) = find_session_transcript(session_id) else {
+        return Ok(AutoCheckpoint::Skipped);
+    };
+
+    let state = app.state::<crate::checkpoint::state::CheckpointState>();
+    let manager = state
+        .get_or_create_manager(
+            session_id.to_string(),
+            project_id.clone(),
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    match manager.file_change_checkpoint_wait().await {
+        None => return Ok(AutoCheckpoint::Skipped),
+        Some(wait) if !wait.is_zero() => return Ok(AutoCheckpoint::Throttled(wait)),
+        Some(_) => {}
+    }
+
+    let _project_lock = state.lock_project(&project_id).await;
+
+    let messages = fs::read_to_string(&transcript)
+        .map_err(|e| format!("Failed to read session transcript: {}", e))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(String::from)
+        .collect();
+    manager.replace_messages(messages).await;
+
+    manager
+        .create_auto_checkpoint(files)
+        .await
+        .map(AutoCheckpoint::Created)
+        .map_err(|e| format!("Failed to create checkpoint: {}", e))
+}
+
+/// Start the task creating automatic checkpoints for one Claude process
+///
+/// Edits are processed one at a time in the order they completed. Edits made
+/// while the previous checkpoint is too recent are collected and checkpointed
+/// together once the interval has passed, even after the process has exited.
+fn spawn_auto_checkpointer(
+    app: AppHandle,
+    project_path: String,
+) -> tokio::sync::mpsc::UnboundedSender<(String, Vec<String>)> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(String, Vec<String>)>();
+    tokio::spawn(async move {
+        // Throttled edits of a session and when to checkpoint them
+        let mut pending: Option<(String, Vec<String>, tokio::time::Instant)> = None;
+        let mut open = true;
+        while open || pending.is_some() {
+            let due = pending.as_ref().map(|(_, _, due)| *due);
+            // None once the throttle window has ended
+            let received = match due {
+                Some(due) if open => tokio::time::timeout_at(due, rx.recv()).await.ok(),
+                Some(due) => {
+                    tokio::time::sleep_until(due).await;
+                    None
+                }
+                None => Some(rx.recv().await),
+            };
+            let (session_id, files) = match (received, pending.take()) {
+                (Some(None), pending_edits) => {
+                    open = false;
+                    pending = pending_edits;
+                    continue;
+                }
+                (Some(Some((session_id, files))), Some((pending_id, mut pending_files, due)))
+                    if pending_id == session_id =>
+                {
+                    for file in files {
+                        if !pending_files.contains(&file) {
+                            pending_files.push(file);
+                        }
+                    }
+                    pending = Some((pending_id, pending_files, due));
+                    continue;
+                }
+                (Some(Some(edit)), pending_edits) => {
+                    pending = pending_edits;
+                    edit
+                }
+                (None, Some((session_id, files, _))) => (session_id, files),
+                (None, None) => continue,
+            };
+
+            match auto_checkpoint_after_edit(&app, &session_id, &project_path, &files).await {
+                Ok(AutoCheckpoint::Created(result)) => {
+                    log::info!(
+                        "Created automatic checkpoint {} for session {}",
+                        result.checkpoint.id,
+                        session_id
+                    );
+                    let _ = app.emit(&format!("checkpoint-created:{}", session_id), &result);
+                }
+                Ok(AutoCheckpoint::Throttled(wait)) => {
+                    pending = Some((session_id, files, tokio::time::Instant::now() + wait));
+                }
+                Ok(AutoCheckpoint::Skipped) => {}
+                Err(e) => {
+                    log::warn!("Automatic checkpoint for session {} failed: {}", session_id, e)
+                }
+            }
+        }
+    });
+    tx
+}
+
 /// Helper function to spawn Claude process and handle streaming
+///
+/// `slot` is released if the spawn fails and handed to the session once it registers.
+#[allow(clippy::too_many_arguments)]
 async fn spawn_claude_process(
     app: AppHandle,
     mut cmd: Command,
     prompt: String,
     model: String,
     project_path: String,
+    env: HashMap<String, String>,
+    recovery: Option<SessionRecovery>,
+    slot: crate::process::SessionSlot,
 ) -> Result<(), String> {
     use std::sync::Mutex;
     use tokio::io::{AsyncBufReadExt, BufReader};
 
+    // Per-launch overrides on top of the inherited environment
+    cmd.envs(&env);
+
     // Spawn the process
     let mut child = cmd
         .spawn()
@@ -1488,6 +2101,15 @@ async fn spawn_claude_process(
     // Get the child PID for logging
     let pid = child.id().unwrap_or(0);
     log::info!("Spawned Claude process with PID: {:?}", pid);
+    let started_at = std::time::Instant::now();
+
+    // Each run keeps its own handle, shared with the registry once it registers
+    let child: crate::process::registry::SharedChild = Arc::new(Mutex::new(Some(child)));
+
+    // The slot is held until the session registers (or exits)
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    slot.set_child(pid, child.clone());
+    let slot_holder = Arc::new(Mutex::new(Some(slot)));
 
     // Create readers first (before moving child)
     let stdout_reader = BufReader::new(stdout);
@@ -1497,29 +2119,22 @@ async fn spawn_claude_process(
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
     let run_id_holder: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
 
-    // Store the child process in the global state (for backward compatibility)
-    let claude_state = app.state::<ClaudeProcessState>();
-    {
-        let mut current_process = claude_state.current_process.lock().await;
-        // If there's already a process running, kill it first
-        if let Some(mut existing_child) = current_process.take() {
-            log::warn!("Killing existing Claude process before starting new one");
-            let _ = existing_child.kill().await;
-        }
-        *current_process = Some(child);
-    }
-
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
     let session_id_holder_clone = session_id_holder.clone();
     let run_id_holder_clone = run_id_holder.clone();
-    let registry = app.state::<crate::process::ProcessRegistryState>();
     let registry_clone = registry.0.clone();
     let project_path_clone = project_path.clone();
     let prompt_clone = prompt.clone();
     let model_clone = model.clone();
+    let env_clone = env.clone();
+    let child_clone = child.clone();
+    let slot_holder_clone = slot_holder.clone();
+    let app_handle_recovery = app.clone();
+    let auto_checkpoint_tx = spawn_auto_checkpointer(app.clone(), project_path.clone());
     let stdout_task = tokio::spawn(async move {
         let mut lines = stdout_reader.lines();
+        let mut edit_detector = crate::checkpoint::auto::FileEditDetector::new();
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
 
@@ -1533,17 +2148,52 @@ async fn spawn_claude_process(
                             log::info!("Extracted Claude session ID: {}", claude_session_id);
 
                             // Now register with ProcessRegistry using Claude's session ID
-                            match registry_clone.register_claude_session(
-                                claude_session_id.to_string(),
-                                pid,
-                                project_path_clone.clone(),
-                                prompt_clone.clone(),
-                                model_clone.clone(),
-                            ) {
+                            let slot = slot_holder_clone
+                                .lock()
+                                .unwrap()
+                                .take()
+                                .ok_or_else(|| "Session slot already released".to_string());
+                            match slot.and_then(|slot| {
+                                registry_clone.register_claude_session(
+                                    claude_session_id.to_string(),
+                                    pid,
+                                    project_path_clone.clone(),
+                                    prompt_clone.clone(),
+                                    model_clone.clone(),
+                                    child_clone.clone(),
+                                    slot,
+                                )
+                            }) {
                                 Ok(run_id) => {
                                     log::info!("Registered Claude session with run_id: {}", run_id);
                                     let mut run_id_guard = run_id_holder_clone.lock().unwrap();
                                     *run_id_guard = Some(run_id);
+                                    registry_clone.set_env_overrides(run_id, env_clone.clone());
+
+                                    // A relaunched crashed session is back up
+                                    if let Some(recovery) = &recovery {
+                                        registry_clone.transfer_restart_state(
+                                            &recovery.previous_session_id,
+                                            claude_session_id,
+                                        );
+                                        emit_session_restart(
+                                            &app_handle_recovery,
+                                            SessionRestartEvent {
+                                                session_id: recovery.previous_session_id.clone(),
+                                                status: "recovered".to_string(),
+                                                attempt: recovery.attempt,
+                                                max_restarts: registry_clone
+                                                    .get_restart_policy(claude_session_id)
+                                                    .map(|policy| policy.max_restarts)
+                                                    .unwrap_or(recovery.attempt),
+                                                delay_ms: 0,
+                                                new_session_id: Some(
+                                                    claude_session_id.to_string(),
+                                                ),
+                                                error: None,
+                                            },
+                                        );
+                                    }
 
                                     // Create project folder structure so it appears in project list
                                     let project_id = project_path_clone
@@ -1590,6 +2240,14 @@ async fn spawn_claude_process(
                 let _ = registry_clone.append_live_output(run_id, &line);
             }
 
+            // Hand finished file edits to the automatic checkpointer
+            let edited_files = edit_detector.observe(&line);
+            if !edited_files.is_empty() {
+                if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
+                    let _ = auto_checkpoint_tx.send((session_id.clone(), edited_files));
+                }
+            }
+
             // Emit the line to the frontend with session isolation if we have session ID
             if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
                 let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
@@ -1601,10 +2259,20 @@ async fn spawn_claude_process(
 
     let app_handle_stderr = app.clone();
     let session_id_holder_clone2 = session_id_holder.clone();
+    let run_id_holder_stderr = run_id_holder.clone();
+    let registry_stderr = registry.0.clone();
     let stderr_task = tokio::spawn(async move {
         let mut lines = stderr_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
             log::error!("Claude stderr: {}", line);
+            // Capture stderr in the registry once the session is registered
+            if let Some(run_id) = *run_id_holder_stderr.lock().unwrap() {
+                let _ = registry_stderr.append_output(
+                    run_id,
+                    crate::process::OutputStream::Stderr,
+                    &line,
+                );
+            }
             // Emit error lines to the frontend with session isolation if we have session ID
             if let Some(ref session_id) = *session_id_holder_clone2.lock().unwrap() {
                 let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id), &line);
@@ -1616,7 +2284,6 @@ async fn spawn_claude_process(
 
     // Wait for the process to complete
     let app_handle_wait = app.clone();
-    let claude_state_wait = claude_state.current_process.clone();
     let session_id_holder_clone3 = session_id_holder.clone();
     let run_id_holder_clone2 = run_id_holder.clone();
     let registry_clone2 = registry.0.clone();
@@ -1624,12 +2291,16 @@ async fn spawn_claude_process(
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
-        // Get the child from the state to wait on it
-        let mut current_process = claude_state_wait.lock().await;
-        if let Some(mut child) = current_process.take() {
-            match child.wait().await {
+        // Whether the process exited cleanly, None if it was taken over elsewhere
+        let mut exit_success = None;
+        let mut exit_code = None;
+
+        if let Some(exit) = crate::process::registry::wait_for_child(&child).await {
+            match exit {
                 Ok(status) => {
                     log::info!("Claude process exited with status: {}", status);
+                    exit_success = Some(status.success());
+                    exit_code = status.code();
                     // Add a small delay to ensure all messages are processed
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
@@ -1641,6 +2312,7 @@ async fn spawn_claude_process(
                 }
                 Err(e) => {
                     log::error!("Failed to wait for Claude process: {}", e);
+                    exit_success = Some(false);
                     // Add a small delay to ensure all messages are processed
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
@@ -1654,16 +2326,74 @@ async fn spawn_claude_process(
         }
 
         // Unregister from ProcessRegistry if we have a run_id
-        if let Some(run_id) = *run_id_holder_clone2.lock().unwrap() {
-            let _ = registry_clone2.unregister_process(run_id);
+        let run_id = *run_id_holder_clone2.lock().unwrap();
+        let stop_requested = match run_id {
+            Some(run_id) => {
+                // The exit was not observed here, so its outcome is unknown
+                let status = match exit_success {
+                    Some(true) => "completed",
+                    Some(false) => "failed",
+                    None => "interrupted",
+                };
+                let _ = registry_clone2.finish_process(run_id, exit_code, status);
+                registry_clone2.take_stop_requested(run_id)
+            }
+            None => false,
+        };
+
+        // Release the handle; the registry entry is gone by now
+        if let Ok(mut child_guard) = child.lock() {
+            *child_guard = None;
         }
 
-        // Clear the process from state
-        *current_process = None;
-    });
+        // Free the slot in case the process exited before registering, then start queued runs
+        if let Ok(mut slot) = slot_holder.lock() {
+            slot.take();
+        }
+        tokio::spawn(start_queued_runs(app_handle_wait.clone()));
 
-    Ok(())
-}
+        // Let a user who tabbed away (or their team chat) know the run is over,
+        // unless they stopped it themselves
+        if let Some(success) = exit_success.filter(|_| !stop_requested) {
+            let event = if success {
+                SessionEvent::Completed
+            } else {
+                SessionEvent::Failed
+            };
+            notify_session_event(
+                &app_handle_wait,
+                event,
+                &project_path,
+                Some(started_at.elapsed()),
+            );
+            super::webhooks::notify_session_finished(
+                &app_handle_wait,
+                success,
+                &project_path,
+                session_id_holder_clone3.lock().unwrap().as_deref(),
+                started_at.elapsed(),
+            );
+        }
+
+        // Relaunch sessions that crashed (not ones we stopped) if they opted in
+        let session_id = session_id_holder_clone3.lock().unwrap().clone();
+        if let (Some(session_id), Some(success)) = (session_id, exit_success) {
+            if success {
+                registry_clone2.clear_restart_attempts(&session_id);
+            } else if !stop_requested {
+                tokio::spawn(restart_crashed_session(
+                    app_handle_wait,
+                    session_id,
+                    project_path,
+                    model,
+                    env,
+                ));
+            }
+        }
+    });
+
+    Ok(())
+}
 
 /// Lists files and directories in a given path
 #[tauri::command]
@@ -1859,36 +2589,18 @@ fn search_files_recursive(
     Ok(())
 }
 
-/// Creates a checkpoint for the current session state
-#[tauri::command]
-pub async fn create_checkpoint(
-    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
-    session_id: String,
-    project_id: String,
-    project_path: String,
+/// Load the session transcript up to `message_index` into the checkpoint manager
+async fn load_transcript_messages(
+    manager: &crate::checkpoint::manager::CheckpointManager,
+    project_id: &str,
+    session_id: &str,
     message_index: Option<usize>,
-    description: Option<String>,
-) -> Result<crate::checkpoint::CheckpointResult, String> {
-    log::info!(
-        "Creating checkpoint for session: {} in project: {}",
-        session_id,
-        project_id
-    );
-
-    let manager = app
-        .get_or_create_manager(
-            session_id.clone(),
-            project_id.clone(),
-            PathBuf::from(&project_path),
-        )
-        .await
-        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
-
+) -> Result<(), String> {
     // Always load current session messages from the JSONL file
     let session_path = get_claude_dir()
         .map_err(|e| e.to_string())?
         .join("projects")
-        .join(&project_id)
+        .join(project_id)
         .join(format!("{}.jsonl", session_id));
 
     if session_path.exists() {
@@ -1913,12 +2625,115 @@ pub async fn create_checkpoint(
         }
     }
 
+    Ok(())
+}
+
+/// Creates a checkpoint for the current session state
+#[tauri::command]
+pub async fn create_checkpoint(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    message_index: Option<usize>,
+    description: Option<String>,
+) -> Result<crate::checkpoint::CheckpointResult, String> {
+    log::info!(
+        "Creating checkpoint for session: {} in project: {}",
+        session_id,
+        project_id
+    );
+
+    let manager = app
+        .get_or_create_manager(
+            session_id.clone(),
+            project_id.clone(),
+            PathBuf::from(&project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    let _project_lock = app.lock_project(&project_id).await;
+    load_transcript_messages(&manager, &project_id, &session_id, message_index).await?;
+
     manager
         .create_checkpoint(description, None)
         .await
         .map_err(|e| format!("Failed to create checkpoint: {}", e))
 }
 
+/// Starts creating a checkpoint in the background and returns its job id
+///
+/// Progress is emitted as `checkpoint-progress:{job_id}`. The job ends with
+/// `checkpoint-complete:{job_id}` carrying the result, also announced as
+/// `checkpoint-created:{session_id}`, or with `checkpoint-error:{job_id}`.
+#[tauri::command]
+pub async fn create_checkpoint_in_background(
+    app: AppHandle,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    message_index: Option<usize>,
+    description: Option<String>,
+) -> Result<String, String> {
+    let job_id = uuid::Uuid::new_v4().to_string();
+    log::info!(
+        "Starting background checkpoint {} for session: {} in project: {}",
+        job_id,
+        session_id,
+        project_id
+    );
+
+    let job = job_id.clone();
+    tokio::spawn(async move {
+        let state = app.state::<crate::checkpoint::state::CheckpointState>();
+        let progress_app = app.clone();
+        let progress_event = format!("checkpoint-progress:{}", job);
+        let progress: crate::checkpoint::ProgressCallback =
+            Arc::new(move |progress: crate::checkpoint::CheckpointProgress| {
+                let _ = progress_app.emit(&progress_event, &progress);
+            });
+
+        let result = async {
+            let manager = state
+                .get_or_create_manager(
+                    session_id.clone(),
+                    project_id.clone(),
+                    PathBuf::from(&project_path),
+                )
+                .await
+                .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+            let _project_lock = state.lock_project(&project_id).await;
+            load_transcript_messages(&manager, &project_id, &session_id, message_index).await?;
+
+            manager
+                .create_checkpoint_with_progress(description, None, Some(progress))
+                .await
+                .map_err(|e| format!("Failed to create checkpoint: {}", e))
+        }
+        .await;
+
+        match result {
+            Ok(result) => {
+                log::info!(
+                    "Background checkpoint {} created checkpoint {}",
+                    job,
+                    result.checkpoint.id
+                );
+                let _ = app.emit(&format!("checkpoint-created:{}", session_id), &result);
+                let _ = app.emit(&format!("checkpoint-complete:{}", job), &result);
+            }
+            Err(e) => {
+                log::error!("Background checkpoint {} failed: {}", job, e);
+                let _ = app.emit(&format!("checkpoint-error:{}", job), &e);
+            }
+        }
+    });
+
+    Ok(job_id)
+}
+
 /// Restores a session to a specific checkpoint
 #[tauri::command]
 pub async fn restore_checkpoint(
@@ -1943,6 +2758,7 @@ pub async fn restore_checkpoint(
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
+    let _project_lock = app.lock_project(&project_id).await;
     let result = manager
         .restore_checkpoint(&checkpoint_id)
         .await
@@ -2040,6 +2856,162 @@ pub async fn fork_from_checkpoint(
         .map_err(|e| format!("Failed to fork checkpoint: {}", e))
 }
 
+/// Load the transcript of the session's current branch into its checkpoint manager
+///
+/// Switching branches may checkpoint uncommitted edits first, which should
+/// record the conversation of the branch being left.
+async fn load_current_branch_messages(
+    manager: &crate::checkpoint::manager::CheckpointManager,
+    claude_dir: &std::path::Path,
+    project_id: &str,
+) -> Result<(), String> {
+    let (branches, current_branch_id) = manager.list_branches().await;
+    let Some(branch) = branches
+        .iter()
+        .find(|b| Some(&b.id) == current_branch_id.as_ref())
+    else {
+        return Ok(());
+    };
+
+    let session_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", branch.session_id));
+    if !session_path.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(&session_path)
+        .map_err(|e| format!("Failed to read session file: {}", e))?;
+    manager
+        .replace_messages(
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(String::from)
+                .collect(),
+        )
+        .await;
+    Ok(())
+}
+
+/// Creates a timeline branch from a historical checkpoint and switches to it
+///
+/// The conversation up to the checkpoint is copied into `branch_session_id`,
+/// so resuming that session continues the branch while the original session
+/// keeps its own history.
+#[tauri::command]
+pub async fn create_checkpoint_branch(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    branch_session_id: String,
+    name: Option<String>,
+) -> Result<crate::checkpoint::TimelineBranch, String> {
+    log::info!(
+        "Creating branch from checkpoint: {} in session: {} (branch session: {})",
+        checkpoint_id,
+        session_id,
+        branch_session_id
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let manager = app
+        .get_or_create_manager(
+            session_id.clone(),
+            project_id.clone(),
+            PathBuf::from(&project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    let _project_lock = app.lock_project(&project_id).await;
+    load_current_branch_messages(&manager, &claude_dir, &project_id).await?;
+
+    let (branch, result) = manager
+        .create_branch(&checkpoint_id, name, branch_session_id.clone())
+        .await
+        .map_err(|e| format!("Failed to create branch: {}", e))?;
+
+    // Fork the conversation into the branch's own session file
+    let (_, _, messages) = manager
+        .storage
+        .load_checkpoint(&project_id, &session_id, &checkpoint_id)
+        .map_err(|e| format!("Failed to load checkpoint data: {}", e))?;
+    let branch_session_path = claude_dir
+        .join("projects")
+        .join(&project_id)
+        .join(format!("{}.jsonl", branch_session_id));
+    fs::write(&branch_session_path, messages)
+        .map_err(|e| format!("Failed to write branch session file: {}", e))?;
+
+    if !result.warnings.is_empty() {
+        log::warn!(
+            "Branch {} created with warnings: {:?}",
+            branch.id,
+            result.warnings
+        );
+    }
+
+    Ok(branch)
+}
+
+/// Lists the timeline branches of a session
+#[tauri::command]
+pub async fn list_checkpoint_branches(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+) -> Result<serde_json::Value, String> {
+    log::info!("Listing branches for session: {}", session_id);
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    let (branches, current_branch_id) = manager.list_branches().await;
+
+    Ok(serde_json::json!({
+        "branches": branches,
+        "current_branch_id": current_branch_id,
+    }))
+}
+
+/// Switches a session's project files to another timeline branch
+///
+/// Returns the branch; its `sessionId` is the Claude session to resume.
+#[tauri::command]
+pub async fn switch_checkpoint_branch(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    branch_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+) -> Result<crate::checkpoint::TimelineBranch, String> {
+    log::info!("Switching session: {} to branch: {}", session_id, branch_id);
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let manager = app
+        .get_or_create_manager(
+            session_id.clone(),
+            project_id.clone(),
+            PathBuf::from(&project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    let _project_lock = app.lock_project(&project_id).await;
+    load_current_branch_messages(&manager, &claude_dir, &project_id).await?;
+
+    manager
+        .switch_branch(&branch_id)
+        .await
+        .map_err(|e| format!("Failed to switch branch: {}", e))
+}
+
 /// Gets the timeline for a session
 #[tauri::command]
 pub async fn get_session_timeline(
@@ -2071,6 +3043,7 @@ pub async fn update_checkpoint_settings(
     project_path: String,
     auto_checkpoint_enabled: bool,
     checkpoint_strategy: String,
+    auto_checkpoint_min_interval_secs: Option<u64>,
 ) -> Result<(), String> {
     use crate::checkpoint::CheckpointStrategy;
 
@@ -2081,6 +3054,7 @@ pub async fn update_checkpoint_settings(
         "per_prompt" => CheckpointStrategy::PerPrompt,
         "per_tool_use" => CheckpointStrategy::PerToolUse,
         "smart" => CheckpointStrategy::Smart,
+        "on_file_change" => CheckpointStrategy::OnFileChange,
         _ => {
             return Err(format!(
                 "Invalid checkpoint strategy: {}",
@@ -2095,7 +3069,11 @@ pub async fn update_checkpoint_settings(
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
     manager
-        .update_settings(auto_checkpoint_enabled, strategy)
+        .update_settings(
+            auto_checkpoint_enabled,
+            strategy,
+            auto_checkpoint_min_interval_secs,
+        )
         .await
         .map_err(|e| format!("Failed to update settings: {}", e))
 }
@@ -2258,6 +3236,237 @@ pub async fn cleanup_old_checkpoints(
         .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))
 }
 
+/// app_settings key storing the checkpoint retention policy (JSON)
+pub const CHECKPOINT_RETENTION_SETTING_KEY: &str = "checkpoint_retention_policy";
+
+fn load_checkpoint_retention_policy(
+    db: &crate::commands::agents::AgentDb,
+) -> Result<crate::checkpoint::retention::RetentionPolicy, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            rusqlite::params![CHECKPOINT_RETENTION_SETTING_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// Gets the checkpoint retention policy
+#[tauri::command]
+pub async fn get_checkpoint_retention_policy(
+    db: tauri::State<'_, crate::commands::agents::AgentDb>,
+) -> Result<crate::checkpoint::retention::RetentionPolicy, String> {
+    load_checkpoint_retention_policy(&db)
+}
+
+/// Sets and persists the checkpoint retention policy
+#[tauri::command]
+pub async fn set_checkpoint_retention_policy(
+    db: tauri::State<'_, crate::commands::agents::AgentDb>,
+    policy: crate::checkpoint::retention::RetentionPolicy,
+) -> Result<(), String> {
+    if policy.max_total_bytes == Some(0) {
+        return Err("Checkpoint size cap must be greater than 0".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&policy).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![CHECKPOINT_RETENTION_SETTING_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save checkpoint retention policy: {}", e))?;
+    Ok(())
+}
+
+/// Prunes checkpoints of all sessions in a project by the retention policy
+///
+/// With `dry_run` the report lists the checkpoints that would be removed
+/// without touching anything.
+#[tauri::command]
+pub async fn prune_checkpoints(
+    db: tauri::State<'_, crate::commands::agents::AgentDb>,
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    project_id: String,
+    dry_run: Option<bool>,
+) -> Result<crate::checkpoint::retention::PruneReport, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    log::info!(
+        "Pruning checkpoints for project: {} (dry run: {})",
+        project_id,
+        dry_run
+    );
+
+    let policy = load_checkpoint_retention_policy(&db)?;
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+
+    let _project_lock = app.lock_project(&project_id).await;
+    let report = storage
+        .prune_checkpoints(&project_id, &policy, dry_run)
+        .map_err(|e| format!("Failed to prune checkpoints: {}", e))?;
+
+    if !dry_run {
+        // Active managers still hold the timelines from before pruning
+        let mut sessions: Vec<&str> = report
+            .pruned
+            .iter()
+            .map(|p| p.session_id.as_str())
+            .collect();
+        sessions.sort_unstable();
+        sessions.dedup();
+        for session_id in sessions {
+            if let Some(manager) = app.get_manager(session_id).await {
+                if let Err(e) = manager.reload_timeline().await {
+                    log::warn!("Failed to reload timeline for {}: {}", session_id, e);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Gets the checkpoint ignore rules of a project: the built-in defaults and
+/// the content of its `.checkpointignore`
+#[tauri::command]
+pub async fn get_checkpoint_ignore(project_path: String) -> Result<serde_json::Value, String> {
+    use crate::checkpoint::ignore::{
+        DEFAULT_IGNORE_PATTERNS, IGNORE_FILE_NAME, MAX_SNAPSHOT_FILE_SIZE,
+    };
+
+    let ignore_path = PathBuf::from(&project_path).join(IGNORE_FILE_NAME);
+    let content = if ignore_path.exists() {
+        fs::read_to_string(&ignore_path)
+            .map_err(|e| format!("Failed to read {}: {}", IGNORE_FILE_NAME, e))?
+    } else {
+        String::new()
+    };
+
+    Ok(serde_json::json!({
+        "default_patterns": DEFAULT_IGNORE_PATTERNS,
+        "max_file_size": MAX_SNAPSHOT_FILE_SIZE,
+        "content": content,
+    }))
+}
+
+/// Saves a project's `.checkpointignore`, overriding the default rules
+#[tauri::command]
+pub async fn save_checkpoint_ignore(project_path: String, content: String) -> Result<(), String> {
+    use crate::checkpoint::ignore::IGNORE_FILE_NAME;
+
+    log::info!("Saving {} for project: {}", IGNORE_FILE_NAME, project_path);
+
+    let ignore_path = PathBuf::from(&project_path).join(IGNORE_FILE_NAME);
+    fs::write(&ignore_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", IGNORE_FILE_NAME, e))
+}
+
+/// Gets the checkpoints of all sessions in a project as one chronological
+/// timeline
+///
+/// Entries carry their session ID; restoring one goes through
+/// `restore_checkpoint` with that session.
+#[tauri::command]
+pub async fn get_project_timeline(
+    project_id: String,
+) -> Result<crate::checkpoint::project_timeline::ProjectTimeline, String> {
+    log::info!("Getting project timeline for project: {}", project_id);
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+
+    crate::checkpoint::project_timeline::load_project_timeline(&storage, &project_id)
+        .map_err(|e| format!("Failed to load project timeline: {}", e))
+}
+
+/// Searches checkpoint descriptions, user prompts and changed file paths
+/// across all sessions of a project
+#[tauri::command]
+pub async fn search_checkpoints(
+    project_id: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<crate::checkpoint::search::CheckpointSearchHit>, String> {
+    log::info!(
+        "Searching checkpoints of project: {} for {:?}",
+        project_id,
+        query
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+
+    crate::checkpoint::search::search_checkpoints(
+        &storage,
+        &project_id,
+        &query,
+        limit.unwrap_or(50),
+    )
+    .map_err(|e| format!("Failed to search checkpoints: {}", e))
+}
+
+/// Exports a checkpoint, or the session's whole timeline, into a portable archive
+#[tauri::command]
+pub async fn export_checkpoint_archive(
+    session_id: String,
+    project_id: String,
+    checkpoint_id: Option<String>,
+    output_path: String,
+) -> Result<crate::checkpoint::archive::ArchiveSummary, String> {
+    log::info!(
+        "Exporting {} of session: {} to {}",
+        checkpoint_id.as_deref().unwrap_or("timeline"),
+        session_id,
+        output_path
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+
+    crate::checkpoint::archive::export_archive(
+        &storage,
+        &project_id,
+        &session_id,
+        checkpoint_id.as_deref(),
+        &PathBuf::from(&output_path),
+    )
+    .map_err(|e| format!("Failed to export checkpoints: {}", e))
+}
+
+/// Imports a checkpoint archive into a project as a new session
+///
+/// Restore one of the imported checkpoints afterwards to bring the project
+/// files to that state.
+#[tauri::command]
+pub async fn import_checkpoint_archive(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    archive_path: String,
+    project_id: String,
+    session_id: Option<String>,
+) -> Result<crate::checkpoint::archive::ArchiveSummary, String> {
+    log::info!(
+        "Importing checkpoint archive {} into project: {}",
+        archive_path,
+        project_id
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = crate::checkpoint::storage::CheckpointStorage::new(claude_dir);
+
+    let _project_lock = app.lock_project(&project_id).await;
+    crate::checkpoint::archive::import_archive(
+        &storage,
+        &PathBuf::from(&archive_path),
+        &project_id,
+        session_id,
+    )
+    .map_err(|e| format!("Failed to import checkpoints: {}", e))
+}
+
 /// Gets checkpoint settings for a session
 #[tauri::command]
 pub async fn get_checkpoint_settings(
@@ -2278,6 +3487,7 @@ pub async fn get_checkpoint_settings(
     Ok(serde_json::json!({
         "auto_checkpoint_enabled": timeline.auto_checkpoint_enabled,
         "checkpoint_strategy": timeline.checkpoint_strategy,
+        "auto_checkpoint_min_interval_secs": timeline.auto_checkpoint_min_interval_secs,
         "total_checkpoints": timeline.total_checkpoints,
         "current_checkpoint_id": timeline.current_checkpoint_id,
     }))
@@ -2557,7 +3767,7 @@ pub async fn set_custom_claude_path(app: AppHandle, custom_path: String) -> Resu
     }
 
     // Store the custom path in database
-    if let Ok(app_data_dir) = app.path().app_data_dir() {
+    if let Ok(app_data_dir) = crate::portable::app_data_dir(&app) {
         if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
             return Err(format!("Failed to create app data directory: {}", e));
         }
@@ -2603,7 +3813,7 @@ pub async fn get_claude_path(app: AppHandle) -> Result<String, String> {
     log::info!("Getting current Claude CLI path");
 
     // Try to get from database first
-    if let Ok(app_data_dir) = app.path().app_data_dir() {
+    if let Ok(app_data_dir) = crate::portable::app_data_dir(&app) {
         let db_path = app_data_dir.join("agents.db");
         if db_path.exists() {
             if let Ok(conn) = rusqlite::Connection::open(&db_path) {
@@ -2634,7 +3844,7 @@ pub async fn get_claude_path(app: AppHandle) -> Result<String, String> {
 pub async fn clear_custom_claude_path(app: AppHandle) -> Result<(), String> {
     log::info!("Clearing custom Claude CLI path");
 
-    if let Ok(app_data_dir) = app.path().app_data_dir() {
+    if let Ok(app_data_dir) = crate::portable::app_data_dir(&app) {
         let db_path = app_data_dir.join("agents.db");
         if db_path.exists() {
             match rusqlite::Connection::open(&db_path) {
@@ -2660,6 +3870,276 @@ pub async fn clear_custom_claude_path(app: AppHandle) -> Result<(), String> {
     }
 }
 
+/// Lists Claude CLI installations: versions installed by the version manager
+/// followed by the ones discovered on the system
+#[tauri::command]
+pub async fn list_claude_versions(
+    app: AppHandle,
+) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut installations = crate::claude_binary::list_managed_installations(&app);
+        let mut seen: std::collections::HashSet<String> =
+            installations.iter().map(|i| i.path.clone()).collect();
+        for installation in crate::claude_binary::cached_claude_installations(&app) {
+            if seen.insert(installation.path.clone()) {
+                installations.push(installation);
+            }
+        }
+        installations
+    })
+    .await
+    .map_err(|e| format!("Failed to list Claude versions: {}", e))
+}
+
+/// Scans for Claude installations again, replacing the cached discovery
+/// results, and returns them like `list_claude_versions`
+#[tauri::command]
+pub async fn refresh_claude_binaries(
+    app: AppHandle,
+) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
+    log::info!("Refreshing Claude binary discovery cache");
+    tokio::task::spawn_blocking(move || {
+        let mut installations = crate::claude_binary::list_managed_installations(&app);
+        let mut seen: std::collections::HashSet<String> =
+            installations.iter().map(|i| i.path.clone()).collect();
+        for installation in crate::claude_binary::refresh_claude_installations(&app) {
+            if seen.insert(installation.path.clone()) {
+                installations.push(installation);
+            }
+        }
+        installations
+    })
+    .await
+    .map_err(|e| format!("Failed to refresh Claude binaries: {}", e))
+}
+
+/// Gets the self-contained Claude runtime, shipped or downloaded, if present
+#[tauri::command]
+pub async fn get_claude_runtime(
+    app: AppHandle,
+) -> Result<Option<crate::claude_binary::ClaudeInstallation>, String> {
+    tokio::task::spawn_blocking(move || crate::claude_binary::find_runtime_installation(&app))
+        .await
+        .map_err(|e| format!("Failed to check Claude runtime: {}", e))
+}
+
+/// Downloads a node runtime with the Claude CLI for machines without Node.js
+///
+/// The Claude CLI version pinned by `project_path` is installed when there is
+/// one, the latest otherwise. The verification of the downloaded node archive
+/// is part of the result and also emitted as `download-verification`.
+#[tauri::command]
+pub async fn install_claude_runtime(
+    app: AppHandle,
+    project_path: Option<String>,
+) -> Result<crate::claude_binary::RuntimeInstall, String> {
+    let version = project_path
+        .and_then(|path| crate::claude_binary::read_version_pin(&PathBuf::from(path)));
+    log::info!(
+        "Installing self-contained Claude runtime with Claude CLI {}",
+        version.as_deref().unwrap_or("latest")
+    );
+    let install = crate::claude_binary::install_claude_runtime(&app, version.as_deref()).await?;
+    crate::claude_binary::clear_capability_cache();
+    let _ = app.emit("download-verification", &install.verification);
+    Ok(install)
+}
+
+/// Removes the downloaded Claude runtime
+#[tauri::command]
+pub async fn remove_claude_runtime(app: AppHandle) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || crate::claude_binary::remove_claude_runtime(&app))
+        .await
+        .map_err(|e| format!("Failed to remove Claude runtime: {}", e))?
+}
+
+/// Lists Claude CLI versions that can be installed, newest first
+#[tauri::command]
+pub async fn list_available_claude_versions() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(crate::claude_binary::list_available_claude_versions)
+        .await
+        .map_err(|e| format!("Failed to list available Claude versions: {}", e))?
+}
+
+/// Downloads and installs a Claude CLI version ("latest" for the newest)
+#[tauri::command]
+pub async fn install_claude_version(
+    app: AppHandle,
+    version: String,
+) -> Result<crate::claude_binary::ClaudeInstallation, String> {
+    log::info!("Installing Claude CLI version: {}", version);
+    tokio::task::spawn_blocking(move || {
+        crate::claude_binary::install_claude_version(&app, &version)
+    })
+    .await
+    .map_err(|e| format!("Failed to install Claude version: {}", e))?
+}
+
+/// Removes a Claude CLI version installed by the version manager
+#[tauri::command]
+pub async fn uninstall_claude_version(app: AppHandle, version: String) -> Result<(), String> {
+    log::info!("Uninstalling Claude CLI version: {}", version);
+    crate::claude_binary::uninstall_claude_version(&app, &version)
+}
+
+/// Makes an installed Claude CLI version the default one
+#[tauri::command]
+pub async fn switch_claude_version(
+    app: AppHandle,
+    version: String,
+) -> Result<crate::claude_binary::ClaudeInstallation, String> {
+    log::info!("Switching Claude CLI to version: {}", version);
+    tokio::task::spawn_blocking(move || crate::claude_binary::switch_claude_version(&app, &version))
+        .await
+        .map_err(|e| format!("Failed to switch Claude version: {}", e))?
+}
+
+/// Gets the Claude CLI version pinned by a project's `.claude-version`
+#[tauri::command]
+pub async fn get_project_claude_version(project_path: String) -> Result<Option<String>, String> {
+    Ok(crate::claude_binary::read_version_pin(&PathBuf::from(
+        project_path,
+    )))
+}
+
+/// Pins a project to a Claude CLI version, or removes the pin when `version` is None
+#[tauri::command]
+pub async fn set_project_claude_version(
+    project_path: String,
+    version: Option<String>,
+) -> Result<(), String> {
+    log::info!(
+        "Setting pinned Claude CLI version of {} to {:?}",
+        project_path,
+        version
+    );
+    crate::claude_binary::write_version_pin(&PathBuf::from(project_path), version.as_deref())
+}
+
+/// Gets the features supported by the active Claude CLI
+#[tauri::command]
+pub async fn get_claude_capabilities(
+    app: AppHandle,
+) -> Result<crate::claude_binary::ClaudeCapabilities, String> {
+    tokio::task::spawn_blocking(move || {
+        let claude_path = find_claude_binary(&app)?;
+        Ok(crate::claude_binary::detect_capabilities(&claude_path))
+    })
+    .await
+    .map_err(|e| format!("Failed to detect Claude capabilities: {}", e))?
+}
+
+/// Outcome of `update_claude_binary`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaudeUpdateResult {
+    /// Binary used after the update
+    pub path: String,
+    pub method: crate::claude_binary::UpdateMethod,
+    pub previous_version: Option<String>,
+    pub new_version: Option<String>,
+    /// Whether the reported version changed
+    pub updated: bool,
+}
+
+/// Updates the active Claude CLI with the updater matching its installation
+///
+/// Updater output is emitted line by line as `claude-update-output`; the
+/// version is checked again once the updater finished.
+#[tauri::command]
+pub async fn update_claude_binary(app: AppHandle) -> Result<ClaudeUpdateResult, String> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let claude_path = find_claude_binary(&app)?;
+    let method = crate::claude_binary::update_method(&app, &claude_path);
+    let previous_version = {
+        let path = claude_path.clone();
+        tokio::task::spawn_blocking(move || crate::claude_binary::get_claude_version(&path))
+            .await
+            .map_err(|e| format!("Failed to check Claude version: {}", e))??
+    };
+    log::info!(
+        "Updating Claude CLI at {} ({:?}) from {:?}",
+        claude_path,
+        method,
+        previous_version
+    );
+
+    let path = if method == crate::claude_binary::UpdateMethod::Managed {
+        // Managed versions stay immutable; the latest one is installed next to them
+        let _ = app.emit(
+            "claude-update-output",
+            "Installing the latest Claude CLI version...",
+        );
+        let app_handle = app.clone();
+        tokio::task::spawn_blocking(move || {
+            let installation = crate::claude_binary::install_claude_version(&app_handle, "latest")?;
+            let version = installation.version.clone().unwrap_or_default();
+            crate::claude_binary::switch_claude_version(&app_handle, &version)
+        })
+        .await
+        .map_err(|e| format!("Failed to update Claude CLI: {}", e))??
+        .path
+    } else {
+        let mut cmd = Command::from(crate::claude_binary::update_command(&claude_path, method)?);
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to start Claude updater: {}", e))?;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let stdout_app = app.clone();
+        let stdout_task = tokio::spawn(async move {
+            if let Some(stdout) = stdout {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let _ = stdout_app.emit("claude-update-output", &line);
+                }
+            }
+        });
+        let stderr_app = app.clone();
+        let stderr_task = tokio::spawn(async move {
+            if let Some(stderr) = stderr {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let _ = stderr_app.emit("claude-update-output", &line);
+                }
+            }
+        });
+
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| format!("Failed to wait for Claude updater: {}", e))?;
+        let _ = stdout_task.await;
+        let _ = stderr_task.await;
+        if !status.success() {
+            return Err(format!("Claude updater exited with {}", status));
+        }
+        claude_path
+    };
+    crate::claude_binary::invalidate_discovery_cache(&app);
+
+    let new_version = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || crate::claude_binary::get_claude_version(&path))
+            .await
+            .map_err(|e| format!("Failed to check Claude version: {}", e))??
+    };
+    let result = ClaudeUpdateResult {
+        updated: new_version.is_some() && new_version != previous_version,
+        path,
+        method,
+        previous_version,
+        new_version,
+    };
+    log::info!("Claude CLI update finished: {:?}", result);
+    let _ = app.emit("claude-update-complete", &result);
+    Ok(result)
+}
+
 /// Enhance a prompt using local Claude Code CLI
 #[tauri::command]
 pub async fn enhance_prompt(
@@ -17,8 +17,8 @@ pub async fn save_clipboard_image(
     base64_data: String,
     format: Option<String>
 ) -> Result<SavedImageResult, String> {
-    println!("Received base64_data length: {}", base64_data.len());
-    println!("Base64 data preview: {}", &base64_data[..std::cmp::min(100, base64_data.len())]);
+    log::debug!("Received base64_data length: {}", base64_data.len());
+    log::debug!("Base64 data preview: {}", &base64_data[..std::cmp::min(100, base64_data.len())]);
     
     // 解析Data URL格式 (data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAA...)
     let data_url_prefix = "data:image/";
@@ -50,15 +50,15 @@ pub async fn save_clipboard_image(
         (base64_data.as_str(), format.as_deref().unwrap_or("png"))
     };
 
-    println!("Detected extension: {}", extension);
-    println!("Base64 content length: {}", base64_content.len());
+    log::debug!("Detected extension: {}", extension);
+    log::debug!("Base64 content length: {}", base64_content.len());
     
     // 解码Base64数据
     let image_data = general_purpose::STANDARD
         .decode(base64_content)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
 
-    println!("Decoded image data size: {} bytes", image_data.len());
+    log::debug!("Decoded image data size: {} bytes", image_data.len());
 
     // 获取用户临时目录，确保使用完整路径
     let temp_dir = std::env::var("TEMP")
@@ -81,7 +81,7 @@ pub async fn save_clipboard_image(
     let filename = format!("clipboard_image_{}.{}", timestamp, extension);
     let file_path = images_dir.join(&filename);
 
-    println!("Saving image to: {}", file_path.display());
+    log::debug!("Saving image to: {}", file_path.display());
 
     // 保存文件
     fs::write(&file_path, image_data)
@@ -100,7 +100,7 @@ pub async fn save_clipboard_image(
         .map(|m| m.len())
         .unwrap_or(0);
     
-    println!("Image saved successfully! File size: {} bytes", file_size);
+    log::debug!("Image saved successfully! File size: {} bytes", file_size);
 
     // 返回清洁的Windows文件路径，移除UNC前缀
     let mut path_str = file_path.to_string_lossy().to_string();
@@ -110,7 +110,7 @@ pub async fn save_clipboard_image(
         path_str = path_str[4..].to_string();
     }
     
-    println!("Final cleaned path: {}", path_str);
+    log::debug!("Final cleaned path: {}", path_str);
     
     Ok(SavedImageResult {
         success: true,
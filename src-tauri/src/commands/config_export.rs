@@ -0,0 +1,274 @@
+//! Configuration snippets for other tools from a provider or relay station
+//!
+//! The applied config, a provider or a station is turned into the config of
+//! claude-code-router (`~/.claude-code-router/config.json`), Cline (its
+//! Anthropic provider settings) and continue.dev (`~/.continue/config.yaml`),
+//! so the workbench stays the one place upstreams are kept. Keys are left as
+//! a placeholder unless asked for, which then goes through the app lock.
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::app_lock::{self, ProtectedAction};
+use super::provider::{self, ProviderConfig};
+use super::relay_stations::{self, RelayStationManager};
+use crate::t;
+
+/// Stands in for the key when secrets are left out
+const KEY_PLACEHOLDER: &str = "YOUR_API_KEY";
+
+/// Model named when the config leaves the choice to Claude Code
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+/// What to export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigExportSource {
+    /// What Claude Code uses now, from settings.json
+    Current,
+    Provider {
+        id: String,
+    },
+    Station {
+        id: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExternalTool {
+    ClaudeCodeRouter,
+    Cline,
+    Continue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolConfigSnippet {
+    pub tool: ExternalTool,
+    pub format: SnippetFormat,
+    /// Where the tool reads it from
+    pub location: String,
+    pub content: String,
+}
+
+fn key_of(config: &ProviderConfig, include_secrets: bool) -> String {
+    match (&config.api_key, &config.auth_token) {
+        (Some(key), _) | (None, Some(key)) if include_secrets => key.clone(),
+        _ => KEY_PLACEHOLDER.to_string(),
+    }
+}
+
+/// Provider name usable in claude-code-router routes, e.g. "my-relay"
+fn slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "workbench".to_string()
+    } else {
+        slug
+    }
+}
+
+fn claude_code_router(config: &ProviderConfig, key: &str, base_url: &str) -> serde_json::Value {
+    let name = slug(&config.name);
+    let model = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    let mut models = vec![model];
+    if let Some(small) = config
+        .small_fast_model
+        .as_deref()
+        .filter(|small| *small != model)
+    {
+        models.push(small);
+    }
+    let mut router = json!({ "default": format!("{},{}", name, model) });
+    if let Some(small) = &config.small_fast_model {
+        router["background"] = json!(format!("{},{}", name, small));
+    }
+    json!({
+        "Providers": [{
+            "name": name,
+            "api_base_url": format!("{}/v1/messages", base_url),
+            "api_key": key,
+            "models": models,
+            "transformer": { "use": ["Anthropic"] },
+        }],
+        "Router": router,
+    })
+}
+
+fn cline(config: &ProviderConfig, key: &str, base_url: &str) -> serde_json::Value {
+    json!({
+        "apiProvider": "anthropic",
+        "anthropicBaseUrl": base_url,
+        "apiKey": key,
+        "apiModelId": config.model.as_deref().unwrap_or(DEFAULT_MODEL),
+    })
+}
+
+fn continue_dev(config: &ProviderConfig, key: &str, base_url: &str) -> serde_json::Value {
+    let model = |name: &str, roles: &[&str]| {
+        json!({
+            "name": format!("{} ({})", config.name, name),
+            "provider": "anthropic",
+            "model": name,
+            "apiKey": key,
+            "apiBase": format!("{}/v1/", base_url),
+            "roles": roles,
+        })
+    };
+    let main = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    let mut models = vec![model(main, &["chat", "edit", "apply"])];
+    if let Some(small) = config
+        .small_fast_model
+        .as_deref()
+        .filter(|small| *small != main)
+    {
+        models.push(model(small, &["autocomplete", "summarize"]));
+    }
+    json!({
+        "name": format!("{} from Claude Workbench", config.name),
+        "version": "1.0.0",
+        "schema": "v1",
+        "models": models,
+    })
+}
+
+/// The snippets for every tool
+pub fn render_snippets(
+    config: &ProviderConfig,
+    include_secrets: bool,
+) -> Result<Vec<ToolConfigSnippet>, String> {
+    let key = key_of(config, include_secrets);
+    let base_url = config.base_url.trim().trim_end_matches('/');
+    if base_url.is_empty() {
+        return Err(format!("{} has no base URL to export", config.name));
+    }
+    let json =
+        |value: &serde_json::Value| serde_json::to_string_pretty(value).map_err(|e| e.to_string());
+    Ok(vec![
+        ToolConfigSnippet {
+            tool: ExternalTool::ClaudeCodeRouter,
+            format: SnippetFormat::Json,
+            location: "~/.claude-code-router/config.json".to_string(),
+            content: json(&claude_code_router(config, &key, base_url))?,
+        },
+        ToolConfigSnippet {
+            tool: ExternalTool::Cline,
+            format: SnippetFormat::Json,
+            location: "Cline > API Provider: Anthropic, with a custom base URL".to_string(),
+            content: json(&cline(config, &key, base_url))?,
+        },
+        ToolConfigSnippet {
+            tool: ExternalTool::Continue,
+            format: SnippetFormat::Yaml,
+            location: "~/.continue/config.yaml".to_string(),
+            content: serde_yaml::to_string(&continue_dev(config, &key, base_url))
+                .map_err(|e| format!("Failed to write YAML: {}", e))?,
+        },
+    ])
+}
+
+fn current_config() -> Result<ProviderConfig, String> {
+    let current = provider::read_current_config()?;
+    let base_url = current
+        .anthropic_base_url
+        .ok_or("Claude Code is not configured with a base URL")?;
+    Ok(ProviderConfig {
+        id: "current".to_string(),
+        name: "Claude Workbench".to_string(),
+        description: String::new(),
+        base_url,
+        auth_token: current.anthropic_auth_token,
+        api_key: current.anthropic_api_key,
+        model: current.anthropic_model,
+        small_fast_model: current.anthropic_small_fast_model,
+        mirror_urls: Vec::new(),
+        binary_path: None,
+        default_args: Vec::new(),
+    })
+}
+
+/// Config snippets for claude-code-router, Cline and continue.dev; with
+/// `include_secrets` the key is filled in, once the app lock allows it
+#[tauri::command]
+pub async fn export_tool_configs(
+    source: ConfigExportSource,
+    include_secrets: Option<bool>,
+    app: AppHandle,
+) -> Result<Vec<ToolConfigSnippet>, String> {
+    let include_secrets = include_secrets.unwrap_or(false);
+    if include_secrets {
+        app_lock::require_unlocked(&app, ProtectedAction::RevealSecret).await?;
+    }
+    let config = match source {
+        ConfigExportSource::Current => current_config()?,
+        ConfigExportSource::Provider { id } => provider::find_provider(&id)?,
+        ConfigExportSource::Station { id } => {
+            let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+            let manager_lock = state
+                .lock()
+                .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+            let manager = manager_lock
+                .as_ref()
+                .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+            relay_stations::station_provider_config(manager, &id)?.0
+        }
+    };
+    render_snippets(&config, include_secrets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_snippets() {
+        let config = ProviderConfig {
+            id: "relay".to_string(),
+            name: "My Relay!".to_string(),
+            description: String::new(),
+            base_url: "https://relay.example.com/".to_string(),
+            auth_token: Some("sk-secret".to_string()),
+            api_key: None,
+            model: Some("claude-opus-4-1".to_string()),
+            small_fast_model: Some("claude-haiku-4-5".to_string()),
+            mirror_urls: Vec::new(),
+            binary_path: None,
+            default_args: Vec::new(),
+        };
+        let snippets = render_snippets(&config, false).unwrap();
+        assert!(snippets.iter().all(|s| !s.content.contains("sk-secret")));
+
+        let router: serde_json::Value = serde_json::from_str(&snippets[0].content).unwrap();
+        assert_eq!(
+            router["Providers"][0]["api_base_url"],
+            "https://relay.example.com/v1/messages"
+        );
+        assert_eq!(router["Router"]["default"], "my-relay,claude-opus-4-1");
+        assert_eq!(router["Router"]["background"], "my-relay,claude-haiku-4-5");
+
+        let snippets = render_snippets(&config, true).unwrap();
+        let cline: serde_json::Value = serde_json::from_str(&snippets[1].content).unwrap();
+        assert_eq!(cline["apiKey"], "sk-secret");
+        assert_eq!(cline["anthropicBaseUrl"], "https://relay.example.com");
+
+        let continue_dev: serde_json::Value = serde_yaml::from_str(&snippets[2].content).unwrap();
+        assert_eq!(
+            continue_dev["models"][0]["apiBase"],
+            "https://relay.example.com/v1/"
+        );
+        assert_eq!(continue_dev["models"][1]["roles"][0], "autocomplete");
+    }
+}
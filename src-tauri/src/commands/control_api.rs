@@ -0,0 +1,433 @@
+//! Optional token-protected REST API on localhost, so editor extensions and
+//! shell scripts can switch providers, apply relay stations and read running
+//! processes and usage
+//!
+//! Off by default. Every request must carry the token, as
+//! `Authorization: Bearer <token>` or `X-Workbench-Token: <token>`.
+use crate::commands::agents::AgentDb;
+use crate::commands::app_lock;
+use crate::commands::provider;
+use crate::commands::relay_stations::RelayStationManager;
+use crate::local_http::{self, HttpRequest};
+use crate::process::ProcessRegistryState;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::{TcpListener, TcpStream};
+
+/// app_settings key storing the control API settings (JSON)
+pub const CONTROL_API_SETTINGS_KEY: &str = "control_api_settings";
+
+const DEFAULT_PORT: u16 = 17878;
+
+const TOKEN_HEADER: &str = "x-workbench-token";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ControlApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Generated when the API is first enabled
+    pub token: String,
+}
+
+impl Default for ControlApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlApiStatus {
+    pub running: bool,
+    /// Base URL clients connect to while running
+    pub url: Option<String>,
+}
+
+struct RunningServer {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+/// The server accept loop, while running
+#[derive(Default)]
+pub struct ControlApiState(Mutex<Option<RunningServer>>);
+
+pub fn load_control_api_settings(conn: &rusqlite::Connection) -> ControlApiSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![CONTROL_API_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_control_api_settings(
+    conn: &rusqlite::Connection,
+    settings: &ControlApiSettings,
+) -> Result<(), String> {
+    let value = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![CONTROL_API_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save control API settings: {}", e))?;
+    Ok(())
+}
+
+pub(crate) fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// The token to save: the saved one when the form sent back nothing or the
+/// masked value it was shown
+fn submitted_token(submitted: &str, saved: String) -> String {
+    if submitted.is_empty() || provider::is_masked(submitted) {
+        saved
+    } else {
+        submitted.to_string()
+    }
+}
+
+/// `settings` with the token masked while the app lock hides secrets
+fn mask_token(app: &AppHandle, mut settings: ControlApiSettings) -> ControlApiSettings {
+    if !settings.token.is_empty() && app_lock::hides_secrets(app) {
+        settings.token = provider::mask_secret(&settings.token);
+    }
+    settings
+}
+
+/// Endpoints of the API
+#[derive(Debug, PartialEq)]
+enum Route<'a> {
+    Status,
+    Providers,
+    SwitchProvider(&'a str),
+    Stations,
+    ApplyStation(&'a str),
+    Processes,
+    Usage,
+}
+
+fn route<'a>(method: &str, path: &'a str) -> Option<Route<'a>> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "status"]) => Some(Route::Status),
+        ("GET", ["v1", "providers"]) => Some(Route::Providers),
+        ("POST", ["v1", "providers", id, "switch"]) => Some(Route::SwitchProvider(id)),
+        ("GET", ["v1", "stations"]) => Some(Route::Stations),
+        ("POST", ["v1", "stations", id, "apply"]) => Some(Route::ApplyStation(id)),
+        ("GET", ["v1", "processes"]) => Some(Route::Processes),
+        ("GET", ["v1", "usage"]) => Some(Route::Usage),
+        _ => None,
+    }
+}
+
+fn list_stations(app: &AppHandle) -> Result<Value, String> {
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let manager_lock = state.lock().map_err(|e| e.to_string())?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or("Relay station manager is not initialized")?;
+    let stations = manager
+        .list_stations()
+        .map_err(|e| format!("Failed to list relay stations: {}", e))?;
+    let usage = manager.get_config_usage_status().unwrap_or_default();
+    Ok(stations
+        .into_iter()
+        .map(|station| {
+            let applied = usage.iter().find(|status| status.station_id == station.id);
+            json!({
+                "id": station.id,
+                "name": station.name,
+                "api_url": station.api_url,
+                "enabled": station.enabled,
+                // Only stations applied once in the app can be applied here
+                "configured": applied.is_some(),
+                "active": applied.is_some_and(|status| status.is_active),
+            })
+        })
+        .collect())
+}
+
+/// Run an endpoint; errors become `(status, message)`
+async fn dispatch(app: &AppHandle, request: &HttpRequest) -> Result<Value, (u16, String)> {
+    let internal = |e: String| (500, e);
+    let Some(route) = route(&request.method, &request.path) else {
+        return Err((
+            404,
+            format!("No endpoint {} {}", request.method, request.path),
+        ));
+    };
+    match route {
+        Route::Status => Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "current_provider": provider::get_current_provider_id().map_err(internal)?,
+        })),
+        Route::Providers => {
+            let current = provider::get_current_provider_id().map_err(internal)?;
+            let providers = provider::load_providers_from_file().map_err(internal)?;
+            // Never hand out tokens and keys
+            Ok(providers
+                .into_iter()
+                .map(|config| {
+                    json!({
+                        "active": current.as_deref() == Some(config.id.as_str()),
+                        "id": config.id,
+                        "name": config.name,
+                        "description": config.description,
+                        "base_url": config.base_url,
+                        "model": config.model,
+                    })
+                })
+                .collect())
+        }
+        Route::SwitchProvider(id) => {
+            let config = provider::find_provider(id).map_err(|e| (404, e))?;
+            let message = provider::switch_provider(app.clone(), config)
+                .await
+                .map_err(internal)?;
+            let _ = app.emit("provider-changed", id);
+            Ok(json!({ "message": message }))
+        }
+        Route::Stations => list_stations(app).map_err(internal),
+        Route::ApplyStation(id) => {
+            let message = crate::tray::switch_station(app, id)
+                .await
+                .map_err(|e| (400, e))?;
+            let _ = app.emit("provider-changed", id);
+            Ok(json!({ "message": message }))
+        }
+        Route::Processes => {
+            let registry = app.state::<ProcessRegistryState>();
+            let processes = registry
+                .0
+                .list_managed_processes()
+                .await
+                .map_err(internal)?;
+            serde_json::to_value(processes).map_err(|e| internal(e.to_string()))
+        }
+        Route::Usage => {
+            let days = match request.query_param("days") {
+                Some(days) => Some(
+                    days.parse::<u32>()
+                        .map_err(|_| (400, format!("Invalid days: {}", days)))?,
+                ),
+                None => None,
+            };
+            let stats =
+                tokio::task::spawn_blocking(move || crate::commands::usage::get_usage_stats(days))
+                    .await
+                    .map_err(|e| internal(e.to_string()))?
+                    .map_err(internal)?;
+            serde_json::to_value(stats).map_err(|e| internal(e.to_string()))
+        }
+    }
+}
+
+async fn handle_connection(app: AppHandle, token: String, mut stream: TcpStream) {
+    let request = match local_http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            log::debug!("Control API: bad request: {}", e);
+            let body = json!({ "error": e.to_string() });
+            let _ = local_http::write_json(&mut stream, 400, &body).await;
+            return;
+        }
+    };
+    let authorized = request
+        .token(TOKEN_HEADER)
+        .is_some_and(|given| local_http::secrets_match(given, &token));
+    let (status, body) = if !authorized {
+        (401, json!({ "error": "Missing or invalid token" }))
+    } else {
+        match dispatch(&app, &request).await {
+            Ok(data) => (200, json!({ "data": data })),
+            Err((status, error)) => (status, json!({ "error": error })),
+        }
+    };
+    log::info!(
+        "Control API: {} {} -> {}",
+        request.method,
+        request.path,
+        status
+    );
+    if let Err(e) = local_http::write_json(&mut stream, status, &body).await {
+        log::debug!("Control API: failed to write response: {}", e);
+    }
+}
+
+fn stop(app: &AppHandle) {
+    let state = app.state::<ControlApiState>();
+    let Ok(mut running) = state.0.lock() else {
+        return;
+    };
+    if let Some(server) = running.take() {
+        server.task.abort();
+        log::info!("Control API on port {} stopped", server.port);
+    }
+}
+
+/// (Re)start the server with the saved settings, or stop it when disabled
+pub async fn restart(app: &AppHandle) -> Result<(), String> {
+    stop(app);
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_control_api_settings(&conn)
+    };
+    if !settings.enabled || settings.token.is_empty() {
+        return Ok(());
+    }
+
+    // Loopback only: the API is never reachable from the network
+    let listener = TcpListener::bind(("127.0.0.1", settings.port))
+        .await
+        .map_err(|e| format!("Failed to listen on port {}: {}", settings.port, e))?;
+    log::info!("Control API listening on 127.0.0.1:{}", settings.port);
+
+    let server_app = app.clone();
+    let token = settings.token;
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(
+                        server_app.clone(),
+                        token.clone(),
+                        stream,
+                    ));
+                }
+                Err(e) => log::warn!("Control API: failed to accept a connection: {}", e),
+            }
+        }
+    });
+    let state = app.state::<ControlApiState>();
+    let mut running = state.0.lock().map_err(|e| e.to_string())?;
+    *running = Some(RunningServer {
+        port: settings.port,
+        task,
+    });
+    Ok(())
+}
+
+/// Start the server if enabled; called once from the app setup
+pub fn start_control_api(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = restart(&app).await {
+            log::warn!("Failed to start the control API: {}", e);
+        }
+    });
+}
+
+/// The settings, with the token masked while the app lock hides secrets
+#[tauri::command]
+pub async fn get_control_api_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<ControlApiSettings, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_control_api_settings(&conn)
+    };
+    Ok(mask_token(&app, settings))
+}
+
+/// Save the settings and apply them; returns them with the generated token
+///
+/// An empty or masked token keeps the saved one.
+#[tauri::command]
+pub async fn set_control_api_settings(
+    app: AppHandle,
+    settings: ControlApiSettings,
+) -> Result<ControlApiSettings, String> {
+    let mut settings = settings;
+    if settings.port == 0 {
+        return Err("Port must not be 0".to_string());
+    }
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        settings.token = submitted_token(&settings.token, load_control_api_settings(&conn).token);
+        if settings.enabled && settings.token.is_empty() {
+            settings.token = generate_token();
+        }
+        save_control_api_settings(&conn, &settings)?;
+    }
+    restart(&app).await?;
+    Ok(mask_token(&app, settings))
+}
+
+/// Replace the token, locking out every client using the old one
+#[tauri::command]
+pub async fn regenerate_control_api_token(app: AppHandle) -> Result<ControlApiSettings, String> {
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = ControlApiSettings {
+            token: generate_token(),
+            ..load_control_api_settings(&conn)
+        };
+        save_control_api_settings(&conn, &settings)?;
+        settings
+    };
+    restart(&app).await?;
+    Ok(mask_token(&app, settings))
+}
+
+#[tauri::command]
+pub async fn get_control_api_status(
+    state: State<'_, ControlApiState>,
+) -> Result<ControlApiStatus, String> {
+    let running = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(ControlApiStatus {
+        running: running.is_some(),
+        url: running
+            .as_ref()
+            .map(|server| format!("http://127.0.0.1:{}", server.port)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("GET", "/v1/status"), Some(Route::Status));
+        assert_eq!(
+            route("POST", "/v1/providers/anyrouter/switch"),
+            Some(Route::SwitchProvider("anyrouter"))
+        );
+        assert_eq!(
+            route("POST", "/v1/stations/1b2c/apply/"),
+            Some(Route::ApplyStation("1b2c"))
+        );
+        assert_eq!(route("GET", "/v1/providers/anyrouter/switch"), None);
+        assert_eq!(route("DELETE", "/v1/processes"), None);
+        assert_eq!(route("GET", "/v2/status"), None);
+        assert_eq!(generate_token().len(), 64);
+    }
+
+    #[test]
+    fn test_masked_token_keeps_the_saved_one() {
+        let saved = generate_token();
+        let masked = provider::mask_secret(&saved);
+        assert_eq!(submitted_token(&masked, saved.clone()), saved);
+        assert_eq!(submitted_token("", saved.clone()), saved);
+        let replaced = generate_token();
+        assert_eq!(submitted_token(&replaced, saved), replaced);
+    }
+}
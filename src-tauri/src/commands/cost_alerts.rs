@@ -0,0 +1,569 @@
+//! Cost alert rules: thresholds on spend, per-model spend and request counts,
+//! checked every few minutes against local Claude Code usage and the spend
+//! relay stations report
+//!
+//! A tripped rule shows a notification, emits `cost-alert-triggered` and is
+//! sent to the webhooks routed `budget_exceeded`, at most once per period.
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::usage::UsageEntry;
+use crate::commands::webhooks::{self, WebhookEvent, WebhookMessage};
+use crate::t;
+
+/// Seconds between two checks of the rules
+const CHECK_INTERVAL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// USD spent
+    #[default]
+    Spend,
+    /// USD spent on models whose name contains the rule's `model`
+    ModelSpend,
+    /// Requests sent
+    RequestCount,
+}
+
+/// Where usage is read from; model spend and request counts only exist locally
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSource {
+    /// Claude Code sessions on this machine
+    Local,
+    /// Spend shown by the balance snapshots of all relay stations
+    Stations,
+    /// The larger of both: sessions routed through a station show up in
+    /// both, so adding them up would count them twice
+    #[default]
+    All,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertPeriod {
+    #[default]
+    Day,
+    /// Since Monday
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostAlertRule {
+    pub id: Option<i64>,
+    pub name: String,
+    pub metric: AlertMetric,
+    #[serde(default)]
+    pub source: AlertSource,
+    /// Model name or part of it, for `model_spend`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// USD for spend metrics, a number of requests for `request_count`
+    pub threshold: f64,
+    #[serde(default)]
+    pub period: AlertPeriod,
+    pub enabled: bool,
+    /// Period the rule last tripped in, e.g. "2026-10-16", "2026-W42" or "2026-10"
+    #[serde(default)]
+    pub last_triggered: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+/// A rule with the current value of its metric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostAlertStatus {
+    pub rule: CostAlertRule,
+    pub value: f64,
+    /// The value reached the threshold in the current period
+    pub tripped: bool,
+}
+
+/// Usage of one period
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub local_spend: f64,
+    pub local_requests: u64,
+    pub model_spend: HashMap<String, f64>,
+    pub station_spend: f64,
+}
+
+pub fn create_cost_alert_rules_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS cost_alert_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            source TEXT NOT NULL,
+            model TEXT,
+            threshold REAL NOT NULL,
+            period TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            last_triggered TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Enum as stored: its serde name
+pub(crate) fn to_column<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+pub(crate) fn from_column<T: for<'de> Deserialize<'de> + Default>(value: String) -> T {
+    serde_json::from_value(serde_json::Value::String(value)).unwrap_or_default()
+}
+
+const RULE_COLUMNS: &str =
+    "id, name, metric, source, model, threshold, period, enabled, last_triggered, created_at";
+
+fn read_rule(row: &rusqlite::Row) -> rusqlite::Result<CostAlertRule> {
+    Ok(CostAlertRule {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        metric: from_column(row.get(2)?),
+        source: from_column(row.get(3)?),
+        model: row.get(4)?,
+        threshold: row.get(5)?,
+        period: from_column(row.get(6)?),
+        enabled: row.get(7)?,
+        last_triggered: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+fn load_rules(conn: &Connection) -> Result<Vec<CostAlertRule>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM cost_alert_rules ORDER BY id",
+            RULE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rules = stmt
+        .query_map([], read_rule)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rules)
+}
+
+fn load_rule(conn: &Connection, id: i64) -> Result<CostAlertRule, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM cost_alert_rules WHERE id = ?1",
+            RULE_COLUMNS
+        ),
+        params![id],
+        read_rule,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Cost alert rule {} not found", id))
+}
+
+fn validate_rule(rule: &CostAlertRule) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("The rule needs a name".to_string());
+    }
+    if !rule.threshold.is_finite() || rule.threshold <= 0.0 {
+        return Err("The threshold must be greater than 0".to_string());
+    }
+    if rule.metric == AlertMetric::ModelSpend
+        && rule
+            .model
+            .as_deref()
+            .is_none_or(|model| model.trim().is_empty())
+    {
+        return Err("Model spend rules need a model".to_string());
+    }
+    if rule.metric != AlertMetric::Spend && rule.source == AlertSource::Stations {
+        return Err("Stations only report spend; use the local source".to_string());
+    }
+    Ok(())
+}
+
+/// Local start of the period `now` falls in
+pub fn period_start(period: AlertPeriod, now: DateTime<Local>) -> DateTime<Local> {
+    let date = now.date_naive();
+    let first_day = match period {
+        AlertPeriod::Day => date,
+        AlertPeriod::Week => {
+            date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+        }
+        AlertPeriod::Month => date.with_day(1).unwrap_or(date),
+    };
+    Local
+        .from_local_datetime(&first_day.and_time(NaiveTime::MIN))
+        .earliest()
+        .unwrap_or(now)
+}
+
+/// Name of the period `now` falls in, to trip a rule once per period
+pub fn period_key(period: AlertPeriod, now: DateTime<Local>) -> String {
+    match period {
+        AlertPeriod::Day => now.format("%Y-%m-%d").to_string(),
+        AlertPeriod::Week => {
+            let week = now.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        AlertPeriod::Month => now.format("%Y-%m").to_string(),
+    }
+}
+
+/// Local usage of the entries made since `since`
+pub fn local_totals(entries: &[UsageEntry], since: DateTime<Local>) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    for entry in entries {
+        let recent = DateTime::parse_from_rfc3339(&entry.timestamp)
+            .is_ok_and(|time| time.with_timezone(&Utc) >= since.with_timezone(&Utc));
+        if !recent {
+            continue;
+        }
+        totals.local_spend += entry.cost;
+        totals.local_requests += 1;
+        *totals.model_spend.entry(entry.model.clone()).or_default() += entry.cost;
+    }
+    totals
+}
+
+/// Current value of a rule's metric
+pub fn metric_value(rule: &CostAlertRule, totals: &UsageTotals) -> f64 {
+    match rule.metric {
+        AlertMetric::Spend => match rule.source {
+            AlertSource::Local => totals.local_spend,
+            AlertSource::Stations => totals.station_spend,
+            AlertSource::All => totals.local_spend.max(totals.station_spend),
+        },
+        AlertMetric::ModelSpend => {
+            let wanted = rule.model.as_deref().unwrap_or_default().to_lowercase();
+            totals
+                .model_spend
+                .iter()
+                .filter(|(model, _)| model.to_lowercase().contains(&wanted))
+                .map(|(_, cost)| cost)
+                .sum()
+        }
+        AlertMetric::RequestCount => totals.local_requests as f64,
+    }
+}
+
+/// Usage of every period the rules look at
+fn gather_totals(
+    conn: &Connection,
+    rules: &[CostAlertRule],
+    entries: &[UsageEntry],
+    now: DateTime<Local>,
+) -> Result<HashMap<AlertPeriod, UsageTotals>, String> {
+    let mut totals = HashMap::new();
+    for rule in rules {
+        if totals.contains_key(&rule.period) {
+            continue;
+        }
+        let since = period_start(rule.period, now);
+        let mut period_totals = local_totals(entries, since);
+        period_totals.station_spend =
+            crate::commands::relay_forecast::spent_since(conn, since.timestamp())?;
+        totals.insert(rule.period, period_totals);
+    }
+    Ok(totals)
+}
+
+fn statuses(
+    rules: Vec<CostAlertRule>,
+    totals: &HashMap<AlertPeriod, UsageTotals>,
+) -> Vec<CostAlertStatus> {
+    rules
+        .into_iter()
+        .map(|rule| {
+            let value = totals
+                .get(&rule.period)
+                .map(|totals| metric_value(&rule, totals))
+                .unwrap_or_default();
+            CostAlertStatus {
+                tripped: rule.enabled && value >= rule.threshold,
+                value,
+                rule,
+            }
+        })
+        .collect()
+}
+
+/// Rules with their values for usage entries already read
+pub(crate) fn statuses_for(
+    conn: &Connection,
+    entries: &[UsageEntry],
+    now: DateTime<Local>,
+) -> Result<Vec<CostAlertStatus>, String> {
+    let rules = load_rules(conn)?;
+    let totals = gather_totals(conn, &rules, entries, now)?;
+    Ok(statuses(rules, &totals))
+}
+
+/// Rules with their current values; reads the usage logs, so call it off the
+/// async runtime
+fn evaluate(app: &AppHandle) -> Result<Vec<CostAlertStatus>, String> {
+    let rules = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_rules(&conn)?
+    };
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+    let entries = match dirs::home_dir() {
+        Some(home) => crate::commands::usage::get_all_usage_entries(&home.join(".claude")),
+        None => Vec::new(),
+    };
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let totals = gather_totals(&conn, &rules, &entries, Local::now())?;
+    Ok(statuses(rules, &totals))
+}
+
+fn alert_text(status: &CostAlertStatus) -> (String, String) {
+    let rule = &status.rule;
+    let period = match rule.period {
+        AlertPeriod::Day => t!("cost_alerts.period_day"),
+        AlertPeriod::Week => t!("cost_alerts.period_week"),
+        AlertPeriod::Month => t!("cost_alerts.period_month"),
+    };
+    let title = t!("cost_alerts.title", "name" => &rule.name);
+    let body = match rule.metric {
+        AlertMetric::Spend => t!(
+            "cost_alerts.spend_body",
+            "value" => &format!("{:.2}", status.value),
+            "threshold" => &format!("{:.2}", rule.threshold),
+            "period" => &period
+        ),
+        AlertMetric::ModelSpend => t!(
+            "cost_alerts.model_spend_body",
+            "value" => &format!("{:.2}", status.value),
+            "threshold" => &format!("{:.2}", rule.threshold),
+            "model" => rule.model.as_deref().unwrap_or_default(),
+            "period" => &period
+        ),
+        AlertMetric::RequestCount => t!(
+            "cost_alerts.request_count_body",
+            "value" => &format!("{}", status.value),
+            "threshold" => &format!("{}", rule.threshold),
+            "period" => &period
+        ),
+    };
+    (title, body)
+}
+
+/// Alert about every rule that tripped for the first time in its period
+fn check_rules(app: &AppHandle) -> Result<(), String> {
+    let now = Local::now();
+    for status in evaluate(app)? {
+        let key = period_key(status.rule.period, now);
+        if !status.tripped || status.rule.last_triggered.as_deref() == Some(key.as_str()) {
+            continue;
+        }
+        {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.execute(
+                "UPDATE cost_alert_rules SET last_triggered = ?1 WHERE id = ?2",
+                params![key, status.rule.id],
+            )
+            .map_err(|e| format!("Failed to update cost alert rule: {}", e))?;
+        }
+        log::info!(
+            "Cost alert '{}' tripped: {} >= {}",
+            status.rule.name,
+            status.value,
+            status.rule.threshold
+        );
+
+        let (title, body) = alert_text(&status);
+        if let Err(e) = crate::commands::notifications::show(app, &title, &body) {
+            log::warn!("{}", e);
+        }
+        let _ = app.emit("cost-alert-triggered", &status);
+        webhooks::dispatch(
+            app,
+            WebhookMessage {
+                event: WebhookEvent::BudgetExceeded,
+                title,
+                text: body,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Check the rules every few minutes; called once from the app setup
+pub fn start_cost_alert_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+            let check_app = app.clone();
+            match tokio::task::spawn_blocking(move || check_rules(&check_app)).await {
+                Ok(Err(e)) => log::warn!("Failed to check cost alerts: {}", e),
+                Err(e) => log::warn!("Cost alert check panicked: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_cost_alert_rules(db: State<'_, AgentDb>) -> Result<Vec<CostAlertRule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_rules(&conn)
+}
+
+#[tauri::command]
+pub async fn create_cost_alert_rule(
+    db: State<'_, AgentDb>,
+    rule: CostAlertRule,
+) -> Result<CostAlertRule, String> {
+    validate_rule(&rule)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO cost_alert_rules (name, metric, source, model, threshold, period, enabled, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            rule.name.trim(),
+            to_column(&rule.metric),
+            to_column(&rule.source),
+            rule.model,
+            rule.threshold,
+            to_column(&rule.period),
+            rule.enabled,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(|e| format!("Failed to create cost alert rule: {}", e))?;
+    load_rule(&conn, conn.last_insert_rowid())
+}
+
+/// Update a rule; a changed rule may trip again in the current period
+#[tauri::command]
+pub async fn update_cost_alert_rule(
+    db: State<'_, AgentDb>,
+    rule: CostAlertRule,
+) -> Result<CostAlertRule, String> {
+    let id = rule.id.ok_or("Rule ID is required")?;
+    validate_rule(&rule)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE cost_alert_rules SET name = ?1, metric = ?2, source = ?3, model = ?4,
+             threshold = ?5, period = ?6, enabled = ?7, last_triggered = NULL WHERE id = ?8",
+            params![
+                rule.name.trim(),
+                to_column(&rule.metric),
+                to_column(&rule.source),
+                rule.model,
+                rule.threshold,
+                to_column(&rule.period),
+                rule.enabled,
+                id,
+            ],
+        )
+        .map_err(|e| format!("Failed to update cost alert rule: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Cost alert rule {} not found", id));
+    }
+    load_rule(&conn, id)
+}
+
+#[tauri::command]
+pub async fn delete_cost_alert_rule(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM cost_alert_rules WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete cost alert rule: {}", e))?;
+    Ok(())
+}
+
+/// Every rule with the current value of its metric, without alerting
+#[tauri::command]
+pub async fn evaluate_cost_alerts(app: AppHandle) -> Result<Vec<CostAlertStatus>, String> {
+    tokio::task::spawn_blocking(move || evaluate(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, model: &str, cost: f64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: model.to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: String::new(),
+            project_path: String::new(),
+            api_base_url: String::new(),
+        }
+    }
+
+    fn rule(metric: AlertMetric, source: AlertSource, threshold: f64) -> CostAlertRule {
+        CostAlertRule {
+            id: None,
+            name: "Budget".to_string(),
+            metric,
+            source,
+            model: Some("opus".to_string()),
+            threshold,
+            period: AlertPeriod::Day,
+            enabled: true,
+            last_triggered: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_totals_and_metrics() {
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 15, 0, 0).unwrap();
+        let today = period_start(AlertPeriod::Day, now);
+        let at = |hours: i64| (today + Duration::hours(hours)).to_rfc3339();
+        let entries = vec![
+            entry(&at(-2), "claude-opus-4-1", 9.0),
+            entry(&at(9), "claude-opus-4-1", 3.0),
+            entry(&at(10), "claude-sonnet-4-5", 1.5),
+        ];
+        let mut totals = local_totals(&entries, today);
+        assert_eq!(totals.local_requests, 2);
+        assert_eq!(totals.local_spend, 4.5);
+        totals.station_spend = 6.0;
+
+        let spend = |source| metric_value(&rule(AlertMetric::Spend, source, 5.0), &totals);
+        assert_eq!(spend(AlertSource::Local), 4.5);
+        assert_eq!(spend(AlertSource::Stations), 6.0);
+        assert_eq!(spend(AlertSource::All), 6.0);
+        let model = rule(AlertMetric::ModelSpend, AlertSource::Local, 2.0);
+        assert_eq!(metric_value(&model, &totals), 3.0);
+        let requests = rule(AlertMetric::RequestCount, AlertSource::Local, 2.0);
+        let tripped = statuses(vec![requests], &HashMap::from([(AlertPeriod::Day, totals)]));
+        assert!(tripped[0].tripped);
+
+        assert!(
+            validate_rule(&rule(AlertMetric::RequestCount, AlertSource::Stations, 1.0)).is_err()
+        );
+        assert!(validate_rule(&rule(AlertMetric::Spend, AlertSource::All, 0.0)).is_err());
+
+        assert_eq!(period_key(AlertPeriod::Week, now), "2026-W42");
+        assert_eq!(period_key(AlertPeriod::Month, now), "2026-10");
+        assert_eq!(period_start(AlertPeriod::Week, now).day(), 12);
+        assert_eq!(period_start(AlertPeriod::Month, now).day(), 1);
+    }
+}
@@ -0,0 +1,579 @@
+//! Display currency for cost figures
+//!
+//! Costs are computed in USD everywhere. Commands returning costs wrap their
+//! result in [`Converted`], which adds a `<field>_converted` sibling to every
+//! amount and a top-level `conversion` object saying which currency and rate
+//! were used; lists come as `{items, conversion}`. Station balances are only
+//! converted for stations billing in USD, see [`STATION_CURRENCY_KEY`].
+//! Rates are fetched at most once a day and only while a currency other than
+//! USD is chosen without a manual rate. Offline, the last fetched rates are
+//! used, then a bundled table.
+use crate::commands::agents::AgentDb;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// app_settings key storing the chosen currency (JSON)
+pub const CURRENCY_SETTINGS_KEY: &str = "currency_settings";
+
+/// app_settings key caching the last fetched exchange rates (JSON)
+pub const EXCHANGE_RATES_KEY: &str = "exchange_rates";
+
+/// USD based rates, free and without an API key
+const RATES_URL: &str = "https://open.er-api.com/v6/latest/USD";
+
+/// Fetched rates older than this are refreshed
+const RATE_MAX_AGE_SECS: i64 = 24 * 3600;
+
+/// Seconds between two checks whether the rates need refreshing
+const REFRESH_CHECK_SECS: u64 = 3600;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Units per USD used when no rates could ever be fetched
+const BUNDLED_RATES: &[(&str, f64)] = &[
+    ("AUD", 1.53),
+    ("CAD", 1.40),
+    ("CNY", 7.12),
+    ("EUR", 0.86),
+    ("GBP", 0.75),
+    ("HKD", 7.78),
+    ("INR", 88.7),
+    ("JPY", 151.0),
+    ("KRW", 1420.0),
+    ("RUB", 81.0),
+    ("SGD", 1.30),
+    ("TWD", 30.6),
+];
+
+/// Fields holding USD amounts, converted wherever they appear in a result
+const AMOUNT_FIELDS: &[&str] = &[
+    "cost",
+    "total_cost",
+    "cost_usd",
+    "local_cost_usd",
+    "station_cost_usd",
+    "cost_difference_usd",
+    "balance",
+    "balance_remaining",
+    "amount_used",
+    "daily_spend",
+    "weekly_spend",
+    "projected_monthly_spend",
+];
+
+/// Amount fields read from a station's balance, in the station's currency
+const STATION_AMOUNT_FIELDS: &[&str] = &[
+    "balance",
+    "balance_remaining",
+    "amount_used",
+    "daily_spend",
+    "weekly_spend",
+    "projected_monthly_spend",
+];
+
+/// `adapter_config` key naming the currency a station bills in; USD if unset
+pub const STATION_CURRENCY_KEY: &str = "currency";
+
+/// Field results carry a station's currency in, next to its balance
+const STATION_CURRENCY_FIELD: &str = "station_currency";
+
+/// The currency a station's balance is in, from its `adapter_config`
+pub fn station_currency(adapter_config: Option<&HashMap<String, Value>>) -> String {
+    adapter_config
+        .and_then(|config| config.get(STATION_CURRENCY_KEY))
+        .and_then(Value::as_str)
+        .map(|currency| currency.trim().to_uppercase())
+        .filter(|currency| !currency.is_empty())
+        .unwrap_or_else(|| "USD".to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrencySettings {
+    /// ISO 4217 code costs are shown in
+    pub currency: String,
+    /// Units of `currency` per USD, instead of fetched rates
+    pub manual_rate: Option<f64>,
+}
+
+impl Default for CurrencySettings {
+    fn default() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            manual_rate: None,
+        }
+    }
+}
+
+impl CurrencySettings {
+    /// Whether converting needs fetched rates
+    fn needs_rates(&self) -> bool {
+        self.currency != "USD" && self.manual_rate.is_none()
+    }
+}
+
+/// Exchange rates as last fetched
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExchangeRates {
+    /// Units per USD, by currency code
+    pub rates: HashMap<String, f64>,
+    /// Unix timestamp the provider last updated the rates at
+    pub updated_at: i64,
+    pub fetched_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    /// Costs are shown in USD
+    Base,
+    /// Set by the user
+    Manual,
+    /// Fetched within the last day
+    Live,
+    /// Fetched earlier; the rates could not be refreshed since
+    Cached,
+    /// Bundled with the app; no rates were ever fetched
+    Bundled,
+    /// No rate is known for the currency; amounts are not converted
+    Unavailable,
+}
+
+/// How amounts are converted at the moment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conversion {
+    pub currency: String,
+    /// Units of `currency` per USD
+    pub rate: Option<f64>,
+    pub source: RateSource,
+    /// Unix timestamp of the rate, for fetched rates
+    pub rate_updated_at: Option<i64>,
+}
+
+impl Conversion {
+    fn base() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            rate: Some(1.0),
+            source: RateSource::Base,
+            rate_updated_at: None,
+        }
+    }
+
+    /// Add converted siblings of the amounts in a serialized result, and the
+    /// conversion itself at the top; a list becomes `{items, conversion}`
+    pub fn annotate(&self, value: &mut Value) {
+        let conversion = serde_json::to_value(self).unwrap_or(Value::Null);
+        self.convert_nested(value);
+        match value {
+            Value::Object(map) => {
+                map.insert("conversion".to_string(), conversion);
+            }
+            Value::Array(items) => {
+                let items = std::mem::take(items);
+                *value = serde_json::json!({ "items": items, "conversion": conversion });
+            }
+            _ => {}
+        }
+    }
+
+    fn convert_fields(&self, map: &mut Map<String, Value>) {
+        // A balance in another currency than USD would be converted as if it were USD
+        let foreign_station = map
+            .get(STATION_CURRENCY_FIELD)
+            .and_then(Value::as_str)
+            .is_some_and(|currency| currency != "USD");
+        let mut converted = Vec::new();
+        for (key, field) in map.iter_mut() {
+            if foreign_station && STATION_AMOUNT_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+            if AMOUNT_FIELDS.contains(&key.as_str()) {
+                if let Some(amount) = self.convert_amount(field) {
+                    converted.push((format!("{}_converted", key), amount));
+                }
+            } else {
+                self.convert_nested(field);
+            }
+        }
+        map.extend(converted);
+    }
+
+    fn convert_nested(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => self.convert_fields(map),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.convert_nested(item)),
+            _ => {}
+        }
+    }
+
+    /// An amount, a missing amount or a list of amounts in the currency
+    fn convert_amount(&self, amount: &Value) -> Option<Value> {
+        match amount {
+            Value::Number(number) => number.as_f64().map(|usd| self.to_currency(usd)),
+            Value::Null => Some(Value::Null),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.convert_amount(item))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            _ => None,
+        }
+    }
+
+    fn to_currency(&self, usd: f64) -> Value {
+        match self.rate {
+            Some(rate) => Value::from(usd * rate),
+            None => Value::Null,
+        }
+    }
+}
+
+/// A command result serialized with converted amounts
+#[derive(Debug)]
+pub struct Converted<T>(pub T);
+
+impl<T: Serialize> Serialize for Converted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(&self.0).map_err(serde::ser::Error::custom)?;
+        current().annotate(&mut value);
+        value.serialize(serializer)
+    }
+}
+
+static CONVERSION: RwLock<Option<Conversion>> = RwLock::new(None);
+
+/// The conversion in effect
+pub fn current() -> Conversion {
+    CONVERSION
+        .read()
+        .ok()
+        .and_then(|conversion| conversion.clone())
+        .unwrap_or_else(Conversion::base)
+}
+
+/// Rate to convert with, from the best source available
+pub fn resolve(settings: &CurrencySettings, rates: Option<&ExchangeRates>, now: i64) -> Conversion {
+    let currency = settings.currency.clone();
+    if currency == "USD" {
+        return Conversion::base();
+    }
+    if let Some(rate) = settings.manual_rate {
+        return Conversion {
+            currency,
+            rate: Some(rate),
+            source: RateSource::Manual,
+            rate_updated_at: None,
+        };
+    }
+    if let Some(rates) = rates {
+        if let Some(rate) = rates.rates.get(&currency) {
+            let source = if now - rates.fetched_at < RATE_MAX_AGE_SECS {
+                RateSource::Live
+            } else {
+                RateSource::Cached
+            };
+            return Conversion {
+                currency,
+                rate: Some(*rate),
+                source,
+                rate_updated_at: Some(rates.updated_at),
+            };
+        }
+    }
+    let bundled = BUNDLED_RATES
+        .iter()
+        .find(|(code, _)| *code == currency)
+        .map(|(_, rate)| *rate);
+    Conversion {
+        currency,
+        rate: bundled,
+        source: if bundled.is_some() {
+            RateSource::Bundled
+        } else {
+            RateSource::Unavailable
+        },
+        rate_updated_at: None,
+    }
+}
+
+pub fn load_currency_settings(conn: &rusqlite::Connection) -> CurrencySettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![CURRENCY_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn load_exchange_rates(conn: &rusqlite::Connection) -> Option<ExchangeRates> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![EXCHANGE_RATES_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+}
+
+fn save_setting<T: Serialize>(
+    conn: &rusqlite::Connection,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let value = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![key, value],
+    )
+    .map_err(|e| format!("Failed to save currency settings: {}", e))?;
+    Ok(())
+}
+
+/// Recompute the conversion in effect from the stored settings and rates
+pub fn reload(conn: &rusqlite::Connection) -> Conversion {
+    let conversion = resolve(
+        &load_currency_settings(conn),
+        load_exchange_rates(conn).as_ref(),
+        chrono::Utc::now().timestamp(),
+    );
+    if let Ok(mut current) = CONVERSION.write() {
+        *current = Some(conversion.clone());
+    }
+    conversion
+}
+
+fn validate_settings(settings: CurrencySettings) -> Result<CurrencySettings, String> {
+    let currency = settings.currency.trim().to_ascii_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "Invalid currency code '{}': use ISO 4217, e.g. CNY",
+            settings.currency
+        ));
+    }
+    if let Some(rate) = settings.manual_rate {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err("The exchange rate must be greater than 0".to_string());
+        }
+    }
+    Ok(CurrencySettings {
+        currency,
+        manual_rate: settings.manual_rate,
+    })
+}
+
+async fn fetch_rates() -> Result<ExchangeRates, String> {
+    #[derive(Deserialize)]
+    struct RatesResponse {
+        result: String,
+        #[serde(default)]
+        rates: HashMap<String, f64>,
+        #[serde(default)]
+        time_last_update_unix: i64,
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response: RatesResponse = client
+        .get(RATES_URL)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch exchange rates: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid exchange rates: {}", e))?;
+    if response.result != "success" || response.rates.is_empty() {
+        return Err(format!(
+            "Failed to fetch exchange rates: {}",
+            response.result
+        ));
+    }
+    Ok(ExchangeRates {
+        rates: response.rates,
+        updated_at: response.time_last_update_unix,
+        fetched_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Fetch and store the rates, then update the conversion in effect
+async fn refresh_rates(app: &AppHandle) -> Result<Conversion, String> {
+    let rates = fetch_rates().await?;
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_setting(&conn, EXCHANGE_RATES_KEY, &rates)?;
+    log::info!(
+        "Fetched exchange rates for {} currencies",
+        rates.rates.len()
+    );
+    Ok(reload(&conn))
+}
+
+/// Whether the stored rates are missing or older than a day while needed
+fn rates_due(app: &AppHandle) -> bool {
+    let db = app.state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return false;
+    };
+    load_currency_settings(&conn).needs_rates()
+        && load_exchange_rates(&conn).is_none_or(|rates| {
+            chrono::Utc::now().timestamp() - rates.fetched_at >= RATE_MAX_AGE_SECS
+        })
+}
+
+/// Load the conversion and keep the rates fresh; called once from the app
+/// setup, after `AgentDb` is managed
+pub fn start_rate_refresher(app: AppHandle) {
+    {
+        let db = app.state::<AgentDb>();
+        if let Ok(conn) = db.0.lock() {
+            reload(&conn);
+        }
+    }
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if rates_due(&app) {
+                if let Err(e) = refresh_rates(&app).await {
+                    log::warn!("{}; using the last known rates", e);
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(REFRESH_CHECK_SECS)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_currency_settings(db: State<'_, AgentDb>) -> Result<CurrencySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_currency_settings(&conn))
+}
+
+/// Choose the display currency; fetches rates right away if none are stored
+#[tauri::command]
+pub async fn set_currency_settings(
+    app: AppHandle,
+    settings: CurrencySettings,
+) -> Result<Conversion, String> {
+    let settings = validate_settings(settings)?;
+    let conversion = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_setting(&conn, CURRENCY_SETTINGS_KEY, &settings)?;
+        reload(&conn)
+    };
+    if rates_due(&app) {
+        match refresh_rates(&app).await {
+            Ok(conversion) => return Ok(conversion),
+            Err(e) => log::warn!("{}; using the last known rates", e),
+        }
+    }
+    Ok(conversion)
+}
+
+/// The currency, rate and rate source costs are converted with
+#[tauri::command]
+pub async fn get_currency_conversion() -> Result<Conversion, String> {
+    Ok(current())
+}
+
+/// Fetch the rates now, even if the stored ones are recent
+#[tauri::command]
+pub async fn refresh_exchange_rates(app: AppHandle) -> Result<Conversion, String> {
+    refresh_rates(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings(currency: &str, manual_rate: Option<f64>) -> CurrencySettings {
+        CurrencySettings {
+            currency: currency.to_string(),
+            manual_rate,
+        }
+    }
+
+    #[test]
+    fn test_resolve_and_annotate() {
+        let now = 1_800_000_000;
+        let rates = ExchangeRates {
+            rates: HashMap::from([("CNY".to_string(), 7.0), ("EUR".to_string(), 0.9)]),
+            updated_at: now - 3600,
+            fetched_at: now - 3600,
+        };
+        let cny = settings("CNY", None);
+        assert_eq!(resolve(&cny, Some(&rates), now).source, RateSource::Live);
+        let stale = resolve(&cny, Some(&rates), now + RATE_MAX_AGE_SECS);
+        assert_eq!((stale.rate, stale.source), (Some(7.0), RateSource::Cached));
+        assert_eq!(resolve(&cny, None, now).source, RateSource::Bundled);
+        assert_eq!(
+            resolve(&settings("XYZ", None), None, now).source,
+            RateSource::Unavailable
+        );
+        let manual = resolve(&settings("CNY", Some(7.3)), Some(&rates), now);
+        assert_eq!(
+            (manual.rate, manual.source),
+            (Some(7.3), RateSource::Manual)
+        );
+        assert_eq!(
+            resolve(&settings("USD", None), Some(&rates), now).source,
+            RateSource::Base
+        );
+
+        let conversion = resolve(&cny, Some(&rates), now);
+        let mut value = json!({
+            "total_cost": 2.0,
+            "total_tokens": 10,
+            "balance": null,
+            "by_model": [{"model": "opus", "total_cost": 1.5}],
+            "series": [{"cost": [1.0, 0.5]}]
+        });
+        conversion.annotate(&mut value);
+        assert_eq!(value["total_cost_converted"], 14.0);
+        assert!(value.get("total_tokens_converted").is_none());
+        assert_eq!(value["balance_converted"], Value::Null);
+        assert_eq!(value["by_model"][0]["total_cost_converted"], 10.5);
+        assert_eq!(value["series"][0]["cost_converted"], json!([7.0, 3.5]));
+        assert_eq!(value["conversion"]["currency"], "CNY");
+
+        let mut list = json!([{"cost_usd": 1.0}]);
+        conversion.annotate(&mut list);
+        assert_eq!(list["items"][0]["cost_usd_converted"], 7.0);
+        assert!(list["items"][0].get("conversion").is_none());
+        assert_eq!(list["conversion"]["source"], "live");
+
+        // Balances of a station billing in CNY are already in CNY
+        let mut forecasts = json!([
+            {"station_currency": "CNY", "balance": 70.0, "daily_spend": 7.0},
+            {"station_currency": "USD", "balance": 10.0, "daily_spend": 1.0}
+        ]);
+        conversion.annotate(&mut forecasts);
+        assert!(forecasts["items"][0].get("balance_converted").is_none());
+        assert!(forecasts["items"][0].get("daily_spend_converted").is_none());
+        assert_eq!(forecasts["items"][1]["balance_converted"], 70.0);
+        assert_eq!(
+            station_currency(Some(&HashMap::from([(
+                STATION_CURRENCY_KEY.to_string(),
+                json!(" cny ")
+            )]))),
+            "CNY"
+        );
+        assert_eq!(station_currency(None), "USD");
+
+        assert_eq!(
+            validate_settings(settings(" cny ", None)).unwrap().currency,
+            "CNY"
+        );
+        assert!(validate_settings(settings("yuan", None)).is_err());
+        assert!(validate_settings(settings("CNY", Some(0.0))).is_err());
+    }
+}
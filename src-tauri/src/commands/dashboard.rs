@@ -0,0 +1,307 @@
+//! Everything the home screen shows, in one call
+//!
+//! `get_dashboard_snapshot` gathers the active provider, the health of the
+//! active relay station, today's spend, running processes and pending alerts
+//! concurrently. Each section carries its own error, so one failing source
+//! (an unreachable station, a locked database) leaves the others intact.
+use chrono::Local;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::cost_alerts::{self, AlertPeriod, CostAlertStatus};
+use crate::commands::currency::Converted;
+use crate::commands::provider;
+use crate::commands::relay_offline;
+use crate::commands::relay_renewals::{self, ExpiringItem};
+use crate::commands::relay_stations::{
+    create_station_adapter, ConnectionTestResult, RelayStation, RelayStationManager,
+};
+use crate::commands::usage::UsageEntry;
+use crate::commands::usage_quotas::{self, UsageQuotaStatus};
+use crate::process::{ProcessInfo, ProcessRegistryState};
+use crate::t;
+
+/// One part of the snapshot: its data, or why it is missing
+///
+/// Both can be set when a section was only partly gathered.
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardSection<T> {
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> From<Result<T, String>> for DashboardSection<T> {
+    fn from(result: Result<T, String>) -> Self {
+        match result {
+            Ok(data) => Self {
+                data: Some(data),
+                error: None,
+            },
+            Err(error) => Self {
+                data: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveProvider {
+    /// The saved provider the current config matches
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StationHealth {
+    pub station_id: String,
+    pub station_name: String,
+    pub api_url: String,
+    /// Unset in offline mode, where stations are not contacted
+    pub test: Option<ConnectionTestResult>,
+}
+
+/// Spend since local midnight, in USD
+#[derive(Debug, Clone, Serialize)]
+pub struct TodaySpend {
+    pub local_cost_usd: f64,
+    pub local_requests: u64,
+    /// Spend the balance snapshots of all stations show
+    pub station_cost_usd: f64,
+    /// The larger of both, as sessions through a station show up in both
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningProcesses {
+    pub claude_sessions: Vec<ProcessInfo>,
+    pub agent_runs: Vec<ProcessInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PendingAlerts {
+    /// Cost alert rules tripped in their current period
+    pub cost_alerts: Vec<CostAlertStatus>,
+    /// Usage quotas used up in their current period
+    pub usage_quotas: Vec<UsageQuotaStatus>,
+    /// Tokens and subscriptions expiring soon
+    pub expiring: Vec<ExpiringItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardSnapshot {
+    pub provider: DashboardSection<ActiveProvider>,
+    /// Data is null when no relay station is active
+    pub station: DashboardSection<Option<StationHealth>>,
+    pub spend: DashboardSection<TodaySpend>,
+    pub processes: DashboardSection<RunningProcesses>,
+    pub alerts: DashboardSection<PendingAlerts>,
+    pub generated_at: i64,
+}
+
+fn active_provider() -> Result<ActiveProvider, String> {
+    let current = provider::read_current_config()?;
+    let provider_id = provider::get_current_provider_id()?;
+    let provider_name = match &provider_id {
+        Some(id) => provider::load_providers_from_file()?
+            .into_iter()
+            .find(|config| &config.id == id)
+            .map(|config| config.name),
+        None => None,
+    };
+    Ok(ActiveProvider {
+        provider_id,
+        provider_name,
+        base_url: current.anthropic_base_url,
+        model: current.anthropic_model,
+    })
+}
+
+/// The station whose applied config is the current one
+pub(crate) fn active_station(app: &AppHandle) -> Result<Option<RelayStation>, String> {
+    let current = provider::read_current_config()?;
+    let Some(base_url) = current.anthropic_base_url else {
+        return Ok(None);
+    };
+    let credential = current.anthropic_auth_token.or(current.anthropic_api_key);
+
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state
+        .lock()
+        .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+    let applied = manager
+        .get_config_usage_status()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|applied| {
+            applied.base_url.trim_end_matches('/') == base_url.trim_end_matches('/')
+                && credential.as_deref() == Some(applied.token.as_str())
+        });
+    match applied {
+        Some(applied) => manager
+            .get_station(&applied.station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string())),
+        None => Ok(None),
+    }
+}
+
+async fn station_health(app: &AppHandle) -> Result<Option<StationHealth>, String> {
+    let Some(station) = active_station(app)? else {
+        return Ok(None);
+    };
+    let test = if relay_offline::is_offline(app) {
+        None
+    } else {
+        let adapter = create_station_adapter(app, &station);
+        Some(
+            adapter
+                .test_connection(&station)
+                .await
+                .map_err(|_e| t!("relay.failed_to_test_connection", "error" => &_e.to_string()))?,
+        )
+    };
+    Ok(Some(StationHealth {
+        station_id: station.id,
+        station_name: station.name,
+        api_url: station.api_url,
+        test,
+    }))
+}
+
+/// Today's spend and the tripped cost alerts and quotas, which all need the
+/// usage logs; those are read once, off the async runtime
+fn usage_sections(
+    app: &AppHandle,
+) -> (
+    Result<TodaySpend, String>,
+    Result<Vec<CostAlertStatus>, String>,
+    Result<Vec<UsageQuotaStatus>, String>,
+) {
+    let entries: Vec<UsageEntry> = match dirs::home_dir() {
+        Some(home) => crate::commands::usage::get_all_usage_entries(&home.join(".claude")),
+        None => Vec::new(),
+    };
+    let now = Local::now();
+    let db = app.state::<AgentDb>();
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => {
+            let error = e.to_string();
+            return (Err(error.clone()), Err(error.clone()), Err(error));
+        }
+    };
+
+    let since = cost_alerts::period_start(AlertPeriod::Day, now);
+    let totals = cost_alerts::local_totals(&entries, since);
+    let spend = crate::commands::relay_forecast::spent_since(&conn, since.timestamp()).map(
+        |station_cost_usd| TodaySpend {
+            local_cost_usd: totals.local_spend,
+            local_requests: totals.local_requests,
+            station_cost_usd,
+            total_cost: totals.local_spend.max(station_cost_usd),
+        },
+    );
+    let alerts = cost_alerts::statuses_for(&conn, &entries, now).map(|statuses| {
+        statuses
+            .into_iter()
+            .filter(|status| status.tripped)
+            .collect()
+    });
+    let quotas = usage_quotas::load_quotas(&conn).map(|quotas| {
+        usage_quotas::statuses(quotas, &entries, now)
+            .into_iter()
+            .filter(|status| status.exceeded)
+            .collect()
+    });
+    (spend, alerts, quotas)
+}
+
+fn running_processes(app: &AppHandle) -> Result<RunningProcesses, String> {
+    let registry = app.state::<ProcessRegistryState>();
+    Ok(RunningProcesses {
+        claude_sessions: registry.0.get_running_claude_sessions()?,
+        agent_runs: registry.0.get_running_agent_processes()?,
+    })
+}
+
+fn pending_alerts(
+    cost_alerts: Result<Vec<CostAlertStatus>, String>,
+    usage_quotas: Result<Vec<UsageQuotaStatus>, String>,
+    expiring: Result<Vec<ExpiringItem>, String>,
+) -> DashboardSection<PendingAlerts> {
+    let mut alerts = PendingAlerts::default();
+    let mut errors = Vec::new();
+    match cost_alerts {
+        Ok(statuses) => alerts.cost_alerts = statuses,
+        Err(e) => errors.push(e),
+    }
+    match usage_quotas {
+        Ok(statuses) => alerts.usage_quotas = statuses,
+        Err(e) => errors.push(e),
+    }
+    match expiring {
+        Ok(items) => alerts.expiring = items,
+        Err(e) => errors.push(e),
+    }
+    DashboardSection {
+        data: Some(alerts),
+        error: (!errors.is_empty()).then(|| errors.join("\n")),
+    }
+}
+
+/// Snapshot of the home screen; sections fail independently
+#[tauri::command]
+pub async fn get_dashboard_snapshot(
+    app: AppHandle,
+) -> Result<Converted<DashboardSnapshot>, String> {
+    let usage_app = app.clone();
+    let usage = tokio::task::spawn_blocking(move || usage_sections(&usage_app));
+    let (station, expiring, usage) = tokio::join!(
+        station_health(&app),
+        relay_renewals::list_expiring_items(None, app.clone()),
+        usage,
+    );
+    let (spend, cost_alerts, usage_quotas) = usage.unwrap_or_else(|e| {
+        let error = e.to_string();
+        (Err(error.clone()), Err(error.clone()), Err(error))
+    });
+
+    Ok(Converted(DashboardSnapshot {
+        provider: active_provider().into(),
+        station: station.into(),
+        spend: spend.into(),
+        processes: running_processes(&app).into(),
+        alerts: pending_alerts(cost_alerts, usage_quotas, expiring),
+        generated_at: chrono::Utc::now().timestamp(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_keep_their_own_errors() {
+        let section: DashboardSection<i64> = Err("locked".to_string()).into();
+        assert_eq!(section.data, None);
+        assert_eq!(section.error.as_deref(), Some("locked"));
+
+        let alerts = pending_alerts(
+            Ok(Vec::new()),
+            Err("quotas failed".to_string()),
+            Err("renewals failed".to_string()),
+        );
+        assert!(alerts.data.is_some());
+        assert_eq!(
+            alerts.error.as_deref(),
+            Some("quotas failed\nrenewals failed")
+        );
+    }
+}
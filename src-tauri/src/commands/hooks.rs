@@ -0,0 +1,435 @@
+//! Typed editing of the `hooks` section of Claude Code settings files
+//!
+//! Settings hold `hooks.<Event>` arrays of rules; each rule has an optional
+//! `matcher` and the hooks that run when it matches.
+use crate::mcp::config::{read_json, write_json};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Events Claude Code fires hooks for
+pub const HOOK_EVENTS: &[&str] = &[
+    "PreToolUse",
+    "PostToolUse",
+    "Notification",
+    "UserPromptSubmit",
+    "Stop",
+    "SubagentStop",
+    "PreCompact",
+    "SessionStart",
+    "SessionEnd",
+];
+
+/// Events whose matcher is compared with the tool name
+const TOOL_EVENTS: &[&str] = &["PreToolUse", "PostToolUse"];
+
+/// Events whose matcher is compared with a fixed trigger instead of a tool
+const TRIGGER_EVENTS: &[(&str, &[&str])] = &[
+    ("PreCompact", &["manual", "auto"]),
+    ("SessionStart", &["startup", "resume", "clear", "compact"]),
+];
+
+/// Serializes our own read-modify-write cycles on the settings files
+pub(crate) static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// One hook of a rule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookHandler {
+    /// "command" runs a shell command, "prompt" asks a model
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Seconds before Claude Code kills the hook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Fields this app does not know about, kept as they are
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// One entry of a `hooks.<Event>` array
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HookRule {
+    /// Tool name, `A|B` list or regex; empty or `*` matches everything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    #[serde(default)]
+    pub hooks: Vec<HookHandler>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A rule and where it is configured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookRuleRecord {
+    /// "user", "project" or "local"
+    pub scope: String,
+    pub event: String,
+    /// Position in the event's array, used to edit or delete the rule
+    pub index: usize,
+    pub rule: HookRule,
+    /// Problems that make Claude Code skip or misread the rule
+    pub errors: Vec<String>,
+}
+
+/// A hook that would run for a sample tool call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookDryRunMatch {
+    pub scope: String,
+    pub event: String,
+    pub index: usize,
+    pub matcher: Option<String>,
+    pub handler: HookHandler,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookDryRun {
+    /// JSON the PreToolUse hooks would receive on stdin
+    pub input: Value,
+    pub matches: Vec<HookDryRunMatch>,
+}
+
+/// Settings file of a scope
+pub(crate) fn settings_path(scope: &str, project_path: Option<&str>) -> Result<PathBuf, String> {
+    let project_dir = |scope: &str| {
+        project_path
+            .map(|path| PathBuf::from(path).join(".claude"))
+            .ok_or_else(|| format!("Project path required for {} scope", scope))
+    };
+    match scope {
+        "user" => Ok(dirs::home_dir()
+            .ok_or("Could not find home directory")?
+            .join(".claude")
+            .join("settings.json")),
+        "project" => Ok(project_dir(scope)?.join("settings.json")),
+        "local" => Ok(project_dir(scope)?.join("settings.local.json")),
+        _ => Err("Invalid scope. Must be 'user', 'project' or 'local'".to_string()),
+    }
+}
+
+/// Whether a matcher selects `target`, the way Claude Code compares them:
+/// plain names and `A|B` lists match exactly, anything else as a regex
+pub fn matcher_matches(matcher: Option<&str>, target: &str) -> bool {
+    let matcher = matcher.map(str::trim).unwrap_or_default();
+    if matcher.is_empty() || matcher == "*" {
+        return true;
+    }
+    if matcher
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '|')
+    {
+        return matcher.split('|').any(|name| name.trim() == target);
+    }
+    Regex::new(matcher).is_ok_and(|regex| regex.is_match(target))
+}
+
+/// Check a rule for `event`; returns every problem found
+pub fn validate_rule(event: &str, rule: &HookRule) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !HOOK_EVENTS.contains(&event) {
+        errors.push(format!("Unknown hook event: {}", event));
+    }
+
+    let matcher = rule.matcher.as_deref().map(str::trim).unwrap_or_default();
+    if !matcher.is_empty() && matcher != "*" {
+        if let Some((_, triggers)) = TRIGGER_EVENTS.iter().find(|(name, _)| *name == event) {
+            if !triggers.contains(&matcher) {
+                errors.push(format!(
+                    "{} matcher must be one of {}",
+                    event,
+                    triggers.join(", ")
+                ));
+            }
+        } else if TOOL_EVENTS.contains(&event) {
+            if let Err(e) = Regex::new(matcher) {
+                errors.push(format!("Invalid matcher regex: {}", e));
+            }
+        } else if HOOK_EVENTS.contains(&event) {
+            errors.push(format!("{} hooks don't use a matcher", event));
+        }
+    }
+
+    if rule.hooks.is_empty() {
+        errors.push("A rule needs at least one hook".to_string());
+    }
+    for (i, handler) in rule.hooks.iter().enumerate() {
+        let filled =
+            |value: &Option<String>| value.as_deref().is_some_and(|v| !v.trim().is_empty());
+        match handler.kind.as_str() {
+            "command" if !filled(&handler.command) => {
+                errors.push(format!("Hook {} has no command", i + 1))
+            }
+            "prompt" if !filled(&handler.prompt) => {
+                errors.push(format!("Hook {} has no prompt", i + 1))
+            }
+            "command" | "prompt" => {}
+            other => errors.push(format!("Hook {} has unknown type {:?}", i + 1, other)),
+        }
+        if handler.timeout == Some(0) {
+            errors.push(format!("Hook {} timeout must be at least 1 second", i + 1));
+        }
+    }
+    errors
+}
+
+/// Rules of every event in one settings file, including invalid ones
+pub fn read_rules(path: &Path, scope: &str) -> Result<Vec<HookRuleRecord>, String> {
+    let settings = read_json(path)?;
+    let Some(events) = settings.get("hooks").and_then(Value::as_object) else {
+        return Ok(Vec::new());
+    };
+
+    let mut records = Vec::new();
+    for (event, rules) in events {
+        let Some(rules) = rules.as_array() else {
+            records.push(HookRuleRecord {
+                scope: scope.to_string(),
+                event: event.clone(),
+                index: 0,
+                rule: HookRule::default(),
+                errors: vec![format!("hooks.{} must be an array", event)],
+            });
+            continue;
+        };
+        for (index, value) in rules.iter().enumerate() {
+            let (rule, errors) = match serde_json::from_value::<HookRule>(value.clone()) {
+                Ok(rule) => {
+                    let errors = validate_rule(event, &rule);
+                    (rule, errors)
+                }
+                Err(e) => (
+                    HookRule::default(),
+                    vec![format!("Invalid hook rule: {}", e)],
+                ),
+            };
+            records.push(HookRuleRecord {
+                scope: scope.to_string(),
+                event: event.clone(),
+                index,
+                rule,
+                errors,
+            });
+        }
+    }
+    Ok(records)
+}
+
+/// Apply `change` to the `hooks.<event>` array of a settings file
+///
+/// Every other setting is written back unchanged; an emptied event is removed.
+fn update_rules<T>(
+    path: &Path,
+    event: &str,
+    change: impl FnOnce(&mut Vec<Value>) -> Result<T, String>,
+) -> Result<T, String> {
+    let _lock = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut settings = read_json(path)?;
+    let root = settings
+        .as_object_mut()
+        .ok_or("Settings file does not contain a JSON object")?;
+    let hooks = root
+        .entry("hooks")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("hooks must be a JSON object")?;
+
+    let mut rules = match hooks.remove(event) {
+        Some(Value::Array(rules)) => rules,
+        Some(_) => return Err(format!("hooks.{} must be an array", event)),
+        None => Vec::new(),
+    };
+    let result = change(&mut rules)?;
+    if !rules.is_empty() {
+        hooks.insert(event.to_string(), Value::Array(rules));
+    }
+
+    write_json(path, &settings)?;
+    Ok(result)
+}
+
+/// Add a rule (`index` is `None`) or replace the rule at `index`
+pub fn save_rule(
+    path: &Path,
+    event: &str,
+    index: Option<usize>,
+    rule: &HookRule,
+) -> Result<usize, String> {
+    let errors = validate_rule(event, rule);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let value = serde_json::to_value(rule).map_err(|e| e.to_string())?;
+    update_rules(path, event, |rules| match index {
+        Some(index) => {
+            let slot = rules
+                .get_mut(index)
+                .ok_or_else(|| format!("No {} rule at position {}", event, index))?;
+            *slot = value;
+            Ok(index)
+        }
+        None => {
+            rules.push(value);
+            Ok(rules.len() - 1)
+        }
+    })
+}
+
+pub fn delete_rule(path: &Path, event: &str, index: usize) -> Result<(), String> {
+    update_rules(path, event, |rules| {
+        if index >= rules.len() {
+            return Err(format!("No {} rule at position {}", event, index));
+        }
+        rules.remove(index);
+        Ok(())
+    })
+}
+
+/// Scopes visible from a project, in the order Claude Code runs their hooks
+pub(crate) fn scopes(project_path: Option<&str>) -> Vec<&'static str> {
+    match project_path {
+        Some(_) => vec!["user", "project", "local"],
+        None => vec!["user"],
+    }
+}
+
+/// List hook rules of the user settings and, with a project, of its shared
+/// and local settings, with validation errors per rule
+#[tauri::command]
+pub async fn hooks_list(project_path: Option<String>) -> Result<Vec<HookRuleRecord>, String> {
+    let mut records = Vec::new();
+    for scope in scopes(project_path.as_deref()) {
+        let path = settings_path(scope, project_path.as_deref())?;
+        records.extend(read_rules(&path, scope)?);
+    }
+    Ok(records)
+}
+
+/// Add a hook rule, or replace the rule at `index` of `event`
+#[tauri::command]
+pub async fn hook_rule_save(
+    scope: String,
+    project_path: Option<String>,
+    event: String,
+    index: Option<usize>,
+    rule: HookRule,
+) -> Result<HookRuleRecord, String> {
+    let path = settings_path(&scope, project_path.as_deref())?;
+    let index = save_rule(&path, &event, index, &rule)?;
+    log::info!("Saved {} hook rule {} in {:?}", event, index, path);
+    Ok(HookRuleRecord {
+        scope,
+        event,
+        index,
+        rule,
+        errors: Vec::new(),
+    })
+}
+
+#[tauri::command]
+pub async fn hook_rule_delete(
+    scope: String,
+    project_path: Option<String>,
+    event: String,
+    index: usize,
+) -> Result<(), String> {
+    let path = settings_path(&scope, project_path.as_deref())?;
+    delete_rule(&path, &event, index)?;
+    log::info!("Deleted {} hook rule {} from {:?}", event, index, path);
+    Ok(())
+}
+
+/// Show which configured hooks would run for a call of `tool_name`, without
+/// running anything
+#[tauri::command]
+pub async fn hooks_dry_run(
+    project_path: Option<String>,
+    tool_name: String,
+    tool_input: Option<Value>,
+) -> Result<HookDryRun, String> {
+    let records = hooks_list(project_path.clone()).await?;
+    let matches = records
+        .into_iter()
+        .filter(|record| TOOL_EVENTS.contains(&record.event.as_str()))
+        .filter(|record| record.errors.is_empty())
+        .filter(|record| matcher_matches(record.rule.matcher.as_deref(), &tool_name))
+        .flat_map(|record| {
+            record
+                .rule
+                .hooks
+                .into_iter()
+                .map(move |handler| HookDryRunMatch {
+                    scope: record.scope.clone(),
+                    event: record.event.clone(),
+                    index: record.index,
+                    matcher: record.rule.matcher.clone(),
+                    handler,
+                })
+        })
+        .collect();
+
+    let input = serde_json::json!({
+        "session_id": "dry-run",
+        "transcript_path": "",
+        "cwd": project_path.unwrap_or_default(),
+        "hook_event_name": "PreToolUse",
+        "tool_name": tool_name,
+        "tool_input": tool_input.unwrap_or_else(|| Value::Object(Map::new())),
+    });
+    Ok(HookDryRun { input, matches })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matcher_matches() {
+        assert!(matcher_matches(None, "Bash"));
+        assert!(matcher_matches(Some("*"), "Bash"));
+        assert!(matcher_matches(Some("Edit|Write"), "Write"));
+        assert!(!matcher_matches(Some("Edit"), "NotebookEdit"));
+        assert!(matcher_matches(
+            Some("mcp__memory__.*"),
+            "mcp__memory__create"
+        ));
+        assert!(!matcher_matches(Some("(unclosed"), "Bash"));
+    }
+
+    #[test]
+    fn test_save_and_delete_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"model": "opus"}"#).unwrap();
+
+        let rule: HookRule = serde_json::from_value(json!({
+            "matcher": "Edit|Write",
+            "hooks": [{"type": "command", "command": "cargo fmt", "timeout": 30}]
+        }))
+        .unwrap();
+        assert_eq!(save_rule(&path, "PostToolUse", None, &rule).unwrap(), 0);
+
+        let invalid = HookRule {
+            matcher: Some("(".to_string()),
+            ..rule.clone()
+        };
+        assert!(save_rule(&path, "PostToolUse", None, &invalid).is_err());
+        assert!(save_rule(&path, "Stop", None, &rule).is_err());
+        assert!(save_rule(&path, "PostToolUse", Some(3), &rule).is_err());
+
+        let settings = read_json(&path).unwrap();
+        assert_eq!(settings["model"], "opus");
+        let records = read_rules(&path, "user").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].rule, rule);
+        assert!(records[0].errors.is_empty());
+
+        delete_rule(&path, "PostToolUse", 0).unwrap();
+        assert_eq!(read_json(&path).unwrap()["hooks"], json!({}));
+    }
+}
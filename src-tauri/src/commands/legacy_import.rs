@@ -0,0 +1,769 @@
+//! Import of agents, providers and settings from claudia and opcode
+//!
+//! Both keep an `agents.db` in their app data directory with the same
+//! `agents` and `app_settings` tables this app grew from; some builds keep
+//! provider presets in a `providers.json` beside it. Columns added in later
+//! versions may be missing and fall back to this app's defaults. Nothing is
+//! written on a dry run, which reports what an import would do.
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::provider::{self, ProviderConfig};
+
+/// App identifiers of the apps imported from, by name
+const LEGACY_APPS: &[(&str, &str)] = &[
+    ("claudia", "claudia.asterisk.so"),
+    ("opcode", "opcode.asterisk.so"),
+];
+
+const LEGACY_DATABASE: &str = "agents.db";
+const LEGACY_PROVIDERS: &str = "providers.json";
+
+/// Settings that mean the same here; others are reported as unsupported
+const COMPATIBLE_SETTINGS: &[&str] = &["claude_binary_path", "claude_installation_preference"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Keep what is here
+    Skip,
+    /// Replace what is here
+    Overwrite,
+    /// Import under a new name next to what is here; settings are skipped
+    #[default]
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LegacyImportOptions {
+    pub agents: bool,
+    pub providers: bool,
+    pub settings: bool,
+    pub conflicts: ConflictStrategy,
+    pub dry_run: bool,
+}
+
+impl Default for LegacyImportOptions {
+    fn default() -> Self {
+        Self {
+            agents: true,
+            providers: true,
+            settings: true,
+            conflicts: ConflictStrategy::default(),
+            dry_run: false,
+        }
+    }
+}
+
+/// A claudia or opcode data directory found on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacyInstall {
+    pub app: String,
+    pub data_dir: String,
+    pub agents: usize,
+    pub providers: usize,
+    pub settings: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportItemKind {
+    Agent,
+    Provider,
+    Setting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The same item is already here
+    Identical,
+    Skipped,
+    Overwritten,
+    Renamed,
+}
+
+/// An imported item that clashed with one already here
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportConflict {
+    pub kind: ImportItemKind,
+    pub name: String,
+    pub resolution: ConflictResolution,
+    /// Name or id it was imported under, when renamed
+    pub new_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportTally {
+    /// Added, renamed ones included
+    pub imported: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyImportReport {
+    pub data_dir: String,
+    pub dry_run: bool,
+    pub agents: ImportTally,
+    pub providers: ImportTally,
+    pub settings: ImportTally,
+    pub conflicts: Vec<ImportConflict>,
+    /// Setting keys this app has no use for
+    pub unsupported_settings: Vec<String>,
+}
+
+impl LegacyImportReport {
+    fn conflict(
+        &mut self,
+        kind: ImportItemKind,
+        name: &str,
+        resolution: ConflictResolution,
+        new_name: Option<String>,
+    ) {
+        let tally = match kind {
+            ImportItemKind::Agent => &mut self.agents,
+            ImportItemKind::Provider => &mut self.providers,
+            ImportItemKind::Setting => &mut self.settings,
+        };
+        match resolution {
+            ConflictResolution::Identical | ConflictResolution::Skipped => tally.skipped += 1,
+            ConflictResolution::Overwritten => tally.overwritten += 1,
+            ConflictResolution::Renamed => tally.imported += 1,
+        }
+        self.conflicts.push(ImportConflict {
+            kind,
+            name: name.to_string(),
+            resolution,
+            new_name,
+        });
+    }
+}
+
+/// Agent as stored by claudia or opcode
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyAgent {
+    pub name: String,
+    pub icon: String,
+    pub system_prompt: String,
+    pub default_task: Option<String>,
+    pub model: String,
+    pub enable_file_read: bool,
+    pub enable_file_write: bool,
+    pub enable_network: bool,
+    pub hooks: Option<String>,
+}
+
+/// Provider preset as stored by claudia forks; fields beyond the URL are
+/// optional there
+#[derive(Debug, Clone, Deserialize)]
+struct LegacyProvider {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    description: String,
+    base_url: String,
+    #[serde(default)]
+    auth_token: Option<String>,
+    #[serde(default)]
+    api_key: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    small_fast_model: Option<String>,
+}
+
+impl LegacyProvider {
+    fn into_config(self) -> ProviderConfig {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        ProviderConfig {
+            id: non_empty(self.id).unwrap_or_else(|| {
+                format!(
+                    "legacy-{}",
+                    self.name.trim().to_lowercase().replace(' ', "-")
+                )
+            }),
+            name: self.name,
+            description: self.description,
+            base_url: self.base_url,
+            auth_token: non_empty(self.auth_token),
+            api_key: non_empty(self.api_key),
+            model: non_empty(self.model),
+            small_fast_model: non_empty(self.small_fast_model),
+            mirror_urls: Vec::new(),
+            binary_path: None,
+            default_args: Vec::new(),
+        }
+    }
+}
+
+/// A column older versions may lack
+fn optional_column<T: rusqlite::types::FromSql>(row: &Row, column: &str) -> Option<T> {
+    row.get::<_, Option<T>>(column).ok().flatten()
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|found| found.is_some())
+    .map_err(|e| e.to_string())
+}
+
+pub fn read_legacy_agents(conn: &Connection) -> Result<Vec<LegacyAgent>, String> {
+    if !table_exists(conn, "agents")? {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn
+        .prepare("SELECT * FROM agents ORDER BY id")
+        .map_err(|e| format!("Failed to read agents: {}", e))?;
+    let agents = stmt
+        .query_map([], |row| {
+            Ok(LegacyAgent {
+                name: row.get("name")?,
+                icon: optional_column(row, "icon").unwrap_or_else(|| "bot".to_string()),
+                system_prompt: row.get("system_prompt")?,
+                default_task: optional_column(row, "default_task"),
+                model: optional_column(row, "model").unwrap_or_else(|| "sonnet".to_string()),
+                enable_file_read: optional_column(row, "enable_file_read").unwrap_or(true),
+                enable_file_write: optional_column(row, "enable_file_write").unwrap_or(true),
+                enable_network: optional_column(row, "enable_network").unwrap_or(false),
+                hooks: optional_column(row, "hooks"),
+            })
+        })
+        .map_err(|e| format!("Failed to read agents: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read agents: {}", e))?;
+    Ok(agents)
+}
+
+pub fn read_legacy_settings(conn: &Connection) -> Result<Vec<(String, String)>, String> {
+    if !table_exists(conn, "app_settings")? {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM app_settings ORDER BY key")
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let settings = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to read settings: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    Ok(settings)
+}
+
+fn read_legacy_providers(data_dir: &Path) -> Result<Vec<ProviderConfig>, String> {
+    let path = data_dir.join(LEGACY_PROVIDERS);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let providers: Vec<LegacyProvider> = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid providers in {}: {}", path.display(), e))?;
+    Ok(providers
+        .into_iter()
+        .map(LegacyProvider::into_config)
+        .collect())
+}
+
+/// First free "<name> (Imported)", "<name> (Imported 2)", ...
+fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let mut candidate = format!("{} (Imported)", name);
+    let mut n = 2;
+    while taken(&candidate) {
+        candidate = format!("{} (Imported {})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+pub fn import_agents(
+    conn: &Connection,
+    agents: &[LegacyAgent],
+    strategy: ConflictStrategy,
+    report: &mut LegacyImportReport,
+) -> Result<(), String> {
+    let name_taken = |name: &str| {
+        conn.query_row(
+            "SELECT 1 FROM agents WHERE name = ?1",
+            params![name],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|found| found.is_some())
+        .unwrap_or(false)
+    };
+    let insert_error = |e: rusqlite::Error| format!("Failed to import agent: {}", e);
+    for agent in agents {
+        let existing: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, system_prompt FROM agents WHERE name = ?1",
+                params![agent.name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let name = match existing {
+            None => agent.name.clone(),
+            Some((_, system_prompt)) if system_prompt == agent.system_prompt => {
+                report.conflict(
+                    ImportItemKind::Agent,
+                    &agent.name,
+                    ConflictResolution::Identical,
+                    None,
+                );
+                continue;
+            }
+            Some((id, _)) => match strategy {
+                ConflictStrategy::Skip => {
+                    report.conflict(
+                        ImportItemKind::Agent,
+                        &agent.name,
+                        ConflictResolution::Skipped,
+                        None,
+                    );
+                    continue;
+                }
+                ConflictStrategy::Overwrite => {
+                    conn.execute(
+                        "UPDATE agents SET icon = ?1, system_prompt = ?2, default_task = ?3, model = ?4,
+                         enable_file_read = ?5, enable_file_write = ?6, enable_network = ?7, hooks = ?8,
+                         updated_at = CURRENT_TIMESTAMP WHERE id = ?9",
+                        params![
+                            agent.icon,
+                            agent.system_prompt,
+                            agent.default_task,
+                            agent.model,
+                            agent.enable_file_read,
+                            agent.enable_file_write,
+                            agent.enable_network,
+                            agent.hooks,
+                            id
+                        ],
+                    )
+                    .map_err(insert_error)?;
+                    report.conflict(
+                        ImportItemKind::Agent,
+                        &agent.name,
+                        ConflictResolution::Overwritten,
+                        None,
+                    );
+                    continue;
+                }
+                ConflictStrategy::Rename => {
+                    let name = free_name(&agent.name, name_taken);
+                    report.conflict(
+                        ImportItemKind::Agent,
+                        &agent.name,
+                        ConflictResolution::Renamed,
+                        Some(name.clone()),
+                    );
+                    name
+                }
+            },
+        };
+        conn.execute(
+            "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                name,
+                agent.icon,
+                agent.system_prompt,
+                agent.default_task,
+                agent.model,
+                agent.enable_file_read,
+                agent.enable_file_write,
+                agent.enable_network,
+                agent.hooks
+            ],
+        )
+        .map_err(insert_error)?;
+        if name == agent.name {
+            report.agents.imported += 1;
+        }
+    }
+    Ok(())
+}
+
+pub fn import_settings(
+    conn: &Connection,
+    settings: &[(String, String)],
+    strategy: ConflictStrategy,
+    report: &mut LegacyImportReport,
+) -> Result<(), String> {
+    for (key, value) in settings {
+        if !COMPATIBLE_SETTINGS.contains(&key.as_str()) {
+            report.unsupported_settings.push(key.clone());
+            continue;
+        }
+        let existing: Option<String> = conn
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match existing {
+            Some(existing) if existing == *value => {
+                report.conflict(
+                    ImportItemKind::Setting,
+                    key,
+                    ConflictResolution::Identical,
+                    None,
+                );
+                continue;
+            }
+            Some(_) if strategy != ConflictStrategy::Overwrite => {
+                report.conflict(
+                    ImportItemKind::Setting,
+                    key,
+                    ConflictResolution::Skipped,
+                    None,
+                );
+                continue;
+            }
+            Some(_) => report.conflict(
+                ImportItemKind::Setting,
+                key,
+                ConflictResolution::Overwritten,
+                None,
+            ),
+            None => report.settings.imported += 1,
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to import setting {}: {}", key, e))?;
+    }
+    Ok(())
+}
+
+/// Add imported providers to `existing`, matched by id
+pub fn merge_providers(
+    existing: &mut Vec<ProviderConfig>,
+    incoming: Vec<ProviderConfig>,
+    strategy: ConflictStrategy,
+    report: &mut LegacyImportReport,
+) {
+    for mut provider in incoming {
+        let Some(index) = existing.iter().position(|p| p.id == provider.id) else {
+            existing.push(provider);
+            report.providers.imported += 1;
+            continue;
+        };
+        let current = &existing[index];
+        let identical = current.base_url == provider.base_url
+            && current.auth_token == provider.auth_token
+            && current.api_key == provider.api_key;
+        let resolution = match strategy {
+            _ if identical => ConflictResolution::Identical,
+            ConflictStrategy::Skip => ConflictResolution::Skipped,
+            ConflictStrategy::Overwrite => ConflictResolution::Overwritten,
+            ConflictStrategy::Rename => ConflictResolution::Renamed,
+        };
+        let mut new_name = None;
+        match resolution {
+            ConflictResolution::Overwritten => existing[index] = provider.clone(),
+            ConflictResolution::Renamed => {
+                let mut id = format!("{}-imported", provider.id);
+                let mut n = 2;
+                while existing.iter().any(|p| p.id == id) {
+                    id = format!("{}-imported-{}", provider.id, n);
+                    n += 1;
+                }
+                provider.id = id.clone();
+                provider.name = format!("{} (Imported)", provider.name);
+                existing.push(provider.clone());
+                new_name = Some(id);
+            }
+            _ => {}
+        }
+        report.conflict(
+            ImportItemKind::Provider,
+            &provider.name,
+            resolution,
+            new_name,
+        );
+    }
+}
+
+fn open_legacy_database(data_dir: &Path) -> Result<Connection, String> {
+    let path = data_dir.join(LEGACY_DATABASE);
+    Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+/// Data directory of a path given as the directory or its `agents.db`
+fn legacy_data_dir(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    let data_dir = if path.is_file() {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        path
+    };
+    if !data_dir.join(LEGACY_DATABASE).is_file() {
+        return Err(format!(
+            "No {} found in {}",
+            LEGACY_DATABASE,
+            data_dir.display()
+        ));
+    }
+    Ok(data_dir)
+}
+
+/// claudia and opcode data directories on this machine, with what they hold
+#[tauri::command]
+pub async fn detect_legacy_installs() -> Result<Vec<LegacyInstall>, String> {
+    let Some(base) = dirs::data_dir() else {
+        return Ok(Vec::new());
+    };
+    let mut installs = Vec::new();
+    for (app, identifier) in LEGACY_APPS {
+        let data_dir = base.join(identifier);
+        if !data_dir.join(LEGACY_DATABASE).is_file() {
+            continue;
+        }
+        let conn = match open_legacy_database(&data_dir) {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("{}", e);
+                continue;
+            }
+        };
+        installs.push(LegacyInstall {
+            app: app.to_string(),
+            data_dir: data_dir.to_string_lossy().to_string(),
+            agents: read_legacy_agents(&conn)?.len(),
+            providers: read_legacy_providers(&data_dir).map_or(0, |p| p.len()),
+            settings: read_legacy_settings(&conn)?.len(),
+        });
+    }
+    Ok(installs)
+}
+
+/// Import from a claudia or opcode data directory (or its `agents.db`)
+#[tauri::command]
+pub async fn import_legacy_data(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    path: String,
+    options: Option<LegacyImportOptions>,
+) -> Result<LegacyImportReport, String> {
+    let options = options.unwrap_or_default();
+    let data_dir = legacy_data_dir(&path)?;
+    let legacy = open_legacy_database(&data_dir)?;
+    let mut report = LegacyImportReport {
+        data_dir: data_dir.to_string_lossy().to_string(),
+        dry_run: options.dry_run,
+        ..LegacyImportReport::default()
+    };
+
+    // First, so a refused app lock leaves nothing half imported
+    if options.providers {
+        let incoming = read_legacy_providers(&data_dir)?;
+        if !incoming.is_empty() {
+            let mut providers = provider::load_providers_from_file()?;
+            merge_providers(&mut providers, incoming, options.conflicts, &mut report);
+            if !options.dry_run {
+                provider::save_providers(&app, &providers).await?;
+                crate::tray::refresh_menu(&app);
+            }
+        }
+    }
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        // Rolled back when dropped uncommitted, as on a dry run
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start the import: {}", e))?;
+        if options.agents {
+            import_agents(
+                &tx,
+                &read_legacy_agents(&legacy)?,
+                options.conflicts,
+                &mut report,
+            )?;
+        }
+        if options.settings {
+            import_settings(
+                &tx,
+                &read_legacy_settings(&legacy)?,
+                options.conflicts,
+                &mut report,
+            )?;
+        }
+        if !options.dry_run {
+            tx.commit()
+                .map_err(|e| format!("Failed to finish the import: {}", e))?;
+        }
+    }
+
+    log::info!(
+        "{} {} agents, {} providers and {} settings from {} ({} conflicts)",
+        if options.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        },
+        report.agents.imported,
+        report.providers.imported,
+        report.settings.imported,
+        report.data_dir,
+        report.conflicts.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(id: &str, base_url: &str) -> ProviderConfig {
+        LegacyProvider {
+            id: Some(id.to_string()),
+            name: id.to_uppercase(),
+            description: String::new(),
+            base_url: base_url.to_string(),
+            auth_token: Some("token".to_string()),
+            api_key: Some(String::new()),
+            model: None,
+            small_fast_model: None,
+        }
+        .into_config()
+    }
+
+    #[test]
+    fn test_import_agents_and_settings() {
+        // An early claudia schema, before models and hooks
+        let legacy = Connection::open_in_memory().unwrap();
+        legacy
+            .execute_batch(
+                "CREATE TABLE agents (id INTEGER PRIMARY KEY, name TEXT, icon TEXT, system_prompt TEXT);
+                 INSERT INTO agents (name, icon, system_prompt) VALUES
+                    ('Reviewer', 'eye', 'Review the diff'),
+                    ('Writer', 'pen', 'Write docs'),
+                    ('Tester', 'bug', 'Write tests');
+                 CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT);
+                 INSERT INTO app_settings VALUES
+                    ('claude_binary_path', '/opt/claude'),
+                    ('claude_installation_preference', 'system'),
+                    ('theme', 'dark');",
+            )
+            .unwrap();
+        let agents = read_legacy_agents(&legacy).unwrap();
+        assert_eq!(agents[0].model, "sonnet");
+        assert!(agents[0].enable_file_write);
+
+        let target = Connection::open_in_memory().unwrap();
+        target
+            .execute_batch(
+                "CREATE TABLE agents (id INTEGER PRIMARY KEY, name TEXT, icon TEXT, system_prompt TEXT,
+                    default_task TEXT, model TEXT, enable_file_read BOOLEAN, enable_file_write BOOLEAN,
+                    enable_network BOOLEAN, hooks TEXT, updated_at TEXT);
+                 INSERT INTO agents (name, icon, system_prompt) VALUES
+                    ('Reviewer', 'eye', 'Review the diff'),
+                    ('Writer', 'pen', 'Write a changelog'),
+                    ('Writer (Imported)', 'pen', 'Old import');
+                 CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT);
+                 INSERT INTO app_settings VALUES ('claude_binary_path', '/usr/bin/claude');",
+            )
+            .unwrap();
+
+        let mut report = LegacyImportReport::default();
+        import_agents(&target, &agents, ConflictStrategy::Rename, &mut report).unwrap();
+        import_settings(
+            &target,
+            &read_legacy_settings(&legacy).unwrap(),
+            ConflictStrategy::Rename,
+            &mut report,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.agents,
+            ImportTally {
+                imported: 2,
+                overwritten: 0,
+                skipped: 1
+            }
+        );
+        let resolutions: Vec<(&str, ConflictResolution, Option<&str>)> = report
+            .conflicts
+            .iter()
+            .map(|c| (c.name.as_str(), c.resolution, c.new_name.as_deref()))
+            .collect();
+        assert_eq!(
+            resolutions,
+            vec![
+                ("Reviewer", ConflictResolution::Identical, None),
+                (
+                    "Writer",
+                    ConflictResolution::Renamed,
+                    Some("Writer (Imported 2)")
+                ),
+                ("claude_binary_path", ConflictResolution::Skipped, None),
+            ]
+        );
+        assert_eq!(report.settings.imported, 1);
+        assert_eq!(report.unsupported_settings, vec!["theme"]);
+
+        let mut report = LegacyImportReport::default();
+        import_agents(
+            &target,
+            &agents[1..2],
+            ConflictStrategy::Overwrite,
+            &mut report,
+        )
+        .unwrap();
+        let prompt: String = target
+            .query_row(
+                "SELECT system_prompt FROM agents WHERE name = 'Writer'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(prompt, "Write docs");
+    }
+
+    #[test]
+    fn test_merge_providers() {
+        let mut existing = vec![provider("a", "https://a.example.com")];
+        let mut report = LegacyImportReport::default();
+        merge_providers(
+            &mut existing,
+            vec![
+                provider("a", "https://a.example.com"),
+                provider("b", "https://b.example.com"),
+            ],
+            ConflictStrategy::Rename,
+            &mut report,
+        );
+        assert_eq!(existing.len(), 2);
+        assert_eq!(existing[1].api_key, None);
+        assert_eq!(
+            report.conflicts[0].resolution,
+            ConflictResolution::Identical
+        );
+
+        merge_providers(
+            &mut existing,
+            vec![provider("a", "https://mirror.example.com")],
+            ConflictStrategy::Rename,
+            &mut report,
+        );
+        assert_eq!(existing[2].id, "a-imported");
+        assert_eq!(existing[2].name, "A (Imported)");
+        assert_eq!(existing[0].base_url, "https://a.example.com");
+    }
+}
@@ -0,0 +1,591 @@
+//! Optional local reverse proxy for the Anthropic API
+//!
+//! Tools pointed at `http://127.0.0.1:<port>` reach whatever upstream Claude
+//! Code currently uses: every request is forwarded to the base URL in
+//! settings.json with its token injected, so they follow provider and relay
+//! station switches made in the workbench without being reconfigured.
+//! Each request is logged with its token counts (see `proxy_log`).
+use crate::commands::agents::AgentDb;
+use crate::commands::app_lock;
+use crate::commands::provider;
+use crate::commands::proxy_log::{self, ProxyRequestLog, TokenUsage, UsageScanner};
+use crate::commands::relay_rate_usage;
+use crate::local_http::{self, HttpRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// app_settings key storing the local proxy settings (JSON)
+pub const LOCAL_PROXY_SETTINGS_KEY: &str = "local_proxy_settings";
+
+const DEFAULT_PORT: u16 = 17879;
+
+/// Shorter access keys are refused; generated ones are 64 characters
+const MIN_ACCESS_KEY_LEN: usize = 16;
+
+/// Responses stream for as long as the model writes; only connecting is bounded
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Not forwarded upstream: connection-level headers and the client's
+/// credentials, which are replaced by the upstream's. Without
+/// `accept-encoding` the upstream answers uncompressed.
+const SKIPPED_REQUEST_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "upgrade",
+    "content-length",
+    "transfer-encoding",
+    "accept-encoding",
+    "authorization",
+    "x-api-key",
+    proxy_log::PROJECT_HEADER,
+];
+
+/// Not copied back to the client: the proxy closes the connection instead
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "content-length",
+    "transfer-encoding",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LocalProxySettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Key clients must send as `x-api-key` or bearer token; generated the
+    /// first time the proxy starts without one
+    pub access_key: String,
+}
+
+impl Default for LocalProxySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            access_key: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalProxyStatus {
+    pub running: bool,
+    /// Base URL tools are pointed at while running
+    pub url: Option<String>,
+    /// Where requests currently go
+    pub upstream: Option<String>,
+}
+
+struct RunningServer {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+/// The proxy accept loop, while running
+#[derive(Default)]
+pub struct LocalProxyState(Mutex<Option<RunningServer>>);
+
+/// The upstream Claude Code uses, with the credential it authenticates with
+#[derive(Debug, PartialEq)]
+struct Upstream {
+    base_url: String,
+    auth: UpstreamAuth,
+}
+
+#[derive(Debug, PartialEq)]
+enum UpstreamAuth {
+    /// ANTHROPIC_API_KEY, sent as `x-api-key`
+    ApiKey(String),
+    /// ANTHROPIC_AUTH_TOKEN, sent as a bearer token
+    Bearer(String),
+    None,
+}
+
+pub fn load_local_proxy_settings(conn: &rusqlite::Connection) -> LocalProxySettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![LOCAL_PROXY_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_local_proxy_settings(
+    conn: &rusqlite::Connection,
+    settings: &LocalProxySettings,
+) -> Result<(), String> {
+    let value = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![LOCAL_PROXY_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save local proxy settings: {}", e))?;
+    Ok(())
+}
+
+fn validate_access_key(access_key: &str) -> Result<(), String> {
+    if access_key.chars().count() < MIN_ACCESS_KEY_LEN {
+        return Err(format!(
+            "Access key must be at least {} characters",
+            MIN_ACCESS_KEY_LEN
+        ));
+    }
+    Ok(())
+}
+
+/// The access key to save: the saved one when the form sent back nothing or
+/// the masked value it was shown
+fn submitted_access_key(submitted: &str, saved: String) -> String {
+    let submitted = submitted.trim();
+    if submitted.is_empty() || provider::is_masked(submitted) {
+        saved
+    } else {
+        submitted.to_string()
+    }
+}
+
+/// `settings` with the access key masked while the app lock hides secrets
+fn mask_access_key(app: &AppHandle, mut settings: LocalProxySettings) -> LocalProxySettings {
+    if !settings.access_key.is_empty() && app_lock::hides_secrets(app) {
+        settings.access_key = provider::mask_secret(&settings.access_key);
+    }
+    settings
+}
+
+/// Whether `base_url` points back at the proxy, which would loop forever
+fn is_own_address(base_url: &str, port: u16) -> bool {
+    let host = base_url
+        .split("://")
+        .nth(1)
+        .unwrap_or(base_url)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    ["127.0.0.1", "localhost", "[::1]"]
+        .iter()
+        .any(|local| host.eq_ignore_ascii_case(&format!("{}:{}", local, port)))
+}
+
+fn current_upstream(port: u16) -> Result<Upstream, String> {
+    let current = provider::read_current_config()?;
+    let base_url = current
+        .anthropic_base_url
+        .filter(|url| !url.is_empty())
+        .ok_or("No provider is active in Claude Code settings")?;
+    if is_own_address(&base_url, port) {
+        return Err(format!(
+            "Claude Code is configured to use the local proxy itself ({}); switch to a provider or station first",
+            base_url
+        ));
+    }
+    // Claude Code prefers the API key when both are set
+    let auth = match (current.anthropic_api_key, current.anthropic_auth_token) {
+        (Some(key), _) if !key.is_empty() => UpstreamAuth::ApiKey(key),
+        (_, Some(token)) if !token.is_empty() => UpstreamAuth::Bearer(token),
+        _ => UpstreamAuth::None,
+    };
+    Ok(Upstream { base_url, auth })
+}
+
+fn upstream_url(base_url: &str, request: &HttpRequest) -> String {
+    let mut url = format!("{}{}", base_url.trim_end_matches('/'), request.path);
+    if !request.query.is_empty() {
+        url.push('?');
+        url.push_str(&request.query);
+    }
+    url
+}
+
+/// Error in the shape the Anthropic API uses, so tools show the message
+fn error_body(kind: &str, message: &str) -> Value {
+    json!({ "type": "error", "error": { "type": kind, "message": message } })
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// What came back from the upstream
+struct Forwarded {
+    status: u16,
+    usage: TokenUsage,
+    /// Model the response named
+    model: Option<String>,
+}
+
+/// Forward a request and stream the response back; errors before the
+/// response started are returned for the caller to report
+async fn forward(
+    request: &HttpRequest,
+    upstream: &Upstream,
+    stream: &mut TcpStream,
+) -> Result<Forwarded, String> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| format!("Unsupported method {}", request.method))?;
+    let mut builder = client().request(method, upstream_url(&upstream.base_url, request));
+    for (name, value) in &request.headers {
+        if !SKIPPED_REQUEST_HEADERS
+            .iter()
+            .any(|skipped| name.eq_ignore_ascii_case(skipped))
+        {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    builder = match &upstream.auth {
+        UpstreamAuth::ApiKey(key) => builder.header("x-api-key", key.as_str()),
+        UpstreamAuth::Bearer(token) => builder.bearer_auth(token),
+        UpstreamAuth::None => builder,
+    };
+    let mut response = builder
+        .body(request.body.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Upstream {} is unreachable: {}", upstream.base_url, e))?;
+
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let mut scanner = UsageScanner::new(content_type);
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !SKIPPED_RESPONSE_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect();
+    let headers: Vec<(&str, &str)> = headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+
+    // Relay chunks as they arrive so streamed (SSE) responses stay live;
+    // the upstream is read to the end even if the client left, since the
+    // tokens are billed either way
+    let mut client_connected = match local_http::write_head(stream, status, &headers).await {
+        Ok(()) => true,
+        Err(e) => {
+            log::debug!("Local proxy: client went away: {}", e);
+            false
+        }
+    };
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                scanner.observe(&chunk);
+                if client_connected && stream.write_all(&chunk).await.is_err() {
+                    log::debug!("Local proxy: client closed the stream");
+                    client_connected = false;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::warn!("Local proxy: upstream stream failed: {}", e);
+                break;
+            }
+        }
+    }
+    let _ = stream.flush().await;
+    let (usage, model) = scanner.finish();
+    Ok(Forwarded {
+        status,
+        usage,
+        model,
+    })
+}
+
+async fn handle_connection(app: AppHandle, settings: LocalProxySettings, mut stream: TcpStream) {
+    let request = match local_http::read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            let body = error_body("invalid_request_error", &e.to_string());
+            let _ = local_http::write_json(&mut stream, 400, &body).await;
+            return;
+        }
+    };
+
+    // Web pages must not spend the user's credits through the proxy
+    let rejection = if request.header("origin").is_some() {
+        Some((
+            403,
+            error_body("permission_error", "Browser requests are not accepted"),
+        ))
+    } else if !request
+        .token("x-api-key")
+        .is_some_and(|key| local_http::secrets_match(key, &settings.access_key))
+    {
+        Some((
+            401,
+            error_body("authentication_error", "Invalid access key"),
+        ))
+    } else if !request.path.starts_with("/v1/") {
+        Some((
+            404,
+            error_body("not_found_error", "Only /v1/ endpoints are proxied"),
+        ))
+    } else {
+        None
+    };
+    if let Some((status, body)) = rejection {
+        let _ = local_http::write_json(&mut stream, status, &body).await;
+        return;
+    }
+
+    let started = Instant::now();
+    let mut entry = ProxyRequestLog {
+        id: 0,
+        created_at: chrono::Utc::now().timestamp(),
+        method: request.method.clone(),
+        path: request.path.clone(),
+        model: proxy_log::request_model(&request.body),
+        project: request
+            .header(proxy_log::PROJECT_HEADER)
+            .filter(|project| !project.is_empty())
+            .map(str::to_string),
+        upstream: None,
+        status: 502,
+        latency_ms: 0,
+        usage: TokenUsage::default(),
+        cost_usd: 0.0,
+    };
+    let forwarded = match current_upstream(settings.port) {
+        Ok(upstream) => {
+            entry.upstream = Some(upstream.base_url.clone());
+            forward(&request, &upstream, &mut stream).await
+        }
+        Err(e) => Err(e),
+    };
+    match forwarded {
+        Ok(forwarded) => {
+            entry.status = forwarded.status;
+            entry.usage = forwarded.usage;
+            entry.model = entry.model.or(forwarded.model);
+        }
+        Err(e) => {
+            log::warn!(
+                "Local proxy: {} {} failed: {}",
+                request.method,
+                request.path,
+                e
+            );
+            let body = error_body("api_error", &e);
+            let _ = local_http::write_json(&mut stream, 502, &body).await;
+        }
+    }
+    entry.latency_ms = started.elapsed().as_millis() as u64;
+    log::info!(
+        "Local proxy: {} {} -> {} in {} ms",
+        entry.method,
+        entry.path,
+        entry.status,
+        entry.latency_ms
+    );
+
+    let db = app.state::<AgentDb>();
+    let result = match db.0.lock() {
+        Ok(conn) => proxy_log::insert_request(&conn, &entry),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        log::warn!("{}", e);
+    }
+    if let Some(upstream) = &entry.upstream {
+        relay_rate_usage::check_upstream(&app, upstream);
+    }
+}
+
+fn stop(app: &AppHandle) {
+    let state = app.state::<LocalProxyState>();
+    let Ok(mut running) = state.0.lock() else {
+        return;
+    };
+    if let Some(server) = running.take() {
+        server.task.abort();
+        log::info!("Local proxy on port {} stopped", server.port);
+    }
+}
+
+/// (Re)start the proxy with the saved settings, or stop it when disabled
+pub async fn restart(app: &AppHandle) -> Result<(), String> {
+    stop(app);
+    let mut settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_local_proxy_settings(&conn)
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        // Any local process could spend the upstream's credits without a key
+        if settings.access_key.is_empty() {
+            settings.access_key = super::control_api::generate_token();
+            save_local_proxy_settings(&conn, &settings)?;
+        }
+        if let Err(e) = proxy_log::prune_old_requests(&conn) {
+            log::warn!("{}", e);
+        }
+    }
+    validate_access_key(&settings.access_key)?;
+
+    // Loopback only: the proxy hands out the upstream's credentials
+    let listener = TcpListener::bind(("127.0.0.1", settings.port))
+        .await
+        .map_err(|e| format!("Failed to listen on port {}: {}", settings.port, e))?;
+    log::info!("Local proxy listening on 127.0.0.1:{}", settings.port);
+
+    let port = settings.port;
+    let server_app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(
+                        server_app.clone(),
+                        settings.clone(),
+                        stream,
+                    ));
+                }
+                Err(e) => log::warn!("Local proxy: failed to accept a connection: {}", e),
+            }
+        }
+    });
+    let state = app.state::<LocalProxyState>();
+    let mut running = state.0.lock().map_err(|e| e.to_string())?;
+    *running = Some(RunningServer { port, task });
+    Ok(())
+}
+
+/// Start the proxy if enabled; called once from the app setup
+pub fn start_local_proxy(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = restart(&app).await {
+            log::warn!("Failed to start the local proxy: {}", e);
+        }
+    });
+}
+
+/// The settings, with the access key masked while the app lock hides secrets
+#[tauri::command]
+pub async fn get_local_proxy_settings(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<LocalProxySettings, String> {
+    let settings = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_local_proxy_settings(&conn)
+    };
+    Ok(mask_access_key(&app, settings))
+}
+
+/// Save the settings and (re)start the proxy; an empty or masked access key
+/// keeps the saved one, or gets a generated one when there is none
+#[tauri::command]
+pub async fn set_local_proxy_settings(
+    app: AppHandle,
+    settings: LocalProxySettings,
+) -> Result<LocalProxySettings, String> {
+    let mut settings = settings;
+    if settings.port == 0 {
+        return Err("Port must not be 0".to_string());
+    }
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        settings.access_key = submitted_access_key(
+            &settings.access_key,
+            load_local_proxy_settings(&conn).access_key,
+        );
+        if settings.enabled && settings.access_key.is_empty() {
+            settings.access_key = super::control_api::generate_token();
+        }
+        if !settings.access_key.is_empty() {
+            validate_access_key(&settings.access_key)?;
+        }
+        save_local_proxy_settings(&conn, &settings)?;
+    }
+    restart(&app).await?;
+    Ok(mask_access_key(&app, settings))
+}
+
+#[tauri::command]
+pub async fn get_local_proxy_status(
+    state: State<'_, LocalProxyState>,
+) -> Result<LocalProxyStatus, String> {
+    let port = {
+        let running = state.0.lock().map_err(|e| e.to_string())?;
+        running.as_ref().map(|server| server.port)
+    };
+    Ok(LocalProxyStatus {
+        running: port.is_some(),
+        url: port.map(|port| format!("http://127.0.0.1:{}", port)),
+        upstream: port
+            .and_then(|port| current_upstream(port).ok())
+            .map(|upstream| upstream.base_url),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_url_and_loop_detection() {
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            query: "beta=true".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        assert_eq!(
+            upstream_url("https://relay.example.com/api/", &request),
+            "https://relay.example.com/api/v1/messages?beta=true"
+        );
+
+        assert!(is_own_address("http://127.0.0.1:17879", 17879));
+        assert!(is_own_address("http://LOCALHOST:17879/", 17879));
+        assert!(!is_own_address("http://127.0.0.1:8080", 17879));
+        assert!(!is_own_address("https://api.anthropic.com", 17879));
+
+        assert!(validate_access_key("short-key").is_err());
+        assert!(validate_access_key(&crate::commands::control_api::generate_token()).is_ok());
+    }
+
+    #[test]
+    fn test_masked_access_key_keeps_the_saved_one() {
+        let saved = crate::commands::control_api::generate_token();
+        let masked = provider::mask_secret(&saved);
+        assert_eq!(submitted_access_key(&masked, saved.clone()), saved);
+        assert_eq!(submitted_access_key("  ", saved.clone()), saved);
+        assert_eq!(
+            submitted_access_key(" new-access-key-0123456789 ", saved),
+            "new-access-key-0123456789"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::commands::agents::AgentDb;
+use rusqlite::params;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+/// app_settings key storing the locale the user picked over the OS one
+pub const LOCALE_SETTING_KEY: &str = "locale";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    /// Active locale, e.g. "zh-CN"
+    pub locale: String,
+    /// Locale detected from the OS
+    pub system_locale: String,
+    /// False when the user picked a locale explicitly
+    pub follows_system: bool,
+    pub supported: Vec<String>,
+}
+
+fn locale_info(follows_system: bool) -> LocaleInfo {
+    LocaleInfo {
+        locale: crate::i18n::current_locale(),
+        system_locale: crate::i18n::system_locale().to_string(),
+        follows_system,
+        supported: crate::i18n::SUPPORTED_LOCALES
+            .iter()
+            .map(|locale| locale.to_string())
+            .collect(),
+    }
+}
+
+/// The locale the user picked, or `None` when following the OS
+pub(crate) fn saved_locale(conn: &rusqlite::Connection) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![LOCALE_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+}
+
+/// Get the locale backend messages are translated into
+#[tauri::command]
+pub async fn get_locale(db: State<'_, AgentDb>) -> Result<LocaleInfo, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(locale_info(saved_locale(&conn).is_none()))
+}
+
+/// Apply `locale`, or the OS locale for `None`, and remember the choice
+pub(crate) fn save_locale(
+    conn: &rusqlite::Connection,
+    locale: Option<&str>,
+) -> Result<LocaleInfo, String> {
+    let selected = match locale {
+        Some(locale) => crate::i18n::set_locale(locale)?,
+        None => crate::i18n::set_locale(crate::i18n::system_locale())?,
+    };
+    match locale {
+        Some(_) => conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![LOCALE_SETTING_KEY, selected],
+        ),
+        None => conn.execute(
+            "DELETE FROM app_settings WHERE key = ?1",
+            params![LOCALE_SETTING_KEY],
+        ),
+    }
+    .map_err(|e| format!("Failed to save locale: {}", e))?;
+
+    log::info!("Backend locale set to {}", selected);
+    Ok(locale_info(locale.is_none()))
+}
+
+/// Tell every window and the tray menu about a new locale
+pub(crate) fn announce_locale(app: &AppHandle, info: &LocaleInfo) {
+    let _ = app.emit("locale-changed", info);
+    crate::tray::refresh_menu(app);
+}
+
+/// Switch the locale of backend messages and persist the choice; pass `null`
+/// to follow the OS language again
+///
+/// Accepts tags like "en", "zh" or "zh-CN". Emits `locale-changed` so every
+/// window can switch language without a restart.
+#[tauri::command]
+pub async fn set_locale(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    locale: Option<String>,
+) -> Result<LocaleInfo, String> {
+    let info = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_locale(&conn, locale.as_deref())?
+    };
+    announce_locale(&app, &info);
+    Ok(info)
+}
+
+/// Apply the saved locale at startup, or the OS one if the user never picked one
+pub fn load_locale(conn: &rusqlite::Connection) {
+    let locale = match saved_locale(conn) {
+        Some(saved) => saved,
+        None => crate::i18n::system_locale().to_string(),
+    };
+    match crate::i18n::set_locale(&locale) {
+        Ok(selected) => log::info!("Backend locale: {}", selected),
+        Err(e) => log::warn!("Ignoring saved locale: {}", e),
+    }
+}
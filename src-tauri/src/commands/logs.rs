@@ -0,0 +1,76 @@
+use crate::commands::agents::AgentDb;
+use rusqlite::params;
+use serde::Serialize;
+use tauri::State;
+
+/// app_settings key storing the backend log level
+pub const LOG_LEVEL_SETTING_KEY: &str = "log_level";
+
+/// Lines returned when the caller does not ask for a number
+const DEFAULT_LOG_TAIL: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppLogs {
+    /// Directory holding the log files, for "open folder" in the UI
+    pub log_dir: String,
+    pub level: String,
+    pub lines: Vec<String>,
+}
+
+/// Read the last `tail` backend log lines, optionally only those containing `filter`
+#[tauri::command]
+pub async fn get_app_logs(filter: Option<String>, tail: Option<usize>) -> Result<AppLogs, String> {
+    let dir = crate::logging::log_dir().ok_or("Failed to get app data directory")?;
+    let tail = tail.unwrap_or(DEFAULT_LOG_TAIL);
+    let lines = {
+        let dir = dir.clone();
+        tokio::task::spawn_blocking(move || {
+            crate::logging::read_logs(&dir, filter.as_deref(), tail)
+        })
+        .await
+        .map_err(|e| format!("Failed to read logs: {}", e))?
+    };
+
+    Ok(AppLogs {
+        log_dir: dir.to_string_lossy().to_string(),
+        level: crate::logging::log_level(),
+        lines,
+    })
+}
+
+/// Get the current backend log level
+#[tauri::command]
+pub async fn get_log_level() -> Result<String, String> {
+    Ok(crate::logging::log_level())
+}
+
+/// Change and persist the backend log level ("error", "warn", "info", "debug",
+/// "trace" or a RUST_LOG style directive)
+#[tauri::command]
+pub async fn set_log_level(db: State<'_, AgentDb>, level: String) -> Result<(), String> {
+    crate::logging::set_log_level(&level)?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![LOG_LEVEL_SETTING_KEY, level.trim()],
+    )
+    .map_err(|e| format!("Failed to save log level: {}", e))?;
+    Ok(())
+}
+
+/// Apply the saved log level at startup unless RUST_LOG overrides it
+pub fn load_log_level(conn: &rusqlite::Connection) {
+    if std::env::var_os("RUST_LOG").is_some() {
+        return;
+    }
+    if let Ok(level) = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![LOG_LEVEL_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    ) {
+        if let Err(e) = crate::logging::set_log_level(&level) {
+            log::warn!("Ignoring saved log level: {}", e);
+        }
+    }
+}
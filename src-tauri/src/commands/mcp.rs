@@ -1,5 +1,9 @@
+use crate::mcp::{
+    find_claude_desktop_config, import_servers, parse_servers_snippet, read_claude_desktop_servers,
+    validate_server_value, McpConfigStore, McpImportReport, McpScope, McpServerRecord,
+    McpTestResult,
+};
 use anyhow::{Context, Result};
-use dirs;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -17,7 +21,8 @@ fn create_command_with_env(program: &str) -> Command {
 /// Finds the full path to the claude binary
 /// This is necessary because Windows apps may have limited PATH environment
 fn find_claude_binary(app_handle: &AppHandle) -> Result<String> {
-    crate::claude_binary::find_claude_binary(app_handle).map_err(|e| anyhow::anyhow!(e))
+    crate::commands::claude::find_claude_binary_for_project(app_handle, None)
+        .map_err(|e| anyhow::anyhow!(e))
 }
 
 /// Represents an MCP server configuration
@@ -100,11 +105,20 @@ fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result
     info!("Executing claude mcp command with args: {:?}", args);
 
     let claude_path = find_claude_binary(app_handle)?;
-    let mut cmd = create_command_with_env(&claude_path);
-    cmd.arg("mcp");
-    for arg in args {
-        cmd.arg(arg);
-    }
+    let mut args: Vec<String> = std::iter::once("mcp")
+        .chain(args)
+        .map(String::from)
+        .collect();
+    let program = match crate::claude_binary::parse_wsl_path(&claude_path) {
+        Some(distro) => {
+            let (program, wsl_args) = crate::claude_binary::wsl_launch_args(distro, &args, None);
+            args = wsl_args;
+            program
+        }
+        None => claude_path,
+    };
+    let mut cmd = create_command_with_env(&program);
+    cmd.args(&args);
 
     // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
     #[cfg(target_os = "windows")]
@@ -475,63 +489,15 @@ pub async fn mcp_add_from_claude_desktop(
         scope
     );
 
-    // Get Claude Desktop config path for Windows
-    let config_path = if cfg!(target_os = "windows") {
-        // Check common Windows locations for Claude Desktop
-        let app_data_dir = dirs::config_dir()
-            .or_else(|| dirs::data_dir())
-            .ok_or_else(|| "Could not find application data directory".to_string())?;
-        
-        // Try common Windows paths for Claude Desktop
-        let possible_paths = vec![
-            app_data_dir.join("Claude").join("claude_desktop_config.json"),
-            app_data_dir.join("Anthropic").join("Claude").join("claude_desktop_config.json"),
-            dirs::home_dir()
-                .ok_or_else(|| "Could not find home directory".to_string())?
-                .join("AppData")
-                .join("Roaming")
-                .join("Claude")
-                .join("claude_desktop_config.json"),
-            dirs::home_dir()
-                .ok_or_else(|| "Could not find home directory".to_string())?
-                .join("AppData")
-                .join("Local")
-                .join("Claude")
-                .join("claude_desktop_config.json"),
-        ];
-        
-        // Find the first existing path
-        possible_paths
-            .into_iter()
-            .find(|path| path.exists())
-            .ok_or_else(|| {
-                "Claude Desktop configuration not found. Please make sure Claude Desktop is installed on Windows.".to_string()
-            })?
-    } else {
-        return Err(
-            "This Windows-optimized version only supports importing from Claude Desktop on Windows.".to_string(),
-        );
-    };
-
-    // Read and parse the config file
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read Claude Desktop config: {}", e))?;
-
-    let config: serde_json::Value = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse Claude Desktop config: {}", e))?;
-
-    // Extract MCP servers
-    let mcp_servers = config
-        .get("mcpServers")
-        .and_then(|v| v.as_object())
-        .ok_or_else(|| "No MCP servers found in Claude Desktop config".to_string())?;
+    let config_path = find_claude_desktop_config()?;
+    let mcp_servers = read_claude_desktop_servers(&config_path)?;
 
     let mut imported_count = 0;
     let mut failed_count = 0;
     let mut server_results = Vec::new();
 
     // Import each server using add-json
-    for (name, server_config) in mcp_servers {
+    for (name, server_config) in &mcp_servers {
         info!("Importing server: {}", name);
 
         // Convert Claude Desktop format to add-json format
@@ -741,3 +707,114 @@ pub async fn mcp_save_project_config(
 
     Ok("Project MCP configuration saved".to_string())
 }
+
+/// Lists MCP servers straight from the config files of every scope visible
+/// from `project_path`, including disabled ones
+#[tauri::command]
+pub async fn mcp_config_list(project_path: Option<String>) -> Result<Vec<McpServerRecord>, String> {
+    McpConfigStore::for_current_user()?.list(project_path.as_deref())
+}
+
+/// Validates a server config against the MCP schema without saving it
+///
+/// Returns the list of problems; empty when the config is valid.
+#[tauri::command]
+pub async fn mcp_config_validate(config: serde_json::Value) -> Result<Vec<String>, String> {
+    Ok(validate_server_value(&config).err().unwrap_or_default())
+}
+
+/// Adds a stdio, SSE or HTTP server to the config file of `scope`
+#[tauri::command]
+pub async fn mcp_config_add(
+    scope: McpScope,
+    project_path: Option<String>,
+    name: String,
+    config: serde_json::Value,
+) -> Result<McpServerRecord, String> {
+    McpConfigStore::for_current_user()?.save(scope, project_path.as_deref(), &name, &config, false)
+}
+
+/// Replaces the config of an existing server
+#[tauri::command]
+pub async fn mcp_config_update(
+    scope: McpScope,
+    project_path: Option<String>,
+    name: String,
+    config: serde_json::Value,
+) -> Result<McpServerRecord, String> {
+    McpConfigStore::for_current_user()?.save(scope, project_path.as_deref(), &name, &config, true)
+}
+
+/// Enables or disables a server without losing its config
+#[tauri::command]
+pub async fn mcp_config_set_enabled(
+    scope: McpScope,
+    project_path: Option<String>,
+    name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    McpConfigStore::for_current_user()?.set_enabled(scope, project_path.as_deref(), &name, enabled)
+}
+
+/// Removes a server from the config file of `scope`
+#[tauri::command]
+pub async fn mcp_config_remove(
+    scope: McpScope,
+    project_path: Option<String>,
+    name: String,
+) -> Result<(), String> {
+    McpConfigStore::for_current_user()?.remove(scope, project_path.as_deref(), &name)
+}
+
+/// Starts (stdio) or connects to (SSE/HTTP) a server, performs the MCP
+/// initialize handshake and reports its version and tools
+///
+/// Connection failures are reported in the result; only an invalid config is an error.
+#[tauri::command]
+pub async fn test_mcp_server(config: serde_json::Value) -> Result<McpTestResult, String> {
+    let entry = validate_server_value(&config).map_err(|errors| errors.join("; "))?;
+    info!("Testing MCP server ({:?} transport)", entry.transport);
+    Ok(crate::mcp::test_server(&entry).await)
+}
+
+/// Imports the servers of Claude Desktop's config into `scope`, skipping
+/// servers that are already configured
+#[tauri::command]
+pub async fn mcp_import_claude_desktop(
+    scope: McpScope,
+    project_path: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<McpImportReport, String> {
+    let config_path = find_claude_desktop_config()?;
+    info!("Importing MCP servers from {}", config_path.display());
+
+    let servers = read_claude_desktop_servers(&config_path)?;
+    let mut report = import_servers(
+        &McpConfigStore::for_current_user()?,
+        scope,
+        project_path.as_deref(),
+        servers,
+        overwrite.unwrap_or(false),
+    )?;
+    report.source = Some(config_path.to_string_lossy().to_string());
+    Ok(report)
+}
+
+/// Imports servers from a pasted `mcpServers` JSON snippet into `scope`,
+/// skipping servers that are already configured
+#[tauri::command]
+pub async fn mcp_import_json(
+    snippet: String,
+    scope: McpScope,
+    project_path: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<McpImportReport, String> {
+    let servers = parse_servers_snippet(&snippet)?;
+    import_servers(
+        &McpConfigStore::for_current_user()?,
+        scope,
+        project_path.as_deref(),
+        servers,
+        overwrite.unwrap_or(false),
+    )
+}
@@ -0,0 +1,369 @@
+//! CLAUDE.md memory files with a backup of every version they are saved over
+//!
+//! Backups live in `<app data>/memory-backups/<path hash>/<timestamp>.md`,
+//! so a bad edit of a global, project or nested memory file can be undone.
+//! Each directory keeps the file's path in `manifest.json`.
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUPS_DIR: &str = "memory-backups";
+
+/// Oldest backups of a file are removed beyond this many
+const MAX_BACKUPS_PER_FILE: usize = 50;
+
+/// Names the file a backup directory belongs to
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Backup file names sort chronologically
+const BACKUP_ID_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// A memory file Claude Code reads, whether or not it exists yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryFile {
+    /// "user", "project", "local" or "nested"
+    pub scope: String,
+    pub path: String,
+    pub exists: bool,
+    pub size: u64,
+    /// Unix timestamp of the last write
+    pub modified: u64,
+    pub backup_count: usize,
+}
+
+/// A saved previous version of a memory file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryBackup {
+    pub id: String,
+    pub path: String,
+    /// Unix timestamp of the save that replaced this version
+    pub created_at: i64,
+    pub size: u64,
+}
+
+/// Whether `path` names a memory file, so these commands never write elsewhere
+fn is_memory_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            name.eq_ignore_ascii_case("CLAUDE.md") || name.eq_ignore_ascii_case("CLAUDE.local.md")
+        })
+}
+
+fn check_memory_file(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() || !is_memory_file(&path) {
+        return Err(format!(
+            "Not a CLAUDE.md or CLAUDE.local.md path: {}",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Contents of a backup directory's `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupManifest {
+    path: String,
+}
+
+/// `path` with its directory resolved, so different spellings of one file
+/// share their backups; the file itself may not exist yet
+fn canonical_path(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Where the backups of the memory files are kept
+#[derive(Debug, Clone)]
+pub struct MemoryBackups {
+    pub root: PathBuf,
+}
+
+impl MemoryBackups {
+    pub fn for_current_user() -> Result<Self, String> {
+        let data_dir = crate::portable::data_dir().ok_or("Failed to get app data directory")?;
+        Ok(Self {
+            root: data_dir.join(BACKUPS_DIR),
+        })
+    }
+
+    /// Backup directory of one file, named by the SHA-256 of its canonical path
+    ///
+    /// Directories from older versions, named by replacing every other
+    /// character with `-`, are moved over the first time the file is used.
+    fn dir_for(&self, path: &Path) -> PathBuf {
+        let path = canonical_path(path);
+        let mut hasher = Sha256::new();
+        hasher.update(path.to_string_lossy().as_bytes());
+        let dir = self.root.join(format!("{:x}", hasher.finalize()));
+        if !dir.exists() {
+            let legacy: String = path
+                .to_string_lossy()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect();
+            let legacy = self.root.join(legacy);
+            if legacy.is_dir() && fs::rename(&legacy, &dir).is_ok() {
+                let _ = self.write_manifest(&dir, &path);
+            }
+        }
+        dir
+    }
+
+    fn write_manifest(&self, dir: &Path, path: &Path) -> Result<(), String> {
+        let manifest = BackupManifest {
+            path: path.to_string_lossy().to_string(),
+        };
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        fs::write(dir.join(MANIFEST_FILE), json)
+            .map_err(|e| format!("Failed to write backup manifest: {}", e))
+    }
+
+    /// Previous versions of a file, newest first
+    pub fn list(&self, path: &Path) -> Vec<MemoryBackup> {
+        let Ok(entries) = fs::read_dir(self.dir_for(path)) else {
+            return Vec::new();
+        };
+        let mut backups: Vec<MemoryBackup> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_path = entry.path();
+                let id = file_path.file_stem()?.to_str()?.to_string();
+                let created_at = NaiveDateTime::parse_from_str(&id, BACKUP_ID_FORMAT)
+                    .ok()?
+                    .and_utc()
+                    .timestamp();
+                Some(MemoryBackup {
+                    id,
+                    path: path.to_string_lossy().to_string(),
+                    created_at,
+                    size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                })
+            })
+            .collect();
+        backups.sort_by(|a, b| b.id.cmp(&a.id));
+        backups
+    }
+
+    pub fn read(&self, path: &Path, id: &str) -> Result<String, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid backup ID: {}", id));
+        }
+        fs::read_to_string(self.dir_for(path).join(format!("{}.md", id)))
+            .map_err(|e| format!("Failed to read backup {}: {}", id, e))
+    }
+
+    /// Keep the current content of `path`, unless it is missing or already the
+    /// newest backup
+    fn back_up(&self, path: &Path) -> Result<Option<String>, String> {
+        let current = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let backups = self.list(path);
+        if let Some(latest) = backups.first() {
+            if self.read(path, &latest.id).ok().as_deref() == Some(current.as_str()) {
+                return Ok(None);
+            }
+        }
+
+        let dir = self.dir_for(path);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        if !dir.join(MANIFEST_FILE).exists() {
+            self.write_manifest(&dir, &canonical_path(path))?;
+        }
+        let mut id = Utc::now().format(BACKUP_ID_FORMAT).to_string();
+        // Saves within the same millisecond (or after the clock went back)
+        // still get distinct, ordered backups
+        if let Some(latest) = backups.first().filter(|latest| latest.id >= id) {
+            let time = NaiveDateTime::parse_from_str(&latest.id, BACKUP_ID_FORMAT)
+                .map_err(|e| e.to_string())?
+                + chrono::Duration::milliseconds(1);
+            id = time.format(BACKUP_ID_FORMAT).to_string();
+        }
+        fs::write(dir.join(format!("{}.md", id)), current)
+            .map_err(|e| format!("Failed to write backup: {}", e))?;
+
+        for old in backups.iter().skip(MAX_BACKUPS_PER_FILE - 1) {
+            let _ = fs::remove_file(dir.join(format!("{}.md", old.id)));
+        }
+        Ok(Some(id))
+    }
+
+    /// Write a memory file after backing up what it replaces
+    pub fn save(&self, path: &Path, content: &str) -> Result<(), String> {
+        if fs::read_to_string(path).ok().as_deref() == Some(content) {
+            return Ok(());
+        }
+        if let Some(id) = self.back_up(path)? {
+            log::debug!("Backed up {} as {}", path.display(), id);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+        }
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Put a previous version back; the version it replaces is backed up too
+    pub fn restore(&self, path: &Path, id: &str) -> Result<String, String> {
+        let content = self.read(path, id)?;
+        self.save(path, &content)?;
+        Ok(content)
+    }
+}
+
+fn memory_file(backups: &MemoryBackups, scope: &str, path: PathBuf) -> MemoryFile {
+    let metadata = fs::metadata(&path).ok();
+    MemoryFile {
+        scope: scope.to_string(),
+        exists: metadata.is_some(),
+        size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        modified: metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|time| time.duration_since(std::time::SystemTime::UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        backup_count: backups.list(&path).len(),
+        path: path.to_string_lossy().to_string(),
+    }
+}
+
+/// Write a memory file, keeping a backup of its previous content
+pub(crate) fn save_with_backup(path: &Path, content: &str) -> Result<(), String> {
+    MemoryBackups::for_current_user()?.save(path, content)
+}
+
+/// List the memory files Claude Code loads: the user's global CLAUDE.md and,
+/// with a project, its CLAUDE.md, CLAUDE.local.md and nested CLAUDE.md files
+#[tauri::command]
+pub async fn memory_files_list(project_path: Option<String>) -> Result<Vec<MemoryFile>, String> {
+    let backups = MemoryBackups::for_current_user()?;
+    let claude_dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude");
+    let mut files = vec![memory_file(&backups, "user", claude_dir.join("CLAUDE.md"))];
+
+    if let Some(project_path) = project_path {
+        let root = PathBuf::from(&project_path);
+        files.push(memory_file(&backups, "project", root.join("CLAUDE.md")));
+        files.push(memory_file(&backups, "local", root.join("CLAUDE.local.md")));
+        let project_dir_file = root.join(".claude").join("CLAUDE.md");
+        if project_dir_file.exists() {
+            files.push(memory_file(&backups, "project", project_dir_file));
+        }
+        for nested in super::claude::find_claude_md_files(project_path).await? {
+            let path = PathBuf::from(&nested.absolute_path);
+            if path.parent() != Some(root.as_path()) {
+                files.push(memory_file(&backups, "nested", path));
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[tauri::command]
+pub async fn memory_file_read(path: String) -> Result<String, String> {
+    let path = check_memory_file(&path)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Save a memory file; the previous content becomes a backup
+///
+/// Returns the file's backups after the save, newest first.
+#[tauri::command]
+pub async fn memory_file_save(path: String, content: String) -> Result<Vec<MemoryBackup>, String> {
+    let path = check_memory_file(&path)?;
+    let backups = MemoryBackups::for_current_user()?;
+    backups.save(&path, &content)?;
+    log::info!("Saved memory file {}", path.display());
+    Ok(backups.list(&path))
+}
+
+/// Previous versions of a memory file, newest first
+#[tauri::command]
+pub async fn memory_backups_list(path: String) -> Result<Vec<MemoryBackup>, String> {
+    let path = check_memory_file(&path)?;
+    Ok(MemoryBackups::for_current_user()?.list(&path))
+}
+
+#[tauri::command]
+pub async fn memory_backup_read(path: String, backup_id: String) -> Result<String, String> {
+    let path = check_memory_file(&path)?;
+    MemoryBackups::for_current_user()?.read(&path, &backup_id)
+}
+
+/// Restore a previous version of a memory file and return its content
+#[tauri::command]
+pub async fn memory_backup_restore(path: String, backup_id: String) -> Result<String, String> {
+    let path = check_memory_file(&path)?;
+    let content = MemoryBackups::for_current_user()?.restore(&path, &backup_id)?;
+    log::info!("Restored {} from backup {}", path.display(), backup_id);
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = MemoryBackups {
+            root: dir.path().join("backups"),
+        };
+        let path = dir.path().join("project").join("CLAUDE.md");
+
+        backups.save(&path, "v1").unwrap();
+        assert!(backups.list(&path).is_empty());
+        backups.save(&path, "v2").unwrap();
+        // Saving unchanged content is not a new version
+        backups.save(&path, "v3").unwrap();
+        backups.save(&path, "v3").unwrap();
+
+        let list = backups.list(&path);
+        let contents: Vec<String> = list
+            .iter()
+            .map(|backup| backups.read(&path, &backup.id).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["v2", "v1"]);
+
+        assert_eq!(backups.restore(&path, &list[1].id).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v1");
+        assert_eq!(backups.list(&path).len(), 3);
+        assert!(backups.read(&path, "../x").is_err());
+
+        // Paths that encoded alike before keep separate backups
+        let dotted = dir.path().join("my.app").join("CLAUDE.md");
+        let other = dir.path().join("my-app").join("CLAUDE.md");
+        backups.save(&dotted, "dotted v1").unwrap();
+        backups.save(&dotted, "dotted v2").unwrap();
+        backups.save(&other, "other v1").unwrap();
+        backups.save(&other, "other v2").unwrap();
+        assert_eq!(backups.list(&dotted).len(), 1);
+        assert_eq!(backups.list(&other).len(), 1);
+        let id = &backups.list(&other)[0].id;
+        assert_eq!(backups.read(&other, id).unwrap(), "other v1");
+        let manifest = fs::read_to_string(backups.dir_for(&other).join(MANIFEST_FILE)).unwrap();
+        let manifest: BackupManifest = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(PathBuf::from(manifest.path), canonical_path(&other));
+
+        assert!(is_memory_file(Path::new("/p/CLAUDE.local.md")));
+        assert!(check_memory_file("/p/notes.md").is_err());
+        assert!(check_memory_file("CLAUDE.md").is_err());
+    }
+}
@@ -1,11 +1,54 @@
 pub mod agents;
+pub mod app_backup;
+pub mod app_lock;
 pub mod about;
+pub mod ccusage;
 pub mod claude;
 pub mod clipboard;
+pub mod config_export;
+pub mod control_api;
+pub mod cost_alerts;
+pub mod currency;
+pub mod dashboard;
+pub mod doctor;
+pub mod hooks;
+pub mod legacy_import;
+pub mod local_proxy;
+pub mod locale;
+pub mod logs;
 pub mod mcp;
+pub mod memory;
+pub mod notifications;
+pub mod output_styles;
+pub mod permissions;
+pub mod plugins;
+pub mod processes;
+pub mod projects;
+pub mod proxy_log;
 pub mod provider;
+pub mod quick_search;
 pub mod relay_adapters;
+pub mod relay_compare;
+pub mod relay_forecast;
+pub mod relay_log_cache;
+pub mod relay_log_export;
+pub mod relay_log_stats;
+pub mod relay_log_stream;
+pub mod relay_offline;
+pub mod relay_probe;
+pub mod relay_rate_usage;
+pub mod relay_reconcile;
+pub mod relay_renewals;
+pub mod relay_share;
 pub mod relay_stations;
+pub mod relay_token_quota;
+pub mod sessions;
+pub mod settings_schema;
 pub mod slash_commands;
 pub mod storage;
+pub mod subagents;
+pub mod task_chains;
+pub mod ui_settings;
 pub mod usage;
+pub mod usage_quotas;
+pub mod webhooks;
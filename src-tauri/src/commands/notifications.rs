@@ -0,0 +1,258 @@
+//! Native OS notifications for Claude session lifecycle events
+//!
+//! Every event is opt-in through settings kept in `app_settings`, so users can
+//! tab away during long agent runs and get called back when they finish, fail
+//! or wait for a permission answer.
+use crate::commands::agents::AgentDb;
+use crate::t;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+/// app_settings key storing the notification settings (JSON)
+pub const NOTIFICATION_SETTINGS_KEY: &str = "notification_settings";
+
+/// The same prompt redrawn by the terminal UI only notifies once per this period
+const PERMISSION_PROMPT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Terminal output kept to match prompts split across reads
+const PROMPT_TAIL_CHARS: usize = 512;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub on_complete: bool,
+    pub on_error: bool,
+    pub on_permission: bool,
+    /// Runs finishing sooner than this do not notify
+    pub min_duration_secs: u64,
+    /// Skip notifications while the main window has focus
+    pub only_when_unfocused: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            on_complete: false,
+            on_error: false,
+            on_permission: false,
+            min_duration_secs: 60,
+            only_when_unfocused: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEvent {
+    Completed,
+    Failed,
+    PermissionRequested,
+}
+
+impl NotificationSettings {
+    /// Whether the user opted in to `event` for a run that took `elapsed`
+    pub fn wants(&self, event: SessionEvent, elapsed: Option<Duration>) -> bool {
+        let opted_in = match event {
+            SessionEvent::Completed => self.on_complete,
+            SessionEvent::Failed => self.on_error,
+            SessionEvent::PermissionRequested => self.on_permission,
+        };
+        // A session waiting for an answer is worth a notification however young it is
+        let long_enough = event == SessionEvent::PermissionRequested
+            || elapsed.is_none_or(|elapsed| elapsed.as_secs() >= self.min_duration_secs);
+        opted_in && long_enough
+    }
+}
+
+pub fn load_notification_settings(conn: &rusqlite::Connection) -> NotificationSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![NOTIFICATION_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+/// "1h 5m", "3m 12s" or "42s"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
+
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+pub(crate) fn show(app: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// Notify about a session event of the project at `project_path`, if the user
+/// opted in to it
+pub fn notify_session_event(
+    app: &AppHandle,
+    event: SessionEvent,
+    project_path: &str,
+    elapsed: Option<Duration>,
+) {
+    let settings = match app.try_state::<AgentDb>() {
+        Some(db) => match db.0.lock() {
+            Ok(conn) => load_notification_settings(&conn),
+            Err(_) => return,
+        },
+        None => return,
+    };
+    if !settings.wants(event, elapsed) {
+        return;
+    }
+    if settings.only_when_unfocused && main_window_focused(app) {
+        return;
+    }
+
+    let project = Path::new(project_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(project_path);
+    let duration = elapsed.map(format_duration).unwrap_or_default();
+    let (title, body) = match event {
+        SessionEvent::Completed => (
+            t!("notifications.session_completed"),
+            t!("notifications.session_completed_body", "project" => project, "duration" => &duration),
+        ),
+        SessionEvent::Failed => (
+            t!("notifications.session_failed"),
+            t!("notifications.session_failed_body", "project" => project, "duration" => &duration),
+        ),
+        SessionEvent::PermissionRequested => (
+            t!("notifications.permission_requested"),
+            t!("notifications.permission_requested_body", "project" => project),
+        ),
+    };
+    if let Err(e) = show(app, &title, &body) {
+        log::warn!("{}", e);
+    }
+}
+
+fn ansi_regex() -> &'static Regex {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    ANSI.get_or_init(|| {
+        Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)|\x1b[@-_]")
+            .expect("valid ANSI escape regex")
+    })
+}
+
+/// Spots Claude Code's interactive permission prompt ("Do you want to
+/// proceed?", "Do you want to make this edit to …?") in terminal output
+#[derive(Default)]
+pub struct PermissionPromptDetector {
+    /// Recent output without escape sequences and whitespace; the terminal UI
+    /// may position words with cursor moves instead of spaces
+    tail: String,
+    last_prompt: Option<Instant>,
+}
+
+impl PermissionPromptDetector {
+    /// Feed a chunk of output; true when it completes a new prompt
+    pub fn observe(&mut self, text: &str) -> bool {
+        let plain = ansi_regex().replace_all(text, "");
+        self.tail
+            .extend(plain.chars().filter(|c| !c.is_whitespace()));
+        let excess = self.tail.chars().count().saturating_sub(PROMPT_TAIL_CHARS);
+        if excess > 0 {
+            let cut = self.tail.char_indices().nth(excess).map_or(0, |(i, _)| i);
+            self.tail.drain(..cut);
+        }
+
+        if !self.tail.contains("Doyouwantto") {
+            return false;
+        }
+        self.tail.clear();
+        if self
+            .last_prompt
+            .is_some_and(|last| last.elapsed() < PERMISSION_PROMPT_COOLDOWN)
+        {
+            return false;
+        }
+        self.last_prompt = Some(Instant::now());
+        true
+    }
+}
+
+#[tauri::command]
+pub async fn get_notification_settings(
+    db: State<'_, AgentDb>,
+) -> Result<NotificationSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_notification_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_notification_settings(
+    db: State<'_, AgentDb>,
+    settings: NotificationSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![NOTIFICATION_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save notification settings: {}", e))?;
+    Ok(())
+}
+
+/// Show a sample notification so the user can check the OS lets them through
+#[tauri::command]
+pub async fn send_test_notification(app: AppHandle) -> Result<(), String> {
+    show(
+        &app,
+        &t!("notifications.test_title"),
+        &t!("notifications.test_body"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_and_prompt_detection() {
+        let settings = NotificationSettings {
+            on_complete: true,
+            on_permission: true,
+            ..Default::default()
+        };
+        let minute = Duration::from_secs(60);
+        assert!(settings.wants(SessionEvent::Completed, Some(minute)));
+        assert!(!settings.wants(SessionEvent::Completed, Some(Duration::from_secs(5))));
+        assert!(!settings.wants(SessionEvent::Failed, Some(minute)));
+        assert!(settings.wants(SessionEvent::PermissionRequested, None));
+        assert_eq!(format_duration(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 5m");
+
+        let mut detector = PermissionPromptDetector::default();
+        assert!(!detector.observe("\x1b[1mBash command\x1b[0m\r\n  npm install\r\n"));
+        assert!(!detector.observe(" Do\x1b[1Cyou want"));
+        assert!(detector.observe(" to proceed?\r\n❯ 1. Yes"));
+        // Redraws of the same prompt stay quiet
+        assert!(!detector.observe("Do you want to proceed?"));
+    }
+}
@@ -0,0 +1,513 @@
+//! Claude Code output styles in `.claude/output-styles/<name>.md`
+//!
+//! A style is markdown with a `name` and `description` in its frontmatter; the
+//! body replaces the coding part of Claude Code's system prompt unless
+//! `keep-coding-instructions` is set. The active style is the `outputStyle`
+//! setting, read from the local, project and user settings in that order.
+use crate::commands::hooks::{scopes, settings_path, SETTINGS_LOCK};
+use crate::frontmatter;
+use crate::mcp::config::{read_json, write_json};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Styles shipped with Claude Code; they have no file and cannot be edited
+const BUILTIN_STYLES: &[(&str, &str)] = &[
+    ("default", "Claude's standard software engineering behavior"),
+    (
+        "Explanatory",
+        "Explains implementation choices and codebase patterns",
+    ),
+    (
+        "Learning",
+        "Asks you to write small pieces of code yourself",
+    ),
+];
+
+/// Style Claude Code uses when `outputStyle` is not set
+const DEFAULT_STYLE: &str = "default";
+
+/// An output style Claude Code can use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputStyle {
+    pub name: String,
+    /// Shown in Claude Code's `/output-style` menu
+    pub description: String,
+    /// Keep Claude Code's coding instructions next to the style's prompt
+    pub keep_coding_instructions: bool,
+    /// Prompt of the style (markdown body)
+    pub content: String,
+    /// "project", "user" or "builtin"
+    pub scope: String,
+    /// `None` for built-in styles
+    pub file_path: Option<String>,
+    /// Whether this is the style new sessions of the project (or the user) use
+    pub active: bool,
+    /// Problems with the file; Claude Code may skip or misname such styles
+    pub errors: Vec<String>,
+}
+
+/// Fields of a style as edited in the GUI
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutputStyleInput {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub keep_coding_instructions: bool,
+    pub content: String,
+}
+
+/// The `outputStyle` setting in effect and where it comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveOutputStyle {
+    pub name: String,
+    /// Settings scope that sets it; `None` when Claude Code falls back to the default
+    pub scope: Option<String>,
+}
+
+/// YAML frontmatter of a style file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StyleFrontmatter {
+    name: Option<String>,
+    description: Option<String>,
+    #[serde(
+        rename = "keep-coding-instructions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    keep_coding_instructions: Option<bool>,
+}
+
+fn is_builtin(name: &str) -> bool {
+    BUILTIN_STYLES
+        .iter()
+        .any(|(builtin, _)| builtin.eq_ignore_ascii_case(name.trim()))
+}
+
+/// File name of a style: its name in lowercase with spaces as hyphens
+fn style_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("{}.md", stem)
+}
+
+/// Check the fields Claude Code needs; returns every problem found
+fn validate_style(input: &OutputStyleInput) -> Vec<String> {
+    let mut errors = Vec::new();
+    let name = input.name.trim();
+    if name.is_empty() {
+        errors.push("name is required".to_string());
+    } else if name.starts_with('.') || name.contains(['/', '\\', ':', '\n']) {
+        errors.push(format!(
+            "name {:?} cannot start with a dot or contain /, \\ or :",
+            name
+        ));
+    } else if is_builtin(name) {
+        errors.push(format!("{} is the name of a built-in style", name));
+    }
+    if input.description.trim().is_empty() {
+        errors.push("description is required".to_string());
+    }
+    if input.content.trim().is_empty() {
+        errors.push("the style prompt is empty".to_string());
+    }
+    errors
+}
+
+/// Load a style file, keeping invalid ones so the GUI can show what is wrong
+fn load_style(file_path: &Path, scope: &str) -> Result<OutputStyle, String> {
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read style file: {}", e))?;
+    let file_stem = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let (input, mut errors) = match frontmatter::split::<StyleFrontmatter>(&content) {
+        Ok((frontmatter, body)) => (
+            OutputStyleInput {
+                // Claude Code names a style after its file when the frontmatter does not
+                name: frontmatter.name.unwrap_or_else(|| file_stem.clone()),
+                description: frontmatter.description.unwrap_or_default(),
+                keep_coding_instructions: frontmatter.keep_coding_instructions.unwrap_or(false),
+                content: body,
+            },
+            Vec::new(),
+        ),
+        Err(e) => (
+            OutputStyleInput {
+                name: file_stem,
+                description: String::new(),
+                keep_coding_instructions: false,
+                content: content.clone(),
+            },
+            vec![e],
+        ),
+    };
+    errors.extend(validate_style(&input));
+
+    Ok(OutputStyle {
+        name: input.name.trim().to_string(),
+        description: input.description.trim().to_string(),
+        keep_coding_instructions: input.keep_coding_instructions,
+        content: input.content,
+        scope: scope.to_string(),
+        file_path: Some(file_path.to_string_lossy().to_string()),
+        active: false,
+        errors,
+    })
+}
+
+/// Render a style as markdown with frontmatter
+fn render_style(input: &OutputStyleInput) -> Result<String, String> {
+    let frontmatter = StyleFrontmatter {
+        name: Some(input.name.trim().to_string()),
+        description: Some(input.description.trim().to_string()),
+        keep_coding_instructions: input.keep_coding_instructions.then_some(true),
+    };
+    frontmatter::render(&frontmatter, &input.content)
+}
+
+/// `.claude/output-styles` directory of a scope
+fn styles_dir(scope: &str, project_path: Option<&str>) -> Result<PathBuf, String> {
+    match scope {
+        "project" => project_path
+            .map(|path| PathBuf::from(path).join(".claude").join("output-styles"))
+            .ok_or_else(|| "Project path required for project scope".to_string()),
+        "user" => Ok(dirs::home_dir()
+            .ok_or_else(|| "Could not find home directory".to_string())?
+            .join(".claude")
+            .join("output-styles")),
+        _ => Err("Invalid scope. Must be 'project' or 'user'".to_string()),
+    }
+}
+
+fn load_styles_in(dir: &Path, scope: &str) -> Vec<OutputStyle> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut styles: Vec<OutputStyle> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| match load_style(&path, scope) {
+            Ok(style) => Some(style),
+            Err(e) => {
+                warn!("Failed to load output style from {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect();
+    styles.sort_by(|a, b| a.name.cmp(&b.name));
+    styles
+}
+
+fn builtin_styles() -> Vec<OutputStyle> {
+    BUILTIN_STYLES
+        .iter()
+        .map(|(name, description)| OutputStyle {
+            name: name.to_string(),
+            description: description.to_string(),
+            keep_coding_instructions: true,
+            content: String::new(),
+            scope: "builtin".to_string(),
+            file_path: None,
+            active: false,
+            errors: Vec::new(),
+        })
+        .collect()
+}
+
+/// Built-in styles followed by the project's (if given) and the user's
+fn all_styles(project_path: Option<&str>) -> Result<Vec<OutputStyle>, String> {
+    let mut styles = builtin_styles();
+    if let Some(project_path) = project_path {
+        styles.extend(load_styles_in(
+            &styles_dir("project", Some(project_path))?,
+            "project",
+        ));
+    }
+    styles.extend(load_styles_in(&styles_dir("user", None)?, "user"));
+    Ok(styles)
+}
+
+/// The `outputStyle` value of one settings file
+fn read_style_setting(path: &Path) -> Result<Option<String>, String> {
+    Ok(read_json(path)?
+        .get("outputStyle")
+        .and_then(Value::as_str)
+        .map(str::to_string))
+}
+
+/// Set `outputStyle` in a settings file, or remove it with `None`, leaving
+/// every other setting as it is
+fn write_style_setting(path: &Path, name: Option<&str>) -> Result<(), String> {
+    let _lock = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut settings = read_json(path)?;
+    let root = settings
+        .as_object_mut()
+        .ok_or("Settings file does not contain a JSON object")?;
+    match name {
+        Some(name) => {
+            root.insert("outputStyle".to_string(), Value::from(name));
+        }
+        None => {
+            root.remove("outputStyle");
+        }
+    }
+    write_json(path, &settings)
+}
+
+/// The style in effect: the most specific scope that sets one wins
+fn effective_style(project_path: Option<&str>) -> Result<ActiveOutputStyle, String> {
+    for scope in scopes(project_path).into_iter().rev() {
+        if let Some(name) = read_style_setting(&settings_path(scope, project_path)?)? {
+            return Ok(ActiveOutputStyle {
+                name,
+                scope: Some(scope.to_string()),
+            });
+        }
+    }
+    Ok(ActiveOutputStyle {
+        name: DEFAULT_STYLE.to_string(),
+        scope: None,
+    })
+}
+
+/// Whether `style` is the one named by an `outputStyle` value; built-in names
+/// are matched without case, as Claude Code does
+fn names_style(setting: &str, style: &OutputStyle) -> bool {
+    if style.scope == "builtin" {
+        style.name.eq_ignore_ascii_case(setting)
+    } else {
+        style.name == setting
+    }
+}
+
+/// Point every settings file that selects `old_name` at `new_name`, or at
+/// nothing when `None`
+fn replace_style_setting(
+    project_path: Option<&str>,
+    old_name: &str,
+    new_name: Option<&str>,
+) -> Result<(), String> {
+    for scope in scopes(project_path) {
+        let path = settings_path(scope, project_path)?;
+        if read_style_setting(&path)?.as_deref() == Some(old_name) {
+            write_style_setting(&path, new_name)?;
+            info!("Updated outputStyle in {:?}", path);
+        }
+    }
+    Ok(())
+}
+
+/// List the built-in styles and the output styles of the project (if given)
+/// and of the user, marking the one in effect
+///
+/// Project styles come before user styles; Claude Code prefers them when names clash.
+#[tauri::command]
+pub async fn output_styles_list(project_path: Option<String>) -> Result<Vec<OutputStyle>, String> {
+    let mut styles = all_styles(project_path.as_deref())?;
+    let active = effective_style(project_path.as_deref())?;
+    if let Some(style) = styles
+        .iter_mut()
+        .find(|style| names_style(&active.name, style))
+    {
+        style.active = true;
+    }
+    debug!("Found {} output styles", styles.len());
+    Ok(styles)
+}
+
+/// The `outputStyle` setting in effect for the project (or the user)
+#[tauri::command]
+pub async fn output_style_get_active(
+    project_path: Option<String>,
+) -> Result<ActiveOutputStyle, String> {
+    effective_style(project_path.as_deref())
+}
+
+/// Create or update an output style
+///
+/// Pass `original_name` when editing so a renamed style replaces its old file;
+/// settings that selected the old name are switched to the new one.
+#[tauri::command]
+pub async fn output_style_save(
+    scope: String,
+    project_path: Option<String>,
+    style: OutputStyleInput,
+    original_name: Option<String>,
+) -> Result<OutputStyle, String> {
+    let errors = validate_style(&style);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let dir = styles_dir(&scope, project_path.as_deref())?;
+    let name = style.name.trim();
+    info!("Saving output style {} in scope {}", name, scope);
+
+    let existing = load_styles_in(&dir, &scope);
+    let find = |name: &str| existing.iter().find(|existing| existing.name == name);
+    let original = original_name.as_deref().and_then(find);
+    if original_name.is_some() && original.is_none() {
+        return Err(format!(
+            "Output style not found: {}",
+            original_name.unwrap_or_default()
+        ));
+    }
+    if let Some(other) = find(name) {
+        if original.map(|original| &original.file_path) != Some(&other.file_path) {
+            return Err(format!("An output style named {} already exists", name));
+        }
+    }
+
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create output styles directory: {}", e))?;
+    let file_path = dir.join(style_file_name(name));
+    if file_path.exists()
+        && original.and_then(|original| original.file_path.as_deref())
+            != Some(file_path.to_string_lossy().as_ref())
+    {
+        return Err(format!(
+            "{} already exists; choose another name",
+            file_path.display()
+        ));
+    }
+    fs::write(&file_path, render_style(&style)?)
+        .map_err(|e| format!("Failed to write output style file: {}", e))?;
+    if let Some(original) = original {
+        if let Some(original_path) = original.file_path.as_deref() {
+            if Path::new(original_path) != file_path {
+                fs::remove_file(original_path)
+                    .map_err(|e| format!("Failed to remove renamed output style file: {}", e))?;
+            }
+        }
+        if original.name != name {
+            replace_style_setting(project_path.as_deref(), &original.name, Some(name))?;
+        }
+    }
+
+    load_style(&file_path, &scope)
+}
+
+/// Delete an output style file
+///
+/// Settings that selected it are cleared so Claude Code falls back to the
+/// next scope or the default style.
+#[tauri::command]
+pub async fn output_style_delete(
+    scope: String,
+    project_path: Option<String>,
+    name: String,
+) -> Result<String, String> {
+    let dir = styles_dir(&scope, project_path.as_deref())?;
+    let style = load_styles_in(&dir, &scope)
+        .into_iter()
+        .find(|style| style.name == name)
+        .ok_or_else(|| format!("Output style not found: {}", name))?;
+    let file_path = style.file_path.unwrap_or_default();
+    fs::remove_file(&file_path)
+        .map_err(|e| format!("Failed to delete output style file: {}", e))?;
+    replace_style_setting(project_path.as_deref(), &name, None)?;
+    info!("Deleted output style {} from {}", name, file_path);
+    Ok(format!("Deleted output style: {}", name))
+}
+
+/// Make a style the active one by writing `outputStyle` to the settings of
+/// `settings_scope` ("user", "project" or "local")
+///
+/// The style must exist; built-in names are stored as Claude Code spells them.
+#[tauri::command]
+pub async fn output_style_activate(
+    settings_scope: String,
+    project_path: Option<String>,
+    name: String,
+) -> Result<ActiveOutputStyle, String> {
+    let style = all_styles(project_path.as_deref())?
+        .into_iter()
+        .find(|style| names_style(name.trim(), style))
+        .ok_or_else(|| format!("Output style not found: {}", name))?;
+    if !style.errors.is_empty() {
+        return Err(format!(
+            "Output style {} has problems: {}",
+            style.name,
+            style.errors.join("; ")
+        ));
+    }
+    let path = settings_path(&settings_scope, project_path.as_deref())?;
+    write_style_setting(&path, Some(&style.name))?;
+    info!("Activated output style {} in {:?}", style.name, path);
+    effective_style(project_path.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = OutputStyleInput {
+            name: "Code Tutor".to_string(),
+            description: "Explains every change: step by step".to_string(),
+            keep_coding_instructions: true,
+            content: "Teach as you go.".to_string(),
+        };
+        assert_eq!(style_file_name(&input.name), "code-tutor.md");
+        let path = dir.path().join(style_file_name(&input.name));
+        fs::write(&path, render_style(&input).unwrap()).unwrap();
+
+        let style = load_style(&path, "user").unwrap();
+        assert!(style.errors.is_empty(), "{:?}", style.errors);
+        assert_eq!(style.name, "Code Tutor");
+        assert_eq!(style.description, input.description);
+        assert!(style.keep_coding_instructions);
+        assert_eq!(style.content, input.content);
+
+        // Name falls back to the file name, as in Claude Code
+        let path = dir.path().join("terse.md");
+        fs::write(&path, "---\ndescription: Short answers\n---\nBe brief.").unwrap();
+        let style = load_style(&path, "user").unwrap();
+        assert_eq!(style.name, "terse");
+        assert!(style.errors.is_empty(), "{:?}", style.errors);
+        assert!(!style.keep_coding_instructions);
+
+        let path = dir.path().join("broken.md");
+        fs::write(&path, "---\nname: Learning\n---\n").unwrap();
+        let style = load_style(&path, "user").unwrap();
+        assert_eq!(style.errors.len(), 3, "{:?}", style.errors);
+
+        fs::write(&path, "No frontmatter").unwrap();
+        let style = load_style(&path, "user").unwrap();
+        assert_eq!(style.name, "broken");
+        assert!(!style.errors.is_empty());
+    }
+
+    #[test]
+    fn test_style_setting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        fs::write(&path, r#"{"model": "opus"}"#).unwrap();
+        assert_eq!(read_style_setting(&path).unwrap(), None);
+
+        write_style_setting(&path, Some("Explanatory")).unwrap();
+        assert_eq!(
+            read_style_setting(&path).unwrap().as_deref(),
+            Some("Explanatory")
+        );
+        write_style_setting(&path, None).unwrap();
+        let settings = read_json(&path).unwrap();
+        assert_eq!(settings, serde_json::json!({"model": "opus"}));
+
+        let builtin = &builtin_styles()[1];
+        assert!(names_style("explanatory", builtin));
+        let mut custom = builtin.clone();
+        custom.scope = "user".to_string();
+        assert!(!names_style("explanatory", &custom));
+    }
+}
@@ -0,0 +1,459 @@
+//! Typed editing of the `permissions.allow` and `permissions.deny` lists of
+//! Claude Code settings files, with bundled presets
+//!
+//! A rule is a tool name, optionally followed by a specifier in parentheses:
+//! `Bash(npm run test:*)`, `Read(./secrets/**)`, `WebFetch(domain:docs.rs)`.
+//! MCP tools are written `mcp__<server>` or `mcp__<server>__<tool>`.
+use crate::commands::hooks::{scopes, settings_path, SETTINGS_LOCK};
+use crate::mcp::config::{read_json, write_json};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Lists this editor manages, in the order they are shown
+pub const PERMISSION_LISTS: &[&str] = &["allow", "deny"];
+
+/// Built-in tools rules can name
+const KNOWN_TOOLS: &[&str] = &[
+    "Bash",
+    "BashOutput",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "KillShell",
+    "LS",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Read",
+    "SlashCommand",
+    "Task",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+
+/// Tools whose specifier is a gitignore-style path pattern
+const PATH_TOOLS: &[&str] = &["Read", "Edit", "Write", "MultiEdit", "NotebookEdit"];
+
+/// One rule and where it is configured
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionRuleRecord {
+    /// "user", "project" or "local"
+    pub scope: String,
+    /// "allow" or "deny"
+    pub list: String,
+    pub rule: String,
+    /// Problems that make Claude Code ignore or misread the rule
+    pub errors: Vec<String>,
+}
+
+/// A bundled set of rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionPreset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+fn rule_regex() -> &'static Regex {
+    static RULE: OnceLock<Regex> = OnceLock::new();
+    RULE.get_or_init(|| Regex::new(r"^([A-Za-z][A-Za-z0-9_-]*)(?:\((.*)\))?$").unwrap())
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+pub fn presets() -> Vec<PermissionPreset> {
+    vec![
+        PermissionPreset {
+            id: "read-only".to_string(),
+            name: "Safe read-only".to_string(),
+            description:
+                "Read and search the project without asking; never edit files or fetch from the web"
+                    .to_string(),
+            allow: strings(&[
+                "Read",
+                "Glob",
+                "Grep",
+                "LS",
+                "NotebookRead",
+                "Bash(git status)",
+                "Bash(git diff:*)",
+                "Bash(git log:*)",
+            ]),
+            deny: strings(&[
+                "Edit",
+                "MultiEdit",
+                "Write",
+                "NotebookEdit",
+                "WebFetch",
+                "Read(./.env)",
+                "Read(./.env.*)",
+            ]),
+        },
+        PermissionPreset {
+            id: "web".to_string(),
+            name: "Web allowed".to_string(),
+            description: "Search the web and fetch pages without asking".to_string(),
+            allow: strings(&["WebSearch", "WebFetch"]),
+            deny: Vec::new(),
+        },
+        PermissionPreset {
+            id: "yolo".to_string(),
+            name: "YOLO".to_string(),
+            description:
+                "Run every built-in tool without asking, except a few destructive commands"
+                    .to_string(),
+            allow: strings(&[
+                "Bash",
+                "Read",
+                "Edit",
+                "MultiEdit",
+                "Write",
+                "NotebookEdit",
+                "Glob",
+                "Grep",
+                "LS",
+                "WebFetch",
+                "WebSearch",
+                "Task",
+                "TodoWrite",
+            ]),
+            deny: strings(&[
+                "Bash(rm -rf /:*)",
+                "Bash(sudo:*)",
+                "Bash(git push --force:*)",
+            ]),
+        },
+    ]
+}
+
+/// Check one rule; returns every problem found
+pub fn validate_rule(rule: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    if rule.trim() != rule {
+        errors.push("Remove the spaces around the rule".to_string());
+    }
+    let Some(captures) = rule_regex().captures(rule.trim()) else {
+        if rule.contains('(') && !rule.trim_end().ends_with(')') {
+            errors.push("Missing closing parenthesis".to_string());
+        } else {
+            errors.push("Expected Tool or Tool(specifier)".to_string());
+        }
+        return errors;
+    };
+    let tool = &captures[1];
+    let specifier = captures.get(2).map(|m| m.as_str());
+
+    if let Some(mcp) = tool.strip_prefix("mcp__") {
+        if mcp.is_empty() || mcp.starts_with('_') {
+            errors.push("MCP rules need a server name: mcp__<server>".to_string());
+        }
+        if specifier.is_some() {
+            errors.push(
+                "MCP rules take no specifier; use mcp__<server>__<tool> for one tool".to_string(),
+            );
+        }
+        return errors;
+    }
+    if !KNOWN_TOOLS.contains(&tool) {
+        match KNOWN_TOOLS
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(tool))
+        {
+            Some(known) => errors.push(format!("Tool names are case-sensitive: use {}", known)),
+            None => errors.push(format!("Unknown tool: {}", tool)),
+        }
+    }
+
+    let Some(specifier) = specifier else {
+        return errors;
+    };
+    if specifier.trim().is_empty() {
+        errors.push(format!(
+            "Empty specifier; write just {} to match every use",
+            tool
+        ));
+        return errors;
+    }
+    match tool {
+        "Bash" => {
+            if specifier == "*" {
+                errors.push("Write just Bash to match every command".to_string());
+            } else if specifier
+                .find(":*")
+                .is_some_and(|at| at + 2 != specifier.len())
+            {
+                errors.push("The :* prefix wildcard is only allowed at the end".to_string());
+            }
+        }
+        "WebFetch" => {
+            if !specifier.starts_with("domain:") {
+                errors
+                    .push("WebFetch rules match domains: WebFetch(domain:example.com)".to_string());
+            } else if specifier.contains("://") || specifier.contains('/') {
+                errors.push("Use a bare domain without scheme or path".to_string());
+            }
+        }
+        tool if PATH_TOOLS.contains(&tool) && specifier.contains('\\') => {
+            errors.push("Use / in path patterns, also on Windows".to_string());
+        }
+        _ => {}
+    }
+    errors
+}
+
+fn read_list(permissions: Option<&Map<String, Value>>, list: &str) -> Result<Vec<String>, String> {
+    match permissions.and_then(|permissions| permissions.get(list)) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("permissions.{} must contain only strings", list))
+            })
+            .collect(),
+        Some(_) => Err(format!("permissions.{} must be an array", list)),
+    }
+}
+
+/// Rules of both lists in one settings file, including invalid ones
+pub fn read_rules(path: &Path, scope: &str) -> Result<Vec<PermissionRuleRecord>, String> {
+    let settings = read_json(path)?;
+    let permissions = settings.get("permissions").and_then(Value::as_object);
+    let mut records = Vec::new();
+    for list in PERMISSION_LISTS {
+        for rule in read_list(permissions, list)? {
+            records.push(PermissionRuleRecord {
+                scope: scope.to_string(),
+                list: list.to_string(),
+                errors: validate_rule(&rule),
+                rule,
+            });
+        }
+    }
+    Ok(records)
+}
+
+/// Apply `change` to the allow and deny lists of a settings file
+///
+/// Every other setting, including the rest of `permissions`, is written back
+/// unchanged; an emptied list is removed.
+fn update_lists(
+    path: &Path,
+    change: impl FnOnce(&mut Vec<String>, &mut Vec<String>),
+) -> Result<(), String> {
+    let _lock = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut settings = read_json(path)?;
+    let root = settings
+        .as_object_mut()
+        .ok_or("Settings file does not contain a JSON object")?;
+    let permissions = root
+        .entry("permissions")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("permissions must be a JSON object")?;
+
+    let mut allow = read_list(Some(permissions), "allow")?;
+    let mut deny = read_list(Some(permissions), "deny")?;
+    change(&mut allow, &mut deny);
+    for (list, rules) in [("allow", allow), ("deny", deny)] {
+        if rules.is_empty() {
+            permissions.remove(list);
+        } else {
+            permissions.insert(list.to_string(), Value::from(rules));
+        }
+    }
+
+    write_json(path, &settings)
+}
+
+/// Replace both lists; fails without writing if any rule is invalid
+pub fn save_rules(path: &Path, allow: Vec<String>, deny: Vec<String>) -> Result<(), String> {
+    let errors: Vec<String> = allow
+        .iter()
+        .chain(&deny)
+        .flat_map(|rule| {
+            validate_rule(rule)
+                .into_iter()
+                .map(move |error| format!("{}: {}", rule, error))
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    update_lists(path, |current_allow, current_deny| {
+        *current_allow = dedup(allow);
+        *current_deny = dedup(deny);
+    })
+}
+
+fn dedup(rules: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(rules.len());
+    for rule in rules {
+        if !unique.contains(&rule) {
+            unique.push(rule);
+        }
+    }
+    unique
+}
+
+/// Add a preset's rules, or with `replace` make them the only rules
+///
+/// Merged rules win over conflicting existing ones: an allowed rule is taken
+/// out of the deny list and the other way round, since denials always win.
+pub fn apply_preset(path: &Path, preset: &PermissionPreset, replace: bool) -> Result<(), String> {
+    update_lists(path, |allow, deny| {
+        if replace {
+            allow.clear();
+            deny.clear();
+        }
+        allow.retain(|rule| !preset.deny.contains(rule));
+        deny.retain(|rule| !preset.allow.contains(rule));
+        allow.extend(preset.allow.iter().cloned());
+        deny.extend(preset.deny.iter().cloned());
+        *allow = dedup(std::mem::take(allow));
+        *deny = dedup(std::mem::take(deny));
+    })
+}
+
+/// List permission rules of the user settings and, with a project, of its
+/// shared and local settings, with validation errors per rule
+#[tauri::command]
+pub async fn permissions_list(
+    project_path: Option<String>,
+) -> Result<Vec<PermissionRuleRecord>, String> {
+    let mut records = Vec::new();
+    for scope in scopes(project_path.as_deref()) {
+        let path = settings_path(scope, project_path.as_deref())?;
+        records.extend(read_rules(&path, scope)?);
+    }
+    Ok(records)
+}
+
+/// Replace the allow and deny lists of one settings file
+#[tauri::command]
+pub async fn permissions_save(
+    scope: String,
+    project_path: Option<String>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+) -> Result<Vec<PermissionRuleRecord>, String> {
+    let path = settings_path(&scope, project_path.as_deref())?;
+    save_rules(&path, allow, deny)?;
+    log::info!("Saved permission rules in {:?}", path);
+    read_rules(&path, &scope)
+}
+
+/// Problems with a rule, for validating while the user types
+#[tauri::command]
+pub async fn permission_rule_validate(rule: String) -> Result<Vec<String>, String> {
+    Ok(validate_rule(&rule))
+}
+
+#[tauri::command]
+pub async fn permission_presets_list() -> Result<Vec<PermissionPreset>, String> {
+    Ok(presets())
+}
+
+#[tauri::command]
+pub async fn permission_preset_apply(
+    scope: String,
+    project_path: Option<String>,
+    preset_id: String,
+    replace: bool,
+) -> Result<Vec<PermissionRuleRecord>, String> {
+    let preset = presets()
+        .into_iter()
+        .find(|preset| preset.id == preset_id)
+        .ok_or_else(|| format!("Unknown permission preset: {}", preset_id))?;
+    let path = settings_path(&scope, project_path.as_deref())?;
+    apply_preset(&path, &preset, replace)?;
+    log::info!("Applied permission preset {} to {:?}", preset_id, path);
+    read_rules(&path, &scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_rule() {
+        for rule in [
+            "Bash",
+            "Bash(npm run test:*)",
+            "Read(~/.zshrc)",
+            "Edit(/src/**/*.rs)",
+            "WebFetch(domain:docs.rs)",
+            "mcp__github",
+            "mcp__github__create_issue",
+        ] {
+            assert!(validate_rule(rule).is_empty(), "{}", rule);
+        }
+        for rule in [
+            "bash(ls)",
+            "Bash(npm:* run)",
+            "Bash(*)",
+            "Bash(ls",
+            "Read()",
+            "WebFetch(https://docs.rs)",
+            "mcp__github(create_issue)",
+            "Browse",
+            " Read",
+        ] {
+            assert!(!validate_rule(rule).is_empty(), "{}", rule);
+        }
+        for preset in presets() {
+            for rule in preset.allow.iter().chain(&preset.deny) {
+                assert!(validate_rule(rule).is_empty(), "{}", rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_and_apply_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"model": "opus", "permissions": {"defaultMode": "plan", "deny": ["WebFetch"]}}"#,
+        )
+        .unwrap();
+
+        assert!(save_rules(&path, vec!["bash".to_string()], Vec::new()).is_err());
+        let web = presets().into_iter().find(|p| p.id == "web").unwrap();
+        apply_preset(&path, &web, false).unwrap();
+        let settings = read_json(&path).unwrap();
+        assert_eq!(settings["model"], "opus");
+        assert_eq!(settings["permissions"]["defaultMode"], "plan");
+        assert_eq!(
+            settings["permissions"]["allow"],
+            json!(["WebSearch", "WebFetch"])
+        );
+        assert!(settings["permissions"].get("deny").is_none());
+
+        save_rules(
+            &path,
+            vec!["Read".to_string(), "Read".to_string()],
+            vec!["Bash(sudo:*)".to_string()],
+        )
+        .unwrap();
+        let records = read_rules(&path, "user").unwrap();
+        let rules: Vec<(&str, &str)> = records
+            .iter()
+            .map(|record| (record.list.as_str(), record.rule.as_str()))
+            .collect();
+        assert_eq!(rules, vec![("allow", "Read"), ("deny", "Bash(sudo:*)")]);
+    }
+}
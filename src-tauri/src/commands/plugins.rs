@@ -0,0 +1,625 @@
+//! Backend plugins: external processes speaking JSON-RPC 2.0 over stdio
+//!
+//! A plugin is a folder in `<app data>/plugins/<id>` holding a `plugin.json`
+//! manifest. It can expose commands, invoked from the frontend through
+//! `invoke_plugin_command`, and relay station adapters, used by stations
+//! the user bound to the plugin with `bind_station_plugin`. Plugins are
+//! installed disabled and only started on first use.
+//!
+//! Requests are single lines `{"jsonrpc":"2.0","id":1,"method":"command/sync","params":{..}}`
+//! on stdin; the plugin answers each with a line holding the same `id` and a
+//! `result` or an `error` on stdout. Commands are called as `command/<name>`,
+//! adapter methods as `adapter/<method>` with the adapter name and station in
+//! the params. Anything a plugin writes to stderr goes to the app log.
+use crate::commands::agents::AgentDb;
+use crate::commands::relay_adapters::plugin;
+use crate::commands::relay_stations::RelayStationManager;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin};
+use tokio::sync::oneshot;
+
+/// app_settings key storing which plugins are enabled (JSON)
+pub const PLUGIN_SETTINGS_KEY: &str = "plugin_settings";
+
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+/// Folder in the app data directory holding installed plugins
+const PLUGINS_DIR: &str = "plugins";
+
+const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginSettings {
+    pub enabled: Vec<String>,
+}
+
+/// A command or adapter a plugin offers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginExport {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginManifest {
+    /// Folder name and identifier: lowercase letters, digits, `-` and `_`
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Program to start; paths starting with `.` are relative to the plugin folder
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub commands: Vec<PluginExport>,
+    #[serde(default)]
+    pub adapters: Vec<PluginExport>,
+}
+
+impl PluginManifest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!(
+                "Invalid plugin id '{}': use lowercase letters, digits, '-' and '_'",
+                self.id
+            ));
+        }
+        if self.name.trim().is_empty() || self.command.trim().is_empty() {
+            return Err(format!("Plugin '{}' needs a name and a command", self.id));
+        }
+        Ok(())
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub manifest: PluginManifest,
+    pub path: String,
+    pub enabled: bool,
+    pub running: bool,
+}
+
+/// Calls awaiting an answer, by request id
+type PendingCalls = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// A running plugin process and its calls awaiting an answer
+struct PluginProcess {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    child: tokio::sync::Mutex<Child>,
+    pending: PendingCalls,
+    next_id: AtomicU64,
+    alive: Arc<AtomicBool>,
+}
+
+pub struct PluginHost {
+    dir: PathBuf,
+    enabled: Mutex<HashSet<String>>,
+    processes: tokio::sync::Mutex<HashMap<String, Arc<PluginProcess>>>,
+}
+
+static HOST: OnceLock<PluginHost> = OnceLock::new();
+
+/// Set up the plugin host; called once from the app setup
+pub fn init(app: &AppHandle, conn: &rusqlite::Connection) {
+    let dir = match crate::portable::app_data_dir(app) {
+        Ok(dir) => dir.join(PLUGINS_DIR),
+        Err(e) => {
+            log::warn!("Plugins are unavailable: {}", e);
+            return;
+        }
+    };
+    let enabled = load_plugin_settings(conn).enabled.into_iter().collect();
+    let _ = HOST.set(PluginHost {
+        dir,
+        enabled: Mutex::new(enabled),
+        processes: tokio::sync::Mutex::new(HashMap::new()),
+    });
+}
+
+pub fn host() -> Result<&'static PluginHost, String> {
+    HOST.get()
+        .ok_or_else(|| "Plugin host is not initialized".to_string())
+}
+
+pub fn load_plugin_settings(conn: &rusqlite::Connection) -> PluginSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![PLUGIN_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn save_plugin_settings(conn: &rusqlite::Connection, host: &PluginHost) -> Result<(), String> {
+    let mut enabled: Vec<String> = host
+        .enabled
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .cloned()
+        .collect();
+    enabled.sort();
+    let value = serde_json::to_string(&PluginSettings { enabled }).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![PLUGIN_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save plugin settings: {}", e))?;
+    Ok(())
+}
+
+pub fn read_manifest(dir: &Path) -> Result<PluginManifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: PluginManifest =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+/// Answer to a request, from one line of plugin output
+fn parse_response(line: &str) -> Option<(u64, Result<Value, String>)> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    let id = message.get("id")?.as_u64()?;
+    let result = match message.get("error") {
+        Some(error) => Err(error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string())),
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    };
+    Some((id, result))
+}
+
+impl PluginHost {
+    fn plugin_dir(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.enabled
+            .lock()
+            .map(|enabled| enabled.contains(id))
+            .unwrap_or(false)
+    }
+
+    pub fn manifests(&self) -> Vec<(PluginManifest, PathBuf)> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut manifests: Vec<(PluginManifest, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| match read_manifest(&entry.path()) {
+                Ok(manifest) => Some((manifest, entry.path())),
+                Err(e) => {
+                    log::warn!("Skipping plugin in {:?}: {}", entry.path(), e);
+                    None
+                }
+            })
+            .collect();
+        manifests.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        manifests
+    }
+
+    /// Manifest of an installed plugin
+    pub fn manifest(&self, id: &str) -> Result<PluginManifest, String> {
+        if !is_valid_id(id) {
+            return Err(format!("Invalid plugin id '{}'", id));
+        }
+        let manifest = read_manifest(&self.plugin_dir(id))?;
+        if manifest.id != id {
+            return Err(format!("Plugin {} is not installed", id));
+        }
+        Ok(manifest)
+    }
+
+    fn spawn(&self, manifest: &PluginManifest) -> Result<PluginProcess, String> {
+        let dir = self.plugin_dir(&manifest.id);
+        let program = if manifest.command.starts_with('.') {
+            dir.join(&manifest.command).to_string_lossy().into_owned()
+        } else {
+            manifest.command.clone()
+        };
+        let mut cmd = crate::claude_binary::create_command_with_env(&program);
+        cmd.args(&manifest.args)
+            .current_dir(&dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        let mut child = tokio::process::Command::from(cmd)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start plugin {}: {}", manifest.id, e))?;
+        let stdin = child.stdin.take().ok_or("Plugin stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("Plugin stdout unavailable")?;
+        let stderr = child.stderr.take().ok_or("Plugin stderr unavailable")?;
+
+        let pending = PendingCalls::default();
+        let alive = Arc::new(AtomicBool::new(true));
+
+        let plugin_id = manifest.id.clone();
+        let reader_pending = pending.clone();
+        let reader_alive = alive.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match parse_response(&line) {
+                    Some((id, result)) => {
+                        let sender = reader_pending.lock().ok().and_then(|mut p| p.remove(&id));
+                        if let Some(sender) = sender {
+                            let _ = sender.send(result);
+                        }
+                    }
+                    None => log::debug!("Plugin {}: {}", plugin_id, line),
+                }
+            }
+            reader_alive.store(false, Ordering::SeqCst);
+            // Dropping the senders fails every call still waiting
+            if let Ok(mut pending) = reader_pending.lock() {
+                pending.clear();
+            }
+            log::info!("Plugin {} exited", plugin_id);
+        });
+
+        let plugin_id = manifest.id.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log::info!("Plugin {}: {}", plugin_id, line);
+            }
+        });
+
+        log::info!("Started plugin {} {}", manifest.id, manifest.version);
+        Ok(PluginProcess {
+            stdin: tokio::sync::Mutex::new(stdin),
+            child: tokio::sync::Mutex::new(child),
+            pending,
+            next_id: AtomicU64::new(1),
+            alive,
+        })
+    }
+
+    /// The plugin's process, started if it is not running
+    async fn process(&self, id: &str) -> Result<Arc<PluginProcess>, String> {
+        if !self.is_enabled(id) {
+            return Err(format!("Plugin {} is not enabled", id));
+        }
+        let mut processes = self.processes.lock().await;
+        if let Some(process) = processes.get(id) {
+            if process.alive.load(Ordering::SeqCst) {
+                return Ok(process.clone());
+            }
+        }
+        let manifest = self.manifest(id)?;
+        let process = Arc::new(self.spawn(&manifest)?);
+        processes.insert(id.to_string(), process.clone());
+        Ok(process)
+    }
+
+    /// Call a JSON-RPC method of a plugin
+    pub async fn call(&self, id: &str, method: &str, params: Value) -> Result<Value, String> {
+        let process = self.process(id).await?;
+        let request_id = process.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        process
+            .pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(request_id, sender);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": method,
+            "params": params,
+        });
+        let line = format!("{}\n", request);
+        {
+            let mut stdin = process.stdin.lock().await;
+            stdin
+                .write_all(line.as_bytes())
+                .await
+                .map_err(|e| format!("Failed to send to plugin {}: {}", id, e))?;
+            stdin
+                .flush()
+                .await
+                .map_err(|e| format!("Failed to send to plugin {}: {}", id, e))?;
+        }
+
+        match tokio::time::timeout(CALL_TIMEOUT, receiver).await {
+            Ok(Ok(result)) => result.map_err(|e| format!("Plugin {}: {}", id, e)),
+            Ok(Err(_)) => Err(format!("Plugin {} exited before answering", id)),
+            Err(_) => {
+                if let Ok(mut pending) = process.pending.lock() {
+                    pending.remove(&request_id);
+                }
+                Err(format!("Plugin {} did not answer {} in time", id, method))
+            }
+        }
+    }
+
+    pub async fn stop(&self, id: &str) {
+        if let Some(process) = self.processes.lock().await.remove(id) {
+            let _ = process.child.lock().await.kill().await;
+            log::info!("Stopped plugin {}", id);
+        }
+    }
+
+    async fn is_running(&self, id: &str) -> bool {
+        self.processes
+            .lock()
+            .await
+            .get(id)
+            .is_some_and(|process| process.alive.load(Ordering::SeqCst))
+    }
+
+    async fn info(&self, manifest: PluginManifest, path: PathBuf) -> PluginInfo {
+        PluginInfo {
+            enabled: self.is_enabled(&manifest.id),
+            running: self.is_running(&manifest.id).await,
+            path: path.to_string_lossy().into_owned(),
+            manifest,
+        }
+    }
+}
+
+fn copy_dir(source: &Path, target: &Path) -> Result<(), String> {
+    for entry in walkdir::WalkDir::new(source) {
+        let entry = entry.map_err(|e| format!("Failed to read plugin files: {}", e))?;
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .map_err(|e| e.to_string())?;
+        let destination = target.join(relative);
+        // A link could pull in files from outside the plugin folder
+        if entry.file_type().is_symlink() {
+            log::warn!("Skipping symlink {} in plugin", entry.path().display());
+            continue;
+        }
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&destination)
+        } else {
+            std::fs::copy(entry.path(), &destination).map(|_| ())
+        }
+        .map_err(|e| format!("Failed to copy {}: {}", entry.path().display(), e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_plugins() -> Result<Vec<PluginInfo>, String> {
+    let host = host()?;
+    let mut plugins = Vec::new();
+    for (manifest, path) in host.manifests() {
+        plugins.push(host.info(manifest, path).await);
+    }
+    Ok(plugins)
+}
+
+/// Install (or update) the plugin in `source_dir`; it starts out disabled,
+/// also when it replaces an enabled version
+#[tauri::command]
+pub async fn install_plugin(
+    db: State<'_, AgentDb>,
+    source_dir: String,
+) -> Result<PluginInfo, String> {
+    let host = host()?;
+    let source = PathBuf::from(&source_dir);
+    let manifest = read_manifest(&source)?;
+    let target = host.plugin_dir(&manifest.id);
+    if source.canonicalize().ok() == target.canonicalize().ok() {
+        return Err(format!("{} is already installed", manifest.id));
+    }
+
+    host.stop(&manifest.id).await;
+    let was_enabled = host
+        .enabled
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&manifest.id);
+    if was_enabled {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        save_plugin_settings(&conn, host)?;
+    }
+    if target.exists() {
+        std::fs::remove_dir_all(&target)
+            .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+    }
+    copy_dir(&source, &target)?;
+    log::info!("Installed plugin {} {}", manifest.id, manifest.version);
+    Ok(host.info(manifest, target).await)
+}
+
+#[tauri::command]
+pub async fn uninstall_plugin(db: State<'_, AgentDb>, id: String) -> Result<(), String> {
+    let host = host()?;
+    // Also rejects ids that would escape the plugins folder
+    host.manifest(&id)?;
+    host.stop(&id).await;
+    std::fs::remove_dir_all(host.plugin_dir(&id))
+        .map_err(|e| format!("Failed to remove plugin {}: {}", id, e))?;
+    host.enabled.lock().map_err(|e| e.to_string())?.remove(&id);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_plugin_settings(&conn, host)?;
+    log::info!("Uninstalled plugin {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_plugin_enabled(
+    db: State<'_, AgentDb>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let host = host()?;
+    host.manifest(&id)?;
+    {
+        let mut set = host.enabled.lock().map_err(|e| e.to_string())?;
+        if enabled {
+            set.insert(id.clone());
+        } else {
+            set.remove(&id);
+        }
+    }
+    if !enabled {
+        host.stop(&id).await;
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_plugin_settings(&conn, host)
+}
+
+/// Bind a station to a plugin adapter, or unbind it when `plugin_id` is unset
+///
+/// The plugin then gets the station's system token with every call, which is
+/// why this is the only way a binding is made.
+#[tauri::command]
+pub async fn bind_station_plugin(
+    app: AppHandle,
+    station_id: String,
+    plugin_id: Option<String>,
+    adapter: Option<String>,
+) -> Result<(), String> {
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let manager = manager
+        .as_ref()
+        .ok_or_else(|| "Relay station manager not initialized".to_string())?;
+    let station = manager
+        .get_station(&station_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Station {} not found", station_id))?;
+
+    let mut config = station.adapter_config;
+    plugin::strip_binding(&mut config);
+    if let Some(plugin_id) = plugin_id {
+        let manifest = host()?.manifest(&plugin_id)?;
+        let adapter = match adapter {
+            Some(adapter) => manifest
+                .adapters
+                .iter()
+                .find(|export| export.name == adapter)
+                .ok_or_else(|| format!("Plugin {} has no adapter {}", plugin_id, adapter))?,
+            None => manifest
+                .adapters
+                .first()
+                .ok_or_else(|| format!("Plugin {} offers no relay adapter", plugin_id))?,
+        };
+        let config = config.get_or_insert_with(HashMap::new);
+        config.insert(plugin::PLUGIN_CONFIG_KEY.to_string(), json!(plugin_id));
+        config.insert(
+            plugin::PLUGIN_ADAPTER_CONFIG_KEY.to_string(),
+            json!(adapter.name),
+        );
+    }
+    manager
+        .set_adapter_config(&station_id, config.as_ref())
+        .map_err(|e| e.to_string())
+}
+
+/// Run a command a plugin declared in its manifest
+#[tauri::command]
+pub async fn invoke_plugin_command(
+    plugin_id: String,
+    command: String,
+    args: Option<Value>,
+) -> Result<Value, String> {
+    let host = host()?;
+    let manifest = host.manifest(&plugin_id)?;
+    if !manifest
+        .commands
+        .iter()
+        .any(|export| export.name == command)
+    {
+        return Err(format!("Plugin {} has no command {}", plugin_id, command));
+    }
+    host.call(
+        &plugin_id,
+        &format!("command/{}", command),
+        args.unwrap_or(Value::Null),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_and_responses() {
+        let manifest: PluginManifest = serde_json::from_str(
+            r#"{"id": "acme-relay", "name": "Acme relay", "version": "0.1.0",
+                "command": "./bin/acme", "adapters": [{"name": "acme"}]}"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_ok());
+        assert_eq!(manifest.adapters[0].name, "acme");
+        let escaping = PluginManifest {
+            id: "../evil".to_string(),
+            ..manifest
+        };
+        assert!(escaping.validate().is_err());
+
+        assert_eq!(
+            parse_response(r#"{"jsonrpc":"2.0","id":3,"result":{"ok":true}}"#),
+            Some((3, Ok(json!({"ok": true}))))
+        );
+        assert_eq!(
+            parse_response(
+                r#"{"jsonrpc":"2.0","id":4,"error":{"code":-32601,"message":"Method not found"}}"#
+            ),
+            Some((4, Err("Method not found".to_string())))
+        );
+        assert_eq!(parse_response("plugin ready"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_skips_symlinks() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        let source = tempfile::tempdir().unwrap();
+        std::fs::create_dir(source.path().join("bin")).unwrap();
+        std::fs::write(source.path().join("bin/acme"), "#!/bin/sh").unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.txt"),
+            source.path().join("bin/secret.txt"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(outside.path(), source.path().join("outside")).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        copy_dir(source.path(), target.path()).unwrap();
+        assert!(target.path().join("bin/acme").is_file());
+        assert!(!target.path().join("bin/secret.txt").exists());
+        assert!(!target.path().join("outside").exists());
+    }
+}
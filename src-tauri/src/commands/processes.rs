@@ -0,0 +1,326 @@
+use crate::commands::agents::AgentDb;
+use crate::process::discovery::{scan_claude_processes, ExternalClaudeProcess};
+use crate::process::{
+    query_runs, ConcurrencyLimits, ManagedProcessInfo, ProcessInfo, ProcessOutputChunk,
+    ProcessRegistry, ProcessRegistryState, QueuedRun, ResourceThresholds, RestartPolicy,
+    RunHistoryPage,
+};
+use rusqlite::params;
+use tauri::{AppHandle, Emitter, State};
+
+/// app_settings key storing the graceful shutdown grace period
+pub const SHUTDOWN_GRACE_SETTING_KEY: &str = "process_shutdown_grace_ms";
+
+/// app_settings key storing the Claude session concurrency limits (JSON)
+pub const CONCURRENCY_LIMITS_SETTING_KEY: &str = "process_concurrency_limits";
+
+/// app_settings key storing the resource usage alert thresholds (JSON)
+pub const RESOURCE_THRESHOLDS_SETTING_KEY: &str = "process_resource_thresholds";
+
+/// List every process tracked by the registry with PID, type, project path,
+/// uptime and live CPU/RAM usage
+#[tauri::command]
+pub async fn list_managed_processes(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<ManagedProcessInfo>, String> {
+    registry.0.list_managed_processes().await
+}
+
+/// Read captured stdout/stderr lines for a run starting at `offset`
+///
+/// Live lines are also emitted as `process-output:{run_id}` events; the
+/// frontend calls this after subscribing to backfill anything it missed.
+#[tauri::command]
+pub async fn get_process_output(
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<ProcessOutputChunk, String> {
+    registry
+        .0
+        .get_output(run_id, offset.unwrap_or(0), limit.unwrap_or(1000))
+}
+
+/// Get the grace period (ms) a process is given to exit after an interrupt
+#[tauri::command]
+pub async fn get_shutdown_grace_period(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<u64, String> {
+    Ok(registry.0.shutdown_grace_period_ms())
+}
+
+/// Set and persist the graceful shutdown grace period (ms)
+#[tauri::command]
+pub async fn set_shutdown_grace_period(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    grace_ms: u64,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![SHUTDOWN_GRACE_SETTING_KEY, grace_ms.to_string()],
+        )
+        .map_err(|e| format!("Failed to save shutdown grace period: {}", e))?;
+    }
+
+    registry.0.set_shutdown_grace_period_ms(grace_ms);
+    Ok(())
+}
+
+/// Enable auto-restart for a Claude session (pass `null` to disable)
+///
+/// When the session exits non-zero it is relaunched with `--resume` using the
+/// policy's backoff; progress is emitted as `claude-session-restart:{session_id}`.
+#[tauri::command]
+pub async fn set_session_restart_policy(
+    registry: State<'_, ProcessRegistryState>,
+    session_id: String,
+    policy: Option<RestartPolicy>,
+) -> Result<(), String> {
+    registry.0.set_restart_policy(&session_id, policy)
+}
+
+/// Get the auto-restart policy of a Claude session, if any
+#[tauri::command]
+pub async fn get_session_restart_policy(
+    registry: State<'_, ProcessRegistryState>,
+    session_id: String,
+) -> Result<Option<RestartPolicy>, String> {
+    Ok(registry.0.get_restart_policy(&session_id))
+}
+
+/// Notify the frontend that the run queue changed
+pub(crate) fn emit_run_queue_changed(app: &AppHandle, registry: &ProcessRegistry) {
+    if let Ok(runs) = registry.run_queue().list() {
+        let _ = app.emit("claude-run-queue-changed", &runs);
+    }
+}
+
+/// Get the maximum number of concurrent Claude sessions (globally and per project)
+#[tauri::command]
+pub async fn get_concurrency_limits(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<ConcurrencyLimits, String> {
+    Ok(registry.0.run_queue().limits())
+}
+
+/// Set and persist the concurrency limits, starting queued runs if slots opened up
+#[tauri::command]
+pub async fn set_concurrency_limits(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    limits: ConcurrencyLimits,
+) -> Result<(), String> {
+    if limits.max_global == Some(0) || limits.max_per_project == Some(0) {
+        return Err("Concurrency limits must be at least 1".to_string());
+    }
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let value = serde_json::to_string(&limits).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![CONCURRENCY_LIMITS_SETTING_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save concurrency limits: {}", e))?;
+    }
+
+    registry.0.run_queue().set_limits(limits);
+    tokio::spawn(crate::commands::claude::start_queued_runs(app));
+    Ok(())
+}
+
+/// List Claude runs waiting for a free session slot, in start order
+#[tauri::command]
+pub async fn get_run_queue(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<QueuedRun>, String> {
+    registry.0.run_queue().list()
+}
+
+/// Drop a queued run before it starts
+#[tauri::command]
+pub async fn remove_queued_run(
+    app: AppHandle,
+    registry: State<'_, ProcessRegistryState>,
+    id: String,
+) -> Result<bool, String> {
+    let removed = registry.0.run_queue().remove(&id)?;
+    if removed {
+        emit_run_queue_changed(&app, &registry.0);
+    }
+    Ok(removed)
+}
+
+/// Move a queued run to a new 0-based position
+#[tauri::command]
+pub async fn move_queued_run(
+    app: AppHandle,
+    registry: State<'_, ProcessRegistryState>,
+    id: String,
+    position: usize,
+) -> Result<(), String> {
+    registry.0.run_queue().move_to(&id, position)?;
+    emit_run_queue_changed(&app, &registry.0);
+    Ok(())
+}
+
+/// List still-running processes left behind by a previous (crashed) launch
+#[tauri::command]
+pub async fn list_orphaned_processes(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<ProcessInfo>, String> {
+    registry.0.get_orphans()
+}
+
+/// Adopt orphaned processes into the registry or kill them
+///
+/// `action` is either "adopt" or "kill". Returns the run IDs that were handled.
+#[tauri::command]
+pub async fn adopt_or_kill_orphans(
+    registry: State<'_, ProcessRegistryState>,
+    run_ids: Vec<i64>,
+    action: String,
+) -> Result<Vec<i64>, String> {
+    let mut handled = Vec::new();
+    for run_id in run_ids {
+        let result = match action.as_str() {
+            "adopt" => registry.0.adopt_orphan(run_id),
+            "kill" => registry.0.kill_orphan(run_id),
+            _ => return Err(format!("Unknown orphan action: {}", action)),
+        };
+        match result {
+            Ok(true) => handled.push(run_id),
+            Ok(false) => log::warn!("Orphaned process {} is no longer available", run_id),
+            Err(e) => log::warn!("Failed to {} orphaned process {}: {}", action, run_id, e),
+        }
+    }
+    Ok(handled)
+}
+
+/// Page through finished runs (newest first), optionally for a single project
+#[tauri::command]
+pub async fn get_run_history(
+    db: State<'_, AgentDb>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    project_path: Option<String>,
+) -> Result<RunHistoryPage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_runs(
+        &conn,
+        page.unwrap_or(1),
+        page_size.unwrap_or(50),
+        project_path.as_deref(),
+    )
+}
+
+/// Scan for Claude CLI processes started outside the workbench
+#[tauri::command]
+pub async fn scan_external_claude_processes(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<Vec<ExternalClaudeProcess>, String> {
+    let tracked = registry.0.tracked_pids()?;
+    tokio::task::spawn_blocking(move || scan_claude_processes(&tracked))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Adopt an external Claude process so it is monitored and stopped on provider switches
+#[tauri::command]
+pub async fn adopt_external_process(
+    registry: State<'_, ProcessRegistryState>,
+    pid: u32,
+) -> Result<i64, String> {
+    registry.0.adopt_external_process(pid)
+}
+
+/// Pause a running process (and its children) instead of killing it
+#[tauri::command]
+pub async fn suspend_process(
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    registry.0.suspend_process(run_id)
+}
+
+/// Continue a process paused with `suspend_process`
+#[tauri::command]
+pub async fn resume_process(
+    registry: State<'_, ProcessRegistryState>,
+    run_id: i64,
+) -> Result<bool, String> {
+    registry.0.resume_process(run_id)
+}
+
+/// Get the memory/CPU thresholds that trigger `process-resource-alert` events
+#[tauri::command]
+pub async fn get_resource_thresholds(
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<ResourceThresholds, String> {
+    Ok(registry.0.resource_thresholds())
+}
+
+/// Set and persist the resource usage thresholds (and whether to auto-kill)
+#[tauri::command]
+pub async fn set_resource_thresholds(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    thresholds: ResourceThresholds,
+) -> Result<(), String> {
+    if thresholds.max_memory_mb == Some(0)
+        || thresholds.max_cpu_percent.is_some_and(|cpu| cpu <= 0.0)
+    {
+        return Err("Resource thresholds must be greater than 0".to_string());
+    }
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let value = serde_json::to_string(&thresholds).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![RESOURCE_THRESHOLDS_SETTING_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save resource thresholds: {}", e))?;
+    }
+
+    registry.0.set_resource_thresholds(thresholds);
+    Ok(())
+}
+
+/// Load persisted process settings into the registry at startup
+pub fn load_process_settings(conn: &rusqlite::Connection, registry: &ProcessRegistryState) {
+    if let Ok(value) = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![SHUTDOWN_GRACE_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    ) {
+        if let Ok(grace_ms) = value.parse::<u64>() {
+            registry.0.set_shutdown_grace_period_ms(grace_ms);
+        }
+    }
+
+    if let Ok(value) = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![CONCURRENCY_LIMITS_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    ) {
+        if let Ok(limits) = serde_json::from_str::<ConcurrencyLimits>(&value) {
+            registry.0.run_queue().set_limits(limits);
+        }
+    }
+
+    if let Ok(value) = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![RESOURCE_THRESHOLDS_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    ) {
+        if let Ok(thresholds) = serde_json::from_str::<ResourceThresholds>(&value) {
+            registry.0.set_resource_thresholds(thresholds);
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::commands::agents::AgentDb;
+use crate::commands::sessions::sync_session_index;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Per-project settings kept by the app, not by Claude Code
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProjectMeta {
+    pub project_id: String,
+    /// Name shown instead of the project path
+    pub display_name: Option<String>,
+    pub pinned: bool,
+    pub hidden: bool,
+    /// Unix timestamp of pinning; pinned projects keep this order
+    pub pinned_at: Option<i64>,
+}
+
+/// Changes to a project's metadata; `None` leaves a field as it is and an
+/// empty display name clears it
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectMetaUpdate {
+    pub display_name: Option<String>,
+    pub pinned: Option<bool>,
+    pub hidden: Option<bool>,
+}
+
+/// Git state of a project directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GitInfo {
+    /// `None` for a detached HEAD
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    /// Modified, staged or untracked files
+    pub changed_files: usize,
+    pub dirty: bool,
+}
+
+/// A project of the project picker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectEntry {
+    /// Directory name under `~/.claude/projects`
+    pub id: String,
+    pub path: String,
+    pub display_name: Option<String>,
+    pub pinned: bool,
+    pub hidden: bool,
+    pub session_count: i64,
+    /// Unix timestamp of the last write to any session of the project
+    pub last_session_at: Option<i64>,
+    /// The project directory still exists
+    pub path_exists: bool,
+    /// `None` when not requested or not a git repository
+    pub git: Option<GitInfo>,
+}
+
+/// Create the `project_meta` table
+pub fn create_project_meta_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_meta (
+            project_id TEXT PRIMARY KEY,
+            display_name TEXT,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            pinned_at INTEGER,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn row_to_meta(row: &rusqlite::Row) -> rusqlite::Result<ProjectMeta> {
+    Ok(ProjectMeta {
+        project_id: row.get(0)?,
+        display_name: row.get(1)?,
+        pinned: row.get(2)?,
+        hidden: row.get(3)?,
+        pinned_at: row.get(4)?,
+    })
+}
+
+fn read_project_meta(conn: &Connection) -> Result<HashMap<String, ProjectMeta>, String> {
+    let mut stmt = conn
+        .prepare("SELECT project_id, display_name, pinned, hidden, pinned_at FROM project_meta")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], row_to_meta)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read project metadata: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|meta| (meta.project_id.clone(), meta))
+        .collect())
+}
+
+/// Apply `update` to a project's metadata and return the result
+pub fn apply_project_meta_update(
+    conn: &Connection,
+    project_id: &str,
+    update: &ProjectMetaUpdate,
+) -> Result<ProjectMeta, String> {
+    let display_name = update.display_name.as_deref().map(str::trim);
+    conn.execute(
+        "INSERT INTO project_meta (project_id) VALUES (?1) ON CONFLICT(project_id) DO NOTHING",
+        params![project_id],
+    )
+    .map_err(|e| format!("Failed to update project metadata: {}", e))?;
+    conn.execute(
+        "UPDATE project_meta SET
+            display_name = CASE WHEN ?2 IS NULL THEN display_name ELSE NULLIF(?2, '') END,
+            pinned_at = CASE
+                WHEN ?3 = 1 AND pinned = 0 THEN strftime('%s', 'now')
+                WHEN ?3 = 0 THEN NULL
+                ELSE pinned_at END,
+            pinned = COALESCE(?3, pinned),
+            hidden = COALESCE(?4, hidden),
+            updated_at = CURRENT_TIMESTAMP
+         WHERE project_id = ?1",
+        params![project_id, display_name, update.pinned, update.hidden],
+    )
+    .map_err(|e| format!("Failed to update project metadata: {}", e))?;
+
+    conn.query_row(
+        "SELECT project_id, display_name, pinned, hidden, pinned_at FROM project_meta
+         WHERE project_id = ?1",
+        params![project_id],
+        row_to_meta,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Project not found: {}", project_id))
+}
+
+/// Projects under `projects_dir`, with session data from the session index
+///
+/// Projects listed in `legacy_hidden` (the old `hidden_projects.json`) count as hidden.
+pub(crate) fn collect_projects(
+    conn: &Connection,
+    projects_dir: &Path,
+    legacy_hidden: &HashSet<String>,
+) -> Result<Vec<ProjectEntry>, String> {
+    let meta = read_project_meta(conn)?;
+
+    // Newest session first, so the first path seen is the current one
+    let mut stmt = conn
+        .prepare(
+            "SELECT project_id, project_path, modified_at FROM session_index
+             ORDER BY modified_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let sessions = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session index: {}", e))?;
+    let mut activity: HashMap<String, (String, i64, i64)> = HashMap::new();
+    for (project_id, project_path, modified_at) in sessions {
+        activity
+            .entry(project_id)
+            .or_insert((project_path, 0, modified_at))
+            .1 += 1;
+    }
+
+    let mut projects = Vec::new();
+    let Ok(entries) = fs::read_dir(projects_dir) else {
+        return Ok(projects);
+    };
+    for entry in entries.flatten() {
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let id = entry.file_name().to_string_lossy().to_string();
+        let (path, session_count, last_session_at) = match activity.remove(&id) {
+            Some((path, count, modified_at)) => (path, count, Some(modified_at)),
+            None => (crate::commands::claude::decode_project_path(&id), 0, None),
+        };
+        let project_meta = meta.get(&id).cloned().unwrap_or_default();
+        projects.push(ProjectEntry {
+            hidden: project_meta.hidden || legacy_hidden.contains(&id),
+            path_exists: Path::new(&path).is_dir(),
+            id,
+            path,
+            display_name: project_meta.display_name,
+            pinned: project_meta.pinned,
+            session_count,
+            last_session_at,
+            git: None,
+        });
+    }
+
+    // Pinned projects first in the order they were pinned, then most recently active
+    projects.sort_by(|a, b| {
+        let pinned_at = |project: &ProjectEntry| {
+            meta.get(&project.id)
+                .and_then(|meta| meta.pinned_at)
+                .unwrap_or(0)
+        };
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| pinned_at(a).cmp(&pinned_at(b)))
+            .then_with(|| b.last_session_at.cmp(&a.last_session_at))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(projects)
+}
+
+/// Parse `git status --porcelain=v1 --branch`
+pub fn parse_git_status(output: &str) -> GitInfo {
+    let mut lines = output.lines();
+    let mut info = GitInfo::default();
+
+    if let Some(header) = lines.next().and_then(|line| line.strip_prefix("## ")) {
+        let (refs, tracking) = match header.split_once(" [") {
+            Some((refs, tracking)) => (refs, tracking.trim_end_matches(']')),
+            None => (header, ""),
+        };
+        let refs = refs
+            .strip_prefix("No commits yet on ")
+            .or_else(|| refs.strip_prefix("Initial commit on "))
+            .unwrap_or(refs);
+        if !refs.starts_with("HEAD (no branch)") {
+            let (branch, upstream) = match refs.split_once("...") {
+                Some((branch, upstream)) => (branch, Some(upstream.to_string())),
+                None => (refs, None),
+            };
+            info.branch = Some(branch.to_string());
+            info.upstream = upstream;
+        }
+        for part in tracking.split(", ") {
+            if let Some(count) = part.strip_prefix("ahead ") {
+                info.ahead = count.parse().unwrap_or(0);
+            } else if let Some(count) = part.strip_prefix("behind ") {
+                info.behind = count.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    info.changed_files = lines.filter(|line| !line.trim().is_empty()).count();
+    info.dirty = info.changed_files > 0;
+    info
+}
+
+/// Git state of `path`, or `None` if it is not a work tree or git is missing
+fn git_info(path: &str) -> Option<GitInfo> {
+    #[allow(unused_mut)]
+    let mut cmd = crate::claude_binary::create_command_with_env("git");
+    cmd.args(["-C", path, "status", "--porcelain=v1", "--branch"]);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_git_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub(crate) fn claude_dir() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude"))
+}
+
+/// Projects hidden through the older `hidden_projects.json` list
+pub(crate) fn read_legacy_hidden(claude_dir: &Path) -> HashSet<String> {
+    fs::read_to_string(claude_dir.join("hidden_projects.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<String>>(&content).ok())
+        .map(|hidden| hidden.into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// List the projects in `~/.claude/projects` with their pin, name and visibility
+/// settings, session activity and git branch and dirty state
+///
+/// Hidden projects are left out unless `include_hidden`; pass `include_git: false`
+/// to skip running git for a quicker list.
+#[tauri::command]
+pub async fn list_project_registry(
+    db: State<'_, AgentDb>,
+    include_hidden: Option<bool>,
+    include_git: Option<bool>,
+) -> Result<Vec<ProjectEntry>, String> {
+    let claude_dir = claude_dir()?;
+    let projects_dir = claude_dir.join("projects");
+    sync_session_index(&db.0, &projects_dir)?;
+
+    let mut projects = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        collect_projects(&conn, &projects_dir, &read_legacy_hidden(&claude_dir))?
+    };
+    if !include_hidden.unwrap_or(false) {
+        projects.retain(|project| !project.hidden);
+    }
+
+    if include_git.unwrap_or(true) {
+        let lookups: Vec<_> = projects
+            .iter()
+            .map(|project| {
+                let path = project.path.clone();
+                let exists = project.path_exists;
+                tokio::task::spawn_blocking(move || exists.then(|| git_info(&path)).flatten())
+            })
+            .collect();
+        for (project, lookup) in projects.iter_mut().zip(lookups) {
+            project.git = lookup.await.map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(projects)
+}
+
+/// Pin, hide or rename a project
+///
+/// Hiding is mirrored to `hidden_projects.json` so the plain project list agrees.
+#[tauri::command]
+pub async fn update_project_meta(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    update: ProjectMetaUpdate,
+) -> Result<ProjectMeta, String> {
+    let meta = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        apply_project_meta_update(&conn, &project_id, &update)?
+    };
+    match update.hidden {
+        Some(true) => {
+            super::claude::delete_project(project_id).await?;
+        }
+        Some(false) => {
+            // Fails when the project was only hidden in the database
+            let _ = super::claude::restore_project(project_id).await;
+        }
+        None => {}
+    }
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::sessions::create_session_index_table;
+
+    #[test]
+    fn test_parse_git_status() {
+        let info =
+            parse_git_status("## main...origin/main [ahead 2, behind 1]\n M a.rs\n?? b.rs\n");
+        assert_eq!(info.branch.as_deref(), Some("main"));
+        assert_eq!(info.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((info.ahead, info.behind, info.changed_files), (2, 1, 2));
+        assert!(info.dirty);
+
+        let info = parse_git_status("## No commits yet on trunk\n");
+        assert_eq!(info.branch.as_deref(), Some("trunk"));
+        assert!(!info.dirty);
+        assert_eq!(parse_git_status("## HEAD (no branch)\n").branch, None);
+    }
+
+    #[test]
+    fn test_project_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        for id in ["-work-a", "-work-b", "-work-c"] {
+            fs::create_dir_all(dir.path().join(id)).unwrap();
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        create_session_index_table(&conn).unwrap();
+        create_project_meta_table(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO session_index (file_path, session_id, project_id, project_path,
+                message_count, file_size, modified_at)
+             VALUES ('1', 's1', '-work-a', '/work/a', 1, 1, 100),
+                    ('2', 's2', '-work-b', '/work/b', 1, 1, 200),
+                    ('3', 's3', '-work-b', '/work/b', 1, 1, 300)",
+            [],
+        )
+        .unwrap();
+
+        let update = ProjectMetaUpdate {
+            display_name: Some("  Alpha ".to_string()),
+            pinned: Some(true),
+            hidden: None,
+        };
+        let meta = apply_project_meta_update(&conn, "-work-a", &update).unwrap();
+        assert_eq!(meta.display_name.as_deref(), Some("Alpha"));
+        assert!(meta.pinned && meta.pinned_at.is_some());
+        let update = ProjectMetaUpdate {
+            display_name: Some(String::new()),
+            ..Default::default()
+        };
+        let meta = apply_project_meta_update(&conn, "-work-a", &update).unwrap();
+        assert_eq!(meta.display_name, None);
+        assert!(meta.pinned);
+
+        let legacy_hidden = HashSet::from(["-work-c".to_string()]);
+        let projects = collect_projects(&conn, dir.path(), &legacy_hidden).unwrap();
+        let order: Vec<&str> = projects.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(order, vec!["-work-a", "-work-b", "-work-c"]);
+        assert_eq!(projects[1].session_count, 2);
+        assert_eq!(projects[1].last_session_at, Some(300));
+        assert!(projects[2].hidden);
+        assert_eq!(projects[2].path, "/work/c");
+    }
+}
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use tauri::{command, AppHandle, Manager};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+use crate::commands::agents::AgentDb;
+use crate::commands::app_lock::{self, ProtectedAction};
 use crate::process::ProcessRegistryState;
 use log::{info, warn};
+use rusqlite::params;
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +23,26 @@ pub struct ProviderConfig {
     pub model: Option<String>,       // 对应 ANTHROPIC_MODEL
     #[serde(default, deserialize_with = "deserialize_optional_string")]
     pub small_fast_model: Option<String>,  // 对应 ANTHROPIC_SMALL_FAST_MODEL
+    #[serde(default)]
+    pub mirror_urls: Vec<String>,    // 同一供应商的镜像地址，切换时自动选择最快的一个
+    #[serde(default, deserialize_with = "deserialize_optional_string")]
+    pub binary_path: Option<String>, // 该代理商使用的 claude 可执行文件，覆盖全局查找
+    #[serde(default)]
+    pub default_args: Vec<String>,   // 启动 claude 时附加的默认参数
+}
+
+impl ProviderConfig {
+    // 返回所有可用端点（主地址优先，去重）
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints = vec![self.base_url.clone()];
+        for url in &self.mirror_urls {
+            let url = url.trim();
+            if !url.is_empty() && !endpoints.iter().any(|e| e == url) {
+                endpoints.push(url.to_string());
+            }
+        }
+        endpoints
+    }
 }
 
 // 自定义反序列化函数，将空字符串转换为None
@@ -40,6 +63,26 @@ pub struct CurrentConfig {
     pub anthropic_small_fast_model: Option<String>,
 }
 
+// 代理商使用区间记录（用于追溯某一时刻使用的上游）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderUsageSession {
+    pub id: i64,
+    pub provider_id: String,
+    pub provider_name: String,
+    pub base_url: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,  // None 表示当前仍在使用
+}
+
+// 端点健康检测结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EndpointProbeResult {
+    pub url: String,
+    pub healthy: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
 // Claude settings.json 文件结构
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClaudeSettings {
@@ -58,7 +101,12 @@ pub struct PermissionsConfig {
 }
 
 // 获取配置文件路径
-fn get_providers_config_path() -> Result<PathBuf, String> {
+pub(crate) fn get_providers_config_path() -> Result<PathBuf, String> {
+    // 便携模式下与数据库一起保存在可执行文件旁
+    if let Some(portable_dir) = crate::portable::portable_dir() {
+        return Ok(portable_dir.join("providers.json"));
+    }
+
     let home_dir = dirs::home_dir()
         .ok_or_else(|| "无法获取用户主目录".to_string())?;
     
@@ -90,7 +138,7 @@ fn get_claude_settings_path() -> Result<PathBuf, String> {
 }
 
 // 从文件加载代理商配置
-fn load_providers_from_file() -> Result<Vec<ProviderConfig>, String> {
+pub(crate) fn load_providers_from_file() -> Result<Vec<ProviderConfig>, String> {
     let config_path = get_providers_config_path()?;
     
     if !config_path.exists() {
@@ -112,7 +160,7 @@ fn load_providers_from_file() -> Result<Vec<ProviderConfig>, String> {
 }
 
 // 保存代理商配置到文件
-fn save_providers_to_file(providers: &Vec<ProviderConfig>) -> Result<(), String> {
+pub(crate) fn save_providers_to_file(providers: &[ProviderConfig]) -> Result<(), String> {
     let config_path = get_providers_config_path()?;
     
     let content = serde_json::to_string_pretty(providers)
@@ -124,26 +172,77 @@ fn save_providers_to_file(providers: &Vec<ProviderConfig>) -> Result<(), String>
     Ok(())
 }
 
+// 校验代理商指定的 claude 可执行文件是否存在（wsl:// 路径除外）
+fn validate_binary_path(config: &ProviderConfig) -> Result<(), String> {
+    match &config.binary_path {
+        Some(path)
+            if crate::claude_binary::parse_wsl_path(path).is_none()
+                && !PathBuf::from(path).is_file() =>
+        {
+            Err(format!("指定的 claude 可执行文件不存在: {}", path))
+        }
+        _ => Ok(()),
+    }
+}
+
+// 应用锁未解锁时返回掩码后的令牌和 API Key
+fn mask_provider_secrets(app: &AppHandle, mut config: ProviderConfig) -> ProviderConfig {
+    if app_lock::hides_secrets(app) {
+        config.auth_token = config.auth_token.as_deref().map(mask_secret);
+        config.api_key = config.api_key.as_deref().map(mask_secret);
+    }
+    config
+}
+
+// 前端在锁定时拿到的是掩码值，原样保存回来时保留原有密钥
+fn keep_masked_secrets(config: &mut ProviderConfig, existing: &ProviderConfig) {
+    if config.auth_token.as_deref().is_some_and(is_masked) {
+        config.auth_token = existing.auth_token.clone();
+    }
+    if config.api_key.as_deref().is_some_and(is_masked) {
+        config.api_key = existing.api_key.clone();
+    }
+}
+
+// 所有写入代理商列表或 Claude 配置的路径都经过这里：开启应用锁时需先解锁，
+// 且拒绝写入掩码后的密钥。命令、托盘、深链接、本地 API 和自动切换均无法绕过
+async fn guard_config_write(app: &AppHandle, config: Option<&ProviderConfig>) -> Result<(), String> {
+    app_lock::require_unlocked(app, ProtectedAction::SwitchConfig).await?;
+    if let Some(config) = config {
+        if config.auth_token.as_deref().is_some_and(is_masked)
+            || config.api_key.as_deref().is_some_and(is_masked)
+        {
+            return Err("配置中的密钥已被掩码，请解锁后重新加载再试".to_string());
+        }
+    }
+    Ok(())
+}
+
+// 保存代理商列表（经过应用锁），供命令和旧数据导入使用
+pub(crate) async fn save_providers(app: &AppHandle, providers: &[ProviderConfig]) -> Result<(), String> {
+    guard_config_write(app, None).await?;
+    save_providers_to_file(providers)
+}
+
+// 按 ID 查找代理商配置（原始值，仅供后端内部使用）
+pub(crate) fn find_provider(id: &str) -> Result<ProviderConfig, String> {
+    load_providers_from_file()?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("未找到ID为 '{}' 的配置", id))
+}
+
 // CRUD 操作 - 获取所有代理商配置
 #[command]
-pub fn get_provider_presets() -> Result<Vec<ProviderConfig>, String> {
-    let config_path = get_providers_config_path()?;
-    
-    if !config_path.exists() {
-        return Ok(vec![]);
-    }
-    
-    let content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("无法读取配置文件: {}", e))?;
-    
-    let configs: Vec<ProviderConfig> = serde_json::from_str(&content)
-        .map_err(|e| format!("配置文件格式错误: {}", e))?;
-    
-    Ok(configs)
+pub fn get_provider_presets(app: AppHandle) -> Result<Vec<ProviderConfig>, String> {
+    Ok(load_providers_from_file()?
+        .into_iter()
+        .map(|config| mask_provider_secrets(&app, config))
+        .collect())
 }
 
 #[command]
-pub fn add_provider_config(config: ProviderConfig) -> Result<String, String> {
+pub async fn add_provider_config(app: AppHandle, config: ProviderConfig) -> Result<String, String> {
     let mut providers = load_providers_from_file()?;
     
     // 检查ID是否已存在
@@ -151,52 +250,55 @@ pub fn add_provider_config(config: ProviderConfig) -> Result<String, String> {
         return Err(format!("ID '{}' 已存在，请使用不同的ID", config.id));
     }
     
+    validate_binary_path(&config)?;
+    guard_config_write(&app, Some(&config)).await?;
     providers.push(config.clone());
     save_providers_to_file(&providers)?;
+    crate::tray::refresh_menu(&app);
     
     Ok(format!("成功添加代理商配置: {}", config.name))
 }
 
 // CRUD 操作 - 更新代理商配置
 #[command]
-pub fn update_provider_config(config: ProviderConfig) -> Result<String, String> {
+pub async fn update_provider_config(app: AppHandle, mut config: ProviderConfig) -> Result<String, String> {
     let mut providers = load_providers_from_file()?;
     
     let index = providers.iter().position(|p| p.id == config.id)
         .ok_or_else(|| format!("未找到ID为 '{}' 的配置", config.id))?;
     
+    validate_binary_path(&config)?;
+    keep_masked_secrets(&mut config, &providers[index]);
     providers[index] = config.clone();
-    save_providers_to_file(&providers)?;
+    save_providers(&app, &providers).await?;
+    crate::tray::refresh_menu(&app);
     
     Ok(format!("成功更新代理商配置: {}", config.name))
 }
 
 // CRUD 操作 - 删除代理商配置
 #[command]
-pub fn delete_provider_config(id: String) -> Result<String, String> {
+pub async fn delete_provider_config(app: AppHandle, id: String) -> Result<String, String> {
     let mut providers = load_providers_from_file()?;
     
     let index = providers.iter().position(|p| p.id == id)
         .ok_or_else(|| format!("未找到ID为 '{}' 的配置", id))?;
     
     let deleted_config = providers.remove(index);
-    save_providers_to_file(&providers)?;
+    save_providers(&app, &providers).await?;
+    crate::tray::refresh_menu(&app);
     
     Ok(format!("成功删除代理商配置: {}", deleted_config.name))
 }
 
 // CRUD 操作 - 获取单个代理商配置
 #[command]
-pub fn get_provider_config(id: String) -> Result<ProviderConfig, String> {
-    let providers = load_providers_from_file()?;
-    
-    providers.into_iter()
-        .find(|p| p.id == id)
-        .ok_or_else(|| format!("未找到ID为 '{}' 的配置", id))
+pub fn get_provider_config(app: AppHandle, id: String) -> Result<ProviderConfig, String> {
+    find_provider(&id).map(|config| mask_provider_secrets(&app, config))
 }
 
-#[command]
-pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
+// 读取当前配置（原始值，仅供后端内部使用）
+pub(crate) fn read_current_config() -> Result<CurrentConfig, String> {
     let settings = load_claude_settings()?;
     
     Ok(CurrentConfig {
@@ -208,6 +310,64 @@ pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
     })
 }
 
+// 掩码敏感值，仅保留首尾少量字符便于辨认
+pub(crate) fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 12 {
+        return "*".repeat(chars.len().max(4));
+    }
+    
+    let head: String = chars[..6].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}****{}", head, tail)
+}
+
+// 是否为 mask_secret 生成的掩码值
+pub(crate) fn is_masked(secret: &str) -> bool {
+    secret.contains("****")
+}
+
+// 获取当前配置 - 令牌和 API Key 默认以掩码形式返回，避免截图或屏幕共享时泄露
+#[command]
+pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
+    let mut config = read_current_config()?;
+    
+    config.anthropic_auth_token = config.anthropic_auth_token.as_deref().map(mask_secret);
+    config.anthropic_api_key = config.anthropic_api_key.as_deref().map(mask_secret);
+    
+    Ok(config)
+}
+
+// 显示当前配置中的原始密钥 - 需要通过系统身份验证
+// field: "auth_token" 或 "api_key"
+#[command]
+pub async fn reveal_current_secret(app: AppHandle, field: String) -> Result<Option<String>, String> {
+    let config = read_current_config()?;
+    
+    let secret = match field.as_str() {
+        "auth_token" => config.anthropic_auth_token,
+        "api_key" => config.anthropic_api_key,
+        _ => return Err(format!("不支持的字段: {}", field)),
+    };
+    
+    if secret.is_none() {
+        return Ok(None);
+    }
+    
+    // 开启应用锁时由应用锁验证，否则每次都需系统验证
+    if !app_lock::require_unlocked(&app, ProtectedAction::RevealSecret).await? {
+        // 系统验证会阻塞直到用户完成操作，放到阻塞线程中执行
+        tokio::task::spawn_blocking(|| {
+            crate::os_auth::require_user_verification("Claude Workbench 需要验证您的身份以显示密钥")
+        })
+        .await
+        .map_err(|e| format!("身份验证任务失败: {}", e))??;
+    }
+    
+    info!("已通过系统验证显示当前配置的 {}", field);
+    Ok(secret)
+}
+
 // 加载 Claude settings.json 文件
 fn load_claude_settings() -> Result<ClaudeSettings, String> {
     let settings_path = get_claude_settings_path()?;
@@ -247,12 +407,21 @@ fn load_claude_settings() -> Result<ClaudeSettings, String> {
     let json_value: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("JSON 格式无效: {}", e))?;
     
-    info!("JSON 解析成功，结构: {:?}", json_value);
+    // 只记录字段名，值里有令牌
+    info!(
+        "JSON 解析成功，字段: {:?}",
+        json_value
+            .as_object()
+            .map(|object| object.keys().collect::<Vec<_>>())
+    );
     
     // 尝试解析为我们的结构体
     let settings: ClaudeSettings = serde_json::from_str(&content)
         .or_else(|e| {
             warn!("解析为 ClaudeSettings 失败: {}, 使用兼容模式", e);
+            for issue in crate::commands::settings_schema::validate_content(&content) {
+                warn!("settings.json 问题 {}: {}", issue.pointer, issue.message);
+            }
             // 如果解析失败，尝试兼容模式
             parse_compatible_settings(&content)
         })
@@ -348,8 +517,122 @@ fn save_claude_settings(settings: &ClaudeSettings) -> Result<(), String> {
     Ok(())
 }
 
+// 探测单个端点 - 只要能收到 HTTP 响应（包括 401/404）即视为可达
+async fn probe_endpoint(client: &reqwest::Client, url: &str) -> EndpointProbeResult {
+    let start = std::time::Instant::now();
+    match client.get(url).send().await {
+        Ok(_) => EndpointProbeResult {
+            url: url.to_string(),
+            healthy: true,
+            latency_ms: Some(start.elapsed().as_millis() as u64),
+            error: None,
+        },
+        Err(e) => EndpointProbeResult {
+            url: url.to_string(),
+            healthy: false,
+            latency_ms: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+// 并发探测多个端点
+async fn probe_endpoints(urls: &[String]) -> Vec<EndpointProbeResult> {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("创建 HTTP 客户端失败: {}", e);
+            return urls.iter().map(|url| EndpointProbeResult {
+                url: url.clone(),
+                healthy: false,
+                latency_ms: None,
+                error: Some(e.to_string()),
+            }).collect();
+        }
+    };
+    
+    futures::future::join_all(urls.iter().map(|url| probe_endpoint(&client, url))).await
+}
+
+// 从探测结果中选出延迟最低的健康端点
+fn select_best_endpoint(results: &[EndpointProbeResult]) -> Option<String> {
+    results
+        .iter()
+        .filter(|r| r.healthy)
+        .min_by_key(|r| r.latency_ms.unwrap_or(u64::MAX))
+        .map(|r| r.url.clone())
+}
+
+// 为配置选择端点 - 单端点直接返回主地址，多端点时选择最快的健康镜像
+async fn select_endpoint_for(config: &ProviderConfig) -> String {
+    let endpoints = config.endpoints();
+    if endpoints.len() <= 1 {
+        return config.base_url.clone();
+    }
+    
+    let results = probe_endpoints(&endpoints).await;
+    match select_best_endpoint(&results) {
+        Some(url) => {
+            info!("为 {} 选择端点: {}", config.name, url);
+            url
+        }
+        None => {
+            warn!("{} 的所有端点均不可达，使用主地址", config.name);
+            config.base_url.clone()
+        }
+    }
+}
+
+// 应用锁由写入配置的共享路径检查
 #[command]
 pub async fn switch_provider_config(app: tauri::AppHandle, config: ProviderConfig) -> Result<String, String> {
+    switch_provider(app, config).await
+}
+
+// 切换代理商；供命令、托盘、深链接、本地 API 和自动切换使用
+pub async fn switch_provider(app: tauri::AppHandle, config: ProviderConfig) -> Result<String, String> {
+    let base_url = select_endpoint_for(&config).await;
+    apply_provider_config(&app, &config, &base_url).await?;
+    
+    Ok(format!("已成功切换到 {} ({})，所有Claude会话已重启以应用新配置", config.name, config.description))
+}
+
+// 无界面命令行模式切换代理商：只写入 settings.json 并记录使用区间，不涉及运行中的应用
+pub async fn switch_provider_settings(conn: &rusqlite::Connection, config: &ProviderConfig) -> Result<String, String> {
+    let base_url = select_endpoint_for(config).await;
+    write_provider_settings(config, &base_url)?;
+    
+    if let Err(e) = record_provider_usage(conn, Some((config, &base_url))) {
+        warn!("记录代理商使用区间失败: {}", e);
+    }
+    
+    Ok(format!("已成功切换到 {} ({})", config.name, base_url))
+}
+
+// 将配置写入 settings.json（使用指定端点），并重启所有Claude会话
+async fn apply_provider_config(app: &AppHandle, config: &ProviderConfig, base_url: &str) -> Result<(), String> {
+    guard_config_write(app, Some(config)).await?;
+    write_provider_settings(config, base_url)?;
+    
+    // 记录使用区间，失败不影响切换
+    if let Err(e) = record_provider_usage_session(app, Some((config, base_url))) {
+        warn!("记录代理商使用区间失败: {}", e);
+    }
+    
+    // 托盘菜单勾选新的代理商
+    crate::tray::refresh_menu(app);
+    
+    // 终止所有运行中的Claude进程以使新配置生效（已开启时在新配置下恢复会话）
+    restart_claude_processes(app).await;
+    
+    Ok(())
+}
+
+// 将配置写入 settings.json（使用指定端点）
+fn write_provider_settings(config: &ProviderConfig, base_url: &str) -> Result<(), String> {
     // 加载当前设置
     let mut settings = load_claude_settings()?;
     
@@ -360,7 +643,7 @@ pub async fn switch_provider_config(app: tauri::AppHandle, config: ProviderConfi
     settings.env.remove("ANTHROPIC_SMALL_FAST_MODEL");
     
     // 更新 ANTHROPIC 相关配置，保留其他配置（如 CLAUDE_CODE_DISABLE_NONESSENTIAL_TRAFFIC, API_TIMEOUT_MS 等）
-    settings.env.insert("ANTHROPIC_BASE_URL".to_string(), config.base_url.clone());
+    settings.env.insert("ANTHROPIC_BASE_URL".to_string(), base_url.to_string());
     
     // 设置认证信息 - 优先使用 API Key，其次是 auth_token
     if let Some(api_key) = &config.api_key {
@@ -378,16 +661,48 @@ pub async fn switch_provider_config(app: tauri::AppHandle, config: ProviderConfi
     }
     
     // 保存设置
-    save_claude_settings(&settings)?;
+    save_claude_settings(&settings)
+}
+
+// 检测当前代理商的所有端点状态
+#[command]
+pub async fn probe_provider_endpoints(config: ProviderConfig) -> Result<Vec<EndpointProbeResult>, String> {
+    Ok(probe_endpoints(&config.endpoints()).await)
+}
+
+// 当前镜像变慢或不可用时，切换到其余端点中最快的健康镜像
+#[command]
+pub async fn rotate_provider_endpoint(app: tauri::AppHandle) -> Result<String, String> {
+    let configs = load_providers_from_file()?;
+    let current_id = detect_current_provider(&configs)
+        .ok_or_else(|| "当前没有生效的代理商配置".to_string())?;
+    let config = configs.iter()
+        .find(|c| c.id == current_id)
+        .ok_or_else(|| "当前配置不是已保存的代理商，无法轮换端点".to_string())?;
+    
+    let current_url = read_current_config()?.anthropic_base_url.unwrap_or_default();
+    let candidates: Vec<String> = config.endpoints()
+        .into_iter()
+        .filter(|url| *url != current_url)
+        .collect();
+    
+    if candidates.is_empty() {
+        return Err(format!("{} 没有配置备用端点", config.name));
+    }
     
-    // 终止所有运行中的Claude进程以使新配置生效
-    terminate_claude_processes(&app).await;
+    let results = probe_endpoints(&candidates).await;
+    let next_url = select_best_endpoint(&results)
+        .ok_or_else(|| format!("{} 的备用端点均不可达", config.name))?;
     
-    Ok(format!("已成功切换到 {} ({})，所有Claude会话已重启以应用新配置", config.name, config.description))
+    apply_provider_config(&app, config, &next_url).await?;
+    
+    Ok(format!("已将 {} 的端点从 {} 切换到 {}", config.name, current_url, next_url))
 }
 
 #[command]
 pub async fn clear_provider_config(app: tauri::AppHandle) -> Result<String, String> {
+    guard_config_write(&app, None).await?;
+    
     // 加载当前设置
     let mut settings = load_claude_settings()?;
     
@@ -401,16 +716,95 @@ pub async fn clear_provider_config(app: tauri::AppHandle) -> Result<String, Stri
     // 保存设置
     save_claude_settings(&settings)?;
     
-    // 终止所有运行中的Claude进程以使清理生效
-    terminate_claude_processes(&app).await;
+    // 结束当前的使用区间
+    if let Err(e) = record_provider_usage_session(&app, None) {
+        warn!("记录代理商使用区间失败: {}", e);
+    }
+    
+    crate::tray::refresh_menu(&app);
+    
+    // 终止所有运行中的Claude进程以使清理生效（已开启时在新配置下恢复会话）
+    restart_claude_processes(&app).await;
     
     Ok("已清理所有 ANTHROPIC 配置，所有Claude会话已重启".to_string())
 }
 
+// 记录代理商使用区间：结束所有未关闭的区间，如提供了新配置（及实际使用的端点）则开启新的区间
+fn record_provider_usage_session(app: &AppHandle, active: Option<(&ProviderConfig, &str)>) -> Result<(), String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_provider_usage(&conn, active)
+}
+
+fn record_provider_usage(conn: &rusqlite::Connection, active: Option<(&ProviderConfig, &str)>) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    
+    conn.execute(
+        "UPDATE provider_usage_sessions SET ended_at = ?1 WHERE ended_at IS NULL",
+        params![now],
+    ).map_err(|e| format!("结束使用区间失败: {}", e))?;
+    
+    if let Some((config, base_url)) = active {
+        conn.execute(
+            "INSERT INTO provider_usage_sessions (provider_id, provider_name, base_url, started_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![config.id, config.name, base_url, now],
+        ).map_err(|e| format!("创建使用区间失败: {}", e))?;
+        info!("开始记录代理商使用区间: {} ({})", config.name, base_url);
+    }
+    
+    Ok(())
+}
+
+// 获取代理商使用时间线 - 返回与 [start_time, end_time] 有交集的所有使用区间
+#[command]
+pub fn get_provider_usage_timeline(
+    db: State<'_, AgentDb>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<Vec<ProviderUsageSession>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    usage_sessions_between(&conn, start_time, end_time)
+}
+
+// 与 [start_time, end_time] 有交集的使用区间，按开始时间排序
+pub(crate) fn usage_sessions_between(
+    conn: &rusqlite::Connection,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<Vec<ProviderUsageSession>, String> {
+    let now = chrono::Utc::now().timestamp();
+    
+    let mut stmt = conn.prepare(
+        "SELECT id, provider_id, provider_name, base_url, started_at, ended_at
+         FROM provider_usage_sessions
+         WHERE (?1 IS NULL OR COALESCE(ended_at, ?3) >= ?1)
+           AND (?2 IS NULL OR started_at <= ?2)
+         ORDER BY started_at ASC"
+    ).map_err(|e| e.to_string())?;
+    
+    let sessions = stmt
+        .query_map(params![start_time, end_time, now], |row| {
+            Ok(ProviderUsageSession {
+                id: row.get(0)?,
+                provider_id: row.get(1)?,
+                provider_name: row.get(2)?,
+                base_url: row.get(3)?,
+                started_at: row.get(4)?,
+                ended_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取使用时间线失败: {}", e))?;
+    
+    Ok(sessions)
+}
+
 // 检测当前使用的代理商配置 - 参考 switch-script 的实现
 fn detect_current_provider(configs: &[ProviderConfig]) -> Option<String> {
     // 获取当前配置
-    let current_config = match get_current_provider_config() {
+    let current_config = match read_current_config() {
         Ok(config) => config,
         Err(_) => return None,
     };
@@ -422,8 +816,11 @@ fn detect_current_provider(configs: &[ProviderConfig]) -> Option<String> {
     for provider_config in configs {
         let mut matches = true;
         
-        // 比较 ANTHROPIC_BASE_URL
-        if current_config.anthropic_base_url.as_deref() != Some(&provider_config.base_url) {
+        // 比较 ANTHROPIC_BASE_URL（主地址或任一镜像）
+        let base_url_matches = current_config.anthropic_base_url.as_deref()
+            .map(|url| provider_config.endpoints().iter().any(|e| e == url))
+            .unwrap_or(false);
+        if !base_url_matches {
             matches = false;
         }
         
@@ -470,6 +867,23 @@ fn detect_current_provider(configs: &[ProviderConfig]) -> Option<String> {
     }
 }
 
+// 当前代理商配置的 claude 可执行文件与默认参数（未配置时返回 None）
+pub fn current_provider_launch_overrides() -> Option<(Option<String>, Vec<String>)> {
+    let configs = load_providers_from_file().ok()?;
+    let current_id = detect_current_provider(&configs)?;
+    let config = configs.into_iter().find(|c| c.id == current_id)?;
+    let default_args: Vec<String> = config
+        .default_args
+        .iter()
+        .map(|arg| arg.trim().to_string())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    if config.binary_path.is_none() && default_args.is_empty() {
+        return None;
+    }
+    Some((config.binary_path, default_args))
+}
+
 // 新增命令：获取当前使用的代理商ID
 #[command]
 pub fn get_current_provider_id() -> Result<Option<String>, String> {
@@ -491,59 +905,208 @@ pub fn test_provider_connection(base_url: String) -> Result<String, String> {
     Ok(format!("连接测试完成：{}", test_url))
 }
 
+// 切换配置时被终止的Claude会话，用于在新配置下通过 --resume 恢复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoppedSession {
+    pub session_id: String,
+    pub project_path: String,
+    pub model: String,
+    // 启动时指定的环境变量覆盖，恢复时沿用
+    pub env: HashMap<String, String>,
+}
+
+// 会话恢复结果事件（provider-session-resumed）
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionResumeEvent {
+    pub session_id: String,
+    pub project_path: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+// app_settings 中控制切换后是否自动恢复会话的键
+const RESUME_ON_SWITCH_SETTING_KEY: &str = "provider_switch_resume_sessions";
+
+// 恢复会话时发送给 Claude 的提示
+const RESUME_AFTER_SWITCH_PROMPT: &str =
+    "The API provider configuration was changed. Continue the task from where it left off.";
+
+// 是否在切换代理商后自动恢复被终止的会话（默认关闭）
+#[command]
+pub fn get_resume_sessions_on_switch(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(read_resume_on_switch(&conn))
+}
+
+#[command]
+pub fn set_resume_sessions_on_switch(db: State<'_, AgentDb>, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![RESUME_ON_SWITCH_SETTING_KEY, enabled.to_string()],
+    ).map_err(|e| format!("保存会话恢复设置失败: {}", e))?;
+    Ok(())
+}
+
+fn read_resume_on_switch(conn: &rusqlite::Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![RESUME_ON_SWITCH_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|value| value == "true")
+    .unwrap_or(false)
+}
+
 /// 终止所有运行中的Claude进程以使新配置文件生效
-async fn terminate_claude_processes(app: &AppHandle) {
+/// 先发送中断信号，等待宽限期让 Claude CLI 写完会话文件，超时后再强制终止；
+/// 如已开启恢复，则在新配置下逐个以 --resume 重新启动，每个会话各自独立运行
+async fn restart_claude_processes(app: &AppHandle) {
+    let stopped = terminate_claude_processes(app).await;
+    
+    let resume_enabled = {
+        let db = app.state::<AgentDb>();
+        let enabled = db.0.lock().map(|conn| read_resume_on_switch(&conn)).unwrap_or(false);
+        enabled
+    };
+    if !resume_enabled || stopped.is_empty() {
+        return;
+    }
+    
+    info!("正在新配置下恢复 {} 个Claude会话", stopped.len());
+    for session in stopped {
+        let result = crate::commands::claude::resume_stopped_session(
+            app,
+            &session.session_id,
+            &session.project_path,
+            &session.model,
+            RESUME_AFTER_SWITCH_PROMPT,
+            session.env.clone(),
+        ).await;
+        
+        match &result {
+            Ok(()) => info!("已恢复Claude会话 {}", session.session_id),
+            Err(e) => warn!("恢复Claude会话 {} 失败: {}", session.session_id, e),
+        }
+        
+        let event = SessionResumeEvent {
+            session_id: session.session_id.clone(),
+            project_path: session.project_path.clone(),
+            success: result.is_ok(),
+            error: result.err(),
+        };
+        let _ = app.emit("provider-session-resumed", &event);
+    }
+}
+
+// 终止所有Claude会话，返回被终止的会话信息
+async fn terminate_claude_processes(app: &AppHandle) -> Vec<StoppedSession> {
     info!("正在终止所有Claude进程以应用新的代理商配置...");
     
     // 获取进程注册表
     let registry = app.state::<ProcessRegistryState>();
     
-    // 获取所有活动的Claude会话
-    match registry.0.get_running_claude_sessions() {
-        Ok(sessions) => {
-            info!("找到 {} 个活动的Claude会话", sessions.len());
+    // 获取所有活动的Claude会话（包括已接管的外部Claude进程）
+    let running = registry.0.get_running_claude_sessions().and_then(|mut sessions| {
+        sessions.extend(registry.0.get_external_claude_processes()?);
+        Ok(sessions)
+    });
+    match running {
+        Ok(mut sessions) => {
+            // 通过环境变量覆盖指定了独立代理商的会话不受全局配置影响，保持运行
+            sessions.retain(|session| {
+                let independent = registry.0.has_provider_override(session.run_id);
+                if independent {
+                    info!("会话 {} 使用独立的代理商环境变量，跳过终止", session.run_id);
+                }
+                !independent
+            });
+            // 终止后注册表会清除覆盖信息，需提前记录
+            let mut envs: HashMap<i64, HashMap<String, String>> = sessions
+                .iter()
+                .map(|session| (session.run_id, registry.0.get_env_overrides(session.run_id)))
+                .collect();
+            
+            info!(
+                "找到 {} 个活动的Claude会话，宽限期 {}ms",
+                sessions.len(),
+                registry.0.shutdown_grace_period_ms()
+            );
             
-            for session in sessions {
-                let session_id_str = match &session.process_type {
-                    crate::process::registry::ProcessType::ClaudeSession { session_id } => session_id.as_str(),
-                    _ => "unknown",
-                };
-                
-                info!("正在终止Claude会话: session_id={}, run_id={}, PID={}", 
-                    session_id_str,
-                    session.run_id, 
-                    session.pid
-                );
-                
-                // 尝试优雅地终止进程
-                match registry.0.kill_process(session.run_id).await {
-                    Ok(success) => {
-                        if success {
+            // 并发关闭，避免多个会话的宽限期串行累加
+            let shutdowns = sessions.iter().map(|session| {
+                let registry = registry.0.clone();
+                async move {
+                    let session_id_str = match &session.process_type {
+                        crate::process::registry::ProcessType::ClaudeSession { session_id } => session_id.as_str(),
+                        _ => "unknown",
+                    };
+                    
+                    info!("正在终止Claude会话: session_id={}, run_id={}, PID={}", 
+                        session_id_str,
+                        session.run_id, 
+                        session.pid
+                    );
+                    
+                    // 优雅关闭（中断 -> 等待 -> 强制终止）
+                    match registry.shutdown_process(session.run_id).await {
+                        Ok(true) => {
                             info!("成功终止Claude会话 {}", session.run_id);
-                        } else {
+                        }
+                        Ok(false) => {
                             warn!("终止Claude会话 {} 返回false", session.run_id);
                             
                             // 尝试强制终止
-                            if let Err(e) = registry.0.kill_process_by_pid(session.run_id, session.pid as u32) {
+                            if let Err(e) = registry.kill_process_by_pid(session.run_id, session.pid) {
                                 warn!("强制终止进程失败: {}", e);
                             }
                         }
-                    }
-                    Err(e) => {
-                        warn!("终止Claude会话 {} 失败: {}", session.run_id, e);
-                        
-                        // 尝试强制终止
-                        if let Err(e2) = registry.0.kill_process_by_pid(session.run_id, session.pid as u32) {
-                            warn!("强制终止进程也失败: {}", e2);
+                        Err(e) => {
+                            warn!("终止Claude会话 {} 失败: {}", session.run_id, e);
+                            
+                            // 尝试强制终止
+                            if let Err(e2) = registry.kill_process_by_pid(session.run_id, session.pid) {
+                                warn!("强制终止进程也失败: {}", e2);
+                            }
                         }
                     }
                 }
-            }
+            });
+            
+            futures::future::join_all(shutdowns).await;
+            
+            info!("Claude进程终止操作完成");
+            sessions
+                .into_iter()
+                .filter_map(|session| match session.process_type {
+                    crate::process::registry::ProcessType::ClaudeSession { session_id } => {
+                        Some(StoppedSession {
+                            session_id,
+                            project_path: session.project_path,
+                            model: session.model,
+                            env: envs.remove(&session.run_id).unwrap_or_default(),
+                        })
+                    }
+                    _ => None,
+                })
+                .collect()
         }
         Err(e) => {
             warn!("获取Claude会话列表失败: {}", e);
+            Vec::new()
         }
     }
-    
-    info!("Claude进程终止操作完成");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_secret() {
+        assert_eq!(mask_secret("sk-ant-REDACTED"), "sk-ant****mnop");
+        assert_eq!(mask_secret("short"), "*****");
+        assert_eq!(mask_secret("ab"), "****");
+        assert!(!mask_secret("sk-1234567890abcdef").contains("7890ab"));
+    }
+}
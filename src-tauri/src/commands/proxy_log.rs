@@ -0,0 +1,434 @@
+//! Requests that went through the local proxy, with the token counts read
+//! from their responses
+//!
+//! This is usage measured on this machine, so it stays accurate for relay
+//! stations that hide or delay their own logs. Tools can attribute requests
+//! to a project with the `X-Workbench-Project` header.
+use crate::commands::agents::AgentDb;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::commands::currency::Converted;
+
+/// Header tools set to attribute a request to a project; not forwarded
+pub const PROJECT_HEADER: &str = "x-workbench-project";
+
+/// Non-streamed response bodies larger than this are not scanned for usage
+const MAX_SCANNED_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Logged requests older than this are pruned when the proxy starts
+const RETENTION_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Take the counts present in an Anthropic `usage` object; streamed
+    /// counts are cumulative, so the largest value seen wins
+    fn merge(&mut self, usage: &Value) {
+        let fields = [
+            ("input_tokens", &mut self.input_tokens),
+            ("output_tokens", &mut self.output_tokens),
+            (
+                "cache_creation_input_tokens",
+                &mut self.cache_creation_tokens,
+            ),
+            ("cache_read_input_tokens", &mut self.cache_read_tokens),
+        ];
+        for (key, count) in fields {
+            if let Some(value) = usage.get(key).and_then(Value::as_u64) {
+                *count = (*count).max(value);
+            }
+        }
+    }
+}
+
+/// Picks the model and token counts out of a response as it streams by,
+/// from `message_start`/`message_delta` events or a plain JSON body
+pub struct UsageScanner {
+    streaming: bool,
+    buffer: Vec<u8>,
+    usage: TokenUsage,
+    model: Option<String>,
+}
+
+impl UsageScanner {
+    pub fn new(content_type: &str) -> Self {
+        Self {
+            streaming: content_type.starts_with("text/event-stream"),
+            buffer: Vec::new(),
+            usage: TokenUsage::default(),
+            model: None,
+        }
+    }
+
+    pub fn observe(&mut self, chunk: &[u8]) {
+        if !self.streaming {
+            if self.buffer.len() + chunk.len() <= MAX_SCANNED_BODY_BYTES {
+                self.buffer.extend_from_slice(chunk);
+            }
+            return;
+        }
+        self.buffer.extend_from_slice(chunk);
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.scan_event_line(&String::from_utf8_lossy(&line));
+        }
+    }
+
+    fn scan_event_line(&mut self, line: &str) {
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            return;
+        };
+        let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+            return;
+        };
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => self.scan_message(&event["message"]),
+            Some("message_delta") => self.usage.merge(&event["usage"]),
+            _ => {}
+        }
+    }
+
+    fn scan_message(&mut self, message: &Value) {
+        self.usage.merge(&message["usage"]);
+        if let Some(model) = message.get("model").and_then(Value::as_str) {
+            self.model = Some(model.to_string());
+        }
+    }
+
+    /// Usage and model once the response ended
+    pub fn finish(mut self) -> (TokenUsage, Option<String>) {
+        if self.streaming {
+            let rest = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            self.scan_event_line(&rest);
+        } else if let Ok(body) = serde_json::from_slice::<Value>(&self.buffer) {
+            self.scan_message(&body);
+        }
+        (self.usage, self.model)
+    }
+}
+
+/// Model named in a request body
+pub fn request_model(body: &[u8]) -> Option<String> {
+    serde_json::from_slice::<Value>(body)
+        .ok()?
+        .get("model")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyRequestLog {
+    pub id: i64,
+    /// Unix seconds
+    pub created_at: i64,
+    pub method: String,
+    pub path: String,
+    pub model: Option<String>,
+    pub project: Option<String>,
+    /// Base URL the request went to; `None` when there was no upstream
+    pub upstream: Option<String>,
+    pub status: u16,
+    pub latency_ms: u64,
+    pub usage: TokenUsage,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProxyUsageGroup {
+    /// Model or project; "unknown" when the request did not say
+    pub key: String,
+    pub requests: u64,
+    pub usage: TokenUsage,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProxyUsageSummary {
+    pub requests: u64,
+    /// Requests answered with a status of 400 or above
+    pub failed_requests: u64,
+    pub avg_latency_ms: f64,
+    pub usage: TokenUsage,
+    pub cost_usd: f64,
+    pub by_model: Vec<ProxyUsageGroup>,
+    pub by_project: Vec<ProxyUsageGroup>,
+}
+
+/// Create the `proxy_requests` table
+pub fn create_proxy_requests_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at INTEGER NOT NULL,
+            method TEXT NOT NULL,
+            path TEXT NOT NULL,
+            model TEXT,
+            project TEXT,
+            upstream TEXT,
+            status INTEGER NOT NULL,
+            latency_ms INTEGER NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_proxy_requests_created_at ON proxy_requests(created_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Store a request; `id` and `cost_usd` are filled in here
+pub fn insert_request(conn: &Connection, entry: &ProxyRequestLog) -> Result<(), String> {
+    let cost = crate::commands::usage::estimate_cost(
+        entry.model.as_deref().unwrap_or_default(),
+        entry.usage.input_tokens,
+        entry.usage.output_tokens,
+        entry.usage.cache_creation_tokens,
+        entry.usage.cache_read_tokens,
+    );
+    conn.execute(
+        "INSERT INTO proxy_requests (created_at, method, path, model, project, upstream, status,
+            latency_ms, input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost_usd)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            entry.created_at,
+            entry.method,
+            entry.path,
+            entry.model,
+            entry.project,
+            entry.upstream,
+            entry.status,
+            entry.latency_ms as i64,
+            entry.usage.input_tokens as i64,
+            entry.usage.output_tokens as i64,
+            entry.usage.cache_creation_tokens as i64,
+            entry.usage.cache_read_tokens as i64,
+            cost,
+        ],
+    )
+    .map_err(|e| format!("Failed to log proxy request: {}", e))?;
+    Ok(())
+}
+
+pub fn prune_old_requests(conn: &Connection) -> Result<usize, String> {
+    let cutoff = chrono::Utc::now().timestamp() - RETENTION_DAYS * 24 * 3600;
+    conn.execute(
+        "DELETE FROM proxy_requests WHERE created_at < ?1",
+        params![cutoff],
+    )
+    .map_err(|e| format!("Failed to prune proxy requests: {}", e))
+}
+
+fn row_to_log(row: &rusqlite::Row) -> rusqlite::Result<ProxyRequestLog> {
+    Ok(ProxyRequestLog {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        method: row.get(2)?,
+        path: row.get(3)?,
+        model: row.get(4)?,
+        project: row.get(5)?,
+        upstream: row.get(6)?,
+        status: row.get(7)?,
+        latency_ms: row.get::<_, i64>(8)? as u64,
+        usage: TokenUsage {
+            input_tokens: row.get::<_, i64>(9)? as u64,
+            output_tokens: row.get::<_, i64>(10)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(11)? as u64,
+            cache_read_tokens: row.get::<_, i64>(12)? as u64,
+        },
+        cost_usd: row.get(13)?,
+    })
+}
+
+fn add_to_group(groups: &mut Vec<ProxyUsageGroup>, key: Option<&str>, entry: &ProxyRequestLog) {
+    let key = key.unwrap_or("unknown");
+    let index = match groups.iter().position(|group| group.key == key) {
+        Some(index) => index,
+        None => {
+            groups.push(ProxyUsageGroup {
+                key: key.to_string(),
+                ..Default::default()
+            });
+            groups.len() - 1
+        }
+    };
+    let group = &mut groups[index];
+    group.requests += 1;
+    group.usage.input_tokens += entry.usage.input_tokens;
+    group.usage.output_tokens += entry.usage.output_tokens;
+    group.usage.cache_creation_tokens += entry.usage.cache_creation_tokens;
+    group.usage.cache_read_tokens += entry.usage.cache_read_tokens;
+    group.cost_usd += entry.cost_usd;
+}
+
+fn summarize(entries: &[ProxyRequestLog]) -> ProxyUsageSummary {
+    let mut summary = ProxyUsageSummary::default();
+    let mut total_latency = 0u64;
+    for entry in entries {
+        summary.requests += 1;
+        if entry.status >= 400 {
+            summary.failed_requests += 1;
+        }
+        total_latency += entry.latency_ms;
+        summary.usage.input_tokens += entry.usage.input_tokens;
+        summary.usage.output_tokens += entry.usage.output_tokens;
+        summary.usage.cache_creation_tokens += entry.usage.cache_creation_tokens;
+        summary.usage.cache_read_tokens += entry.usage.cache_read_tokens;
+        summary.cost_usd += entry.cost_usd;
+        add_to_group(&mut summary.by_model, entry.model.as_deref(), entry);
+        add_to_group(&mut summary.by_project, entry.project.as_deref(), entry);
+    }
+    if summary.requests > 0 {
+        summary.avg_latency_ms = total_latency as f64 / summary.requests as f64;
+    }
+    for groups in [&mut summary.by_model, &mut summary.by_project] {
+        groups.sort_by(|a, b| {
+            b.cost_usd
+                .total_cmp(&a.cost_usd)
+                .then(b.requests.cmp(&a.requests))
+        });
+    }
+    summary
+}
+
+const SELECT_COLUMNS: &str = "SELECT id, created_at, method, path, model, project, upstream, status, latency_ms,
+    input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost_usd FROM proxy_requests";
+
+/// Logged requests, newest first; page with the smallest `id` seen
+#[tauri::command]
+pub async fn list_proxy_requests(
+    db: State<'_, AgentDb>,
+    limit: Option<u32>,
+    before_id: Option<i64>,
+) -> Result<Converted<Vec<ProxyRequestLog>>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE id < ?1 ORDER BY id DESC LIMIT ?2",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(
+            params![
+                before_id.unwrap_or(i64::MAX),
+                limit.unwrap_or(100).min(1000)
+            ],
+            row_to_log,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(Converted(entries))
+}
+
+/// Totals of the last `days` days (default 30), by model and by project
+#[tauri::command]
+pub async fn get_proxy_usage_summary(
+    db: State<'_, AgentDb>,
+    days: Option<u32>,
+) -> Result<Converted<ProxyUsageSummary>, String> {
+    let since = chrono::Utc::now().timestamp() - i64::from(days.unwrap_or(30)) * 24 * 3600;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!("{} WHERE created_at >= ?1", SELECT_COLUMNS))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(params![since], row_to_log)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(Converted(summarize(&entries)))
+}
+
+#[tauri::command]
+pub async fn clear_proxy_requests(db: State<'_, AgentDb>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM proxy_requests", [])
+        .map_err(|e| format!("Failed to clear proxy requests: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_scanner_and_summary() {
+        let mut scanner = UsageScanner::new("text/event-stream; charset=utf-8");
+        scanner.observe(b"event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4-5\",\"usage\":{\"input_tokens\":120,\"cache_read_input_tokens\":4000,\"output_tokens\":1}}}\n\n");
+        scanner.observe(
+            b"event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"output_",
+        );
+        scanner.observe(b"tokens\":57}}\n\n");
+        let (usage, model) = scanner.finish();
+        assert_eq!(model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(
+            usage,
+            TokenUsage {
+                input_tokens: 120,
+                output_tokens: 57,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 4000,
+            }
+        );
+
+        let mut scanner = UsageScanner::new("application/json");
+        scanner.observe(
+            br#"{"model":"claude-haiku-4-5","usage":{"input_tokens":10,"output_tokens":3}}"#,
+        );
+        assert_eq!(scanner.finish().0.output_tokens, 3);
+        assert_eq!(
+            request_model(br#"{"model":"claude-opus-4-1","messages":[]}"#).as_deref(),
+            Some("claude-opus-4-1")
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_proxy_requests_table(&conn).unwrap();
+        let mut entry = ProxyRequestLog {
+            id: 0,
+            created_at: chrono::Utc::now().timestamp(),
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            model: model.clone(),
+            project: Some("/work/app".to_string()),
+            upstream: Some("https://relay.example.com".to_string()),
+            status: 200,
+            latency_ms: 800,
+            usage,
+            cost_usd: 0.0,
+        };
+        insert_request(&conn, &entry).unwrap();
+        entry.status = 502;
+        entry.project = None;
+        insert_request(&conn, &entry).unwrap();
+
+        let mut stmt = conn.prepare(SELECT_COLUMNS).unwrap();
+        let entries: Vec<ProxyRequestLog> = stmt
+            .query_map([], row_to_log)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let summary = summarize(&entries);
+        assert_eq!(summary.requests, 2);
+        assert_eq!(summary.failed_requests, 1);
+        assert_eq!(summary.usage.output_tokens, 114);
+        assert!(summary.cost_usd > 0.0);
+        assert_eq!(summary.by_model.len(), 1);
+        assert_eq!(summary.by_project.len(), 2);
+    }
+}
@@ -0,0 +1,611 @@
+//! Fuzzy search behind the quick switcher (Cmd+K)
+//!
+//! One call ranks relay stations, providers, projects, sessions and recently
+//! run palette commands against the typed query. Items the palette reported
+//! as picked through `record_quick_search_pick` rank higher the more often
+//! and the more recently they were picked; with an empty query these recent
+//! picks are all that is returned.
+use crate::commands::agents::AgentDb;
+use crate::commands::projects;
+use crate::commands::provider;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::relay_stations::RelayStationManager;
+
+/// Only the most recently active sessions are searched
+const SESSION_CANDIDATES: u32 = 1000;
+
+/// Picks kept for ranking and the recent list
+const MAX_PICKS: i64 = 200;
+
+/// Longest title of a session result, cut from its prompt
+const SESSION_TITLE_MAX_CHARS: usize = 80;
+
+const DAY_SECONDS: i64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickSearchKind {
+    /// A palette command the frontend ran, known from its picks only
+    Command,
+    Project,
+    Session,
+    Station,
+    Provider,
+}
+
+impl QuickSearchKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Project => "project",
+            Self::Session => "session",
+            Self::Station => "station",
+            Self::Provider => "provider",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "command" => Some(Self::Command),
+            "project" => Some(Self::Project),
+            "session" => Some(Self::Session),
+            "station" => Some(Self::Station),
+            "provider" => Some(Self::Provider),
+            _ => None,
+        }
+    }
+}
+
+/// Something the quick switcher can jump to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuickSearchItem {
+    pub kind: QuickSearchKind,
+    /// Station, provider, project or session id, or the frontend's command id
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickSearchResult {
+    #[serde(flatten)]
+    pub item: QuickSearchItem,
+    /// Higher is better
+    pub score: i64,
+    /// Character positions in `title` that matched the query, for highlighting
+    pub title_matches: Vec<usize>,
+}
+
+/// A palette pick as recorded
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickSearchPick {
+    pub item: QuickSearchItem,
+    pub use_count: i64,
+    pub used_at: i64,
+}
+
+/// Create the `quick_search_picks` table
+pub fn create_quick_search_picks_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quick_search_picks (
+            kind TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            subtitle TEXT,
+            use_count INTEGER NOT NULL DEFAULT 1,
+            used_at INTEGER NOT NULL,
+            PRIMARY KEY (kind, item_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Count a pick of `item` at `now` and forget the oldest picks beyond the limit
+pub fn record_pick(conn: &Connection, item: &QuickSearchItem, now: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO quick_search_picks (kind, item_id, title, subtitle, used_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(kind, item_id) DO UPDATE SET
+            title = excluded.title,
+            subtitle = excluded.subtitle,
+            use_count = use_count + 1,
+            used_at = excluded.used_at",
+        params![item.kind.as_str(), item.id, item.title, item.subtitle, now],
+    )
+    .map_err(|e| format!("Failed to record quick search pick: {}", e))?;
+    conn.execute(
+        "DELETE FROM quick_search_picks WHERE rowid NOT IN (
+            SELECT rowid FROM quick_search_picks ORDER BY used_at DESC LIMIT ?1
+         )",
+        params![MAX_PICKS],
+    )
+    .map_err(|e| format!("Failed to prune quick search picks: {}", e))?;
+    Ok(())
+}
+
+/// Recorded picks, most recent first
+pub fn load_picks(conn: &Connection) -> Result<Vec<QuickSearchPick>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT kind, item_id, title, subtitle, use_count, used_at FROM quick_search_picks
+             ORDER BY used_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read quick search picks: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(kind, id, title, subtitle, use_count, used_at)| {
+            Some(QuickSearchPick {
+                item: QuickSearchItem {
+                    kind: QuickSearchKind::parse(&kind)?,
+                    id,
+                    title,
+                    subtitle,
+                },
+                use_count,
+                used_at,
+            })
+        })
+        .collect())
+}
+
+/// Score `text` against one lowercase query term, with the matched character
+/// positions
+///
+/// Every character of the term has to appear in order. Matches at the start
+/// of the text or of a word and runs of adjacent characters score higher,
+/// gaps lower. `None` when the term does not match.
+pub fn fuzzy_score(term: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let term: Vec<char> = term.chars().collect();
+    let first = *term.first()?;
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let word_start = |i: usize| {
+        i == 0
+            || !chars[i - 1].is_alphanumeric()
+            || (chars[i - 1].is_lowercase() && chars[i].is_uppercase())
+    };
+
+    // Greedy from every occurrence of the first character, keeping the best
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == first) {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for c in &term[1..] {
+            match (next..lower.len()).find(|&i| lower[i] == *c) {
+                Some(i) => {
+                    positions.push(i);
+                    next = i + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < term.len() {
+            // Later starts leave even fewer characters to match
+            break;
+        }
+
+        let mut score = -(start.min(5) as i64);
+        for (n, &i) in positions.iter().enumerate() {
+            score += 1;
+            if i == 0 {
+                score += 8;
+            } else if word_start(i) {
+                score += 6;
+            }
+            if n > 0 {
+                let gap = i - positions[n - 1] - 1;
+                if gap == 0 {
+                    score += 6;
+                } else {
+                    score -= gap.min(3) as i64;
+                }
+            }
+        }
+        if !matches!(&best, Some((best_score, _)) if *best_score >= score) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+/// Score an item against all query terms; every term has to match its title
+/// or subtitle, and subtitle matches count half
+fn score_item(terms: &[String], item: &QuickSearchItem) -> Option<(i64, Vec<usize>)> {
+    let mut total = 0;
+    let mut title_matches = Vec::new();
+    for term in terms {
+        let title = fuzzy_score(term, &item.title);
+        let subtitle = item
+            .subtitle
+            .as_deref()
+            .and_then(|subtitle| fuzzy_score(term, subtitle))
+            .map(|(score, _)| score / 2);
+        match (title, subtitle) {
+            (Some((score, positions)), subtitle) if !matches!(subtitle, Some(s) if s > score) => {
+                total += score;
+                title_matches.extend(positions);
+            }
+            (_, Some(score)) => total += score,
+            (None, None) => return None,
+        }
+    }
+    if item.title.to_lowercase().contains(&terms.join(" ")) {
+        total += 10;
+    }
+    title_matches.sort_unstable();
+    title_matches.dedup();
+    Some((total, title_matches))
+}
+
+/// Bonus for items picked often or lately
+fn pick_boost(pick: &QuickSearchPick, now: i64) -> i64 {
+    let age = now - pick.used_at;
+    let recency = if age < DAY_SECONDS {
+        8
+    } else if age < 7 * DAY_SECONDS {
+        4
+    } else {
+        0
+    };
+    pick.use_count.min(10) * 2 + recency
+}
+
+/// Rank `candidates` and the recorded command picks against `query`
+///
+/// An empty query lists the picked items, most recent first.
+pub fn rank(
+    query: &str,
+    candidates: Vec<QuickSearchItem>,
+    picks: &[QuickSearchPick],
+    now: i64,
+    limit: usize,
+) -> Vec<QuickSearchResult> {
+    let picked: HashMap<(QuickSearchKind, &str), &QuickSearchPick> = picks
+        .iter()
+        .map(|pick| ((pick.item.kind, pick.item.id.as_str()), pick))
+        .collect();
+    let commands = picks
+        .iter()
+        .filter(|pick| pick.item.kind == QuickSearchKind::Command)
+        .map(|pick| pick.item.clone());
+    let items = candidates.into_iter().chain(commands);
+
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        let mut recent: Vec<(i64, QuickSearchItem)> = items
+            .filter_map(|item| {
+                let pick = picked.get(&(item.kind, item.id.as_str()))?;
+                Some((pick.used_at, item))
+            })
+            .collect();
+        recent.sort_by(|a, b| b.0.cmp(&a.0));
+        return recent
+            .into_iter()
+            .take(limit)
+            .map(|(_, item)| QuickSearchResult {
+                item,
+                score: 0,
+                title_matches: Vec::new(),
+            })
+            .collect();
+    }
+
+    let mut results: Vec<QuickSearchResult> = items
+        .filter_map(|item| {
+            let (score, title_matches) = score_item(&terms, &item)?;
+            let boost = picked
+                .get(&(item.kind, item.id.as_str()))
+                .map_or(0, |pick| pick_boost(pick, now));
+            Some(QuickSearchResult {
+                item,
+                score: score + boost,
+                title_matches,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.item.kind.cmp(&b.item.kind))
+            .then_with(|| a.item.title.len().cmp(&b.item.title.len()))
+            .then_with(|| a.item.title.cmp(&b.item.title))
+    });
+    results.truncate(limit);
+    results
+}
+
+/// Single-line title of a session: its generated summary, else its first prompt
+fn session_title(
+    summary: Option<String>,
+    first_prompt: Option<String>,
+    session_id: &str,
+) -> String {
+    let text = summary
+        .or(first_prompt)
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| session_id.to_string());
+    if text.chars().count() > SESSION_TITLE_MAX_CHARS {
+        let cut: String = text.chars().take(SESSION_TITLE_MAX_CHARS - 1).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        text
+    }
+}
+
+fn session_candidates(conn: &Connection) -> Result<Vec<QuickSearchItem>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_id, project_path, first_prompt, summary FROM session_index
+             ORDER BY modified_at DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let sessions = stmt
+        .query_map(params![SESSION_CANDIDATES], |row| {
+            let session_id: String = row.get(0)?;
+            Ok(QuickSearchItem {
+                kind: QuickSearchKind::Session,
+                title: session_title(row.get(3)?, row.get(2)?, &session_id),
+                id: session_id,
+                subtitle: Some(row.get(1)?),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session index: {}", e))?;
+    Ok(sessions)
+}
+
+fn project_candidates(conn: &Connection) -> Result<Vec<QuickSearchItem>, String> {
+    let claude_dir = projects::claude_dir()?;
+    let entries = projects::collect_projects(
+        conn,
+        &claude_dir.join("projects"),
+        &projects::read_legacy_hidden(&claude_dir),
+    )?;
+    Ok(entries
+        .into_iter()
+        .filter(|project| !project.hidden)
+        .map(|project| {
+            let name = project.display_name.clone().unwrap_or_else(|| {
+                std::path::Path::new(&project.path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| project.path.clone())
+            });
+            QuickSearchItem {
+                kind: QuickSearchKind::Project,
+                id: project.id,
+                title: name,
+                subtitle: Some(project.path),
+            }
+        })
+        .collect())
+}
+
+fn station_candidates(app: &AppHandle) -> Vec<QuickSearchItem> {
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let Ok(manager_lock) = state.lock() else {
+        return Vec::new();
+    };
+    let Some(manager) = manager_lock.as_ref() else {
+        return Vec::new();
+    };
+    manager
+        .list_stations()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to list relay stations for quick search: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .map(|station| QuickSearchItem {
+            kind: QuickSearchKind::Station,
+            id: station.id,
+            title: station.name,
+            subtitle: Some(station.api_url),
+        })
+        .collect()
+}
+
+fn provider_candidates() -> Vec<QuickSearchItem> {
+    provider::load_providers_from_file()
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load providers for quick search: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .map(|config| QuickSearchItem {
+            kind: QuickSearchKind::Provider,
+            id: config.id,
+            title: config.name,
+            subtitle: Some(config.base_url),
+        })
+        .collect()
+}
+
+/// Fuzzy-search stations, providers, projects, sessions and recent palette
+/// commands, best match first
+///
+/// Sessions come from the session index as last synced by the session browser
+/// or project list, so typing does not rescan transcripts.
+#[tauri::command]
+pub async fn quick_search(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickSearchResult>, String> {
+    let mut candidates = station_candidates(&app);
+    candidates.extend(provider_candidates());
+    let picks = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        match project_candidates(&conn) {
+            Ok(projects) => candidates.extend(projects),
+            Err(e) => log::warn!("Failed to list projects for quick search: {}", e),
+        }
+        candidates.extend(session_candidates(&conn)?);
+        load_picks(&conn)?
+    };
+    Ok(rank(
+        &query,
+        candidates,
+        &picks,
+        chrono::Utc::now().timestamp(),
+        limit.unwrap_or(20).clamp(1, 100),
+    ))
+}
+
+/// Remember that the user chose `item` in the quick switcher
+///
+/// Commands of the palette itself are recorded with kind `command` and the
+/// frontend's command id; they show up in later searches from then on.
+#[tauri::command]
+pub async fn record_quick_search_pick(
+    db: State<'_, AgentDb>,
+    item: QuickSearchItem,
+) -> Result<(), String> {
+    if item.id.trim().is_empty() || item.title.trim().is_empty() {
+        return Err("A quick search pick needs an id and a title".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    record_pick(&conn, &item, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(
+        kind: QuickSearchKind,
+        id: &str,
+        title: &str,
+        subtitle: Option<&str>,
+    ) -> QuickSearchItem {
+        QuickSearchItem {
+            kind,
+            id: id.to_string(),
+            title: title.to_string(),
+            subtitle: subtitle.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        let (score, positions) = fuzzy_score("ws", "Work Station").unwrap();
+        assert_eq!(positions, vec![0, 5]);
+        // Word starts beat letters inside words
+        assert!(score > fuzzy_score("ws", "newsroom").unwrap().0);
+        // A run of adjacent letters beats a scattered match
+        assert!(
+            fuzzy_score("app", "apple").unwrap().0 > fuzzy_score("app", "alpha pop").unwrap().0
+        );
+        // The best start is kept, not the first one
+        assert_eq!(fuzzy_score("cl", "cool claude").unwrap().1, vec![5, 6]);
+        assert_eq!(fuzzy_score("ab", "AnyBody").unwrap().1, vec![0, 3]);
+        assert!(fuzzy_score("xyz", "claude").is_none());
+        assert!(fuzzy_score("", "claude").is_none());
+    }
+
+    #[test]
+    fn test_rank_and_picks() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_quick_search_picks_table(&conn).unwrap();
+        let now = 1_700_000_000;
+        let run = item(
+            QuickSearchKind::Command,
+            "run-doctor",
+            "Run environment doctor",
+            None,
+        );
+        record_pick(&conn, &run, now - 10 * DAY_SECONDS).unwrap();
+        let station = item(
+            QuickSearchKind::Station,
+            "s2",
+            "Relay Two",
+            Some("https://two.example"),
+        );
+        record_pick(&conn, &station, now - 60).unwrap();
+        record_pick(&conn, &station, now - 30).unwrap();
+        let picks = load_picks(&conn).unwrap();
+        assert_eq!(picks.len(), 2);
+        assert_eq!(picks[0].item, station);
+        assert_eq!(picks[0].use_count, 2);
+
+        let candidates = vec![
+            item(
+                QuickSearchKind::Station,
+                "s1",
+                "Relay One",
+                Some("https://one.example"),
+            ),
+            station.clone(),
+            item(
+                QuickSearchKind::Provider,
+                "p1",
+                "Anthropic",
+                Some("https://api.anthropic.com"),
+            ),
+            item(
+                QuickSearchKind::Session,
+                "abc",
+                "Fix relay retries",
+                Some("/work/app"),
+            ),
+        ];
+
+        // The recently picked station wins over the equally matching one
+        let results = rank("relay", candidates.clone(), &picks, now, 10);
+        let ids: Vec<&str> = results.iter().map(|r| r.item.id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s1", "abc"]);
+        assert_eq!(results[1].title_matches, vec![0, 1, 2, 3, 4]);
+
+        // Every term has to match; the subtitle counts too
+        let results = rank("anthropic api", candidates.clone(), &picks, now, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, "p1");
+
+        // Picked palette commands are searchable
+        let results = rank("doctor", candidates.clone(), &picks, now, 10);
+        assert_eq!(results[0].item, run);
+
+        // No query lists recent picks
+        let results = rank("  ", candidates, &picks, now, 10);
+        let ids: Vec<&str> = results.iter().map(|r| r.item.id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "run-doctor"]);
+
+        assert_eq!(
+            session_title(None, Some("  Fix\nthe   bug ".to_string()), "abc"),
+            "Fix the bug"
+        );
+        assert_eq!(session_title(None, None, "abc"), "abc");
+        assert_eq!(
+            session_title(Some("x".repeat(100)), None, "abc")
+                .chars()
+                .count(),
+            SESSION_TITLE_MAX_CHARS
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use anyhow::{Result, anyhow};
+use tokio::sync::watch;
+
+/// Cancellation signal for a single relay command invocation
+#[derive(Clone)]
+pub struct CancellationToken {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        let (tx, _rx) = watch::channel(false);
+        Self { tx: Arc::new(tx) }
+    }
+
+    pub fn cancel(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        let mut rx = self.tx.subscribe();
+        while !*rx.borrow_and_update() {
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Await `future` unless the token is cancelled first
+    ///
+    /// On cancellation the future is dropped, which aborts an in-flight reqwest request.
+    pub async fn run<T, E>(&self, future: impl Future<Output = std::result::Result<T, E>>) -> Result<T>
+    where
+        E: Into<anyhow::Error>,
+    {
+        if self.is_cancelled() {
+            return Err(anyhow!("Request cancelled"));
+        }
+        tokio::select! {
+            result = future => result.map_err(Into::into),
+            _ = self.cancelled() => Err(anyhow!("Request cancelled")),
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tokens of in-flight relay requests, keyed by a frontend-chosen request ID
+#[derive(Default)]
+pub struct RelayRequestRegistry {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl RelayRequestRegistry {
+    /// Create the token for a new request
+    ///
+    /// A previous request still running under the same ID is cancelled, so a
+    /// view re-fetching (e.g. paging through logs) only keeps the latest call alive.
+    /// Requests without an ID get a token that cannot be cancelled externally.
+    pub fn begin(&self, request_id: Option<&str>) -> CancellationToken {
+        let token = CancellationToken::new();
+        if let (Some(id), Ok(mut tokens)) = (request_id, self.tokens.lock()) {
+            if let Some(previous) = tokens.insert(id.to_string(), token.clone()) {
+                previous.cancel();
+            }
+        }
+        token
+    }
+
+    /// Forget a finished request, unless its ID was already taken by a newer one
+    pub fn finish(&self, request_id: Option<&str>, token: &CancellationToken) {
+        if let (Some(id), Ok(mut tokens)) = (request_id, self.tokens.lock()) {
+            if tokens.get(id).is_some_and(|current| Arc::ptr_eq(&current.tx, &token.tx)) {
+                tokens.remove(id);
+            }
+        }
+    }
+
+    /// Cancel an in-flight request, returning whether it was found
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.tokens.lock() {
+            Ok(mut tokens) => match tokens.remove(request_id) {
+                Some(token) => {
+                    token.cancel();
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
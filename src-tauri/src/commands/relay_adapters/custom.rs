@@ -7,6 +7,8 @@ use crate::commands::relay_stations::{
     StationAdapter
 };
 
+use super::cancel::CancellationToken;
+
 /// Custom adapter implementation - minimal functionality for simple provider configurations
 /// This adapter doesn't make API calls and is used for basic URL+key configurations
 pub struct CustomAdapter;
@@ -34,7 +36,7 @@ impl StationAdapter for CustomAdapter {
         Err(anyhow!("User info not available for custom configurations"))
     }
 
-    async fn get_logs(&self, _station: &RelayStation, _page: Option<usize>, _page_size: Option<usize>, _filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
+    async fn get_logs(&self, _station: &RelayStation, _page: Option<usize>, _page_size: Option<usize>, _filters: Option<serde_json::Value>, _cancel: &CancellationToken) -> Result<LogPaginationResponse> {
         Err(anyhow!("Logs not available for custom configurations"))
     }
 
@@ -49,7 +51,7 @@ impl StationAdapter for CustomAdapter {
         })
     }
 
-    async fn list_tokens(&self, _station: &RelayStation, _page: Option<usize>, _size: Option<usize>) -> Result<TokenPaginationResponse> {
+    async fn list_tokens(&self, _station: &RelayStation, _page: Option<usize>, _size: Option<usize>, _cancel: &CancellationToken) -> Result<TokenPaginationResponse> {
         Err(anyhow!("Token management not available for custom configurations"))
     }
 
@@ -72,4 +74,8 @@ impl StationAdapter for CustomAdapter {
     async fn get_user_groups(&self, _station: &RelayStation) -> Result<serde_json::Value> {
         Err(anyhow!("User groups not available for custom configurations"))
     }
+
+    async fn get_user_models(&self, _station: &RelayStation) -> Result<Vec<String>> {
+        Err(anyhow!("Model list not available for custom configurations"))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,158 @@
+//! Token groups a station offers, and which one new tokens default to
+//!
+//! NewAPI stations define their own groups, each with a price ratio. Stations
+//! differ in what they call them, so the default is picked from the groups the
+//! station returns instead of assuming a name.
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenGroup {
+    pub name: String,
+    pub description: Option<String>,
+    /// Price multiplier of the group; lower is cheaper
+    pub ratio: Option<f64>,
+}
+
+/// The station's groups and the one suggested for new tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenGroupSuggestion {
+    pub groups: Vec<TokenGroup>,
+    /// Unset when the station lists no groups; tokens then get the user's group
+    pub suggested: Option<String>,
+}
+
+impl TokenGroupSuggestion {
+    pub fn from_response(response: &Value) -> Self {
+        let groups = parse_groups(response);
+        let suggested = suggest(&groups);
+        Self { groups, suggested }
+    }
+}
+
+fn ratio_of(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|ratio| ratio.trim().parse().ok()))
+}
+
+/// Groups from a `/api/user/self/groups` response
+///
+/// NewAPI maps group names to `{desc, ratio}`; older panels send a list of
+/// names or of `{name, desc, ratio}` objects.
+pub fn parse_groups(response: &Value) -> Vec<TokenGroup> {
+    let data = response.get("data").unwrap_or(response);
+    let mut groups: Vec<TokenGroup> = match data {
+        Value::Object(map) => map
+            .iter()
+            .map(|(name, info)| TokenGroup {
+                name: name.clone(),
+                description: info
+                    .get("desc")
+                    .and_then(Value::as_str)
+                    .filter(|desc| !desc.is_empty())
+                    .map(str::to_string),
+                ratio: info.get("ratio").and_then(ratio_of),
+            })
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(name) => Some(TokenGroup {
+                    name: name.clone(),
+                    description: None,
+                    ratio: None,
+                }),
+                Value::Object(_) => Some(TokenGroup {
+                    name: item.get("name").and_then(Value::as_str)?.to_string(),
+                    description: item
+                        .get("desc")
+                        .or_else(|| item.get("description"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    ratio: item.get("ratio").and_then(ratio_of),
+                }),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    groups.retain(|group| !group.name.trim().is_empty());
+    groups
+}
+
+/// The group new tokens should use: the cheapest one meant for Claude, else
+/// the station's `default` group, else the cheapest one
+pub fn suggest(groups: &[TokenGroup]) -> Option<String> {
+    let cheapest = |candidates: Vec<&TokenGroup>| {
+        candidates
+            .into_iter()
+            .min_by(|a, b| {
+                let ratio = |group: &TokenGroup| group.ratio.unwrap_or(f64::MAX);
+                ratio(a)
+                    .total_cmp(&ratio(b))
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+            .map(|group| group.name.clone())
+    };
+    let for_claude: Vec<&TokenGroup> = groups
+        .iter()
+        .filter(|group| {
+            group.name.to_lowercase().contains("claude")
+                || group
+                    .description
+                    .as_deref()
+                    .is_some_and(|desc| desc.to_lowercase().contains("claude"))
+        })
+        .collect();
+    if !for_claude.is_empty() {
+        return cheapest(for_claude);
+    }
+    if let Some(default) = groups.iter().find(|group| group.name == "default") {
+        return Some(default.name.clone());
+    }
+    cheapest(groups.iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_suggest_group() {
+        let response = json!({"success": true, "data": {
+            "default": {"desc": "默认分组", "ratio": 1},
+            "vip": {"desc": "VIP", "ratio": 0.8},
+            "cc": {"desc": "Claude Code专用", "ratio": 1.2},
+            "claude-cheap": {"desc": "", "ratio": "0.9"}
+        }});
+        let suggestion = TokenGroupSuggestion::from_response(&response);
+        assert_eq!(suggestion.groups.len(), 4);
+        assert_eq!(suggestion.suggested.as_deref(), Some("claude-cheap"));
+
+        let response = json!({"data": {
+            "default": {"desc": "默认分组", "ratio": 1},
+            "vip": {"desc": "VIP", "ratio": 0.8}
+        }});
+        assert_eq!(
+            TokenGroupSuggestion::from_response(&response)
+                .suggested
+                .as_deref(),
+            Some("default")
+        );
+
+        let response = json!({"data": [
+            {"name": "svip", "ratio": 2},
+            {"name": "plus", "ratio": 1.5}
+        ]});
+        assert_eq!(
+            TokenGroupSuggestion::from_response(&response)
+                .suggested
+                .as_deref(),
+            Some("plus")
+        );
+
+        assert_eq!(suggest(&parse_groups(&json!({"data": {}}))), None);
+    }
+}
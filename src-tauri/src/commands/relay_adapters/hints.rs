@@ -0,0 +1,261 @@
+//! Troubleshooting hints for failed relay station requests
+//!
+//! Adapters report non-success responses as an `UpstreamError`, which keeps the
+//! status and the start of the body around so the failure can be classified
+//! into something the user can act on (a wrong token, a group the account is
+//! not in, a Cloudflare challenge, ...).
+
+use std::collections::HashMap;
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::t;
+
+/// Upper bound on how much of an error body is kept for classification
+const MAX_BODY_BYTES: usize = 2048;
+
+/// A non-success HTTP response from a relay station
+#[derive(Debug)]
+pub struct UpstreamError {
+    pub context: String,
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The body can echo request data back, so it is never shown
+        write!(f, "{}: {}", self.context, self.status)
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+/// Builds the error for a non-success response, keeping the start of its body
+pub async fn upstream_error(context: &str, response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
+    let mut body = response.text().await.unwrap_or_default();
+    if body.len() > MAX_BODY_BYTES {
+        let mut end = MAX_BODY_BYTES;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+    }
+    anyhow::Error::new(UpstreamError {
+        context: context.to_string(),
+        status,
+        body,
+    })
+}
+
+/// What most likely went wrong with a station request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidToken,
+    UserIdMismatch,
+    GroupNotAllowed,
+    Forbidden,
+    RateLimited,
+    CloudflareChallenge,
+    NotFound,
+    ServerError,
+    Timeout,
+    Unreachable,
+    InvalidResponse,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidToken => "invalid_token",
+            ErrorKind::UserIdMismatch => "user_id_mismatch",
+            ErrorKind::GroupNotAllowed => "group_not_allowed",
+            ErrorKind::Forbidden => "forbidden",
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::CloudflareChallenge => "cloudflare_challenge",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::ServerError => "server_error",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Unreachable => "unreachable",
+            ErrorKind::InvalidResponse => "invalid_response",
+        }
+    }
+
+    /// Localized suggestion for fixing the problem
+    pub fn hint(&self) -> String {
+        t!(&format!("relay_hints.{}", self.as_str()))
+    }
+}
+
+fn is_cloudflare_challenge(status: Option<u16>, body: &str) -> bool {
+    const MARKERS: [&str; 4] = [
+        "cf-chl",
+        "challenge-platform",
+        "<title>just a moment",
+        "attention required! | cloudflare",
+    ];
+    MARKERS.iter().any(|m| body.contains(m))
+        || (matches!(status, Some(403) | Some(503)) && body.contains("cloudflare"))
+}
+
+/// Classifies a response by its status code and body
+pub fn classify(status: Option<u16>, body: &str) -> Option<ErrorKind> {
+    let body = body.to_lowercase();
+    // A challenge page can come back with any status, so it goes first
+    if is_cloudflare_challenge(status, &body) {
+        return Some(ErrorKind::CloudflareChallenge);
+    }
+    if body.contains("new-api-user") {
+        return Some(ErrorKind::UserIdMismatch);
+    }
+    let rate_limited = [
+        "rate limit",
+        "too many requests",
+        "请求过于频繁",
+        "请求频率",
+    ];
+    if status == Some(429) || rate_limited.iter().any(|m| body.contains(m)) {
+        return Some(ErrorKind::RateLimited);
+    }
+    let group = body.contains("group") || body.contains("分组");
+    let invalid_token = [
+        "invalid token",
+        "invalid_token",
+        "无效的令牌",
+        "令牌无效",
+        "未提供令牌",
+    ];
+    match status {
+        Some(401) => Some(ErrorKind::InvalidToken),
+        Some(403) if group => Some(ErrorKind::GroupNotAllowed),
+        Some(403) => Some(ErrorKind::Forbidden),
+        Some(404) => Some(ErrorKind::NotFound),
+        Some(408) | Some(504) => Some(ErrorKind::Timeout),
+        Some(500..=599) => Some(ErrorKind::ServerError),
+        _ if invalid_token.iter().any(|m| body.contains(m)) => Some(ErrorKind::InvalidToken),
+        _ if group
+            && (body.contains("not allowed")
+                || body.contains("无权")
+                || body.contains("不可用")) =>
+        {
+            Some(ErrorKind::GroupNotAllowed)
+        }
+        _ if body.trim_start().starts_with("<!doctype")
+            || body.trim_start().starts_with("<html") =>
+        {
+            Some(ErrorKind::InvalidResponse)
+        }
+        _ if body.contains("invalid response format") => Some(ErrorKind::InvalidResponse),
+        _ => None,
+    }
+}
+
+/// Classifies an adapter error, looking through its whole cause chain
+pub fn classify_error(error: &anyhow::Error) -> Option<ErrorKind> {
+    for cause in error.chain() {
+        if let Some(upstream) = cause.downcast_ref::<UpstreamError>() {
+            return classify(Some(upstream.status.as_u16()), &upstream.body);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return Some(ErrorKind::Timeout);
+            }
+            if e.is_connect() {
+                return Some(ErrorKind::Unreachable);
+            }
+            if e.is_decode() {
+                return Some(ErrorKind::InvalidResponse);
+            }
+        }
+    }
+    classify(None, &error.to_string())
+}
+
+/// The error message, followed by a hint when the failure is recognised
+pub fn with_hint(error: &anyhow::Error) -> String {
+    match classify_error(error) {
+        Some(kind) => {
+            t!("relay_hints.with_hint", "error" => &error.to_string(), "hint" => &kind.hint())
+        }
+        None => error.to_string(),
+    }
+}
+
+/// `details` entries for a failed connection test
+pub fn details(kind: ErrorKind) -> HashMap<String, serde_json::Value> {
+    let mut map = HashMap::new();
+    map.insert(
+        "kind".to_string(),
+        serde_json::Value::String(kind.as_str().to_string()),
+    );
+    map.insert("hint".to_string(), serde_json::Value::String(kind.hint()));
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_upstream_failures() {
+        assert_eq!(
+            classify(Some(401), "{\"message\":\"无效的令牌\"}"),
+            Some(ErrorKind::InvalidToken)
+        );
+        assert_eq!(
+            classify(Some(403), "{\"message\":\"该令牌无权使用分组 vip\"}"),
+            Some(ErrorKind::GroupNotAllowed)
+        );
+        assert_eq!(classify(Some(403), ""), Some(ErrorKind::Forbidden));
+        assert_eq!(classify(Some(429), ""), Some(ErrorKind::RateLimited));
+        assert_eq!(
+            classify(Some(401), "未提供 New-Api-User"),
+            Some(ErrorKind::UserIdMismatch)
+        );
+        assert_eq!(
+            classify(
+                Some(200),
+                "{\"success\":false,\"message\":\"invalid token\"}"
+            ),
+            Some(ErrorKind::InvalidToken)
+        );
+        assert_eq!(classify(Some(400), "bad request"), None);
+    }
+
+    #[test]
+    fn cloudflare_challenge_wins_over_status() {
+        let page = "<!DOCTYPE html><html><head><title>Just a moment...</title>\
+                    <script src=\"/cdn-cgi/challenge-platform/h/b/orchestrate\"></script>";
+        assert_eq!(
+            classify(Some(403), page),
+            Some(ErrorKind::CloudflareChallenge)
+        );
+        assert_eq!(
+            classify(Some(429), page),
+            Some(ErrorKind::CloudflareChallenge)
+        );
+        assert_eq!(
+            classify(Some(503), "<html>cloudflare</html>"),
+            Some(ErrorKind::CloudflareChallenge)
+        );
+        assert_eq!(
+            classify(Some(200), "<!doctype html><html></html>"),
+            Some(ErrorKind::InvalidResponse)
+        );
+    }
+
+    #[test]
+    fn upstream_error_keeps_the_status_only_message() {
+        let error = anyhow::Error::new(UpstreamError {
+            context: "Failed to list tokens".to_string(),
+            status: StatusCode::UNAUTHORIZED,
+            body: "{\"message\":\"secret body\"}".to_string(),
+        });
+        assert_eq!(error.to_string(), "Failed to list tokens: 401 Unauthorized");
+        assert_eq!(classify_error(&error), Some(ErrorKind::InvalidToken));
+    }
+}
@@ -1,7 +1,15 @@
+pub mod cancel;
+pub mod groups;
+pub mod hints;
 pub mod newapi;
 pub mod yourapi;
 pub mod custom;
+pub mod plugin;
+pub mod offline;
 
+pub use cancel::{CancellationToken, RelayRequestRegistry};
 pub use newapi::NewApiAdapter;
 pub use yourapi::YourApiAdapter;
-pub use custom::CustomAdapter;
\ No newline at end of file
+pub use custom::CustomAdapter;
+pub use plugin::PluginAdapter;
+pub use offline::OfflineAdapter;
\ No newline at end of file
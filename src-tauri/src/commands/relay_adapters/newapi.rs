@@ -9,6 +9,10 @@ use crate::commands::relay_stations::{
     StationAdapter
 };
 
+use super::cancel::CancellationToken;
+use super::groups::TokenGroupSuggestion;
+use super::hints::{self, upstream_error};
+
 /// NewAPI adapter implementation
 pub struct NewApiAdapter;
 
@@ -51,7 +55,7 @@ impl StationAdapter for NewApiAdapter {
                 }),
             })
         } else {
-            Err(anyhow!("Failed to get station info: {}", response.status()))
+            Err(upstream_error("Failed to get station info", response).await)
         }
     }
 
@@ -106,11 +110,11 @@ impl StationAdapter for NewApiAdapter {
                 }),
             })
         } else {
-            Err(anyhow!("Failed to get user info: {}", response.status()))
+            Err(upstream_error("Failed to get user info", response).await)
         }
     }
 
-    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
+    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>, cancel: &CancellationToken) -> Result<LogPaginationResponse> {
         let client = reqwest::Client::new();
         let page = page.unwrap_or(1);
         let page_size = page_size.unwrap_or(10);
@@ -159,15 +163,16 @@ impl StationAdapter for NewApiAdapter {
             urlencoding::encode(&group)
         );
 
-        let response = client
+        // Dropped on cancellation, which aborts the in-flight request
+        let request = client
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
-            .await?;
+            .send();
+        let response = cancel.run(request).await?;
 
         if response.status().is_success() {
-            let data: serde_json::Value = response.json().await?;
+            let data: serde_json::Value = cancel.run(response.json()).await?;
             let log_data = data["data"].as_object().ok_or_else(|| anyhow!("Invalid response format"))?;
             let empty_vec = vec![];
             let logs = log_data.get("items").and_then(|v| v.as_array()).unwrap_or(&empty_vec);
@@ -242,7 +247,7 @@ impl StationAdapter for NewApiAdapter {
                 total: log_data.get("total").and_then(|v| v.as_i64()).unwrap_or(0),
             })
         } else {
-            Err(anyhow!("Failed to get logs: {}", response.status()))
+            Err(upstream_error("Failed to get logs", response).await)
         }
     }
 
@@ -271,28 +276,30 @@ impl StationAdapter for NewApiAdapter {
                         details: None,
                     })
                 } else {
+                    let body = response.text().await.unwrap_or_default();
                     Ok(ConnectionTestResult {
                         success: false,
                         response_time: Some(response_time),
                         message: format!("HTTP {}", status_code),
                         status_code: Some(status_code),
-                        details: None,
+                        details: hints::classify(Some(status_code), &body).map(hints::details),
                     })
                 }
             }
             Err(e) => {
+                let e = anyhow::Error::new(e);
                 Ok(ConnectionTestResult {
                     success: false,
                     response_time: None,
-                    message: format!("Connection failed: {}", e),
+                    message: format!("Connection failed: {}", crate::redact::redact(&e.to_string())),
                     status_code: None,
-                    details: None,
+                    details: hints::classify_error(&e).map(hints::details),
                 })
             }
         }
     }
 
-    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
+    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>, cancel: &CancellationToken) -> Result<TokenPaginationResponse> {
         let client = reqwest::Client::new();
         let user_id = station.user_id.as_deref().unwrap_or("1");
         let page = page.unwrap_or(1);
@@ -300,15 +307,16 @@ impl StationAdapter for NewApiAdapter {
         
         let url = format!("{}/api/token/?p={}&size={}", station.api_url, page, size);
         
-        let response = client
+        // Dropped on cancellation, which aborts the in-flight request
+        let request = client
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
-            .await?;
+            .send();
+        let response = cancel.run(request).await?;
 
         if response.status().is_success() {
-            let data: serde_json::Value = response.json().await?;
+            let data: serde_json::Value = cancel.run(response.json()).await?;
             let token_data = data["data"].as_object().ok_or_else(|| anyhow!("Invalid response format"))?;
             let empty_vec = vec![];
             let tokens = token_data.get("items").and_then(|v| v.as_array()).unwrap_or(&empty_vec);
@@ -371,7 +379,7 @@ impl StationAdapter for NewApiAdapter {
                 total: token_data.get("total").and_then(|v| v.as_i64()).unwrap_or(0),
             })
         } else {
-            Err(anyhow!("Failed to list tokens: {}", response.status()))
+            Err(upstream_error("Failed to list tokens", response).await)
         }
     }
 
@@ -379,6 +387,19 @@ impl StationAdapter for NewApiAdapter {
         let client = reqwest::Client::new();
         let user_id = station.user_id.as_deref().unwrap_or("1");
         
+        // Without a group the station's own groups decide; an empty group
+        // gives the token the user's group
+        let group = match token_data.group.as_deref().filter(|g| !g.is_empty()) {
+            Some(group) => Some(group.to_string()),
+            None => match self.get_user_groups(station).await {
+                Ok(groups) => TokenGroupSuggestion::from_response(&groups).suggested,
+                Err(e) => {
+                    log::warn!("Failed to get groups of station {}: {}", station.id, e);
+                    None
+                }
+            },
+        };
+        
         let request_body = serde_json::json!({
             "name": token_data.name,
             "remain_quota": token_data.remain_quota.unwrap_or(500000),
@@ -386,7 +407,7 @@ impl StationAdapter for NewApiAdapter {
             "unlimited_quota": token_data.unlimited_quota.unwrap_or(true),
             "model_limits_enabled": token_data.model_limits_enabled.unwrap_or(false),
             "model_limits": token_data.model_limits.as_deref().unwrap_or(""),
-            "group": token_data.group.as_deref().unwrap_or("Claude Code专用"),
+            "group": group.as_deref().unwrap_or(""),
             "allow_ips": token_data.allow_ips.as_deref().unwrap_or("")
         });
 
@@ -415,7 +436,7 @@ impl StationAdapter for NewApiAdapter {
                     user_id: Some(user_id.to_string()),
                     enabled: true,
                     expires_at: if token_data.expired_time.unwrap_or(-1) == -1 { None } else { token_data.expired_time },
-                    group: token_data.group.clone(),
+                    group,
                     remain_quota: token_data.remain_quota,
                     unlimited_quota: token_data.unlimited_quota,
                     metadata: Some({
@@ -433,7 +454,7 @@ impl StationAdapter for NewApiAdapter {
                 Err(anyhow!("Failed to create token: {}", message))
             }
         } else {
-            Err(anyhow!("Failed to create token: {}", response.status()))
+            Err(upstream_error("Failed to create token", response).await)
         }
     }
 
@@ -528,7 +549,7 @@ impl StationAdapter for NewApiAdapter {
                 Err(anyhow!("Invalid response format"))
             }
         } else {
-            Err(anyhow!("Failed to update token: {}", response.status()))
+            Err(upstream_error("Failed to update token", response).await)
         }
     }
 
@@ -546,7 +567,7 @@ impl StationAdapter for NewApiAdapter {
         if response.status().is_success() {
             Ok(())
         } else {
-            Err(anyhow!("Failed to delete token: {}", response.status()))
+            Err(upstream_error("Failed to delete token", response).await)
         }
     }
 
@@ -616,7 +637,7 @@ impl StationAdapter for NewApiAdapter {
                 Err(anyhow!("Invalid response format"))
             }
         } else {
-            Err(anyhow!("Failed to toggle token: {}", response.status()))
+            Err(upstream_error("Failed to toggle token", response).await)
         }
     }
 
@@ -635,7 +656,27 @@ impl StationAdapter for NewApiAdapter {
             let data: serde_json::Value = response.json().await?;
             Ok(data)
         } else {
-            Err(anyhow!("API request failed with status: {}", response.status()))
+            Err(upstream_error("API request failed with status", response).await)
+        }
+    }
+
+    async fn get_user_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        let client = reqwest::Client::new();
+        let user_id = station.user_id.as_deref().unwrap_or("1");
+
+        let response = client
+            .get(&format!("{}/api/user/models", station.api_url))
+            .header("Authorization", &format!("Bearer {}", station.system_token))
+            .header("New-API-User", user_id)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let data: serde_json::Value = response.json().await?;
+            let models = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format"))?;
+            Ok(models.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
+        } else {
+            Err(upstream_error("API request failed with status", response).await)
         }
     }
 }
\ No newline at end of file
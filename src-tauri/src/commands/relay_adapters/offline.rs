@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::commands::relay_stations::{
+    ConnectionTestResult, CreateTokenRequest, LogPaginationResponse, RelayStation,
+    RelayStationToken, StationAdapter, StationInfo, TokenPaginationResponse, UpdateTokenRequest,
+    UserInfo,
+};
+use crate::t;
+
+use super::cancel::CancellationToken;
+
+/// Adapter used while offline mode is on: it refuses every call instead of
+/// contacting the station, so callers fall back to what was cached
+pub struct OfflineAdapter;
+
+fn offline<T>() -> Result<T> {
+    Err(anyhow!(t!("offline.station_not_contacted")))
+}
+
+#[async_trait::async_trait]
+impl StationAdapter for OfflineAdapter {
+    async fn get_station_info(&self, _station: &RelayStation) -> Result<StationInfo> {
+        offline()
+    }
+
+    async fn get_user_info(&self, _station: &RelayStation, _user_id: &str) -> Result<UserInfo> {
+        offline()
+    }
+
+    async fn get_logs(
+        &self,
+        _station: &RelayStation,
+        _page: Option<usize>,
+        _page_size: Option<usize>,
+        _filters: Option<Value>,
+        _cancel: &CancellationToken,
+    ) -> Result<LogPaginationResponse> {
+        offline()
+    }
+
+    async fn test_connection(&self, _station: &RelayStation) -> Result<ConnectionTestResult> {
+        offline()
+    }
+
+    async fn list_tokens(
+        &self,
+        _station: &RelayStation,
+        _page: Option<usize>,
+        _size: Option<usize>,
+        _cancel: &CancellationToken,
+    ) -> Result<TokenPaginationResponse> {
+        offline()
+    }
+
+    async fn create_token(
+        &self,
+        _station: &RelayStation,
+        _token_data: &CreateTokenRequest,
+    ) -> Result<RelayStationToken> {
+        offline()
+    }
+
+    async fn update_token(
+        &self,
+        _station: &RelayStation,
+        _token_id: &str,
+        _token_data: &UpdateTokenRequest,
+    ) -> Result<RelayStationToken> {
+        offline()
+    }
+
+    async fn delete_token(&self, _station: &RelayStation, _token_id: &str) -> Result<()> {
+        offline()
+    }
+
+    async fn toggle_token(
+        &self,
+        _station: &RelayStation,
+        _token_id: &str,
+        _enabled: bool,
+    ) -> Result<RelayStationToken> {
+        offline()
+    }
+
+    async fn get_user_groups(&self, _station: &RelayStation) -> Result<Value> {
+        offline()
+    }
+
+    async fn get_user_models(&self, _station: &RelayStation) -> Result<Vec<String>> {
+        offline()
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::commands::plugins;
+use crate::commands::relay_stations::{
+    ConnectionTestResult, CreateTokenRequest, LogPaginationResponse, RelayStation,
+    RelayStationToken, StationAdapter, StationInfo, TokenPaginationResponse, UpdateTokenRequest,
+    UserInfo,
+};
+
+use super::cancel::CancellationToken;
+
+/// `adapter_config` key naming the plugin that serves a station
+pub const PLUGIN_CONFIG_KEY: &str = "plugin";
+
+/// `adapter_config` key naming the plugin adapter, for plugins offering several
+pub const PLUGIN_ADAPTER_CONFIG_KEY: &str = "plugin_adapter";
+
+/// Drop a plugin binding from a station's `adapter_config`
+///
+/// A bound plugin receives the station's system token, so bindings are only
+/// made through `bind_station_plugin` and never come from the add-station
+/// form, an import or a share.
+pub fn strip_binding(config: &mut Option<HashMap<String, Value>>) {
+    if let Some(config) = config {
+        config.remove(PLUGIN_CONFIG_KEY);
+        config.remove(PLUGIN_ADAPTER_CONFIG_KEY);
+    }
+}
+
+/// Adapter forwarding every call to a plugin as `adapter/<method>`
+///
+/// The params hold the adapter name, the full station (including its system
+/// token, which the plugin needs to talk to the station) and the method's
+/// arguments; the result must have the shape of the method's return type.
+pub struct PluginAdapter {
+    plugin_id: String,
+    adapter: Option<String>,
+}
+
+impl PluginAdapter {
+    /// Adapter for a station the user bound to a plugin
+    pub fn for_station(station: &RelayStation) -> Option<Self> {
+        let config = station.adapter_config.as_ref()?;
+        let plugin_id = config.get(PLUGIN_CONFIG_KEY)?.as_str()?.trim();
+        if plugin_id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            plugin_id: plugin_id.to_string(),
+            adapter: config
+                .get(PLUGIN_ADAPTER_CONFIG_KEY)
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        station: &RelayStation,
+        args: Value,
+    ) -> Result<T> {
+        let mut params = json!({
+            "adapter": self.adapter,
+            "station": station,
+        });
+        if let (Some(params), Value::Object(args)) = (params.as_object_mut(), args) {
+            params.extend(args);
+        }
+        let host = plugins::host().map_err(|e| anyhow!(e))?;
+        let result = host
+            .call(&self.plugin_id, &format!("adapter/{}", method), params)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        serde_json::from_value(result).map_err(|e| {
+            anyhow!(
+                "Plugin {} returned an invalid {} result: {}",
+                self.plugin_id,
+                method,
+                e
+            )
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl StationAdapter for PluginAdapter {
+    async fn get_station_info(&self, station: &RelayStation) -> Result<StationInfo> {
+        self.call("get_station_info", station, json!({})).await
+    }
+
+    async fn get_user_info(&self, station: &RelayStation, user_id: &str) -> Result<UserInfo> {
+        self.call("get_user_info", station, json!({ "user_id": user_id }))
+            .await
+    }
+
+    async fn get_logs(
+        &self,
+        station: &RelayStation,
+        page: Option<usize>,
+        page_size: Option<usize>,
+        filters: Option<Value>,
+        cancel: &CancellationToken,
+    ) -> Result<LogPaginationResponse> {
+        let args = json!({ "page": page, "page_size": page_size, "filters": filters });
+        cancel.run(self.call("get_logs", station, args)).await
+    }
+
+    async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult> {
+        self.call("test_connection", station, json!({})).await
+    }
+
+    async fn list_tokens(
+        &self,
+        station: &RelayStation,
+        page: Option<usize>,
+        size: Option<usize>,
+        cancel: &CancellationToken,
+    ) -> Result<TokenPaginationResponse> {
+        let args = json!({ "page": page, "size": size });
+        cancel.run(self.call("list_tokens", station, args)).await
+    }
+
+    async fn create_token(
+        &self,
+        station: &RelayStation,
+        token_data: &CreateTokenRequest,
+    ) -> Result<RelayStationToken> {
+        self.call("create_token", station, json!({ "token": token_data }))
+            .await
+    }
+
+    async fn update_token(
+        &self,
+        station: &RelayStation,
+        token_id: &str,
+        token_data: &UpdateTokenRequest,
+    ) -> Result<RelayStationToken> {
+        let args = json!({ "token_id": token_id, "token": token_data });
+        self.call("update_token", station, args).await
+    }
+
+    async fn delete_token(&self, station: &RelayStation, token_id: &str) -> Result<()> {
+        self.call::<Value>("delete_token", station, json!({ "token_id": token_id }))
+            .await
+            .map(|_| ())
+    }
+
+    async fn toggle_token(
+        &self,
+        station: &RelayStation,
+        token_id: &str,
+        enabled: bool,
+    ) -> Result<RelayStationToken> {
+        let args = json!({ "token_id": token_id, "enabled": enabled });
+        self.call("toggle_token", station, args).await
+    }
+
+    async fn get_user_groups(&self, station: &RelayStation) -> Result<Value> {
+        self.call("get_user_groups", station, json!({})).await
+    }
+
+    async fn get_user_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        self.call("get_user_models", station, json!({})).await
+    }
+}
@@ -8,6 +8,8 @@ use crate::commands::relay_stations::{
     StationAdapter
 };
 
+use super::cancel::CancellationToken;
+use super::hints::upstream_error;
 use super::newapi::NewApiAdapter;
 
 /// YourAPI adapter implementation - inherits most functionality from NewAPI but overrides token listing
@@ -34,8 +36,8 @@ impl StationAdapter for YourApiAdapter {
         self.newapi.get_user_info(station, user_id).await
     }
 
-    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse> {
-        self.newapi.get_logs(station, page, page_size, filters).await
+    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>, cancel: &CancellationToken) -> Result<LogPaginationResponse> {
+        self.newapi.get_logs(station, page, page_size, filters, cancel).await
     }
 
     async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult> {
@@ -62,8 +64,12 @@ impl StationAdapter for YourApiAdapter {
         self.newapi.get_user_groups(station).await
     }
 
+    async fn get_user_models(&self, station: &RelayStation) -> Result<Vec<String>> {
+        self.newapi.get_user_models(station).await
+    }
+
     // Override list_tokens for YourAPI format
-    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse> {
+    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>, cancel: &CancellationToken) -> Result<TokenPaginationResponse> {
         let client = reqwest::Client::new();
         let user_id = station.user_id.as_deref().unwrap_or("1");
         let page = page.unwrap_or(1); // Use 1-based pagination like frontend expects
@@ -74,15 +80,16 @@ impl StationAdapter for YourApiAdapter {
         let fetch_size = size + 1; // Get one extra item to check if there are more pages
         let url = format!("{}/api/token/?p={}&size={}", station.api_url, page - 1, fetch_size); // Convert to 0-based for API
         
-        let response = client
+        // Dropped on cancellation, which aborts the in-flight request
+        let request = client
             .get(&url)
             .header("Authorization", &format!("Bearer {}", station.system_token))
             .header("New-API-User", user_id)
-            .send()
-            .await?;
+            .send();
+        let response = cancel.run(request).await?;
 
         if response.status().is_success() {
-            let data: serde_json::Value = response.json().await?;
+            let data: serde_json::Value = cancel.run(response.json()).await?;
             
             // YourAPI returns data as direct array, not nested in pagination object
             let tokens = data["data"].as_array().ok_or_else(|| anyhow!("Invalid response format: data is not an array"))?;
@@ -168,7 +175,7 @@ impl StationAdapter for YourApiAdapter {
                 total: estimated_total,
             })
         } else {
-            Err(anyhow!("Failed to list tokens: {}", response.status()))
+            Err(upstream_error("Failed to list tokens", response).await)
         }
     }
 }
\ No newline at end of file
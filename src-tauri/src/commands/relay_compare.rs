@@ -0,0 +1,312 @@
+//! Side-by-side comparison of relay stations
+//!
+//! Every station is queried at once for its latency, balance, quota per
+//! unit, models and group ratios. Spend of the last week comes from the
+//! recorded balance snapshots. A figure that could not be read is left empty
+//! and the reason is kept in the station's `errors`.
+use crate::commands::agents::AgentDb;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::currency::{self, Converted};
+use super::relay_forecast::{load_snapshots, record_snapshot, total_spent};
+use super::relay_stations::{
+    create_station_adapter, RelayStation, RelayStationAdapter, RelayStationManager, UserInfo,
+};
+use crate::t;
+
+/// Spend is summed over this many days
+const SPEND_WINDOW_DAYS: i64 = 7;
+
+/// Billing ratio of a user group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupRatio {
+    pub group: String,
+    pub ratio: f64,
+    pub description: Option<String>,
+}
+
+/// One row of the comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationComparison {
+    pub station_id: String,
+    pub station_name: String,
+    pub adapter: RelayStationAdapter,
+    pub reachable: bool,
+    /// Response time of the connection test in milliseconds
+    pub latency_ms: Option<u64>,
+    /// Currency the balance and spend are in; only USD amounts are converted
+    pub station_currency: String,
+    /// Remaining balance
+    pub balance: Option<f64>,
+    pub quota_per_unit: Option<i64>,
+    /// Sorted model names
+    pub models: Vec<String>,
+    /// Cheapest group first
+    pub group_ratios: Vec<GroupRatio>,
+    /// Spent over the last 7 days, as far as balance snapshots show
+    pub weekly_spend: Option<f64>,
+    /// Why a figure is missing, by field name
+    pub errors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationComparisonReport {
+    /// In the order the ids were given
+    pub stations: Vec<StationComparison>,
+    /// Every model offered by any of the stations, sorted
+    pub models: Vec<String>,
+    pub generated_at: i64,
+}
+
+/// Group ratios from a `/api/user/self/groups` style response
+///
+/// Accepts the response itself or its `data`, mapping group names to either
+/// `{"ratio": .., "desc": ..}` or a bare ratio.
+pub fn parse_group_ratios(groups: &Value) -> Vec<GroupRatio> {
+    let groups = groups.get("data").unwrap_or(groups);
+    let Some(groups) = groups.as_object() else {
+        return Vec::new();
+    };
+    let mut ratios: Vec<GroupRatio> = groups
+        .iter()
+        .filter_map(|(group, value)| {
+            let ratio = value
+                .get("ratio")
+                .unwrap_or(value)
+                .as_f64()
+                .or_else(|| value.get("ratio")?.as_str()?.parse().ok())?;
+            let description = value
+                .get("desc")
+                .and_then(Value::as_str)
+                .filter(|desc| !desc.is_empty())
+                .map(str::to_string);
+            Some(GroupRatio {
+                group: group.clone(),
+                ratio,
+                description,
+            })
+        })
+        .collect();
+    ratios.sort_by(|a, b| {
+        a.ratio
+            .total_cmp(&b.ratio)
+            .then_with(|| a.group.cmp(&b.group))
+    });
+    ratios
+}
+
+/// Model names, sorted and without duplicates or blanks
+fn normalize_models(models: Vec<String>) -> Vec<String> {
+    models
+        .into_iter()
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Union of the stations' models
+fn all_models(stations: &[StationComparison]) -> Vec<String> {
+    stations
+        .iter()
+        .flat_map(|station| station.models.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Query a station; the balance is left to the caller, which records it
+async fn compare_station(
+    app: &AppHandle,
+    station: &RelayStation,
+) -> (StationComparison, Option<UserInfo>) {
+    let adapter = create_station_adapter(app, station);
+    let user_info = async {
+        match station.user_id.as_deref() {
+            Some(user_id) => adapter
+                .get_user_info(station, user_id)
+                .await
+                .map_err(|e| e.to_string()),
+            None => Err("The station has no user id to read the balance of".to_string()),
+        }
+    };
+    let (connection, info, user_info, groups, models) = tokio::join!(
+        adapter.test_connection(station),
+        adapter.get_station_info(station),
+        user_info,
+        adapter.get_user_groups(station),
+        adapter.get_user_models(station),
+    );
+
+    let mut errors = BTreeMap::new();
+    let (reachable, latency_ms) = match connection {
+        Ok(result) => {
+            if !result.success {
+                errors.insert("latency_ms".to_string(), result.message);
+            }
+            (result.success, result.response_time)
+        }
+        Err(e) => {
+            errors.insert("latency_ms".to_string(), e.to_string());
+            (false, None)
+        }
+    };
+    let quota_per_unit = match info {
+        Ok(info) => info.quota_per_unit,
+        Err(e) => {
+            errors.insert("quota_per_unit".to_string(), e.to_string());
+            None
+        }
+    };
+    let balance = match &user_info {
+        Ok(info) => info.balance_remaining,
+        Err(e) => {
+            errors.insert("balance".to_string(), e.clone());
+            None
+        }
+    };
+    let group_ratios = match groups {
+        Ok(groups) => parse_group_ratios(&groups),
+        Err(e) => {
+            errors.insert("group_ratios".to_string(), e.to_string());
+            Vec::new()
+        }
+    };
+    let models = match models {
+        Ok(models) => normalize_models(models),
+        Err(e) => {
+            errors.insert("models".to_string(), e.to_string());
+            Vec::new()
+        }
+    };
+
+    let comparison = StationComparison {
+        station_id: station.id.clone(),
+        station_name: station.name.clone(),
+        adapter: station.adapter.clone(),
+        station_currency: currency::station_currency(station.adapter_config.as_ref()),
+        reachable,
+        latency_ms,
+        balance,
+        quota_per_unit,
+        models,
+        group_ratios,
+        weekly_spend: None,
+        errors,
+    };
+    (comparison, user_info.ok())
+}
+
+/// Compare stations side by side: latency, balance, quota per unit, models,
+/// group ratios and spend of the last 7 days
+#[tauri::command]
+pub async fn compare_stations(
+    ids: Vec<String>,
+    app: AppHandle,
+) -> Result<Converted<StationComparisonReport>, String> {
+    if ids.is_empty() {
+        return Err("Choose at least one station to compare".to_string());
+    }
+    let stations = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        ids.iter()
+            .map(|id| {
+                manager
+                    .get_station(id)
+                    .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+                    .ok_or_else(|| t!("relay.station_not_found"))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+
+    let results = futures::future::join_all(
+        stations
+            .iter()
+            .map(|station| compare_station(&app, station)),
+    )
+    .await;
+
+    let now = chrono::Utc::now().timestamp();
+    let since = now - SPEND_WINDOW_DAYS * 24 * 3600;
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut compared = Vec::with_capacity(results.len());
+    for (mut comparison, user_info) in results {
+        if let Some(info) = user_info {
+            if let Err(e) = record_snapshot(&conn, &comparison.station_id, &info) {
+                log::warn!("{}", e);
+            }
+        }
+        match load_snapshots(&conn, &comparison.station_id, since) {
+            Ok(snapshots) if snapshots.len() >= 2 => {
+                comparison.weekly_spend = Some(total_spent(&snapshots));
+            }
+            Ok(_) => {
+                comparison.errors.insert(
+                    "weekly_spend".to_string(),
+                    "Not enough balance history in the last 7 days".to_string(),
+                );
+            }
+            Err(e) => {
+                comparison.errors.insert("weekly_spend".to_string(), e);
+            }
+        }
+        compared.push(comparison);
+    }
+
+    Ok(Converted(StationComparisonReport {
+        models: all_models(&compared),
+        stations: compared,
+        generated_at: now,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_group_ratios() {
+        let response = json!({
+            "success": true,
+            "data": {
+                "vip": {"ratio": 0.8, "desc": "VIP"},
+                "default": {"ratio": 1, "desc": ""},
+                "svip": {"ratio": "0.5", "desc": "Super VIP"},
+                "broken": {"desc": "no ratio"}
+            }
+        });
+        let ratios = parse_group_ratios(&response);
+        let groups: Vec<&str> = ratios.iter().map(|ratio| ratio.group.as_str()).collect();
+        assert_eq!(groups, vec!["svip", "vip", "default"]);
+        assert_eq!(ratios[0].ratio, 0.5);
+        assert_eq!(ratios[1].description.as_deref(), Some("VIP"));
+        assert_eq!(ratios[2].description, None);
+
+        let bare = parse_group_ratios(&json!({"default": 1.0, "cheap": 0.3}));
+        assert_eq!(bare[0].group, "cheap");
+        assert!(parse_group_ratios(&json!([1, 2])).is_empty());
+
+        assert_eq!(
+            normalize_models(vec![
+                "gpt-5".to_string(),
+                " claude-opus-4-1 ".to_string(),
+                "gpt-5".to_string(),
+                String::new(),
+            ]),
+            vec!["claude-opus-4-1", "gpt-5"]
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! Spend forecasting for relay stations from recorded balance snapshots
+//!
+//! A snapshot is stored whenever the balance of a station is fetched. The
+//! spend rate of the last week comes from the growth of the station's "used"
+//! amount where it reports one, otherwise from balance drops; top-ups never
+//! count as spend.
+use crate::commands::agents::AgentDb;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::currency::{self, Converted};
+use super::relay_stations::{create_station_adapter, RelayStation, RelayStationManager, UserInfo};
+use crate::t;
+
+/// Spend rate is measured over this many days
+const FORECAST_WINDOW_DAYS: i64 = 7;
+
+/// Snapshots spanning less than this give no spend rate
+const MIN_SPAN_SECONDS: i64 = 3600;
+
+/// An unchanged balance is recorded again only after this long
+const MIN_SNAPSHOT_INTERVAL_SECONDS: i64 = 600;
+
+/// Snapshots older than this are pruned when a new one is recorded
+const RETENTION_DAYS: i64 = 90;
+
+const DAY_SECONDS: f64 = 86_400.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub station_id: String,
+    /// Remaining balance in USD
+    pub balance: Option<f64>,
+    /// Total spent in USD, as reported by the station
+    pub amount_used: Option<f64>,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceForecast {
+    pub station_id: String,
+    pub station_name: String,
+    /// Currency the balance and spend are in; only USD amounts are converted
+    pub station_currency: String,
+    /// Latest known balance
+    pub balance: Option<f64>,
+    pub balance_recorded_at: Option<i64>,
+    /// Average spent per day over the window
+    pub daily_spend: Option<f64>,
+    pub days_until_empty: Option<f64>,
+    /// Unix timestamp the balance runs out at the current rate
+    pub empty_at: Option<i64>,
+    /// Daily spend times 30
+    pub projected_monthly_spend: Option<f64>,
+    /// Snapshots the rate is based on
+    pub snapshot_count: usize,
+    /// Why the balance could not be refreshed, if it could not
+    pub fetch_error: Option<String>,
+}
+
+pub fn create_balance_snapshots_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_balance_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            station_id TEXT NOT NULL,
+            balance REAL,
+            amount_used REAL,
+            recorded_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_station_balance_snapshots_station
+         ON station_balance_snapshots(station_id, recorded_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn row_to_snapshot(row: &rusqlite::Row) -> rusqlite::Result<BalanceSnapshot> {
+    Ok(BalanceSnapshot {
+        station_id: row.get(0)?,
+        balance: row.get(1)?,
+        amount_used: row.get(2)?,
+        recorded_at: row.get(3)?,
+    })
+}
+
+const SELECT_COLUMNS: &str =
+    "SELECT station_id, balance, amount_used, recorded_at FROM station_balance_snapshots";
+
+fn latest_snapshot(conn: &Connection, station_id: &str) -> Result<Option<BalanceSnapshot>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE station_id = ?1 ORDER BY recorded_at DESC LIMIT 1",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query_map(params![station_id], row_to_snapshot)
+        .map_err(|e| e.to_string())?;
+    rows.next().transpose().map_err(|e| e.to_string())
+}
+
+/// Last snapshot of a station recorded before `before`
+fn snapshot_before(
+    conn: &Connection,
+    station_id: &str,
+    before: i64,
+) -> Result<Option<BalanceSnapshot>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE station_id = ?1 AND recorded_at < ?2 ORDER BY recorded_at DESC LIMIT 1",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query_map(params![station_id, before], row_to_snapshot)
+        .map_err(|e| e.to_string())?;
+    rows.next().transpose().map_err(|e| e.to_string())
+}
+
+/// Snapshots of a station since `since`, oldest first
+pub fn load_snapshots(
+    conn: &Connection,
+    station_id: &str,
+    since: i64,
+) -> Result<Vec<BalanceSnapshot>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE station_id = ?1 AND recorded_at >= ?2 ORDER BY recorded_at",
+            SELECT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let snapshots = stmt
+        .query_map(params![station_id, since], row_to_snapshot)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(snapshots)
+}
+
+/// Store the balance of a fetched user info; unchanged balances are stored
+/// again only every few minutes
+pub fn record_snapshot(conn: &Connection, station_id: &str, info: &UserInfo) -> Result<(), String> {
+    if info.balance_remaining.is_none() && info.amount_used.is_none() {
+        return Ok(());
+    }
+    let now = chrono::Utc::now().timestamp();
+    if let Some(last) = latest_snapshot(conn, station_id)? {
+        let unchanged =
+            last.balance == info.balance_remaining && last.amount_used == info.amount_used;
+        if unchanged && now - last.recorded_at < MIN_SNAPSHOT_INTERVAL_SECONDS {
+            return Ok(());
+        }
+    }
+    conn.execute(
+        "INSERT INTO station_balance_snapshots (station_id, balance, amount_used, recorded_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![station_id, info.balance_remaining, info.amount_used, now],
+    )
+    .map_err(|e| format!("Failed to record balance snapshot: {}", e))?;
+    conn.execute(
+        "DELETE FROM station_balance_snapshots WHERE recorded_at < ?1",
+        params![now - RETENTION_DAYS * 24 * 3600],
+    )
+    .map_err(|e| format!("Failed to prune balance snapshots: {}", e))?;
+    Ok(())
+}
+
+/// USD spent between consecutive snapshots (oldest first)
+pub fn total_spent(snapshots: &[BalanceSnapshot]) -> f64 {
+    snapshots
+        .windows(2)
+        .map(|pair| {
+            let (before, after) = (&pair[0], &pair[1]);
+            match (before.amount_used, after.amount_used) {
+                (Some(used_before), Some(used_after)) if used_after >= used_before => {
+                    used_after - used_before
+                }
+                // A balance that went up was topped up
+                _ => match (before.balance, after.balance) {
+                    (Some(balance_before), Some(balance_after)) => {
+                        (balance_before - balance_after).max(0.0)
+                    }
+                    _ => 0.0,
+                },
+            }
+        })
+        .sum()
+}
+
+/// USD spent per day between the first and last snapshot (oldest first)
+pub fn daily_spend(snapshots: &[BalanceSnapshot]) -> Option<f64> {
+    let (first, last) = (snapshots.first()?, snapshots.last()?);
+    let span = last.recorded_at - first.recorded_at;
+    if span < MIN_SPAN_SECONDS {
+        return None;
+    }
+    Some(total_spent(snapshots) / (span as f64 / DAY_SECONDS))
+}
+
+/// USD spent after `since`, from snapshots starting with the last one
+/// recorded before it (oldest first)
+///
+/// Spend between that snapshot and the first one after `since` is prorated
+/// by the part of the interval that lies after `since`.
+fn spent_after(snapshots: &[BalanceSnapshot], since: i64) -> f64 {
+    match snapshots {
+        [before, after, ..] if before.recorded_at < since => {
+            let interval = (after.recorded_at - before.recorded_at) as f64;
+            let share = (after.recorded_at - since) as f64 / interval;
+            total_spent(&snapshots[..2]) * share + total_spent(&snapshots[1..])
+        }
+        _ => total_spent(snapshots),
+    }
+}
+
+/// USD spent on every station since `since`, as far as snapshots show
+pub fn spent_since(conn: &Connection, since: i64) -> Result<f64, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT station_id FROM station_balance_snapshots WHERE recorded_at >= ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let station_ids = stmt
+        .query_map(params![since], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut spent = 0.0;
+    for station_id in station_ids {
+        let mut snapshots: Vec<BalanceSnapshot> = snapshot_before(conn, &station_id, since)?
+            .into_iter()
+            .collect();
+        snapshots.extend(load_snapshots(conn, &station_id, since)?);
+        spent += spent_after(&snapshots, since);
+    }
+    Ok(spent)
+}
+
+/// Forecast from the window's snapshots and the latest known balance
+pub fn forecast(
+    station_id: &str,
+    station_name: &str,
+    snapshots: &[BalanceSnapshot],
+    latest: Option<&BalanceSnapshot>,
+    now: i64,
+) -> BalanceForecast {
+    let latest = snapshots.last().or(latest);
+    let balance = latest.and_then(|snapshot| snapshot.balance);
+    let daily_spend = daily_spend(snapshots);
+    let days_until_empty = match (balance, daily_spend) {
+        (Some(balance), _) if balance <= 0.0 => Some(0.0),
+        (Some(balance), Some(rate)) if rate > 0.0 => Some(balance / rate),
+        _ => None,
+    };
+    BalanceForecast {
+        station_id: station_id.to_string(),
+        station_name: station_name.to_string(),
+        station_currency: "USD".to_string(),
+        balance,
+        balance_recorded_at: latest.map(|snapshot| snapshot.recorded_at),
+        daily_spend,
+        days_until_empty,
+        empty_at: days_until_empty.map(|days| now + (days * DAY_SECONDS) as i64),
+        projected_monthly_spend: daily_spend.map(|rate| rate * 30.0),
+        snapshot_count: snapshots.len(),
+        fetch_error: None,
+    }
+}
+
+fn stored_forecast(conn: &Connection, station: &RelayStation) -> Result<BalanceForecast, String> {
+    let now = chrono::Utc::now().timestamp();
+    let snapshots = load_snapshots(conn, &station.id, now - FORECAST_WINDOW_DAYS * 24 * 3600)?;
+    let latest = if snapshots.is_empty() {
+        latest_snapshot(conn, &station.id)?
+    } else {
+        None
+    };
+    Ok(BalanceForecast {
+        station_currency: currency::station_currency(station.adapter_config.as_ref()),
+        ..forecast(&station.id, &station.name, &snapshots, latest.as_ref(), now)
+    })
+}
+
+/// Refresh the balance of a station and estimate when it runs out and what
+/// a month costs at the spend rate of the last week
+#[tauri::command]
+pub async fn forecast_balance(
+    station_id: String,
+    app: AppHandle,
+) -> Result<Converted<BalanceForecast>, String> {
+    let station = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?
+    };
+
+    let fetched = match station.user_id.as_deref() {
+        Some(user_id) => create_station_adapter(&app, &station)
+            .get_user_info(&station, user_id)
+            .await
+            .map_err(|e| e.to_string()),
+        None => Err("The station has no user id to read the balance of".to_string()),
+    };
+
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let fetch_error = match fetched {
+        Ok(info) => record_snapshot(&conn, &station.id, &info).err(),
+        Err(e) => {
+            log::warn!("Failed to refresh the balance of {}: {}", station.name, e);
+            Some(e)
+        }
+    };
+    Ok(Converted(BalanceForecast {
+        fetch_error,
+        ..stored_forecast(&conn, &station)?
+    }))
+}
+
+/// Forecasts of every enabled station from stored snapshots, without
+/// contacting the stations; for the dashboard
+#[tauri::command]
+pub async fn forecast_all_balances(
+    app: AppHandle,
+) -> Result<Converted<Vec<BalanceForecast>>, String> {
+    let stations = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .list_stations()
+            .map_err(|_e| t!("relay.failed_to_list_stations", "error" => &_e.to_string()))?
+    };
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    stations
+        .iter()
+        .filter(|station| station.enabled)
+        .map(|station| stored_forecast(&conn, station))
+        .collect::<Result<_, _>>()
+        .map(Converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(hours: i64, balance: f64, amount_used: Option<f64>) -> BalanceSnapshot {
+        BalanceSnapshot {
+            station_id: "s1".to_string(),
+            balance: Some(balance),
+            amount_used,
+            recorded_at: 1_700_000_000 + hours * 3600,
+        }
+    }
+
+    #[test]
+    fn test_forecast() {
+        // $2 spent over a day, then a $10 top-up that must not count
+        let snapshots = vec![
+            snapshot(0, 10.0, None),
+            snapshot(12, 9.0, None),
+            snapshot(24, 8.0, None),
+            snapshot(36, 17.0, None),
+            snapshot(48, 16.0, None),
+        ];
+        assert_eq!(daily_spend(&snapshots), Some(1.5));
+        let now = snapshots[4].recorded_at;
+        let result = forecast("s1", "Station", &snapshots, None, now);
+        assert_eq!(result.balance, Some(16.0));
+        assert_eq!(result.days_until_empty, Some(16.0 / 1.5));
+        assert_eq!(result.projected_monthly_spend, Some(45.0));
+        assert!(result.empty_at.unwrap() > now);
+
+        // The used amount wins over balance changes when reported
+        let used = vec![snapshot(0, 5.0, Some(1.0)), snapshot(24, 25.0, Some(4.0))];
+        assert_eq!(daily_spend(&used), Some(3.0));
+
+        assert_eq!(daily_spend(&snapshots[..1]), None);
+        let empty = forecast("s1", "Station", &[], Some(&snapshot(0, 0.0, None)), now);
+        assert_eq!(empty.days_until_empty, Some(0.0));
+        assert_eq!(empty.daily_spend, None);
+    }
+
+    #[test]
+    fn test_spent_since_prorates_boundary() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_balance_snapshots_table(&conn).unwrap();
+        // $2 spent over the first day, $1 in the following 12 hours
+        for (hours, balance) in [(0, 10.0), (24, 8.0), (36, 7.0)] {
+            conn.execute(
+                "INSERT INTO station_balance_snapshots (station_id, balance, recorded_at)
+                 VALUES ('s1', ?1, ?2)",
+                params![balance, snapshot(hours, balance, None).recorded_at],
+            )
+            .unwrap();
+        }
+        let at = |hours: i64| snapshot(hours, 0.0, None).recorded_at;
+
+        // Half of the first day lies after the cutoff
+        assert_eq!(spent_since(&conn, at(12)).unwrap(), 2.0);
+        assert_eq!(spent_since(&conn, at(0)).unwrap(), 3.0);
+        assert_eq!(spent_since(&conn, at(24)).unwrap(), 1.0);
+        assert_eq!(spent_since(&conn, at(48)).unwrap(), 0.0);
+    }
+}
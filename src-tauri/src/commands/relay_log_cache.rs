@@ -0,0 +1,991 @@
+//! Local mirror of relay station logs
+//!
+//! Every log page fetched from a station is stored, and `sync_station_logs`
+//! fetches what is new since the last sync. The logs page reads the cache
+//! first, so it shows instantly, works offline and can filter on fields the
+//! station API cannot.
+//!
+//! A sync pins its end time and pages from the oldest logs to the newest, so
+//! logs arriving meanwhile cannot shift pages, and a sync cut short by the
+//! page limit resumes where it stopped.
+//!
+//! Messages, models, token names and request ids are indexed with a trigram
+//! FTS5 table, so `search_station_logs` finds any substring of three or more
+//! characters, which the NewAPI log filter cannot.
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::relay_adapters::RelayRequestRegistry;
+use super::relay_stations::{
+    create_station_adapter, LogPaginationResponse, RelayStationManager, StationLogEntry,
+};
+use crate::t;
+
+const SYNC_PAGE_SIZE: usize = 100;
+
+/// Pages fetched by one sync at most
+const MAX_SYNC_PAGES: usize = 50;
+
+/// A station's first sync goes back this far
+const INITIAL_SYNC_DAYS: i64 = 30;
+
+/// Logs from just before the cursor are fetched again, as stations may log a
+/// request a little after it happened
+const SYNC_OVERLAP_SECS: i64 = 300;
+
+/// Cached logs older than this are pruned after a sync
+const RETENTION_DAYS: i64 = 180;
+
+/// Filters applied to the cached logs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CachedLogQuery {
+    /// Part of the model name
+    pub model_name: Option<String>,
+    pub token_name: Option<String>,
+    pub group: Option<String>,
+    /// Text searched in the message, model, token name and request id
+    pub keyword: Option<String>,
+    /// Unix timestamps, inclusive
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub is_stream: Option<bool>,
+    /// Only logs charging at least this much quota
+    pub min_quota: Option<i64>,
+}
+
+/// Filters of a full-text log search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSearchFilters {
+    /// Every station when unset
+    pub station_id: Option<String>,
+    /// Part of the model name
+    pub model_name: Option<String>,
+    pub group: Option<String>,
+    /// Unix timestamps, inclusive
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub stream_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearchHit {
+    pub station_id: String,
+    #[serde(flatten)]
+    pub log: StationLogEntry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearchResults {
+    pub items: Vec<LogSearchHit>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogCacheStatus {
+    pub station_id: String,
+    pub cached_logs: i64,
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    /// Logs up to this time are cached
+    pub synced_until: Option<i64>,
+    pub last_synced_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSyncResult {
+    pub fetched: usize,
+    /// Logs that were not cached before
+    pub added: i64,
+    /// The page limit was reached; syncing again fetches the rest
+    pub complete: bool,
+    pub status: LogCacheStatus,
+}
+
+/// Every column of `station_log_cache` but `id`
+const CACHE_COLUMNS: &str = "station_id, log_id, timestamp, level, message, user_id, request_id,
+    metadata, model_name, prompt_tokens, completion_tokens, quota, token_name, use_time,
+    is_stream, channel, group_name";
+
+/// `id` is the search index's rowid, so it must survive `VACUUM`
+const CREATE_CACHE_TABLE: &str = "CREATE TABLE IF NOT EXISTS station_log_cache (
+    id INTEGER PRIMARY KEY,
+    station_id TEXT NOT NULL,
+    log_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    level TEXT NOT NULL,
+    message TEXT NOT NULL,
+    user_id TEXT,
+    request_id TEXT,
+    metadata TEXT,
+    model_name TEXT,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    quota INTEGER,
+    token_name TEXT,
+    use_time INTEGER,
+    is_stream INTEGER,
+    channel INTEGER,
+    group_name TEXT,
+    UNIQUE (station_id, log_id)
+)";
+
+pub fn create_station_log_cache_tables(conn: &Connection) -> rusqlite::Result<()> {
+    migrate_to_stable_ids(conn)?;
+    conn.execute(CREATE_CACHE_TABLE, [])?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_station_log_cache_time
+         ON station_log_cache(station_id, timestamp)",
+        [],
+    )?;
+    create_search_index(conn)?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_log_sync (
+            station_id TEXT PRIMARY KEY,
+            synced_until INTEGER NOT NULL,
+            last_synced_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Give a cache created without an `id` column one
+///
+/// The search index pointed at the implicit rowid, which `VACUUM` may
+/// renumber; the logs are copied into the current schema and the index is
+/// rebuilt on the stable `id`.
+fn migrate_to_stable_ids(conn: &Connection) -> rusqlite::Result<()> {
+    let outdated: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'station_log_cache'
+         AND NOT EXISTS (SELECT 1 FROM pragma_table_info('station_log_cache') WHERE name = 'id')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !outdated {
+        return Ok(());
+    }
+    log::info!("Migrating the station log cache to stable row ids");
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(
+        "DROP TRIGGER IF EXISTS station_log_cache_ai;
+        DROP TRIGGER IF EXISTS station_log_cache_ad;
+        DROP TRIGGER IF EXISTS station_log_cache_au;
+        DROP TABLE IF EXISTS station_log_fts;
+        DROP INDEX IF EXISTS idx_station_log_cache_time;
+        ALTER TABLE station_log_cache RENAME TO station_log_cache_old;",
+    )?;
+    tx.execute(CREATE_CACHE_TABLE, [])?;
+    tx.execute_batch(&format!(
+        "INSERT INTO station_log_cache ({columns})
+         SELECT {columns} FROM station_log_cache_old ORDER BY station_id, timestamp;
+         DROP TABLE station_log_cache_old;",
+        columns = CACHE_COLUMNS
+    ))?;
+    tx.commit()
+}
+
+/// Trigram index over the cached logs, kept in sync by triggers
+fn create_search_index(conn: &Connection) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'station_log_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS station_log_fts USING fts5(
+            message, model_name, token_name, request_id,
+            content = 'station_log_cache', content_rowid = 'id', tokenize = 'trigram'
+        );
+        CREATE TRIGGER IF NOT EXISTS station_log_cache_ai AFTER INSERT ON station_log_cache BEGIN
+            INSERT INTO station_log_fts (rowid, message, model_name, token_name, request_id)
+            VALUES (new.id, new.message, new.model_name, new.token_name, new.request_id);
+        END;
+        CREATE TRIGGER IF NOT EXISTS station_log_cache_ad AFTER DELETE ON station_log_cache BEGIN
+            INSERT INTO station_log_fts (station_log_fts, rowid, message, model_name, token_name,
+                request_id)
+            VALUES ('delete', old.id, old.message, old.model_name, old.token_name,
+                old.request_id);
+        END;
+        CREATE TRIGGER IF NOT EXISTS station_log_cache_au AFTER UPDATE ON station_log_cache BEGIN
+            INSERT INTO station_log_fts (station_log_fts, rowid, message, model_name, token_name,
+                request_id)
+            VALUES ('delete', old.id, old.message, old.model_name, old.token_name,
+                old.request_id);
+            INSERT INTO station_log_fts (rowid, message, model_name, token_name, request_id)
+            VALUES (new.id, new.message, new.model_name, new.token_name, new.request_id);
+        END;",
+    )?;
+    // Index logs cached before the index existed
+    if !exists {
+        conn.execute(
+            "INSERT INTO station_log_fts (station_log_fts) VALUES ('rebuild')",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Store fetched logs, updating cached copies; returns how many were new
+///
+/// An upsert rather than `INSERT OR REPLACE`, whose implicit deletes would
+/// not fire the trigger keeping the search index in sync.
+pub fn store_logs(
+    conn: &Connection,
+    station_id: &str,
+    logs: &[StationLogEntry],
+) -> Result<i64, String> {
+    let before = count_logs(conn, station_id)?;
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO station_log_cache (station_id, log_id, timestamp, level, message,
+             user_id, request_id, metadata, model_name, prompt_tokens, completion_tokens, quota,
+             token_name, use_time, is_stream, channel, group_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+             ON CONFLICT(station_id, log_id) DO UPDATE SET timestamp = excluded.timestamp,
+             level = excluded.level, message = excluded.message, user_id = excluded.user_id,
+             request_id = excluded.request_id, metadata = excluded.metadata,
+             model_name = excluded.model_name, prompt_tokens = excluded.prompt_tokens,
+             completion_tokens = excluded.completion_tokens, quota = excluded.quota,
+             token_name = excluded.token_name, use_time = excluded.use_time,
+             is_stream = excluded.is_stream, channel = excluded.channel,
+             group_name = excluded.group_name",
+        )
+        .map_err(|e| e.to_string())?;
+    for log in logs {
+        let metadata = log
+            .metadata
+            .as_ref()
+            .and_then(|metadata| serde_json::to_string(metadata).ok());
+        stmt.execute(params![
+            station_id,
+            log.id,
+            log.timestamp,
+            log.level,
+            log.message,
+            log.user_id,
+            log.request_id,
+            metadata,
+            log.model_name,
+            log.prompt_tokens,
+            log.completion_tokens,
+            log.quota,
+            log.token_name,
+            log.use_time,
+            log.is_stream,
+            log.channel,
+            log.group,
+        ])
+        .map_err(|e| format!("Failed to cache station logs: {}", e))?;
+    }
+    Ok(count_logs(conn, station_id)? - before)
+}
+
+fn count_logs(conn: &Connection, station_id: &str) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM station_log_cache WHERE station_id = ?1",
+        params![station_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn row_to_log(row: &rusqlite::Row) -> rusqlite::Result<StationLogEntry> {
+    let metadata: Option<String> = row.get(6)?;
+    Ok(StationLogEntry {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        level: row.get(2)?,
+        message: row.get(3)?,
+        user_id: row.get(4)?,
+        request_id: row.get(5)?,
+        metadata: metadata.and_then(|metadata| {
+            serde_json::from_str::<HashMap<String, serde_json::Value>>(&metadata).ok()
+        }),
+        model_name: row.get(7)?,
+        prompt_tokens: row.get(8)?,
+        completion_tokens: row.get(9)?,
+        quota: row.get(10)?,
+        token_name: row.get(11)?,
+        use_time: row.get(12)?,
+        is_stream: row.get(13)?,
+        channel: row.get(14)?,
+        group: row.get(15)?,
+    })
+}
+
+/// SQL conditions and their values for a query
+fn query_conditions(
+    station_id: Option<&str>,
+    query: &CachedLogQuery,
+) -> (Vec<String>, Vec<Box<dyn ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(station_id) = station_id {
+        conditions.push("station_id = ?".to_string());
+        values.push(Box::new(station_id.to_string()));
+    }
+    let like = |text: &str| format!("%{}%", text.trim());
+    if let Some(model) = query.model_name.as_deref().filter(|m| !m.trim().is_empty()) {
+        conditions.push("model_name LIKE ?".to_string());
+        values.push(Box::new(like(model)));
+    }
+    if let Some(token) = query.token_name.as_deref().filter(|t| !t.trim().is_empty()) {
+        conditions.push("token_name = ?".to_string());
+        values.push(Box::new(token.trim().to_string()));
+    }
+    if let Some(group) = query.group.as_deref().filter(|g| !g.trim().is_empty()) {
+        conditions.push("group_name = ?".to_string());
+        values.push(Box::new(group.trim().to_string()));
+    }
+    if let Some(keyword) = query.keyword.as_deref().filter(|k| !k.trim().is_empty()) {
+        push_keyword(&mut conditions, &mut values, keyword);
+    }
+    if let Some(start) = query.start_time {
+        conditions.push("timestamp >= ?".to_string());
+        values.push(Box::new(start));
+    }
+    if let Some(end) = query.end_time {
+        conditions.push("timestamp <= ?".to_string());
+        values.push(Box::new(end));
+    }
+    if let Some(is_stream) = query.is_stream {
+        conditions.push("is_stream = ?".to_string());
+        values.push(Box::new(is_stream));
+    }
+    if let Some(min_quota) = query.min_quota {
+        conditions.push("quota >= ?".to_string());
+        values.push(Box::new(min_quota));
+    }
+    (conditions, values)
+}
+
+/// Match `keyword` anywhere in the indexed text columns, without the index
+fn push_keyword(conditions: &mut Vec<String>, values: &mut Vec<Box<dyn ToSql>>, keyword: &str) {
+    conditions.push(
+        "(message LIKE ? OR model_name LIKE ? OR token_name LIKE ? OR request_id LIKE ?)"
+            .to_string(),
+    );
+    for _ in 0..4 {
+        values.push(Box::new(format!("%{}%", keyword.trim())));
+    }
+}
+
+/// Columns read by `row_to_log`, followed by the station id
+const LOG_COLUMNS: &str = "log_id, timestamp, level, message, user_id, request_id, metadata,
+    model_name, prompt_tokens, completion_tokens, quota, token_name, use_time, is_stream, channel,
+    group_name, station_id";
+
+/// Total and one page, newest first, of the cached logs matching all
+/// `conditions`, with their station ids
+fn select_logs(
+    conn: &Connection,
+    conditions: &[String],
+    mut values: Vec<Box<dyn ToSql>>,
+    page: usize,
+    page_size: usize,
+) -> Result<(i64, Vec<(String, StationLogEntry)>), String> {
+    let filter = if conditions.is_empty() {
+        "1".to_string()
+    } else {
+        conditions.join(" AND ")
+    };
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM station_log_cache WHERE {}", filter),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    values.push(Box::new(page_size as i64));
+    values.push(Box::new(((page - 1) * page_size) as i64));
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM station_log_cache WHERE {}
+             ORDER BY timestamp DESC, log_id DESC LIMIT ? OFFSET ?",
+            LOG_COLUMNS, filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let logs = stmt
+        .query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((row.get(16)?, row_to_log(row)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok((total, logs))
+}
+
+/// A page of cached logs matching `query`, newest first
+pub fn query_logs(
+    conn: &Connection,
+    station_id: &str,
+    query: &CachedLogQuery,
+    page: usize,
+    page_size: usize,
+) -> Result<LogPaginationResponse, String> {
+    let (conditions, values) = query_conditions(Some(station_id), query);
+    let (total, logs) = select_logs(conn, &conditions, values, page, page_size)?;
+    Ok(LogPaginationResponse {
+        items: logs.into_iter().map(|(_, log)| log).collect(),
+        page,
+        page_size,
+        total,
+    })
+}
+
+/// The last log a walk through the cached logs returned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogCursor {
+    pub timestamp: i64,
+    pub log_id: String,
+}
+
+impl LogCursor {
+    pub fn of(log: &StationLogEntry) -> Self {
+        Self {
+            timestamp: log.timestamp,
+            log_id: log.id.clone(),
+        }
+    }
+}
+
+/// Number of cached logs matching `query`
+pub fn count_logs_matching(
+    conn: &Connection,
+    station_id: &str,
+    query: &CachedLogQuery,
+) -> Result<i64, String> {
+    let (conditions, values) = query_conditions(Some(station_id), query);
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM station_log_cache WHERE {}",
+            conditions.join(" AND ")
+        ),
+        rusqlite::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Up to `limit` cached logs matching `query` that follow `after`, newest
+/// first; unlike an offset, the cursor costs the same however deep into a
+/// range it points
+pub fn logs_after(
+    conn: &Connection,
+    station_id: &str,
+    query: &CachedLogQuery,
+    after: Option<&LogCursor>,
+    limit: usize,
+) -> Result<Vec<StationLogEntry>, String> {
+    let (mut conditions, mut values) = query_conditions(Some(station_id), query);
+    if let Some(after) = after {
+        conditions.push("(timestamp < ? OR (timestamp = ? AND log_id < ?))".to_string());
+        values.push(Box::new(after.timestamp));
+        values.push(Box::new(after.timestamp));
+        values.push(Box::new(after.log_id.clone()));
+    }
+    values.push(Box::new(limit as i64));
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM station_log_cache WHERE {}
+             ORDER BY timestamp DESC, log_id DESC LIMIT ?",
+            LOG_COLUMNS,
+            conditions.join(" AND ")
+        ))
+        .map_err(|e| e.to_string())?;
+    let logs = stmt
+        .query_map(rusqlite::params_from_iter(values.iter()), row_to_log)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(logs)
+}
+
+/// FTS5 query matching every term of three or more characters literally,
+/// and the shorter terms, which trigrams cannot match
+fn fts_query(text: &str) -> (Option<String>, Vec<String>) {
+    let (long, short): (Vec<&str>, Vec<&str>) = text
+        .split_whitespace()
+        .partition(|term| term.chars().count() >= 3);
+    let expression = (!long.is_empty()).then(|| {
+        long.iter()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    });
+    (expression, short.into_iter().map(str::to_string).collect())
+}
+
+/// Cached logs containing every term of `text`, newest first
+pub fn search_logs(
+    conn: &Connection,
+    text: &str,
+    filters: &LogSearchFilters,
+    page: usize,
+    page_size: usize,
+) -> Result<LogSearchResults, String> {
+    let (expression, short_terms) = fts_query(text);
+    if expression.is_none() && short_terms.is_empty() {
+        return Err("Enter text to search for".to_string());
+    }
+    let query = CachedLogQuery {
+        model_name: filters.model_name.clone(),
+        group: filters.group.clone(),
+        start_time: filters.start_time,
+        end_time: filters.end_time,
+        is_stream: filters.stream_only.then_some(true),
+        ..CachedLogQuery::default()
+    };
+    let (mut conditions, mut values) = query_conditions(filters.station_id.as_deref(), &query);
+    if let Some(expression) = expression {
+        conditions.push(
+            "id IN (SELECT rowid FROM station_log_fts WHERE station_log_fts MATCH ?)".to_string(),
+        );
+        values.push(Box::new(expression));
+    }
+    for term in &short_terms {
+        push_keyword(&mut conditions, &mut values, term);
+    }
+    let (total, logs) = select_logs(conn, &conditions, values, page, page_size)?;
+    Ok(LogSearchResults {
+        items: logs
+            .into_iter()
+            .map(|(station_id, log)| LogSearchHit { station_id, log })
+            .collect(),
+        page,
+        page_size,
+        total,
+    })
+}
+
+fn synced_until(conn: &Connection, station_id: &str) -> Result<Option<i64>, String> {
+    conn.query_row(
+        "SELECT synced_until FROM station_log_sync WHERE station_id = ?1",
+        params![station_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn set_synced_until(conn: &Connection, station_id: &str, until: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO station_log_sync (station_id, synced_until, last_synced_at)
+         VALUES (?1, ?2, ?3)",
+        params![station_id, until, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| format!("Failed to save log sync state: {}", e))?;
+    Ok(())
+}
+
+pub fn cache_status(conn: &Connection, station_id: &str) -> Result<LogCacheStatus, String> {
+    let (cached_logs, oldest, newest) = conn
+        .query_row(
+            "SELECT COUNT(*), MIN(timestamp), MAX(timestamp) FROM station_log_cache
+             WHERE station_id = ?1",
+            params![station_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let sync: Option<(i64, i64)> = conn
+        .query_row(
+            "SELECT synced_until, last_synced_at FROM station_log_sync WHERE station_id = ?1",
+            params![station_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(LogCacheStatus {
+        station_id: station_id.to_string(),
+        cached_logs,
+        oldest,
+        newest,
+        synced_until: sync.map(|(until, _)| until),
+        last_synced_at: sync.map(|(_, at)| at),
+    })
+}
+
+/// Forget the cached logs of a station
+pub fn clear_cache(conn: &Connection, station_id: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM station_log_cache WHERE station_id = ?1",
+        params![station_id],
+    )
+    .map_err(|e| format!("Failed to clear the log cache: {}", e))?;
+    conn.execute(
+        "DELETE FROM station_log_sync WHERE station_id = ?1",
+        params![station_id],
+    )
+    .map_err(|e| format!("Failed to clear the log cache: {}", e))?;
+    Ok(())
+}
+
+/// Pages to fetch after the first, oldest first (the last page holds the
+/// oldest logs), and whether they reach back to the first page
+fn older_pages(total: i64, page_size: usize, max_pages: usize) -> (Vec<usize>, bool) {
+    let pages = (total.max(0) as usize).div_ceil(page_size);
+    let lowest = pages.saturating_sub(max_pages.saturating_sub(2)).max(2);
+    ((lowest..=pages).rev().collect(), lowest == 2)
+}
+
+/// Fetch the logs a station recorded since the last sync into the cache
+#[tauri::command]
+pub async fn sync_station_logs(
+    station_id: String,
+    request_id: Option<String>,
+    app: AppHandle,
+) -> Result<LogSyncResult, String> {
+    let station = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?
+    };
+    let db = app.state::<AgentDb>();
+
+    // Station log filters take UTC minutes
+    let now = chrono::Utc::now().timestamp();
+    let end = now - now.rem_euclid(60);
+    let start = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        synced_until(&conn, &station.id)?.unwrap_or(end - INITIAL_SYNC_DAYS * 24 * 3600)
+            - SYNC_OVERLAP_SECS
+    };
+    let minute = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%dT%H:%M")
+            .to_string()
+    };
+    let filters = serde_json::json!({
+        "startTime": minute(start),
+        "endTime": minute(end),
+    });
+
+    let adapter = create_station_adapter(&app, &station);
+    let requests: State<RelayRequestRegistry> = app.state();
+    let cancel = requests.begin(request_id.as_deref());
+    // The request stays registered until every page is in, whatever fails
+    let result = async {
+        let mut fetched = 0;
+        let mut added = 0;
+        let mut complete = true;
+        let mut pages = vec![1];
+        let mut index = 0;
+        while let Some(&page) = pages.get(index) {
+            let response = adapter
+                .get_logs(
+                    &station,
+                    Some(page),
+                    Some(SYNC_PAGE_SIZE),
+                    Some(filters.clone()),
+                    &cancel,
+                )
+                .await
+                .map_err(|e| t!("relay.failed_to_get_logs", "error" => &e.to_string()))?;
+            if index == 0 {
+                let (older, reaches_first) =
+                    older_pages(response.total, SYNC_PAGE_SIZE, MAX_SYNC_PAGES);
+                pages.extend(older);
+                complete = reaches_first;
+            }
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            fetched += response.items.len();
+            added += store_logs(&conn, &station.id, &response.items)?;
+            // Older pages are fetched in order, so everything up to the newest
+            // log of this page is cached; the first page is not contiguous
+            if index > 0 && !complete {
+                if let Some(newest) = response.items.iter().map(|log| log.timestamp).max() {
+                    set_synced_until(&conn, &station.id, newest)?;
+                }
+            }
+            index += 1;
+        }
+        Ok::<_, String>((fetched, added, complete))
+    }
+    .await;
+    requests.finish(request_id.as_deref(), &cancel);
+    let (fetched, added, complete) = result?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if complete {
+        set_synced_until(&conn, &station.id, end)?;
+    }
+    conn.execute(
+        "DELETE FROM station_log_cache WHERE station_id = ?1 AND timestamp < ?2",
+        params![station.id, now - RETENTION_DAYS * 24 * 3600],
+    )
+    .map_err(|e| format!("Failed to prune the log cache: {}", e))?;
+    log::info!(
+        "Synced {} logs of {} ({} new, complete: {})",
+        fetched,
+        station.name,
+        added,
+        complete
+    );
+    Ok(LogSyncResult {
+        fetched,
+        added,
+        complete,
+        status: cache_status(&conn, &station.id)?,
+    })
+}
+
+/// Cached logs of a station, newest first, filtered locally
+#[tauri::command]
+pub async fn query_cached_station_logs(
+    db: State<'_, AgentDb>,
+    station_id: String,
+    query: Option<CachedLogQuery>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<LogPaginationResponse, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_logs(
+        &conn,
+        &station_id,
+        &query.unwrap_or_default(),
+        page.unwrap_or(1).max(1),
+        page_size.unwrap_or(50).clamp(1, 500),
+    )
+}
+
+/// Search the message, model, token name and request id of cached logs;
+/// every whitespace-separated term must occur
+#[tauri::command]
+pub async fn search_station_logs(
+    db: State<'_, AgentDb>,
+    query: String,
+    filters: Option<LogSearchFilters>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<LogSearchResults, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    search_logs(
+        &conn,
+        &query,
+        &filters.unwrap_or_default(),
+        page.unwrap_or(1).max(1),
+        page_size.unwrap_or(50).clamp(1, 500),
+    )
+}
+
+#[tauri::command]
+pub async fn get_log_cache_status(
+    db: State<'_, AgentDb>,
+    station_id: String,
+) -> Result<LogCacheStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    cache_status(&conn, &station_id)
+}
+
+#[tauri::command]
+pub async fn clear_station_log_cache(
+    db: State<'_, AgentDb>,
+    station_id: String,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    clear_cache(&conn, &station_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(id: &str, timestamp: i64, model: &str, token: &str, quota: i64) -> StationLogEntry {
+        StationLogEntry {
+            id: id.to_string(),
+            timestamp,
+            level: "info".to_string(),
+            message: format!("{} via {}", model, token),
+            user_id: None,
+            request_id: Some(format!("req-{}", id)),
+            metadata: Some(HashMap::from([(
+                "ip".to_string(),
+                serde_json::json!("::1"),
+            )])),
+            model_name: Some(model.to_string()),
+            prompt_tokens: Some(10),
+            completion_tokens: Some(5),
+            quota: Some(quota),
+            token_name: Some(token.to_string()),
+            use_time: Some(1),
+            is_stream: Some(quota > 100),
+            channel: Some(3),
+            group: Some("default".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_store_and_query() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_station_log_cache_tables(&conn).unwrap();
+        let logs = vec![
+            log("1", 100, "claude-opus-4-1", "work", 500),
+            log("2", 200, "claude-sonnet-4-5", "ci", 50),
+            log("3", 300, "claude-opus-4-1", "ci", 800),
+        ];
+        assert_eq!(store_logs(&conn, "s1", &logs).unwrap(), 3);
+        // Stored again, e.g. by an overlapping sync: nothing new
+        assert_eq!(store_logs(&conn, "s1", &logs[1..]).unwrap(), 0);
+        assert_eq!(store_logs(&conn, "s2", &logs[..1]).unwrap(), 1);
+
+        let all = query_logs(&conn, "s1", &CachedLogQuery::default(), 1, 2).unwrap();
+        assert_eq!(all.total, 3);
+        let ids: Vec<&str> = all.items.iter().map(|log| log.id.as_str()).collect();
+        assert_eq!(ids, vec!["3", "2"]);
+        assert_eq!(all.items[0].metadata, logs[2].metadata);
+
+        let query = CachedLogQuery {
+            model_name: Some("opus".to_string()),
+            min_quota: Some(600),
+            ..CachedLogQuery::default()
+        };
+        let opus = query_logs(&conn, "s1", &query, 1, 10).unwrap();
+        assert_eq!(opus.total, 1);
+        assert_eq!(opus.items[0].id, "3");
+
+        let query = CachedLogQuery {
+            keyword: Some("req-2".to_string()),
+            end_time: Some(250),
+            ..CachedLogQuery::default()
+        };
+        assert_eq!(query_logs(&conn, "s1", &query, 1, 10).unwrap().total, 1);
+
+        // Walking with a cursor visits every log once, ties on time included
+        store_logs(&conn, "s1", &[log("4", 300, "claude-opus-4-1", "ci", 10)]).unwrap();
+        let query = CachedLogQuery::default();
+        let first = logs_after(&conn, "s1", &query, None, 3).unwrap();
+        let cursor = LogCursor::of(&first[2]);
+        let rest = logs_after(&conn, "s1", &query, Some(&cursor), 3).unwrap();
+        let ids: Vec<&str> = first
+            .iter()
+            .chain(&rest)
+            .map(|log| log.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["4", "3", "2", "1"]);
+        assert_eq!(count_logs_matching(&conn, "s1", &query).unwrap(), 4);
+
+        let status = cache_status(&conn, "s1").unwrap();
+        assert_eq!((status.oldest, status.newest), (Some(100), Some(300)));
+        clear_cache(&conn, "s1").unwrap();
+        assert_eq!(cache_status(&conn, "s1").unwrap().cached_logs, 0);
+        assert_eq!(cache_status(&conn, "s2").unwrap().cached_logs, 1);
+    }
+
+    #[test]
+    fn test_search() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_station_log_cache_tables(&conn).unwrap();
+        let mut failed = log("1", 100, "claude-opus-4-1", "work", 500);
+        failed.message = "upstream error: 上游负载已饱和".to_string();
+        let logs = vec![
+            failed,
+            log("2", 200, "claude-sonnet-4-5", "ci", 50),
+            log("3", 300, "claude-opus-4-1", "ci", 800),
+        ];
+        store_logs(&conn, "s1", &logs).unwrap();
+        store_logs(&conn, "s2", &logs[2..]).unwrap();
+
+        let all = LogSearchFilters::default();
+        let search = |text: &str, filters: &LogSearchFilters| {
+            search_logs(&conn, text, filters, 1, 10).unwrap()
+        };
+        assert_eq!(search("负载已饱和", &all).total, 1);
+        assert_eq!(search("UPSTREAM err", &all).items[0].log.id, "1");
+        let opus = search("opus", &all);
+        assert_eq!(opus.total, 3);
+        assert_eq!(opus.items[0].log.id, "3");
+
+        let filtered = LogSearchFilters {
+            station_id: Some("s2".to_string()),
+            stream_only: true,
+            ..LogSearchFilters::default()
+        };
+        assert_eq!(search("opus", &filtered).total, 1);
+        assert!(search_logs(&conn, "  ", &all, 1, 10).is_err());
+
+        // Updated and deleted logs leave the index
+        let mut edited = logs[1].clone();
+        edited.message = "rate limited".to_string();
+        store_logs(&conn, "s1", &[edited]).unwrap();
+        assert_eq!(search("rate limited", &all).total, 1);
+        assert_eq!(search("sonnet via", &all).total, 0);
+        clear_cache(&conn, "s1").unwrap();
+        assert_eq!(search("opus", &all).total, 1);
+    }
+
+    #[test]
+    fn test_migrate_to_stable_ids() {
+        let conn = Connection::open_in_memory().unwrap();
+        // The schema before `id`, indexed on the implicit rowid
+        conn.execute_batch(
+            "CREATE TABLE station_log_cache (
+                station_id TEXT NOT NULL, log_id TEXT NOT NULL, timestamp INTEGER NOT NULL,
+                level TEXT NOT NULL, message TEXT NOT NULL, user_id TEXT, request_id TEXT,
+                metadata TEXT, model_name TEXT, prompt_tokens INTEGER, completion_tokens INTEGER,
+                quota INTEGER, token_name TEXT, use_time INTEGER, is_stream INTEGER,
+                channel INTEGER, group_name TEXT, PRIMARY KEY (station_id, log_id)
+            );
+            CREATE VIRTUAL TABLE station_log_fts USING fts5(
+                message, model_name, token_name, request_id,
+                content = 'station_log_cache', tokenize = 'trigram'
+            );
+            INSERT INTO station_log_cache (station_id, log_id, timestamp, level, message,
+                model_name) VALUES
+                ('s1', '1', 100, 'api', 'upstream timeout', 'claude-opus-4-1'),
+                ('s1', '2', 200, 'api', 'quota exhausted', 'claude-sonnet-4-5');
+            INSERT INTO station_log_fts (station_log_fts) VALUES ('rebuild');",
+        )
+        .unwrap();
+
+        create_station_log_cache_tables(&conn).unwrap();
+        // Idempotent once migrated
+        create_station_log_cache_tables(&conn).unwrap();
+        let all = LogSearchFilters::default();
+        assert_eq!(cache_status(&conn, "s1").unwrap().cached_logs, 2);
+        assert_eq!(
+            search_logs(&conn, "timeout", &all, 1, 10).unwrap().items[0]
+                .log
+                .id,
+            "1"
+        );
+
+        // Deleting a row and vacuuming keeps the index pointing at the right logs
+        conn.execute("DELETE FROM station_log_cache WHERE log_id = '1'", [])
+            .unwrap();
+        conn.execute("VACUUM", []).unwrap();
+        store_logs(&conn, "s1", &[log("3", 300, "claude-haiku-4-5", "ci", 5)]).unwrap();
+        let exhausted = search_logs(&conn, "exhausted", &all, 1, 10).unwrap();
+        assert_eq!(exhausted.total, 1);
+        assert_eq!(exhausted.items[0].log.id, "2");
+        assert_eq!(search_logs(&conn, "timeout", &all, 1, 10).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_older_pages() {
+        assert_eq!(older_pages(0, 100, 50), (vec![], true));
+        assert_eq!(older_pages(100, 100, 50), (vec![], true));
+        assert_eq!(older_pages(250, 100, 50), (vec![3, 2], true));
+        let (pages, complete) = older_pages(10_000, 100, 50);
+        assert!(!complete);
+        assert_eq!(pages.len(), 49);
+        assert_eq!((pages[0], pages[48]), (100, 52));
+    }
+}
@@ -0,0 +1,324 @@
+//! Export of relay station logs to JSONL or CSV files
+//!
+//! Pages are written as they arrive, so exports of any size use little
+//! memory. The file is first written next to the target with a `.part`
+//! suffix and only renamed once complete.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::relay_adapters::RelayRequestRegistry;
+use super::relay_log_stats::LogTimeRange;
+use super::relay_reconcile::station_quota_per_unit;
+use super::relay_stations::{create_station_adapter, RelayStationManager, StationLogEntry};
+use crate::t;
+
+const EXPORT_PAGE_SIZE: usize = 100;
+
+/// Stop after this many pages, a million logs
+const MAX_EXPORT_PAGES: usize = 10_000;
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "time",
+    "model_name",
+    "token_name",
+    "group",
+    "prompt_tokens",
+    "completion_tokens",
+    "quota",
+    "cost_usd",
+    "use_time",
+    "is_stream",
+    "channel",
+    "request_id",
+    "level",
+    "message",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogExportResult {
+    pub file_path: String,
+    pub format: LogExportFormat,
+    pub exported: usize,
+    /// Sum of the exported logs' charges
+    pub cost_usd: f64,
+    /// More logs existed than `MAX_EXPORT_PAGES` allows
+    pub truncated: bool,
+}
+
+/// Emitted after every page as `station-logs-export-progress`
+#[derive(Debug, Clone, Serialize)]
+struct ExportProgress<'a> {
+    station_id: &'a str,
+    exported: usize,
+    total: i64,
+}
+
+#[derive(Serialize)]
+struct JsonlLog<'a> {
+    #[serde(flatten)]
+    log: &'a StationLogEntry,
+    cost_usd: f64,
+}
+
+/// A CSV field, quoted when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+fn csv_row(log: &StationLogEntry, cost_usd: f64) -> String {
+    let time = DateTime::<Utc>::from_timestamp(log.timestamp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+    [
+        log.id.clone(),
+        time,
+        optional(&log.model_name),
+        optional(&log.token_name),
+        optional(&log.group),
+        optional(&log.prompt_tokens),
+        optional(&log.completion_tokens),
+        optional(&log.quota),
+        format!("{:.6}", cost_usd),
+        optional(&log.use_time),
+        optional(&log.is_stream),
+        optional(&log.channel),
+        optional(&log.request_id),
+        log.level.clone(),
+        log.message.clone(),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Write logs in `format`; returns their summed cost
+pub fn write_logs(
+    writer: &mut impl Write,
+    logs: &[StationLogEntry],
+    format: LogExportFormat,
+    quota_per_unit: f64,
+) -> std::io::Result<f64> {
+    let mut cost = 0.0;
+    for log in logs {
+        let cost_usd = log.quota.unwrap_or(0) as f64 / quota_per_unit;
+        cost += cost_usd;
+        match format {
+            LogExportFormat::Jsonl => {
+                serde_json::to_writer(&mut *writer, &JsonlLog { log, cost_usd })?;
+                writeln!(writer)?;
+            }
+            LogExportFormat::Csv => writeln!(writer, "{}", csv_row(log, cost_usd))?,
+        }
+    }
+    Ok(cost)
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Write every log of a station within `range` to `file_path`
+///
+/// Progress is emitted as `station-logs-export-progress`; pass `request_id`
+/// to be able to cancel with `cancel_relay_request`.
+#[tauri::command]
+pub async fn export_station_logs(
+    station_id: String,
+    range: Option<LogTimeRange>,
+    format: LogExportFormat,
+    file_path: String,
+    request_id: Option<String>,
+    app: AppHandle,
+) -> Result<LogExportResult, String> {
+    let (start, end) = range.unwrap_or_default().resolve()?;
+    let station = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?
+    };
+    let adapter = create_station_adapter(&app, &station);
+    let quota_per_unit = station_quota_per_unit(adapter.as_ref(), &station).await;
+
+    let path = PathBuf::from(&file_path);
+    let part = part_path(&path);
+    let file =
+        File::create(&part).map_err(|e| format!("Failed to create {}: {}", part.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", part.display(), e);
+    if format == LogExportFormat::Csv {
+        writeln!(writer, "{}", CSV_HEADER.join(",")).map_err(write_error)?;
+    }
+
+    // Station log filters take UTC minutes
+    let filters = serde_json::json!({
+        "startTime": start.format("%Y-%m-%dT%H:%M").to_string(),
+        "endTime": end.format("%Y-%m-%dT%H:%M").to_string(),
+    });
+    let requests: State<RelayRequestRegistry> = app.state();
+    let cancel = requests.begin(request_id.as_deref());
+    let mut exported = 0;
+    let mut cost_usd = 0.0;
+    let mut truncated = false;
+    let mut failure = None;
+    for page in 1..=MAX_EXPORT_PAGES {
+        let response = match adapter
+            .get_logs(
+                &station,
+                Some(page),
+                Some(EXPORT_PAGE_SIZE),
+                Some(filters.clone()),
+                &cancel,
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                failure = Some(t!("relay.failed_to_get_logs", "error" => &e.to_string()));
+                break;
+            }
+        };
+        let fetched = response.items.len();
+        let logs: Vec<StationLogEntry> = response
+            .items
+            .into_iter()
+            .filter(|log| log.timestamp >= start.timestamp() && log.timestamp <= end.timestamp())
+            .collect();
+        match write_logs(&mut writer, &logs, format, quota_per_unit) {
+            Ok(cost) => cost_usd += cost,
+            Err(e) => {
+                failure = Some(write_error(e));
+                break;
+            }
+        }
+        exported += logs.len();
+        let _ = app.emit(
+            "station-logs-export-progress",
+            ExportProgress {
+                station_id: &station_id,
+                exported,
+                total: response.total,
+            },
+        );
+        if fetched < EXPORT_PAGE_SIZE || (page * EXPORT_PAGE_SIZE) as i64 >= response.total {
+            break;
+        }
+        truncated = page == MAX_EXPORT_PAGES;
+    }
+    requests.finish(request_id.as_deref(), &cancel);
+
+    let finished = match failure {
+        Some(e) => Err(e),
+        None => writer
+            .flush()
+            .map_err(write_error)
+            .and_then(|_| std::fs::rename(&part, &path).map_err(write_error)),
+    };
+    if let Err(e) = finished {
+        let _ = std::fs::remove_file(&part);
+        return Err(e);
+    }
+    log::info!(
+        "Exported {} logs of {} to {}",
+        exported,
+        station.name,
+        path.display()
+    );
+    Ok(LogExportResult {
+        file_path,
+        format,
+        exported,
+        cost_usd,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(id: &str, message: &str, quota: Option<i64>) -> StationLogEntry {
+        StationLogEntry {
+            id: id.to_string(),
+            timestamp: 1_792_164_600,
+            level: "info".to_string(),
+            message: message.to_string(),
+            user_id: None,
+            request_id: None,
+            metadata: None,
+            model_name: Some("claude-opus-4-1".to_string()),
+            prompt_tokens: Some(100),
+            completion_tokens: Some(10),
+            quota,
+            token_name: Some("work".to_string()),
+            use_time: Some(2),
+            is_stream: Some(true),
+            channel: None,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn test_write_logs() {
+        let logs = vec![
+            log("1", "plain", Some(250_000)),
+            log("2", "say \"hi\", then\nstop", None),
+        ];
+        let mut csv = Vec::new();
+        let cost = write_logs(&mut csv, &logs, LogExportFormat::Csv, 500_000.0).unwrap();
+        assert_eq!(cost, 0.5);
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(
+            "1,2026-10-16T15:30:00+00:00,claude-opus-4-1,work,,100,10,250000,0.500000,2,true,,,info,plain\n"
+        ));
+        assert!(csv.ends_with(",info,\"say \"\"hi\"\", then\nstop\"\n"));
+
+        let mut jsonl = Vec::new();
+        write_logs(&mut jsonl, &logs, LogExportFormat::Jsonl, 500_000.0).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["cost_usd"], 0.5);
+        assert_eq!(lines[1]["message"], "say \"hi\", then\nstop");
+
+        assert_eq!(
+            part_path(Path::new("/tmp/logs.csv")),
+            PathBuf::from("/tmp/logs.csv.part")
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Grouped sums of relay station logs for the charts view
+//!
+//! Logs are paged through in the backend and only the sums per model, hour,
+//! day or token name are returned, so the webview never holds raw entries.
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::currency::Converted;
+use super::relay_adapters::RelayRequestRegistry;
+use super::relay_reconcile::{fetch_station_logs, parse_time, station_quota_per_unit};
+use super::relay_stations::{create_station_adapter, RelayStationManager, StationLogEntry};
+use crate::t;
+
+/// Default range when none is given
+const DEFAULT_RANGE_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogGroupBy {
+    Model,
+    /// Local hours
+    Hour,
+    /// Local days
+    Day,
+    /// API key name
+    TokenName,
+}
+
+/// Time range of the logs; RFC 3339, the end defaults to now
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogTimeRange {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+impl LogTimeRange {
+    /// Start and end, defaulting to the last 7 days
+    pub fn resolve(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+        let end = match self.end_time.as_deref() {
+            Some(end_time) => parse_time(end_time)?,
+            None => Utc::now(),
+        };
+        let start = match self.start_time.as_deref() {
+            Some(start_time) => parse_time(start_time)?,
+            None => end - Duration::days(DEFAULT_RANGE_DAYS),
+        };
+        if end <= start {
+            return Err("The end time must be after the start time".to_string());
+        }
+        Ok((start, end))
+    }
+}
+
+/// Sums of the logs of one group
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogGroup {
+    /// Model, token name, "YYYY-MM-DD HH:00" or "YYYY-MM-DD"
+    pub key: String,
+    /// Unix timestamp the hour or day starts at, for time groups
+    pub period_start: Option<i64>,
+    pub requests: u64,
+    pub stream_requests: u64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub quota: i64,
+    pub cost_usd: f64,
+    /// Average response time in seconds, of the logs reporting one
+    pub avg_use_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationLogAggregate {
+    pub station_id: String,
+    pub group_by: LogGroupBy,
+    pub start: i64,
+    pub end: i64,
+    pub quota_per_unit: f64,
+    pub total_requests: u64,
+    pub total_quota: i64,
+    pub cost_usd: f64,
+    /// More logs existed than were fetched; the sums are incomplete
+    pub logs_truncated: bool,
+    /// Time groups oldest first, others by cost, largest first
+    pub groups: Vec<LogGroup>,
+}
+
+/// Group key and period start of a log
+fn group_key<Tz: TimeZone>(
+    log: &StationLogEntry,
+    group_by: LogGroupBy,
+    tz: &Tz,
+) -> Option<(String, Option<i64>)> {
+    let named = |name: &Option<String>| {
+        let name = name.as_deref().map(str::trim).unwrap_or_default();
+        if name.is_empty() {
+            "unknown".to_string()
+        } else {
+            name.to_string()
+        }
+    };
+    match group_by {
+        LogGroupBy::Model => Some((named(&log.model_name), None)),
+        LogGroupBy::TokenName => Some((named(&log.token_name), None)),
+        LogGroupBy::Hour | LogGroupBy::Day => {
+            let local = tz.timestamp_opt(log.timestamp, 0).single()?.naive_local();
+            let (start, format) = if group_by == LogGroupBy::Hour {
+                (
+                    local.date().and_hms_opt(local.hour(), 0, 0)?,
+                    "%Y-%m-%d %H:00",
+                )
+            } else {
+                (local.date().and_time(NaiveTime::MIN), "%Y-%m-%d")
+            };
+            let start_time = tz.from_local_datetime(&start).earliest()?;
+            Some((
+                start.format(format).to_string(),
+                Some(start_time.timestamp()),
+            ))
+        }
+    }
+}
+
+/// Sum consumption logs (those with a model) per group
+pub fn aggregate<Tz: TimeZone>(
+    logs: &[StationLogEntry],
+    group_by: LogGroupBy,
+    quota_per_unit: f64,
+    tz: &Tz,
+) -> Vec<LogGroup> {
+    let mut groups: HashMap<String, LogGroup> = HashMap::new();
+    let mut use_times: HashMap<String, (i64, u64)> = HashMap::new();
+    for log in logs.iter().filter(|log| log.model_name.is_some()) {
+        let Some((key, period_start)) = group_key(log, group_by, tz) else {
+            continue;
+        };
+        let group = groups.entry(key.clone()).or_insert_with(|| LogGroup {
+            key: key.clone(),
+            period_start,
+            ..LogGroup::default()
+        });
+        let quota = log.quota.unwrap_or(0);
+        group.requests += 1;
+        if log.is_stream == Some(true) {
+            group.stream_requests += 1;
+        }
+        group.prompt_tokens += log.prompt_tokens.unwrap_or(0);
+        group.completion_tokens += log.completion_tokens.unwrap_or(0);
+        group.quota += quota;
+        group.cost_usd += quota as f64 / quota_per_unit;
+        if let Some(use_time) = log.use_time {
+            let (total, count) = use_times.entry(key).or_default();
+            *total += use_time;
+            *count += 1;
+        }
+    }
+
+    let mut groups: Vec<LogGroup> = groups
+        .into_values()
+        .map(|mut group| {
+            group.avg_use_time = use_times
+                .get(&group.key)
+                .map(|(total, count)| *total as f64 / *count as f64);
+            group
+        })
+        .collect();
+    match group_by {
+        LogGroupBy::Hour | LogGroupBy::Day => groups.sort_by_key(|group| group.period_start),
+        LogGroupBy::Model | LogGroupBy::TokenName => groups.sort_by(|a, b| {
+            b.cost_usd
+                .total_cmp(&a.cost_usd)
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+    }
+    groups
+}
+
+/// Sum a station's logs per model, hour, day or token name
+///
+/// The range defaults to the last 7 days. Pass `request_id` to be able to
+/// cancel paging with `cancel_relay_request`.
+#[tauri::command]
+pub async fn aggregate_station_logs(
+    station_id: String,
+    group_by: LogGroupBy,
+    range: Option<LogTimeRange>,
+    request_id: Option<String>,
+    app: AppHandle,
+) -> Result<Converted<StationLogAggregate>, String> {
+    let (start, end) = range.unwrap_or_default().resolve()?;
+
+    let station = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?
+    };
+    let adapter = create_station_adapter(&app, &station);
+    let quota_per_unit = station_quota_per_unit(adapter.as_ref(), &station).await;
+
+    let requests: State<RelayRequestRegistry> = app.state();
+    let cancel = requests.begin(request_id.as_deref());
+    let fetched = fetch_station_logs(adapter.as_ref(), &station, start, end, &cancel).await;
+    requests.finish(request_id.as_deref(), &cancel);
+    let (logs, logs_truncated) =
+        fetched.map_err(|e| t!("relay.failed_to_get_logs", "error" => &e.to_string()))?;
+
+    let groups = aggregate(&logs, group_by, quota_per_unit, &chrono::Local);
+    Ok(Converted(StationLogAggregate {
+        station_id,
+        group_by,
+        start: start.timestamp(),
+        end: end.timestamp(),
+        quota_per_unit,
+        total_requests: groups.iter().map(|group| group.requests).sum(),
+        total_quota: groups.iter().map(|group| group.quota).sum(),
+        cost_usd: groups.iter().map(|group| group.cost_usd).sum(),
+        logs_truncated,
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn log(timestamp: i64, model: Option<&str>, token: &str, quota: i64) -> StationLogEntry {
+        StationLogEntry {
+            id: timestamp.to_string(),
+            timestamp,
+            level: "info".to_string(),
+            message: String::new(),
+            user_id: None,
+            request_id: None,
+            metadata: None,
+            model_name: model.map(str::to_string),
+            prompt_tokens: Some(100),
+            completion_tokens: Some(10),
+            quota: Some(quota),
+            token_name: Some(token.to_string()),
+            use_time: Some(2),
+            is_stream: Some(true),
+            channel: None,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        // 2026-10-16 23:30 and 2026-10-17 00:10 in UTC+8
+        let late = 1_792_164_600;
+        let logs = vec![
+            log(late, Some("claude-opus-4-1"), "work", 500_000),
+            log(late + 2400, Some("claude-sonnet-4-5"), "work", 250_000),
+            log(late + 2700, Some("claude-opus-4-1"), "ci", 1_000_000),
+            // Top-ups and other logs without a model are left out
+            log(late + 2800, None, "work", 9_000_000),
+        ];
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+
+        let models = aggregate(&logs, LogGroupBy::Model, 500_000.0, &tz);
+        assert_eq!(models[0].key, "claude-opus-4-1");
+        assert_eq!(models[0].requests, 2);
+        assert_eq!(models[0].cost_usd, 3.0);
+        assert_eq!(models[1].prompt_tokens, 100);
+
+        let days = aggregate(&logs, LogGroupBy::Day, 500_000.0, &tz);
+        let keys: Vec<&str> = days.iter().map(|group| group.key.as_str()).collect();
+        assert_eq!(keys, vec!["2026-10-16", "2026-10-17"]);
+        assert_eq!(days[1].requests, 2);
+        assert_eq!(days[1].period_start, Some(late + 1800));
+
+        let hours = aggregate(&logs, LogGroupBy::Hour, 500_000.0, &tz);
+        assert_eq!(hours[0].key, "2026-10-16 23:00");
+        assert_eq!(hours[1].avg_use_time, Some(2.0));
+
+        let tokens = aggregate(&logs, LogGroupBy::TokenName, 500_000.0, &tz);
+        assert_eq!(tokens[0].key, "ci");
+        assert_eq!(tokens[1].stream_requests, 2);
+    }
+}
@@ -0,0 +1,217 @@
+//! Streaming of cached relay station logs to the frontend
+//!
+//! Large ranges (a month of logs and more) are sent page by page over a Tauri
+//! channel instead of as one JSON response. The frontend acknowledges pages
+//! as it renders or writes them; at most `window` pages are in flight, so a
+//! busy webview slows the stream down instead of being flooded.
+//!
+//! A stream runs under a request id and is stopped with
+//! `cancel_relay_request`, like other long relay requests.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Semaphore;
+
+use super::agents::AgentDb;
+use super::relay_adapters::{CancellationToken, RelayRequestRegistry};
+use super::relay_log_cache::{self, CachedLogQuery, LogCursor};
+use super::relay_stations::StationLogEntry;
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+
+/// Unacknowledged pages allowed by default
+const DEFAULT_WINDOW: usize = 4;
+
+/// A stream whose pages go unacknowledged this long is given up, e.g. after
+/// the webview reloaded
+const ACK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What a stream sends over its channel
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogStreamEvent {
+    /// Sent first, with the number of logs the stream will send
+    Started { total: i64, page_size: usize },
+    /// A page of logs, newest first; acknowledge it with `ack_log_stream`
+    Page {
+        index: usize,
+        items: Vec<StationLogEntry>,
+    },
+    /// Sent last
+    Finished { sent: usize, cancelled: bool },
+}
+
+/// How a stream ended, also the result of the command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogStreamSummary {
+    pub total: i64,
+    pub sent: usize,
+    pub pages: usize,
+    pub cancelled: bool,
+}
+
+/// Credit of the running streams, keyed by request id
+#[derive(Default)]
+pub struct LogStreamRegistry {
+    credits: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl LogStreamRegistry {
+    fn open(&self, stream_id: &str, window: usize) -> Arc<Semaphore> {
+        let credit = Arc::new(Semaphore::new(window));
+        if let Ok(mut credits) = self.credits.lock() {
+            credits.insert(stream_id.to_string(), credit.clone());
+        }
+        credit
+    }
+
+    /// Forget a stream, unless its id was already taken by a newer one
+    fn close(&self, stream_id: &str, credit: &Arc<Semaphore>) {
+        if let Ok(mut credits) = self.credits.lock() {
+            if credits
+                .get(stream_id)
+                .is_some_and(|current| Arc::ptr_eq(current, credit))
+            {
+                credits.remove(stream_id);
+            }
+        }
+    }
+
+    fn ack(&self, stream_id: &str, pages: usize) -> bool {
+        match self.credits.lock() {
+            Ok(credits) => match credits.get(stream_id) {
+                Some(credit) => {
+                    credit.add_permits(pages);
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
+
+/// Wait until a page may be sent; false once the stream is cancelled
+async fn take_credit(credit: &Semaphore, cancel: &CancellationToken) -> Result<bool, String> {
+    if cancel.is_cancelled() {
+        return Ok(false);
+    }
+    tokio::select! {
+        permit = tokio::time::timeout(ACK_TIMEOUT, credit.acquire()) => match permit {
+            Ok(Ok(permit)) => {
+                // Returned by `ack_log_stream`, not on drop
+                permit.forget();
+                Ok(true)
+            }
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("The log stream was not read for too long".to_string()),
+        },
+        _ = cancel.cancelled() => Ok(false),
+    }
+}
+
+async fn run_stream(
+    app: &AppHandle,
+    station_id: &str,
+    query: &CachedLogQuery,
+    page_size: usize,
+    credit: &Semaphore,
+    cancel: &CancellationToken,
+    on_event: &Channel<LogStreamEvent>,
+) -> Result<LogStreamSummary, String> {
+    let send = |event: LogStreamEvent| {
+        on_event
+            .send(event)
+            .map_err(|e| format!("Failed to send logs: {}", e))
+    };
+    let total = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        relay_log_cache::count_logs_matching(&conn, station_id, query)?
+    };
+    send(LogStreamEvent::Started { total, page_size })?;
+
+    let mut summary = LogStreamSummary {
+        total,
+        sent: 0,
+        pages: 0,
+        cancelled: false,
+    };
+    let mut cursor: Option<LogCursor> = None;
+    loop {
+        if !take_credit(credit, cancel).await? {
+            summary.cancelled = true;
+            break;
+        }
+        // The lock is only held for one page, so other commands keep going
+        let items = {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            relay_log_cache::logs_after(&conn, station_id, query, cursor.as_ref(), page_size)?
+        };
+        if items.is_empty() {
+            break;
+        }
+        let last_page = items.len() < page_size;
+        cursor = items.last().map(LogCursor::of);
+        summary.sent += items.len();
+        send(LogStreamEvent::Page {
+            index: summary.pages,
+            items,
+        })?;
+        summary.pages += 1;
+        if last_page {
+            break;
+        }
+    }
+    send(LogStreamEvent::Finished {
+        sent: summary.sent,
+        cancelled: summary.cancelled,
+    })?;
+    Ok(summary)
+}
+
+/// Stream the cached logs of a station matching `query`, newest first;
+/// resolves once the last page was sent
+#[tauri::command]
+pub async fn stream_cached_station_logs(
+    app: AppHandle,
+    station_id: String,
+    query: Option<CachedLogQuery>,
+    page_size: Option<usize>,
+    window: Option<usize>,
+    request_id: String,
+    on_event: Channel<LogStreamEvent>,
+) -> Result<LogStreamSummary, String> {
+    let query = query.unwrap_or_default();
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, 5000);
+    let window = window.unwrap_or(DEFAULT_WINDOW).clamp(1, 64);
+
+    let requests: State<RelayRequestRegistry> = app.state();
+    let streams: State<LogStreamRegistry> = app.state();
+    let cancel = requests.begin(Some(&request_id));
+    let credit = streams.open(&request_id, window);
+    let result = run_stream(
+        &app,
+        &station_id,
+        &query,
+        page_size,
+        &credit,
+        &cancel,
+        &on_event,
+    )
+    .await;
+    streams.close(&request_id, &credit);
+    requests.finish(Some(&request_id), &cancel);
+    result
+}
+
+/// Acknowledge pages of a stream, letting it send as many more
+#[tauri::command]
+pub fn ack_log_stream(app: AppHandle, request_id: String, pages: Option<usize>) -> bool {
+    let streams: State<LogStreamRegistry> = app.state();
+    streams.ack(&request_id, pages.unwrap_or(1).clamp(1, 64))
+}
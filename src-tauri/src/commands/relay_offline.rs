@@ -0,0 +1,395 @@
+//! Offline mode for relay station data
+//!
+//! Station info, user info, token lists, logs and groups are kept in SQLite
+//! whenever they are fetched. In offline mode, or when a station cannot be
+//! reached, those commands answer from this cache instead of failing; the
+//! answer then carries a `cached` field saying how old it is and why it was
+//! used. Token keys are masked before they are kept.
+use anyhow::anyhow;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::future::Future;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::provider;
+use crate::t;
+
+/// app_settings key storing the offline settings (JSON)
+pub const OFFLINE_SETTINGS_KEY: &str = "offline_mode_settings";
+
+/// Cache kind of token lists, whose keys are masked before they are kept
+const TOKENS_KIND: &str = "tokens";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OfflineSettings {
+    /// Never contact stations; answer from the cache
+    pub enabled: bool,
+    /// Answer from the cache when a station cannot be reached
+    pub fallback_when_unreachable: bool,
+}
+
+impl Default for OfflineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fallback_when_unreachable: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheReason {
+    Offline,
+    Unreachable,
+}
+
+/// How old a cached answer is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Staleness {
+    pub fetched_at: i64,
+    pub age_secs: i64,
+    pub reason: CacheReason,
+}
+
+/// A command result, fresh or from the cache
+///
+/// Serialized as the result itself, with a `cached` field added to objects
+/// answered from the cache.
+#[derive(Debug)]
+pub struct Cached<T> {
+    pub data: T,
+    pub cached: Option<Staleness>,
+}
+
+impl<T> Cached<T> {
+    pub fn fresh(data: T) -> Self {
+        Self { data, cached: None }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Cached<U> {
+        Cached {
+            data: f(self.data),
+            cached: self.cached,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Cached<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(&self.data).map_err(serde::ser::Error::custom)?;
+        if let (Some(cached), Some(object)) = (&self.cached, value.as_object_mut()) {
+            let cached = serde_json::to_value(cached).map_err(serde::ser::Error::custom)?;
+            object.insert("cached".to_string(), cached);
+        }
+        value.serialize(serializer)
+    }
+}
+
+pub fn create_response_cache_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_response_cache (
+            station_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            request TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            PRIMARY KEY (station_id, kind, request)
+        )",
+        [],
+    )?;
+    mask_cached_tokens(conn)
+}
+
+/// Hide the keys in a response about to be kept
+fn redact(kind: &str, data: &mut Value) {
+    if kind != TOKENS_KIND {
+        return;
+    }
+    let items = data.get_mut("items").and_then(Value::as_array_mut);
+    for token in items.into_iter().flatten() {
+        if let Some(key) = token.get_mut("token") {
+            if let Some(secret) = key.as_str().filter(|secret| !provider::is_masked(secret)) {
+                *key = Value::from(provider::mask_secret(secret));
+            }
+        }
+    }
+}
+
+/// Mask the keys of token lists kept before they were masked
+fn mask_cached_tokens(conn: &Connection) -> rusqlite::Result<()> {
+    let rows: Vec<(String, String, String)> = conn
+        .prepare("SELECT station_id, request, payload FROM station_response_cache WHERE kind = ?1")?
+        .query_map(params![TOKENS_KIND], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    for (station_id, request, payload) in rows {
+        let Ok(mut data) = serde_json::from_str::<Value>(&payload) else {
+            continue;
+        };
+        redact(TOKENS_KIND, &mut data);
+        let masked = data.to_string();
+        if masked != payload {
+            conn.execute(
+                "UPDATE station_response_cache SET payload = ?1
+                 WHERE station_id = ?2 AND kind = ?3 AND request = ?4",
+                params![masked, station_id, TOKENS_KIND, request],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep a response; `request` tells apart pages, filters and users
+pub fn store_response<T: Serialize>(
+    conn: &Connection,
+    station_id: &str,
+    kind: &str,
+    request: &str,
+    data: &T,
+    fetched_at: i64,
+) -> Result<(), String> {
+    let mut data = serde_json::to_value(data).map_err(|e| e.to_string())?;
+    redact(kind, &mut data);
+    let payload = data.to_string();
+    conn.execute(
+        "INSERT OR REPLACE INTO station_response_cache (station_id, kind, request, payload, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![station_id, kind, request, payload, fetched_at],
+    )
+    .map_err(|e| format!("Failed to cache station response: {}", e))?;
+    Ok(())
+}
+
+/// A kept response and when it was fetched
+pub fn load_response<T: DeserializeOwned>(
+    conn: &Connection,
+    station_id: &str,
+    kind: &str,
+    request: &str,
+) -> Result<Option<(T, i64)>, String> {
+    let row: Option<(String, i64)> = conn
+        .query_row(
+            "SELECT payload, fetched_at FROM station_response_cache
+             WHERE station_id = ?1 AND kind = ?2 AND request = ?3",
+            params![station_id, kind, request],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    // A response whose shape changed since is as good as none
+    Ok(row.and_then(|(payload, fetched_at)| {
+        serde_json::from_str(&payload)
+            .ok()
+            .map(|data| (data, fetched_at))
+    }))
+}
+
+pub fn clear_station(conn: &Connection, station_id: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM station_response_cache WHERE station_id = ?1",
+        params![station_id],
+    )
+    .map_err(|e| format!("Failed to clear cached station responses: {}", e))?;
+    Ok(())
+}
+
+/// Whether an adapter error means the station could not be reached, rather
+/// than that it answered with an error
+pub fn is_network_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+    })
+}
+
+pub fn load_offline_settings(conn: &Connection) -> OfflineSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![OFFLINE_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn settings(app: &AppHandle) -> OfflineSettings {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock();
+    conn.map(|conn| load_offline_settings(&conn))
+        .unwrap_or_default()
+}
+
+/// Whether stations should not be contacted at all
+pub fn is_offline(app: &AppHandle) -> bool {
+    settings(app).enabled
+}
+
+fn from_cache<T: DeserializeOwned>(
+    app: &AppHandle,
+    station_id: &str,
+    kind: &str,
+    request: &str,
+    reason: CacheReason,
+) -> Result<Option<Cached<T>>, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp();
+    Ok(
+        load_response(&conn, station_id, kind, request)?.map(|(data, fetched_at)| Cached {
+            data,
+            cached: Some(Staleness {
+                fetched_at,
+                age_secs: (now - fetched_at).max(0),
+                reason,
+            }),
+        }),
+    )
+}
+
+/// Run `fetch` and keep its answer, or answer from the cache when offline or
+/// when the station cannot be reached
+pub async fn fetch_or_cached<T, F>(
+    app: &AppHandle,
+    station_id: &str,
+    kind: &str,
+    request: &str,
+    fetch: F,
+) -> anyhow::Result<Cached<T>>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let settings = settings(app);
+    if settings.enabled {
+        return from_cache(app, station_id, kind, request, CacheReason::Offline)
+            .map_err(|e| anyhow!(e))?
+            .ok_or_else(|| anyhow!(t!("offline.no_cached_data")));
+    }
+
+    match fetch.await {
+        Ok(data) => {
+            let db = app.state::<AgentDb>();
+            if let Ok(conn) = db.0.lock() {
+                let now = chrono::Utc::now().timestamp();
+                if let Err(e) = store_response(&conn, station_id, kind, request, &data, now) {
+                    log::warn!("{}", e);
+                }
+            }
+            Ok(Cached::fresh(data))
+        }
+        Err(e) if settings.fallback_when_unreachable && is_network_error(&e) => {
+            match from_cache(app, station_id, kind, request, CacheReason::Unreachable) {
+                Ok(Some(cached)) => {
+                    log::info!(
+                        "Station {} unreachable, answering {} from the cache: {}",
+                        station_id,
+                        kind,
+                        e
+                    );
+                    Ok(cached)
+                }
+                _ => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[tauri::command]
+pub async fn get_offline_settings(db: State<'_, AgentDb>) -> Result<OfflineSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_offline_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_offline_settings(
+    app: AppHandle,
+    settings: OfflineSettings,
+) -> Result<OfflineSettings, String> {
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            params![OFFLINE_SETTINGS_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save offline settings: {}", e))?;
+    }
+    log::info!(
+        "Offline mode {}",
+        if settings.enabled { "on" } else { "off" }
+    );
+    let _ = app.emit("offline-mode-changed", settings.enabled);
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_response_cache_table(&conn).unwrap();
+        let page = json!({"items": [{"id": "1"}], "total": 1});
+        store_response(&conn, "s1", "tokens", "1:10", &page, 100).unwrap();
+        store_response(&conn, "s1", "tokens", "2:10", &json!({"items": []}), 100).unwrap();
+
+        let (cached, fetched_at) =
+            load_response::<serde_json::Value>(&conn, "s1", "tokens", "1:10")
+                .unwrap()
+                .unwrap();
+        assert_eq!((cached, fetched_at), (page.clone(), 100));
+        assert!(
+            load_response::<serde_json::Value>(&conn, "s1", "logs", "1:10")
+                .unwrap()
+                .is_none()
+        );
+        // A changed shape reads as no cache
+        assert!(load_response::<Vec<String>>(&conn, "s1", "tokens", "1:10")
+            .unwrap()
+            .is_none());
+
+        let fresh = Cached {
+            data: page.clone(),
+            cached: None,
+        };
+        assert_eq!(serde_json::to_value(&fresh).unwrap(), page);
+
+        let tokens = json!({"items": [{"id": 1, "token": "sk-abcdefghijklmnop"}], "total": 1});
+        store_response(&conn, "s1", "tokens", "3:10", &tokens, 100).unwrap();
+        let (kept, _) = load_response::<serde_json::Value>(&conn, "s1", "tokens", "3:10")
+            .unwrap()
+            .unwrap();
+        assert_eq!(kept["items"][0]["token"], "sk-abc****mnop");
+
+        let stale = Cached {
+            data: page,
+            cached: Some(Staleness {
+                fetched_at: 100,
+                age_secs: 60,
+                reason: CacheReason::Unreachable,
+            }),
+        };
+        let value = serde_json::to_value(&stale).unwrap();
+        assert_eq!(value["total"], 1);
+        assert_eq!(value["cached"]["reason"], "unreachable");
+
+        clear_station(&conn, "s1").unwrap();
+        assert!(
+            load_response::<serde_json::Value>(&conn, "s1", "tokens", "1:10")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
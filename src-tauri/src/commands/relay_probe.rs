@@ -0,0 +1,367 @@
+//! Detection of a relay station's panel for the setup wizard
+//!
+//! `probe_station` fingerprints the station's `/api/status` response to tell
+//! NewAPI, One API and one-hub panels from plain API endpoints, checks the
+//! token where the panel allows it, and returns what the add-station form
+//! needs: the adapter to use, the user ID when it could be read and an
+//! `adapter_config` noting what was detected.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::relay_adapters::hints;
+use super::relay_stations::{AuthMethod, RelayStationAdapter};
+use crate::t;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `/api/status` fields only NewAPI sends
+const NEWAPI_FIELDS: &[&str] = &[
+    "self_use_mode_enabled",
+    "default_use_auto_group",
+    "demo_site_enabled",
+    "data_export_default_time",
+    "enable_drawing",
+    "enable_task",
+    "setup",
+    "linuxdo_oauth",
+    "oidc_enabled",
+];
+
+/// `/api/status` fields only one-hub sends
+const ONEHUB_FIELDS: &[&str] = &[
+    "chat_links",
+    "oidc_auth",
+    "PaymentUSDRate",
+    "RechargeDiscount",
+    "UserInvoiceMonth",
+    "lark_login",
+];
+
+/// Fields every One API descendant sends
+const ONEAPI_FIELDS: &[&str] = &[
+    "system_name",
+    "quota_per_unit",
+    "display_in_currency",
+    "github_oauth",
+    "turnstile_check",
+    "top_up_link",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanelType {
+    NewApi,
+    OneApi,
+    OneHub,
+    /// No panel API, e.g. a plain Anthropic-compatible endpoint
+    Generic,
+}
+
+impl PanelType {
+    pub fn recommended_adapter(&self) -> RelayStationAdapter {
+        match self {
+            PanelType::NewApi => RelayStationAdapter::Newapi,
+            // one-hub kept One API's endpoints
+            PanelType::OneApi | PanelType::OneHub => RelayStationAdapter::Oneapi,
+            PanelType::Generic => RelayStationAdapter::Custom,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            PanelType::NewApi => "new_api",
+            PanelType::OneApi => "one_api",
+            PanelType::OneHub => "one_hub",
+            PanelType::Generic => "generic",
+        }
+    }
+}
+
+/// What a probe found out about a station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationProbe {
+    /// The URL to save: trimmed, without a trailing slash or `/v1`
+    pub api_url: String,
+    pub panel: PanelType,
+    /// `/api/status` fields the panel was recognised by
+    pub signals: Vec<String>,
+    pub recommended_adapter: RelayStationAdapter,
+    pub auth_method: AuthMethod,
+    pub suggested_name: Option<String>,
+    pub version: Option<String>,
+    pub user_id: Option<String>,
+    /// The panel wants the user ID with every request and it could not be read
+    pub needs_user_id: bool,
+    /// Unknown when no token was given or the panel did not say
+    pub token_valid: Option<bool>,
+    pub adapter_config: HashMap<String, Value>,
+    /// Localized remarks for the wizard to show
+    pub notes: Vec<String>,
+}
+
+/// The station URL as it is stored
+pub fn normalize_url(api_url: &str) -> Result<String, String> {
+    let url = api_url.trim().trim_end_matches('/');
+    let url = url.strip_suffix("/v1").unwrap_or(url).trim_end_matches('/');
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(t!("relay_probe.invalid_url"));
+    }
+    Ok(url.to_string())
+}
+
+/// The panel a `/api/status` response comes from, with the fields that told
+pub fn fingerprint(status: &Value) -> (PanelType, Vec<String>) {
+    let Some(data) = status.get("data").and_then(Value::as_object) else {
+        return (PanelType::Generic, Vec::new());
+    };
+    let found = |fields: &[&str]| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|field| data.contains_key(**field))
+            .map(|field| field.to_string())
+            .collect()
+    };
+    let newapi = found(NEWAPI_FIELDS);
+    if !newapi.is_empty() {
+        return (PanelType::NewApi, newapi);
+    }
+    let onehub = found(ONEHUB_FIELDS);
+    if !onehub.is_empty() {
+        return (PanelType::OneHub, onehub);
+    }
+    let oneapi = found(ONEAPI_FIELDS);
+    if !oneapi.is_empty() {
+        return (PanelType::OneApi, oneapi);
+    }
+    (PanelType::Generic, Vec::new())
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// What `/api/user/self` says about the token
+struct TokenCheck {
+    valid: Option<bool>,
+    user_id: Option<String>,
+    needs_user_id: bool,
+    hint: Option<String>,
+}
+
+async fn check_panel_token(client: &reqwest::Client, api_url: &str, token: &str) -> TokenCheck {
+    let mut check = TokenCheck {
+        valid: None,
+        user_id: None,
+        needs_user_id: false,
+        hint: None,
+    };
+    let response = match client
+        .get(format!("{}/api/user/self", api_url))
+        .bearer_auth(token)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            check.hint = hints::classify_error(&anyhow::Error::new(e)).map(|kind| kind.hint());
+            return check;
+        }
+    };
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let json: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    let success = json.get("success").and_then(Value::as_bool);
+
+    if status.is_success() && success != Some(false) {
+        check.valid = Some(true);
+        check.user_id = json.pointer("/data/id").and_then(|id| {
+            id.as_i64()
+                .map(|id| id.to_string())
+                .or(id.as_str().map(str::to_string))
+        });
+        return check;
+    }
+    match hints::classify(Some(status.as_u16()), &body) {
+        // NewAPI refuses before looking at the token when the user is missing
+        Some(hints::ErrorKind::UserIdMismatch) => check.needs_user_id = true,
+        Some(hints::ErrorKind::InvalidToken) => {
+            check.valid = Some(false);
+            check.hint = Some(hints::ErrorKind::InvalidToken.hint());
+        }
+        Some(kind) => check.hint = Some(kind.hint()),
+        None => {}
+    }
+    check
+}
+
+/// Whether a plain endpoint accepts the token, from its model list
+async fn check_endpoint_token(
+    client: &reqwest::Client,
+    api_url: &str,
+    token: &str,
+) -> Option<bool> {
+    let response = client
+        .get(format!("{}/v1/models", api_url))
+        .bearer_auth(token)
+        .header("x-api-key", token)
+        .header("anthropic-version", "2023-06-01")
+        .send()
+        .await
+        .ok()?;
+    match response.status().as_u16() {
+        200..=299 => Some(true),
+        401 | 403 => Some(false),
+        _ => None,
+    }
+}
+
+fn status_string(data: &Value, key: &str) -> Option<String> {
+    data.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Detect the panel behind `api_url` and check `token` against it
+#[tauri::command]
+pub async fn probe_station(api_url: String, token: Option<String>) -> Result<StationProbe, String> {
+    let api_url = normalize_url(&api_url)?;
+    let token = token
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+    let client = client()?;
+
+    let status = client
+        .get(format!("{}/api/status", api_url))
+        .send()
+        .await
+        .map_err(|e| hints::with_hint(&anyhow::Error::new(e)))?;
+    let status_code = status.status();
+    let body = status.text().await.unwrap_or_default();
+    if hints::classify(Some(status_code.as_u16()), &body)
+        == Some(hints::ErrorKind::CloudflareChallenge)
+    {
+        return Err(hints::ErrorKind::CloudflareChallenge.hint());
+    }
+    let status_json: Value = if status_code.is_success() {
+        serde_json::from_str(&body).unwrap_or(Value::Null)
+    } else {
+        Value::Null
+    };
+    let (panel, signals) = fingerprint(&status_json);
+    let data = status_json.get("data").cloned().unwrap_or(Value::Null);
+
+    let mut probe = StationProbe {
+        api_url: api_url.clone(),
+        panel,
+        signals,
+        recommended_adapter: panel.recommended_adapter(),
+        auth_method: AuthMethod::BearerToken,
+        suggested_name: status_string(&data, "system_name"),
+        version: status_string(&data, "version"),
+        user_id: None,
+        needs_user_id: false,
+        token_valid: None,
+        adapter_config: HashMap::new(),
+        notes: Vec::new(),
+    };
+
+    probe
+        .adapter_config
+        .insert("panel".to_string(), Value::from(panel.as_str()));
+    probe.adapter_config.insert(
+        "probed_at".to_string(),
+        Value::from(chrono::Utc::now().timestamp()),
+    );
+    if let Some(version) = &probe.version {
+        probe
+            .adapter_config
+            .insert("panel_version".to_string(), Value::from(version.clone()));
+    }
+    for key in ["quota_per_unit", "display_in_currency"] {
+        if let Some(value) = data.get(key).filter(|value| !value.is_null()) {
+            probe.adapter_config.insert(key.to_string(), value.clone());
+        }
+    }
+
+    match (&token, panel) {
+        (None, _) => probe.notes.push(t!("relay_probe.no_token")),
+        (Some(token), PanelType::Generic) => {
+            probe.token_valid = check_endpoint_token(&client, &api_url, token).await;
+        }
+        (Some(token), _) => {
+            let check = check_panel_token(&client, &api_url, token).await;
+            probe.token_valid = check.valid;
+            probe.user_id = check.user_id;
+            probe.needs_user_id = check.needs_user_id;
+            probe.notes.extend(check.hint);
+        }
+    }
+
+    match panel {
+        PanelType::NewApi if probe.needs_user_id || probe.user_id.is_none() => {
+            probe.needs_user_id = true;
+            probe.notes.push(t!("relay_probe.newapi_user_id"));
+        }
+        PanelType::OneHub => probe.notes.push(t!("relay_probe.onehub_limited")),
+        PanelType::Generic => probe.notes.push(t!("relay_probe.generic")),
+        _ => {}
+    }
+    if probe.token_valid == Some(false) {
+        probe.notes.push(t!("relay_probe.token_rejected"));
+    }
+    Ok(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fingerprint() {
+        let newapi = json!({"success": true, "data": {
+            "system_name": "New API", "quota_per_unit": 500000,
+            "self_use_mode_enabled": false, "setup": true, "version": "v0.6.11"
+        }});
+        let (panel, signals) = fingerprint(&newapi);
+        assert_eq!(panel, PanelType::NewApi);
+        assert_eq!(signals, vec!["self_use_mode_enabled", "setup"]);
+
+        let onehub = json!({"success": true, "data": {
+            "system_name": "One Hub", "chat_links": "[]", "quota_per_unit": 500000
+        }});
+        assert_eq!(fingerprint(&onehub).0, PanelType::OneHub);
+
+        let oneapi = json!({"success": true, "data": {
+            "system_name": "One API", "quota_per_unit": 500000, "github_oauth": false
+        }});
+        assert_eq!(fingerprint(&oneapi).0, PanelType::OneApi);
+
+        assert_eq!(fingerprint(&json!({"type": "error"})).0, PanelType::Generic);
+        assert_eq!(fingerprint(&Value::Null).0, PanelType::Generic);
+        assert!(matches!(
+            PanelType::OneHub.recommended_adapter(),
+            RelayStationAdapter::Oneapi
+        ));
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url(" https://relay.example.com/v1/ ").unwrap(),
+            "https://relay.example.com"
+        );
+        assert_eq!(
+            normalize_url("http://localhost:3000/").unwrap(),
+            "http://localhost:3000"
+        );
+        assert!(normalize_url("relay.example.com").is_err());
+    }
+}
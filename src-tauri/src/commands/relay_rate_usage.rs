@@ -0,0 +1,622 @@
+//! Request rate usage of relay station tokens
+//!
+//! Requests are counted per token over the last minute and hour from the
+//! cached station logs and, for the token applied to Claude Code, from the
+//! local proxy's request log. When a token gets close to the station's
+//! request limits, `token-rate-limit-near` is emitted once per limit window,
+//! so long sessions can back off before the station starts answering 429.
+//!
+//! Limits come from the station's status response when it advertises them,
+//! otherwise from limits entered per station in the settings.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::relay_offline;
+use super::relay_stations::{ConfigUsageStatus, RelayStation, RelayStationManager, StationInfo};
+use super::relay_token_quota;
+use crate::commands::agents::AgentDb;
+use crate::t;
+
+/// app_settings key storing the rate usage settings (JSON)
+pub const TOKEN_RATE_SETTINGS_KEY: &str = "token_rate_settings";
+
+/// A request limit: at most `max_requests` within `window_minutes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateWindow {
+    pub window_minutes: i64,
+    pub max_requests: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenRateSettings {
+    pub enabled: bool,
+    /// Warn once a token used this share of a limit, in percent
+    pub warn_percent: f64,
+    /// Limits by station id, for stations that do not advertise theirs
+    pub station_limits: HashMap<String, Vec<RateWindow>>,
+}
+
+impl Default for TokenRateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            warn_percent: 80.0,
+            station_limits: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitSource {
+    Station,
+    Settings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitUsage {
+    pub window_minutes: i64,
+    pub max_requests: i64,
+    pub used: i64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenRateUsage {
+    pub token_name: String,
+    pub token_id: Option<String>,
+    pub last_minute: i64,
+    pub last_hour: i64,
+    /// Busiest single minute within the last hour
+    pub peak_minute: i64,
+    pub limits: Vec<RateLimitUsage>,
+    pub near_limit: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationRateUsage {
+    pub station_id: String,
+    pub station_name: String,
+    pub limits: Vec<RateWindow>,
+    pub limit_source: Option<LimitSource>,
+    pub warn_percent: f64,
+    pub tokens: Vec<TokenRateUsage>,
+    pub computed_at: i64,
+}
+
+/// Payload of `token-rate-limit-near`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenRateWarning {
+    pub station_id: String,
+    pub station_name: String,
+    pub token_name: String,
+    pub window_minutes: i64,
+    pub max_requests: i64,
+    pub used: i64,
+    pub percent: f64,
+}
+
+pub fn create_token_rate_warnings_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS token_rate_warnings (
+            station_id TEXT NOT NULL,
+            token_name TEXT NOT NULL,
+            window_minutes INTEGER NOT NULL,
+            warned_at INTEGER NOT NULL,
+            PRIMARY KEY (station_id, token_name, window_minutes)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn load_token_rate_settings(conn: &Connection) -> TokenRateSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![TOKEN_RATE_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn positive(value: Option<&serde_json::Value>) -> Option<i64> {
+    let value = value?;
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .filter(|n| *n > 0)
+}
+
+/// Limits a station advertises in its status response
+///
+/// NewAPI forks expose the model request limit under a few names; plain
+/// `rpm` / `rph` fields are read as well.
+pub fn advertised_limits(info: &StationInfo) -> Vec<RateWindow> {
+    let Some(status) = info
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("response"))
+        .and_then(|response| response.as_object())
+    else {
+        return Vec::new();
+    };
+    let field = |names: &[&str]| names.iter().find_map(|name| positive(status.get(*name)));
+
+    let mut limits = Vec::new();
+    if let Some(count) = field(&[
+        "model_request_rate_limit_count",
+        "ModelRequestRateLimitCount",
+    ]) {
+        let minutes = field(&[
+            "model_request_rate_limit_duration_minutes",
+            "ModelRequestRateLimitDurationMinutes",
+        ])
+        .unwrap_or(1);
+        limits.push(RateWindow {
+            window_minutes: minutes,
+            max_requests: count,
+        });
+    }
+    if let Some(rpm) = field(&["rpm", "rpm_limit", "rate_limit_rpm"]) {
+        limits.push(RateWindow {
+            window_minutes: 1,
+            max_requests: rpm,
+        });
+    }
+    if let Some(rph) = field(&["rph", "rph_limit", "rate_limit_rph"]) {
+        limits.push(RateWindow {
+            window_minutes: 60,
+            max_requests: rph,
+        });
+    }
+    limits.sort_by_key(|limit| limit.window_minutes);
+    limits.dedup_by_key(|limit| limit.window_minutes);
+    limits
+}
+
+/// Request times per token name from the cached logs
+pub fn log_requests(
+    conn: &Connection,
+    station_id: &str,
+    since: i64,
+) -> Result<HashMap<String, Vec<i64>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT token_name, timestamp FROM station_log_cache
+             WHERE station_id = ?1 AND timestamp >= ?2 AND token_name IS NOT NULL AND token_name != ''",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![station_id, since], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut requests: HashMap<String, Vec<i64>> = HashMap::new();
+    for row in rows {
+        let (token_name, timestamp) = row.map_err(|e| e.to_string())?;
+        requests.entry(token_name).or_default().push(timestamp);
+    }
+    Ok(requests)
+}
+
+/// Request times through the local proxy to an upstream
+pub fn proxy_requests(conn: &Connection, base_url: &str, since: i64) -> Result<Vec<i64>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT created_at FROM proxy_requests
+             WHERE RTRIM(upstream, '/') = ?1 AND created_at >= ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let times = stmt
+        .query_map(params![base_url.trim_end_matches('/'), since], |row| {
+            row.get(0)
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(times)
+}
+
+fn count_since(times: &[i64], since: i64) -> i64 {
+    times.iter().filter(|time| **time > since).count() as i64
+}
+
+fn peak_minute(times: &[i64], now: i64) -> i64 {
+    let mut minutes: HashMap<i64, i64> = HashMap::new();
+    for time in times.iter().filter(|time| **time > now - 3600) {
+        *minutes.entry((now - time) / 60).or_default() += 1;
+    }
+    minutes.into_values().max().unwrap_or(0)
+}
+
+/// Usage of every token seen in the logs or through the proxy
+///
+/// A request through the proxy also shows up in the station's logs once they
+/// are synced, so the two sources are not added up: each count takes the
+/// larger of the two.
+pub fn summarize(
+    logs: &HashMap<String, Vec<i64>>,
+    proxy: Option<(&str, &[i64])>,
+    limits: &[RateWindow],
+    warn_percent: f64,
+    now: i64,
+) -> Vec<TokenRateUsage> {
+    let mut names: Vec<&str> = logs.keys().map(String::as_str).collect();
+    if let Some((name, times)) = proxy {
+        if !times.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut usage: Vec<TokenRateUsage> = names
+        .into_iter()
+        .map(|name| {
+            let logged = logs.get(name).map(Vec::as_slice).unwrap_or_default();
+            let proxied = proxy
+                .filter(|(proxy_name, _)| *proxy_name == name)
+                .map(|(_, times)| times)
+                .unwrap_or_default();
+            let count = |minutes: i64| {
+                let since = now - minutes * 60;
+                count_since(logged, since).max(count_since(proxied, since))
+            };
+            let limits: Vec<RateLimitUsage> = limits
+                .iter()
+                .map(|limit| {
+                    let used = count(limit.window_minutes);
+                    RateLimitUsage {
+                        window_minutes: limit.window_minutes,
+                        max_requests: limit.max_requests,
+                        used,
+                        percent: used as f64 * 100.0 / limit.max_requests as f64,
+                    }
+                })
+                .collect();
+            TokenRateUsage {
+                token_name: name.to_string(),
+                token_id: None,
+                last_minute: count(1),
+                last_hour: count(60),
+                peak_minute: peak_minute(logged, now).max(peak_minute(proxied, now)),
+                near_limit: limits.iter().any(|limit| limit.percent >= warn_percent),
+                limits,
+            }
+        })
+        .collect();
+    usage.sort_by(|a, b| {
+        b.last_minute
+            .cmp(&a.last_minute)
+            .then(b.last_hour.cmp(&a.last_hour))
+            .then(a.token_name.cmp(&b.token_name))
+    });
+    usage
+}
+
+/// The station's limits and where they came from
+fn station_limits(
+    conn: &Connection,
+    station_id: &str,
+    settings: &TokenRateSettings,
+) -> (Vec<RateWindow>, Option<LimitSource>) {
+    let advertised =
+        relay_offline::load_response::<StationInfo>(conn, station_id, "station_info", "")
+            .ok()
+            .flatten()
+            .map(|(info, _)| advertised_limits(&info))
+            .unwrap_or_default();
+    if !advertised.is_empty() {
+        return (advertised, Some(LimitSource::Station));
+    }
+    match settings.station_limits.get(station_id) {
+        Some(limits) if !limits.is_empty() => (limits.clone(), Some(LimitSource::Settings)),
+        _ => (Vec::new(), None),
+    }
+}
+
+/// Rate usage of a station's tokens; `applied` is the station's entry in the
+/// config usage status, whose token the proxy's requests are counted against
+pub fn station_usage(
+    conn: &Connection,
+    station: &RelayStation,
+    applied: Option<&ConfigUsageStatus>,
+    now: i64,
+) -> Result<StationRateUsage, String> {
+    let settings = load_token_rate_settings(conn);
+    let (limits, limit_source) = station_limits(conn, &station.id, &settings);
+    let longest = limits
+        .iter()
+        .map(|limit| limit.window_minutes)
+        .max()
+        .unwrap_or(60)
+        .max(60);
+    let since = now - longest * 60;
+
+    let tokens = relay_token_quota::load_tokens(conn, &station.id)?;
+    let logs = log_requests(conn, &station.id, since)?;
+    let proxy = match applied {
+        Some(applied) => {
+            let name = tokens
+                .iter()
+                .find(|token| token.matches(&applied.token))
+                .map(|token| token.token_name.clone())
+                .unwrap_or_else(|| t!("relay_rate.applied_token"));
+            Some((name, proxy_requests(conn, &applied.base_url, since)?))
+        }
+        None => None,
+    };
+    let mut usage = summarize(
+        &logs,
+        proxy
+            .as_ref()
+            .map(|(name, times)| (name.as_str(), times.as_slice())),
+        &limits,
+        settings.warn_percent,
+        now,
+    );
+    for token in &mut usage {
+        token.token_id = tokens
+            .iter()
+            .find(|cached| cached.token_name == token.token_name)
+            .map(|cached| cached.token_id.clone());
+    }
+    Ok(StationRateUsage {
+        station_id: station.id.clone(),
+        station_name: station.name.clone(),
+        limits,
+        limit_source,
+        warn_percent: settings.warn_percent,
+        tokens: usage,
+        computed_at: now,
+    })
+}
+
+/// Limits that just got close, each reported once per limit window
+pub fn new_warnings(
+    conn: &Connection,
+    usage: &StationRateUsage,
+) -> Result<Vec<TokenRateWarning>, String> {
+    let mut warnings = Vec::new();
+    for token in &usage.tokens {
+        for limit in &token.limits {
+            if limit.percent < usage.warn_percent {
+                continue;
+            }
+            let warned = conn
+                .execute(
+                    "INSERT INTO token_rate_warnings (station_id, token_name, window_minutes, warned_at)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT(station_id, token_name, window_minutes) DO UPDATE
+                     SET warned_at = excluded.warned_at
+                     WHERE token_rate_warnings.warned_at <= excluded.warned_at - ?5",
+                    params![
+                        usage.station_id,
+                        token.token_name,
+                        limit.window_minutes,
+                        usage.computed_at,
+                        limit.window_minutes * 60,
+                    ],
+                )
+                .map_err(|e| format!("Failed to record rate limit warning: {}", e))?;
+            if warned > 0 {
+                warnings.push(TokenRateWarning {
+                    station_id: usage.station_id.clone(),
+                    station_name: usage.station_name.clone(),
+                    token_name: token.token_name.clone(),
+                    window_minutes: limit.window_minutes,
+                    max_requests: limit.max_requests,
+                    used: limit.used,
+                    percent: limit.percent,
+                });
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+fn find_station(
+    app: &AppHandle,
+    matches: impl Fn(&RelayStation, Option<&ConfigUsageStatus>) -> bool,
+) -> Result<Option<(RelayStation, Option<ConfigUsageStatus>)>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state
+        .lock()
+        .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+    let applied = manager
+        .get_config_usage_status()
+        .map_err(|e| e.to_string())?;
+    let stations = manager.list_stations().map_err(|e| e.to_string())?;
+    Ok(stations.into_iter().find_map(|station| {
+        let status = applied
+            .iter()
+            .find(|status| status.station_id == station.id)
+            .cloned();
+        matches(&station, status.as_ref()).then_some((station, status))
+    }))
+}
+
+/// Compute a station's usage and emit warnings for limits getting close
+pub fn check_station(
+    app: &AppHandle,
+    station: &RelayStation,
+    applied: Option<&ConfigUsageStatus>,
+) -> Result<StationRateUsage, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let usage = station_usage(&conn, station, applied, chrono::Utc::now().timestamp())?;
+    if load_token_rate_settings(&conn).enabled {
+        for warning in new_warnings(&conn, &usage)? {
+            log::info!(
+                "Token {} of {} made {} of {} requests allowed per {} min",
+                warning.token_name,
+                warning.station_name,
+                warning.used,
+                warning.max_requests,
+                warning.window_minutes
+            );
+            let _ = app.emit("token-rate-limit-near", &warning);
+        }
+    }
+    Ok(usage)
+}
+
+/// Check the station behind an upstream after a request through the proxy
+pub fn check_upstream(app: &AppHandle, upstream: &str) {
+    let upstream = upstream.trim_end_matches('/');
+    let result = find_station(app, |_, applied| {
+        applied.is_some_and(|applied| applied.base_url.trim_end_matches('/') == upstream)
+    })
+    .and_then(|found| match found {
+        Some((station, applied)) => check_station(app, &station, applied.as_ref()).map(|_| ()),
+        None => Ok(()),
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to check token rate usage: {}", e);
+    }
+}
+
+/// Forget a station's rate limit warnings
+pub fn clear_station(conn: &Connection, station_id: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM token_rate_warnings WHERE station_id = ?1",
+        params![station_id],
+    )
+    .map_err(|e| format!("Failed to clear rate limit warnings: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_token_rate_usage(
+    station_id: String,
+    app: AppHandle,
+) -> Result<StationRateUsage, String> {
+    let (station, applied) = find_station(&app, |station, _| station.id == station_id)?
+        .ok_or_else(|| t!("relay.station_not_found"))?;
+    check_station(&app, &station, applied.as_ref())
+}
+
+#[tauri::command]
+pub async fn get_token_rate_settings(db: State<'_, AgentDb>) -> Result<TokenRateSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_token_rate_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_token_rate_settings(
+    db: State<'_, AgentDb>,
+    settings: TokenRateSettings,
+) -> Result<TokenRateSettings, String> {
+    if !settings.warn_percent.is_finite()
+        || settings.warn_percent <= 0.0
+        || settings.warn_percent > 100.0
+    {
+        return Err("The warning threshold must be between 0 and 100 percent".to_string());
+    }
+    let invalid = settings
+        .station_limits
+        .values()
+        .flatten()
+        .any(|limit| limit.window_minutes <= 0 || limit.max_requests <= 0);
+    if invalid {
+        return Err("Rate limits need a positive window and request count".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![TOKEN_RATE_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save rate limit settings: {}", e))?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advertised_limits() {
+        let info = StationInfo {
+            name: "s1".to_string(),
+            announcement: None,
+            api_url: "https://relay.example.com".to_string(),
+            version: None,
+            metadata: Some(HashMap::from([(
+                "response".to_string(),
+                serde_json::json!({
+                    "ModelRequestRateLimitCount": "600",
+                    "ModelRequestRateLimitDurationMinutes": 60,
+                    "rpm": 30,
+                    "rph": 0
+                }),
+            )])),
+            quota_per_unit: None,
+        };
+        assert_eq!(
+            advertised_limits(&info),
+            vec![
+                RateWindow {
+                    window_minutes: 1,
+                    max_requests: 30
+                },
+                RateWindow {
+                    window_minutes: 60,
+                    max_requests: 600
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_and_warn_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_token_rate_warnings_table(&conn).unwrap();
+        let now = 10_000;
+        let logs = HashMap::from([
+            (
+                "ci".to_string(),
+                vec![now - 10, now - 20, now - 30, now - 400],
+            ),
+            ("dev".to_string(), vec![now - 3000]),
+        ]);
+        // Proxied requests of "ci" that are already in the logs are not doubled
+        let proxied = [now - 10, now - 20];
+        let limits = [RateWindow {
+            window_minutes: 1,
+            max_requests: 4,
+        }];
+        let usage = summarize(&logs, Some(("ci", &proxied)), &limits, 75.0, now);
+
+        assert_eq!(usage[0].token_name, "ci");
+        assert_eq!((usage[0].last_minute, usage[0].last_hour), (3, 4));
+        assert_eq!(usage[0].peak_minute, 3);
+        assert_eq!(usage[0].limits[0].percent, 75.0);
+        assert!(usage[0].near_limit);
+        assert!(!usage[1].near_limit);
+
+        let station = StationRateUsage {
+            station_id: "s1".to_string(),
+            station_name: "s1".to_string(),
+            limits: limits.to_vec(),
+            limit_source: Some(LimitSource::Settings),
+            warn_percent: 75.0,
+            tokens: usage,
+            computed_at: now,
+        };
+        assert_eq!(new_warnings(&conn, &station).unwrap().len(), 1);
+        assert!(new_warnings(&conn, &station).unwrap().is_empty());
+        let later = StationRateUsage {
+            computed_at: now + 60,
+            ..station
+        };
+        assert_eq!(new_warnings(&conn, &later).unwrap().len(), 1);
+    }
+}
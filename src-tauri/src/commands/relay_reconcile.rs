@@ -0,0 +1,510 @@
+//! Reconcile locally recorded Claude Code usage against what a relay station billed
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use super::agents::AgentDb;
+use super::currency::Converted;
+use super::provider::{self, ProviderUsageSession};
+use super::relay_adapters::{CancellationToken, RelayRequestRegistry};
+use super::relay_stations::{
+    create_station_adapter, RelayStation, RelayStationManager, StationAdapter, StationLogEntry,
+};
+use super::usage::{get_all_usage_entries, UsageEntry};
+use crate::t;
+
+/// NewAPI-style stations bill 500000 quota units per USD unless they report otherwise
+const DEFAULT_QUOTA_PER_UNIT: f64 = 500_000.0;
+
+/// Differences below this are rounding, not over-billing
+const MIN_COST_DIFFERENCE_USD: f64 = 0.01;
+
+const LOG_PAGE_SIZE: usize = 100;
+
+/// Stop paging station logs after this many pages
+const MAX_LOG_PAGES: usize = 50;
+
+/// Requests are matched per hour and model
+const BUCKET_SECONDS: i64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileStatus {
+    Match,
+    /// The station charged more than the local usage costs at list price
+    Overbilled,
+    Underbilled,
+    /// Only the station saw requests, e.g. another client sharing the key
+    StationOnly,
+    /// Only Claude Code recorded requests, e.g. sent through another endpoint
+    LocalOnly,
+}
+
+/// Local usage and station billing of one model within one hour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileBucket {
+    /// Unix timestamp of the start of the hour
+    pub hour: i64,
+    pub model: String,
+    pub local_requests: u64,
+    pub station_requests: u64,
+    pub local_input_tokens: u64,
+    pub local_output_tokens: u64,
+    pub local_cache_creation_tokens: u64,
+    pub local_cache_read_tokens: u64,
+    pub station_prompt_tokens: i64,
+    pub station_completion_tokens: i64,
+    pub local_cost_usd: f64,
+    pub station_cost_usd: f64,
+    /// Station cost minus local cost
+    pub cost_difference_usd: f64,
+    pub status: ReconcileStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub station_id: String,
+    pub station_name: String,
+    pub start: i64,
+    pub end: i64,
+    pub quota_per_unit: f64,
+    pub tolerance_percent: f64,
+    pub local_cost_usd: f64,
+    pub station_cost_usd: f64,
+    pub cost_difference_usd: f64,
+    pub local_output_tokens: u64,
+    pub station_completion_tokens: i64,
+    pub overbilled_buckets: usize,
+    /// More logs existed than were fetched; the station totals are incomplete
+    pub station_logs_truncated: bool,
+    pub buckets: Vec<ReconcileBucket>,
+}
+
+/// Compare endpoint URLs regardless of a trailing slash or `/v1`
+fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    url.strip_suffix("/v1")
+        .unwrap_or(url)
+        .trim_end_matches('/')
+        .to_lowercase()
+}
+
+/// The base URL that was configured at `timestamp`, from the recorded
+/// provider switches; `None` before the first one or while none was set
+fn base_url_at(switches: &[ProviderUsageSession], timestamp: i64) -> Option<&str> {
+    switches
+        .iter()
+        .rev()
+        .find(|switch| {
+            switch.started_at <= timestamp && timestamp < switch.ended_at.unwrap_or(i64::MAX)
+        })
+        .map(|switch| switch.base_url.as_str())
+}
+
+/// Local entries sent through one of `station_urls`
+///
+/// `UsageEntry::api_base_url` is the endpoint at scan time, not at request
+/// time, so each entry is attributed through the switch history instead;
+/// entries from before any recorded switch are left out.
+fn station_entries(
+    entries: Vec<UsageEntry>,
+    switches: &[ProviderUsageSession],
+    station_urls: &[String],
+) -> Vec<UsageEntry> {
+    let station_urls: Vec<String> = station_urls.iter().map(|url| normalize_url(url)).collect();
+    entries
+        .into_iter()
+        .filter(|entry| {
+            parse_time(&entry.timestamp)
+                .ok()
+                .and_then(|time| base_url_at(switches, time.timestamp()))
+                .is_some_and(|url| station_urls.contains(&normalize_url(url)))
+        })
+        .collect()
+}
+
+/// Compare model names regardless of case and date suffix
+/// ("claude-sonnet-4-5-20250929" and "claude-sonnet-4-5" are the same model)
+fn normalize_model(model: &str) -> String {
+    let model = model.trim().to_lowercase();
+    match model.rsplit_once('-') {
+        Some((base, date)) if date.len() == 8 && date.chars().all(|c| c.is_ascii_digit()) => {
+            base.to_string()
+        }
+        _ => model,
+    }
+}
+
+fn bucket_for<'a>(
+    buckets: &'a mut BTreeMap<(i64, String), ReconcileBucket>,
+    timestamp: i64,
+    model: &str,
+) -> &'a mut ReconcileBucket {
+    let hour = timestamp - timestamp.rem_euclid(BUCKET_SECONDS);
+    let model = normalize_model(model);
+    buckets
+        .entry((hour, model.clone()))
+        .or_insert_with(|| ReconcileBucket {
+            hour,
+            model,
+            local_requests: 0,
+            station_requests: 0,
+            local_input_tokens: 0,
+            local_output_tokens: 0,
+            local_cache_creation_tokens: 0,
+            local_cache_read_tokens: 0,
+            station_prompt_tokens: 0,
+            station_completion_tokens: 0,
+            local_cost_usd: 0.0,
+            station_cost_usd: 0.0,
+            cost_difference_usd: 0.0,
+            status: ReconcileStatus::Match,
+        })
+}
+
+fn classify(bucket: &ReconcileBucket, tolerance_percent: f64) -> ReconcileStatus {
+    if bucket.local_requests == 0 {
+        return ReconcileStatus::StationOnly;
+    }
+    if bucket.station_requests == 0 {
+        return ReconcileStatus::LocalOnly;
+    }
+    let allowed = (bucket.local_cost_usd * tolerance_percent / 100.0).max(MIN_COST_DIFFERENCE_USD);
+    if bucket.cost_difference_usd > allowed {
+        ReconcileStatus::Overbilled
+    } else if bucket.cost_difference_usd < -allowed {
+        ReconcileStatus::Underbilled
+    } else {
+        ReconcileStatus::Match
+    }
+}
+
+/// Match local usage entries with station consumption logs by hour and model
+///
+/// Station cost is the logged quota divided by `quota_per_unit`; local cost is
+/// what the usage parser computed at list prices.
+pub fn reconcile(
+    local: &[UsageEntry],
+    logs: &[StationLogEntry],
+    quota_per_unit: f64,
+    tolerance_percent: f64,
+) -> Vec<ReconcileBucket> {
+    let mut buckets = BTreeMap::new();
+
+    for entry in local {
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+            continue;
+        };
+        let bucket = bucket_for(&mut buckets, timestamp.timestamp(), &entry.model);
+        bucket.local_requests += 1;
+        bucket.local_input_tokens += entry.input_tokens;
+        bucket.local_output_tokens += entry.output_tokens;
+        bucket.local_cache_creation_tokens += entry.cache_creation_tokens;
+        bucket.local_cache_read_tokens += entry.cache_read_tokens;
+        bucket.local_cost_usd += entry.cost;
+    }
+
+    // Only consumption logs carry a model and a charge
+    for log in logs {
+        let (Some(model), Some(quota)) = (&log.model_name, log.quota) else {
+            continue;
+        };
+        let bucket = bucket_for(&mut buckets, log.timestamp, model);
+        bucket.station_requests += 1;
+        bucket.station_prompt_tokens += log.prompt_tokens.unwrap_or(0);
+        bucket.station_completion_tokens += log.completion_tokens.unwrap_or(0);
+        bucket.station_cost_usd += quota as f64 / quota_per_unit;
+    }
+
+    buckets
+        .into_values()
+        .map(|mut bucket| {
+            bucket.cost_difference_usd = bucket.station_cost_usd - bucket.local_cost_usd;
+            bucket.status = classify(&bucket, tolerance_percent);
+            bucket
+        })
+        .collect()
+}
+
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid time {:?}: {}", value, e))
+}
+
+/// Quota units a station bills per USD, or the NewAPI default
+pub async fn station_quota_per_unit(adapter: &dyn StationAdapter, station: &RelayStation) -> f64 {
+    match adapter.get_station_info(station).await {
+        Ok(info) => info
+            .quota_per_unit
+            .filter(|quota| *quota > 0)
+            .map(|quota| quota as f64)
+            .unwrap_or(DEFAULT_QUOTA_PER_UNIT),
+        Err(e) => {
+            log::warn!("Failed to get quota per unit of {}: {}", station.name, e);
+            DEFAULT_QUOTA_PER_UNIT
+        }
+    }
+}
+
+/// Station logs between `start` and `end`, paging until all are fetched or
+/// `MAX_LOG_PAGES` is reached; the flag tells whether more logs existed
+pub async fn fetch_station_logs(
+    adapter: &dyn StationAdapter,
+    station: &RelayStation,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    cancel: &CancellationToken,
+) -> anyhow::Result<(Vec<StationLogEntry>, bool)> {
+    // Station log filters take UTC minutes
+    let filters = serde_json::json!({
+        "startTime": start.format("%Y-%m-%dT%H:%M").to_string(),
+        "endTime": end.format("%Y-%m-%dT%H:%M").to_string(),
+    });
+    let mut logs = Vec::new();
+    let mut truncated = false;
+    for page in 1..=MAX_LOG_PAGES {
+        let response = adapter
+            .get_logs(
+                station,
+                Some(page),
+                Some(LOG_PAGE_SIZE),
+                Some(filters.clone()),
+                cancel,
+            )
+            .await?;
+        let fetched = response.items.len();
+        logs.extend(response.items);
+        if fetched < LOG_PAGE_SIZE || logs.len() as i64 >= response.total {
+            break;
+        }
+        truncated = page == MAX_LOG_PAGES;
+    }
+    logs.retain(|log| log.timestamp >= start.timestamp() && log.timestamp <= end.timestamp());
+    Ok((logs, truncated))
+}
+
+/// Compare local Claude Code usage with a relay station's billing logs
+///
+/// `start_time`/`end_time` are RFC 3339; the end defaults to now. Buckets whose
+/// station cost exceeds the local cost by more than `tolerance_percent`
+/// (default 5) are reported as over-billed. Only local requests made while the
+/// station was the configured endpoint are counted.
+#[tauri::command]
+pub async fn reconcile_station_usage(
+    station_id: String,
+    start_time: String,
+    end_time: Option<String>,
+    tolerance_percent: Option<f64>,
+    request_id: Option<String>,
+    app: AppHandle,
+) -> Result<Converted<ReconciliationReport>, String> {
+    let start = parse_time(&start_time)?;
+    let end = match end_time.as_deref() {
+        Some(end_time) => parse_time(end_time)?,
+        None => Utc::now(),
+    };
+    if end <= start {
+        return Err("The end time must be after the start time".to_string());
+    }
+    let tolerance_percent = tolerance_percent.unwrap_or(5.0).max(0.0);
+
+    let (station, station_urls) = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        let station = manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?;
+        // The URL the station was last applied with may differ from its panel URL
+        let mut station_urls = vec![station.api_url.clone()];
+        station_urls.extend(
+            manager
+                .get_config_usage_status()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|applied| applied.station_id == station.id)
+                .map(|applied| applied.base_url),
+        );
+        (station, station_urls)
+    };
+    let switches = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        provider::usage_sessions_between(&conn, Some(start.timestamp()), Some(end.timestamp()))?
+    };
+    let adapter = create_station_adapter(&app, &station);
+    let quota_per_unit = station_quota_per_unit(adapter.as_ref(), &station).await;
+
+    let requests: State<RelayRequestRegistry> = app.state();
+    let cancel = requests.begin(request_id.as_deref());
+    let fetched = fetch_station_logs(adapter.as_ref(), &station, start, end, &cancel).await;
+    requests.finish(request_id.as_deref(), &cancel);
+    let (logs, station_logs_truncated) =
+        fetched.map_err(|e| t!("relay.failed_to_get_logs", "error" => &e.to_string()))?;
+
+    let claude_path = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+    let local: Vec<UsageEntry> = get_all_usage_entries(&claude_path)
+        .into_iter()
+        .filter(|entry| {
+            parse_time(&entry.timestamp)
+                .map(|time| time >= start && time <= end)
+                .unwrap_or(false)
+        })
+        .collect();
+    let local = station_entries(local, &switches, &station_urls);
+
+    let buckets = reconcile(&local, &logs, quota_per_unit, tolerance_percent);
+    let local_cost_usd: f64 = buckets.iter().map(|b| b.local_cost_usd).sum();
+    let station_cost_usd: f64 = buckets.iter().map(|b| b.station_cost_usd).sum();
+    log::info!(
+        "Reconciled {} local requests with {} station logs of {}",
+        local.len(),
+        logs.len(),
+        station.name
+    );
+
+    Ok(Converted(ReconciliationReport {
+        station_id,
+        station_name: station.name,
+        start: start.timestamp(),
+        end: end.timestamp(),
+        quota_per_unit,
+        tolerance_percent,
+        local_cost_usd,
+        station_cost_usd,
+        cost_difference_usd: station_cost_usd - local_cost_usd,
+        local_output_tokens: buckets.iter().map(|b| b.local_output_tokens).sum(),
+        station_completion_tokens: buckets.iter().map(|b| b.station_completion_tokens).sum(),
+        overbilled_buckets: buckets
+            .iter()
+            .filter(|b| b.status == ReconcileStatus::Overbilled)
+            .count(),
+        station_logs_truncated,
+        buckets,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(timestamp: &str, model: &str, cost: f64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: model.to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: "s".to_string(),
+            project_path: "/work/app".to_string(),
+            api_base_url: "https://relay.example.com".to_string(),
+        }
+    }
+
+    fn station(timestamp: i64, model: &str, quota: i64) -> StationLogEntry {
+        StationLogEntry {
+            id: timestamp.to_string(),
+            timestamp,
+            level: "api".to_string(),
+            message: String::new(),
+            user_id: None,
+            request_id: None,
+            metadata: None,
+            model_name: Some(model.to_string()),
+            prompt_tokens: Some(100),
+            completion_tokens: Some(50),
+            quota: Some(quota),
+            token_name: None,
+            use_time: None,
+            is_stream: None,
+            channel: None,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_buckets() {
+        // 2025-03-01T10:00:00Z
+        let ten = 1_740_823_200;
+        let local = vec![
+            local("2025-03-01T10:05:00Z", "claude-sonnet-4-5-20250929", 1.0),
+            local("2025-03-01T11:05:00Z", "claude-opus-4-1-20250805", 2.0),
+            local("2025-03-01T12:05:00Z", "claude-haiku-4-5-20251001", 0.5),
+        ];
+        let logs = vec![
+            // Same cost within tolerance
+            station(ten + 310, "claude-sonnet-4-5", 510_000),
+            // Twice the local cost
+            station(ten + 3600 + 310, "claude-opus-4-1-20250805", 2_000_000),
+            station(ten + 2 * 3600 + 10, "gpt-4o", 1_000),
+        ];
+
+        let buckets = reconcile(&local, &logs, 500_000.0, 5.0);
+        let statuses: Vec<(&str, ReconcileStatus)> = buckets
+            .iter()
+            .map(|b| (b.model.as_str(), b.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("claude-sonnet-4-5", ReconcileStatus::Match),
+                ("claude-opus-4-1", ReconcileStatus::Overbilled),
+                ("claude-haiku-4-5", ReconcileStatus::LocalOnly),
+                ("gpt-4o", ReconcileStatus::StationOnly),
+            ]
+        );
+        assert_eq!(buckets[0].hour, ten);
+        assert!((buckets[1].cost_difference_usd - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_station_entries_follow_switch_history() {
+        let switch = |url: &str, started_at: i64, ended_at: Option<i64>| ProviderUsageSession {
+            id: started_at,
+            provider_id: url.to_string(),
+            provider_name: url.to_string(),
+            base_url: url.to_string(),
+            started_at,
+            ended_at,
+        };
+        // 2025-03-01T10:00:00Z: station A until 11:00, then station B
+        let ten = 1_740_823_200;
+        let switches = vec![
+            switch("https://a.example.com/", ten, Some(ten + 3600)),
+            switch("https://b.example.com/v1", ten + 3600, None),
+        ];
+        // Every entry was scanned while B was configured
+        let mut entries = vec![
+            local("2025-03-01T09:30:00Z", "claude-sonnet-4-5", 0.1),
+            local("2025-03-01T10:05:00Z", "claude-sonnet-4-5", 1.0),
+            local("2025-03-01T10:55:00Z", "claude-opus-4-1", 2.0),
+            local("2025-03-01T11:05:00Z", "claude-opus-4-1", 4.0),
+        ];
+        for entry in &mut entries {
+            entry.api_base_url = "https://b.example.com".to_string();
+        }
+
+        let costs = |urls: &[&str]| -> Vec<f64> {
+            let urls: Vec<String> = urls.iter().map(|url| url.to_string()).collect();
+            station_entries(entries.clone(), &switches, &urls)
+                .iter()
+                .map(|entry| entry.cost)
+                .collect()
+        };
+        assert_eq!(costs(&["https://a.example.com"]), vec![1.0, 2.0]);
+        assert_eq!(costs(&["https://B.example.com/"]), vec![4.0]);
+        assert!(costs(&["https://c.example.com"]).is_empty());
+    }
+}
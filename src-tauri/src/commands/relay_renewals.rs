@@ -0,0 +1,559 @@
+//! Reminders before relay station tokens expire and subscriptions renew
+//!
+//! Token expiry dates are tracked whenever a station's tokens are listed and
+//! refreshed by a background check every few hours. Subscription renewal
+//! dates are set per station by the user. Each item is reminded of once per
+//! expiry date with a notification and a `renewal-reminder` event.
+use chrono::{Local, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::relay_adapters::CancellationToken;
+use super::relay_offline::is_offline;
+use super::relay_stations::{
+    create_station_adapter, RelayStation, RelayStationManager, RelayStationToken,
+};
+use super::relay_token_quota::check_tokens;
+use crate::commands::agents::AgentDb;
+use crate::t;
+
+/// app_settings key storing the reminder settings (JSON)
+pub const RENEWAL_SETTINGS_KEY: &str = "renewal_reminder_settings";
+
+/// Seconds between two refreshes of the token expiry dates
+const CHECK_INTERVAL_SECS: u64 = 6 * 3600;
+
+/// Tokens fetched per station by the background check
+const TOKEN_PAGE_SIZE: usize = 100;
+
+const DAY_SECONDS: i64 = 86_400;
+
+/// Longest reminder lead time and dashboard window, in days
+const MAX_WINDOW_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenewalSettings {
+    pub enabled: bool,
+    /// Remind this many days before an expiry
+    pub days_before: i64,
+}
+
+impl Default for RenewalSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            days_before: 7,
+        }
+    }
+}
+
+/// Subscription renewal date of a station
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StationRenewal {
+    pub station_id: String,
+    pub renews_at: i64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryKind {
+    Token,
+    Subscription,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpiringItem {
+    pub kind: ExpiryKind,
+    pub station_id: String,
+    pub station_name: String,
+    /// For tokens
+    pub token_id: Option<String>,
+    /// Token name, or the subscription's note
+    pub name: Option<String>,
+    pub expires_at: i64,
+    /// Whole days left; negative once expired
+    pub days_left: i64,
+    /// A reminder was already shown for this expiry date
+    pub reminded: bool,
+}
+
+pub fn create_renewal_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_renewals (
+            station_id TEXT PRIMARY KEY,
+            renews_at INTEGER NOT NULL,
+            note TEXT,
+            reminded_for INTEGER
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_token_expiries (
+            station_id TEXT NOT NULL,
+            token_id TEXT NOT NULL,
+            token_name TEXT NOT NULL,
+            expires_at INTEGER NOT NULL,
+            reminded_for INTEGER,
+            PRIMARY KEY (station_id, token_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Remember the expiry dates of listed tokens; with `complete`, the tokens
+/// are all the station has and others are forgotten
+pub fn track_tokens(
+    conn: &Connection,
+    station_id: &str,
+    tokens: &[RelayStationToken],
+    complete: bool,
+) -> Result<(), String> {
+    let track_error = |e: rusqlite::Error| format!("Failed to track token expiry: {}", e);
+    if complete {
+        let mut stmt = conn
+            .prepare("SELECT token_id FROM station_token_expiries WHERE station_id = ?1")
+            .map_err(track_error)?;
+        let tracked = stmt
+            .query_map(params![station_id], |row| row.get::<_, String>(0))
+            .map_err(track_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(track_error)?;
+        for token_id in tracked {
+            if !tokens.iter().any(|token| token.id == token_id) {
+                conn.execute(
+                    "DELETE FROM station_token_expiries WHERE station_id = ?1 AND token_id = ?2",
+                    params![station_id, token_id],
+                )
+                .map_err(track_error)?;
+            }
+        }
+    }
+    for token in tokens {
+        match token.expires_at.filter(|expires_at| *expires_at > 0) {
+            // The reminder is kept as long as the date stays the same
+            Some(expires_at) => conn.execute(
+                "INSERT INTO station_token_expiries (station_id, token_id, token_name, expires_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (station_id, token_id)
+                 DO UPDATE SET token_name = excluded.token_name, expires_at = excluded.expires_at",
+                params![station_id, token.id, token.name, expires_at],
+            ),
+            // -1 means the token never expires
+            None => conn.execute(
+                "DELETE FROM station_token_expiries WHERE station_id = ?1 AND token_id = ?2",
+                params![station_id, token.id],
+            ),
+        }
+        .map_err(track_error)?;
+    }
+    Ok(())
+}
+
+/// Forget a station's renewal date and tokens
+pub fn clear_station(conn: &Connection, station_id: &str) -> Result<(), String> {
+    for table in ["station_renewals", "station_token_expiries"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE station_id = ?1", table),
+            params![station_id],
+        )
+        .map_err(|e| format!("Failed to clear renewal reminders: {}", e))?;
+    }
+    Ok(())
+}
+
+pub fn load_renewal(conn: &Connection, station_id: &str) -> Result<Option<StationRenewal>, String> {
+    conn.query_row(
+        "SELECT station_id, renews_at, note FROM station_renewals WHERE station_id = ?1",
+        params![station_id],
+        |row| {
+            Ok(StationRenewal {
+                station_id: row.get(0)?,
+                renews_at: row.get(1)?,
+                note: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Set or, with `None`, remove a station's renewal date
+pub fn save_renewal(
+    conn: &Connection,
+    station_id: &str,
+    renews_at: Option<i64>,
+    note: Option<&str>,
+) -> Result<(), String> {
+    let note = note.map(str::trim).filter(|note| !note.is_empty());
+    match renews_at {
+        Some(renews_at) => conn.execute(
+            "INSERT INTO station_renewals (station_id, renews_at, note) VALUES (?1, ?2, ?3)
+             ON CONFLICT (station_id) DO UPDATE SET renews_at = excluded.renews_at, note = excluded.note",
+            params![station_id, renews_at, note],
+        ),
+        None => conn.execute(
+            "DELETE FROM station_renewals WHERE station_id = ?1",
+            params![station_id],
+        ),
+    }
+    .map_err(|e| format!("Failed to save renewal date: {}", e))?;
+    Ok(())
+}
+
+/// Items expiring before `now` plus `within_days`, expired ones included,
+/// soonest first; only stations in `station_names` are listed
+pub fn expiring_items(
+    conn: &Connection,
+    now: i64,
+    within_days: i64,
+    station_names: &HashMap<String, String>,
+) -> Result<Vec<ExpiringItem>, String> {
+    let until = now + within_days * DAY_SECONDS;
+    let mut stmt = conn
+        .prepare(
+            "SELECT 'subscription', station_id, NULL, note, renews_at, reminded_for
+             FROM station_renewals WHERE renews_at <= ?1
+             UNION ALL
+             SELECT 'token', station_id, token_id, token_name, expires_at, reminded_for
+             FROM station_token_expiries WHERE expires_at <= ?1
+             ORDER BY 5",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![until], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .into_iter()
+        .filter_map(
+            |(kind, station_id, token_id, name, expires_at, reminded_for)| {
+                let station_name = station_names.get(&station_id)?.clone();
+                Some(ExpiringItem {
+                    kind: if kind == "token" {
+                        ExpiryKind::Token
+                    } else {
+                        ExpiryKind::Subscription
+                    },
+                    station_id,
+                    station_name,
+                    token_id,
+                    name,
+                    expires_at,
+                    days_left: (expires_at - now).div_euclid(DAY_SECONDS),
+                    reminded: reminded_for == Some(expires_at),
+                })
+            },
+        )
+        .collect())
+}
+
+fn mark_reminded(conn: &Connection, item: &ExpiringItem) -> Result<(), String> {
+    match item.kind {
+        ExpiryKind::Subscription => conn.execute(
+            "UPDATE station_renewals SET reminded_for = ?1 WHERE station_id = ?2",
+            params![item.expires_at, item.station_id],
+        ),
+        ExpiryKind::Token => conn.execute(
+            "UPDATE station_token_expiries SET reminded_for = ?1
+             WHERE station_id = ?2 AND token_id = ?3",
+            params![item.expires_at, item.station_id, item.token_id],
+        ),
+    }
+    .map_err(|e| format!("Failed to update renewal reminder: {}", e))?;
+    Ok(())
+}
+
+pub fn load_renewal_settings(conn: &Connection) -> RenewalSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![RENEWAL_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn list_stations(app: &AppHandle) -> Result<Vec<RelayStation>, String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state
+        .lock()
+        .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+    manager
+        .list_stations()
+        .map_err(|_e| t!("relay.failed_to_list_stations", "error" => &_e.to_string()))
+}
+
+fn station_names(stations: &[RelayStation]) -> HashMap<String, String> {
+    stations
+        .iter()
+        .map(|station| (station.id.clone(), station.name.clone()))
+        .collect()
+}
+
+/// Fetch the tokens of every enabled station, track their expiry dates and
+/// check their quota; skipped in offline mode
+async fn refresh_tokens(app: &AppHandle, stations: &[RelayStation]) {
+    if is_offline(app) {
+        return;
+    }
+    for station in stations.iter().filter(|station| station.enabled) {
+        let adapter = create_station_adapter(app, station);
+        let cancel = CancellationToken::new();
+        let tokens = match adapter
+            .list_tokens(station, Some(1), Some(TOKEN_PAGE_SIZE), &cancel)
+            .await
+        {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                log::debug!("Failed to list the tokens of {}: {}", station.name, e);
+                continue;
+            }
+        };
+        let complete = tokens.total <= tokens.items.len() as i64;
+        {
+            let db = app.state::<AgentDb>();
+            let Ok(conn) = db.0.lock() else {
+                return;
+            };
+            if let Err(e) = track_tokens(&conn, &station.id, &tokens.items, complete) {
+                log::warn!("{}", e);
+            }
+        }
+        if let Err(e) = check_tokens(app, station, &tokens.items, complete).await {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+fn reminder_text(item: &ExpiringItem) -> (String, String) {
+    let date = Local
+        .timestamp_opt(item.expires_at, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let name = item.name.as_deref().unwrap_or_default();
+    match item.kind {
+        ExpiryKind::Token => (
+            t!("renewals.token_title", "name" => name),
+            t!("renewals.token_body", "name" => name, "station" => &item.station_name, "date" => &date),
+        ),
+        ExpiryKind::Subscription => (
+            t!("renewals.subscription_title", "station" => &item.station_name),
+            t!("renewals.subscription_body", "station" => &item.station_name, "date" => &date),
+        ),
+    }
+}
+
+/// Refresh the tokens, then remind of every item not reminded of yet
+async fn check_renewals(app: &AppHandle) -> Result<(), String> {
+    let stations = list_stations(app)?;
+    refresh_tokens(app, &stations).await;
+    let settings = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_renewal_settings(&conn)
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let due = expiring_items(&conn, now, settings.days_before, &station_names(&stations))?;
+    for item in due.into_iter().filter(|item| !item.reminded) {
+        mark_reminded(&conn, &item)?;
+        log::info!(
+            "Reminding of {:?} of {} expiring in {} days",
+            item.kind,
+            item.station_name,
+            item.days_left
+        );
+        let (title, body) = reminder_text(&item);
+        if let Err(e) = crate::commands::notifications::show(app, &title, &body) {
+            log::warn!("{}", e);
+        }
+        let _ = app.emit("renewal-reminder", &item);
+    }
+    Ok(())
+}
+
+/// Check for expiring items every few hours; called once from the app setup
+pub fn start_renewal_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = check_renewals(&app).await {
+                log::warn!("Failed to check renewal reminders: {}", e);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_renewal_settings(db: State<'_, AgentDb>) -> Result<RenewalSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_renewal_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_renewal_settings(
+    db: State<'_, AgentDb>,
+    settings: RenewalSettings,
+) -> Result<RenewalSettings, String> {
+    if !(1..=MAX_WINDOW_DAYS).contains(&settings.days_before) {
+        return Err(format!(
+            "Remind between 1 and {} days before an expiry",
+            MAX_WINDOW_DAYS
+        ));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![RENEWAL_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save renewal reminder settings: {}", e))?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_station_renewal(
+    db: State<'_, AgentDb>,
+    station_id: String,
+) -> Result<Option<StationRenewal>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_renewal(&conn, &station_id)
+}
+
+/// Set the date a station's subscription renews at (Unix timestamp), or
+/// remove it with `None`
+#[tauri::command]
+pub async fn set_station_renewal(
+    station_id: String,
+    renews_at: Option<i64>,
+    note: Option<String>,
+    app: AppHandle,
+) -> Result<Option<StationRenewal>, String> {
+    if !list_stations(&app)?
+        .iter()
+        .any(|station| station.id == station_id)
+    {
+        return Err(t!("relay.station_not_found"));
+    }
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_renewal(&conn, &station_id, renews_at, note.as_deref())?;
+    load_renewal(&conn, &station_id)
+}
+
+/// Tokens and subscriptions expiring within `days` (the reminder setting by
+/// default, at most a year), expired ones included; for the dashboard
+#[tauri::command]
+pub async fn list_expiring_items(
+    days: Option<i64>,
+    app: AppHandle,
+) -> Result<Vec<ExpiringItem>, String> {
+    let stations = list_stations(&app)?;
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let days = days
+        .unwrap_or_else(|| load_renewal_settings(&conn).days_before)
+        .clamp(0, MAX_WINDOW_DAYS);
+    expiring_items(
+        &conn,
+        chrono::Utc::now().timestamp(),
+        days,
+        &station_names(&stations),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: &str, expires_at: Option<i64>) -> RelayStationToken {
+        RelayStationToken {
+            id: id.to_string(),
+            station_id: "s1".to_string(),
+            name: format!("token {}", id),
+            token: "sk-test".to_string(),
+            user_id: None,
+            enabled: true,
+            expires_at,
+            group: None,
+            remain_quota: None,
+            unlimited_quota: None,
+            metadata: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_expiring_items() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_renewal_tables(&conn).unwrap();
+        let now = 1_792_164_600;
+        let names = HashMap::from([("s1".to_string(), "Station".to_string())]);
+
+        let tokens = vec![
+            token("1", Some(now + 3 * DAY_SECONDS + 60)),
+            token("2", Some(-1)),
+            token("3", Some(now + 30 * DAY_SECONDS)),
+            token("4", Some(now - 60)),
+        ];
+        track_tokens(&conn, "s1", &tokens, true).unwrap();
+        save_renewal(&conn, "s1", Some(now + DAY_SECONDS), Some(" Pro plan ")).unwrap();
+        save_renewal(&conn, "deleted", Some(now), None).unwrap();
+
+        let items = expiring_items(&conn, now, 7, &names).unwrap();
+        let summary: Vec<(ExpiryKind, Option<&str>, i64)> = items
+            .iter()
+            .map(|item| (item.kind, item.name.as_deref(), item.days_left))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ExpiryKind::Token, Some("token 4"), -1),
+                (ExpiryKind::Subscription, Some("Pro plan"), 1),
+                (ExpiryKind::Token, Some("token 1"), 3),
+            ]
+        );
+
+        // Reminded once per expiry date
+        mark_reminded(&conn, &items[2]).unwrap();
+        track_tokens(&conn, "s1", &tokens[..1], false).unwrap();
+        assert!(expiring_items(&conn, now, 7, &names).unwrap()[2].reminded);
+        track_tokens(&conn, "s1", &tokens, true).unwrap();
+        assert!(expiring_items(&conn, now, 7, &names).unwrap()[2].reminded);
+        let moved = [token("1", Some(now + 2 * DAY_SECONDS))];
+        track_tokens(&conn, "s1", &moved, false).unwrap();
+        let items = expiring_items(&conn, now, 7, &names).unwrap();
+        assert!(items.iter().all(|item| !item.reminded));
+
+        // A complete list forgets removed tokens
+        track_tokens(&conn, "s1", &tokens[1..2], true).unwrap();
+        save_renewal(&conn, "s1", None, None).unwrap();
+        assert!(expiring_items(&conn, now, 7, &names).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,354 @@
+//! Passphrase-encrypted share strings for a relay station or a single token
+//!
+//! A share is `cwbs1.` followed by base64url of the salt, the nonce and the
+//! ChaCha20-Poly1305 sealed, zstd-compressed station JSON. The key is derived
+//! from the passphrase with PBKDF2-HMAC-SHA256, so the string can travel over
+//! chat or a QR code while the passphrase is handed over some other way.
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::app_lock::{self, ProtectedAction};
+use super::relay_adapters::CancellationToken;
+use super::relay_stations::{
+    create_station_adapter, AuthMethod, RelayStation, RelayStationAdapter, RelayStationExport,
+    RelayStationExportItem, RelayStationManager,
+};
+use super::relay_token_quota;
+use crate::commands::agents::AgentDb;
+use crate::t;
+
+/// Marks a share string and its format version
+pub const SHARE_PREFIX: &str = "cwbs1.";
+
+pub(crate) const SALT_LEN: usize = 16;
+pub(crate) const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 200_000;
+pub(crate) const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Letters and digits that cannot be mistaken for one another
+const PASSPHRASE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Pages of tokens searched for a shared token missing from the cache
+const MAX_TOKEN_PAGES: usize = 10;
+const TOKEN_PAGE_SIZE: usize = 100;
+
+/// What a share string holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationShare {
+    pub station: RelayStationExportItem,
+    /// Name of the token, when a token is shared rather than the station
+    pub token_name: Option<String>,
+    pub shared_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareResult {
+    pub payload: String,
+    /// `claude-workbench://import-station` link carrying the payload
+    pub link: String,
+    /// SVG QR code of the link
+    pub qr_svg: String,
+    pub passphrase: String,
+    /// The passphrase was generated rather than chosen
+    pub generated_passphrase: bool,
+}
+
+/// A random passphrase like `k7mq-2xpf-9hrt-c4wd`
+fn generate_passphrase() -> String {
+    (0..4)
+        .map(|_| (0..4).map(|_| random_passphrase_char()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A uniformly drawn letter of the passphrase alphabet
+fn random_passphrase_char() -> char {
+    // Bytes beyond the last whole multiple of the alphabet size would make
+    // its first letters more likely, so those are drawn again
+    let limit = 256 - 256 % PASSPHRASE_ALPHABET.len();
+    loop {
+        let byte = (OsRng.next_u32() & 0xff) as usize;
+        if byte < limit {
+            return PASSPHRASE_ALPHABET[byte % PASSPHRASE_ALPHABET.len()] as char;
+        }
+    }
+}
+
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = Key::default();
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
+        passphrase.as_bytes(),
+        salt,
+        PBKDF2_ROUNDS,
+        key.as_mut_slice(),
+    );
+    key
+}
+
+/// Whether a payload is an encrypted share rather than a plain export
+pub fn is_share(payload: &str) -> bool {
+    payload.trim().starts_with(SHARE_PREFIX)
+}
+
+pub fn encrypt_share(share: &StationShare, passphrase: &str) -> Result<String, String> {
+    let json = serde_json::to_vec(share).map_err(|e| e.to_string())?;
+    let compressed = zstd::stream::encode_all(json.as_slice(), 19)
+        .map_err(|e| format!("Failed to compress the share: {}", e))?;
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = ChaCha20Poly1305::new(&derive_key(passphrase, &salt))
+        .encrypt(&nonce, compressed.as_slice())
+        .map_err(|e| format!("Failed to encrypt the share: {}", e))?;
+
+    let mut bytes = Vec::with_capacity(SALT_LEN + NONCE_LEN + sealed.len());
+    bytes.extend_from_slice(&salt);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&sealed);
+    Ok(format!(
+        "{}{}",
+        SHARE_PREFIX,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    ))
+}
+
+pub fn decrypt_share(payload: &str, passphrase: &str) -> Result<StationShare, String> {
+    let encoded = payload
+        .trim()
+        .strip_prefix(SHARE_PREFIX)
+        .ok_or("Not a station share")?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid station share: {}", e))?;
+    if bytes.len() <= SALT_LEN + NONCE_LEN {
+        return Err("Invalid station share: too short".to_string());
+    }
+    let (salt, rest) = bytes.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let compressed = ChaCha20Poly1305::new(&derive_key(passphrase, salt))
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| "Wrong passphrase or damaged share".to_string())?;
+    let json = zstd::stream::decode_all(compressed.as_slice())
+        .map_err(|e| format!("Invalid station share: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Invalid station share: {}", e))
+}
+
+fn qr_svg(data: &str) -> Result<String, String> {
+    let code = qrcode::QrCode::new(data.as_bytes())
+        .map_err(|e| format!("Failed to make the QR code: {}", e))?;
+    Ok(code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(256, 256)
+        .build())
+}
+
+/// Station entry for a single token: applying it only needs the URL and key,
+/// which custom stations keep as their system token
+fn token_station(station: &RelayStation, token_name: &str, key: &str) -> RelayStationExportItem {
+    RelayStationExportItem {
+        name: format!("{} - {}", station.name, token_name),
+        description: station.description.clone(),
+        api_url: station.api_url.clone(),
+        adapter: RelayStationAdapter::Custom,
+        auth_method: AuthMethod::BearerToken,
+        system_token: format!("sk-{}", key.trim_start_matches("sk-")),
+        user_id: None,
+        adapter_config: None,
+        enabled: true,
+    }
+}
+
+/// Name and key of a station token, from the cache or the station
+async fn find_token(
+    station: &RelayStation,
+    token_id: &str,
+    app: &AppHandle,
+) -> Result<(String, String), String> {
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Some(token) = relay_token_quota::load_tokens(&conn, &station.id)?
+            .into_iter()
+            .find(|token| token.token_id == token_id)
+        {
+            return Ok((token.token_name, token.key));
+        }
+    }
+    let adapter = create_station_adapter(app, station);
+    let cancel = CancellationToken::new();
+    for page in 1..=MAX_TOKEN_PAGES {
+        let response = adapter
+            .list_tokens(station, Some(page), Some(TOKEN_PAGE_SIZE), &cancel)
+            .await
+            .map_err(|_e| t!("relay.failed_to_list_tokens", "error" => &_e.to_string()))?;
+        if let Some(token) = response.items.iter().find(|token| token.id == token_id) {
+            return Ok((token.name.clone(), token.token.clone()));
+        }
+        if response.items.len() < TOKEN_PAGE_SIZE {
+            break;
+        }
+    }
+    Err(format!("Token {} not found on {}", token_id, station.name))
+}
+
+/// Encrypt a station, or with `token_id` only that token, into a share
+/// string, link and QR code; a passphrase is generated when none is given
+#[tauri::command]
+pub async fn share_relay_station(
+    station_id: String,
+    token_id: Option<String>,
+    passphrase: Option<String>,
+    app: AppHandle,
+) -> Result<ShareResult, String> {
+    app_lock::require_unlocked(&app, ProtectedAction::ExportStations).await?;
+    let (passphrase, generated_passphrase) = match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) if passphrase.chars().count() < MIN_PASSPHRASE_LEN => {
+            return Err(format!(
+                "The passphrase needs at least {} characters",
+                MIN_PASSPHRASE_LEN
+            ))
+        }
+        Some(passphrase) => (passphrase, false),
+        None => (generate_passphrase(), true),
+    };
+    let (station, export) = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        let station = manager
+            .get_station(&station_id)
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+            .ok_or_else(|| t!("relay.station_not_found"))?;
+        let export = manager
+            .export_stations(Some(vec![station_id.clone()]))
+            .map_err(|_e| t!("relay.failed_to_export_stations", "error" => &_e.to_string()))?;
+        (station, export)
+    };
+
+    let share = match token_id {
+        Some(token_id) => {
+            let (token_name, key) = find_token(&station, &token_id, &app).await?;
+            StationShare {
+                station: token_station(&station, &token_name, &key),
+                token_name: Some(token_name),
+                shared_at: chrono::Utc::now().timestamp(),
+            }
+        }
+        None => StationShare {
+            station: export
+                .stations
+                .into_iter()
+                .next()
+                .ok_or_else(|| t!("relay.station_not_found"))?,
+            token_name: None,
+            shared_at: chrono::Utc::now().timestamp(),
+        },
+    };
+    let payload = encrypt_share(&share, &passphrase)?;
+    let link = format!(
+        "{}://import-station?payload={}",
+        crate::deep_link::SCHEME,
+        payload
+    );
+    Ok(ShareResult {
+        qr_svg: qr_svg(&link)?,
+        payload,
+        link,
+        passphrase,
+        generated_passphrase,
+    })
+}
+
+/// Decrypt a share without importing it, to show what it holds
+#[tauri::command]
+pub async fn preview_station_share(
+    payload: String,
+    passphrase: String,
+) -> Result<StationShare, String> {
+    let mut share = decrypt_share(&payload, &passphrase)?;
+    share.station.system_token = String::new();
+    Ok(share)
+}
+
+/// Decrypt a share and add its station; a station of the same name is only
+/// replaced with `overwrite_existing`
+#[tauri::command]
+pub async fn import_station_share(
+    payload: String,
+    passphrase: String,
+    overwrite_existing: Option<bool>,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    let share = decrypt_share(&payload, &passphrase)?;
+    let export = RelayStationExport {
+        version: 1,
+        exported_at: share.shared_at,
+        stations: vec![share.station],
+    };
+    let imported = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .import_stations(&export, overwrite_existing.unwrap_or(false))
+            .map_err(|_e| t!("relay.failed_to_import_stations", "error" => &_e.to_string()))?
+    };
+    crate::tray::refresh_menu(&app);
+    let _ = app.emit("relay-stations-changed", &imported);
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_round_trip() {
+        let share = StationShare {
+            station: RelayStationExportItem {
+                name: "Team station".to_string(),
+                description: None,
+                api_url: "https://relay.example.com".to_string(),
+                adapter: RelayStationAdapter::Newapi,
+                auth_method: AuthMethod::BearerToken,
+                system_token: "secret-system-token".to_string(),
+                user_id: Some("42".to_string()),
+                adapter_config: None,
+                enabled: true,
+            },
+            token_name: None,
+            shared_at: 1_792_164_600,
+        };
+        let payload = encrypt_share(&share, "correct horse").unwrap();
+        assert!(is_share(&payload));
+        assert!(!payload.contains("secret"));
+        // Fresh salt and nonce every time
+        assert_ne!(payload, encrypt_share(&share, "correct horse").unwrap());
+
+        let decrypted = decrypt_share(&payload, "correct horse").unwrap();
+        assert_eq!(decrypted.station.system_token, "secret-system-token");
+        assert_eq!(decrypted.station.user_id.as_deref(), Some("42"));
+        assert!(decrypt_share(&payload, "wrong horse").is_err());
+        assert!(decrypt_share("cwbs1.AAAA", "correct horse").is_err());
+
+        let passphrase = generate_passphrase();
+        assert_eq!(passphrase.len(), 19);
+        assert_eq!(passphrase.matches('-').count(), 3);
+        assert!(passphrase
+            .bytes()
+            .all(|byte| byte == b'-' || PASSPHRASE_ALPHABET.contains(&byte)));
+    }
+}
@@ -1,9 +1,3 @@
-// Simple macro for internationalization - returns the key as a string for now
-macro_rules! t {
-    ($key:expr $(, $($name:expr => $value:expr),+)?) => {
-        $key.to_string()
-    };
-}
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -13,8 +7,14 @@ use uuid::Uuid;
 use anyhow::{Result, anyhow};
 use rusqlite::{params, Connection};
 use std::sync::Mutex;
+use crate::t;
+use crate::commands::provider::{self, ProviderConfig};
+use crate::commands::app_lock::{self, ProtectedAction};
 
-use super::relay_adapters::{NewApiAdapter, YourApiAdapter, CustomAdapter};
+use super::relay_adapters::groups::TokenGroupSuggestion;
+use super::relay_adapters::{hints, plugin, NewApiAdapter, YourApiAdapter, CustomAdapter, PluginAdapter, OfflineAdapter, CancellationToken, RelayRequestRegistry};
+use super::currency::{self, Converted};
+use super::relay_offline::{fetch_or_cached, Cached};
 
 /// Relay station adapter type for different station implementations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,6 +107,14 @@ pub struct UserInfo {
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// User information with the currency its balance is in
+#[derive(Debug, Clone, Serialize)]
+pub struct StationUserInfo {
+    #[serde(flatten)]
+    pub info: UserInfo,
+    pub station_currency: String,
+}
+
 /// Log entry from a relay station
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StationLogEntry {
@@ -257,11 +265,11 @@ pub struct RelayStationExportItem {
 pub trait StationAdapter: Send + Sync {
     async fn get_station_info(&self, station: &RelayStation) -> Result<StationInfo>;
     async fn get_user_info(&self, station: &RelayStation, user_id: &str) -> Result<UserInfo>;
-    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>) -> Result<LogPaginationResponse>;
+    async fn get_logs(&self, station: &RelayStation, page: Option<usize>, page_size: Option<usize>, filters: Option<serde_json::Value>, cancel: &CancellationToken) -> Result<LogPaginationResponse>;
     async fn test_connection(&self, station: &RelayStation) -> Result<ConnectionTestResult>;
     
     // Token management methods
-    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>) -> Result<TokenPaginationResponse>;
+    async fn list_tokens(&self, station: &RelayStation, page: Option<usize>, size: Option<usize>, cancel: &CancellationToken) -> Result<TokenPaginationResponse>;
     async fn create_token(&self, station: &RelayStation, token_data: &CreateTokenRequest) -> Result<RelayStationToken>;
     async fn update_token(&self, station: &RelayStation, token_id: &str, token_data: &UpdateTokenRequest) -> Result<RelayStationToken>;
     async fn delete_token(&self, station: &RelayStation, token_id: &str) -> Result<()>;
@@ -269,6 +277,9 @@ pub trait StationAdapter: Send + Sync {
     
     // User groups management
     async fn get_user_groups(&self, station: &RelayStation) -> Result<serde_json::Value>;
+
+    /// Models the user may call on the station
+    async fn get_user_models(&self, station: &RelayStation) -> Result<Vec<String>>;
 }
 
 
@@ -282,6 +293,19 @@ pub fn create_adapter(adapter_type: &RelayStationAdapter) -> Box<dyn StationAdap
     }
 }
 
+/// Adapter for a station: the plugin named in its adapter_config, or the built-in one for its type
+///
+/// In offline mode every caller gets an adapter that refuses to contact the station.
+pub fn create_station_adapter(app: &AppHandle, station: &RelayStation) -> Box<dyn StationAdapter> {
+    if super::relay_offline::is_offline(app) {
+        return Box::new(OfflineAdapter);
+    }
+    match PluginAdapter::for_station(station) {
+        Some(adapter) => Box::new(adapter),
+        None => create_adapter(&station.adapter),
+    }
+}
+
 /// Database manager for relay stations
 pub struct RelayStationManager {
     db: Arc<Mutex<Connection>>,
@@ -772,11 +796,25 @@ impl RelayStationManager {
         Ok(())
     }
 
+    /// Replace a station's adapter_config
+    pub fn set_adapter_config(&self, station_id: &str, config: Option<&HashMap<String, serde_json::Value>>) -> Result<()> {
+        let conn = self.db.lock().unwrap();
+        let config_str = config.map(serde_json::to_string).transpose()?;
+        let updated = conn.execute(
+            "UPDATE relay_stations SET adapter_config = ?1, updated_at = ?2 WHERE id = ?3",
+            params![config_str, Utc::now().timestamp(), station_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow!("Station {} not found", station_id));
+        }
+        Ok(())
+    }
+
     /// Export relay stations to JSON format
     pub fn export_stations(&self, station_ids: Option<Vec<String>>) -> Result<RelayStationExport> {
         let conn = self.db.lock().unwrap();
         
-        let stations = if let Some(ids) = station_ids {
+        let mut stations = if let Some(ids) = station_ids {
             // Export specific stations
             let mut stations = Vec::new();
             for id in ids {
@@ -855,6 +893,9 @@ impl RelayStationManager {
 
             station_iter.collect::<Result<Vec<_>, _>>().map_err(|e| anyhow!("Database error: {}", e))?
         };
+        for station in &mut stations {
+            plugin::strip_binding(&mut station.adapter_config);
+        }
 
         Ok(RelayStationExport {
             version: 1,
@@ -889,7 +930,9 @@ impl RelayStationManager {
                 Uuid::new_v4().to_string()
             };
 
-            let adapter_config_str = if let Some(config) = &station_data.adapter_config {
+            let mut adapter_config = station_data.adapter_config.clone();
+            plugin::strip_binding(&mut adapter_config);
+            let adapter_config_str = if let Some(config) = &adapter_config {
                 Some(serde_json::to_string(config)?)
             } else {
                 None
@@ -989,13 +1032,25 @@ impl RelayStationManager {
 
 // Tauri command handlers
 
+/// Mask the system token of a station the window gets while the app lock
+/// hides secrets
+fn mask_station(hide: bool, mut station: RelayStation) -> RelayStation {
+    if hide {
+        station.system_token = provider::mask_secret(&station.system_token);
+    }
+    station
+}
+
 #[tauri::command]
 pub async fn list_relay_stations(app: AppHandle) -> Result<Vec<RelayStation>, String> {
+    let hide = app_lock::hides_secrets(&app);
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
-        manager.list_stations().map_err(|_e| t!("relay.failed_to_list_stations", "error" => &_e.to_string()))
+        manager.list_stations()
+            .map(|stations| stations.into_iter().map(|station| mask_station(hide, station)).collect())
+            .map_err(|_e| t!("relay.failed_to_list_stations", "error" => &_e.to_string()))
     } else {
         Ok(Vec::new()) // Return empty list if manager not initialized
     }
@@ -1003,11 +1058,14 @@ pub async fn list_relay_stations(app: AppHandle) -> Result<Vec<RelayStation>, St
 
 #[tauri::command]
 pub async fn get_relay_station(station_id: String, app: AppHandle) -> Result<Option<RelayStation>, String> {
+    let hide = app_lock::hides_secrets(&app);
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
-        manager.get_station(&station_id).map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))
+        manager.get_station(&station_id)
+            .map(|station| station.map(|station| mask_station(hide, station)))
+            .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))
     } else {
         Ok(None)
     }
@@ -1022,6 +1080,8 @@ pub async fn add_relay_station(
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
+        let mut adapter_config = station_request.adapter_config;
+        plugin::strip_binding(&mut adapter_config);
         let station = RelayStation {
             id: Uuid::new_v4().to_string(),
             name: station_request.name,
@@ -1031,13 +1091,15 @@ pub async fn add_relay_station(
             auth_method: station_request.auth_method,
             system_token: station_request.system_token,
             user_id: station_request.user_id,
-            adapter_config: station_request.adapter_config,
+            adapter_config,
             enabled: station_request.enabled,
             created_at: Utc::now().timestamp(),
             updated_at: Utc::now().timestamp(),
         };
         
         manager.add_station(&station).map_err(|_e| t!("relay.failed_to_add_station", "error" => &_e.to_string()))?;
+        drop(manager_lock);
+        crate::tray::refresh_menu(&app);
         Ok(t!("relay.station_add_success"))
     } else {
         Err(t!("relay.manager_not_initialized"))
@@ -1047,14 +1109,20 @@ pub async fn add_relay_station(
 #[tauri::command]
 pub async fn update_relay_station(
     station_id: String,
-    updates: HashMap<String, serde_json::Value>,
+    mut updates: HashMap<String, serde_json::Value>,
     app: AppHandle,
 ) -> Result<String, String> {
+    // A masked token sent back from a locked window keeps the stored one
+    if updates.get("system_token").and_then(|v| v.as_str()).is_some_and(provider::is_masked) {
+        updates.remove("system_token");
+    }
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     
     if let Some(manager) = manager_lock.as_ref() {
         manager.update_station(&station_id, &updates).map_err(|_e| t!("relay.failed_to_update_station", "error" => &_e.to_string()))?;
+        drop(manager_lock);
+        crate::tray::refresh_menu(&app);
         Ok(t!("relay.station_update_success"))
     } else {
         Err(t!("relay.manager_not_initialized"))
@@ -1068,6 +1136,26 @@ pub async fn delete_relay_station(station_id: String, app: AppHandle) -> Result<
     
     if let Some(manager) = manager_lock.as_ref() {
         manager.delete_station(&station_id).map_err(|_e| t!("relay.failed_to_delete_station", "error" => &_e.to_string()))?;
+        drop(manager_lock);
+        let db = app.state::<crate::commands::agents::AgentDb>();
+        if let Ok(conn) = db.0.lock() {
+            if let Err(e) = super::relay_log_cache::clear_cache(&conn, &station_id) {
+                log::warn!("{}", e);
+            }
+            if let Err(e) = super::relay_renewals::clear_station(&conn, &station_id) {
+                log::warn!("{}", e);
+            }
+            if let Err(e) = super::relay_token_quota::clear_station(&conn, &station_id) {
+                log::warn!("{}", e);
+            }
+            if let Err(e) = super::relay_rate_usage::clear_station(&conn, &station_id) {
+                log::warn!("{}", e);
+            }
+            if let Err(e) = super::relay_offline::clear_station(&conn, &station_id) {
+                log::warn!("{}", e);
+            }
+        }
+        crate::tray::refresh_menu(&app);
         Ok(t!("relay.station_delete_success"))
     } else {
         Err(t!("relay.manager_not_initialized"))
@@ -1075,7 +1163,7 @@ pub async fn delete_relay_station(station_id: String, app: AppHandle) -> Result<
 }
 
 #[tauri::command]
-pub async fn get_station_info(station_id: String, app: AppHandle) -> Result<StationInfo, String> {
+pub async fn get_station_info(station_id: String, app: AppHandle) -> Result<Cached<StationInfo>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get the station first, releasing the lock before the async call
@@ -1089,15 +1177,27 @@ pub async fn get_station_info(station_id: String, app: AppHandle) -> Result<Stat
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.get_station_info(&station).await.map_err(|_e| t!("relay.failed_to_get_station_info", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        fetch_or_cached(&app, &station.id, "station_info", "", adapter.get_station_info(&station))
+            .await
+            .map_err(|_e| t!("relay.failed_to_get_station_info", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
 }
 
+/// Mask token keys the window gets while the app lock hides secrets
+fn mask_tokens(app: &AppHandle, mut tokens: Cached<TokenPaginationResponse>) -> Cached<TokenPaginationResponse> {
+    if app_lock::hides_secrets(app) {
+        for token in &mut tokens.data.items {
+            token.token = provider::mask_secret(&token.token);
+        }
+    }
+    tokens
+}
+
 #[tauri::command]
-pub async fn list_station_tokens(station_id: String, page: Option<usize>, size: Option<usize>, app: AppHandle) -> Result<TokenPaginationResponse, String> {
+pub async fn list_station_tokens(station_id: String, page: Option<usize>, size: Option<usize>, request_id: Option<String>, app: AppHandle) -> Result<Cached<TokenPaginationResponse>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get the station first, releasing the lock before the async call
@@ -1106,25 +1206,47 @@ pub async fn list_station_tokens(station_id: String, page: Option<usize>, size:
         if let Some(manager) = manager_lock.as_ref() {
             manager.get_station(&station_id).map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
         } else {
-            return Ok(TokenPaginationResponse {
+            return Ok(Cached::fresh(TokenPaginationResponse {
                 items: Vec::new(),
                 page: 1,
                 page_size: 10,
                 total: 0,
-            });
+            }));
         }
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.list_tokens(&station, page, size).await.map_err(|_e| t!("relay.failed_to_list_tokens", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        let requests: State<RelayRequestRegistry> = app.state();
+        let cancel = requests.begin(request_id.as_deref());
+        let request = format!("{:?}/{:?}", page, size);
+        let result = fetch_or_cached(&app, &station.id, "tokens", &request, adapter.list_tokens(&station, page, size, &cancel)).await;
+        requests.finish(request_id.as_deref(), &cancel);
+        let cached = result.map_err(|_e| t!("relay.failed_to_list_tokens", "error" => &hints::with_hint(&_e)))?;
+        if cached.cached.is_some() {
+            return Ok(mask_tokens(&app, cached));
+        }
+        let response = &cached.data;
+        // Track expiry dates for renewal reminders
+        let complete = page.unwrap_or(1) <= 1 && response.total <= response.items.len() as i64;
+        let db = app.state::<crate::commands::agents::AgentDb>();
+        if let Ok(conn) = db.0.lock() {
+            if let Err(e) = super::relay_renewals::track_tokens(&conn, &station.id, &response.items, complete) {
+                log::warn!("{}", e);
+            }
+        }
+        // Warn about tokens running low on quota
+        if let Err(e) = super::relay_token_quota::check_tokens(&app, &station, &response.items, complete).await {
+            log::warn!("{}", e);
+        }
+        Ok(mask_tokens(&app, cached))
     } else {
-        Ok(TokenPaginationResponse {
+        Ok(Cached::fresh(TokenPaginationResponse {
             items: Vec::new(),
             page: 1,
             page_size: 10,
             total: 0,
-        })
+        }))
     }
 }
 
@@ -1147,8 +1269,8 @@ pub async fn add_station_token(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.create_token(&station, &token_data).await.map_err(|_e| t!("relay.failed_to_create_token", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        adapter.create_token(&station, &token_data).await.map_err(|_e| t!("relay.failed_to_create_token", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1174,8 +1296,8 @@ pub async fn update_station_token(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.update_token(&station, &token_id, &token_data).await.map_err(|_e| t!("relay.failed_to_update_token", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        adapter.update_token(&station, &token_id, &token_data).await.map_err(|_e| t!("relay.failed_to_update_token", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1200,8 +1322,8 @@ pub async fn delete_station_token(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.delete_token(&station, &token_id).await.map_err(|_e| t!("relay.failed_to_delete_token", "error" => &_e.to_string()))?;
+        let adapter = create_station_adapter(&app, &station);
+        adapter.delete_token(&station, &token_id).await.map_err(|_e| t!("relay.failed_to_delete_token", "error" => &hints::with_hint(&_e)))?;
         Ok(t!("relay.token_delete_success"))
     } else {
         Err(t!("relay.station_not_found"))
@@ -1213,7 +1335,7 @@ pub async fn get_token_user_info(
     station_id: String,
     user_id: String,
     app: AppHandle,
-) -> Result<UserInfo, String> {
+) -> Result<Cached<Converted<StationUserInfo>>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get station data first, releasing the lock before async call
@@ -1227,9 +1349,20 @@ pub async fn get_token_user_info(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
+        let adapter = create_station_adapter(&app, &station);
         // Use the provided user_id directly (from station configuration)
-        adapter.get_user_info(&station, &user_id).await.map_err(|_e| t!("relay.failed_to_get_user_info", "error" => &_e.to_string()))
+        let info = fetch_or_cached(&app, &station.id, "user_info", &user_id, adapter.get_user_info(&station, &user_id))
+            .await
+            .map_err(|_e| t!("relay.failed_to_get_user_info", "error" => &hints::with_hint(&_e)))?;
+        // Keep the balance for spend forecasts
+        let db = app.state::<crate::commands::agents::AgentDb>();
+        if let (None, Ok(conn)) = (&info.cached, db.0.lock()) {
+            if let Err(e) = super::relay_forecast::record_snapshot(&conn, &station.id, &info.data) {
+                log::warn!("{}", e);
+            }
+        }
+        let station_currency = currency::station_currency(station.adapter_config.as_ref());
+        Ok(info.map(|info| Converted(StationUserInfo { info, station_currency })))
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1241,8 +1374,9 @@ pub async fn get_station_logs(
     page: Option<usize>,
     page_size: Option<usize>,
     filters: Option<serde_json::Value>,
+    request_id: Option<String>,
     app: AppHandle,
-) -> Result<LogPaginationResponse, String> {
+) -> Result<Cached<LogPaginationResponse>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get the station first, releasing the lock before the async call
@@ -1256,13 +1390,33 @@ pub async fn get_station_logs(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.get_logs(&station, page, page_size, filters).await.map_err(|_e| t!("relay.failed_to_get_logs", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        let requests: State<RelayRequestRegistry> = app.state();
+        let cancel = requests.begin(request_id.as_deref());
+        let request = format!("{:?}/{:?}/{}", page, page_size, filters.as_ref().map(|f| f.to_string()).unwrap_or_default());
+        let result = fetch_or_cached(&app, &station.id, "logs", &request, adapter.get_logs(&station, page, page_size, filters, &cancel)).await;
+        requests.finish(request_id.as_deref(), &cancel);
+        let response = result.map_err(|_e| t!("relay.failed_to_get_logs", "error" => &hints::with_hint(&_e)))?;
+        // Mirror the page for offline viewing
+        let db = app.state::<crate::commands::agents::AgentDb>();
+        if let (None, Ok(conn)) = (&response.cached, db.0.lock()) {
+            if let Err(e) = super::relay_log_cache::store_logs(&conn, &station.id, &response.data.items) {
+                log::warn!("{}", e);
+            }
+        }
+        Ok(response)
     } else {
         Err(t!("relay.station_not_found"))
     }
 }
 
+/// Abort an in-flight `get_station_logs`/`list_station_tokens` call started with `request_id`
+#[tauri::command]
+pub fn cancel_relay_request(request_id: String, app: AppHandle) -> bool {
+    let requests: State<RelayRequestRegistry> = app.state();
+    requests.cancel(&request_id)
+}
+
 #[tauri::command]
 pub async fn test_station_connection(station_id: String, app: AppHandle) -> Result<ConnectionTestResult, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
@@ -1278,15 +1432,31 @@ pub async fn test_station_connection(station_id: String, app: AppHandle) -> Resu
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.test_connection(&station).await.map_err(|_e| t!("relay.failed_to_test_connection", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        let result = adapter.test_connection(&station).await;
+        let failure = match &result {
+            Ok(test) if test.success => None,
+            Ok(test) => Some(test.message.clone()),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(reason) = failure {
+            crate::commands::webhooks::dispatch(
+                &app,
+                crate::commands::webhooks::WebhookMessage {
+                    event: crate::commands::webhooks::WebhookEvent::StationDown,
+                    title: format!("Relay station {} is down", station.name),
+                    text: format!("{}\n{}", station.api_url, reason),
+                },
+            );
+        }
+        result.map_err(|_e| t!("relay.failed_to_test_connection", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
 }
 
 #[tauri::command]
-pub async fn api_user_self_groups(station_id: String, app: AppHandle) -> Result<serde_json::Value, String> {
+pub async fn api_user_self_groups(station_id: String, app: AppHandle) -> Result<Cached<serde_json::Value>, String> {
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     // Get the station first, releasing the lock before the async call
@@ -1300,13 +1470,22 @@ pub async fn api_user_self_groups(station_id: String, app: AppHandle) -> Result<
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.get_user_groups(&station).await.map_err(|_e| t!("relay.failed_to_get_user_groups", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        fetch_or_cached(&app, &station.id, "groups", "", adapter.get_user_groups(&station))
+            .await
+            .map_err(|_e| t!("relay.failed_to_get_user_groups", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
 }
 
+/// The station's token groups with the one new tokens should default to
+#[tauri::command]
+pub async fn suggest_token_group(station_id: String, app: AppHandle) -> Result<Cached<TokenGroupSuggestion>, String> {
+    let groups = api_user_self_groups(station_id, app).await?;
+    Ok(groups.map(|groups| TokenGroupSuggestion::from_response(&groups)))
+}
+
 #[tauri::command]
 pub async fn toggle_station_token(
     station_id: String,
@@ -1327,8 +1506,8 @@ pub async fn toggle_station_token(
     };
     
     if let Some(station) = station {
-        let adapter = create_adapter(&station.adapter);
-        adapter.toggle_token(&station, &token_id, enabled).await.map_err(|_e| t!("relay.failed_to_toggle_token", "error" => &_e.to_string()))
+        let adapter = create_station_adapter(&app, &station);
+        adapter.toggle_token(&station, &token_id, enabled).await.map_err(|_e| t!("relay.failed_to_toggle_token", "error" => &hints::with_hint(&_e)))
     } else {
         Err(t!("relay.station_not_found"))
     }
@@ -1354,7 +1533,7 @@ pub async fn load_station_api_endpoints(
     
     if let Some(station) = station {
         // Try to get endpoints from station API status
-        let adapter = create_adapter(&station.adapter);
+        let adapter = create_station_adapter(&app, &station);
         match adapter.get_station_info(&station).await {
             Ok(info) => {
                 // Extract API endpoints from metadata if available
@@ -1363,7 +1542,7 @@ pub async fn load_station_api_endpoints(
                     if let Some(response_data) = metadata.get("response") {
                         if let Some(api_info) = response_data.get("api_info") {
                             if let Ok(endpoints) = serde_json::from_value::<Vec<ApiEndpoint>>(api_info.clone()) {
-                                println!("Successfully parsed {} API endpoints from api_info", endpoints.len());
+                                log::debug!("Successfully parsed {} API endpoints from api_info", endpoints.len());
                                 return Ok(endpoints);
                             }
                         }
@@ -1372,13 +1551,13 @@ pub async fn load_station_api_endpoints(
                     // Also try direct api_info for backward compatibility
                     if let Some(api_info) = metadata.get("api_info") {
                         if let Ok(endpoints) = serde_json::from_value::<Vec<ApiEndpoint>>(api_info.clone()) {
-                            println!("Successfully parsed {} API endpoints from direct api_info", endpoints.len());
+                            log::debug!("Successfully parsed {} API endpoints from direct api_info", endpoints.len());
                             return Ok(endpoints);
                         }
                     }
                 }
                 
-                println!("No api_info found in metadata, using fallback default endpoint");
+                log::info!("No api_info found in metadata, using fallback default endpoint");
                 
                 // Fallback: create default endpoint from station URL
                 Ok(vec![ApiEndpoint {
@@ -1471,16 +1650,61 @@ pub async fn get_station_config(
 /// Get configuration usage status for display
 #[tauri::command]
 pub async fn get_config_usage_status(app: AppHandle) -> Result<Vec<ConfigUsageStatus>, String> {
+    let hide = app_lock::hides_secrets(&app);
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     if let Some(manager) = manager_lock.as_ref() {
-        manager.get_config_usage_status().map_err(|_e| t!("relay.failed_to_get_usage_status", "error" => &_e.to_string()))
+        let mut statuses = manager.get_config_usage_status().map_err(|_e| t!("relay.failed_to_get_usage_status", "error" => &_e.to_string()))?;
+        if hide {
+            for status in &mut statuses {
+                status.token = provider::mask_secret(&status.token);
+            }
+        }
+        Ok(statuses)
     } else {
         Err(t!("relay.manager_not_initialized"))
     }
 }
 
+/// Provider config applying the configuration last used with a station again,
+/// the same one the relay station dialog builds when applying
+pub fn station_provider_config(
+    manager: &RelayStationManager,
+    station_id: &str,
+) -> Result<(ProviderConfig, ConfigUsageStatus), String> {
+    let station = manager
+        .get_station(station_id)
+        .map_err(|_e| t!("relay.failed_to_get_station", "error" => &_e.to_string()))?
+        .ok_or_else(|| t!("relay.station_not_found"))?;
+    let applied = manager
+        .get_config_usage_status()
+        .map_err(|_e| t!("relay.failed_to_get_usage_status", "error" => &_e.to_string()))?
+        .into_iter()
+        .find(|status| status.station_id == station.id)
+        .ok_or_else(|| t!("tray.station_not_configured", "name" => &station.name))?;
+    let model = manager
+        .get_station_config(station_id)
+        .ok()
+        .flatten()
+        .and_then(|config| config.model);
+
+    let config = ProviderConfig {
+        id: format!("relay-{}-{}", station.id, Utc::now().timestamp_millis()),
+        name: format!("{} - 配置", station.name),
+        description: format!("从中转站 {} 应用的配置", station.name),
+        base_url: applied.base_url.clone(),
+        auth_token: Some(applied.token.clone()),
+        api_key: None,
+        model,
+        small_fast_model: None,
+        mirror_urls: Vec::new(),
+        binary_path: None,
+        default_args: Vec::new(),
+    };
+    Ok((config, applied))
+}
+
 /// Record configuration usage (when a config is applied)
 #[tauri::command]
 pub async fn record_config_usage(
@@ -1489,11 +1713,16 @@ pub async fn record_config_usage(
     token: String,
     app: AppHandle,
 ) -> Result<String, String> {
+    if provider::is_masked(&token) {
+        return Err(t!("app_lock.locked"));
+    }
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     
     let mut manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     if let Some(manager) = manager_lock.as_mut() {
         manager.record_config_usage(&station_id, &base_url, &token).map_err(|_e| t!("relay.failed_to_record_usage", "error" => &_e.to_string()))?;
+        drop(manager_lock);
+        crate::tray::refresh_menu(&app);
         Ok(t!("relay.usage_record_updated"))
     } else {
         Err(t!("relay.manager_not_initialized"))
@@ -1506,6 +1735,7 @@ pub async fn export_relay_stations(
     station_ids: Option<Vec<String>>,
     app: AppHandle,
 ) -> Result<RelayStationExport, String> {
+    app_lock::require_unlocked(&app, ProtectedAction::ExportStations).await?;
     let state: State<Mutex<Option<RelayStationManager>>> = app.state();
     let manager_lock = state.lock().map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
     
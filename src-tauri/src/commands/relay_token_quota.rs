@@ -0,0 +1,495 @@
+//! Low quota warnings for relay station tokens
+//!
+//! The quota of every listed token is cached. When a token's remaining share
+//! of its quota drops below the configured percentage, `token-quota-low` is
+//! emitted once, until the token is topped up again. If the token is the one
+//! applied for its station, the applied config can be switched to the
+//! station's token with the most quota left.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::relay_stations::{self, RelayStation, RelayStationManager, RelayStationToken};
+use crate::commands::agents::AgentDb;
+use crate::commands::provider;
+use crate::t;
+
+/// app_settings key storing the quota warning settings (JSON)
+pub const TOKEN_QUOTA_SETTINGS_KEY: &str = "token_quota_settings";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenQuotaSettings {
+    pub enabled: bool,
+    /// Warn below this share of a token's quota, in percent
+    pub threshold_percent: f64,
+    /// Switch the applied config to another token of the station
+    pub auto_switch: bool,
+}
+
+impl Default for TokenQuotaSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_percent: 10.0,
+            auto_switch: false,
+        }
+    }
+}
+
+/// A token's quota as last listed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedTokenQuota {
+    pub station_id: String,
+    pub token_id: String,
+    pub token_name: String,
+    /// Fingerprint of the token's key, see `key_fingerprint`
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub enabled: bool,
+    pub remain_quota: Option<i64>,
+    pub used_quota: Option<i64>,
+    pub unlimited: bool,
+}
+
+impl CachedTokenQuota {
+    fn from_token(station_id: &str, token: &RelayStationToken) -> Self {
+        let used_quota = token
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("used_quota"))
+            .and_then(serde_json::Value::as_i64);
+        Self {
+            station_id: station_id.to_string(),
+            token_id: token.id.clone(),
+            token_name: token.name.clone(),
+            key_hash: key_fingerprint(&token.token),
+            enabled: token.enabled,
+            remain_quota: token.remain_quota,
+            used_quota,
+            unlimited: token.unlimited_quota == Some(true),
+        }
+    }
+
+    /// Remaining share of the quota in percent; unknown for unlimited tokens
+    pub fn remain_percent(&self) -> Option<f64> {
+        if self.unlimited {
+            return None;
+        }
+        let remain = self.remain_quota?.max(0);
+        let total = remain + self.used_quota?.max(0);
+        if total == 0 {
+            return None;
+        }
+        Some(remain as f64 * 100.0 / total as f64)
+    }
+
+    fn is_low(&self, threshold_percent: f64) -> bool {
+        self.remain_percent()
+            .is_some_and(|percent| percent < threshold_percent)
+    }
+
+    /// Whether `token`, as applied in a config, is this token's key
+    pub fn matches(&self, token: &str) -> bool {
+        !self.key_hash.is_empty() && key_fingerprint(token) == self.key_hash
+    }
+}
+
+/// SHA-256 of a token key without its `sk-` prefix, empty for a missing key
+///
+/// Only this is cached so the database never holds the keys themselves.
+fn key_fingerprint(key: &str) -> String {
+    let key = key.trim();
+    let key = key.strip_prefix("sk-").unwrap_or(key);
+    if key.is_empty() {
+        return String::new();
+    }
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Payload of `token-quota-low`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenQuotaLow {
+    pub station_id: String,
+    pub station_name: String,
+    pub token: CachedTokenQuota,
+    pub remain_percent: f64,
+    pub threshold_percent: f64,
+    /// Name of the token the applied config was switched to
+    pub switched_to: Option<String>,
+    /// Why switching failed, if it was tried
+    pub switch_error: Option<String>,
+}
+
+pub fn create_token_quota_table(conn: &Connection) -> rusqlite::Result<()> {
+    // Earlier versions cached the keys themselves; the cache is rebuilt the
+    // next time tokens are listed
+    if conn
+        .prepare("SELECT key FROM station_token_quota LIMIT 0")
+        .is_ok()
+    {
+        conn.execute("DROP TABLE station_token_quota", [])?;
+    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS station_token_quota (
+            station_id TEXT NOT NULL,
+            token_id TEXT NOT NULL,
+            token_name TEXT NOT NULL,
+            key_hash TEXT NOT NULL,
+            enabled INTEGER NOT NULL,
+            remain_quota INTEGER,
+            used_quota INTEGER,
+            unlimited INTEGER NOT NULL DEFAULT 0,
+            low INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (station_id, token_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn load_token_quota_settings(conn: &Connection) -> TokenQuotaSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![TOKEN_QUOTA_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+/// Cached tokens of a station
+pub fn load_tokens(conn: &Connection, station_id: &str) -> Result<Vec<CachedTokenQuota>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT station_id, token_id, token_name, key_hash, enabled, remain_quota, used_quota, unlimited
+             FROM station_token_quota WHERE station_id = ?1 ORDER BY token_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let tokens = stmt
+        .query_map(params![station_id], |row| {
+            Ok(CachedTokenQuota {
+                station_id: row.get(0)?,
+                token_id: row.get(1)?,
+                token_name: row.get(2)?,
+                key_hash: row.get(3)?,
+                enabled: row.get(4)?,
+                remain_quota: row.get(5)?,
+                used_quota: row.get(6)?,
+                unlimited: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tokens)
+}
+
+/// Cache listed tokens and return those that just dropped below the
+/// threshold; with `complete`, the tokens are all the station has and others
+/// are forgotten
+pub fn store_tokens(
+    conn: &Connection,
+    station_id: &str,
+    tokens: &[RelayStationToken],
+    complete: bool,
+    threshold_percent: f64,
+) -> Result<Vec<CachedTokenQuota>, String> {
+    let store_error = |e: rusqlite::Error| format!("Failed to cache token quota: {}", e);
+    if complete {
+        for cached in load_tokens(conn, station_id)? {
+            if !tokens.iter().any(|token| token.id == cached.token_id) {
+                conn.execute(
+                    "DELETE FROM station_token_quota WHERE station_id = ?1 AND token_id = ?2",
+                    params![station_id, cached.token_id],
+                )
+                .map_err(store_error)?;
+            }
+        }
+    }
+    let now = chrono::Utc::now().timestamp();
+    let mut dropped = Vec::new();
+    for token in tokens {
+        let quota = CachedTokenQuota::from_token(station_id, token);
+        let low = quota.is_low(threshold_percent);
+        let was_low: bool = conn
+            .query_row(
+                "SELECT low FROM station_token_quota WHERE station_id = ?1 AND token_id = ?2",
+                params![station_id, quota.token_id],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        conn.execute(
+            "INSERT OR REPLACE INTO station_token_quota
+             (station_id, token_id, token_name, key_hash, enabled, remain_quota, used_quota, unlimited, low, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                station_id,
+                quota.token_id,
+                quota.token_name,
+                quota.key_hash,
+                quota.enabled,
+                quota.remain_quota,
+                quota.used_quota,
+                quota.unlimited,
+                low,
+                now,
+            ],
+        )
+        .map_err(store_error)?;
+        if low && !was_low && quota.enabled {
+            dropped.push(quota);
+        }
+    }
+    Ok(dropped)
+}
+
+/// Enabled token of the station to switch to: unlimited ones first, then
+/// the one with the most quota left, if above the threshold
+pub fn pick_replacement<'a>(
+    tokens: &'a [CachedTokenQuota],
+    low: &CachedTokenQuota,
+    threshold_percent: f64,
+) -> Option<&'a CachedTokenQuota> {
+    tokens
+        .iter()
+        .filter(|token| {
+            token.enabled && token.token_id != low.token_id && !token.key_hash.is_empty()
+        })
+        .filter(|token| token.unlimited || !token.is_low(threshold_percent))
+        .filter(|token| token.unlimited || token.remain_quota.unwrap_or(0) > 0)
+        .max_by_key(|token| (token.unlimited, token.remain_quota.unwrap_or(0)))
+}
+
+/// Apply the station's config again with the token key `key`
+async fn switch_token(app: &AppHandle, station_id: &str, key: &str) -> Result<(), String> {
+    let (mut config, applied) = {
+        let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+        let manager_lock = state
+            .lock()
+            .map_err(|_e| t!("relay.lock_error", "error" => &_e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        relay_stations::station_provider_config(manager, station_id)?
+    };
+    let auth_token = format!("sk-{}", key.trim().trim_start_matches("sk-"));
+    config.auth_token = Some(auth_token.clone());
+    provider::switch_provider(app.clone(), config).await?;
+    relay_stations::record_config_usage(
+        applied.station_id,
+        applied.base_url,
+        auth_token,
+        app.clone(),
+    )
+    .await?;
+    let _ = app.emit("provider-changed", station_id);
+    Ok(())
+}
+
+/// The token applied for a station, if any
+fn applied_token(app: &AppHandle, station_id: &str) -> Option<String> {
+    let state: State<Mutex<Option<RelayStationManager>>> = app.state();
+    let manager_lock = state.lock().ok()?;
+    manager_lock
+        .as_ref()?
+        .get_config_usage_status()
+        .ok()?
+        .into_iter()
+        .find(|status| status.station_id == station_id)
+        .map(|status| status.token)
+}
+
+/// Cache listed tokens of a station and warn about those running low;
+/// called wherever tokens are listed
+pub async fn check_tokens(
+    app: &AppHandle,
+    station: &RelayStation,
+    tokens: &[RelayStationToken],
+    complete: bool,
+) -> Result<(), String> {
+    let (settings, dropped) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = load_token_quota_settings(&conn);
+        let dropped = store_tokens(
+            &conn,
+            &station.id,
+            tokens,
+            complete,
+            settings.threshold_percent,
+        )?;
+        (settings, dropped)
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    // Only listed tokens can be switched to, the cache holds no keys
+    let listed: Vec<CachedTokenQuota> = tokens
+        .iter()
+        .map(|token| CachedTokenQuota::from_token(&station.id, token))
+        .collect();
+
+    for token in dropped {
+        let mut warning = TokenQuotaLow {
+            station_id: station.id.clone(),
+            station_name: station.name.clone(),
+            remain_percent: token.remain_percent().unwrap_or(0.0),
+            threshold_percent: settings.threshold_percent,
+            token,
+            switched_to: None,
+            switch_error: None,
+        };
+        let applied =
+            applied_token(app, &station.id).is_some_and(|applied| warning.token.matches(&applied));
+        if settings.auto_switch && applied {
+            let replacement = pick_replacement(&listed, &warning.token, settings.threshold_percent)
+                .and_then(|replacement| {
+                    tokens.iter().find(|token| token.id == replacement.token_id)
+                });
+            match replacement {
+                Some(next) => match switch_token(app, &station.id, &next.token).await {
+                    Ok(()) => warning.switched_to = Some(next.name.clone()),
+                    Err(e) => warning.switch_error = Some(e),
+                },
+                None => {
+                    warning.switch_error =
+                        Some("No other token of the station has quota left".to_string())
+                }
+            }
+        }
+        log::info!(
+            "Token {} of {} is down to {:.1}% of its quota{}",
+            warning.token.token_name,
+            station.name,
+            warning.remain_percent,
+            warning
+                .switched_to
+                .as_ref()
+                .map(|name| format!(", switched to {}", name))
+                .unwrap_or_default()
+        );
+        let _ = app.emit("token-quota-low", &warning);
+    }
+    Ok(())
+}
+
+/// Forget a station's cached tokens
+pub fn clear_station(conn: &Connection, station_id: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM station_token_quota WHERE station_id = ?1",
+        params![station_id],
+    )
+    .map_err(|e| format!("Failed to clear cached token quota: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_token_quota_settings(
+    db: State<'_, AgentDb>,
+) -> Result<TokenQuotaSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_token_quota_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_token_quota_settings(
+    db: State<'_, AgentDb>,
+    settings: TokenQuotaSettings,
+) -> Result<TokenQuotaSettings, String> {
+    if !settings.threshold_percent.is_finite()
+        || settings.threshold_percent <= 0.0
+        || settings.threshold_percent >= 100.0
+    {
+        return Err("The threshold must be between 0 and 100 percent".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![TOKEN_QUOTA_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save token quota settings: {}", e))?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn token(id: &str, remain: i64, used: i64, unlimited: bool) -> RelayStationToken {
+        RelayStationToken {
+            id: id.to_string(),
+            station_id: "s1".to_string(),
+            name: format!("token {}", id),
+            token: format!("key{}", id),
+            user_id: None,
+            enabled: true,
+            expires_at: None,
+            group: None,
+            remain_quota: Some(remain),
+            unlimited_quota: Some(unlimited),
+            metadata: Some(HashMap::from([(
+                "used_quota".to_string(),
+                serde_json::json!(used),
+            )])),
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_low_quota_and_replacement() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_token_quota_table(&conn).unwrap();
+
+        let tokens = vec![
+            token("1", 50, 950, false),
+            token("2", 400, 600, false),
+            token("3", 0, 0, true),
+        ];
+        let dropped = store_tokens(&conn, "s1", &tokens, true, 10.0).unwrap();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].remain_percent(), Some(5.0));
+        assert!(dropped[0].matches("sk-key1") && dropped[0].matches("key1"));
+        assert!(!dropped[0].matches("sk-key2"));
+        let stored: String = conn
+            .query_row(
+                "SELECT key_hash FROM station_token_quota WHERE token_id = '1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!stored.contains("key1"));
+
+        // Warned once until topped up
+        assert!(store_tokens(&conn, "s1", &tokens, true, 10.0)
+            .unwrap()
+            .is_empty());
+        store_tokens(&conn, "s1", &[token("1", 500, 950, false)], false, 10.0).unwrap();
+        assert_eq!(
+            store_tokens(&conn, "s1", &tokens[..1], false, 10.0)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let cached = load_tokens(&conn, "s1").unwrap();
+        let replacement = pick_replacement(&cached, &dropped[0], 10.0).unwrap();
+        assert_eq!(replacement.token_id, "3");
+        let limited: Vec<CachedTokenQuota> = cached
+            .into_iter()
+            .filter(|token| !token.unlimited)
+            .collect();
+        let replacement = pick_replacement(&limited, &dropped[0], 10.0).unwrap();
+        assert_eq!(replacement.token_id, "2");
+        assert!(pick_replacement(&limited, &dropped[0], 50.0).is_none());
+
+        store_tokens(&conn, "s1", &tokens[1..2], true, 10.0).unwrap();
+        assert_eq!(load_tokens(&conn, "s1").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,487 @@
+use crate::commands::agents::AgentDb;
+use crate::transcript::{read_page, TranscriptPage};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::State;
+
+/// Longest first prompt kept in the index; the full text is in the transcript
+const FIRST_PROMPT_MAX_CHARS: usize = 500;
+
+/// Metadata of a session transcript in `~/.claude/projects/<project>/<id>.jsonl`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// Directory name under `~/.claude/projects`
+    pub project_id: String,
+    /// Working directory of the session
+    pub project_path: String,
+    pub first_prompt: Option<String>,
+    /// Title Claude Code generated for the conversation, if any
+    pub summary: Option<String>,
+    pub first_timestamp: Option<String>,
+    pub last_timestamp: Option<String>,
+    /// User and assistant messages in the transcript
+    pub message_count: i64,
+    /// Model of the last assistant message
+    pub model: Option<String>,
+    pub file_size: i64,
+    /// Unix timestamp of the last write to the transcript
+    pub modified_at: i64,
+}
+
+/// One page of sessions, most recently active first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionSummary>,
+    pub total: i64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Create the `session_index` cache table
+pub fn create_session_index_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_index (
+            file_path TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            first_prompt TEXT,
+            summary TEXT,
+            first_timestamp TEXT,
+            last_timestamp TEXT,
+            message_count INTEGER NOT NULL,
+            model TEXT,
+            file_size INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_index_modified_at ON session_index(modified_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Text of a user message, whether `content` is a string or a list of blocks
+fn message_text(message: &Value) -> Option<String> {
+    match &message["content"] {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(blocks) => {
+            let text: Vec<&str> = blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect();
+            (!text.is_empty()).then(|| text.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a user message is something the user actually typed
+fn is_prompt(text: &str) -> bool {
+    let text = text.trim_start();
+    !text.is_empty()
+        && !text.starts_with("Caveat: The messages below were generated by the user")
+        && !text.starts_with("<command-name>")
+        && !text.starts_with("<local-command-stdout>")
+}
+
+/// Read the metadata of one transcript, skipping lines that aren't valid JSON
+pub fn read_session_summary(path: &Path, project_id: &str) -> Result<SessionSummary, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read session metadata: {}", e))?;
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open session: {}", e))?;
+
+    let mut summary = SessionSummary {
+        session_id: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        project_id: project_id.to_string(),
+        file_size: metadata.len() as i64,
+        modified_at: unix_seconds(metadata.modified().ok()),
+        ..Default::default()
+    };
+    let mut cwd = None;
+
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            continue;
+        };
+        let Ok(entry) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if cwd.is_none() {
+            cwd = entry["cwd"].as_str().map(str::to_string);
+        }
+        if let Some(timestamp) = entry["timestamp"].as_str() {
+            if summary.first_timestamp.is_none() {
+                summary.first_timestamp = Some(timestamp.to_string());
+            }
+            summary.last_timestamp = Some(timestamp.to_string());
+        }
+
+        match entry["type"].as_str() {
+            Some("summary") => {
+                summary.summary = entry["summary"].as_str().map(str::to_string);
+            }
+            Some("user") => {
+                summary.message_count += 1;
+                if summary.first_prompt.is_none() && entry["isMeta"] != true {
+                    summary.first_prompt = message_text(&entry["message"])
+                        .filter(|text| is_prompt(text))
+                        .map(|text| text.trim().chars().take(FIRST_PROMPT_MAX_CHARS).collect());
+                }
+            }
+            Some("assistant") => {
+                summary.message_count += 1;
+                if let Some(model) = entry["message"]["model"].as_str() {
+                    // Locally generated messages (e.g. API errors) carry "<synthetic>"
+                    if !model.starts_with('<') {
+                        summary.model = Some(model.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    summary.project_path =
+        cwd.unwrap_or_else(|| crate::commands::claude::decode_project_path(project_id));
+    Ok(summary)
+}
+
+fn unix_seconds(time: Option<SystemTime>) -> i64 {
+    time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Transcripts under `projects_dir` with their size and modification time
+fn find_session_files(projects_dir: &Path) -> Vec<(PathBuf, String, i64, i64)> {
+    let Ok(projects) = fs::read_dir(projects_dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for project in projects.flatten() {
+        let project_id = project.file_name().to_string_lossy().to_string();
+        let Ok(sessions) = fs::read_dir(project.path()) else {
+            continue;
+        };
+        for session in sessions.flatten() {
+            let path = session.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            if let Ok(metadata) = session.metadata() {
+                if metadata.is_file() {
+                    let modified_at = unix_seconds(metadata.modified().ok());
+                    files.push((path, project_id.clone(), metadata.len() as i64, modified_at));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Bring the `session_index` cache in line with the transcripts on disk
+///
+/// Only transcripts whose size or modification time changed are re-read.
+/// The database is locked only to read and write the cache, not while parsing.
+pub fn sync_session_index(
+    db: &std::sync::Mutex<Connection>,
+    projects_dir: &Path,
+) -> Result<usize, String> {
+    let indexed: HashMap<String, (i64, i64)> = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT file_path, file_size, modified_at FROM session_index")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| format!("Failed to read session index: {}", e))?;
+        rows
+    };
+
+    let files = find_session_files(projects_dir);
+    let changed: Vec<(String, SessionSummary)> = files
+        .iter()
+        .filter(|(path, _, size, modified_at)| {
+            indexed.get(path.to_string_lossy().as_ref()) != Some(&(*size, *modified_at))
+        })
+        .filter_map(
+            |(path, project_id, _, _)| match read_session_summary(path, project_id) {
+                Ok(summary) => Some((path.to_string_lossy().to_string(), summary)),
+                Err(e) => {
+                    log::warn!("Failed to index session {:?}: {}", path, e);
+                    None
+                }
+            },
+        )
+        .collect();
+    let present: Vec<String> = files
+        .iter()
+        .map(|(path, ..)| path.to_string_lossy().to_string())
+        .collect();
+
+    let mut conn = db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (file_path, summary) in &changed {
+        tx.execute(
+            "INSERT OR REPLACE INTO session_index (
+                file_path, session_id, project_id, project_path, first_prompt, summary,
+                first_timestamp, last_timestamp, message_count, model, file_size, modified_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                file_path,
+                summary.session_id,
+                summary.project_id,
+                summary.project_path,
+                summary.first_prompt,
+                summary.summary,
+                summary.first_timestamp,
+                summary.last_timestamp,
+                summary.message_count,
+                summary.model,
+                summary.file_size,
+                summary.modified_at,
+            ],
+        )
+        .map_err(|e| format!("Failed to update session index: {}", e))?;
+    }
+    for file_path in indexed.keys().filter(|path| !present.contains(path)) {
+        tx.execute(
+            "DELETE FROM session_index WHERE file_path = ?1",
+            params![file_path],
+        )
+        .map_err(|e| format!("Failed to update session index: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if !changed.is_empty() {
+        log::debug!("Indexed {} changed session transcripts", changed.len());
+    }
+    Ok(changed.len())
+}
+
+/// Read a page of indexed sessions (1-based `page`), optionally for one
+/// project and matching `search` in the prompt, title or project path
+pub fn query_sessions(
+    conn: &Connection,
+    project_id: Option<&str>,
+    search: Option<&str>,
+    page: u32,
+    page_size: u32,
+) -> Result<SessionPage, String> {
+    let page = page.max(1);
+    let page_size = page_size.clamp(1, 500);
+    let offset = (page as i64 - 1) * page_size as i64;
+    let search = search
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("%{}%", search));
+    let filter = "(?1 IS NULL OR project_id = ?1)
+         AND (?2 IS NULL OR first_prompt LIKE ?2 OR summary LIKE ?2 OR project_path LIKE ?2)";
+
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM session_index WHERE {}", filter),
+            params![project_id, search],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT session_id, project_id, project_path, first_prompt, summary, first_timestamp,
+                    last_timestamp, message_count, model, file_size, modified_at
+             FROM session_index
+             WHERE {}
+             ORDER BY modified_at DESC, session_id
+             LIMIT ?3 OFFSET ?4",
+            filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let sessions = stmt
+        .query_map(params![project_id, search, page_size, offset], |row| {
+            Ok(SessionSummary {
+                session_id: row.get(0)?,
+                project_id: row.get(1)?,
+                project_path: row.get(2)?,
+                first_prompt: row.get(3)?,
+                summary: row.get(4)?,
+                first_timestamp: row.get(5)?,
+                last_timestamp: row.get(6)?,
+                message_count: row.get(7)?,
+                model: row.get(8)?,
+                file_size: row.get(9)?,
+                modified_at: row.get(10)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read session index: {}", e))?;
+
+    Ok(SessionPage {
+        sessions,
+        total,
+        page,
+        page_size,
+    })
+}
+
+fn projects_dir() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects"))
+}
+
+/// Page through previous sessions of all projects (or one), most recent first
+///
+/// Transcripts that changed since the last call are re-indexed first.
+#[tauri::command]
+pub async fn list_sessions(
+    db: State<'_, AgentDb>,
+    project_id: Option<String>,
+    search: Option<String>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> Result<SessionPage, String> {
+    sync_session_index(&db.0, &projects_dir()?)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_sessions(
+        &conn,
+        project_id.as_deref(),
+        search.as_deref(),
+        page.unwrap_or(1),
+        page_size.unwrap_or(50),
+    )
+}
+
+/// Locate the transcript of a session, via the index or by scanning the projects
+fn find_session_file(conn: &Connection, session_id: &str) -> Result<PathBuf, String> {
+    let indexed: Option<String> = conn
+        .query_row(
+            "SELECT file_path FROM session_index WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(path) = indexed.map(PathBuf::from).filter(|path| path.is_file()) {
+        return Ok(path);
+    }
+
+    let file_name = format!("{}.jsonl", session_id);
+    fs::read_dir(projects_dir()?)
+        .map_err(|e| format!("Failed to read projects directory: {}", e))?
+        .flatten()
+        .map(|project| project.path().join(&file_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("Session file not found: {}", session_id))
+}
+
+/// Read `limit` parsed messages of a session starting at message `offset`
+///
+/// Lets the frontend page through long conversations instead of loading the
+/// whole transcript at once.
+#[tauri::command]
+pub async fn get_session_messages(
+    db: State<'_, AgentDb>,
+    session_id: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<TranscriptPage, String> {
+    if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.contains("..") {
+        return Err(format!("Invalid session ID: {}", session_id));
+    }
+    let path = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find_session_file(&conn, &session_id)?
+    };
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(100).clamp(1, 1000);
+
+    tokio::task::spawn_blocking(move || read_page(&path, offset, limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Drop the session cache and index every transcript again
+#[tauri::command]
+pub async fn rebuild_session_index(db: State<'_, AgentDb>) -> Result<usize, String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM session_index", [])
+            .map_err(|e| format!("Failed to clear session index: {}", e))?;
+    }
+    sync_session_index(&db.0, &projects_dir()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_session_index_sync_and_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("-work-app");
+        fs::create_dir_all(&project_dir).unwrap();
+        let transcript = [
+            r#"{"type":"user","cwd":"/work/app","timestamp":"2025-01-01T10:00:00Z","message":{"role":"user","content":"<command-name>/clear</command-name>"}}"#,
+            r#"{"type":"user","cwd":"/work/app","timestamp":"2025-01-01T10:00:01Z","message":{"role":"user","content":[{"type":"text","text":"Fix the login bug"}]}}"#,
+            "not json",
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:00:05Z","message":{"role":"assistant","model":"claude-sonnet-4-5","content":[]}}"#,
+            r#"{"type":"summary","summary":"Login bug fix"}"#,
+        ];
+        let path = project_dir.join("abc.jsonl");
+        fs::write(&path, transcript.join("\n")).unwrap();
+
+        let summary = read_session_summary(&path, "-work-app").unwrap();
+        assert_eq!(summary.project_path, "/work/app");
+        assert_eq!(summary.first_prompt.as_deref(), Some("Fix the login bug"));
+        assert_eq!(summary.summary.as_deref(), Some("Login bug fix"));
+        assert_eq!(summary.message_count, 3);
+        assert_eq!(summary.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(
+            summary.last_timestamp.as_deref(),
+            Some("2025-01-01T10:00:05Z")
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        create_session_index_table(&conn).unwrap();
+        let db = Mutex::new(conn);
+        assert_eq!(sync_session_index(&db, dir.path()).unwrap(), 1);
+        // Unchanged transcripts are not re-read
+        assert_eq!(sync_session_index(&db, dir.path()).unwrap(), 0);
+
+        let conn = db.lock().unwrap();
+        let page = query_sessions(&conn, None, Some("login"), 1, 10).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.sessions[0].session_id, "abc");
+        assert_eq!(
+            query_sessions(&conn, Some("-other"), None, 1, 10)
+                .unwrap()
+                .total,
+            0
+        );
+        drop(conn);
+
+        fs::remove_file(&path).unwrap();
+        sync_session_index(&db, dir.path()).unwrap();
+        let conn = db.lock().unwrap();
+        assert_eq!(query_sessions(&conn, None, None, 1, 10).unwrap().total, 0);
+    }
+}
@@ -0,0 +1,477 @@
+//! Validation and repair of Claude Code settings files against a bundled schema
+//!
+//! The schema (`schemas/claude-settings.schema.json`) uses a small subset of
+//! JSON Schema: `type`, `properties`, `additionalProperties`, `items`, `enum`
+//! and `minimum`. Problems are reported per JSON pointer; the ones with an
+//! obvious fix (a string where a list belongs, a known typo in a key, `"true"`
+//! for `true`) can be repaired in place. Claude Code gains settings faster than
+//! the schema, so keys it does not list are only warnings and are never
+//! renamed unless they are a known typo.
+use crate::commands::hooks::{settings_path, SETTINGS_LOCK};
+use crate::mcp::config::write_json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+const SCHEMA: &str = include_str!("../../schemas/claude-settings.schema.json");
+
+/// Repair rounds; a renamed key is only checked in the next round
+const MAX_REPAIR_ROUNDS: usize = 3;
+
+/// Misspelled keys and the key they are repaired to, where that key is known
+const KNOWN_TYPOS: &[(&str, &str)] = &[
+    ("modle", "model"),
+    ("mdoel", "model"),
+    ("enviroment", "env"),
+    ("environment", "env"),
+    ("permission", "permissions"),
+    ("hook", "hooks"),
+    ("statusline", "statusLine"),
+    ("status_line", "statusLine"),
+    ("outputstyle", "outputStyle"),
+    ("output_style", "outputStyle"),
+    ("includeCoauthoredBy", "includeCoAuthoredBy"),
+    ("include_co_authored_by", "includeCoAuthoredBy"),
+    ("cleanupPeriod", "cleanupPeriodDays"),
+    ("allowed", "allow"),
+    ("denied", "deny"),
+    ("default_mode", "defaultMode"),
+    ("defaultmode", "defaultMode"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Fix {
+    Replace(Value),
+    /// Rename the key the issue points at
+    Rename(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsIssue {
+    /// JSON pointer of the offending value, "" for the whole file
+    pub pointer: String,
+    /// "invalid_json", "unknown_key", "type_mismatch" or "invalid_value"
+    pub kind: String,
+    pub message: String,
+    /// Known key a misspelled key probably meant
+    pub suggestion: Option<String>,
+    pub repairable: bool,
+    /// Only worth a look, e.g. a key the schema does not know yet
+    pub warning: bool,
+    #[serde(skip)]
+    fix: Option<Fix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsValidation {
+    pub path: String,
+    pub valid: bool,
+    pub issues: Vec<SettingsIssue>,
+    /// Fixes applied, when returned from a repair
+    pub repaired: usize,
+}
+
+fn schema() -> &'static Value {
+    static PARSED: OnceLock<Value> = OnceLock::new();
+    PARSED.get_or_init(|| serde_json::from_str(SCHEMA).expect("bundled settings schema is valid"))
+}
+
+fn issue(pointer: &str, kind: &str, message: String, fix: Option<Fix>) -> SettingsIssue {
+    SettingsIssue {
+        pointer: pointer.to_string(),
+        kind: kind.to_string(),
+        message,
+        suggestion: match &fix {
+            Some(Fix::Rename(key)) => Some(key.clone()),
+            _ => None,
+        },
+        repairable: fix.is_some(),
+        warning: false,
+        fix,
+    }
+}
+
+fn child_pointer(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected || (expected == "number" && actual == "integer")
+}
+
+/// Levenshtein distance, for spotting misspelled keys
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The value converted to `expected`, when the intent is unambiguous
+fn coerce(value: &Value, expected: &str, schema: &Value) -> Option<Value> {
+    match (expected, value) {
+        ("array", Value::String(_)) => {
+            let item_type = schema.pointer("/items/type").and_then(Value::as_str);
+            matches!(item_type, None | Some("string")).then(|| Value::Array(vec![value.clone()]))
+        }
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        ("string", Value::Array(items)) if items.len() == 1 && items[0].is_string() => {
+            Some(items[0].clone())
+        }
+        ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+        ("number", Value::String(s)) => s.trim().parse::<f64>().ok().map(Value::from),
+        _ => None,
+    }
+}
+
+fn check(schema: &Value, value: &Value, pointer: &str, issues: &mut Vec<SettingsIssue>) {
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        if !has_type(value, expected) {
+            let fix = coerce(value, expected, schema).map(Fix::Replace);
+            issues.push(issue(
+                pointer,
+                "type_mismatch",
+                format!("Expected {}, found {}", expected, type_name(value)),
+                fix,
+            ));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let fix = value.as_str().and_then(|given| {
+                allowed
+                    .iter()
+                    .find(|option| {
+                        option
+                            .as_str()
+                            .is_some_and(|option| option.eq_ignore_ascii_case(given))
+                    })
+                    .map(|option| Fix::Replace(option.clone()))
+            });
+            let options: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            issues.push(issue(
+                pointer,
+                "invalid_value",
+                format!("Must be one of {}", options.join(", ")),
+                fix,
+            ));
+        }
+    }
+    if let (Some(minimum), Some(number)) = (
+        schema.get("minimum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if number < minimum {
+            issues.push(issue(
+                pointer,
+                "invalid_value",
+                format!("Must be at least {}", minimum),
+                None,
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => check_object(schema, object, pointer, issues),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check(item_schema, item, &format!("{}/{}", pointer, index), issues);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_object(
+    schema: &Value,
+    object: &Map<String, Value>,
+    pointer: &str,
+    issues: &mut Vec<SettingsIssue>,
+) {
+    let empty = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    for (key, value) in object {
+        let child = child_pointer(pointer, key);
+        if let Some(property) = properties.get(key) {
+            check(property, value, &child, issues);
+            continue;
+        }
+        match schema.get("additionalProperties") {
+            Some(additional @ Value::Object(_)) => check(additional, value, &child, issues),
+            // Objects without listed properties, such as `sandbox`, take any key
+            _ if !properties.is_empty() => {
+                issues.push(unknown_key(properties, object, key, &child))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Warning for a key the schema does not list, renamed only when it is a
+/// known typo
+fn unknown_key(
+    properties: &Map<String, Value>,
+    object: &Map<String, Value>,
+    key: &str,
+    pointer: &str,
+) -> SettingsIssue {
+    let free = |known: &str| properties.contains_key(known) && !object.contains_key(known);
+    let typo = KNOWN_TYPOS
+        .iter()
+        .find(|(typo, known)| *typo == key && free(known))
+        .map(|(_, known)| known.to_string());
+    let suggestion = typo.clone().or_else(|| {
+        properties
+            .keys()
+            .filter(|known| free(known))
+            .map(|known| {
+                (
+                    edit_distance(&key.to_lowercase(), &known.to_lowercase()),
+                    known,
+                )
+            })
+            .filter(|(distance, _)| *distance <= 2)
+            .min()
+            .map(|(_, known)| known.clone())
+    });
+    let message = match &suggestion {
+        Some(known) => format!("Unknown setting {}; did you mean {}?", key, known),
+        None => format!("Unknown setting {}", key),
+    };
+    let mut warning = issue(pointer, "unknown_key", message, typo.map(Fix::Rename));
+    warning.suggestion = suggestion;
+    warning.warning = true;
+    warning
+}
+
+/// Problems of a settings file's content
+pub fn validate_content(content: &str) -> Vec<SettingsIssue> {
+    if content.trim().is_empty() {
+        return Vec::new();
+    }
+    match serde_json::from_str::<Value>(content) {
+        Ok(value) => {
+            let mut issues = Vec::new();
+            check(schema(), &value, "", &mut issues);
+            issues
+        }
+        Err(e) => vec![issue("", "invalid_json", e.to_string(), None)],
+    }
+}
+
+fn apply_fix(settings: &mut Value, pointer: &str, fix: &Fix) -> bool {
+    match fix {
+        Fix::Replace(replacement) => match settings.pointer_mut(pointer) {
+            Some(slot) => {
+                *slot = replacement.clone();
+                true
+            }
+            None => false,
+        },
+        Fix::Rename(new_key) => {
+            let Some((parent, key)) = pointer.rsplit_once('/') else {
+                return false;
+            };
+            let key = key.replace("~1", "/").replace("~0", "~");
+            let Some(object) = settings.pointer_mut(parent).and_then(Value::as_object_mut) else {
+                return false;
+            };
+            if object.contains_key(new_key) {
+                return false;
+            }
+            match object.remove(&key) {
+                Some(value) => {
+                    object.insert(new_key.clone(), value);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+/// Apply every available fix; returns the number of fixes applied
+pub fn repair_value(settings: &mut Value) -> usize {
+    let mut repaired = 0;
+    for _ in 0..MAX_REPAIR_ROUNDS {
+        let mut issues = Vec::new();
+        check(schema(), settings, "", &mut issues);
+        let applied = issues
+            .iter()
+            .filter_map(|issue| Some((issue.pointer.as_str(), issue.fix.as_ref()?)))
+            .filter(|(pointer, fix)| apply_fix(settings, pointer, fix))
+            .count();
+        if applied == 0 {
+            break;
+        }
+        repaired += applied;
+    }
+    repaired
+}
+
+fn validation(path: &Path, issues: Vec<SettingsIssue>, repaired: usize) -> SettingsValidation {
+    SettingsValidation {
+        path: path.to_string_lossy().to_string(),
+        valid: issues.iter().all(|issue| issue.warning),
+        issues,
+        repaired,
+    }
+}
+
+fn read_content(path: &Path) -> Result<String, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Check a settings file (the user's by default) against the bundled schema
+#[tauri::command]
+pub async fn validate_claude_settings(
+    scope: Option<String>,
+    project_path: Option<String>,
+) -> Result<SettingsValidation, String> {
+    let path = settings_path(scope.as_deref().unwrap_or("user"), project_path.as_deref())?;
+    let content = read_content(&path)?;
+    Ok(validation(&path, validate_content(&content), 0))
+}
+
+/// Apply the available fixes to a settings file, keeping the previous content
+/// next to it as `.bak`, and return what is left to fix by hand
+#[tauri::command]
+pub async fn repair_claude_settings(
+    scope: Option<String>,
+    project_path: Option<String>,
+) -> Result<SettingsValidation, String> {
+    let path = settings_path(scope.as_deref().unwrap_or("user"), project_path.as_deref())?;
+    let _lock = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+    let content = read_content(&path)?;
+    if content.trim().is_empty() {
+        return Ok(validation(&path, Vec::new(), 0));
+    }
+    // Unparseable files are reported, never rewritten
+    let Ok(mut settings) = serde_json::from_str::<Value>(&content) else {
+        return Ok(validation(&path, validate_content(&content), 0));
+    };
+
+    let repaired = repair_value(&mut settings);
+    if repaired > 0 {
+        let backup = path.with_extension("json.bak");
+        std::fs::write(&backup, &content)
+            .map_err(|e| format!("Failed to write {}: {}", backup.display(), e))?;
+        write_json(&path, &settings)?;
+        log::info!("Repaired {} settings issue(s) in {:?}", repaired, path);
+    }
+    let mut issues = Vec::new();
+    check(schema(), &settings, "", &mut issues);
+    Ok(validation(&path, issues, repaired))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_and_repair() {
+        let content = r#"{
+            "modle": "opus",
+            "env": {"MAX_THINKING_TOKENS": 8000},
+            "permissions": {"allow": "Read", "defaultMode": "acceptedits"},
+            "includeCoAuthoredBy": "false",
+            "cleanupPeriodDays": -1,
+            "somethingElse": true,
+            "outputStyl": "Explanatory"
+        }"#;
+        let issues = validate_content(content);
+        let mut kinds: Vec<(&str, &str, bool)> = issues
+            .iter()
+            .map(|issue| {
+                (
+                    issue.pointer.as_str(),
+                    issue.kind.as_str(),
+                    issue.repairable,
+                )
+            })
+            .collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                ("/cleanupPeriodDays", "invalid_value", false),
+                ("/env/MAX_THINKING_TOKENS", "type_mismatch", true),
+                ("/includeCoAuthoredBy", "type_mismatch", true),
+                ("/modle", "unknown_key", true),
+                ("/outputStyl", "unknown_key", false),
+                ("/permissions/allow", "type_mismatch", true),
+                ("/permissions/defaultMode", "invalid_value", true),
+                ("/somethingElse", "unknown_key", false),
+            ]
+        );
+        let typo = issues
+            .iter()
+            .find(|issue| issue.pointer == "/modle")
+            .unwrap();
+        assert_eq!(typo.suggestion.as_deref(), Some("model"));
+        assert!(typo.warning);
+        // Close to a known key but not a known typo: suggested, never renamed
+        let close = issues
+            .iter()
+            .find(|issue| issue.pointer == "/outputStyl")
+            .unwrap();
+        assert_eq!(close.suggestion.as_deref(), Some("outputStyle"));
+
+        let mut settings: Value = serde_json::from_str(content).unwrap();
+        assert_eq!(repair_value(&mut settings), 5);
+        assert_eq!(settings["model"], "opus");
+        assert_eq!(settings["env"]["MAX_THINKING_TOKENS"], "8000");
+        assert_eq!(settings["permissions"]["allow"], json!(["Read"]));
+        assert_eq!(settings["permissions"]["defaultMode"], "acceptEdits");
+        assert_eq!(settings["includeCoAuthoredBy"], false);
+        assert_eq!(settings["outputStyl"], "Explanatory");
+        assert_eq!(settings["somethingElse"], true);
+
+        // Unknown keys alone leave the file valid
+        let path = Path::new("settings.json");
+        assert!(validation(path, validate_content(r#"{"newSetting": 1}"#), 0).valid);
+        assert!(validate_content(r#"{"sandbox": {"enabled": true}}"#).is_empty());
+
+        assert_eq!(validate_content("{\"model\": }")[0].kind, "invalid_json");
+        assert!(validate_content("").is_empty());
+    }
+}
@@ -3,7 +3,7 @@ use dirs;
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Represents a custom slash command
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +26,12 @@ pub struct SlashCommand {
     pub description: Option<String>,
     /// Allowed tools from frontmatter
     pub allowed_tools: Vec<String>,
+    /// Hint for the arguments shown in Claude Code's autocomplete (e.g. "[pr-number]")
+    #[serde(default)]
+    pub argument_hint: Option<String>,
+    /// Model the command runs with instead of the session model
+    #[serde(default)]
+    pub model: Option<String>,
     /// Whether the command has bash commands (!)
     pub has_bash_commands: bool,
     /// Whether the command has file references (@)
@@ -35,11 +41,25 @@ pub struct SlashCommand {
 }
 
 /// YAML frontmatter structure
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct CommandFrontmatter {
-    #[serde(rename = "allowed-tools")]
-    allowed_tools: Option<Vec<String>>,
+    /// YAML list, or the comma separated string Claude Code's docs use
+    #[serde(
+        rename = "allowed-tools",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    allowed_tools: Option<serde_yaml::Value>,
+    #[serde(
+        rename = "argument-hint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    argument_hint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
 }
 
 /// Parse a markdown file with optional YAML frontmatter
@@ -47,7 +67,7 @@ fn parse_markdown_with_frontmatter(content: &str) -> Result<(Option<CommandFront
     let lines: Vec<&str> = content.lines().collect();
     
     // Check if the file starts with YAML frontmatter
-    if lines.is_empty() || lines[0] != "---" {
+    if lines.is_empty() || lines[0].trim_end() != "---" {
         // No frontmatter
         return Ok((None, content.to_string()));
     }
@@ -55,7 +75,7 @@ fn parse_markdown_with_frontmatter(content: &str) -> Result<(Option<CommandFront
     // Find the end of frontmatter
     let mut frontmatter_end = None;
     for (i, line) in lines.iter().enumerate().skip(1) {
-        if *line == "---" {
+        if line.trim_end() == "---" {
             frontmatter_end = Some(i);
             break;
         }
@@ -81,33 +101,136 @@ fn parse_markdown_with_frontmatter(content: &str) -> Result<(Option<CommandFront
     }
 }
 
+/// Split `allowed-tools` into tool rules, keeping commas inside rules like
+/// `Bash(git add:*, git commit:*)` intact
+fn parse_allowed_tools(value: &serde_yaml::Value) -> Vec<String> {
+    let mut tools = Vec::new();
+    match value {
+        serde_yaml::Value::String(list) => {
+            let mut depth = 0usize;
+            let mut current = String::new();
+            for c in list.chars() {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth = depth.saturating_sub(1),
+                    ',' if depth == 0 => {
+                        tools.push(std::mem::take(&mut current));
+                        continue;
+                    }
+                    _ => {}
+                }
+                current.push(c);
+            }
+            tools.push(current);
+        }
+        serde_yaml::Value::Sequence(items) => {
+            tools.extend(
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .map(str::to_string),
+            );
+        }
+        _ => {}
+    }
+    tools
+        .into_iter()
+        .map(|tool| tool.trim().to_string())
+        .filter(|tool| !tool.is_empty())
+        .collect()
+}
+
+/// File names Windows reserves for devices, whatever the extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Check a command or namespace segment, which becomes a file or folder name
+///
+/// Any script is allowed; only what cannot be typed after `/` or would escape
+/// or break the path is rejected.
+fn validate_segment(segment: &str, what: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        return Err(format!("{} cannot be empty", what));
+    }
+    if segment == "." || segment == ".." {
+        return Err(format!("{} {:?} is not a valid name", what, segment));
+    }
+    if let Some(c) = segment
+        .chars()
+        .find(|c| matches!(c, '/' | '\\' | ':') || c.is_control() || c.is_whitespace())
+    {
+        return Err(format!("{} {:?} may not contain {:?}", what, segment, c));
+    }
+    let stem = segment.split('.').next().unwrap_or(segment).to_lowercase();
+    if RESERVED_NAMES.contains(&stem.as_str()) {
+        return Err(format!("{} {:?} is a reserved file name", what, segment));
+    }
+    Ok(())
+}
+
+/// Validate a command name, rejecting names taken by built-in commands
+fn validate_command_name(name: &str, namespace: Option<&[String]>) -> Result<(), String> {
+    validate_segment(name, "Command name")?;
+    if namespace.is_none() && create_default_commands().iter().any(|cmd| cmd.name == name) {
+        return Err(format!("/{} is a built-in command", name));
+    }
+    Ok(())
+}
+
+/// Split a namespace written as "frontend:components" or "frontend/components"
+/// into folder names; an empty namespace means none
+fn parse_namespace(namespace: Option<&str>) -> Result<Option<Vec<String>>, String> {
+    let Some(namespace) = namespace.map(str::trim).filter(|ns| !ns.is_empty()) else {
+        return Ok(None);
+    };
+    let segments: Vec<String> = namespace
+        .split([':', '/', '\\'])
+        .map(|segment| segment.trim().to_string())
+        .collect();
+    for segment in &segments {
+        validate_segment(segment, "Namespace")?;
+    }
+    Ok(Some(segments))
+}
+
+/// `.claude/commands` directory of a scope
+fn commands_dir(scope: &str, project_path: Option<&str>) -> Result<PathBuf, String> {
+    match scope {
+        "project" => project_path
+            .map(|path| PathBuf::from(path).join(".claude").join("commands"))
+            .ok_or_else(|| "Project path required for project scope".to_string()),
+        "user" => Ok(dirs::home_dir()
+            .ok_or_else(|| "Could not find home directory".to_string())?
+            .join(".claude")
+            .join("commands")),
+        _ => Err("Invalid scope. Must be 'project' or 'user'".to_string()),
+    }
+}
+
 /// Extract command name and namespace from file path
 fn extract_command_info(file_path: &Path, base_path: &Path) -> Result<(String, Option<String>)> {
     let relative_path = file_path
         .strip_prefix(base_path)
         .context("Failed to get relative path")?;
     
-    // Remove .md extension
-    let path_without_ext = relative_path
+    // Split into components, without the .md extension
+    let components: Vec<String> = relative_path
         .with_extension("")
-        .to_string_lossy()
-        .to_string();
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
     
-    // Split into components
-    let components: Vec<&str> = path_without_ext.split('/').collect();
-    
-    if components.is_empty() {
-        return Err(anyhow::anyhow!("Invalid command path"));
-    }
-    
-    if components.len() == 1 {
+    match components.split_last() {
+        None => Err(anyhow::anyhow!("Invalid command path")),
         // No namespace
-        Ok((components[0].to_string(), None))
-    } else {
+        Some((name, [])) => Ok((name.clone(), None)),
         // Last component is the command name, rest is namespace
-        let command_name = components.last().unwrap().to_string();
-        let namespace = components[..components.len() - 1].join(":");
-        Ok((command_name, Some(namespace)))
+        Some((name, namespace)) => Ok((name.clone(), Some(namespace.join(":")))),
     }
 }
 
@@ -141,14 +264,15 @@ fn load_command_from_file(
     // Check for special content
     let has_bash_commands = body.contains("!`");
     let has_file_references = body.contains('@');
-    let accepts_arguments = body.contains("$ARGUMENTS");
+    let accepts_arguments = body.contains("$ARGUMENTS") || body.contains("$1");
     
     // Extract metadata from frontmatter
-    let (description, allowed_tools) = if let Some(fm) = frontmatter {
-        (fm.description, fm.allowed_tools.unwrap_or_default())
-    } else {
-        (None, Vec::new())
-    };
+    let frontmatter = frontmatter.unwrap_or_default();
+    let allowed_tools = frontmatter
+        .allowed_tools
+        .as_ref()
+        .map(parse_allowed_tools)
+        .unwrap_or_default();
     
     Ok(SlashCommand {
         id,
@@ -158,14 +282,57 @@ fn load_command_from_file(
         namespace,
         file_path: file_path.to_string_lossy().to_string(),
         content: body,
-        description,
+        description: frontmatter.description,
         allowed_tools,
+        argument_hint: frontmatter.argument_hint,
+        model: frontmatter.model,
         has_bash_commands,
         has_file_references,
         accepts_arguments,
     })
 }
 
+/// Render a command as markdown, with frontmatter only if there is metadata
+fn render_command(
+    content: &str,
+    description: Option<&str>,
+    allowed_tools: &[String],
+    argument_hint: Option<&str>,
+    model: Option<&str>,
+) -> Result<String, String> {
+    let non_empty = |value: Option<&str>| {
+        value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let frontmatter = CommandFrontmatter {
+        allowed_tools: (!allowed_tools.is_empty()).then(|| {
+            serde_yaml::Value::Sequence(
+                allowed_tools
+                    .iter()
+                    .map(|tool| serde_yaml::Value::String(tool.trim().to_string()))
+                    .collect(),
+            )
+        }),
+        argument_hint: non_empty(argument_hint),
+        description: non_empty(description),
+        model: non_empty(model),
+    };
+    
+    if frontmatter.allowed_tools.is_none()
+        && frontmatter.argument_hint.is_none()
+        && frontmatter.description.is_none()
+        && frontmatter.model.is_none()
+    {
+        return Ok(content.to_string());
+    }
+    // serde_yaml quotes descriptions containing ':' or '#' that hand-written YAML would break on
+    let yaml = serde_yaml::to_string(&frontmatter)
+        .map_err(|e| format!("Failed to serialize frontmatter: {}", e))?;
+    Ok(format!("---\n{}---\n\n{}", yaml, content))
+}
+
 /// Recursively find all markdown files in a directory
 fn find_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
     if !dir.exists() {
@@ -197,6 +364,35 @@ fn find_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
     Ok(())
 }
 
+/// Load every command under a scope's commands directory
+fn load_commands_in(dir: &Path, scope: &str) -> Vec<SlashCommand> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+    debug!("Scanning {} commands at: {:?}", scope, dir);
+    
+    let mut md_files = Vec::new();
+    if let Err(e) = find_markdown_files(dir, &mut md_files) {
+        error!("Failed to find {} command files: {}", scope, e);
+        return Vec::new();
+    }
+    md_files.sort();
+    
+    let mut commands = Vec::new();
+    for file_path in md_files {
+        match load_command_from_file(&file_path, dir, scope) {
+            Ok(cmd) => {
+                debug!("Loaded {} command: {}", scope, cmd.full_command);
+                commands.push(cmd);
+            }
+            Err(e) => {
+                error!("Failed to load command from {:?}: {}", file_path, e);
+            }
+        }
+    }
+    commands
+}
+
 /// Create default/built-in slash commands
 fn create_default_commands() -> Vec<SlashCommand> {
     vec![
@@ -211,6 +407,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Add additional working directories".to_string(),
             description: Some("添加额外的工作目录".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -226,6 +424,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Manage custom AI subagents for specialized tasks".to_string(),
             description: Some("管理专门任务的自定义AI子代理".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -241,6 +441,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Report bugs (sends conversation to Anthropic)".to_string(),
             description: Some("报告错误（发送对话给Anthropic）".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -256,6 +458,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Clear conversation history".to_string(),
             description: Some("清除对话历史".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -271,6 +475,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Compact conversation with optional focus instructions".to_string(),
             description: Some("压缩对话内容以节省令牌".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -286,6 +492,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "View/modify configuration".to_string(),
             description: Some("查看/修改配置".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -301,6 +509,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Show token usage statistics".to_string(),
             description: Some("显示令牌使用统计".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -316,6 +526,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Checks the health of your Claude Code installation".to_string(),
             description: Some("检查Claude Code安装的健康状态".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -331,6 +543,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Get usage help".to_string(),
             description: Some("获取使用帮助".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -346,6 +560,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Initialize project with CLAUDE.md guide".to_string(),
             description: Some("使用CLAUDE.md指南初始化项目".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -361,6 +577,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Switch Anthropic accounts".to_string(),
             description: Some("切换Anthropic账户".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -376,6 +594,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Sign out from your Anthropic account".to_string(),
             description: Some("退出Anthropic账户".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -391,6 +611,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Manage MCP server connections and OAuth authentication".to_string(),
             description: Some("管理MCP服务器连接和OAuth认证".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -406,6 +628,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Edit CLAUDE.md memory files".to_string(),
             description: Some("编辑CLAUDE.md记忆文件".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -421,6 +645,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Select or change the AI model".to_string(),
             description: Some("选择或更改AI模型".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -436,6 +662,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "View or update permissions".to_string(),
             description: Some("查看或更新权限".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -451,6 +679,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "View pull request comments".to_string(),
             description: Some("查看拉取请求评论".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -466,6 +696,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Request code review".to_string(),
             description: Some("请求代码审查".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -481,6 +713,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "View account and system statuses".to_string(),
             description: Some("查看账户和系统状态".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -496,6 +730,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Install Shift+Enter key binding for newlines".to_string(),
             description: Some("安装Shift+Enter键绑定用于换行".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -511,6 +747,8 @@ fn create_default_commands() -> Vec<SlashCommand> {
             content: "Enter vim mode for alternating insert and command modes".to_string(),
             description: Some("进入vim模式，交替使用插入和命令模式".to_string()),
             allowed_tools: vec![],
+            argument_hint: None,
+            model: None,
             has_bash_commands: false,
             has_file_references: false,
             accepts_arguments: false,
@@ -530,53 +768,16 @@ pub async fn slash_commands_list(
     commands.extend(create_default_commands());
     
     // Load project commands if project path is provided
-    if let Some(proj_path) = project_path {
-        let project_commands_dir = PathBuf::from(&proj_path).join(".claude").join("commands");
-        if project_commands_dir.exists() {
-            debug!("Scanning project commands at: {:?}", project_commands_dir);
-            
-            let mut md_files = Vec::new();
-            if let Err(e) = find_markdown_files(&project_commands_dir, &mut md_files) {
-                error!("Failed to find project command files: {}", e);
-            } else {
-                for file_path in md_files {
-                    match load_command_from_file(&file_path, &project_commands_dir, "project") {
-                        Ok(cmd) => {
-                            debug!("Loaded project command: {}", cmd.full_command);
-                            commands.push(cmd);
-                        }
-                        Err(e) => {
-                            error!("Failed to load command from {:?}: {}", file_path, e);
-                        }
-                    }
-                }
-            }
-        }
+    if let Some(proj_path) = project_path.as_deref() {
+        commands.extend(load_commands_in(
+            &commands_dir("project", Some(proj_path))?,
+            "project",
+        ));
     }
     
     // Load user commands
-    if let Some(home_dir) = dirs::home_dir() {
-        let user_commands_dir = home_dir.join(".claude").join("commands");
-        if user_commands_dir.exists() {
-            debug!("Scanning user commands at: {:?}", user_commands_dir);
-            
-            let mut md_files = Vec::new();
-            if let Err(e) = find_markdown_files(&user_commands_dir, &mut md_files) {
-                error!("Failed to find user command files: {}", e);
-            } else {
-                for file_path in md_files {
-                    match load_command_from_file(&file_path, &user_commands_dir, "user") {
-                        Ok(cmd) => {
-                            debug!("Loaded user command: {}", cmd.full_command);
-                            commands.push(cmd);
-                        }
-                        Err(e) => {
-                            error!("Failed to load command from {:?}: {}", file_path, e);
-                        }
-                    }
-                }
-            }
-        }
+    if let Ok(user_commands_dir) = commands_dir("user", None) {
+        commands.extend(load_commands_in(&user_commands_dir, "user"));
     }
     
     info!("Found {} slash commands", commands.len());
@@ -584,19 +785,16 @@ pub async fn slash_commands_list(
 }
 
 /// Get a single slash command by ID
+///
+/// Pass `project_path` to find project commands as well.
 #[tauri::command]
-pub async fn slash_command_get(command_id: String) -> Result<SlashCommand, String> {
+pub async fn slash_command_get(
+    command_id: String,
+    project_path: Option<String>,
+) -> Result<SlashCommand, String> {
     debug!("Getting slash command: {}", command_id);
     
-    // Parse the ID to determine scope and reconstruct file path
-    let parts: Vec<&str> = command_id.split('-').collect();
-    if parts.len() < 2 {
-        return Err("Invalid command ID".to_string());
-    }
-    
-    // The actual implementation would need to reconstruct the path and reload the command
-    // For now, we'll list all commands and find the matching one
-    let commands = slash_commands_list(None).await?;
+    let commands = slash_commands_list(project_path).await?;
     
     commands
         .into_iter()
@@ -604,8 +802,47 @@ pub async fn slash_command_get(command_id: String) -> Result<SlashCommand, Strin
         .ok_or_else(|| format!("Command not found: {}", command_id))
 }
 
+/// Namespaces (folders) of a scope, including empty ones, sorted
+#[tauri::command]
+pub async fn slash_command_namespaces(
+    scope: String,
+    project_path: Option<String>,
+) -> Result<Vec<String>, String> {
+    let base_dir = commands_dir(&scope, project_path.as_deref())?;
+    let mut namespaces = Vec::new();
+    let mut pending = vec![base_dir.clone()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !path.is_dir() || hidden {
+                continue;
+            }
+            if let Ok((name, parent)) = extract_command_info(&path, &base_dir) {
+                namespaces.push(match parent {
+                    Some(parent) => format!("{}:{}", parent, name),
+                    None => name,
+                });
+            }
+            pending.push(path);
+        }
+    }
+    namespaces.sort();
+    Ok(namespaces)
+}
+
 /// Create or update a slash command
+///
+/// `namespace` may be written as "frontend:components" or "frontend/components";
+/// folders are created as needed. Pass `original_id` when editing so a renamed or
+/// moved command replaces its old file; without it an existing command is never
+/// overwritten.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn slash_command_save(
     scope: String,
     name: String,
@@ -613,75 +850,75 @@ pub async fn slash_command_save(
     content: String,
     description: Option<String>,
     allowed_tools: Vec<String>,
+    argument_hint: Option<String>,
+    model: Option<String>,
+    original_id: Option<String>,
     project_path: Option<String>,
 ) -> Result<SlashCommand, String> {
     info!("Saving slash command: {} in scope: {}", name, scope);
     
     // Validate inputs
-    if name.is_empty() {
-        return Err("Command name cannot be empty".to_string());
+    let name = name.trim();
+    let namespace = parse_namespace(namespace.as_deref())?;
+    validate_command_name(name, namespace.as_deref())?;
+    if let Some(tool) = allowed_tools.iter().find(|tool| tool.trim().is_empty()) {
+        return Err(format!("Invalid allowed tool: {:?}", tool));
     }
+    let base_dir = commands_dir(&scope, project_path.as_deref())?;
     
-    if !["project", "user"].contains(&scope.as_str()) {
-        return Err("Invalid scope. Must be 'project' or 'user'".to_string());
+    // Build file path
+    let mut dir_path = base_dir.clone();
+    for component in namespace.iter().flatten() {
+        dir_path = dir_path.join(component);
     }
+    let file_path = dir_path.join(format!("{}.md", name));
     
-    // Determine base directory
-    let base_dir = if scope == "project" {
-        if let Some(proj_path) = project_path {
-            PathBuf::from(proj_path).join(".claude").join("commands")
-        } else {
-            return Err("Project path required for project scope".to_string());
-        }
-    } else {
-        dirs::home_dir()
-            .ok_or_else(|| "Could not find home directory".to_string())?
-            .join(".claude")
-            .join("commands")
+    let existing = load_commands_in(&base_dir, &scope);
+    let original = match original_id.as_deref() {
+        Some(original_id) => Some(
+            existing
+                .iter()
+                .find(|cmd| cmd.id == original_id)
+                .ok_or_else(|| format!("Command not found: {}", original_id))?,
+        ),
+        None => None,
     };
-    
-    // Build file path
-    let mut file_path = base_dir.clone();
-    if let Some(ns) = &namespace {
-        for component in ns.split(':') {
-            file_path = file_path.join(component);
-        }
+    let original_path = original.map(|cmd| PathBuf::from(&cmd.file_path));
+    if file_path.exists() && original_path.as_deref() != Some(file_path.as_path()) {
+        let full_command = match &namespace {
+            Some(ns) => format!("/{}:{}", ns.join(":"), name),
+            None => format!("/{}", name),
+        };
+        return Err(format!(
+            "A {} command {} already exists",
+            scope, full_command
+        ));
     }
     
     // Create directories if needed
-    fs::create_dir_all(&file_path)
-        .map_err(|e| format!("Failed to create directories: {}", e))?;
-    
-    // Add filename
-    file_path = file_path.join(format!("{}.md", name));
-    
-    // Build content with frontmatter
-    let mut full_content = String::new();
-    
-    // Add frontmatter if we have metadata
-    if description.is_some() || !allowed_tools.is_empty() {
-        full_content.push_str("---\n");
-        
-        if let Some(desc) = &description {
-            full_content.push_str(&format!("description: {}\n", desc));
-        }
-        
-        if !allowed_tools.is_empty() {
-            full_content.push_str("allowed-tools:\n");
-            for tool in &allowed_tools {
-                full_content.push_str(&format!("  - {}\n", tool));
-            }
-        }
-        
-        full_content.push_str("---\n\n");
-    }
+    fs::create_dir_all(&dir_path).map_err(|e| format!("Failed to create directories: {}", e))?;
     
-    full_content.push_str(&content);
+    let full_content = render_command(
+        &content,
+        description.as_deref(),
+        &allowed_tools,
+        argument_hint.as_deref(),
+        model.as_deref(),
+    )?;
     
     // Write file
     fs::write(&file_path, &full_content)
         .map_err(|e| format!("Failed to write command file: {}", e))?;
     
+    // Remove the old file of a renamed or moved command
+    if let Some(original_path) = original_path.filter(|path| *path != file_path) {
+        fs::remove_file(&original_path)
+            .map_err(|e| format!("Failed to remove old command file: {}", e))?;
+        if let Some(parent) = original_path.parent() {
+            let _ = remove_empty_dirs(parent, &base_dir);
+        }
+    }
+    
     // Load and return the saved command
     load_command_from_file(&file_path, &base_dir, &scope)
         .map_err(|e| format!("Failed to load saved command: {}", e))
@@ -701,29 +938,33 @@ pub async fn slash_command_delete(command_id: String, project_path: Option<Strin
     }
     
     // List all commands (including project commands if applicable)
-    let commands = slash_commands_list(project_path).await?;
+    let commands = slash_commands_list(project_path.clone()).await?;
     
     // Find the command by ID
     let command = commands
         .into_iter()
         .find(|cmd| cmd.id == command_id)
         .ok_or_else(|| format!("Command not found: {}", command_id))?;
+    if command.scope == "default" {
+        return Err(format!("{} is a built-in command", command.full_command));
+    }
     
     // Delete the file
     fs::remove_file(&command.file_path)
         .map_err(|e| format!("Failed to delete command file: {}", e))?;
     
-    // Clean up empty directories
+    // Clean up empty namespace folders
+    let base_dir = commands_dir(&command.scope, project_path.as_deref())?;
     if let Some(parent) = Path::new(&command.file_path).parent() {
-        let _ = remove_empty_dirs(parent);
+        let _ = remove_empty_dirs(parent, &base_dir);
     }
     
     Ok(format!("Deleted command: {}", command.full_command))
 }
 
-/// Remove empty directories recursively
-fn remove_empty_dirs(dir: &Path) -> Result<()> {
-    if !dir.exists() {
+/// Remove empty directories recursively, up to but not including `base_dir`
+fn remove_empty_dirs(dir: &Path, base_dir: &Path) -> Result<()> {
+    if !dir.exists() || dir == base_dir || !dir.starts_with(base_dir) {
         return Ok(());
     }
     
@@ -735,9 +976,67 @@ fn remove_empty_dirs(dir: &Path) -> Result<()> {
         
         // Try to remove parent if it's also empty
         if let Some(parent) = dir.parent() {
-            let _ = remove_empty_dirs(parent);
+            let _ = remove_empty_dirs(parent, base_dir);
         }
     }
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("git").join("commit.md");
+        fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        let tools = vec![
+            "Bash(git add:*, git commit:*)".to_string(),
+            "Read".to_string(),
+        ];
+        let content = render_command(
+            "Commit with message: $ARGUMENTS",
+            Some("Create a commit: staged files only"),
+            &tools,
+            Some("[message]"),
+            None,
+        )
+        .unwrap();
+        fs::write(&file_path, content).unwrap();
+
+        let command = load_command_from_file(&file_path, dir.path(), "user").unwrap();
+        assert_eq!(command.full_command, "/git:commit");
+        assert_eq!(command.namespace.as_deref(), Some("git"));
+        assert_eq!(
+            command.description.as_deref(),
+            Some("Create a commit: staged files only")
+        );
+        assert_eq!(command.allowed_tools, tools);
+        assert_eq!(command.argument_hint.as_deref(), Some("[message]"));
+        assert!(command.accepts_arguments);
+
+        let comma_list = serde_yaml::Value::String("Bash(git status:*), Read ,".to_string());
+        assert_eq!(
+            parse_allowed_tools(&comma_list),
+            vec!["Bash(git status:*)", "Read"]
+        );
+
+        assert_eq!(
+            parse_namespace(Some("frontend/components")).unwrap(),
+            Some(vec!["frontend".to_string(), "components".to_string()])
+        );
+        assert!(parse_namespace(Some("../outside")).is_err());
+        assert!(validate_command_name("clear", None).is_err());
+        assert!(validate_command_name("clear", Some(&["git".to_string()])).is_ok());
+        assert!(validate_command_name("bad name", None).is_err());
+        assert!(validate_command_name("コミット", None).is_ok());
+        assert!(validate_command_name("review.v2", None).is_ok());
+        assert!(validate_command_name("..", None).is_err());
+        assert!(validate_command_name("Con", None).is_err());
+        assert!(validate_command_name("nul.txt", None).is_err());
+        assert!(validate_command_name("tab\there", None).is_err());
+        assert!(parse_namespace(Some("前端:组件")).is_ok());
+    }
+}
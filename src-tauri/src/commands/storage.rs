@@ -449,6 +449,20 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
             .map_err(|e| format!("Failed to drop agents table: {}", e))?;
         conn.execute("DROP TABLE IF EXISTS app_settings", [])
             .map_err(|e| format!("Failed to drop app_settings table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS provider_usage_sessions", [])
+            .map_err(|e| format!("Failed to drop provider_usage_sessions table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS managed_processes", [])
+            .map_err(|e| format!("Failed to drop managed_processes table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS run_history", [])
+            .map_err(|e| format!("Failed to drop run_history table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS task_chains", [])
+            .map_err(|e| format!("Failed to drop task_chains table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS task_chain_runs", [])
+            .map_err(|e| format!("Failed to drop task_chain_runs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_index", [])
+            .map_err(|e| format!("Failed to drop session_index table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS project_meta", [])
+            .map_err(|e| format!("Failed to drop project_meta table: {}", e))?;
         
         // Drop relay station tables
         conn.execute("DROP TABLE IF EXISTS relay_station_tokens", [])
@@ -481,11 +495,7 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
         use crate::commands::relay_stations::RelayStationManager;
         use std::sync::{Arc, Mutex};
         
-        let db_path = app
-            .path()
-            .app_data_dir()
-            .unwrap()
-            .join("agents.db");
+        let db_path = crate::portable::app_data_dir(&app).unwrap().join("agents.db");
         
         let relay_conn = rusqlite::Connection::open(&db_path)
             .map_err(|e| format!("Failed to open agents database for relay station manager: {}", e))?;
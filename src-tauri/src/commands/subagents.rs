@@ -0,0 +1,344 @@
+use crate::frontmatter;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Model aliases Claude Code accepts in a subagent's `model` field
+const MODEL_ALIASES: &[&str] = &["sonnet", "opus", "haiku", "inherit"];
+
+/// A Claude Code subagent defined in `.claude/agents/<name>.md`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubAgent {
+    /// Unique identifier used by Claude to pick the agent
+    pub name: String,
+    /// When Claude should delegate to this agent
+    pub description: String,
+    /// Tools the agent may use; `None` inherits every tool of the session
+    pub tools: Option<Vec<String>>,
+    /// Model alias or full model ID; `None` uses the default subagent model
+    pub model: Option<String>,
+    /// Color shown for the agent in the Claude UI
+    pub color: Option<String>,
+    /// System prompt (markdown body)
+    pub system_prompt: String,
+    /// "project" or "user"
+    pub scope: String,
+    pub file_path: String,
+    /// Problems with the file; Claude Code ignores agents that have any
+    pub errors: Vec<String>,
+}
+
+/// Fields of a subagent as edited in the GUI
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubAgentInput {
+    pub name: String,
+    pub description: String,
+    pub tools: Option<Vec<String>>,
+    pub model: Option<String>,
+    pub color: Option<String>,
+    pub system_prompt: String,
+}
+
+/// YAML frontmatter of an agent file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AgentFrontmatter {
+    name: Option<String>,
+    description: Option<String>,
+    /// Comma separated list, or a YAML list in hand-written files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tools: Option<serde_yaml::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+}
+
+fn parse_tools(value: &serde_yaml::Value) -> Result<Vec<String>, String> {
+    let tools: Vec<String> = match value {
+        serde_yaml::Value::String(list) => list
+            .split(',')
+            .map(|tool| tool.trim().to_string())
+            .collect(),
+        serde_yaml::Value::Sequence(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(|tool| tool.trim().to_string())
+                    .ok_or_else(|| "tools must be a list of tool names".to_string())
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err("tools must be a comma separated list of tool names".to_string()),
+    };
+    Ok(tools.into_iter().filter(|tool| !tool.is_empty()).collect())
+}
+
+/// Check the fields Claude Code requires; returns every problem found
+fn validate_agent(input: &SubAgentInput) -> Vec<String> {
+    let mut errors = Vec::new();
+    let name = input.name.trim();
+    if name.is_empty() {
+        errors.push("name is required".to_string());
+    } else if !name.split('-').all(|part| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    }) {
+        errors.push(format!(
+            "name {:?} must use lowercase letters, digits and hyphens, e.g. code-reviewer",
+            name
+        ));
+    }
+    if input.description.trim().is_empty() {
+        errors.push("description is required".to_string());
+    }
+    if let Some(tools) = &input.tools {
+        if tools
+            .iter()
+            .any(|tool| tool.trim().is_empty() || tool.contains(','))
+        {
+            errors.push("tools must be non-empty tool names without commas".to_string());
+        }
+    }
+    if let Some(model) = &input.model {
+        let model = model.trim();
+        if !MODEL_ALIASES.contains(&model) && !model.starts_with("claude-") {
+            errors.push(format!(
+                "model must be one of {} or a full Claude model ID",
+                MODEL_ALIASES.join(", ")
+            ));
+        }
+    }
+    errors
+}
+
+/// Load an agent file, keeping invalid ones so the GUI can show what is wrong
+fn load_agent(file_path: &Path, scope: &str) -> Result<SubAgent, String> {
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read agent file: {}", e))?;
+    let file_stem = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let (input, mut errors) = match frontmatter::split::<AgentFrontmatter>(&content) {
+        Ok((frontmatter, body)) => {
+            let mut errors = Vec::new();
+            let tools = match frontmatter.tools.as_ref().map(parse_tools).transpose() {
+                Ok(tools) => tools,
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            };
+            let input = SubAgentInput {
+                name: frontmatter.name.unwrap_or_default(),
+                description: frontmatter.description.unwrap_or_default(),
+                tools,
+                model: frontmatter.model,
+                color: frontmatter.color,
+                system_prompt: body,
+            };
+            (input, errors)
+        }
+        Err(e) => (
+            SubAgentInput {
+                name: String::new(),
+                description: String::new(),
+                tools: None,
+                model: None,
+                color: None,
+                system_prompt: content.clone(),
+            },
+            vec![e],
+        ),
+    };
+    errors.extend(validate_agent(&input));
+
+    Ok(SubAgent {
+        // Fall back to the file name so broken files can still be listed and deleted
+        name: if input.name.is_empty() {
+            file_stem
+        } else {
+            input.name
+        },
+        description: input.description,
+        tools: input.tools,
+        model: input.model,
+        color: input.color,
+        system_prompt: input.system_prompt,
+        scope: scope.to_string(),
+        file_path: file_path.to_string_lossy().to_string(),
+        errors,
+    })
+}
+
+/// Render an agent as markdown with frontmatter
+fn render_agent(input: &SubAgentInput) -> Result<String, String> {
+    let frontmatter = AgentFrontmatter {
+        name: Some(input.name.trim().to_string()),
+        description: Some(input.description.trim().to_string()),
+        tools: input
+            .tools
+            .as_ref()
+            .map(|tools| serde_yaml::Value::String(tools.join(", "))),
+        model: input.model.as_ref().map(|model| model.trim().to_string()),
+        color: input.color.clone().filter(|color| !color.trim().is_empty()),
+    };
+    frontmatter::render(&frontmatter, &input.system_prompt)
+}
+
+/// `.claude/agents` directory of a scope
+fn agents_dir(scope: &str, project_path: Option<&str>) -> Result<PathBuf, String> {
+    match scope {
+        "project" => project_path
+            .map(|path| PathBuf::from(path).join(".claude").join("agents"))
+            .ok_or_else(|| "Project path required for project scope".to_string()),
+        "user" => Ok(dirs::home_dir()
+            .ok_or_else(|| "Could not find home directory".to_string())?
+            .join(".claude")
+            .join("agents")),
+        _ => Err("Invalid scope. Must be 'project' or 'user'".to_string()),
+    }
+}
+
+fn load_agents_in(dir: &Path, scope: &str) -> Vec<SubAgent> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut agents: Vec<SubAgent> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| match load_agent(&path, scope) {
+            Ok(agent) => Some(agent),
+            Err(e) => {
+                warn!("Failed to load agent from {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect();
+    agents.sort_by(|a, b| a.name.cmp(&b.name));
+    agents
+}
+
+/// List subagents of the project (if given) and of the user
+///
+/// Project agents come first; Claude Code prefers them over user agents of the same name.
+#[tauri::command]
+pub async fn subagents_list(project_path: Option<String>) -> Result<Vec<SubAgent>, String> {
+    let mut agents = Vec::new();
+    if let Some(project_path) = project_path.as_deref() {
+        agents.extend(load_agents_in(
+            &agents_dir("project", Some(project_path))?,
+            "project",
+        ));
+    }
+    agents.extend(load_agents_in(&agents_dir("user", None)?, "user"));
+    debug!("Found {} subagents", agents.len());
+    Ok(agents)
+}
+
+/// Create or update a subagent
+///
+/// Pass `original_name` when editing so a renamed agent replaces its old file.
+#[tauri::command]
+pub async fn subagent_save(
+    scope: String,
+    project_path: Option<String>,
+    agent: SubAgentInput,
+    original_name: Option<String>,
+) -> Result<SubAgent, String> {
+    let errors = validate_agent(&agent);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let dir = agents_dir(&scope, project_path.as_deref())?;
+    let name = agent.name.trim();
+    info!("Saving subagent {} in scope {}", name, scope);
+
+    let existing = load_agents_in(&dir, &scope);
+    let find = |name: &str| existing.iter().find(|existing| existing.name == name);
+    let original = original_name.as_deref().and_then(find);
+    if original_name.is_some() && original.is_none() {
+        return Err(format!(
+            "Agent not found: {}",
+            original_name.unwrap_or_default()
+        ));
+    }
+    if let Some(other) = find(name) {
+        if original.map(|original| &original.file_path) != Some(&other.file_path) {
+            return Err(format!("An agent named {} already exists", name));
+        }
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create agents directory: {}", e))?;
+    let file_path = dir.join(format!("{}.md", name));
+    fs::write(&file_path, render_agent(&agent)?)
+        .map_err(|e| format!("Failed to write agent file: {}", e))?;
+    if let Some(original) = original {
+        if Path::new(&original.file_path) != file_path {
+            fs::remove_file(&original.file_path)
+                .map_err(|e| format!("Failed to remove renamed agent file: {}", e))?;
+        }
+    }
+
+    load_agent(&file_path, &scope)
+}
+
+/// Delete a subagent file
+#[tauri::command]
+pub async fn subagent_delete(
+    scope: String,
+    project_path: Option<String>,
+    name: String,
+) -> Result<String, String> {
+    let dir = agents_dir(&scope, project_path.as_deref())?;
+    let agent = load_agents_in(&dir, &scope)
+        .into_iter()
+        .find(|agent| agent.name == name)
+        .ok_or_else(|| format!("Agent not found: {}", name))?;
+    fs::remove_file(&agent.file_path).map_err(|e| format!("Failed to delete agent file: {}", e))?;
+    info!("Deleted subagent {} from {}", name, agent.file_path);
+    Ok(format!("Deleted agent: {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = SubAgentInput {
+            name: "code-reviewer".to_string(),
+            description: "Reviews code: use after every change".to_string(),
+            tools: Some(vec!["Read".to_string(), "Grep".to_string()]),
+            model: Some("sonnet".to_string()),
+            color: None,
+            system_prompt: "You are a senior reviewer.".to_string(),
+        };
+        let path = dir.path().join("code-reviewer.md");
+        fs::write(&path, render_agent(&input).unwrap()).unwrap();
+
+        let agent = load_agent(&path, "user").unwrap();
+        assert!(agent.errors.is_empty(), "{:?}", agent.errors);
+        assert_eq!(agent.description, input.description);
+        assert_eq!(agent.tools, input.tools);
+        assert_eq!(agent.system_prompt, input.system_prompt);
+
+        let path = dir.path().join("broken.md");
+        fs::write(
+            &path,
+            "---\nname: Bad Name\ntools: [1]\nmodel: gpt\n---\nPrompt",
+        )
+        .unwrap();
+        let agent = load_agent(&path, "user").unwrap();
+        assert_eq!(agent.errors.len(), 4, "{:?}", agent.errors);
+
+        fs::write(&path, "No frontmatter").unwrap();
+        let agent = load_agent(&path, "user").unwrap();
+        assert_eq!(agent.name, "broken");
+        assert!(!agent.errors.is_empty());
+    }
+}
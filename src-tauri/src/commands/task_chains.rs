@@ -0,0 +1,866 @@
+//! Task chains: sequences of headless Claude CLI runs executed one after another,
+//! started manually or on a cron schedule. A step runs a prompt file, a saved
+//! prompt or a saved agent against a project, so a single-step chain is a
+//! scheduled prompt. Each step is tracked by the process registry like any
+//! other managed process.
+use chrono::{DateTime, Local, Timelike, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::commands::agents::AgentDb;
+use crate::process::schedule::CronSchedule;
+use crate::process::{summarize_usage, OutputStream, ProcessRegistryState, RunUsageSummary};
+
+/// What to do when a step exits unsuccessfully
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Skip the remaining steps and mark the run failed
+    #[default]
+    Stop,
+    /// Carry on with the next step
+    Continue,
+    /// Run the step again, up to `max_attempts` attempts in total, then stop
+    Retry { max_attempts: u32 },
+}
+
+/// One Claude CLI invocation of a chain
+///
+/// The prompt comes from `prompt_file`, `prompt` or the agent `agent_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainStep {
+    pub project_path: String,
+    /// Prompt file, relative paths are resolved against `project_path`
+    #[serde(default)]
+    pub prompt_file: String,
+    /// Prompt saved with the step; the task when running an agent
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Saved agent to run, with its system prompt and default task
+    #[serde(default)]
+    pub agent_id: Option<i64>,
+    /// Empty runs an agent step with the agent's model
+    #[serde(default)]
+    pub model: String,
+    /// Overrides the chain's failure policy for this step
+    #[serde(default)]
+    pub failure_policy: Option<FailurePolicy>,
+}
+
+/// A stored task chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskChain {
+    pub id: Option<i64>,
+    pub name: String,
+    pub steps: Vec<ChainStep>,
+    /// Five-field cron expression, `None` for manual runs only
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub failure_policy: FailurePolicy,
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_run_at: Option<String>,
+    #[serde(default)]
+    pub next_run_at: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+/// Progress of a single step within a chain run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainStepResult {
+    pub index: usize,
+    /// "pending", "running", "completed", "failed", "skipped" or "cancelled"
+    pub status: String,
+    /// Registry run ID of the latest attempt
+    pub run_id: Option<i64>,
+    pub attempts: u32,
+    pub exit_code: Option<i32>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+    /// Final answer of the latest attempt
+    #[serde(default)]
+    pub result: Option<String>,
+    /// Tokens and cost of the latest attempt
+    #[serde(default)]
+    pub usage: Option<RunUsageSummary>,
+}
+
+/// One execution of a chain, emitted as `task-chain-progress` on every change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainRun {
+    pub id: i64,
+    pub chain_id: i64,
+    pub chain_name: String,
+    /// "manual" or "schedule"
+    pub trigger: String,
+    /// "running", "completed", "failed" or "cancelled"
+    pub status: String,
+    pub steps: Vec<ChainStepResult>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+struct ActiveChainRun {
+    chain_id: i64,
+    cancelled: Arc<AtomicBool>,
+    current_run_id: Arc<Mutex<Option<i64>>>,
+}
+
+/// Chain runs currently executing, keyed by chain run ID
+#[derive(Default)]
+pub struct TaskChainState {
+    active: Mutex<HashMap<i64, ActiveChainRun>>,
+}
+
+impl TaskChainState {
+    fn is_chain_running(&self, chain_id: i64) -> bool {
+        self.active
+            .lock()
+            .map(|active| active.values().any(|run| run.chain_id == chain_id))
+            .unwrap_or(false)
+    }
+}
+
+fn next_run_at(schedule: Option<&str>) -> Option<String> {
+    let schedule = CronSchedule::parse(schedule?).ok()?;
+    schedule.next_after(&Local::now()).map(|time| time.to_rfc3339())
+}
+
+fn upcoming_runs(
+    schedule: &CronSchedule,
+    after: DateTime<Local>,
+    count: usize,
+) -> Vec<DateTime<Local>> {
+    std::iter::successors(schedule.next_after(&after), |time| {
+        schedule.next_after(time)
+    })
+    .take(count)
+    .collect()
+}
+
+fn validate_chain(chain: &TaskChain) -> Result<(), String> {
+    if chain.name.trim().is_empty() {
+        return Err("Chain name is required".to_string());
+    }
+    if chain.steps.is_empty() {
+        return Err("A chain needs at least one step".to_string());
+    }
+    for (index, step) in chain.steps.iter().enumerate() {
+        if step.project_path.trim().is_empty() {
+            return Err(format!("Step {} needs a project path", index + 1));
+        }
+        let has_file = !step.prompt_file.trim().is_empty();
+        let has_prompt = step.prompt.as_deref().is_some_and(|p| !p.trim().is_empty());
+        // An agent step may carry a prompt as its task
+        let valid = match step.agent_id {
+            Some(_) => !has_file,
+            None => has_file != has_prompt,
+        };
+        if !valid {
+            return Err(format!(
+                "Step {} needs exactly one of a prompt file, a prompt or an agent",
+                index + 1
+            ));
+        }
+    }
+    let policies = std::iter::once(&chain.failure_policy)
+        .chain(chain.steps.iter().filter_map(|step| step.failure_policy.as_ref()));
+    for policy in policies {
+        if let FailurePolicy::Retry { max_attempts: 0 } = policy {
+            return Err("Retry policy needs at least 1 attempt".to_string());
+        }
+    }
+    if let Some(schedule) = &chain.schedule {
+        CronSchedule::parse(schedule)?;
+    }
+    Ok(())
+}
+
+fn read_chain(row: &rusqlite::Row) -> rusqlite::Result<TaskChain> {
+    let steps: String = row.get(2)?;
+    let schedule: Option<String> = row.get(3)?;
+    let policy: String = row.get(4)?;
+    let enabled: bool = row.get(5)?;
+    Ok(TaskChain {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        steps: serde_json::from_str(&steps).unwrap_or_default(),
+        // A disabled chain is not going to run on its schedule
+        next_run_at: next_run_at(schedule.as_deref().filter(|_| enabled)),
+        schedule,
+        failure_policy: serde_json::from_str(&policy).unwrap_or_default(),
+        enabled,
+        last_run_at: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+const CHAIN_COLUMNS: &str =
+    "id, name, steps, schedule, failure_policy, enabled, last_run_at, created_at, updated_at";
+
+fn load_chain(conn: &Connection, id: i64) -> Result<TaskChain, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM task_chains WHERE id = ?1", CHAIN_COLUMNS),
+        params![id],
+        read_chain,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Task chain {} not found", id))
+}
+
+fn save_chain_run(conn: &Connection, run: &ChainRun) -> Result<(), String> {
+    let steps = serde_json::to_string(&run.steps).map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE task_chain_runs SET status = ?1, steps = ?2, finished_at = ?3 WHERE id = ?4",
+        params![run.status, steps, run.finished_at, run.id],
+    )
+    .map_err(|e| format!("Failed to save chain run: {}", e))?;
+    Ok(())
+}
+
+/// List all task chains with their next scheduled run
+#[tauri::command]
+pub async fn list_task_chains(db: State<'_, AgentDb>) -> Result<Vec<TaskChain>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM task_chains ORDER BY created_at DESC",
+            CHAIN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let chains = stmt
+        .query_map([], read_chain)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(chains)
+}
+
+/// Create a task chain
+#[tauri::command]
+pub async fn create_task_chain(
+    db: State<'_, AgentDb>,
+    chain: TaskChain,
+) -> Result<TaskChain, String> {
+    validate_chain(&chain)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO task_chains (name, steps, schedule, failure_policy, enabled, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+        params![
+            chain.name,
+            serde_json::to_string(&chain.steps).map_err(|e| e.to_string())?,
+            chain.schedule,
+            serde_json::to_string(&chain.failure_policy).map_err(|e| e.to_string())?,
+            chain.enabled,
+            now,
+        ],
+    )
+    .map_err(|e| format!("Failed to create task chain: {}", e))?;
+    load_chain(&conn, conn.last_insert_rowid())
+}
+
+/// Update a task chain's steps, schedule and policies
+#[tauri::command]
+pub async fn update_task_chain(
+    db: State<'_, AgentDb>,
+    chain: TaskChain,
+) -> Result<TaskChain, String> {
+    let id = chain.id.ok_or("Task chain ID is required")?;
+    validate_chain(&chain)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE task_chains SET name = ?1, steps = ?2, schedule = ?3, failure_policy = ?4,
+                    enabled = ?5, updated_at = ?6
+             WHERE id = ?7",
+            params![
+                chain.name,
+                serde_json::to_string(&chain.steps).map_err(|e| e.to_string())?,
+                chain.schedule,
+                serde_json::to_string(&chain.failure_policy).map_err(|e| e.to_string())?,
+                chain.enabled,
+                Utc::now().to_rfc3339(),
+                id,
+            ],
+        )
+        .map_err(|e| format!("Failed to update task chain: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Task chain {} not found", id));
+    }
+    load_chain(&conn, id)
+}
+
+/// Delete a task chain and its run history
+#[tauri::command]
+pub async fn delete_task_chain(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM task_chain_runs WHERE chain_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM task_chains WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete task chain: {}", e))?;
+    Ok(())
+}
+
+/// Turn a chain's schedule on or off without touching its steps
+#[tauri::command]
+pub async fn set_task_chain_enabled(
+    db: State<'_, AgentDb>,
+    id: i64,
+    enabled: bool,
+) -> Result<TaskChain, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE task_chains SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+            params![enabled, Utc::now().to_rfc3339(), id],
+        )
+        .map_err(|e| format!("Failed to update task chain: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Task chain {} not found", id));
+    }
+    load_chain(&conn, id)
+}
+
+/// Next `count` times (default 5) a cron expression fires, to check it before saving
+#[tauri::command]
+pub async fn preview_task_chain_schedule(
+    schedule: String,
+    count: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let schedule = CronSchedule::parse(&schedule)?;
+    let runs = upcoming_runs(&schedule, Local::now(), count.unwrap_or(5).min(50));
+    Ok(runs.iter().map(|time| time.to_rfc3339()).collect())
+}
+
+/// Start a chain now, returning the chain run ID
+#[tauri::command]
+pub async fn run_task_chain(app: AppHandle, id: i64) -> Result<i64, String> {
+    start_chain_run(&app, id, "manual")
+}
+
+/// Stop a running chain: the current step is killed and the rest are skipped
+#[tauri::command]
+pub async fn cancel_task_chain_run(
+    state: State<'_, TaskChainState>,
+    registry: State<'_, ProcessRegistryState>,
+    chain_run_id: i64,
+) -> Result<bool, String> {
+    let current_run_id = {
+        let active = state.active.lock().map_err(|e| e.to_string())?;
+        let Some(run) = active.get(&chain_run_id) else {
+            return Ok(false);
+        };
+        run.cancelled.store(true, Ordering::SeqCst);
+        let current = run.current_run_id.lock().map_err(|e| e.to_string())?;
+        *current
+    };
+
+    if let Some(run_id) = current_run_id {
+        registry.0.kill_process(run_id).await?;
+    }
+    Ok(true)
+}
+
+/// List recent runs of a chain (or of all chains), newest first
+#[tauri::command]
+pub async fn list_task_chain_runs(
+    db: State<'_, AgentDb>,
+    chain_id: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<ChainRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.id, r.chain_id, COALESCE(c.name, ''), r.trigger, r.status, r.steps,
+                    r.started_at, r.finished_at
+             FROM task_chain_runs r LEFT JOIN task_chains c ON c.id = r.chain_id
+             WHERE (?1 IS NULL OR r.chain_id = ?1)
+             ORDER BY r.started_at DESC, r.id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let runs = stmt
+        .query_map(params![chain_id, limit.unwrap_or(50)], |row| {
+            let steps: String = row.get(5)?;
+            Ok(ChainRun {
+                id: row.get(0)?,
+                chain_id: row.get(1)?,
+                chain_name: row.get(2)?,
+                trigger: row.get(3)?,
+                status: row.get(4)?,
+                steps: serde_json::from_str(&steps).unwrap_or_default(),
+                started_at: row.get(6)?,
+                finished_at: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(runs)
+}
+
+/// Record a new chain run and execute it in the background
+fn start_chain_run(app: &AppHandle, chain_id: i64, trigger: &str) -> Result<i64, String> {
+    let state = app.state::<TaskChainState>();
+    if state.is_chain_running(chain_id) {
+        return Err(format!("Task chain {} is already running", chain_id));
+    }
+
+    let db = app.state::<AgentDb>();
+    let (chain, run) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let chain = load_chain(&conn, chain_id)?;
+        let now = Utc::now().to_rfc3339();
+        let steps: Vec<ChainStepResult> = (0..chain.steps.len())
+            .map(|index| ChainStepResult {
+                index,
+                status: "pending".to_string(),
+                run_id: None,
+                attempts: 0,
+                exit_code: None,
+                started_at: None,
+                finished_at: None,
+                error: None,
+                result: None,
+                usage: None,
+            })
+            .collect();
+        conn.execute(
+            "INSERT INTO task_chain_runs (chain_id, trigger, status, steps, started_at)
+             VALUES (?1, ?2, 'running', ?3, ?4)",
+            params![
+                chain_id,
+                trigger,
+                serde_json::to_string(&steps).map_err(|e| e.to_string())?,
+                now,
+            ],
+        )
+        .map_err(|e| format!("Failed to record chain run: {}", e))?;
+        conn.execute(
+            "UPDATE task_chains SET last_run_at = ?1 WHERE id = ?2",
+            params![now, chain_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let run = ChainRun {
+            id: conn.last_insert_rowid(),
+            chain_id,
+            chain_name: chain.name.clone(),
+            trigger: trigger.to_string(),
+            status: "running".to_string(),
+            steps,
+            started_at: now,
+            finished_at: None,
+        };
+        (chain, run)
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let current_run_id = Arc::new(Mutex::new(None));
+    state.active.lock().map_err(|e| e.to_string())?.insert(
+        run.id,
+        ActiveChainRun {
+            chain_id,
+            cancelled: cancelled.clone(),
+            current_run_id: current_run_id.clone(),
+        },
+    );
+
+    log::info!(
+        "Starting task chain '{}' ({} steps, trigger: {})",
+        chain.name,
+        chain.steps.len(),
+        trigger
+    );
+    let run_id = run.id;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        execute_chain(&app, chain, run, cancelled, current_run_id).await;
+        if let Ok(mut active) = app.state::<TaskChainState>().active.lock() {
+            active.remove(&run_id);
+        }
+    });
+    Ok(run_id)
+}
+
+fn publish_progress(app: &AppHandle, run: &ChainRun) {
+    let db = app.state::<AgentDb>();
+    if let Ok(conn) = db.0.lock() {
+        if let Err(e) = save_chain_run(&conn, run) {
+            log::warn!("{}", e);
+        }
+    }
+    let _ = app.emit("task-chain-progress", run);
+}
+
+async fn execute_chain(
+    app: &AppHandle,
+    chain: TaskChain,
+    mut run: ChainRun,
+    cancelled: Arc<AtomicBool>,
+    current_run_id: Arc<Mutex<Option<i64>>>,
+) {
+    let chain_id = chain.id.unwrap_or(run.chain_id);
+    let mut failed = false;
+
+    for (index, step) in chain.steps.iter().enumerate() {
+        let policy = step
+            .failure_policy
+            .clone()
+            .unwrap_or_else(|| chain.failure_policy.clone());
+        let max_attempts = match policy {
+            FailurePolicy::Retry { max_attempts } => max_attempts.max(1),
+            _ => 1,
+        };
+
+        let mut succeeded = false;
+        while run.steps[index].attempts < max_attempts && !cancelled.load(Ordering::SeqCst) {
+            run.steps[index].attempts += 1;
+            run.steps[index].status = "running".to_string();
+            run.steps[index].started_at = Some(Utc::now().to_rfc3339());
+            run.steps[index].finished_at = None;
+            run.steps[index].error = None;
+            run.steps[index].result = None;
+            run.steps[index].usage = None;
+            publish_progress(app, &run);
+
+            let outcome = run_step(app, chain_id, run.id, index, step, &current_run_id).await;
+            let result = &mut run.steps[index];
+            result.finished_at = Some(Utc::now().to_rfc3339());
+            match outcome {
+                Ok(outcome) => {
+                    let exit_code = outcome.exit_code;
+                    result.run_id = Some(outcome.run_id);
+                    result.exit_code = exit_code;
+                    result.result = outcome.result;
+                    result.usage = outcome.usage;
+                    succeeded = exit_code == Some(0);
+                    if !succeeded {
+                        result.error = Some(match exit_code {
+                            Some(code) => format!("Exited with code {}", code),
+                            None => "Process was terminated".to_string(),
+                        });
+                    }
+                }
+                Err(e) => result.error = Some(e),
+            }
+
+            if succeeded {
+                break;
+            }
+            log::warn!(
+                "Task chain '{}' step {} attempt {} failed: {}",
+                chain.name,
+                index + 1,
+                result.attempts,
+                result.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+
+        if cancelled.load(Ordering::SeqCst) {
+            if run.steps[index].attempts > 0 {
+                run.steps[index].status = "cancelled".to_string();
+            }
+            break;
+        }
+        run.steps[index].status = if succeeded { "completed" } else { "failed" }.to_string();
+        publish_progress(app, &run);
+
+        if !succeeded {
+            failed = true;
+            if policy != FailurePolicy::Continue {
+                break;
+            }
+        }
+    }
+
+    for result in run.steps.iter_mut().filter(|result| result.status == "pending") {
+        result.status = "skipped".to_string();
+    }
+    run.status = if cancelled.load(Ordering::SeqCst) {
+        "cancelled"
+    } else if failed {
+        "failed"
+    } else {
+        "completed"
+    }
+    .to_string();
+    run.finished_at = Some(Utc::now().to_rfc3339());
+    log::info!("Task chain '{}' finished: {}", chain.name, run.status);
+    publish_progress(app, &run);
+}
+
+/// What a finished step attempt produced
+struct StepOutcome {
+    run_id: i64,
+    exit_code: Option<i32>,
+    result: Option<String>,
+    usage: Option<RunUsageSummary>,
+}
+
+/// Prompt, system prompt and model a step runs with
+async fn resolve_step(
+    app: &AppHandle,
+    step: &ChainStep,
+) -> Result<(String, Option<String>, String), String> {
+    let saved_prompt = step.prompt.clone().filter(|p| !p.trim().is_empty());
+    if let Some(agent_id) = step.agent_id {
+        let agent = crate::commands::agents::get_agent(app.state::<AgentDb>(), agent_id).await?;
+        let task = saved_prompt
+            .or(agent.default_task.clone())
+            .ok_or_else(|| format!("Agent '{}' has no default task", agent.name))?;
+        let model = if step.model.trim().is_empty() {
+            agent.model
+        } else {
+            step.model.clone()
+        };
+        return Ok((task, Some(agent.system_prompt), model));
+    }
+
+    let prompt = match saved_prompt {
+        Some(prompt) => prompt,
+        None => {
+            let prompt_path = Path::new(&step.project_path).join(&step.prompt_file);
+            std::fs::read_to_string(&prompt_path).map_err(|e| {
+                format!(
+                    "Failed to read prompt file {}: {}",
+                    prompt_path.display(),
+                    e
+                )
+            })?
+        }
+    };
+    Ok((prompt, None, step.model.clone()))
+}
+
+/// Run one step to completion
+async fn run_step(
+    app: &AppHandle,
+    chain_id: i64,
+    chain_run_id: i64,
+    index: usize,
+    step: &ChainStep,
+    current_run_id: &Arc<Mutex<Option<i64>>>,
+) -> Result<StepOutcome, String> {
+    let (prompt, system_prompt, model) = resolve_step(app, step).await?;
+    crate::commands::usage_quotas::ensure_launch_allowed(app, &model).await?;
+
+    let claude_path = crate::claude_binary::find_claude_binary(app)?;
+    let mut args = vec!["-p".to_string(), prompt.clone()];
+    if let Some(system_prompt) = system_prompt {
+        args.push("--system-prompt".to_string());
+        args.push(system_prompt);
+    }
+    // An empty model leaves the choice to the CLI's own settings
+    if !model.trim().is_empty() {
+        args.push("--model".to_string());
+        args.push(model.clone());
+    }
+    args.extend([
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+        "--dangerously-skip-permissions".to_string(),
+    ]);
+    let mut cmd =
+        crate::commands::claude::create_system_command(&claude_path, args, &step.project_path)
+            .await?;
+    cmd.stdin(std::process::Stdio::null());
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+    let pid = child.id().unwrap_or(0);
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let registry = app.state::<ProcessRegistryState>().0.clone();
+    let run_id = registry.register_chain_step(
+        chain_id,
+        chain_run_id,
+        index,
+        pid,
+        step.project_path.clone(),
+        prompt,
+        model,
+        child,
+    )?;
+    if let Ok(mut current) = current_run_id.lock() {
+        *current = Some(run_id);
+    }
+
+    // Capture output in the registry so it streams as `process-output:{run_id}`
+    let mut readers = Vec::new();
+    // The final `result` message carries the answer and the usage totals
+    let result_line = Arc::new(Mutex::new(None::<String>));
+    if let Some(stdout) = stdout {
+        let registry = registry.clone();
+        let result_line = result_line.clone();
+        readers.push(tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = registry.append_output(run_id, OutputStream::Stdout, &line);
+                let is_result = line.contains("\"result\"")
+                    && serde_json::from_str::<serde_json::Value>(&line)
+                        .is_ok_and(|msg| msg["type"] == "result");
+                if is_result {
+                    if let Ok(mut result_line) = result_line.lock() {
+                        *result_line = Some(line);
+                    }
+                }
+            }
+        }));
+    }
+    if let Some(stderr) = stderr {
+        let registry = registry.clone();
+        readers.push(tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = registry.append_output(run_id, OutputStream::Stderr, &line);
+            }
+        }));
+    }
+
+    let status = registry.wait_for_exit(run_id).await;
+    for reader in readers {
+        let _ = reader.await;
+    }
+    if let Ok(mut current) = current_run_id.lock() {
+        *current = None;
+    }
+
+    let exit_code = status.and_then(|status| status.code());
+    if status.is_some() {
+        let outcome = if exit_code == Some(0) { "completed" } else { "failed" };
+        if let Err(e) = registry.finish_process(run_id, exit_code, outcome) {
+            log::warn!("Failed to finish chain step {}: {}", run_id, e);
+        }
+    }
+
+    let result_line = result_line.lock().ok().and_then(|line| line.clone());
+    let result = result_line
+        .as_deref()
+        .and_then(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .and_then(|msg| msg["result"].as_str().map(str::to_string));
+    let usage = result_line.map(|line| summarize_usage(std::iter::once(line.as_str())));
+    Ok(StepOutcome {
+        run_id,
+        exit_code,
+        result,
+        usage,
+    })
+}
+
+/// Start chains whose cron schedule matches, checking once per minute
+pub fn start_task_chain_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            // Wake shortly after the start of the next minute
+            let now = Local::now();
+            let wait_secs = 61 - u64::from(now.second());
+            tokio::time::sleep(std::time::Duration::from_secs(wait_secs)).await;
+
+            let due: Vec<i64> = {
+                let db = app.state::<AgentDb>();
+                let Ok(conn) = db.0.lock() else {
+                    continue;
+                };
+                let chains = conn
+                    .prepare(&format!(
+                        "SELECT {} FROM task_chains WHERE enabled = 1 AND schedule IS NOT NULL",
+                        CHAIN_COLUMNS
+                    ))
+                    .and_then(|mut stmt| {
+                        stmt.query_map([], read_chain)?
+                            .collect::<Result<Vec<_>, _>>()
+                    });
+                let now = Local::now();
+                match chains {
+                    Ok(chains) => chains
+                        .into_iter()
+                        .filter(|chain| {
+                            chain
+                                .schedule
+                                .as_deref()
+                                .and_then(|schedule| CronSchedule::parse(schedule).ok())
+                                .is_some_and(|schedule| schedule.matches(&now))
+                        })
+                        .filter_map(|chain| chain.id)
+                        .collect(),
+                    Err(e) => {
+                        log::warn!("Failed to load scheduled task chains: {}", e);
+                        continue;
+                    }
+                }
+            };
+
+            for chain_id in due {
+                if let Err(e) = start_chain_run(&app, chain_id, "schedule") {
+                    log::warn!("Scheduled run of task chain {} not started: {}", chain_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn step(prompt_file: &str, prompt: Option<&str>, agent_id: Option<i64>) -> ChainStep {
+        ChainStep {
+            project_path: "/tmp/project".to_string(),
+            prompt_file: prompt_file.to_string(),
+            prompt: prompt.map(str::to_string),
+            agent_id,
+            model: String::new(),
+            failure_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_steps_and_preview() {
+        let mut chain = TaskChain {
+            id: None,
+            name: "Nightly audit".to_string(),
+            steps: vec![step("", Some("Run cargo audit and summarize"), None)],
+            schedule: Some("0 3 * * *".to_string()),
+            failure_policy: FailurePolicy::Stop,
+            enabled: true,
+            last_run_at: None,
+            next_run_at: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        assert!(validate_chain(&chain).is_ok());
+        chain.steps = vec![step("", Some("Check dependencies"), Some(1))];
+        assert!(validate_chain(&chain).is_ok());
+        chain.steps = vec![step("audit.md", Some("Both"), None)];
+        assert!(validate_chain(&chain).is_err());
+        chain.steps = vec![step("audit.md", None, Some(1))];
+        assert!(validate_chain(&chain).is_err());
+        chain.steps = vec![step("", None, None)];
+        assert!(validate_chain(&chain).is_err());
+
+        let schedule = CronSchedule::parse("0 3 * * *").unwrap();
+        let after = Local.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let runs = upcoming_runs(&schedule, after, 3);
+        let at_three = |day| Local.with_ymd_and_hms(2026, 1, day, 3, 0, 0).unwrap();
+        assert_eq!(runs, vec![at_three(2), at_three(3), at_three(4)]);
+    }
+}
@@ -0,0 +1,393 @@
+//! Preferences of the frontend, kept in SQLite instead of localStorage
+//!
+//! Values are JSON and live in `app_settings` under a `ui.` prefix, apart from
+//! the keys the backend owns. Known keys are checked and have a default; any
+//! other key of the right shape holds whatever JSON the frontend stores.
+//! `locale` is the backend locale itself, so both sides always agree.
+//! Every change is emitted as `app-setting-changed`.
+use crate::commands::agents::AgentDb;
+use crate::commands::locale;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+
+/// Prefix of frontend keys in `app_settings`
+const KEY_PREFIX: &str = "ui.";
+
+/// Key backed by the backend locale setting
+const LOCALE_KEY: &str = "locale";
+
+/// Largest serialized value accepted for a key
+const MAX_VALUE_BYTES: usize = 64 * 1024;
+
+const MAX_KEY_CHARS: usize = 128;
+
+#[derive(Debug, Clone, Copy)]
+enum SettingKind {
+    /// One of the listed strings
+    Choice(&'static [&'static str], &'static str),
+    /// Whole seconds in `min..=max`, or 0 to turn the refresh off
+    Interval { min: u64, max: u64, default: u64 },
+}
+
+/// Keys the frontend shares with the backend, with their type and default
+const KNOWN_SETTINGS: &[(&str, SettingKind)] = &[
+    (
+        "theme",
+        SettingKind::Choice(&["system", "light", "dark"], "system"),
+    ),
+    (
+        "refresh_interval.usage",
+        SettingKind::Interval {
+            min: 10,
+            max: 86_400,
+            default: 60,
+        },
+    ),
+    (
+        "refresh_interval.relay_stations",
+        SettingKind::Interval {
+            min: 30,
+            max: 86_400,
+            default: 300,
+        },
+    ),
+    (
+        "refresh_interval.sessions",
+        SettingKind::Interval {
+            min: 5,
+            max: 3_600,
+            default: 30,
+        },
+    ),
+    (
+        "refresh_interval.processes",
+        SettingKind::Interval {
+            min: 1,
+            max: 600,
+            default: 5,
+        },
+    ),
+];
+
+/// Payload of `app-setting-changed`
+#[derive(Debug, Clone, Serialize)]
+pub struct AppSettingChange {
+    pub key: String,
+    /// The value now in effect; the default after a reset
+    pub value: Value,
+}
+
+fn known_kind(key: &str) -> Option<SettingKind> {
+    KNOWN_SETTINGS
+        .iter()
+        .find(|(known, _)| *known == key)
+        .map(|(_, kind)| *kind)
+}
+
+fn default_value(key: &str) -> Value {
+    match known_kind(key) {
+        Some(SettingKind::Choice(_, default)) => Value::from(default),
+        Some(SettingKind::Interval { default, .. }) => Value::from(default),
+        None => Value::Null,
+    }
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    let valid = !key.is_empty()
+        && key.chars().count() <= MAX_KEY_CHARS
+        && key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid setting key {:?}: use lowercase letters, digits, '_', '.' and '-'",
+            key
+        ))
+    }
+}
+
+/// Check `value` for `key` and return it as it is stored
+pub fn validate_setting(key: &str, value: &Value) -> Result<Value, String> {
+    validate_key(key)?;
+    match known_kind(key) {
+        Some(SettingKind::Choice(choices, _)) => match value.as_str() {
+            Some(choice) if choices.contains(&choice) => Ok(value.clone()),
+            _ => Err(format!("{} must be one of {}", key, choices.join(", "))),
+        },
+        Some(SettingKind::Interval { min, max, .. }) => {
+            let seconds = value
+                .as_u64()
+                .or_else(|| {
+                    value
+                        .as_f64()
+                        .filter(|f| f.fract() == 0.0 && *f >= 0.0)
+                        .map(|f| f as u64)
+                })
+                .ok_or_else(|| format!("{} must be a whole number of seconds", key))?;
+            if seconds != 0 && !(min..=max).contains(&seconds) {
+                return Err(format!(
+                    "{} must be 0 (off) or between {} and {} seconds",
+                    key, min, max
+                ));
+            }
+            Ok(Value::from(seconds))
+        }
+        None => {
+            let size = serde_json::to_string(value)
+                .map_err(|e| e.to_string())?
+                .len();
+            if size > MAX_VALUE_BYTES {
+                return Err(format!(
+                    "Value of {} is {} bytes; the limit is {}",
+                    key, size, MAX_VALUE_BYTES
+                ));
+            }
+            Ok(value.clone())
+        }
+    }
+}
+
+/// A stored value, or the default of a known key; `null` for unset other keys
+///
+/// A stored value that no longer validates reads as the default.
+pub fn load_setting(conn: &Connection, key: &str) -> Result<Value, String> {
+    validate_key(key)?;
+    if key == LOCALE_KEY {
+        return Ok(locale::saved_locale(conn).map_or(Value::Null, Value::from));
+    }
+    let stored = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![format!("{}{}", KEY_PREFIX, key)],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|value| serde_json::from_str::<Value>(&value).ok())
+        .and_then(|value| validate_setting(key, &value).ok());
+    Ok(stored.unwrap_or_else(|| default_value(key)))
+}
+
+/// Store a value, or remove it with `None`; returns the value now in effect
+///
+/// Not for `locale`, which has to go through the locale module.
+pub fn store_setting(conn: &Connection, key: &str, value: Option<&Value>) -> Result<Value, String> {
+    let stored_key = format!("{}{}", KEY_PREFIX, key);
+    match value {
+        Some(value) => {
+            let value = validate_setting(key, value)?;
+            conn.execute(
+                "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value,
+                    updated_at = CURRENT_TIMESTAMP",
+                params![stored_key, value.to_string()],
+            )
+            .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+            Ok(value)
+        }
+        None => {
+            validate_key(key)?;
+            conn.execute(
+                "DELETE FROM app_settings WHERE key = ?1",
+                params![stored_key],
+            )
+            .map_err(|e| format!("Failed to reset setting {}: {}", key, e))?;
+            Ok(default_value(key))
+        }
+    }
+}
+
+/// Every known key and every stored frontend key with its value
+pub fn load_all_settings(conn: &Connection) -> Result<Map<String, Value>, String> {
+    let mut stmt = conn
+        .prepare("SELECT substr(key, ?1) FROM app_settings WHERE key LIKE ?2")
+        .map_err(|e| e.to_string())?;
+    let stored = stmt
+        .query_map(
+            params![KEY_PREFIX.len() + 1, format!("{}%", KEY_PREFIX)],
+            |row| row.get::<_, String>(0),
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+
+    let keys = KNOWN_SETTINGS
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .chain([LOCALE_KEY.to_string()])
+        .chain(stored.into_iter().filter(|key| validate_key(key).is_ok()));
+    let mut settings = Map::new();
+    for key in keys {
+        let value = load_setting(conn, &key)?;
+        settings.insert(key, value);
+    }
+    Ok(settings)
+}
+
+/// Read a frontend setting; known keys fall back to their default
+#[tauri::command]
+pub async fn get_app_setting(db: State<'_, AgentDb>, key: String) -> Result<Value, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_setting(&conn, &key)
+}
+
+/// Store a frontend setting, or reset it with `null`, and emit
+/// `app-setting-changed` to every window
+///
+/// `locale` switches the backend locale as `set_locale` does.
+#[tauri::command]
+pub async fn set_app_setting(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    key: String,
+    value: Value,
+) -> Result<Value, String> {
+    let value = if key == LOCALE_KEY {
+        let locale = match &value {
+            Value::Null => None,
+            Value::String(locale) => Some(locale.as_str()),
+            _ => return Err("locale must be a locale tag or null".to_string()),
+        };
+        let info = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            locale::save_locale(&conn, locale)?
+        };
+        locale::announce_locale(&app, &info);
+        if info.follows_system {
+            Value::Null
+        } else {
+            Value::from(info.locale)
+        }
+    } else {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        store_setting(&conn, &key, Some(&value).filter(|value| !value.is_null()))?
+    };
+
+    log::debug!("App setting {} changed", key);
+    let _ = app.emit(
+        "app-setting-changed",
+        AppSettingChange {
+            key,
+            value: value.clone(),
+        },
+    );
+    Ok(value)
+}
+
+/// Current values of `keys`, or of every setting without keys, to start
+/// watching them; later changes arrive as `app-setting-changed` events
+#[tauri::command]
+pub async fn watch_app_settings(
+    db: State<'_, AgentDb>,
+    keys: Option<Vec<String>>,
+) -> Result<Map<String, Value>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match keys {
+        Some(keys) => keys
+            .into_iter()
+            .map(|key| Ok((key.clone(), load_setting(&conn, &key)?)))
+            .collect(),
+        None => load_all_settings(&conn),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_validate_setting() {
+        assert_eq!(
+            validate_setting("theme", &json!("dark")).unwrap(),
+            json!("dark")
+        );
+        assert!(validate_setting("theme", &json!("blue")).is_err());
+        assert_eq!(
+            validate_setting("refresh_interval.usage", &json!(120.0)).unwrap(),
+            json!(120)
+        );
+        assert_eq!(
+            validate_setting("refresh_interval.usage", &json!(0)).unwrap(),
+            json!(0)
+        );
+        assert!(validate_setting("refresh_interval.usage", &json!(5)).is_err());
+        assert!(validate_setting("refresh_interval.usage", &json!(1.5)).is_err());
+        assert!(validate_setting("refresh_interval.usage", &json!("60")).is_err());
+        assert_eq!(
+            validate_setting("sidebar.width", &json!({"px": 280})).unwrap(),
+            json!({"px": 280})
+        );
+        assert!(validate_setting("Sidebar", &json!(1)).is_err());
+        assert!(validate_setting(".hidden", &json!(1)).is_err());
+        assert!(validate_setting("notes", &json!("x".repeat(MAX_VALUE_BYTES))).is_err());
+    }
+
+    #[test]
+    fn test_store_and_load_settings() {
+        let conn = settings_db();
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('app_lock_passcode', 'hash')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(load_setting(&conn, "theme").unwrap(), json!("system"));
+        assert_eq!(load_setting(&conn, "sidebar.width").unwrap(), Value::Null);
+
+        store_setting(&conn, "theme", Some(&json!("light"))).unwrap();
+        store_setting(&conn, "sidebar.width", Some(&json!(280))).unwrap();
+        assert!(store_setting(&conn, "theme", Some(&json!("neon"))).is_err());
+        assert_eq!(load_setting(&conn, "theme").unwrap(), json!("light"));
+
+        // Backend keys are neither listed nor reachable
+        assert_eq!(
+            load_setting(&conn, "app_lock_passcode").unwrap(),
+            Value::Null
+        );
+        let all = load_all_settings(&conn).unwrap();
+        assert_eq!(all["theme"], json!("light"));
+        assert_eq!(all["sidebar.width"], json!(280));
+        assert_eq!(all["refresh_interval.processes"], json!(5));
+        assert_eq!(all[LOCALE_KEY], Value::Null);
+        assert!(!all.contains_key("app_lock_passcode"));
+
+        assert_eq!(
+            store_setting(&conn, "theme", None).unwrap(),
+            json!("system")
+        );
+        assert_eq!(load_setting(&conn, "theme").unwrap(), json!("system"));
+
+        // A stored value that no longer validates reads as the default
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('ui.refresh_interval.usage', '3')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            load_setting(&conn, "refresh_interval.usage").unwrap(),
+            json!(60)
+        );
+    }
+}
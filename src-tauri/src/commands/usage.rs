@@ -7,6 +7,8 @@ use std::path::PathBuf;
 use std::env;
 use tauri::command;
 
+use crate::commands::currency::Converted;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ClaudeSettings {
     env: Option<HashMap<String, serde_json::Value>>,
@@ -40,16 +42,16 @@ fn get_api_base_url() -> String {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UsageEntry {
-    timestamp: String,
-    model: String,
-    input_tokens: u64,
-    output_tokens: u64,
-    cache_creation_tokens: u64,
-    cache_read_tokens: u64,
-    cost: f64,
-    session_id: String,
-    project_path: String,
-    api_base_url: String,
+    pub(crate) timestamp: String,
+    pub(crate) model: String,
+    pub(crate) input_tokens: u64,
+    pub(crate) output_tokens: u64,
+    pub(crate) cache_creation_tokens: u64,
+    pub(crate) cache_read_tokens: u64,
+    pub(crate) cost: f64,
+    pub(crate) session_id: String,
+    pub(crate) project_path: String,
+    pub(crate) api_base_url: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -109,6 +111,17 @@ pub struct ApiBaseUrlUsage {
     session_count: u64,
 }
 
+// Claude 4.5 pricing constants (per million tokens); Sonnet 4.5 costs the same as Sonnet 4
+const OPUS_45_INPUT_PRICE: f64 = 5.0;
+const OPUS_45_OUTPUT_PRICE: f64 = 25.0;
+const OPUS_45_CACHE_WRITE_PRICE: f64 = 6.25;
+const OPUS_45_CACHE_READ_PRICE: f64 = 0.50;
+
+const HAIKU_45_INPUT_PRICE: f64 = 1.0;
+const HAIKU_45_OUTPUT_PRICE: f64 = 5.0;
+const HAIKU_45_CACHE_WRITE_PRICE: f64 = 1.25;
+const HAIKU_45_CACHE_READ_PRICE: f64 = 0.10;
+
 // Claude 4 pricing constants (per million tokens) - Updated January 2025
 const OPUS_4_INPUT_PRICE: f64 = 15.0;
 const OPUS_4_OUTPUT_PRICE: f64 = 75.0;
@@ -140,6 +153,9 @@ const HAIKU_35_CACHE_READ_PRICE: f64 = 0.08;
 // Claude Code session window duration (5 hours)
 const SESSION_WINDOW_HOURS: i64 = 5;
 
+// Longest span a usage series covers, so a chart never gets an unbounded number of days
+const MAX_SERIES_DAYS: u32 = 366;
+
 // Helper function to check if a session is still active based on Claude Code's 5-hour window
 // fn is_session_active(session_start: &str, current_time: &DateTime<Local>) -> bool {
 //     if let Ok(start_time) = DateTime::parse_from_rfc3339(session_start) {
@@ -206,9 +222,24 @@ fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
     let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
     let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
 
-    // Calculate cost based on model - improved pattern matching
+    // Calculate cost based on model - improved pattern matching.
+    // Claude 3.x model IDs put the version first ("claude-3-5-haiku-20241022").
     let (input_price, output_price, cache_write_price, cache_read_price) =
-        if model.contains("opus-4") || model.contains("claude-opus-4") {
+        if model.contains("opus-4-5") {
+            (
+                OPUS_45_INPUT_PRICE,
+                OPUS_45_OUTPUT_PRICE,
+                OPUS_45_CACHE_WRITE_PRICE,
+                OPUS_45_CACHE_READ_PRICE,
+            )
+        } else if model.contains("haiku-4-5") {
+            (
+                HAIKU_45_INPUT_PRICE,
+                HAIKU_45_OUTPUT_PRICE,
+                HAIKU_45_CACHE_WRITE_PRICE,
+                HAIKU_45_CACHE_READ_PRICE,
+            )
+        } else if model.contains("opus-4") || model.contains("claude-opus-4") {
             (
                 OPUS_4_INPUT_PRICE,
                 OPUS_4_OUTPUT_PRICE,
@@ -222,21 +253,21 @@ fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
                 SONNET_4_CACHE_WRITE_PRICE,
                 SONNET_4_CACHE_READ_PRICE,
             )
-        } else if model.contains("sonnet-3.7") || model.contains("claude-sonnet-3.7") {
+        } else if model.contains("sonnet-3.7") || model.contains("3-7-sonnet") {
             (
                 SONNET_37_INPUT_PRICE,
                 SONNET_37_OUTPUT_PRICE,
                 SONNET_37_CACHE_WRITE_PRICE,
                 SONNET_37_CACHE_READ_PRICE,
             )
-        } else if model.contains("sonnet-3.5") || model.contains("claude-sonnet-3.5") {
+        } else if model.contains("sonnet-3.5") || model.contains("3-5-sonnet") {
             (
                 SONNET_35_INPUT_PRICE,
                 SONNET_35_OUTPUT_PRICE,
                 SONNET_35_CACHE_WRITE_PRICE,
                 SONNET_35_CACHE_READ_PRICE,
             )
-        } else if model.contains("haiku-3.5") || model.contains("claude-haiku-3.5") {
+        } else if model.contains("haiku-3.5") || model.contains("3-5-haiku") {
             (
                 HAIKU_35_INPUT_PRICE,
                 HAIKU_35_OUTPUT_PRICE,
@@ -257,6 +288,26 @@ fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
     cost
 }
 
+/// Cost of token counts that did not come from a session file, such as
+/// requests logged by the local proxy
+pub fn estimate_cost(
+    model: &str,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+) -> f64 {
+    calculate_cost(
+        model,
+        &UsageData {
+            input_tokens: Some(input_tokens),
+            output_tokens: Some(output_tokens),
+            cache_creation_input_tokens: Some(cache_creation_tokens),
+            cache_read_input_tokens: Some(cache_read_tokens),
+        },
+    )
+}
+
 fn parse_jsonl_file(
     path: &PathBuf,
     encoded_project_name: &str,
@@ -387,7 +438,7 @@ fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
     None
 }
 
-fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
+pub(crate) fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
     let mut all_entries = Vec::new();
     let mut processed_hashes = HashSet::new();
     let projects_dir = claude_path.join("projects");
@@ -427,7 +478,7 @@ fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
 }
 
 #[command]
-pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
+pub fn get_usage_stats(days: Option<u32>) -> Result<Converted<UsageStats>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -435,7 +486,7 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
     let all_entries = get_all_usage_entries(&claude_path);
 
     if all_entries.is_empty() {
-        return Ok(UsageStats {
+        return Ok(Converted(UsageStats {
             total_cost: 0.0,
             total_tokens: 0,
             total_input_tokens: 0,
@@ -447,7 +498,7 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
             by_date: vec![],
             by_project: vec![],
             by_api_base_url: vec![],
-        });
+        }));
     }
 
     // Filter by days if specified
@@ -634,7 +685,7 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
     }).collect();
     by_api_base_url.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
 
-    Ok(UsageStats {
+    Ok(Converted(UsageStats {
         total_cost,
         total_tokens,
         total_input_tokens,
@@ -646,11 +697,11 @@ pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
         by_date,
         by_project,
         by_api_base_url,
-    })
+    }))
 }
 
 #[command]
-pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<UsageStats, String> {
+pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<Converted<UsageStats>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -685,7 +736,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
         .collect();
 
     if filtered_entries.is_empty() {
-        return Ok(UsageStats {
+        return Ok(Converted(UsageStats {
             total_cost: 0.0,
             total_tokens: 0,
             total_input_tokens: 0,
@@ -697,7 +748,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
             by_date: vec![],
             by_project: vec![],
             by_api_base_url: vec![],
-        });
+        }));
     }
 
     // Calculate aggregated stats (same logic as get_usage_stats)
@@ -867,7 +918,7 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
     }).collect();
     by_api_base_url.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
 
-    Ok(UsageStats {
+    Ok(Converted(UsageStats {
         total_cost,
         total_tokens,
         total_input_tokens,
@@ -879,14 +930,14 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
         by_date,
         by_project,
         by_api_base_url,
-    })
+    }))
 }
 
 #[command]
 pub fn get_usage_details(
     project_path: Option<String>,
     date: Option<String>,
-) -> Result<Vec<UsageEntry>, String> {
+) -> Result<Converted<Vec<UsageEntry>>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -903,11 +954,11 @@ pub fn get_usage_details(
         all_entries.retain(|e| e.timestamp.starts_with(&date));
     }
 
-    Ok(all_entries)
+    Ok(Converted(all_entries))
 }
 
 #[command]
-pub fn get_today_usage_stats() -> Result<UsageStats, String> {
+pub fn get_today_usage_stats() -> Result<Converted<UsageStats>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -930,7 +981,7 @@ pub fn get_today_usage_stats() -> Result<UsageStats, String> {
         .collect();
 
     if today_entries.is_empty() {
-        return Ok(UsageStats {
+        return Ok(Converted(UsageStats {
             total_cost: 0.0,
             total_tokens: 0,
             total_input_tokens: 0,
@@ -942,7 +993,7 @@ pub fn get_today_usage_stats() -> Result<UsageStats, String> {
             by_date: vec![],
             by_project: vec![],
             by_api_base_url: vec![],
-        });
+        }));
     }
 
     // Calculate aggregated stats for today
@@ -1112,7 +1163,7 @@ pub fn get_today_usage_stats() -> Result<UsageStats, String> {
     }).collect();
     by_api_base_url.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
 
-    Ok(UsageStats {
+    Ok(Converted(UsageStats {
         total_cost,
         total_tokens,
         total_input_tokens,
@@ -1124,7 +1175,7 @@ pub fn get_today_usage_stats() -> Result<UsageStats, String> {
         by_date,
         by_project,
         by_api_base_url,
-    })
+    }))
 }
 
 #[command]
@@ -1132,7 +1183,7 @@ pub fn get_session_stats(
     since: Option<String>,
     until: Option<String>,
     order: Option<String>,
-) -> Result<Vec<ProjectUsage>, String> {
+) -> Result<Converted<Vec<ProjectUsage>>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -1195,11 +1246,11 @@ pub fn get_session_stats(
         by_session.sort_by(|a, b| b.last_used.cmp(&a.last_used));
     }
 
-    Ok(by_session)
+    Ok(Converted(by_session))
 }
 
 #[command]
-pub fn get_usage_by_api_base_url() -> Result<Vec<ApiBaseUrlUsage>, String> {
+pub fn get_usage_by_api_base_url() -> Result<Converted<Vec<ApiBaseUrlUsage>>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -1207,7 +1258,7 @@ pub fn get_usage_by_api_base_url() -> Result<Vec<ApiBaseUrlUsage>, String> {
     let all_entries = get_all_usage_entries(&claude_path);
 
     if all_entries.is_empty() {
-        return Ok(vec![]);
+        return Ok(Converted(vec![]));
     }
 
     let mut api_base_url_stats: HashMap<String, ApiBaseUrlUsage> = HashMap::new();
@@ -1249,7 +1300,7 @@ pub fn get_usage_by_api_base_url() -> Result<Vec<ApiBaseUrlUsage>, String> {
     }).collect();
     by_api_base_url.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
 
-    Ok(by_api_base_url)
+    Ok(Converted(by_api_base_url))
 }
 
 #[derive(Debug, Serialize)]
@@ -1265,14 +1316,14 @@ pub struct ActiveSessionInfo {
 }
 
 #[command]
-pub fn get_active_sessions() -> Result<Vec<ActiveSessionInfo>, String> {
+pub fn get_active_sessions() -> Result<Converted<Vec<ActiveSessionInfo>>, String> {
     let claude_path = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
 
     let all_entries = get_all_usage_entries(&claude_path);
     if all_entries.is_empty() {
-        return Ok(vec![]);
+        return Ok(Converted(vec![]));
     }
 
     let session_starts = track_active_sessions(&all_entries);
@@ -1325,7 +1376,7 @@ pub fn get_active_sessions() -> Result<Vec<ActiveSessionInfo>, String> {
         }
     });
     
-    Ok(active_sessions)
+    Ok(Converted(active_sessions))
 }
 
 #[derive(Debug, Serialize)]
@@ -1431,3 +1482,226 @@ pub fn get_burn_rate_analysis() -> Result<BurnRateInfo, String> {
         recommendations,
     })
 }
+
+/// Dimension usage series are split by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGroupBy {
+    Model,
+    Project,
+    /// A single series with the daily totals
+    None,
+}
+
+/// Daily values of one model or project, aligned with `UsageSeries::dates`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageSeriesLine {
+    /// Model ID, project path, "total" or "other" for the remaining groups
+    key: String,
+    total_cost: f64,
+    total_tokens: u64,
+    cost: Vec<f64>,
+    tokens: Vec<u64>,
+    input_tokens: Vec<u64>,
+    output_tokens: Vec<u64>,
+    cache_creation_tokens: Vec<u64>,
+    cache_read_tokens: Vec<u64>,
+}
+
+/// Chart-ready daily usage: one entry per day, zero-filled, in every series
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageSeries {
+    group_by: UsageGroupBy,
+    /// Local dates (YYYY-MM-DD), oldest first
+    dates: Vec<String>,
+    /// Series ordered by total cost, largest first
+    series: Vec<UsageSeriesLine>,
+}
+
+pub(crate) fn local_date(timestamp: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|dt| dt.with_timezone(&Local).date_naive())
+}
+
+/// Bucket `entries` per local day between `start` and `end` (inclusive)
+///
+/// Groups beyond the `top` most expensive are summed into an "other" series.
+fn build_usage_series(
+    entries: &[UsageEntry],
+    group_by: UsageGroupBy,
+    start: NaiveDate,
+    end: NaiveDate,
+    top: usize,
+) -> UsageSeries {
+    let dates: Vec<NaiveDate> = start.iter_days().take_while(|date| *date <= end).collect();
+    let new_line = |key: String| UsageSeriesLine {
+        key,
+        total_cost: 0.0,
+        total_tokens: 0,
+        cost: vec![0.0; dates.len()],
+        tokens: vec![0; dates.len()],
+        input_tokens: vec![0; dates.len()],
+        output_tokens: vec![0; dates.len()],
+        cache_creation_tokens: vec![0; dates.len()],
+        cache_read_tokens: vec![0; dates.len()],
+    };
+
+    let mut lines: HashMap<String, UsageSeriesLine> = HashMap::new();
+    for entry in entries {
+        let Some(day) = local_date(&entry.timestamp)
+            .filter(|date| *date >= start && *date <= end)
+            .map(|date| (date - start).num_days() as usize)
+        else {
+            continue;
+        };
+        let key = match group_by {
+            UsageGroupBy::Model => entry.model.clone(),
+            UsageGroupBy::Project => entry.project_path.clone(),
+            UsageGroupBy::None => "total".to_string(),
+        };
+        let tokens = entry.input_tokens
+            + entry.output_tokens
+            + entry.cache_creation_tokens
+            + entry.cache_read_tokens;
+        let line = lines.entry(key.clone()).or_insert_with(|| new_line(key));
+        line.total_cost += entry.cost;
+        line.total_tokens += tokens;
+        line.cost[day] += entry.cost;
+        line.tokens[day] += tokens;
+        line.input_tokens[day] += entry.input_tokens;
+        line.output_tokens[day] += entry.output_tokens;
+        line.cache_creation_tokens[day] += entry.cache_creation_tokens;
+        line.cache_read_tokens[day] += entry.cache_read_tokens;
+    }
+
+    let mut series: Vec<UsageSeriesLine> = lines.into_values().collect();
+    series.sort_by(|a, b| {
+        b.total_cost
+            .total_cmp(&a.total_cost)
+            .then(b.total_tokens.cmp(&a.total_tokens))
+            .then(a.key.cmp(&b.key))
+    });
+    if series.len() > top.max(1) {
+        let mut other = new_line("other".to_string());
+        for line in series.split_off(top.max(1)) {
+            other.total_cost += line.total_cost;
+            other.total_tokens += line.total_tokens;
+            for day in 0..dates.len() {
+                other.cost[day] += line.cost[day];
+                other.tokens[day] += line.tokens[day];
+                other.input_tokens[day] += line.input_tokens[day];
+                other.output_tokens[day] += line.output_tokens[day];
+                other.cache_creation_tokens[day] += line.cache_creation_tokens[day];
+                other.cache_read_tokens[day] += line.cache_read_tokens[day];
+            }
+        }
+        series.push(other);
+    }
+
+    UsageSeries {
+        group_by,
+        dates: dates
+            .iter()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .collect(),
+        series,
+    }
+}
+
+/// Daily token and cost series for charts, split by model or project
+///
+/// Covers the last `days` days (default 30, at most a year) and keeps the
+/// `top` most expensive groups (default 8), summing the rest into "other".
+#[command]
+pub fn get_usage_series(
+    days: Option<u32>,
+    group_by: Option<UsageGroupBy>,
+    top: Option<usize>,
+) -> Result<Converted<UsageSeries>, String> {
+    let claude_path = dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude");
+
+    let all_entries = get_all_usage_entries(&claude_path);
+    let today = Local::now().date_naive();
+    let days = days.unwrap_or(30).clamp(1, MAX_SERIES_DAYS);
+    let start = today - Duration::days(days as i64 - 1);
+
+    Ok(Converted(build_usage_series(
+        &all_entries,
+        group_by.unwrap_or(UsageGroupBy::None),
+        start,
+        today,
+        top.unwrap_or(8),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, model: &str, output_tokens: u64, cost: f64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: model.to_string(),
+            input_tokens: 0,
+            output_tokens,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost,
+            session_id: "s".to_string(),
+            project_path: "/work/app".to_string(),
+            api_base_url: "https://api.anthropic.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_calculate_cost_model_ids() {
+        let usage = UsageData {
+            input_tokens: Some(1_000_000),
+            output_tokens: Some(0),
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: None,
+        };
+        assert_eq!(calculate_cost("claude-opus-4-5-20251101", &usage), 5.0);
+        assert_eq!(calculate_cost("claude-opus-4-1-20250805", &usage), 15.0);
+        assert_eq!(calculate_cost("claude-3-5-haiku-20241022", &usage), 0.8);
+        assert_eq!(calculate_cost("claude-haiku-4-5-20251001", &usage), 1.0);
+    }
+
+    #[test]
+    fn test_build_usage_series() {
+        let noon = |date: &str| {
+            let local = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap();
+            local.to_rfc3339()
+        };
+        let entries = vec![
+            entry(&noon("2025-03-01"), "opus", 10, 3.0),
+            entry(&noon("2025-03-03"), "sonnet", 5, 1.0),
+            entry(&noon("2025-03-03"), "opus", 1, 1.0),
+            entry(&noon("2025-03-03"), "haiku", 1, 0.5),
+            // Outside the range
+            entry(&noon("2025-02-20"), "opus", 100, 10.0),
+        ];
+        let start = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+
+        let series = build_usage_series(&entries, UsageGroupBy::Model, start, end, 2);
+        assert_eq!(series.dates, vec!["2025-03-01", "2025-03-02", "2025-03-03"]);
+        let keys: Vec<&str> = series.series.iter().map(|line| line.key.as_str()).collect();
+        assert_eq!(keys, vec!["opus", "sonnet", "other"]);
+        assert_eq!(series.series[0].cost, vec![3.0, 0.0, 1.0]);
+        assert_eq!(series.series[0].tokens, vec![10, 0, 1]);
+        assert_eq!(series.series[2].total_cost, 0.5);
+
+        let series = build_usage_series(&entries, UsageGroupBy::None, start, end, 8);
+        assert_eq!(series.series.len(), 1);
+        assert_eq!(series.series[0].total_tokens, 17);
+    }
+}
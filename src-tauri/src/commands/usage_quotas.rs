@@ -0,0 +1,492 @@
+//! Local per-model usage quotas, e.g. at most 2M Opus tokens a week
+//!
+//! Quotas are soft limits kept on this machine, independent of what relay
+//! stations enforce. Usage is read from the local Claude Code logs. An
+//! exceeded quota shows a notification and emits `usage-quota-exceeded` once
+//! per period; quotas set to block also refuse new sessions and agent runs
+//! with a matching model until the period is over.
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::cost_alerts::{from_column, period_key, period_start, to_column, AlertPeriod};
+use crate::commands::usage::UsageEntry;
+use crate::t;
+
+/// Seconds between two checks of the quotas
+const CHECK_INTERVAL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaAction {
+    /// Notify only
+    #[default]
+    Warn,
+    /// Notify and refuse new launches with the model
+    Block,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageQuota {
+    pub id: Option<i64>,
+    pub name: String,
+    /// Model name or part of it, e.g. "opus"
+    pub model: String,
+    /// Input plus output tokens allowed per period
+    pub max_tokens: u64,
+    #[serde(default)]
+    pub period: AlertPeriod,
+    #[serde(default)]
+    pub action: QuotaAction,
+    /// Count cache reads and writes too
+    #[serde(default)]
+    pub include_cache: bool,
+    pub enabled: bool,
+    /// Period the quota was last reported exceeded in
+    #[serde(default)]
+    pub last_warned: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+impl UsageQuota {
+    /// Whether the quota covers a model, as logged or as passed to `--model`
+    ///
+    /// Either side may be the shorter one: a quota on `opus` covers
+    /// `claude-opus-4-1`, and one on `claude-opus-4-1` covers launches with
+    /// the `opus` alias, which may resolve to it.
+    pub fn applies_to(&self, model: &str) -> bool {
+        let model = normalize_model(model);
+        let quota = normalize_model(&self.model);
+        !model.is_empty() && !quota.is_empty() && (model.contains(&quota) || quota.contains(&model))
+    }
+
+    fn entry_tokens(&self, entry: &UsageEntry) -> u64 {
+        let mut tokens = entry.input_tokens + entry.output_tokens;
+        if self.include_cache {
+            tokens += entry.cache_creation_tokens + entry.cache_read_tokens;
+        }
+        tokens
+    }
+}
+
+/// Lowercase model name without the context-window suffix, e.g. `sonnet[1m]`
+fn normalize_model(model: &str) -> String {
+    let model = model.trim().to_lowercase();
+    match model.find('[') {
+        Some(index) => model[..index].trim().to_string(),
+        None => model,
+    }
+}
+
+/// A quota with its usage in the current period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageQuotaStatus {
+    pub quota: UsageQuota,
+    pub used_tokens: u64,
+    pub percent: f64,
+    pub exceeded: bool,
+    /// When the current period ends and the quota resets
+    pub resets_at: String,
+}
+
+pub fn create_usage_quotas_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_quotas (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            model TEXT NOT NULL,
+            max_tokens INTEGER NOT NULL,
+            period TEXT NOT NULL,
+            action TEXT NOT NULL,
+            include_cache INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            last_warned TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const QUOTA_COLUMNS: &str =
+    "id, name, model, max_tokens, period, action, include_cache, enabled, last_warned, created_at";
+
+fn read_quota(row: &rusqlite::Row) -> rusqlite::Result<UsageQuota> {
+    Ok(UsageQuota {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        model: row.get(2)?,
+        max_tokens: row.get::<_, i64>(3)?.max(0) as u64,
+        period: from_column(row.get(4)?),
+        action: from_column(row.get(5)?),
+        include_cache: row.get(6)?,
+        enabled: row.get(7)?,
+        last_warned: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+pub(crate) fn load_quotas(conn: &Connection) -> Result<Vec<UsageQuota>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM usage_quotas ORDER BY id",
+            QUOTA_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let quotas = stmt
+        .query_map([], read_quota)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(quotas)
+}
+
+fn load_quota(conn: &Connection, id: i64) -> Result<UsageQuota, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM usage_quotas WHERE id = ?1", QUOTA_COLUMNS),
+        params![id],
+        read_quota,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Usage quota {} not found", id))
+}
+
+fn validate_quota(quota: &UsageQuota) -> Result<(), String> {
+    if quota.name.trim().is_empty() {
+        return Err("The quota needs a name".to_string());
+    }
+    if quota.model.trim().is_empty() {
+        return Err("The quota needs a model".to_string());
+    }
+    if quota.max_tokens == 0 || quota.max_tokens > i64::MAX as u64 {
+        return Err("The token limit must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+/// Local end of the period `now` falls in
+fn period_end(period: AlertPeriod, now: DateTime<Local>) -> DateTime<Local> {
+    let start = period_start(period, now);
+    let next = match period {
+        AlertPeriod::Day => start + chrono::Duration::days(1),
+        AlertPeriod::Week => start + chrono::Duration::days(7),
+        AlertPeriod::Month => start
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(start + chrono::Duration::days(31)),
+    };
+    period_start(period, next)
+}
+
+/// Tokens a quota counts among the entries made since `since`
+pub fn used_tokens(quota: &UsageQuota, entries: &[UsageEntry], since: DateTime<Local>) -> u64 {
+    let since = since.with_timezone(&Utc);
+    entries
+        .iter()
+        .filter(|entry| quota.applies_to(&entry.model))
+        .filter(|entry| {
+            DateTime::parse_from_rfc3339(&entry.timestamp)
+                .is_ok_and(|time| time.with_timezone(&Utc) >= since)
+        })
+        .map(|entry| quota.entry_tokens(entry))
+        .sum()
+}
+
+pub fn statuses(
+    quotas: Vec<UsageQuota>,
+    entries: &[UsageEntry],
+    now: DateTime<Local>,
+) -> Vec<UsageQuotaStatus> {
+    quotas
+        .into_iter()
+        .map(|quota| {
+            let used_tokens = used_tokens(&quota, entries, period_start(quota.period, now));
+            UsageQuotaStatus {
+                percent: used_tokens as f64 * 100.0 / quota.max_tokens.max(1) as f64,
+                exceeded: quota.enabled && used_tokens >= quota.max_tokens,
+                resets_at: period_end(quota.period, now).to_rfc3339(),
+                used_tokens,
+                quota,
+            }
+        })
+        .collect()
+}
+
+fn local_entries() -> Vec<UsageEntry> {
+    match dirs::home_dir() {
+        Some(home) => crate::commands::usage::get_all_usage_entries(&home.join(".claude")),
+        None => Vec::new(),
+    }
+}
+
+/// Quotas with their usage; reads the usage logs, so call it off the async
+/// runtime
+fn evaluate(app: &AppHandle) -> Result<Vec<UsageQuotaStatus>, String> {
+    let quotas = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_quotas(&conn)?
+    };
+    if quotas.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(statuses(quotas, &local_entries(), Local::now()))
+}
+
+fn period_name(period: AlertPeriod) -> String {
+    match period {
+        AlertPeriod::Day => t!("cost_alerts.period_day"),
+        AlertPeriod::Week => t!("cost_alerts.period_week"),
+        AlertPeriod::Month => t!("cost_alerts.period_month"),
+    }
+}
+
+/// Report every quota exceeded for the first time in its period
+fn check_quotas(app: &AppHandle) -> Result<(), String> {
+    let now = Local::now();
+    for status in evaluate(app)? {
+        let key = period_key(status.quota.period, now);
+        if !status.exceeded || status.quota.last_warned.as_deref() == Some(key.as_str()) {
+            continue;
+        }
+        {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.execute(
+                "UPDATE usage_quotas SET last_warned = ?1 WHERE id = ?2",
+                params![key, status.quota.id],
+            )
+            .map_err(|e| format!("Failed to update usage quota: {}", e))?;
+        }
+        log::info!(
+            "Usage quota '{}' exceeded: {} >= {} tokens",
+            status.quota.name,
+            status.used_tokens,
+            status.quota.max_tokens
+        );
+
+        let title = t!("usage_quotas.title", "name" => &status.quota.name);
+        let body = t!(
+            "usage_quotas.exceeded_body",
+            "used" => &status.used_tokens.to_string(),
+            "limit" => &status.quota.max_tokens.to_string(),
+            "model" => &status.quota.model,
+            "period" => &period_name(status.quota.period)
+        );
+        if let Err(e) = crate::commands::notifications::show(app, &title, &body) {
+            log::warn!("{}", e);
+        }
+        let _ = app.emit("usage-quota-exceeded", &status);
+    }
+    Ok(())
+}
+
+/// Check the quotas every few minutes; called once from the app setup
+pub fn start_usage_quota_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+            let check_app = app.clone();
+            match tokio::task::spawn_blocking(move || check_quotas(&check_app)).await {
+                Ok(Err(e)) => log::warn!("Failed to check usage quotas: {}", e),
+                Err(e) => log::warn!("Usage quota check panicked: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+/// Refuse a launch with `model` while a blocking quota covering it is used up
+pub async fn ensure_launch_allowed(app: &AppHandle, model: &str) -> Result<(), String> {
+    let blocking: Vec<UsageQuota> = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_quotas(&conn)?
+            .into_iter()
+            .filter(|quota| {
+                quota.enabled && quota.action == QuotaAction::Block && quota.applies_to(model)
+            })
+            .collect()
+    };
+    // Nothing to check without blocking quotas; spares reading the logs
+    if blocking.is_empty() {
+        return Ok(());
+    }
+    let exceeded = tokio::task::spawn_blocking(move || {
+        statuses(blocking, &local_entries(), Local::now())
+            .into_iter()
+            .find(|status| status.exceeded)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    match exceeded {
+        Some(status) => Err(t!(
+            "usage_quotas.launch_blocked",
+            "name" => &status.quota.name,
+            "model" => model,
+            "used" => &status.used_tokens.to_string(),
+            "limit" => &status.quota.max_tokens.to_string(),
+            "resets_at" => &status.resets_at
+        )),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn list_usage_quotas(db: State<'_, AgentDb>) -> Result<Vec<UsageQuota>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_quotas(&conn)
+}
+
+#[tauri::command]
+pub async fn create_usage_quota(
+    db: State<'_, AgentDb>,
+    quota: UsageQuota,
+) -> Result<UsageQuota, String> {
+    validate_quota(&quota)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO usage_quotas (name, model, max_tokens, period, action, include_cache, enabled, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            quota.name.trim(),
+            quota.model.trim(),
+            quota.max_tokens as i64,
+            to_column(&quota.period),
+            to_column(&quota.action),
+            quota.include_cache,
+            quota.enabled,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(|e| format!("Failed to create usage quota: {}", e))?;
+    load_quota(&conn, conn.last_insert_rowid())
+}
+
+/// Update a quota; a changed quota may be reported again in the current period
+#[tauri::command]
+pub async fn update_usage_quota(
+    db: State<'_, AgentDb>,
+    quota: UsageQuota,
+) -> Result<UsageQuota, String> {
+    let id = quota.id.ok_or("Quota ID is required")?;
+    validate_quota(&quota)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE usage_quotas SET name = ?1, model = ?2, max_tokens = ?3, period = ?4,
+             action = ?5, include_cache = ?6, enabled = ?7, last_warned = NULL WHERE id = ?8",
+            params![
+                quota.name.trim(),
+                quota.model.trim(),
+                quota.max_tokens as i64,
+                to_column(&quota.period),
+                to_column(&quota.action),
+                quota.include_cache,
+                quota.enabled,
+                id,
+            ],
+        )
+        .map_err(|e| format!("Failed to update usage quota: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Usage quota {} not found", id));
+    }
+    load_quota(&conn, id)
+}
+
+#[tauri::command]
+pub async fn delete_usage_quota(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM usage_quotas WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete usage quota: {}", e))?;
+    Ok(())
+}
+
+/// Every quota with its usage in the current period, without notifying
+#[tauri::command]
+pub async fn evaluate_usage_quotas(app: AppHandle) -> Result<Vec<UsageQuotaStatus>, String> {
+    tokio::task::spawn_blocking(move || evaluate(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Duration, TimeZone};
+
+    fn entry(timestamp: &str, model: &str, input: u64, output: u64, cache: u64) -> UsageEntry {
+        UsageEntry {
+            timestamp: timestamp.to_string(),
+            model: model.to_string(),
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_tokens: 0,
+            cache_read_tokens: cache,
+            cost: 0.0,
+            session_id: String::new(),
+            project_path: String::new(),
+            api_base_url: String::new(),
+        }
+    }
+
+    fn quota(max_tokens: u64, include_cache: bool) -> UsageQuota {
+        UsageQuota {
+            id: None,
+            name: "Opus".to_string(),
+            model: "Opus".to_string(),
+            max_tokens,
+            period: AlertPeriod::Week,
+            action: QuotaAction::Block,
+            include_cache,
+            enabled: true,
+            last_warned: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_used_tokens_and_statuses() {
+        // A Friday; the week started on Monday the 12th
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 15, 0, 0).unwrap();
+        let week = period_start(AlertPeriod::Week, now);
+        let at = |hours: i64| (week + Duration::hours(hours)).to_rfc3339();
+        let entries = vec![
+            entry(&at(-1), "claude-opus-4-1", 5_000, 5_000, 0),
+            entry(&at(1), "claude-opus-4-1", 1_000, 500, 10_000),
+            entry(&at(30), "claude-opus-4-1", 2_000, 1_500, 0),
+            entry(&at(31), "claude-sonnet-4-5", 9_000, 9_000, 0),
+        ];
+
+        assert_eq!(used_tokens(&quota(0, false), &entries, week), 5_000);
+        assert_eq!(used_tokens(&quota(0, true), &entries, week), 15_000);
+
+        let checked = statuses(
+            vec![quota(5_000, false), quota(20_000, true)],
+            &entries,
+            now,
+        );
+        assert!(checked[0].exceeded);
+        assert_eq!(checked[0].percent, 100.0);
+        assert!(!checked[1].exceeded);
+        let resets_at = DateTime::parse_from_rfc3339(&checked[0].resets_at).unwrap();
+        assert_eq!(resets_at.day(), 19);
+
+        // Launch models are often aliases
+        assert!(quota(1, false).applies_to("opus"));
+        assert!(!quota(1, false).applies_to("sonnet"));
+        let pinned = UsageQuota {
+            model: "claude-opus-4-1".to_string(),
+            ..quota(1, false)
+        };
+        assert!(pinned.applies_to("opus"));
+        assert!(pinned.applies_to("claude-opus-4-1-20250805"));
+        assert!(!pinned.applies_to("claude-sonnet-4-5"));
+        assert!(!pinned.applies_to(""));
+        assert!(quota(1, false).applies_to("Opus[1m]"));
+        assert!(validate_quota(&quota(0, false)).is_err());
+    }
+}
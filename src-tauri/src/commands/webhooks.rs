@@ -0,0 +1,584 @@
+//! Outbound notifications to chat webhooks (Slack, Discord, Feishu, Telegram
+//! or any endpoint taking JSON)
+//!
+//! Targets and the events each one receives are kept in `app_settings`;
+//! messages are delivered in the background with retries, so a slow or
+//! failing webhook never holds up the code that raised the event.
+use crate::commands::agents::AgentDb;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// app_settings key storing the webhook settings (JSON)
+pub const WEBHOOK_SETTINGS_KEY: &str = "webhook_settings";
+
+const DELIVERY_ATTEMPTS: u32 = 3;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discord rejects longer messages; the others are kept to the same size
+const MAX_MESSAGE_CHARS: usize = 1900;
+
+/// How often the active station is checked for `station_down`
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Day the budget alert last fired, so it fires once per day
+static BUDGET_ALERTED_ON: Mutex<Option<NaiveDate>> = Mutex::new(None);
+
+/// Station last reported down, so an outage is reported once
+static STATION_DOWN: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookKind {
+    Slack,
+    Discord,
+    Feishu,
+    Telegram,
+    /// Posts `{event, title, text}` as JSON
+    Generic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    SessionFinished,
+    SessionFailed,
+    BudgetExceeded,
+    StationDown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookTarget {
+    /// Generated when the target is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub kind: WebhookKind,
+    /// Incoming webhook URL; unused for Telegram
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub bot_token: Option<String>,
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// Events routed to this target
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WebhookSettings {
+    pub targets: Vec<WebhookTarget>,
+    /// Daily cost in USD above which `budget_exceeded` is sent
+    pub daily_budget_usd: Option<f64>,
+}
+
+/// A message before it is shaped for a particular service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookMessage {
+    pub event: WebhookEvent,
+    pub title: String,
+    pub text: String,
+}
+
+/// Result of a test delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub target_id: String,
+    pub success: bool,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+fn validate_target(target: &WebhookTarget) -> Result<(), String> {
+    if target.name.trim().is_empty() {
+        return Err("Webhook name is required".to_string());
+    }
+    let filled = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.trim().is_empty());
+    match target.kind {
+        WebhookKind::Telegram => {
+            if !filled(&target.bot_token) || !filled(&target.chat_id) {
+                return Err(format!(
+                    "Telegram target '{}' needs a bot token and a chat ID",
+                    target.name
+                ));
+            }
+        }
+        _ => {
+            let url = target.url.trim();
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(format!(
+                    "Webhook target '{}' needs an http(s) URL",
+                    target.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// URL and JSON body a target expects for `message`
+fn build_request(target: &WebhookTarget, message: &WebhookMessage) -> (String, Value) {
+    let text = truncate(&message.text, MAX_MESSAGE_CHARS);
+    match target.kind {
+        WebhookKind::Slack => (
+            target.url.clone(),
+            json!({ "text": format!("*{}*\n{}", message.title, text) }),
+        ),
+        WebhookKind::Discord => (
+            target.url.clone(),
+            json!({ "content": format!("**{}**\n{}", message.title, text) }),
+        ),
+        WebhookKind::Feishu => (
+            target.url.clone(),
+            json!({
+                "msg_type": "text",
+                "content": { "text": format!("{}\n{}", message.title, text) },
+            }),
+        ),
+        WebhookKind::Telegram => (
+            format!(
+                "https://api.telegram.org/bot{}/sendMessage",
+                target.bot_token.as_deref().unwrap_or_default().trim()
+            ),
+            json!({
+                "chat_id": target.chat_id.as_deref().unwrap_or_default().trim(),
+                "text": format!("{}\n{}", message.title, text),
+                "disable_web_page_preview": true,
+            }),
+        ),
+        WebhookKind::Generic => (
+            target.url.clone(),
+            json!({ "event": message.event, "title": message.title, "text": text }),
+        ),
+    }
+}
+
+/// Error reported in a 200 response body (Feishu answers `{"code": 19001, ...}`)
+fn body_error(kind: WebhookKind, body: &str) -> Option<String> {
+    let body: Value = serde_json::from_str(body).ok()?;
+    match kind {
+        WebhookKind::Feishu => body["code"]
+            .as_i64()
+            .filter(|code| *code != 0)
+            .map(|code| format!("Feishu error {}: {}", code, body["msg"])),
+        WebhookKind::Telegram => {
+            (body["ok"] == false).then(|| format!("Telegram error: {}", body["description"]))
+        }
+        _ => None,
+    }
+}
+
+/// `text` without the target's credentials; Telegram puts the bot token in the URL
+fn scrub(target: &WebhookTarget, text: &str) -> String {
+    let mut text = crate::redact::redact(text).into_owned();
+    if let Some(token) = target
+        .bot_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+    {
+        text = text.replace(token, "***");
+    }
+    if !target.url.trim().is_empty() {
+        text = text.replace(target.url.trim(), "<webhook url>");
+    }
+    text
+}
+
+/// Post `message` to a target, retrying network errors, 429 and 5xx responses
+async fn deliver(
+    client: &reqwest::Client,
+    target: &WebhookTarget,
+    message: &WebhookMessage,
+) -> WebhookDelivery {
+    let (url, body) = build_request(target, message);
+    let mut error = None;
+    let mut attempts = 0;
+    while attempts < DELIVERY_ATTEMPTS {
+        if attempts > 0 {
+            tokio::time::sleep(Duration::from_secs(1 << (attempts - 1))).await;
+        }
+        attempts += 1;
+
+        let retry = match client.post(&url).json(&body).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                if status.is_success() {
+                    error = body_error(target.kind, &text).map(|error| scrub(target, &error));
+                    if error.is_none() {
+                        break;
+                    }
+                    false
+                } else {
+                    error = Some(format!(
+                        "HTTP {}: {}",
+                        status,
+                        truncate(&scrub(target, &text), 200)
+                    ));
+                    status.is_server_error() || status.as_u16() == 429
+                }
+            }
+            Err(e) => {
+                error = Some(scrub(target, &e.without_url().to_string()));
+                true
+            }
+        };
+        if !retry {
+            break;
+        }
+    }
+    WebhookDelivery {
+        target_id: target.id.clone(),
+        success: error.is_none(),
+        attempts,
+        error,
+    }
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+pub fn load_webhook_settings(conn: &rusqlite::Connection) -> WebhookSettings {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        rusqlite::params![WEBHOOK_SETTINGS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+fn settings_of(app: &AppHandle) -> Option<WebhookSettings> {
+    let db = app.try_state::<AgentDb>()?;
+    let conn = db.0.lock().ok()?;
+    Some(load_webhook_settings(&conn))
+}
+
+/// Send `message` to every enabled target routed its event, in the background
+///
+/// Failed deliveries are logged and emitted as `webhook-delivery-failed`.
+pub fn dispatch(app: &AppHandle, message: WebhookMessage) {
+    if let Some(settings) = settings_of(app) {
+        dispatch_to(app, &settings, message);
+    }
+}
+
+fn dispatch_to(app: &AppHandle, settings: &WebhookSettings, message: WebhookMessage) {
+    let targets: Vec<WebhookTarget> = settings
+        .targets
+        .iter()
+        .filter(|target| target.enabled && target.events.contains(&message.event))
+        .cloned()
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = match client() {
+            Ok(client) => client,
+            Err(e) => {
+                log::warn!("{}", e);
+                return;
+            }
+        };
+        for target in &targets {
+            let mut delivery = deliver(&client, target, &message).await;
+            if let Some(error) = delivery.error.take() {
+                // Errors quote what the target answered, which may echo credentials
+                let error = crate::redact::redact(&error).into_owned();
+                log::warn!(
+                    "Webhook '{}' failed after {} attempts: {}",
+                    target.name,
+                    delivery.attempts,
+                    error
+                );
+                delivery.error = Some(error);
+                let _ = app.emit("webhook-delivery-failed", &delivery);
+            }
+        }
+    });
+}
+
+/// Today's cost from the usage logs, in local time
+fn today_cost() -> f64 {
+    let Some(home) = dirs::home_dir() else {
+        return 0.0;
+    };
+    let today = Local::now().date_naive();
+    super::usage::get_all_usage_entries(&home.join(".claude"))
+        .iter()
+        .filter(|entry| {
+            DateTime::parse_from_rfc3339(&entry.timestamp)
+                .is_ok_and(|time| time.with_timezone(&Local).date_naive() == today)
+        })
+        .map(|entry| entry.cost)
+        .sum()
+}
+
+/// Report a finished session, then the daily budget if this run exceeded it
+pub fn notify_session_finished(
+    app: &AppHandle,
+    success: bool,
+    project_path: &str,
+    session_id: Option<&str>,
+    elapsed: Duration,
+) {
+    let Some(settings) = settings_of(app) else {
+        return;
+    };
+    let (event, title) = if success {
+        (WebhookEvent::SessionFinished, "Claude session finished")
+    } else {
+        (WebhookEvent::SessionFailed, "Claude session failed")
+    };
+    let secs = elapsed.as_secs();
+    dispatch_to(
+        app,
+        &settings,
+        WebhookMessage {
+            event,
+            title: title.to_string(),
+            text: format!(
+                "Project: {}\nSession: {}\nDuration: {}m {}s",
+                project_path,
+                session_id.unwrap_or("-"),
+                secs / 60,
+                secs % 60
+            ),
+        },
+    );
+
+    let Some(budget) = settings.daily_budget_usd else {
+        return;
+    };
+    let today = Local::now().date_naive();
+    if BUDGET_ALERTED_ON
+        .lock()
+        .is_ok_and(|day| *day == Some(today))
+    {
+        return;
+    }
+    let app = app.clone();
+    // Reading the usage logs takes a while on large histories
+    tauri::async_runtime::spawn_blocking(move || {
+        let cost = today_cost();
+        if cost <= budget {
+            return;
+        }
+        if let Ok(mut day) = BUDGET_ALERTED_ON.lock() {
+            if *day == Some(today) {
+                return;
+            }
+            *day = Some(today);
+        }
+        dispatch(
+            &app,
+            WebhookMessage {
+                event: WebhookEvent::BudgetExceeded,
+                title: "Daily budget exceeded".to_string(),
+                text: format!("Spent ${:.2} today, over the ${:.2} budget", cost, budget),
+            },
+        );
+    });
+}
+
+/// Test the station the current config points at, reporting `station_down`
+/// when it stops answering and again only after it has recovered
+async fn check_station_health(app: &AppHandle) -> Result<(), String> {
+    let routed = settings_of(app).is_some_and(|settings| {
+        settings
+            .targets
+            .iter()
+            .any(|target| target.enabled && target.events.contains(&WebhookEvent::StationDown))
+    });
+    if !routed || super::relay_offline::is_offline(app) {
+        return Ok(());
+    }
+    let Some(station) = super::dashboard::active_station(app)? else {
+        return Ok(());
+    };
+    let adapter = super::relay_stations::create_station_adapter(app, &station);
+    let failure = match adapter.test_connection(&station).await {
+        Ok(test) if test.success => None,
+        Ok(test) => Some(test.message),
+        Err(e) => Some(super::relay_adapters::hints::with_hint(&e)),
+    };
+
+    let mut down = STATION_DOWN.lock().map_err(|e| e.to_string())?;
+    match failure {
+        None => *down = None,
+        Some(_) if down.as_deref() == Some(station.id.as_str()) => {}
+        Some(reason) => {
+            *down = Some(station.id.clone());
+            drop(down);
+            dispatch(
+                app,
+                WebhookMessage {
+                    event: WebhookEvent::StationDown,
+                    title: format!("Relay station {} is down", station.name),
+                    text: crate::redact::redact(&format!("{}\n{}", station.api_url, reason))
+                        .into_owned(),
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check the active station every few minutes; called once from the app setup
+pub fn start_station_health_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            if let Err(e) = check_station_health(&app).await {
+                log::warn!("Failed to check station health: {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_webhook_settings(db: State<'_, AgentDb>) -> Result<WebhookSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_webhook_settings(&conn))
+}
+
+/// Save the webhook targets and budget; new targets get an ID
+#[tauri::command]
+pub async fn set_webhook_settings(
+    db: State<'_, AgentDb>,
+    mut settings: WebhookSettings,
+) -> Result<WebhookSettings, String> {
+    for target in &mut settings.targets {
+        validate_target(target)?;
+        if target.id.is_empty() {
+            target.id = uuid::Uuid::new_v4().to_string();
+        }
+    }
+    if settings
+        .daily_budget_usd
+        .is_some_and(|budget| budget <= 0.0)
+    {
+        return Err("Daily budget must be greater than 0".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![WEBHOOK_SETTINGS_KEY, value],
+    )
+    .map_err(|e| format!("Failed to save webhook settings: {}", e))?;
+    Ok(settings)
+}
+
+/// Send a test message to a (possibly unsaved) target and report the outcome
+#[tauri::command]
+pub async fn test_webhook_target(target: WebhookTarget) -> Result<WebhookDelivery, String> {
+    validate_target(&target)?;
+    let message = WebhookMessage {
+        event: WebhookEvent::SessionFinished,
+        title: "Claude Workbench test message".to_string(),
+        text: format!("Webhook '{}' is set up correctly", target.name),
+    };
+    Ok(deliver(&client()?, &target, &message).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(kind: WebhookKind) -> WebhookTarget {
+        WebhookTarget {
+            id: String::new(),
+            name: "team".to_string(),
+            kind,
+            url: "https://hooks.example.com/T1".to_string(),
+            bot_token: None,
+            chat_id: None,
+            events: vec![WebhookEvent::SessionFinished],
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_build_and_validate_requests() {
+        let message = WebhookMessage {
+            event: WebhookEvent::StationDown,
+            title: "Station down".to_string(),
+            text: "relay-1 is unreachable".to_string(),
+        };
+
+        let (url, body) = build_request(&target(WebhookKind::Slack), &message);
+        assert_eq!(url, "https://hooks.example.com/T1");
+        assert_eq!(body["text"], "*Station down*\nrelay-1 is unreachable");
+        let (_, body) = build_request(&target(WebhookKind::Feishu), &message);
+        assert_eq!(body["msg_type"], "text");
+        let (_, body) = build_request(&target(WebhookKind::Generic), &message);
+        assert_eq!(body["event"], "station_down");
+
+        let mut telegram = target(WebhookKind::Telegram);
+        assert!(validate_target(&telegram).is_err());
+        telegram.bot_token = Some("123:abc".to_string());
+        telegram.chat_id = Some("-100".to_string());
+        assert!(validate_target(&telegram).is_ok());
+        let (url, body) = build_request(&telegram, &message);
+        assert_eq!(url, "https://api.telegram.org/bot123:abc/sendMessage");
+        assert_eq!(body["chat_id"], "-100");
+
+        let mut slack = target(WebhookKind::Slack);
+        slack.url = "hooks.example.com".to_string();
+        assert!(validate_target(&slack).is_err());
+
+        assert!(body_error(WebhookKind::Feishu, r#"{"code":0}"#).is_none());
+        assert!(body_error(WebhookKind::Feishu, r#"{"code":19001,"msg":"x"}"#).is_some());
+        assert_eq!(truncate("abcdef", 3), "abc…");
+    }
+
+    #[test]
+    fn test_scrub_errors() {
+        let mut telegram = target(WebhookKind::Telegram);
+        telegram.url = String::new();
+        telegram.bot_token = Some("123456:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw".to_string());
+        assert_eq!(
+            scrub(
+                &telegram,
+                "Telegram error for /bot123456:AAHdqTcvCH1vGWJxfSeofSAs0K5PALDsaw/sendMessage"
+            ),
+            "Telegram error for /bot***/sendMessage"
+        );
+        assert_eq!(
+            scrub(
+                &target(WebhookKind::Slack),
+                "404 from https://hooks.example.com/T1"
+            ),
+            "404 from <webhook url>"
+        );
+    }
+}
@@ -0,0 +1,555 @@
+//! `claude-workbench://` links, so web pages and docs can apply a relay
+//! station, open a project or share station settings with one click
+//!
+//! Links reach the app in three ways: as a command line argument when the OS
+//! starts it, as `RunEvent::Opened` on macOS, and forwarded over a loopback
+//! socket by a second instance the OS started while the app was running. The
+//! socket only takes links sent with the per-launch secret from the port file.
+//!
+//! Any web page can open a link, so links that change stations are not carried
+//! out directly: they are emitted as `deep-link-confirm` and wait for
+//! `confirm_deep_link`.
+use crate::commands::relay_stations::{RelayStationExport, RelayStationManager};
+use crate::t;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const SCHEME: &str = "claude-workbench";
+
+/// File in the data directory holding the port the running instance listens
+/// on for forwarded links and the secret they have to be sent with
+const PORT_FILE: &str = "deep-link.port";
+
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLink {
+    /// `claude-workbench://apply-station/<id>`
+    ApplyStation { station_id: String },
+    /// `claude-workbench://open-project?path=<path>`
+    OpenProject { path: String },
+    /// `claude-workbench://import-station?payload=<base64url JSON or encrypted share>`
+    ImportStation { payload: String },
+}
+
+/// Outcome of a link, emitted to the frontend as `deep-link-handled`
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkResult {
+    pub url: String,
+    pub success: bool,
+    pub message: String,
+}
+
+impl DeepLink {
+    /// Link action, logged instead of the link, which may carry tokens
+    pub fn action(&self) -> &'static str {
+        match self {
+            DeepLink::ApplyStation { .. } => "apply-station",
+            DeepLink::OpenProject { .. } => "open-project",
+            DeepLink::ImportStation { .. } => "import-station",
+        }
+    }
+}
+
+/// A link waiting for the user, emitted as `deep-link-confirm`
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkConfirmation {
+    pub id: String,
+    pub action: &'static str,
+    /// Station to apply, or the stations to import
+    pub stations: Vec<String>,
+}
+
+/// Project opened by a link before the frontend was listening, and links
+/// waiting for confirmation
+#[derive(Default)]
+pub struct DeepLinkState {
+    pending_project: Mutex<Option<String>>,
+    pending_confirmations: Mutex<HashMap<String, (DeepLink, DeepLinkConfirmation)>>,
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| {
+            urlencoding::decode(value)
+                .map(|value| value.into_owned())
+                .unwrap_or_else(|_| value.to_string())
+        })
+    })
+}
+
+pub fn parse(url: &str) -> Result<DeepLink, String> {
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .ok_or_else(|| format!("Not a {} link: {}", SCHEME, url))?;
+    let rest = rest.trim_start_matches('/');
+    let (route, query) = rest.split_once('?').unwrap_or((rest, ""));
+    // Browsers may add a trailing slash to links without a path
+    let route = route.trim_end_matches('/');
+    let (action, argument) = route.split_once('/').unwrap_or((route, ""));
+
+    let required = |name: &str| {
+        query_param(query, name)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("Link is missing the `{}` parameter: {}", name, url))
+    };
+    match action {
+        "apply-station" if !argument.is_empty() => Ok(DeepLink::ApplyStation {
+            station_id: urlencoding::decode(argument)
+                .map_err(|e| format!("Invalid station id in {}: {}", url, e))?
+                .into_owned(),
+        }),
+        "open-project" => Ok(DeepLink::OpenProject {
+            path: required("path")?,
+        }),
+        "import-station" => Ok(DeepLink::ImportStation {
+            payload: required("payload")?,
+        }),
+        _ => Err(format!("Unsupported link: {}", url)),
+    }
+}
+
+/// Station export carried by an `import-station` link: base64url (the
+/// padding optional) or plain JSON
+pub fn decode_station_payload(payload: &str) -> Result<RelayStationExport, String> {
+    let json = if payload.trim_start().starts_with('{') {
+        payload.as_bytes().to_vec()
+    } else {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .or_else(|_| base64::engine::general_purpose::STANDARD.decode(payload))
+            .map_err(|e| format!("Invalid station payload: {}", e))?
+    };
+    serde_json::from_slice(&json).map_err(|e| format!("Invalid station payload: {}", e))
+}
+
+/// Links among the command line arguments
+pub fn links_in_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let prefix = format!("{}:", SCHEME);
+    args.into_iter()
+        .filter(|arg| arg.starts_with(&prefix))
+        .collect()
+}
+
+fn import_stations(app: &AppHandle, payload: &str) -> Result<String, String> {
+    // Encrypted shares need the passphrase, which the frontend asks for
+    if crate::commands::relay_share::is_share(payload) {
+        let _ = app.emit("station-share-received", payload);
+        return Ok(t!("deep_link.share_needs_passphrase"));
+    }
+    let export = decode_station_payload(payload)?;
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let manager_lock = state
+        .lock()
+        .map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
+    let manager = manager_lock
+        .as_ref()
+        .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+    // Never overwrite existing stations from a link
+    let imported = manager
+        .import_stations(&export, false)
+        .map_err(|e| t!("relay.failed_to_import_stations", "error" => &e.to_string()))?;
+    drop(manager_lock);
+    crate::tray::refresh_menu(app);
+    let _ = app.emit("relay-stations-changed", &imported);
+    Ok(t!("deep_link.stations_imported", "count" => &imported.len().to_string()))
+}
+
+/// What the user is asked to confirm, or None for links carried out directly
+fn confirmation_for(
+    app: &AppHandle,
+    link: &DeepLink,
+) -> Result<Option<DeepLinkConfirmation>, String> {
+    let stations = match link {
+        DeepLink::ApplyStation { station_id } => {
+            let state = app.state::<Mutex<Option<RelayStationManager>>>();
+            let manager_lock = state
+                .lock()
+                .map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
+            let manager = manager_lock
+                .as_ref()
+                .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+            let station = manager
+                .get_station(station_id)
+                .map_err(|e| t!("relay.failed_to_get_station", "error" => &e.to_string()))?
+                .ok_or_else(|| t!("relay.station_not_found"))?;
+            vec![station.name]
+        }
+        // Encrypted shares already wait for the passphrase
+        DeepLink::ImportStation { payload } if !crate::commands::relay_share::is_share(payload) => {
+            decode_station_payload(payload)?
+                .stations
+                .into_iter()
+                .map(|station| station.name)
+                .collect()
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(DeepLinkConfirmation {
+        id: uuid::Uuid::new_v4().to_string(),
+        action: link.action(),
+        stations,
+    }))
+}
+
+async fn run(app: &AppHandle, link: DeepLink) -> Result<String, String> {
+    match link {
+        DeepLink::ApplyStation { station_id } => {
+            let message = crate::tray::switch_station(app, &station_id).await?;
+            crate::tray::refresh_menu(app);
+            let _ = app.emit("provider-changed", &station_id);
+            Ok(message)
+        }
+        DeepLink::OpenProject { path } => {
+            if !Path::new(&path).is_dir() {
+                return Err(t!("deep_link.project_not_found", "path" => &path));
+            }
+            if let Some(state) = app.try_state::<DeepLinkState>() {
+                if let Ok(mut pending) = state.pending_project.lock() {
+                    *pending = Some(path.clone());
+                }
+            }
+            let _ = app.emit("deep-link-open-project", &path);
+            Ok(t!("deep_link.project_opened", "path" => &path))
+        }
+        DeepLink::ImportStation { payload } => import_stations(app, &payload),
+    }
+}
+
+fn emit_result(app: &AppHandle, url: String, action: &str, outcome: Result<String, String>) {
+    let result = match outcome {
+        Ok(message) => DeepLinkResult {
+            url,
+            success: true,
+            message,
+        },
+        Err(message) => {
+            log::warn!("Deep link {} failed", action);
+            DeepLinkResult {
+                url,
+                success: false,
+                message,
+            }
+        }
+    };
+    let _ = app.emit("deep-link-handled", &result);
+}
+
+/// Bring the window up and carry out a link in the background, or ask the
+/// frontend to confirm it first
+pub fn handle(app: &AppHandle, url: &str) {
+    crate::tray::show_main_window(app);
+    let link = match parse(url) {
+        Ok(link) => link,
+        Err(e) => {
+            log::warn!("Ignoring an unsupported deep link");
+            emit_result(app, url.to_string(), "unsupported", Err(e));
+            return;
+        }
+    };
+    log::info!("Handling deep link {}", link.action());
+    match confirmation_for(app, &link) {
+        Ok(Some(confirmation)) => {
+            if let Some(state) = app.try_state::<DeepLinkState>() {
+                if let Ok(mut pending) = state.pending_confirmations.lock() {
+                    pending.insert(confirmation.id.clone(), (link, confirmation.clone()));
+                }
+            }
+            let _ = app.emit("deep-link-confirm", &confirmation);
+        }
+        Ok(None) => {
+            let app = app.clone();
+            let url = url.to_string();
+            tauri::async_runtime::spawn(async move {
+                let action = link.action();
+                let outcome = run(&app, link).await;
+                emit_result(&app, url, action, outcome);
+            });
+        }
+        Err(e) => emit_result(app, url.to_string(), link.action(), Err(e)),
+    }
+}
+
+fn port_file() -> Option<PathBuf> {
+    crate::portable::data_dir().map(|dir| dir.join(PORT_FILE))
+}
+
+/// Hand links to an instance that is already running; false when there is
+/// none and this instance has to handle them itself
+pub fn forward_to_running_instance(urls: &[String]) -> bool {
+    let Some(contents) = port_file().and_then(|file| std::fs::read_to_string(file).ok()) else {
+        return false;
+    };
+    let mut lines = contents.lines();
+    let (Some(port), Some(secret)) = (
+        lines
+            .next()
+            .and_then(|port| port.trim().parse::<u16>().ok()),
+        lines.next().map(str::trim),
+    ) else {
+        return false;
+    };
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, FORWARD_TIMEOUT) else {
+        // Stale port file left by an instance that did not exit cleanly
+        return false;
+    };
+    let _ = stream.set_write_timeout(Some(FORWARD_TIMEOUT));
+    writeln!(stream, "{}", secret).is_ok()
+        && urls.iter().all(|url| writeln!(stream, "{}", url).is_ok())
+}
+
+fn new_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn write_port_file(file: &Path, contents: &str) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut port_file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(file)?;
+        port_file.write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        std::fs::write(file, contents)
+    }
+}
+
+/// Accept links forwarded by later instances; called once from the app setup
+///
+/// A connection must start with the secret written next to the port, so other
+/// local processes cannot drive the app through the socket.
+pub fn listen(app: &AppHandle) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let secret = new_secret();
+    if let Some(file) = port_file() {
+        write_port_file(
+            &file,
+            &format!("{}\n{}\n", listener.local_addr()?.port(), secret),
+        )?;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+            let mut lines = BufReader::new(stream).lines().map_while(Result::ok);
+            if lines.next().as_deref().map(str::trim) != Some(secret.as_str()) {
+                log::warn!("Rejected a deep link connection without the secret");
+                continue;
+            }
+            for line in lines {
+                if line.starts_with(SCHEME) {
+                    handle(&app, line.trim());
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Make the OS open `claude-workbench://` links with this executable. The
+/// installers cover macOS through Info.plist; Windows and Linux register the
+/// running executable, which also keeps portable copies working. Nothing is
+/// written while the registration already points at this executable.
+pub fn register_scheme() -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+
+        let exe = std::env::current_exe()
+            .map_err(|e| format!("Failed to locate the executable: {}", e))?;
+        let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+        let command = format!("\"{}\" \"%1\"", exe.display());
+        let current = std::process::Command::new("reg")
+            .args(["query", &format!(r"{}\shell\open\command", key), "/ve"])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+        if current.is_some_and(|current| current.contains(&command)) {
+            return Ok(());
+        }
+        let entries = [
+            (key.clone(), None, "URL:Claude Workbench".to_string()),
+            (key.clone(), Some("URL Protocol"), String::new()),
+            (format!(r"{}\shell\open\command", key), None, command),
+        ];
+        for (key, value_name, data) in entries {
+            let mut cmd = std::process::Command::new("reg");
+            cmd.args(["add", &key]);
+            match value_name {
+                Some(name) => cmd.args(["/v", name]),
+                None => cmd.arg("/ve"),
+            };
+            cmd.args(["/d", &data, "/f"]);
+            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+            let status = cmd
+                .status()
+                .map_err(|e| format!("Failed to run reg: {}", e))?;
+            if !status.success() {
+                return Err(format!("reg add {} failed with {}", key, status));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // AppImages run from a temporary mount; register the image itself
+        let exe = match std::env::var_os("APPIMAGE") {
+            Some(image) => PathBuf::from(image),
+            None => std::env::current_exe()
+                .map_err(|e| format!("Failed to locate the executable: {}", e))?,
+        };
+        let applications = dirs::data_dir()
+            .ok_or("Failed to locate the data directory")?
+            .join("applications");
+        std::fs::create_dir_all(&applications)
+            .map_err(|e| format!("Failed to create {:?}: {}", applications, e))?;
+        let desktop_file = format!("{}-handler.desktop", SCHEME);
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=Claude Workbench\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+            exe.display(),
+            SCHEME
+        );
+        let registered = std::process::Command::new("xdg-mime")
+            .args(["query", "default", &format!("x-scheme-handler/{}", SCHEME)])
+            .output()
+            .ok()
+            .is_some_and(|output| String::from_utf8_lossy(&output.stdout).trim() == desktop_file);
+        let current = std::fs::read_to_string(applications.join(&desktop_file)).ok();
+        if registered && current.as_deref() == Some(entry.as_str()) {
+            return Ok(());
+        }
+        std::fs::write(applications.join(&desktop_file), entry)
+            .map_err(|e| format!("Failed to write {}: {}", desktop_file, e))?;
+        let status = std::process::Command::new("xdg-mime")
+            .args([
+                "default",
+                &desktop_file,
+                &format!("x-scheme-handler/{}", SCHEME),
+            ])
+            .status()
+            .map_err(|e| format!("Failed to run xdg-mime: {}", e))?;
+        if !status.success() {
+            return Err(format!("xdg-mime failed with {}", status));
+        }
+    }
+
+    Ok(())
+}
+
+/// Carry out a link from `deep-link-confirm` once the user accepted it, or
+/// drop it
+#[tauri::command]
+pub async fn confirm_deep_link(app: AppHandle, id: String, accept: bool) -> Result<String, String> {
+    let link = app
+        .state::<DeepLinkState>()
+        .pending_confirmations
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&id)
+        .map(|(link, _)| link)
+        .ok_or_else(|| t!("deep_link.confirmation_expired"))?;
+    if !accept {
+        log::info!("Deep link {} declined", link.action());
+        return Ok(t!("deep_link.declined"));
+    }
+    run(&app, link).await
+}
+
+/// Links still waiting for confirmation, for a frontend that subscribed to
+/// `deep-link-confirm` after they arrived
+#[tauri::command]
+pub fn list_pending_deep_links(
+    state: tauri::State<'_, DeepLinkState>,
+) -> Vec<DeepLinkConfirmation> {
+    state
+        .pending_confirmations
+        .lock()
+        .map(|pending| {
+            pending
+                .values()
+                .map(|(_, confirmation)| confirmation.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Project a link asked to open before the frontend subscribed to
+/// `deep-link-open-project`; cleared once taken
+#[tauri::command]
+pub fn take_deep_link_project(state: tauri::State<'_, DeepLinkState>) -> Option<String> {
+    state
+        .pending_project
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        assert_eq!(
+            parse("claude-workbench://apply-station/1b2c-3d/"),
+            Ok(DeepLink::ApplyStation {
+                station_id: "1b2c-3d".to_string()
+            })
+        );
+        assert_eq!(
+            parse("claude-workbench://open-project?path=%2Fhome%2Fme%2Fmy%20app"),
+            Ok(DeepLink::OpenProject {
+                path: "/home/me/my app".to_string()
+            })
+        );
+        assert_eq!(
+            parse("claude-workbench://apply-station/1")
+                .unwrap()
+                .action(),
+            "apply-station"
+        );
+        assert!(parse("claude-workbench://open-project").is_err());
+        assert!(parse("claude-workbench://apply-station").is_err());
+        assert!(parse("https://example.com/apply-station/1").is_err());
+
+        let json = r#"{"version":1,"exported_at":0,"stations":[]}"#;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        let url = format!("claude-workbench://import-station?payload={}", payload);
+        let Ok(DeepLink::ImportStation { payload }) = parse(&url) else {
+            panic!("expected an import-station link");
+        };
+        assert_eq!(decode_station_payload(&payload).unwrap().version, 1);
+        assert_eq!(decode_station_payload(json).unwrap().version, 1);
+        assert!(decode_station_payload("not base64!").is_err());
+
+        assert_eq!(
+            links_in_args(vec![
+                "--portable".to_string(),
+                "claude-workbench://apply-station/1".to_string()
+            ]),
+            vec!["claude-workbench://apply-station/1".to_string()]
+        );
+    }
+}
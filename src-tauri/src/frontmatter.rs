@@ -0,0 +1,53 @@
+//! Markdown files with YAML frontmatter, as Claude Code reads them for
+//! subagents and output styles
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Split a file into its frontmatter and its trimmed body
+pub fn split<T: DeserializeOwned>(content: &str) -> Result<(T, String), String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.first().map(|line| line.trim_end()) != Some("---") {
+        return Err("Missing YAML frontmatter".to_string());
+    }
+    let end = lines
+        .iter()
+        .skip(1)
+        .position(|line| line.trim_end() == "---")
+        .map(|index| index + 1)
+        .ok_or("Frontmatter is not closed with ---")?;
+
+    let frontmatter = serde_yaml::from_str::<T>(&lines[1..end].join("\n"))
+        .map_err(|e| format!("Invalid frontmatter: {}", e))?;
+    let body = lines[end + 1..].join("\n").trim().to_string();
+    Ok((frontmatter, body))
+}
+
+/// Render frontmatter and a body as a markdown file
+pub fn render<T: Serialize>(frontmatter: &T, body: &str) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(frontmatter)
+        .map_err(|e| format!("Failed to serialize frontmatter: {}", e))?;
+    Ok(format!("---\n{}---\n\n{}\n", yaml, body.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_split_and_render() {
+        let (frontmatter, body) =
+            split::<BTreeMap<String, String>>("\u{feff}---\nname: Reviewer\n---\n\nReview.\n")
+                .unwrap();
+        assert_eq!(frontmatter["name"], "Reviewer");
+        assert_eq!(body, "Review.");
+        assert_eq!(
+            render(&frontmatter, &body).unwrap(),
+            "---\nname: Reviewer\n---\n\nReview.\n"
+        );
+
+        assert!(split::<BTreeMap<String, String>>("name: Reviewer").is_err());
+        assert!(split::<BTreeMap<String, String>>("---\nname: Reviewer\n").is_err());
+    }
+}
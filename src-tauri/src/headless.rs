@@ -0,0 +1,404 @@
+//! `--headless <command>`: run core operations from scripts and CI without
+//! opening the window, reusing the logic behind the provider, relay station
+//! and doctor pages
+//!
+//! Changes are written to the same settings files and database the app uses;
+//! a running app picks them up the next time it reads them. Commands the app
+//! lock guards are refused while it is enabled, as there is no window to unlock
+//! in. On Windows release builds the executable is a GUI program, so output is
+//! only visible when redirected to a file or pipe.
+use crate::commands::app_lock::{self, ProtectedAction};
+use crate::commands::ccusage::{self, CcusageReport};
+use crate::commands::doctor::{self, DoctorStatus};
+use crate::commands::provider;
+use crate::commands::relay_stations::{self, RelayStationManager};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Command line switch selecting headless mode; the command follows it
+pub const HEADLESS_ARG: &str = "--headless";
+
+const USAGE: &str = "Usage: claude-workbench --headless <command>
+
+Commands:
+  list-providers                    List provider configs, * marks the active one
+  switch-provider <id>              Apply a provider config to Claude Code
+  list-stations                     List relay stations
+  apply-station <id>                Apply the configuration last used with a relay station
+  export-stations [--output <file>] [<id>...]
+                                    Export relay stations as JSON
+  usage-export <daily|session> [--since <YYYYMMDD>] [--until <YYYYMMDD>] [--output <file>]
+                                    Export usage as ccusage's --json output
+  doctor [--json]                   Check the environment the Claude CLI runs in";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    ListProviders,
+    SwitchProvider(String),
+    ListStations,
+    ApplyStation(String),
+    ExportStations {
+        output: Option<PathBuf>,
+        station_ids: Vec<String>,
+    },
+    UsageExport {
+        report: CcusageReport,
+        since: Option<String>,
+        until: Option<String>,
+        output: Option<PathBuf>,
+    },
+    Doctor {
+        json: bool,
+    },
+    Help,
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(Command::Help);
+    };
+    let single_id = |what: &str| match rest {
+        [id] => Ok(id.clone()),
+        _ => Err(format!("{} expects exactly one {} id", name, what)),
+    };
+    match name.as_str() {
+        "list-providers" => Ok(Command::ListProviders),
+        "switch-provider" => single_id("provider").map(Command::SwitchProvider),
+        "list-stations" => Ok(Command::ListStations),
+        "apply-station" => single_id("station").map(Command::ApplyStation),
+        "export-stations" => {
+            let mut output = None;
+            let mut station_ids = Vec::new();
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                if arg == "--output" || arg == "-o" {
+                    let file = rest.next().ok_or("--output expects a file name")?;
+                    output = Some(PathBuf::from(file));
+                } else {
+                    station_ids.push(arg.clone());
+                }
+            }
+            Ok(Command::ExportStations {
+                output,
+                station_ids,
+            })
+        }
+        "usage-export" => {
+            let mut rest = rest.iter();
+            let report = rest
+                .next()
+                .ok_or("usage-export expects daily or session")?
+                .parse()?;
+            let (mut since, mut until, mut output) = (None, None, None);
+            while let Some(arg) = rest.next() {
+                let mut value = |name: &str| {
+                    rest.next()
+                        .cloned()
+                        .ok_or_else(|| format!("{} expects a value", name))
+                };
+                match arg.as_str() {
+                    "--since" => since = Some(value("--since")?),
+                    "--until" => until = Some(value("--until")?),
+                    "--output" | "-o" => output = Some(PathBuf::from(value("--output")?)),
+                    _ => return Err(format!("Unknown usage-export option: {}", arg)),
+                }
+            }
+            Ok(Command::UsageExport {
+                report,
+                since,
+                until,
+                output,
+            })
+        }
+        "doctor" => Ok(Command::Doctor {
+            json: rest.iter().any(|arg| arg == "--json"),
+        }),
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
+
+/// The app database, as the running app opens it
+fn open_database() -> Result<Connection, String> {
+    let dir = crate::portable::data_dir().ok_or("Failed to locate the app data directory")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    Connection::open(dir.join("agents.db")).map_err(|e| format!("Failed to open database: {}", e))
+}
+
+fn relay_manager() -> Result<RelayStationManager, String> {
+    let conn = open_database()?;
+    RelayStationManager::new(Arc::new(Mutex::new(conn)))
+        .map_err(|e| format!("Failed to initialize relay station manager: {}", e))
+}
+
+/// Refuse a command the app lock guards
+fn ensure_unguarded(conn: &Connection, action: ProtectedAction) -> Result<(), String> {
+    if app_lock::load_app_lock_settings(conn).protects(action) {
+        return Err(
+            "The app lock guards this command; run it in the app or turn off its protection in the app lock settings"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Write an export readable only by the current user, as it may hold tokens
+fn write_export(file: &Path, contents: &str) -> Result<(), String> {
+    let written = {
+        #[cfg(unix)]
+        {
+            use std::io::Write;
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(file)
+                .and_then(|mut export| {
+                    // `mode` only applies to new files
+                    export.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+                    export.write_all(contents.as_bytes())
+                })
+        }
+        #[cfg(not(unix))]
+        {
+            std::fs::write(file, contents)
+        }
+    };
+    written.map_err(|e| format!("Failed to write {}: {}", file.display(), e))
+}
+
+fn status_label(status: DoctorStatus) -> &'static str {
+    match status {
+        DoctorStatus::Pass => "PASS",
+        DoctorStatus::Warn => "WARN",
+        DoctorStatus::Fail => "FAIL",
+    }
+}
+
+/// Run a command; `Ok(false)` for a command that ran but reports failure
+async fn execute(command: Command) -> Result<bool, String> {
+    match command {
+        Command::Help => println!("{}", USAGE),
+        Command::ListProviders => {
+            let current = provider::get_current_provider_id()?;
+            for config in provider::load_providers_from_file()? {
+                let marker = if current.as_deref() == Some(config.id.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!(
+                    "{} {}\t{}\t{}",
+                    marker, config.id, config.name, config.base_url
+                );
+            }
+        }
+        Command::SwitchProvider(id) => {
+            let conn = open_database()?;
+            ensure_unguarded(&conn, ProtectedAction::SwitchConfig)?;
+            let config = provider::find_provider(&id)?;
+            println!(
+                "{}",
+                provider::switch_provider_settings(&conn, &config).await?
+            );
+        }
+        Command::ListStations => {
+            let manager = relay_manager()?;
+            let stations = manager
+                .list_stations()
+                .map_err(|e| format!("Failed to list relay stations: {}", e))?;
+            for station in stations {
+                let state = if station.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    station.id, station.name, station.api_url, state
+                );
+            }
+        }
+        Command::ApplyStation(id) => {
+            let conn = open_database()?;
+            ensure_unguarded(&conn, ProtectedAction::SwitchConfig)?;
+            let manager = relay_manager()?;
+            let (config, applied) = relay_stations::station_provider_config(&manager, &id)?;
+            let message = provider::switch_provider_settings(&conn, &config).await?;
+            manager
+                .record_config_usage(&applied.station_id, &applied.base_url, &applied.token)
+                .map_err(|e| format!("Failed to record station usage: {}", e))?;
+            println!("{}", message);
+        }
+        Command::ExportStations {
+            output,
+            station_ids,
+        } => {
+            ensure_unguarded(&open_database()?, ProtectedAction::ExportStations)?;
+            let manager = relay_manager()?;
+            let ids = (!station_ids.is_empty()).then_some(station_ids);
+            let export = manager
+                .export_stations(ids)
+                .map_err(|e| format!("Failed to export relay stations: {}", e))?;
+            let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+            match output {
+                Some(file) => {
+                    write_export(&file, &json)?;
+                    println!(
+                        "Exported {} relay station(s) to {}",
+                        export.stations.len(),
+                        file.display()
+                    );
+                }
+                None => println!("{}", json),
+            }
+        }
+        Command::UsageExport {
+            report,
+            since,
+            until,
+            output,
+        } => {
+            let json = ccusage::export_json(report, since.as_deref(), until.as_deref())?;
+            match output {
+                Some(file) => {
+                    write_export(&file, &json)?;
+                    println!("Exported usage to {}", file.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+        Command::Doctor { json } => {
+            let report = doctor::run_checks(None);
+            if json {
+                let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+                println!("{}", json);
+            } else {
+                for check in &report.checks {
+                    println!(
+                        "[{}] {}: {}",
+                        status_label(check.status),
+                        check.title,
+                        check.detail
+                    );
+                    if let Some(suggestion) = &check.suggestion {
+                        println!("       {}", suggestion);
+                    }
+                }
+            }
+            return Ok(report.status != DoctorStatus::Fail);
+        }
+    }
+    Ok(true)
+}
+
+/// Run the headless command if `args` (without the program name) asks for
+/// one; returns the exit code, or `None` to start the app normally
+pub fn run_from_args(args: &[String]) -> Option<i32> {
+    let position = args.iter().position(|arg| arg == HEADLESS_ARG)?;
+    let command = match parse_command(&args[position + 1..]) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(2);
+        }
+    };
+    let code = match tauri::async_runtime::block_on(execute(command)) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(&[]), Ok(Command::Help));
+        assert_eq!(
+            parse_command(&args(&["switch-provider", "anyrouter"])),
+            Ok(Command::SwitchProvider("anyrouter".to_string()))
+        );
+        assert!(parse_command(&args(&["apply-station"])).is_err());
+        assert_eq!(
+            parse_command(&args(&["export-stations", "a", "-o", "out.json", "b"])),
+            Ok(Command::ExportStations {
+                output: Some(PathBuf::from("out.json")),
+                station_ids: args(&["a", "b"]),
+            })
+        );
+        assert!(parse_command(&args(&["export-stations", "--output"])).is_err());
+        assert_eq!(
+            parse_command(&args(&["usage-export", "daily", "--since", "20250601"])),
+            Ok(Command::UsageExport {
+                report: CcusageReport::Daily,
+                since: Some("20250601".to_string()),
+                until: None,
+                output: None,
+            })
+        );
+        assert!(parse_command(&args(&["usage-export", "monthly"])).is_err());
+        assert_eq!(
+            parse_command(&args(&["doctor", "--json"])),
+            Ok(Command::Doctor { json: true })
+        );
+        assert!(parse_command(&args(&["frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn test_app_lock_refuses_guarded_commands() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        assert!(ensure_unguarded(&conn, ProtectedAction::SwitchConfig).is_ok());
+
+        let settings = app_lock::AppLockSettings {
+            enabled: true,
+            protect_export: false,
+            ..Default::default()
+        };
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)",
+            rusqlite::params![
+                app_lock::APP_LOCK_SETTINGS_KEY,
+                serde_json::to_string(&settings).unwrap()
+            ],
+        )
+        .unwrap();
+        assert!(ensure_unguarded(&conn, ProtectedAction::SwitchConfig).is_err());
+        assert!(ensure_unguarded(&conn, ProtectedAction::ExportStations).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exports_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("stations.json");
+        std::fs::write(&file, "old").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_export(&file, "{}").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{}");
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,199 @@
+//! Backend localization: translations bundled from `locales/*.json`, looked
+//! up by dotted key (e.g. "relay.station_not_found") with `{{name}}`
+//! placeholders, the same format the frontend's i18next files use
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Locale used when a key is missing from the active one
+pub const FALLBACK_LOCALE: &str = "en";
+
+/// Locales bundled into the binary
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "zh-CN"];
+
+static CATALOGS: OnceLock<HashMap<&'static str, Value>> = OnceLock::new();
+static CURRENT_LOCALE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Translate `key` in the current locale, substituting `{{name}}` placeholders
+///
+/// Use through `t!("relay.failed_to_get_station", "error" => &e)`.
+#[macro_export]
+macro_rules! t {
+    ($key:expr $(, $($name:expr => $value:expr),+)?) => {
+        $crate::i18n::translate($key, &[$($(($name, $value.to_string())),+)?])
+    };
+}
+
+fn catalogs() -> &'static HashMap<&'static str, Value> {
+    CATALOGS.get_or_init(|| {
+        let bundled = [
+            ("en", include_str!("../locales/en.json")),
+            ("zh-CN", include_str!("../locales/zh-CN.json")),
+        ];
+        bundled
+            .into_iter()
+            .filter_map(|(locale, source)| match serde_json::from_str(source) {
+                Ok(catalog) => Some((locale, catalog)),
+                Err(e) => {
+                    log::error!("Invalid bundled locale {}: {}", locale, e);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// Map a locale tag like "zh", "zh_CN" or "en-US" to a bundled locale
+pub fn normalize_locale(tag: &str) -> Option<&'static str> {
+    let tag = tag.trim().replace('_', "-");
+    // Drop encodings such as "zh_CN.UTF-8"
+    let tag = tag.split('.').next().unwrap_or_default().to_lowercase();
+    if let Some(locale) = SUPPORTED_LOCALES
+        .iter()
+        .copied()
+        .find(|locale| locale.to_lowercase() == tag)
+    {
+        return Some(locale);
+    }
+    match tag.split('-').next().unwrap_or_default() {
+        "en" => Some("en"),
+        // Only Simplified Chinese is bundled so far
+        "zh" => Some("zh-CN"),
+        _ => None,
+    }
+}
+
+/// Bundled locale closest to the OS language, or the fallback
+pub fn system_locale() -> &'static str {
+    sys_locale::get_locale()
+        .and_then(|tag| normalize_locale(&tag))
+        .unwrap_or(FALLBACK_LOCALE)
+}
+
+/// Active locale
+pub fn current_locale() -> String {
+    CURRENT_LOCALE
+        .read()
+        .ok()
+        .and_then(|locale| locale.clone())
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
+
+/// Switch the active locale; returns the bundled locale that was selected
+pub fn set_locale(tag: &str) -> Result<&'static str, String> {
+    let locale = normalize_locale(tag).ok_or_else(|| {
+        format!(
+            "Unsupported locale {:?}, expected one of {}",
+            tag,
+            SUPPORTED_LOCALES.join(", ")
+        )
+    })?;
+    let mut current = CURRENT_LOCALE.write().map_err(|e| e.to_string())?;
+    *current = Some(locale.to_string());
+    Ok(locale)
+}
+
+fn lookup<'a>(catalog: &'a Value, key: &str) -> Option<&'a str> {
+    key.split('.')
+        .try_fold(catalog, |node, part| node.get(part))?
+        .as_str()
+}
+
+/// Replace `{{name}}` with the matching argument; unknown placeholders are kept
+fn interpolate(template: &str, args: &[(&str, String)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+fn translate_in(locale: &str, key: &str, args: &[(&str, String)]) -> String {
+    // Arguments are mostly error details, which may quote URLs with keys
+    let args: Vec<(&str, String)> = args
+        .iter()
+        .map(|(name, value)| (*name, crate::redact::redact(value).into_owned()))
+        .collect();
+    let args = args.as_slice();
+    let catalogs = catalogs();
+    let template = [locale, FALLBACK_LOCALE]
+        .iter()
+        .filter_map(|locale| catalogs.get(locale))
+        .find_map(|catalog| lookup(catalog, key));
+    match template {
+        Some(template) => interpolate(template, args),
+        None => {
+            log::warn!("Missing translation for {:?}", key);
+            // Show the raw key with its arguments rather than losing the error detail
+            let details: Vec<&str> = args.iter().map(|(_, value)| value.as_str()).collect();
+            if details.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}: {}", key, details.join(", "))
+            }
+        }
+    }
+}
+
+/// Translate `key` in the active locale; prefer the `t!` macro
+pub fn translate(key: &str, args: &[(&str, String)]) -> String {
+    translate_in(&current_locale(), key, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_with_fallback() {
+        assert_eq!(normalize_locale("zh_CN.UTF-8"), Some("zh-CN"));
+        assert_eq!(normalize_locale("en-US"), Some("en"));
+        assert_eq!(normalize_locale("fr"), None);
+
+        let args = [("error", "timeout".to_string())];
+        assert_eq!(
+            translate_in("en", "relay.failed_to_get_station", &args),
+            "Failed to get relay station: timeout"
+        );
+        assert_eq!(
+            translate_in("zh-CN", "relay.failed_to_get_station", &args),
+            "获取中转站失败：timeout"
+        );
+        assert_eq!(
+            translate_in("zh-CN", "relay.unknown_key", &args),
+            "relay.unknown_key: timeout"
+        );
+
+        let args = [(
+            "error",
+            "GET https://relay.example.com/api?key=secret".to_string(),
+        )];
+        assert_eq!(
+            translate_in("en", "relay.failed_to_get_station", &args),
+            "Failed to get relay station: GET https://relay.example.com/api?key=***"
+        );
+    }
+
+    #[test]
+    fn test_bundled_locales_have_the_same_keys() {
+        fn keys(value: &Value, prefix: &str, out: &mut Vec<String>) {
+            match value.as_object() {
+                Some(map) => {
+                    for (name, child) in map {
+                        keys(child, &format!("{}{}.", prefix, name), out);
+                    }
+                }
+                None => out.push(prefix.trim_end_matches('.').to_string()),
+            }
+        }
+
+        let mut expected = Vec::new();
+        keys(&catalogs()[FALLBACK_LOCALE], "", &mut expected);
+        expected.sort();
+        for locale in SUPPORTED_LOCALES {
+            let mut actual = Vec::new();
+            keys(&catalogs()[locale], "", &mut actual);
+            actual.sort();
+            assert_eq!(actual, expected, "keys of {}", locale);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Shared module for verifying downloaded artifacts before they are executed
+//! Checks SHA-256 checksums and, when gpg is available, detached signatures
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::Command;
+
+/// Outcome of a detached signature check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// The signature is valid for a trusted key
+    Verified,
+    /// The signature does not match the signed data
+    Invalid,
+    /// No signature was published, gpg is missing or the signing key is unknown
+    Unavailable,
+}
+
+/// Verification of one downloaded artifact, shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactVerification {
+    /// File name of the artifact
+    pub artifact: String,
+    pub expected_sha256: String,
+    pub actual_sha256: String,
+    pub checksum_matches: bool,
+    pub signature: SignatureStatus,
+    /// Signer or error reported by the signature check
+    pub signature_detail: Option<String>,
+}
+
+impl ArtifactVerification {
+    /// Compare the checksum of `content` with the published one
+    pub fn new(artifact: &str, expected_sha256: &str, content: &[u8]) -> Self {
+        let expected_sha256 = expected_sha256.trim().to_lowercase();
+        let actual_sha256 = sha256_hex(content);
+        Self {
+            artifact: artifact.to_string(),
+            checksum_matches: expected_sha256 == actual_sha256,
+            expected_sha256,
+            actual_sha256,
+            signature: SignatureStatus::Unavailable,
+            signature_detail: None,
+        }
+    }
+
+    /// Error unless the checksum matches and the signature, if any, is valid
+    pub fn ensure_verified(&self) -> Result<(), String> {
+        if !self.checksum_matches {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                self.artifact, self.expected_sha256, self.actual_sha256
+            ));
+        }
+        if self.signature == SignatureStatus::Invalid {
+            return Err(format!(
+                "Invalid signature for {}: {}",
+                self.artifact,
+                self.signature_detail.as_deref().unwrap_or("unknown error")
+            ));
+        }
+        info!(
+            "Verified {} (sha256 {}, signature {:?})",
+            self.artifact, self.actual_sha256, self.signature
+        );
+        Ok(())
+    }
+}
+
+/// Hex-encoded SHA-256 of `content`
+pub fn sha256_hex(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Look up the checksum of `file_name` in a `sha256sum`-style list
+pub fn checksum_from_list(list: &str, file_name: &str) -> Option<String> {
+    list.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let checksum = parts.next()?;
+        let name = parts.next()?.trim_start_matches('*');
+        (name == file_name && checksum.len() == 64).then(|| checksum.to_lowercase())
+    })
+}
+
+/// Check a detached signature of `data` with gpg
+pub fn verify_gpg_signature(data: &Path, signature: &Path) -> (SignatureStatus, Option<String>) {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new("gpg");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = match cmd
+        .args(["--batch", "--verify"])
+        .arg(signature)
+        .arg(data)
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            return (
+                SignatureStatus::Unavailable,
+                Some(format!("gpg is not available: {}", e)),
+            )
+        }
+    };
+
+    let report = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    let detail = report.lines().last().map(|line| line.to_string());
+    if output.status.success() {
+        (SignatureStatus::Verified, detail)
+    } else if report.contains("BAD signature") {
+        warn!("Bad signature on {:?}: {}", data, report);
+        (SignatureStatus::Invalid, detail)
+    } else {
+        (SignatureStatus::Unavailable, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_verification() {
+        let content = b"node";
+        let checksum = sha256_hex(content);
+        let list = format!(
+            "{}  node-v20.18.0-linux-x64.tar.gz\n{} *node-v20.18.0-win-x64.zip\n",
+            "0".repeat(64),
+            checksum.to_uppercase()
+        );
+
+        assert_eq!(
+            checksum_from_list(&list, "node-v20.18.0-win-x64.zip"),
+            Some(checksum.clone())
+        );
+        assert_eq!(checksum_from_list(&list, "node-v20.18.0-win-x64.7z"), None);
+
+        let verified = ArtifactVerification::new("node.zip", &checksum, content);
+        assert!(verified.ensure_verified().is_ok());
+        let tampered = ArtifactVerification::new("node.zip", &"0".repeat(64), content);
+        assert!(!tampered.checksum_matches);
+        assert!(tampered.ensure_verified().is_err());
+    }
+}
@@ -1,18 +1,24 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
-// Simple internationalization macro - returns the first parameter as-is for now
-#[macro_export]
-macro_rules! t {
-    ($key:expr $(, $($name:expr => $value:expr),+)?) => {
-        $key.to_string()
-    };
-}
-
 // Declare modules
 pub mod checkpoint;
 pub mod claude_binary;
 pub mod commands;
+pub mod deep_link;
+pub mod frontmatter;
+pub mod headless;
+pub mod i18n;
+pub mod integrity;
+pub mod local_http;
+pub mod logging;
+pub mod mcp;
+pub mod os_auth;
+pub mod portable;
 pub mod process;
+pub mod redact;
+pub mod transcript;
+pub mod tray;
+pub mod windows;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
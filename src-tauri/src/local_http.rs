@@ -0,0 +1,246 @@
+//! Minimal HTTP/1.1 handling for the servers the app runs on localhost
+//!
+//! Clients are scripts, editor extensions and CLI tools on the same machine,
+//! so one request per connection is enough: every response closes it.
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Request line plus headers larger than this are rejected
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Bodies larger than this are rejected; requests with images stay well below
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// Connections that have not sent a whole request by then are dropped, so
+/// idle clients cannot hold on to them
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    /// Path without the query string
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Value of a header, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| {
+                urlencoding::decode(value)
+                    .map(|value| value.into_owned())
+                    .unwrap_or_else(|_| value.to_string())
+            })
+        })
+    }
+
+    /// Token sent as `Authorization: Bearer <token>` or in `header`
+    pub fn token(&self, header: &str) -> Option<&str> {
+        self.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| self.header(header))
+            .map(str::trim)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Read one request; chunked request bodies are not supported
+pub async fn read_request<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<HttpRequest> {
+    read_request_within(stream, REQUEST_TIMEOUT).await
+}
+
+async fn read_request_within<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    timeout: Duration,
+) -> io::Result<HttpRequest> {
+    tokio::time::timeout(timeout, read_request_unbounded(stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Timed out reading the request"))?
+}
+
+async fn read_request_unbounded<R: AsyncRead + Unpin>(stream: &mut R) -> io::Result<HttpRequest> {
+    let mut buffer = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(invalid("Request headers too large"));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..head_end])
+        .map_err(|_| invalid("Request headers are not UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(invalid("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut request = HttpRequest {
+        method: method.to_ascii_uppercase(),
+        path: path.to_string(),
+        query: query.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        return Err(invalid("Chunked request bodies are not supported"));
+    }
+    let length = match request.header("content-length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| invalid("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(invalid("Request body too large"));
+    }
+
+    let mut body = buffer.split_off(head_end + 4);
+    body.truncate(length);
+    if body.len() < length {
+        let already = body.len();
+        body.resize(length, 0);
+        stream.read_exact(&mut body[already..]).await?;
+    }
+    request.body = body;
+    Ok(request)
+}
+
+pub fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+/// Status line and headers; a body written afterwards ends with the connection
+pub async fn write_head<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    status: u16,
+    headers: &[(&str, &str)],
+) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, status_text(status));
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes()).await
+}
+
+pub async fn write_response<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let length = body.len().to_string();
+    write_head(
+        stream,
+        status,
+        &[("Content-Type", content_type), ("Content-Length", &length)],
+    )
+    .await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+pub async fn write_json<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    status: u16,
+    value: &serde_json::Value,
+) -> io::Result<()> {
+    write_response(
+        stream,
+        status,
+        "application/json",
+        value.to_string().as_bytes(),
+    )
+    .await
+}
+
+/// Compare secrets without leaking the matching prefix length through timing
+pub fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = b"POST /v1/messages?beta=true HTTP/1.1\r\nHost: 127.0.0.1\r\nAuthorization: Bearer abc\r\nContent-Length: 11\r\n\r\n{\"a\": true}";
+        let request = read_request(&mut &raw[..]).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/messages");
+        assert_eq!(request.query_param("beta").as_deref(), Some("true"));
+        assert_eq!(request.header("content-length"), Some("11"));
+        assert_eq!(request.token("x-workbench-token"), Some("abc"));
+        assert_eq!(request.body, b"{\"a\": true}");
+
+        let truncated = b"GET / HTTP/1.1\r\nHost: x\r\n";
+        assert!(read_request(&mut &truncated[..]).await.is_err());
+
+        // A client that stops sending mid-request is cut off
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab")
+            .await
+            .unwrap();
+        let error = read_request_within(&mut server, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        assert!(secrets_match("token", "token"));
+        assert!(!secrets_match("token", "tokem"));
+        assert!(!secrets_match("tok", "token"));
+    }
+}
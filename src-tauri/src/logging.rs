@@ -0,0 +1,222 @@
+//! Shared logging setup: a tracing subscriber writing to stderr and to daily
+//! rotated files in the app data directory. Records from the `log` macros used
+//! across the backend are bridged into it, and the level can change at runtime.
+//! Secrets are redacted from every line before it is written
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// Level used when neither RUST_LOG nor a saved setting says otherwise
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Log files are named `workbench.YYYY-MM-DD.log`
+const LOG_FILE_PREFIX: &str = "workbench";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Number of daily log files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static CURRENT_LEVEL: OnceLock<std::sync::Mutex<String>> = OnceLock::new();
+/// Flushes buffered lines to the log file when dropped, so it lives for the whole process
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Directory holding the rotated log files
+pub fn log_dir() -> Option<PathBuf> {
+    crate::portable::data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Install the global subscriber; call once before anything logs
+pub fn init() {
+    let level = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_LOG_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let file_layer = log_dir().and_then(|dir| match file_appender(&dir) {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_GUARD.set(guard);
+            Some(fmt::layer().with_writer(Redacting(writer)).with_ansi(false))
+        }
+        Err(e) => {
+            eprintln!("Failed to open log directory {:?}: {}", dir, e);
+            None
+        }
+    });
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(Redacting(std::io::stderr)))
+        .with(file_layer)
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to initialize logging: {}", e);
+        return;
+    }
+
+    // The bridge caps `log` records at the initial level; let the filter decide instead
+    log::set_max_level(log::LevelFilter::Trace);
+    let _ = FILTER_HANDLE.set(handle);
+    let _ = CURRENT_LEVEL.set(std::sync::Mutex::new(level));
+}
+
+/// Makes writers that redact secrets from what is written to them
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+/// The formatter writes each event in one call, so secrets are never split
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(crate::redact::redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn file_appender(dir: &std::path::Path) -> Result<RollingFileAppender, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(|e| e.to_string())
+}
+
+/// Current filter, e.g. "info" or "claude_workbench=debug,info"
+pub fn log_level() -> String {
+    CURRENT_LEVEL
+        .get()
+        .and_then(|level| level.lock().ok().map(|level| level.clone()))
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string())
+}
+
+/// Change the filter of the running subscriber
+///
+/// Accepts a plain level ("error" … "trace") or any RUST_LOG style directive.
+pub fn set_log_level(level: &str) -> Result<(), String> {
+    let level = level.trim();
+    if level.is_empty() {
+        return Err("Log level must not be empty".to_string());
+    }
+    let filter =
+        EnvFilter::try_new(level).map_err(|e| format!("Invalid log level {:?}: {}", level, e))?;
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or("Logging has not been initialized")?;
+    handle
+        .reload(filter)
+        .map_err(|e| format!("Failed to change log level: {}", e))?;
+
+    if let Some(current) = CURRENT_LEVEL.get() {
+        if let Ok(mut current) = current.lock() {
+            *current = level.to_string();
+        }
+    }
+    log::info!("Log level set to {}", level);
+    Ok(())
+}
+
+/// Log files in `dir`, oldest first
+fn log_files(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| {
+                            name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                        })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // The date in the file name sorts chronologically
+    files.sort();
+    files
+}
+
+/// Last `tail` lines containing `filter` (case-insensitive), oldest first
+pub fn read_logs(dir: &std::path::Path, filter: Option<&str>, tail: usize) -> Vec<String> {
+    let filter = filter
+        .map(|filter| filter.trim().to_lowercase())
+        .filter(|filter| !filter.is_empty());
+    let mut lines = Vec::new();
+
+    for file in log_files(dir).iter().rev() {
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let mut matching: Vec<String> = content
+            .lines()
+            .filter(|line| match &filter {
+                Some(filter) => line.to_lowercase().contains(filter),
+                None => true,
+            })
+            // Files written before redaction may still hold secrets
+            .map(|line| crate::redact::redact(line).into_owned())
+            .collect();
+        // Older files go in front of the lines already collected
+        matching.append(&mut lines);
+        lines = matching;
+        if lines.len() >= tail {
+            break;
+        }
+    }
+
+    let skip = lines.len().saturating_sub(tail);
+    lines.split_off(skip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_logs_across_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("workbench.2024-05-01.log"),
+            "INFO one\nWARN two\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("workbench.2024-05-02.log"),
+            "INFO three\nERROR four\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("other.txt"), "INFO ignored\n").unwrap();
+
+        assert_eq!(
+            read_logs(dir.path(), None, 3),
+            vec!["WARN two", "INFO three", "ERROR four"]
+        );
+        assert_eq!(
+            read_logs(dir.path(), Some("info"), 10),
+            vec!["INFO one", "INFO three"]
+        );
+        assert!(read_logs(dir.path(), Some("debug"), 10).is_empty());
+    }
+}
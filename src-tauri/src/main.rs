@@ -4,7 +4,21 @@
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod deep_link;
+mod frontmatter;
+mod headless;
+mod i18n;
+mod integrity;
+mod local_http;
+mod logging;
+mod mcp;
+mod os_auth;
+mod portable;
 mod process;
+mod redact;
+mod transcript;
+mod tray;
+mod windows;
 
 use checkpoint::state::CheckpointState;
 use commands::agents::{
@@ -13,33 +27,53 @@ use commands::agents::{
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
     get_live_session_output, get_session_output, get_session_status, import_agent,
     import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
-    list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
+    list_agent_run_history, list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
     list_running_sessions, load_agent_session_history, set_claude_binary_path, stream_session_output, update_agent, AgentDb,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
-    clear_checkpoint_manager, continue_claude_code, create_checkpoint, delete_project, execute_claude_code,
+    clear_checkpoint_manager, continue_claude_code, create_checkpoint, create_checkpoint_branch,
+    create_checkpoint_in_background,
+    delete_project, execute_claude_code, export_checkpoint_archive, import_checkpoint_archive,
     find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings,
-    get_checkpoint_state_stats, get_claude_session_output, get_claude_settings, get_project_sessions,
-    get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
-    list_directory_contents, list_projects, list_running_claude_sessions, load_session_history,
-    open_new_session, read_claude_md_file, restore_checkpoint, resume_claude_code,
-    save_claude_md_file, save_claude_settings, save_system_prompt, search_files,
+    get_checkpoint_ignore, get_checkpoint_retention_policy, get_checkpoint_state_stats, get_claude_session_output, get_claude_settings, get_project_sessions,
+    get_project_timeline, get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
+    list_checkpoint_branches, list_directory_contents, list_projects, list_running_claude_sessions, load_session_history,
+    open_new_session, prune_checkpoints, save_checkpoint_ignore, read_claude_md_file, restore_checkpoint,
+    resume_claude_code, set_checkpoint_retention_policy,
+    save_claude_md_file, save_claude_settings, save_system_prompt, search_checkpoints, search_files,
+    switch_checkpoint_branch,
     track_checkpoint_message, track_session_messages, update_checkpoint_settings,
     get_hooks_config, update_hooks_config, validate_hook_command,
     set_custom_claude_path, get_claude_path, clear_custom_claude_path,
+    list_claude_versions, list_available_claude_versions, install_claude_version,
+    uninstall_claude_version, switch_claude_version, get_project_claude_version,
+    set_project_claude_version, update_claude_binary, get_claude_capabilities,
+    refresh_claude_binaries, get_claude_runtime, install_claude_runtime, remove_claude_runtime,
     restore_project, list_hidden_projects, enhance_prompt,
-    ClaudeProcessState,
+    start_interactive_claude_session, write_claude_pty, resize_claude_pty,
+    get_claude_pty_scrollback, close_claude_pty,
 };
 use commands::mcp::{
     mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_get, mcp_get_server_status, mcp_list,
     mcp_read_project_config, mcp_remove, mcp_reset_project_choices, mcp_save_project_config,
-    mcp_serve, mcp_test_connection,
+    mcp_serve, mcp_test_connection, mcp_config_add, mcp_config_list, mcp_config_remove,
+    mcp_config_set_enabled, mcp_config_update, mcp_config_validate, test_mcp_server,
+    mcp_import_claude_desktop, mcp_import_json,
 };
 
+use commands::processes::{
+    get_concurrency_limits, get_process_output, get_run_queue, get_session_restart_policy,
+    get_shutdown_grace_period, list_managed_processes, list_orphaned_processes,
+    load_process_settings, move_queued_run, remove_queued_run, set_concurrency_limits,
+    set_session_restart_policy, set_shutdown_grace_period, adopt_or_kill_orphans, get_run_history,
+    scan_external_claude_processes, adopt_external_process, suspend_process, resume_process,
+    get_resource_thresholds, set_resource_thresholds,
+};
 use commands::usage::{
     get_session_stats, get_usage_by_date_range, get_usage_details, get_usage_stats,
     get_today_usage_stats, get_usage_by_api_base_url, get_active_sessions, get_burn_rate_analysis,
+    get_usage_series,
 };
 use commands::storage::{
     storage_list_tables, storage_read_table, storage_update_row, storage_delete_row,
@@ -52,41 +86,97 @@ use commands::provider::{
     get_provider_presets, get_current_provider_config, get_current_provider_id, switch_provider_config,
     clear_provider_config, test_provider_connection, add_provider_config,
     update_provider_config, delete_provider_config, get_provider_config,
+    get_provider_usage_timeline, reveal_current_secret, probe_provider_endpoints,
+    rotate_provider_endpoint, get_resume_sessions_on_switch, set_resume_sessions_on_switch,
 };
+use commands::doctor::environment_doctor;
+use commands::locale::{get_locale, load_locale, set_locale};
+use commands::logs::{get_app_logs, get_log_level, load_log_level, set_log_level};
 use commands::about::{
-    get_app_version, get_database_path, get_app_info, check_for_updates,
+    get_app_version, get_database_path, get_app_info, check_for_updates, verify_update_artifact,
+    install_update, restart_app, get_update_channel, set_update_channel, get_network_proxy,
+    set_network_proxy, get_release_history,
 };
 use commands::relay_stations::{
     list_relay_stations, get_relay_station, add_relay_station, update_relay_station,
     delete_relay_station, get_station_info, list_station_tokens, add_station_token,
     update_station_token, delete_station_token, get_token_user_info, get_station_logs,
-    test_station_connection, api_user_self_groups, toggle_station_token,
+    test_station_connection, api_user_self_groups, suggest_token_group, toggle_station_token,
     load_station_api_endpoints, save_station_config, get_station_config,
     get_config_usage_status, record_config_usage, export_relay_stations, import_relay_stations,
-    RelayStationManager,
+    cancel_relay_request, RelayStationManager,
+};
+use commands::relay_probe::probe_station;
+use commands::relay_reconcile::reconcile_station_usage;
+use commands::relay_forecast::{forecast_all_balances, forecast_balance};
+use commands::relay_log_stats::aggregate_station_logs;
+use commands::relay_log_export::export_station_logs;
+use commands::relay_compare::compare_stations;
+use commands::relay_offline::{get_offline_settings, set_offline_settings};
+use commands::relay_renewals::{
+    get_renewal_settings, get_station_renewal, list_expiring_items, set_renewal_settings,
+    set_station_renewal,
+};
+use commands::relay_share::{import_station_share, preview_station_share, share_relay_station};
+use commands::relay_token_quota::{get_token_quota_settings, set_token_quota_settings};
+use commands::relay_rate_usage::{
+    get_token_rate_settings, get_token_rate_usage, set_token_rate_settings,
+};
+use commands::app_backup::{backup_app_data, preview_app_restore, restore_app_data};
+use commands::app_lock::{get_app_lock_status, lock_app, set_app_lock_settings, unlock_app};
+use commands::config_export::export_tool_configs;
+use commands::ccusage::export_ccusage_json;
+use commands::legacy_import::{detect_legacy_installs, import_legacy_data};
+use commands::relay_log_cache::{
+    clear_station_log_cache, get_log_cache_status, query_cached_station_logs, search_station_logs,
+    sync_station_logs,
+};
+use commands::task_chains::{
+    cancel_task_chain_run, create_task_chain, delete_task_chain, list_task_chain_runs,
+    list_task_chains, preview_task_chain_schedule, run_task_chain, set_task_chain_enabled,
+    start_task_chain_scheduler, update_task_chain,
+    TaskChainState,
 };
 use process::ProcessRegistryState;
 use std::sync::Mutex;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 fn main() {
-    // Initialize logger
-    env_logger::init();
+    // Initialize logging to stderr and the rotated log files
+    logging::init();
+    if let Some(dir) = portable::portable_dir() {
+        log::info!("Portable mode: data is stored in {:?}", dir);
+    }
+
+    // Scripts and CI: run a backend operation without the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = headless::run_from_args(&args) {
+        std::process::exit(code);
+    }
+
+    // A link opened while the app runs starts a second instance; pass it on
+    let startup_links = deep_link::links_in_args(args);
+    if !startup_links.is_empty() && deep_link::forward_to_running_instance(&startup_links) {
+        return;
+    }
 
 
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-        .setup(|app| {
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .setup(move |app| {
             // Initialize agents database
             let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
+            load_log_level(&conn);
+            load_locale(&conn);
+            commands::plugins::init(app.handle(), &conn);
             app.manage(AgentDb(Mutex::new(conn)));
 
             // Initialize relay station manager with shared agents database
-            let db_path = app.handle()
-                .path()
-                .app_data_dir()
+            let db_path = portable::app_data_dir(app.handle())
                 .unwrap()
                 .join("agents.db");
             
@@ -97,6 +187,8 @@ fn main() {
                 .expect("Failed to initialize relay station manager");
             
             app.manage(Mutex::new(Some(relay_manager)));
+            app.manage(commands::relay_adapters::RelayRequestRegistry::default());
+            app.manage(commands::relay_log_stream::LogStreamRegistry::default());
 
             // Initialize checkpoint state
             let checkpoint_state = CheckpointState::new();
@@ -120,14 +212,84 @@ fn main() {
             app.manage(checkpoint_state);
 
             // Initialize process registry
-            app.manage(ProcessRegistryState::default());
+            let process_registry = ProcessRegistryState::default();
+            process_registry.0.set_app_handle(app.handle().clone());
+            {
+                let db = app.state::<AgentDb>();
+                if let Ok(conn) = db.0.lock() {
+                    load_process_settings(&conn, &process_registry);
+                }
+            }
+            // Persist registry entries and look for processes left by a previous crash
+            if let Err(e) = process_registry.0.enable_persistence(&db_path) {
+                log::warn!("Failed to enable process persistence: {}", e);
+            }
+            match process_registry.0.scan_orphans() {
+                Ok(orphans) if !orphans.is_empty() => {
+                    let _ = app.emit("orphaned-processes-detected", &orphans);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to scan for orphaned processes: {}", e),
+            }
+            process_registry.0.start_resource_watchdog();
+            app.manage(process_registry);
+
+            app.manage(std::sync::Arc::new(process::pty::PtySessions::default()));
+
+            // Initialize task chains and their cron scheduler
+            app.manage(TaskChainState::default());
+            start_task_chain_scheduler(app.handle().clone());
+
+            // Size and position the main window had when last closed
+            windows::restore_main_window(app.handle());
+
+            // Tray menu for switching providers and relay stations
+            if let Err(e) = tray::setup(app.handle()) {
+                log::warn!("Failed to create the system tray icon: {}", e);
+            }
+
+            // claude-workbench:// links
+            app.manage(deep_link::DeepLinkState::default());
+            if let Err(e) = deep_link::listen(app.handle()) {
+                log::warn!("Failed to listen for forwarded deep links: {}", e);
+            }
+            std::thread::spawn(|| {
+                if let Err(e) = deep_link::register_scheme() {
+                    log::warn!("Failed to register the {} URL scheme: {}", deep_link::SCHEME, e);
+                }
+            });
+            for url in &startup_links {
+                deep_link::handle(app.handle(), url);
+            }
+
+            // App lock for shared machines, off by default
+            app.manage(commands::app_lock::AppLockState::default());
+
+            // Local REST API for editors and scripts, off by default
+            app.manage(commands::control_api::ControlApiState::default());
+            commands::control_api::start_control_api(app.handle().clone());
+
+            // Local Anthropic API proxy following provider switches, off by default
+            app.manage(commands::local_proxy::LocalProxyState::default());
+            commands::local_proxy::start_local_proxy(app.handle().clone());
+
+            // Cost alert rules and usage quotas, checked every few minutes
+            commands::cost_alerts::start_cost_alert_monitor(app.handle().clone());
+            commands::usage_quotas::start_usage_quota_monitor(app.handle().clone());
 
-            // Initialize Claude process state
-            app.manage(ClaudeProcessState::default());
+            // Display currency for costs, with exchange rates refreshed daily
+            commands::currency::start_rate_refresher(app.handle().clone());
+
+            // Reminders before station tokens expire and subscriptions renew
+            commands::relay_renewals::start_renewal_monitor(app.handle().clone());
+
+            // `station_down` webhooks for the station in use
+            commands::webhooks::start_station_health_monitor(app.handle().clone());
 
 
             Ok(())
         })
+        .on_window_event(windows::on_window_event)
         .invoke_handler(tauri::generate_handler![
             // Claude & Project Management
             list_projects,
@@ -141,6 +303,8 @@ fn main() {
             check_claude_version,
             save_system_prompt,
             save_claude_settings,
+            commands::settings_schema::validate_claude_settings,
+            commands::settings_schema::repair_claude_settings,
             find_claude_md_files,
             read_claude_md_file,
             save_claude_md_file,
@@ -149,8 +313,42 @@ fn main() {
             continue_claude_code,
             resume_claude_code,
             cancel_claude_execution,
+            start_interactive_claude_session,
+            write_claude_pty,
+            resize_claude_pty,
+            get_claude_pty_scrollback,
+            close_claude_pty,
             list_running_claude_sessions,
             get_claude_session_output,
+            list_managed_processes,
+            get_process_output,
+            get_shutdown_grace_period,
+            set_shutdown_grace_period,
+            set_session_restart_policy,
+            get_session_restart_policy,
+            get_concurrency_limits,
+            set_concurrency_limits,
+            get_run_queue,
+            remove_queued_run,
+            move_queued_run,
+            list_orphaned_processes,
+            adopt_or_kill_orphans,
+            get_run_history,
+            scan_external_claude_processes,
+            adopt_external_process,
+            suspend_process,
+            resume_process,
+            get_resource_thresholds,
+            set_resource_thresholds,
+            list_task_chains,
+            create_task_chain,
+            update_task_chain,
+            delete_task_chain,
+            run_task_chain,
+            cancel_task_chain_run,
+            list_task_chain_runs,
+            set_task_chain_enabled,
+            preview_task_chain_schedule,
             list_directory_contents,
             search_files,
             get_recently_modified_files,
@@ -160,20 +358,46 @@ fn main() {
             set_custom_claude_path,
             get_claude_path,
             clear_custom_claude_path,
+            list_claude_versions,
+            list_available_claude_versions,
+            install_claude_version,
+            uninstall_claude_version,
+            switch_claude_version,
+            get_project_claude_version,
+            set_project_claude_version,
+            update_claude_binary,
+            get_claude_capabilities,
+            refresh_claude_binaries,
+            get_claude_runtime,
+            install_claude_runtime,
+            remove_claude_runtime,
             enhance_prompt,
             
             // Checkpoint Management
             create_checkpoint,
+            create_checkpoint_in_background,
             restore_checkpoint,
             list_checkpoints,
             fork_from_checkpoint,
+            create_checkpoint_branch,
+            list_checkpoint_branches,
+            switch_checkpoint_branch,
             get_session_timeline,
+            get_project_timeline,
             update_checkpoint_settings,
             get_checkpoint_diff,
             track_checkpoint_message,
             track_session_messages,
             check_auto_checkpoint,
             cleanup_old_checkpoints,
+            get_checkpoint_retention_policy,
+            set_checkpoint_retention_policy,
+            prune_checkpoints,
+            search_checkpoints,
+            get_checkpoint_ignore,
+            save_checkpoint_ignore,
+            export_checkpoint_archive,
+            import_checkpoint_archive,
             get_checkpoint_settings,
             clear_checkpoint_manager,
             get_checkpoint_state_stats,
@@ -188,6 +412,7 @@ fn main() {
             list_agent_runs,
             get_agent_run,
             list_agent_runs_with_metrics,
+            list_agent_run_history,
             get_agent_run_with_real_time_metrics,
             list_running_sessions,
             kill_agent_session,
@@ -217,6 +442,11 @@ fn main() {
             get_session_stats,
             get_active_sessions,
             get_burn_rate_analysis,
+            get_usage_series,
+            commands::currency::get_currency_settings,
+            commands::currency::set_currency_settings,
+            commands::currency::get_currency_conversion,
+            commands::currency::refresh_exchange_rates,
             
             // MCP (Model Context Protocol)
             mcp_add,
@@ -231,6 +461,15 @@ fn main() {
             mcp_get_server_status,
             mcp_read_project_config,
             mcp_save_project_config,
+            mcp_config_list,
+            mcp_config_validate,
+            mcp_config_add,
+            mcp_config_update,
+            mcp_config_set_enabled,
+            mcp_config_remove,
+            test_mcp_server,
+            mcp_import_claude_desktop,
+            mcp_import_json,
 
             
             // Storage Management
@@ -242,11 +481,113 @@ fn main() {
             storage_execute_sql,
             storage_reset_database,
             
+            // Session Browser
+            commands::sessions::list_sessions,
+            commands::sessions::rebuild_session_index,
+            commands::sessions::get_session_messages,
+            
+            // Project Registry
+            commands::projects::list_project_registry,
+            commands::projects::update_project_meta,
+            
+            // Dashboard
+            commands::dashboard::get_dashboard_snapshot,
+            
+            // Quick Switcher
+            commands::quick_search::quick_search,
+            commands::quick_search::record_quick_search_pick,
+            
+            // System Tray
+            tray::refresh_tray_menu,
+            
+            // Windows
+            windows::open_session_window,
+            windows::open_station_window,
+            windows::list_app_windows,
+            
+            // Deep Links
+            deep_link::take_deep_link_project,
+            deep_link::confirm_deep_link,
+            deep_link::list_pending_deep_links,
+            
+            // Control API
+            commands::control_api::get_control_api_settings,
+            commands::control_api::set_control_api_settings,
+            commands::control_api::regenerate_control_api_token,
+            commands::control_api::get_control_api_status,
+            
+            // Local Proxy
+            commands::local_proxy::get_local_proxy_settings,
+            commands::local_proxy::set_local_proxy_settings,
+            commands::local_proxy::get_local_proxy_status,
+            commands::proxy_log::list_proxy_requests,
+            commands::proxy_log::get_proxy_usage_summary,
+            commands::proxy_log::clear_proxy_requests,
+            
+            // Plugins
+            commands::plugins::list_plugins,
+            commands::plugins::install_plugin,
+            commands::plugins::uninstall_plugin,
+            commands::plugins::set_plugin_enabled,
+            commands::plugins::bind_station_plugin,
+            commands::plugins::invoke_plugin_command,
+            
+            // Notifications
+            commands::notifications::get_notification_settings,
+            commands::notifications::set_notification_settings,
+            commands::notifications::send_test_notification,
+            commands::webhooks::get_webhook_settings,
+            commands::webhooks::set_webhook_settings,
+            commands::webhooks::test_webhook_target,
+            commands::cost_alerts::list_cost_alert_rules,
+            commands::cost_alerts::create_cost_alert_rule,
+            commands::cost_alerts::update_cost_alert_rule,
+            commands::cost_alerts::delete_cost_alert_rule,
+            commands::cost_alerts::evaluate_cost_alerts,
+            commands::usage_quotas::list_usage_quotas,
+            commands::usage_quotas::create_usage_quota,
+            commands::usage_quotas::update_usage_quota,
+            commands::usage_quotas::delete_usage_quota,
+            commands::usage_quotas::evaluate_usage_quotas,
+            
             // Slash Commands
             commands::slash_commands::slash_commands_list,
             commands::slash_commands::slash_command_get,
             commands::slash_commands::slash_command_save,
+            commands::slash_commands::slash_command_namespaces,
             commands::slash_commands::slash_command_delete,
+            
+            // Memory Files
+            commands::memory::memory_files_list,
+            commands::memory::memory_file_read,
+            commands::memory::memory_file_save,
+            commands::memory::memory_backups_list,
+            commands::memory::memory_backup_read,
+            commands::memory::memory_backup_restore,
+            
+            // Hooks
+            commands::hooks::hooks_list,
+            commands::hooks::hook_rule_save,
+            commands::hooks::hook_rule_delete,
+            commands::hooks::hooks_dry_run,
+            commands::permissions::permissions_list,
+            commands::permissions::permissions_save,
+            commands::permissions::permission_rule_validate,
+            commands::permissions::permission_presets_list,
+            commands::permissions::permission_preset_apply,
+            
+            // Subagents
+            commands::subagents::subagents_list,
+            commands::subagents::subagent_save,
+            commands::subagents::subagent_delete,
+            
+            // Output Styles
+            commands::output_styles::output_styles_list,
+            commands::output_styles::output_style_get_active,
+            commands::output_styles::output_style_save,
+            commands::output_styles::output_style_delete,
+            commands::output_styles::output_style_activate,
+            
             // Clipboard
             save_clipboard_image,
             
@@ -261,12 +602,37 @@ fn main() {
             update_provider_config,
             delete_provider_config,
             get_provider_config,
+            get_provider_usage_timeline,
+            reveal_current_secret,
+            probe_provider_endpoints,
+            rotate_provider_endpoint,
+            get_resume_sessions_on_switch,
+            set_resume_sessions_on_switch,
             
             // App Information
             get_app_version,
             get_database_path,
             get_app_info,
             check_for_updates,
+            verify_update_artifact,
+            install_update,
+            restart_app,
+            get_update_channel,
+            set_update_channel,
+            get_network_proxy,
+            set_network_proxy,
+            get_release_history,
+            
+            // Environment Doctor
+            environment_doctor,
+            get_locale,
+            set_locale,
+            get_app_logs,
+            get_log_level,
+            set_log_level,
+            commands::ui_settings::get_app_setting,
+            commands::ui_settings::set_app_setting,
+            commands::ui_settings::watch_app_settings,
             
             // Relay Station Management
             list_relay_stations,
@@ -282,7 +648,9 @@ fn main() {
             get_token_user_info,
             get_station_logs,
             test_station_connection,
+            probe_station,
             api_user_self_groups,
+            suggest_token_group,
             toggle_station_token,
             load_station_api_endpoints,
             save_station_config,
@@ -291,7 +659,60 @@ fn main() {
             record_config_usage,
             export_relay_stations,
             import_relay_stations,
+            cancel_relay_request,
+            reconcile_station_usage,
+            forecast_balance,
+            forecast_all_balances,
+            aggregate_station_logs,
+            sync_station_logs,
+            query_cached_station_logs,
+            search_station_logs,
+            export_station_logs,
+            get_log_cache_status,
+            clear_station_log_cache,
+            commands::relay_log_stream::stream_cached_station_logs,
+            commands::relay_log_stream::ack_log_stream,
+            compare_stations,
+            get_renewal_settings,
+            set_renewal_settings,
+            get_station_renewal,
+            set_station_renewal,
+            list_expiring_items,
+            get_token_quota_settings,
+            set_token_quota_settings,
+            get_token_rate_usage,
+            get_token_rate_settings,
+            set_token_rate_settings,
+            share_relay_station,
+            preview_station_share,
+            import_station_share,
+            detect_legacy_installs,
+            import_legacy_data,
+            get_app_lock_status,
+            set_app_lock_settings,
+            unlock_app,
+            lock_app,
+            get_offline_settings,
+            set_offline_settings,
+            export_tool_configs,
+            export_ccusage_json,
+            backup_app_data,
+            preview_app_restore,
+            restore_app_data,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            if let tauri::RunEvent::ExitRequested { .. } = _event {
+                windows::persist_all(_app);
+            }
+
+            // macOS hands links to the running app instead of the command line
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                for url in urls {
+                    deep_link::handle(_app, url.as_str());
+                }
+            }
+        });
 }
@@ -0,0 +1,621 @@
+//! Direct editing of MCP server entries in Claude Code's config files
+//!
+//! - user scope: `mcpServers` in `~/.claude.json`
+//! - local scope: `projects.<path>.mcpServers` in `~/.claude.json`
+//! - project scope: `mcpServers` in `<project>/.mcp.json`
+//!
+//! Claude Code has no per-entry "disabled" flag, so disabling a server moves
+//! its entry into a stash file in the app data directory until it is enabled.
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Stash of disabled servers, inside the app data directory
+const DISABLED_SERVERS_FILE: &str = "mcp-disabled.json";
+
+/// Serializes our own read-modify-write cycles on the config files
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpScope {
+    /// Every project of the current user
+    User,
+    /// One project, private to the current user
+    Local,
+    /// One project, shared through `.mcp.json`
+    Project,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransport {
+    #[default]
+    Stdio,
+    Sse,
+    Http,
+}
+
+/// One entry of an `mcpServers` object
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpServerEntry {
+    #[serde(rename = "type", default)]
+    pub transport: McpTransport,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Fields this app does not know about, kept as they are
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A configured server and where it lives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerRecord {
+    pub name: String,
+    pub scope: McpScope,
+    /// Project the entry belongs to (local and project scope)
+    pub project_path: Option<String>,
+    pub enabled: bool,
+    pub config: McpServerEntry,
+}
+
+/// Validate a raw server entry against the MCP config schema
+///
+/// Returns every problem found so the UI can show them together.
+pub fn validate_server_value(value: &Value) -> Result<McpServerEntry, Vec<String>> {
+    let Some(object) = value.as_object() else {
+        return Err(vec!["Server config must be a JSON object".to_string()]);
+    };
+    let mut errors = Vec::new();
+
+    // Absent optional fields are valid
+    let is_string = |field: &str| object.get(field).map(Value::is_string).unwrap_or(true);
+    let is_string_array = |field: &str| {
+        object
+            .get(field)
+            .map(|value| {
+                value
+                    .as_array()
+                    .is_some_and(|items| items.iter().all(Value::is_string))
+            })
+            .unwrap_or(true)
+    };
+    let is_string_map = |field: &str| {
+        object
+            .get(field)
+            .map(|value| {
+                value
+                    .as_object()
+                    .is_some_and(|map| map.values().all(Value::is_string))
+            })
+            .unwrap_or(true)
+    };
+
+    let transport = match object.get("type") {
+        None => Some(McpTransport::Stdio),
+        Some(value) => match value.as_str() {
+            Some("stdio") => Some(McpTransport::Stdio),
+            Some("sse") => Some(McpTransport::Sse),
+            Some("http") => Some(McpTransport::Http),
+            _ => {
+                errors.push(format!(
+                    "type must be \"stdio\", \"sse\" or \"http\", got {}",
+                    value
+                ));
+                None
+            }
+        },
+    };
+    for field in ["command", "url"] {
+        if !is_string(field) {
+            errors.push(format!("{} must be a string", field));
+        }
+    }
+    if !is_string_array("args") {
+        errors.push("args must be an array of strings".to_string());
+    }
+    for field in ["env", "headers"] {
+        if !is_string_map(field) {
+            errors.push(format!("{} must be an object with string values", field));
+        }
+    }
+
+    let text = |field: &str| {
+        object
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    };
+    match transport {
+        Some(McpTransport::Stdio) => {
+            if text("command").is_none() {
+                errors.push("command is required for stdio servers".to_string());
+            }
+            if object.contains_key("url") {
+                errors.push("url is only used by sse and http servers".to_string());
+            }
+        }
+        Some(transport) => match text("url") {
+            None => errors.push(format!(
+                "url is required for {} servers",
+                if transport == McpTransport::Sse {
+                    "sse"
+                } else {
+                    "http"
+                }
+            )),
+            Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => errors
+                .push(format!(
+                    "url must start with http:// or https://, got {}",
+                    url
+                )),
+            Some(_) => {}
+        },
+        None => {}
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    serde_json::from_value(value.clone()).map_err(|e| vec![e.to_string()])
+}
+
+/// Server names become CLI arguments and tool prefixes, so keep them simple
+pub fn validate_server_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Server name must not be empty".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Server name {:?} may only contain letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Disabled entry as kept in the stash file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DisabledServer {
+    name: String,
+    scope: McpScope,
+    project_path: Option<String>,
+    config: Value,
+}
+
+/// Compare project paths the way they are written as keys by different tools
+fn same_project(a: &str, b: &str) -> bool {
+    let normalize = |path: &str| {
+        let path = path.replace('\\', "/");
+        let path = path.trim_end_matches('/').to_string();
+        if cfg!(windows) {
+            path.to_lowercase()
+        } else {
+            path
+        }
+    };
+    normalize(a) == normalize(b)
+}
+
+pub(crate) fn read_json(path: &Path) -> Result<Value, String> {
+    match fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(Value::Object(Map::new())),
+        // Never rewrite a file we could not parse, the user would lose it
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Value::Object(Map::new())),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write through a temporary file so the Claude CLI never reads half a file
+pub(crate) fn write_json(path: &Path, value: &Value) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn object_entry<'a>(parent: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = parent
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value
+        .as_object_mut()
+        .expect("value was just made an object")
+}
+
+/// Locations of the files this module edits
+#[derive(Debug, Clone)]
+pub struct McpConfigStore {
+    /// `~/.claude.json`
+    pub claude_json: PathBuf,
+    /// Stash of disabled servers
+    pub disabled_servers: PathBuf,
+}
+
+impl McpConfigStore {
+    /// The real config files of the current user
+    pub fn for_current_user() -> Result<Self, String> {
+        let home = dirs::home_dir().ok_or("Could not find home directory")?;
+        let data_dir = crate::portable::data_dir().ok_or("Failed to get app data directory")?;
+        Ok(Self {
+            claude_json: home.join(".claude.json"),
+            disabled_servers: data_dir.join(DISABLED_SERVERS_FILE),
+        })
+    }
+
+    fn config_path(&self, scope: McpScope, project_path: Option<&str>) -> Result<PathBuf, String> {
+        match scope {
+            McpScope::User | McpScope::Local => Ok(self.claude_json.clone()),
+            McpScope::Project => project_path
+                .map(|project| Path::new(project).join(".mcp.json"))
+                .ok_or_else(|| "Project scope needs a project path".to_string()),
+        }
+    }
+
+    /// The `mcpServers` object of a scope inside its parsed config file
+    fn servers_mut<'a>(
+        root: &'a mut Value,
+        scope: McpScope,
+        project_path: Option<&str>,
+    ) -> Result<&'a mut Map<String, Value>, String> {
+        if !root.is_object() {
+            return Err("Config file does not contain a JSON object".to_string());
+        }
+        let root = root.as_object_mut().expect("checked above");
+        match scope {
+            McpScope::User | McpScope::Project => Ok(object_entry(root, "mcpServers")),
+            McpScope::Local => {
+                let project = project_path.ok_or("Local scope needs a project path")?;
+                let projects = object_entry(root, "projects");
+                let key = projects
+                    .keys()
+                    .find(|key| same_project(key, project))
+                    .cloned()
+                    .unwrap_or_else(|| project.to_string());
+                Ok(object_entry(object_entry(projects, &key), "mcpServers"))
+            }
+        }
+    }
+
+    fn read_disabled(&self) -> Result<Vec<DisabledServer>, String> {
+        match read_json(&self.disabled_servers)? {
+            Value::Array(items) => Ok(items
+                .into_iter()
+                .filter_map(|item| serde_json::from_value(item).ok())
+                .collect()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn write_disabled(&self, disabled: &[DisabledServer]) -> Result<(), String> {
+        let value = serde_json::to_value(disabled).map_err(|e| e.to_string())?;
+        write_json(&self.disabled_servers, &value)
+    }
+
+    fn is_disabled_entry(
+        entry: &DisabledServer,
+        scope: McpScope,
+        project_path: Option<&str>,
+        name: &str,
+    ) -> bool {
+        entry.name == name
+            && entry.scope == scope
+            && (scope == McpScope::User
+                || matches!(
+                    (entry.project_path.as_deref(), project_path),
+                    (Some(a), Some(b)) if same_project(a, b)
+                ))
+    }
+
+    /// Servers of every scope visible from `project_path`, enabled ones first
+    pub fn list(&self, project_path: Option<&str>) -> Result<Vec<McpServerRecord>, String> {
+        let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut scopes = vec![McpScope::User];
+        if project_path.is_some() {
+            scopes.extend([McpScope::Local, McpScope::Project]);
+        }
+
+        let mut records = Vec::new();
+        for scope in scopes {
+            let mut root = read_json(&self.config_path(scope, project_path)?)?;
+            let project = match scope {
+                McpScope::User => None,
+                _ => project_path.map(str::to_string),
+            };
+            for (name, value) in Self::servers_mut(&mut root, scope, project_path)?.iter() {
+                match serde_json::from_value::<McpServerEntry>(value.clone()) {
+                    Ok(config) => records.push(McpServerRecord {
+                        name: name.clone(),
+                        scope,
+                        project_path: project.clone(),
+                        enabled: true,
+                        config,
+                    }),
+                    Err(e) => log::warn!("Skipping invalid MCP server {}: {}", name, e),
+                }
+            }
+        }
+
+        for entry in self.read_disabled()? {
+            let visible = entry.scope == McpScope::User
+                || matches!(
+                    (entry.project_path.as_deref(), project_path),
+                    (Some(a), Some(b)) if same_project(a, b)
+                );
+            if !visible {
+                continue;
+            }
+            if let Ok(config) = serde_json::from_value(entry.config) {
+                records.push(McpServerRecord {
+                    name: entry.name,
+                    scope: entry.scope,
+                    project_path: entry.project_path,
+                    enabled: false,
+                    config,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    /// Add a server, or replace an existing one when `replace` is set
+    pub fn save(
+        &self,
+        scope: McpScope,
+        project_path: Option<&str>,
+        name: &str,
+        config: &Value,
+        replace: bool,
+    ) -> Result<McpServerRecord, String> {
+        validate_server_name(name)?;
+        let entry = validate_server_value(config).map_err(|errors| errors.join("; "))?;
+        let value = serde_json::to_value(&entry).map_err(|e| e.to_string())?;
+        let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+
+        // Edits of a disabled server stay in the stash
+        let mut disabled = self.read_disabled()?;
+        if let Some(stashed) = disabled
+            .iter_mut()
+            .find(|entry| Self::is_disabled_entry(entry, scope, project_path, name))
+        {
+            if !replace {
+                return Err(format!("MCP server {} already exists (disabled)", name));
+            }
+            stashed.config = value;
+            self.write_disabled(&disabled)?;
+            return Ok(McpServerRecord {
+                name: name.to_string(),
+                scope,
+                project_path: project_path.map(str::to_string),
+                enabled: false,
+                config: entry,
+            });
+        }
+
+        let path = self.config_path(scope, project_path)?;
+        let mut root = read_json(&path)?;
+        let servers = Self::servers_mut(&mut root, scope, project_path)?;
+        match (servers.contains_key(name), replace) {
+            (true, false) => return Err(format!("MCP server {} already exists", name)),
+            (false, true) => return Err(format!("MCP server {} not found", name)),
+            _ => {}
+        }
+        servers.insert(name.to_string(), value);
+        write_json(&path, &root)?;
+        log::info!("Saved MCP server {} ({:?} scope)", name, scope);
+
+        Ok(McpServerRecord {
+            name: name.to_string(),
+            scope,
+            project_path: project_path.map(str::to_string),
+            enabled: true,
+            config: entry,
+        })
+    }
+
+    /// Delete a server, enabled or not
+    pub fn remove(
+        &self,
+        scope: McpScope,
+        project_path: Option<&str>,
+        name: &str,
+    ) -> Result<(), String> {
+        let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+
+        let mut disabled = self.read_disabled()?;
+        let before = disabled.len();
+        disabled.retain(|entry| !Self::is_disabled_entry(entry, scope, project_path, name));
+        if disabled.len() != before {
+            return self.write_disabled(&disabled);
+        }
+
+        let path = self.config_path(scope, project_path)?;
+        let mut root = read_json(&path)?;
+        if Self::servers_mut(&mut root, scope, project_path)?
+            .remove(name)
+            .is_none()
+        {
+            return Err(format!("MCP server {} not found", name));
+        }
+        write_json(&path, &root)?;
+        log::info!("Removed MCP server {} ({:?} scope)", name, scope);
+        Ok(())
+    }
+
+    /// Move a server between its config file and the disabled stash
+    pub fn set_enabled(
+        &self,
+        scope: McpScope,
+        project_path: Option<&str>,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), String> {
+        let _lock = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+        let path = self.config_path(scope, project_path)?;
+        let mut root = read_json(&path)?;
+        let mut disabled = self.read_disabled()?;
+        let stashed = disabled
+            .iter()
+            .position(|entry| Self::is_disabled_entry(entry, scope, project_path, name));
+        let servers = Self::servers_mut(&mut root, scope, project_path)?;
+
+        match (enabled, stashed) {
+            (true, Some(index)) => {
+                if servers.contains_key(name) {
+                    return Err(format!(
+                        "Cannot enable {}: a server with that name was added meanwhile",
+                        name
+                    ));
+                }
+                let entry = disabled.remove(index);
+                servers.insert(name.to_string(), entry.config);
+                // Restore the server before dropping it from the stash
+                write_json(&path, &root)?;
+                self.write_disabled(&disabled)?;
+            }
+            (false, None) => {
+                let config = servers
+                    .remove(name)
+                    .ok_or_else(|| format!("MCP server {} not found", name))?;
+                disabled.push(DisabledServer {
+                    name: name.to_string(),
+                    scope,
+                    project_path: project_path.map(str::to_string),
+                    config,
+                });
+                self.write_disabled(&disabled)?;
+                write_json(&path, &root)?;
+            }
+            (true, None) | (false, Some(_)) => {
+                // Already in the requested state
+                return Ok(());
+            }
+        }
+        log::info!(
+            "{} MCP server {} ({:?} scope)",
+            if enabled { "Enabled" } else { "Disabled" },
+            name,
+            scope
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_server_value() {
+        let entry = validate_server_value(&json!({
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-memory"],
+            "timeout": 30
+        }))
+        .unwrap();
+        assert_eq!(entry.transport, McpTransport::Stdio);
+        assert_eq!(entry.extra.get("timeout"), Some(&json!(30)));
+
+        assert!(validate_server_value(&json!({
+            "type": "http",
+            "url": "https://mcp.example.com/mcp",
+            "headers": {"Authorization": "Bearer token"}
+        }))
+        .is_ok());
+
+        let errors = validate_server_value(&json!({
+            "type": "sse",
+            "url": "mcp.example.com",
+            "args": [1],
+            "env": {"DEBUG": true}
+        }))
+        .unwrap_err();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(validate_server_value(&json!({"type": "websocket"})).is_err());
+        assert!(validate_server_name("github").is_ok());
+        assert!(validate_server_name("my server").is_err());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        let project = project.to_str().unwrap();
+        let store = McpConfigStore {
+            claude_json: dir.path().join(".claude.json"),
+            disabled_servers: dir.path().join("data").join(DISABLED_SERVERS_FILE),
+        };
+        fs::write(&store.claude_json, r#"{"numStartups": 3}"#).unwrap();
+
+        let memory = json!({"command": "npx", "args": ["server-memory"]});
+        store
+            .save(McpScope::User, None, "memory", &memory, false)
+            .unwrap();
+        assert!(store
+            .save(McpScope::User, None, "memory", &memory, false)
+            .is_err());
+        store
+            .save(
+                McpScope::Local,
+                Some(project),
+                "docs",
+                &json!({"type": "sse", "url": "http://localhost:3000/sse"}),
+                false,
+            )
+            .unwrap();
+
+        let root = read_json(&store.claude_json).unwrap();
+        assert_eq!(root["numStartups"], json!(3));
+        assert_eq!(
+            root["projects"][project]["mcpServers"]["docs"]["type"],
+            json!("sse")
+        );
+        assert_eq!(store.list(None).unwrap().len(), 1);
+        assert_eq!(store.list(Some(project)).unwrap().len(), 2);
+
+        store
+            .set_enabled(McpScope::User, None, "memory", false)
+            .unwrap();
+        assert!(read_json(&store.claude_json).unwrap()["mcpServers"]
+            .get("memory")
+            .is_none());
+        let records = store.list(None).unwrap();
+        assert!(!records[0].enabled);
+
+        store
+            .set_enabled(McpScope::User, None, "memory", true)
+            .unwrap();
+        assert!(store.list(None).unwrap()[0].enabled);
+
+        store.remove(McpScope::User, None, "memory").unwrap();
+        assert!(store.remove(McpScope::User, None, "memory").is_err());
+        assert!(store.list(None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! Health check for a configured MCP server: start or connect to it, run the
+//! MCP initialize handshake and list the tools it advertises
+use super::config::{McpServerEntry, McpTransport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// Protocol revision sent in `initialize`; servers answer with the one they speak
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Upper bound for the whole check, including `npx` downloading the server
+const TEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Keep at most this much stderr of a stdio server for the report
+const MAX_STDERR_LEN: usize = 4000;
+
+const INITIALIZE_ID: u64 = 1;
+const TOOLS_LIST_ID: u64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Outcome of `test_server`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpTestResult {
+    pub success: bool,
+    pub protocol_version: Option<String>,
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub tools: Vec<McpToolInfo>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+    /// Tail of the server's stderr (stdio servers)
+    pub stderr: Option<String>,
+}
+
+fn initialize_request() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": INITIALIZE_ID,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {
+                "name": "claude-workbench",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        },
+    })
+}
+
+fn initialized_notification() -> Value {
+    json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+}
+
+fn tools_list_request() -> Value {
+    json!({"jsonrpc": "2.0", "id": TOOLS_LIST_ID, "method": "tools/list", "params": {}})
+}
+
+fn is_response_to(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(Value::as_u64) == Some(id)
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+fn rpc_result<'a>(response: &'a Value, method: &str) -> Result<&'a Value, String> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(format!("{} failed: {}", method, message));
+    }
+    response
+        .get("result")
+        .ok_or_else(|| format!("{} response has no result", method))
+}
+
+/// Record the server details from the initialize response; returns whether
+/// the server advertises tools
+fn apply_initialize(result: &mut McpTestResult, response: &Value) -> Result<bool, String> {
+    let init = rpc_result(response, "initialize")?;
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    result.protocol_version = text(&init["protocolVersion"]);
+    result.server_name = text(&init["serverInfo"]["name"]);
+    result.server_version = text(&init["serverInfo"]["version"]);
+    Ok(init["capabilities"].get("tools").is_some())
+}
+
+fn parse_tools(response: &Value) -> Result<Vec<McpToolInfo>, String> {
+    let tools = rpc_result(response, "tools/list")?;
+    Ok(tools["tools"]
+        .as_array()
+        .map(|tools| {
+            tools
+                .iter()
+                .filter_map(|tool| {
+                    Some(McpToolInfo {
+                        name: tool.get("name")?.as_str()?.to_string(),
+                        description: tool
+                            .get("description")
+                            .and_then(Value::as_str)
+                            .map(str::to_string),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Start or connect to the server described by `entry` and run the handshake
+pub async fn test_server(entry: &McpServerEntry) -> McpTestResult {
+    let started = Instant::now();
+    let mut result = McpTestResult::default();
+    let outcome = match tokio::time::timeout(TEST_TIMEOUT, run_test(entry, &mut result)).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(format!(
+            "Server did not answer within {} seconds",
+            TEST_TIMEOUT.as_secs()
+        )),
+    };
+
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(()) => result.success = true,
+        Err(e) => {
+            log::warn!("MCP server test failed: {}", e);
+            result.error = Some(e);
+        }
+    }
+    result
+}
+
+async fn run_test(entry: &McpServerEntry, result: &mut McpTestResult) -> Result<(), String> {
+    match entry.transport {
+        McpTransport::Stdio => test_stdio(entry, result).await,
+        McpTransport::Http => test_http(entry, result).await,
+        McpTransport::Sse => test_sse(entry, result).await,
+    }
+}
+
+fn stdio_command(entry: &McpServerEntry) -> Result<std::process::Command, String> {
+    let command = entry
+        .command
+        .as_deref()
+        .ok_or("command is required for stdio servers")?;
+
+    // npx, uvx and friends are batch files on Windows and need cmd to start
+    #[cfg(target_os = "windows")]
+    let mut cmd = if command.to_lowercase().ends_with(".exe") {
+        let mut cmd = crate::claude_binary::create_command_with_env(command);
+        cmd.args(&entry.args);
+        cmd
+    } else {
+        let mut cmd = crate::claude_binary::create_command_with_env("cmd");
+        cmd.arg("/C").arg(command).args(&entry.args);
+        cmd
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut cmd = crate::claude_binary::create_command_with_env(command);
+        cmd.args(&entry.args);
+        cmd
+    };
+
+    cmd.envs(&entry.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    Ok(cmd)
+}
+
+async fn test_stdio(entry: &McpServerEntry, result: &mut McpTestResult) -> Result<(), String> {
+    let mut cmd = tokio::process::Command::from(stdio_command(entry)?);
+    cmd.kill_on_drop(true);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start MCP server: {}", e))?;
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open server stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to open server stdout")?;
+    let mut stderr = child.stderr.take().ok_or("Failed to open server stderr")?;
+    let stderr_task = tokio::spawn(async move {
+        let mut output = Vec::new();
+        let _ = stderr.read_to_end(&mut output).await;
+        String::from_utf8_lossy(&output).to_string()
+    });
+
+    let mut lines = BufReader::new(stdout).lines();
+    let handshake = async {
+        write_message(&mut stdin, &initialize_request()).await?;
+        let response = read_stdio_response(&mut lines, INITIALIZE_ID).await?;
+        let has_tools = apply_initialize(result, &response)?;
+        write_message(&mut stdin, &initialized_notification()).await?;
+        if has_tools {
+            write_message(&mut stdin, &tools_list_request()).await?;
+            let response = read_stdio_response(&mut lines, TOOLS_LIST_ID).await?;
+            result.tools = parse_tools(&response)?;
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+
+    let _ = child.kill().await;
+    if let Ok(Ok(stderr)) = tokio::time::timeout(Duration::from_secs(2), stderr_task).await {
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            let start = stderr.len().saturating_sub(MAX_STDERR_LEN);
+            let start = (start..stderr.len())
+                .find(|i| stderr.is_char_boundary(*i))
+                .unwrap_or(0);
+            result.stderr = Some(stderr[start..].to_string());
+        }
+    }
+    handshake
+}
+
+async fn write_message(
+    stdin: &mut tokio::process::ChildStdin,
+    message: &Value,
+) -> Result<(), String> {
+    let mut line = message.to_string();
+    line.push('\n');
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to server: {}", e))?;
+    stdin
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write to server: {}", e))
+}
+
+async fn read_stdio_response(
+    lines: &mut tokio::io::Lines<BufReader<tokio::process::ChildStdout>>,
+    id: u64,
+) -> Result<Value, String> {
+    loop {
+        let line = lines
+            .next_line()
+            .await
+            .map_err(|e| format!("Failed to read from server: {}", e))?
+            .ok_or("Server exited before answering")?;
+        // Some servers print banners or logs to stdout; skip anything else
+        match serde_json::from_str::<Value>(&line) {
+            Ok(message) if is_response_to(&message, id) => return Ok(message),
+            Ok(_) => {}
+            Err(_) => log::debug!("Ignoring non JSON-RPC output: {}", line),
+        }
+    }
+}
+
+/// One server-sent event
+#[derive(Debug, Clone, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Debug, Default)]
+struct SseParser {
+    /// Bytes of the line not yet complete; chunks may end inside a character
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take().unwrap_or_else(|| "message".to_string()),
+                        data: self.data.join("\n"),
+                    });
+                }
+                self.event = None;
+                self.data.clear();
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Event stream read from a reqwest response chunk by chunk
+struct SseStream {
+    response: reqwest::Response,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+}
+
+impl SseStream {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            response,
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    async fn next_event(&mut self) -> Result<SseEvent, String> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            let chunk = self
+                .response
+                .chunk()
+                .await
+                .map_err(|e| format!("Failed to read event stream: {}", e))?
+                .ok_or("Server closed the event stream before answering")?;
+            self.pending.extend(self.parser.push(&chunk));
+        }
+    }
+
+    /// Next JSON-RPC response with `id`
+    async fn response_to(&mut self, id: u64) -> Result<Value, String> {
+        loop {
+            let event = self.next_event().await?;
+            if event.event != "message" {
+                continue;
+            }
+            if let Ok(message) = serde_json::from_str::<Value>(&event.data) {
+                if is_response_to(&message, id) {
+                    return Ok(message);
+                }
+            }
+        }
+    }
+}
+
+fn http_client(entry: &McpServerEntry) -> Result<reqwest::Client, String> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &entry.headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header name {:?}: {}", name, e))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+        headers.insert(name, value);
+    }
+    reqwest::Client::builder()
+        .user_agent(concat!("claude-workbench/", env!("CARGO_PKG_VERSION")))
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn server_url(entry: &McpServerEntry) -> Result<&str, String> {
+    entry
+        .url
+        .as_deref()
+        .ok_or_else(|| "url is required for sse and http servers".to_string())
+}
+
+fn request_error(e: reqwest::Error) -> String {
+    // The URL of an MCP server often carries its key
+    let detail = crate::redact::redact(&e.to_string()).into_owned();
+    if e.is_connect() {
+        format!("Could not connect to the server: {}", detail)
+    } else {
+        format!("Request to the server failed: {}", detail)
+    }
+}
+
+/// Streamable HTTP transport: every message is a POST, answers come back as
+/// JSON or as a short event stream
+async fn post_http_message(
+    client: &reqwest::Client,
+    url: &str,
+    session_id: Option<&str>,
+    message: &Value,
+    response_id: Option<u64>,
+) -> Result<(Option<Value>, Option<String>), String> {
+    let mut request = client
+        .post(url)
+        .header(
+            reqwest::header::ACCEPT,
+            "application/json, text/event-stream",
+        )
+        .json(message);
+    if let Some(session_id) = session_id {
+        request = request.header("Mcp-Session-Id", session_id);
+    }
+    let response = request.send().await.map_err(request_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Server returned {}: {}", status, body.trim()));
+    }
+    let session_id = response
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let Some(id) = response_id else {
+        return Ok((None, session_id));
+    };
+
+    let is_stream = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    let message = if is_stream {
+        SseStream::new(response).response_to(id).await?
+    } else {
+        response
+            .json::<Value>()
+            .await
+            .map_err(|e| format!("Server sent an invalid response: {}", e))?
+    };
+    Ok((Some(message), session_id))
+}
+
+async fn test_http(entry: &McpServerEntry, result: &mut McpTestResult) -> Result<(), String> {
+    let url = server_url(entry)?;
+    let client = http_client(entry)?;
+
+    let (response, session_id) = post_http_message(
+        &client,
+        url,
+        None,
+        &initialize_request(),
+        Some(INITIALIZE_ID),
+    )
+    .await?;
+    let response = response.ok_or("Server did not answer initialize")?;
+    let has_tools = apply_initialize(result, &response)?;
+    let session_id = session_id.as_deref();
+
+    post_http_message(&client, url, session_id, &initialized_notification(), None).await?;
+    if has_tools {
+        let (response, _) = post_http_message(
+            &client,
+            url,
+            session_id,
+            &tools_list_request(),
+            Some(TOOLS_LIST_ID),
+        )
+        .await?;
+        let response = response.ok_or("Server did not answer tools/list")?;
+        result.tools = parse_tools(&response)?;
+    }
+    Ok(())
+}
+
+/// Legacy HTTP+SSE transport: answers arrive on a GET event stream, messages
+/// are POSTed to the endpoint the stream announces first
+async fn test_sse(entry: &McpServerEntry, result: &mut McpTestResult) -> Result<(), String> {
+    let url = server_url(entry)?;
+    let client = http_client(entry)?;
+
+    let response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .map_err(request_error)?;
+    if !response.status().is_success() {
+        return Err(format!("Server returned {}", response.status()));
+    }
+    let mut stream = SseStream::new(response);
+
+    let endpoint = loop {
+        let event = stream.next_event().await?;
+        if event.event == "endpoint" {
+            break event.data;
+        }
+    };
+    let endpoint = reqwest::Url::parse(url)
+        .and_then(|base| base.join(endpoint.trim()))
+        .map_err(|e| format!("Server announced an invalid endpoint {:?}: {}", endpoint, e))?;
+
+    let post = |message: Value| {
+        let request = client.post(endpoint.clone()).json(&message);
+        async move {
+            let response = request.send().await.map_err(request_error)?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(format!("Server returned {}", response.status()))
+            }
+        }
+    };
+
+    post(initialize_request()).await?;
+    let response = stream.response_to(INITIALIZE_ID).await?;
+    let has_tools = apply_initialize(result, &response)?;
+    post(initialized_notification()).await?;
+    if has_tools {
+        post(tools_list_request()).await?;
+        let response = stream.response_to(TOOLS_LIST_ID).await?;
+        result.tools = parse_tools(&response)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser
+            .push(b"event: endpoint\ndata: /messages?session")
+            .is_empty());
+        let events = parser.push(b"_id=1\r\n\r\ndata: {\"id\":1}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "endpoint".to_string(),
+                    data: "/messages?session_id=1".to_string(),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"id\":1}".to_string(),
+                },
+            ]
+        );
+        // A character split across chunks survives
+        let message = "data: {\"text\":\"检查\"}\n\n".as_bytes();
+        assert!(parser.push(&message[..16]).is_empty());
+        assert_eq!(parser.push(&message[16..])[0].data, "{\"text\":\"检查\"}");
+    }
+
+    #[test]
+    fn test_handshake_responses() {
+        let mut result = McpTestResult::default();
+        let init = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "protocolVersion": "2024-11-05",
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": "memory", "version": "0.6.0"}
+            }
+        });
+        assert!(is_response_to(&init, INITIALIZE_ID));
+        assert!(!is_response_to(&init, TOOLS_LIST_ID));
+        assert!(apply_initialize(&mut result, &init).unwrap());
+        assert_eq!(result.server_name.as_deref(), Some("memory"));
+        assert_eq!(result.server_version.as_deref(), Some("0.6.0"));
+
+        let tools = parse_tools(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "result": {"tools": [
+                {"name": "create_entities", "description": "Create entities"},
+                {"name": "read_graph"}
+            ]}
+        }))
+        .unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1].name, "read_graph");
+
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602, "message": "Unsupported protocol version"}});
+        assert_eq!(
+            apply_initialize(&mut result, &error).unwrap_err(),
+            "initialize failed: Unsupported protocol version"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Import MCP servers from Claude Desktop or pasted `mcpServers` JSON into a
+//! Claude Code config scope, skipping servers that are already configured
+use super::config::{
+    validate_server_name, validate_server_value, McpConfigStore, McpScope, McpServerEntry,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpImportStatus {
+    Imported,
+    /// Replaced an existing server of the same name (overwrite was requested)
+    Updated,
+    /// Already configured, or the name is taken and overwrite was not requested
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpImportOutcome {
+    pub name: String,
+    pub status: McpImportStatus,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpImportReport {
+    /// File the servers were read from, if any
+    pub source: Option<String>,
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub servers: Vec<McpImportOutcome>,
+}
+
+/// Where Claude Desktop keeps its config on this platform
+pub fn claude_desktop_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    // %APPDATA%, ~/Library/Application Support and ~/.config respectively
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("Claude").join("claude_desktop_config.json"));
+        paths.push(
+            config_dir
+                .join("Anthropic")
+                .join("Claude")
+                .join("claude_desktop_config.json"),
+        );
+    }
+    if cfg!(target_os = "windows") {
+        if let Some(local_dir) = dirs::data_local_dir() {
+            paths.push(local_dir.join("Claude").join("claude_desktop_config.json"));
+        }
+    }
+    paths
+}
+
+/// The Claude Desktop config file, if Claude Desktop is installed
+pub fn find_claude_desktop_config() -> Result<PathBuf, String> {
+    claude_desktop_config_paths()
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            "Claude Desktop configuration not found. Please make sure Claude Desktop is installed."
+                .to_string()
+        })
+}
+
+fn looks_like_server(object: &serde_json::Map<String, Value>) -> bool {
+    ["command", "url", "type"]
+        .iter()
+        .any(|field| object.get(*field).is_some_and(Value::is_string))
+}
+
+/// Servers in a pasted snippet
+///
+/// Accepts a whole config (`{"mcpServers": {...}}`), the `mcpServers` object
+/// itself, or its entries pasted without the surrounding braces.
+pub fn parse_servers_snippet(snippet: &str) -> Result<Vec<(String, Value)>, String> {
+    let snippet = snippet.trim().trim_end_matches(',');
+    let value: Value = serde_json::from_str(snippet)
+        .or_else(|e| serde_json::from_str(&format!("{{{}}}", snippet)).map_err(|_| e))
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    let object = value.as_object().ok_or("Snippet must be a JSON object")?;
+    let servers = match object.get("mcpServers") {
+        Some(servers) => servers
+            .as_object()
+            .ok_or("mcpServers must be a JSON object")?,
+        None if looks_like_server(object) => {
+            return Err(
+                "The snippet is a single server without a name; paste it as {\"name\": {...}}"
+                    .to_string(),
+            )
+        }
+        None => object,
+    };
+    if servers.is_empty() {
+        return Err("No MCP servers found in the snippet".to_string());
+    }
+    Ok(servers
+        .iter()
+        .map(|(name, config)| (name.clone(), config.clone()))
+        .collect())
+}
+
+/// Servers of a Claude Desktop config file
+pub fn read_claude_desktop_servers(path: &std::path::Path) -> Result<Vec<(String, Value)>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read Claude Desktop config: {}", e))?;
+    let config: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Claude Desktop config: {}", e))?;
+    let servers = config
+        .get("mcpServers")
+        .and_then(Value::as_object)
+        .ok_or("No MCP servers found in Claude Desktop config")?;
+    Ok(servers
+        .iter()
+        .map(|(name, config)| (name.clone(), config.clone()))
+        .collect())
+}
+
+impl McpImportReport {
+    fn record(&mut self, name: &str, status: McpImportStatus, detail: Option<String>) {
+        match status {
+            McpImportStatus::Imported | McpImportStatus::Updated => self.imported += 1,
+            McpImportStatus::Skipped => self.skipped += 1,
+            McpImportStatus::Failed => self.failed += 1,
+        }
+        self.servers.push(McpImportOutcome {
+            name: name.to_string(),
+            status,
+            detail,
+        });
+    }
+}
+
+/// Write `servers` into `scope`, deduplicating against what is configured
+///
+/// A server is skipped when the same config already exists under any name
+/// visible from the project, or when its name is taken in the target scope
+/// and `overwrite` is not set.
+pub fn import_servers(
+    store: &McpConfigStore,
+    scope: McpScope,
+    project_path: Option<&str>,
+    servers: Vec<(String, Value)>,
+    overwrite: bool,
+) -> Result<McpImportReport, String> {
+    let mut existing = store.list(project_path)?;
+    let mut report = McpImportReport::default();
+
+    for (name, config) in servers {
+        let entry: McpServerEntry = match validate_server_name(&name)
+            .map_err(|e| vec![e])
+            .and_then(|_| validate_server_value(&config))
+        {
+            Ok(entry) => entry,
+            Err(errors) => {
+                report.record(&name, McpImportStatus::Failed, Some(errors.join("; ")));
+                continue;
+            }
+        };
+
+        if let Some(duplicate) = existing.iter().find(|record| record.config == entry) {
+            let detail = if duplicate.name == name {
+                format!("Already configured in {:?} scope", duplicate.scope)
+            } else {
+                format!(
+                    "Same config already exists as {} ({:?} scope)",
+                    duplicate.name, duplicate.scope
+                )
+            };
+            report.record(&name, McpImportStatus::Skipped, Some(detail));
+            continue;
+        }
+
+        let taken = existing
+            .iter()
+            .any(|record| record.name == name && record.scope == scope);
+        if taken && !overwrite {
+            report.record(
+                &name,
+                McpImportStatus::Skipped,
+                Some("A different server with this name exists".to_string()),
+            );
+            continue;
+        }
+
+        match store.save(scope, project_path, &name, &config, taken) {
+            Ok(record) => {
+                let status = if taken {
+                    McpImportStatus::Updated
+                } else {
+                    McpImportStatus::Imported
+                };
+                existing.retain(|other| !(other.name == name && other.scope == scope));
+                existing.push(record);
+                report.record(&name, status, None);
+            }
+            Err(e) => report.record(&name, McpImportStatus::Failed, Some(e)),
+        }
+    }
+
+    log::info!(
+        "MCP import: {} imported, {} skipped, {} failed",
+        report.imported,
+        report.skipped,
+        report.failed
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_servers_snippet() {
+        let full = r#"{"mcpServers": {"memory": {"command": "npx"}}}"#;
+        assert_eq!(parse_servers_snippet(full).unwrap()[0].0, "memory");
+
+        let entries = r#"
+            "memory": {"command": "npx", "args": ["server-memory"]},
+            "docs": {"type": "http", "url": "https://example.com/mcp"},
+        "#;
+        assert_eq!(parse_servers_snippet(entries).unwrap().len(), 2);
+
+        assert!(parse_servers_snippet(r#"{"command": "npx"}"#).is_err());
+        assert!(parse_servers_snippet("not json").is_err());
+    }
+
+    #[test]
+    fn test_import_deduplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = McpConfigStore {
+            claude_json: dir.path().join(".claude.json"),
+            disabled_servers: dir.path().join("mcp-disabled.json"),
+        };
+        store
+            .save(
+                McpScope::User,
+                None,
+                "memory",
+                &json!({"command": "npx", "args": ["server-memory"]}),
+                false,
+            )
+            .unwrap();
+
+        let servers = vec![
+            // Same config under another name
+            (
+                "mem".to_string(),
+                json!({"command": "npx", "args": ["server-memory"]}),
+            ),
+            // Name taken with a different config
+            ("memory".to_string(), json!({"command": "uvx"})),
+            (
+                "fetch".to_string(),
+                json!({"command": "uvx", "args": ["mcp-server-fetch"]}),
+            ),
+            ("broken".to_string(), json!({"type": "sse"})),
+        ];
+        let report = import_servers(&store, McpScope::User, None, servers.clone(), false).unwrap();
+        let statuses: Vec<McpImportStatus> = report.servers.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                McpImportStatus::Skipped,
+                McpImportStatus::Skipped,
+                McpImportStatus::Imported,
+                McpImportStatus::Failed,
+            ]
+        );
+
+        let report = import_servers(&store, McpScope::User, None, servers, true).unwrap();
+        assert_eq!(report.servers[1].status, McpImportStatus::Updated);
+        assert_eq!(report.servers[2].status, McpImportStatus::Skipped);
+        assert_eq!(store.list(None).unwrap().len(), 2);
+    }
+}
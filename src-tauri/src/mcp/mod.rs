@@ -0,0 +1,7 @@
+pub mod config;
+pub mod health;
+pub mod import;
+
+pub use config::*;
+pub use health::*;
+pub use import::*;
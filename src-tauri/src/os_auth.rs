@@ -0,0 +1,102 @@
+//! Shared module for OS-level user verification
+//! Uses Windows Hello on Windows, an administrator prompt on macOS and polkit on Linux
+use log::{info, warn};
+use std::process::Command;
+
+/// Outcome of an OS verification prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationResult {
+    /// The user successfully authenticated
+    Verified,
+    /// The user cancelled or failed the prompt
+    Denied,
+    /// No OS authentication mechanism is available on this machine
+    Unavailable(String),
+}
+
+/// Ask the operating system to verify the current user, blocking until the prompt closes
+pub fn request_user_verification(reason: &str) -> VerificationResult {
+    info!("Requesting OS user verification: {}", reason);
+
+    let result = platform_verify(reason);
+    match &result {
+        VerificationResult::Verified => info!("OS user verification succeeded"),
+        VerificationResult::Denied => warn!("OS user verification was denied"),
+        VerificationResult::Unavailable(msg) => warn!("OS user verification unavailable: {}", msg),
+    }
+    result
+}
+
+/// Convenience wrapper that turns the verification outcome into a command error
+pub fn require_user_verification(reason: &str) -> Result<(), String> {
+    match request_user_verification(reason) {
+        VerificationResult::Verified => Ok(()),
+        VerificationResult::Denied => Err("系统身份验证未通过".to_string()),
+        VerificationResult::Unavailable(msg) => Err(format!("系统身份验证不可用: {}", msg)),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_verify(reason: &str) -> VerificationResult {
+    use std::os::windows::process::CommandExt;
+
+    // Windows Hello via UserConsentVerifier, driven from PowerShell so no WinRT bindings are needed
+    let script = format!(
+        r#"
+Add-Type -AssemblyName System.Runtime.WindowsRuntime
+$asTask = ([System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object {{ $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncOperation`1' }})[0]
+[Windows.Security.Credentials.UI.UserConsentVerifier,Windows.Security.Credentials.UI,ContentType=WindowsRuntime] | Out-Null
+$op = [Windows.Security.Credentials.UI.UserConsentVerifier]::RequestVerificationAsync('{}')
+$task = $asTask.MakeGenericMethod([Windows.Security.Credentials.UI.UserConsentVerificationResult]).Invoke($null, @($op))
+$task.Wait(-1) | Out-Null
+Write-Output $task.Result
+"#,
+        reason.replace('\'', "''")
+    );
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output();
+
+    match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            match stdout.trim() {
+                "Verified" => VerificationResult::Verified,
+                "DeviceNotPresent" | "NotConfiguredForUser" | "DisabledByPolicy" => {
+                    VerificationResult::Unavailable(format!("Windows Hello: {}", stdout.trim()))
+                }
+                _ => VerificationResult::Denied,
+            }
+        }
+        Err(e) => VerificationResult::Unavailable(format!("Failed to run PowerShell: {}", e)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_verify(reason: &str) -> VerificationResult {
+    let script = format!(
+        "do shell script \"true\" with prompt \"{}\" with administrator privileges",
+        reason.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+
+    match Command::new("osascript").args(["-e", &script]).output() {
+        Ok(output) if output.status.success() => VerificationResult::Verified,
+        Ok(_) => VerificationResult::Denied,
+        Err(e) => VerificationResult::Unavailable(format!("Failed to run osascript: {}", e)),
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn platform_verify(_reason: &str) -> VerificationResult {
+    match Command::new("pkexec").arg("true").output() {
+        Ok(output) if output.status.success() => VerificationResult::Verified,
+        // 127 means pkexec could not find an authentication agent
+        Ok(output) if output.status.code() == Some(127) => {
+            VerificationResult::Unavailable("No polkit authentication agent".to_string())
+        }
+        Ok(_) => VerificationResult::Denied,
+        Err(e) => VerificationResult::Unavailable(format!("Failed to run pkexec: {}", e)),
+    }
+}
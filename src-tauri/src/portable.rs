@@ -0,0 +1,86 @@
+//! Portable mode: keep the database, providers.json and logs in a `data`
+//! folder next to the executable instead of the per-user app data directory,
+//! so the app can run from a USB stick or a synced folder
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+
+/// Placing this file next to the executable turns on portable mode
+pub const PORTABLE_FLAG_FILE: &str = "portable.flag";
+
+/// Command line switch turning on portable mode
+pub const PORTABLE_ARG: &str = "--portable";
+
+/// Folder next to the executable holding the portable data
+const PORTABLE_DATA_DIR: &str = "data";
+
+/// Matches `identifier` in tauri.conf.json, for code running without an AppHandle
+const APP_IDENTIFIER: &str = "claude.workbench.app";
+
+static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Portable data directory for an executable in `exe_dir` started with `args`
+fn portable_dir_for(exe_dir: &Path, args: &[String]) -> Option<PathBuf> {
+    let enabled =
+        args.iter().any(|arg| arg == PORTABLE_ARG) || exe_dir.join(PORTABLE_FLAG_FILE).is_file();
+    enabled.then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+/// Data directory of portable mode, or `None` when running installed
+pub fn portable_dir() -> Option<&'static Path> {
+    PORTABLE_DIR
+        .get_or_init(|| {
+            let exe = std::env::current_exe().ok()?;
+            let exe_dir = exe.parent()?;
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let dir = portable_dir_for(exe_dir, &args)?;
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                // Read-only media: fall back to the installed layout
+                eprintln!("Portable data directory {:?} is not writable: {}", dir, e);
+                return None;
+            }
+            Some(dir)
+        })
+        .as_deref()
+}
+
+pub fn is_portable() -> bool {
+    portable_dir().is_some()
+}
+
+/// App data directory, honouring portable mode; use instead of
+/// `app.path().app_data_dir()`
+pub fn app_data_dir(app: &tauri::AppHandle) -> tauri::Result<PathBuf> {
+    match portable_dir() {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => app.path().app_data_dir(),
+    }
+}
+
+/// App data directory for code that runs before Tauri starts
+pub fn data_dir() -> Option<PathBuf> {
+    match portable_dir() {
+        Some(dir) => Some(dir.to_path_buf()),
+        None => dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_detection() {
+        let exe_dir = tempfile::tempdir().unwrap();
+        let data_dir = exe_dir.path().join(PORTABLE_DATA_DIR);
+
+        assert_eq!(portable_dir_for(exe_dir.path(), &[]), None);
+        assert_eq!(
+            portable_dir_for(exe_dir.path(), &[PORTABLE_ARG.to_string()]),
+            Some(data_dir.clone())
+        );
+
+        std::fs::write(exe_dir.path().join(PORTABLE_FLAG_FILE), "").unwrap();
+        assert_eq!(portable_dir_for(exe_dir.path(), &[]), Some(data_dir));
+    }
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::monitor::ProcessUsage;
+
+/// Limits a managed process (with its descendants) may not exceed for long
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceThresholds {
+    pub enabled: bool,
+    /// Resident memory limit in megabytes, `None` to ignore memory
+    pub max_memory_mb: Option<u64>,
+    /// CPU limit in percent (may exceed 100 on multi-core machines), `None` to ignore CPU
+    pub max_cpu_percent: Option<f32>,
+    /// How long a limit has to be exceeded before alerting
+    pub sustained_secs: u64,
+    /// Seconds between usage samples
+    pub check_interval_secs: u64,
+    /// Kill the process tree once an alert fires
+    pub auto_kill: bool,
+}
+
+impl Default for ResourceThresholds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_memory_mb: Some(4096),
+            max_cpu_percent: None,
+            sustained_secs: 60,
+            check_interval_secs: 10,
+            auto_kill: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceKind {
+    Memory,
+    Cpu,
+}
+
+/// Payload of the `process-resource-alert` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceAlert {
+    pub run_id: i64,
+    pub pid: u32,
+    pub project_path: String,
+    pub resource: ResourceKind,
+    /// Current usage: megabytes for memory, percent for CPU
+    pub value: f64,
+    pub threshold: f64,
+    pub exceeded_for_secs: u64,
+    /// Whether the process was killed because of this alert
+    pub killed: bool,
+}
+
+#[derive(Default)]
+struct Breach {
+    since: Option<Instant>,
+    alerted: bool,
+}
+
+/// Tracks how long each run has been over its limits
+///
+/// An alert fires once per continuous breach; dropping back under the
+/// limit re-arms it.
+#[derive(Default)]
+pub struct ResourceAlertTracker {
+    breaches: HashMap<(i64, ResourceKind), Breach>,
+}
+
+impl ResourceAlertTracker {
+    /// Feed a usage sample and return the limits that have now been exceeded
+    /// for the sustained period (value, threshold, exceeded duration)
+    pub fn evaluate(
+        &mut self,
+        thresholds: &ResourceThresholds,
+        run_id: i64,
+        usage: &ProcessUsage,
+        now: Instant,
+    ) -> Vec<(ResourceKind, f64, f64, Duration)> {
+        let checks = [
+            (
+                ResourceKind::Memory,
+                usage.memory_bytes as f64 / (1024.0 * 1024.0),
+                thresholds.max_memory_mb.map(|mb| mb as f64),
+            ),
+            (
+                ResourceKind::Cpu,
+                usage.cpu_usage as f64,
+                thresholds.max_cpu_percent.map(|percent| percent as f64),
+            ),
+        ];
+        let sustained = Duration::from_secs(thresholds.sustained_secs);
+
+        let mut fired = Vec::new();
+        for (kind, value, threshold) in checks {
+            let Some(threshold) = threshold.filter(|_| thresholds.enabled) else {
+                self.breaches.remove(&(run_id, kind));
+                continue;
+            };
+            if value <= threshold {
+                self.breaches.remove(&(run_id, kind));
+                continue;
+            }
+
+            let breach = self.breaches.entry((run_id, kind)).or_default();
+            let since = *breach.since.get_or_insert(now);
+            let elapsed = now.duration_since(since);
+            if !breach.alerted && elapsed >= sustained {
+                breach.alerted = true;
+                fired.push((kind, value, threshold, elapsed));
+            }
+        }
+        fired
+    }
+
+    /// Drop state for runs that are no longer sampled
+    pub fn retain_runs(&mut self, run_ids: &[i64]) {
+        self.breaches
+            .retain(|(run_id, _), _| run_ids.contains(run_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(memory_mb: u64, cpu_usage: f32) -> ProcessUsage {
+        ProcessUsage {
+            pid: 42,
+            cpu_usage,
+            memory_bytes: memory_mb * 1024 * 1024,
+            child_count: 0,
+        }
+    }
+
+    fn thresholds(sustained_secs: u64) -> ResourceThresholds {
+        ResourceThresholds {
+            max_memory_mb: Some(1024),
+            max_cpu_percent: Some(150.0),
+            sustained_secs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_alert_fires_once_per_sustained_breach() {
+        let thresholds = thresholds(30);
+        let mut tracker = ResourceAlertTracker::default();
+        let start = Instant::now();
+        let mut sample = |memory_mb, secs| {
+            let at = start + Duration::from_secs(secs);
+            tracker.evaluate(&thresholds, 1, &usage(memory_mb, 10.0), at)
+        };
+
+        // Over the limit, but not for long enough yet
+        assert!(sample(2048, 0).is_empty());
+        assert!(sample(2048, 20).is_empty());
+        let fired = sample(2048, 30);
+        let thirty_secs = Duration::from_secs(30);
+        assert_eq!(
+            fired,
+            vec![(ResourceKind::Memory, 2048.0, 1024.0, thirty_secs)]
+        );
+        // Still the same breach
+        assert!(sample(2048, 60).is_empty());
+
+        // Dropping under the limit re-arms the alert
+        assert!(sample(512, 70).is_empty());
+        assert!(sample(2048, 80).is_empty());
+        assert_eq!(sample(2048, 110).len(), 1);
+    }
+
+    #[test]
+    fn test_limits_are_tracked_per_run_and_resource() {
+        let thresholds = thresholds(0);
+        let mut tracker = ResourceAlertTracker::default();
+        let now = Instant::now();
+        let mut fired = |run_id, memory_mb, cpu_usage| -> Vec<ResourceKind> {
+            tracker
+                .evaluate(&thresholds, run_id, &usage(memory_mb, cpu_usage), now)
+                .into_iter()
+                .map(|(kind, ..)| kind)
+                .collect()
+        };
+
+        assert_eq!(
+            fired(1, 2048, 300.0),
+            vec![ResourceKind::Memory, ResourceKind::Cpu]
+        );
+        assert_eq!(fired(2, 100, 300.0), vec![ResourceKind::Cpu]);
+        // Exactly at the limit is not a breach
+        assert!(fired(3, 1024, 150.0).is_empty());
+
+        // Runs that stopped being sampled start over
+        tracker.retain_runs(&[2]);
+        let mut fired = |run_id, memory_mb, cpu_usage| {
+            tracker.evaluate(&thresholds, run_id, &usage(memory_mb, cpu_usage), now)
+        };
+        assert_eq!(fired(1, 2048, 0.0).len(), 1);
+        assert!(fired(2, 100, 300.0).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_or_unset_limits_never_fire() {
+        let mut tracker = ResourceAlertTracker::default();
+        let now = Instant::now();
+        let disabled = ResourceThresholds {
+            enabled: false,
+            ..thresholds(0)
+        };
+        assert!(tracker
+            .evaluate(&disabled, 1, &usage(8192, 800.0), now)
+            .is_empty());
+
+        // The default has no CPU limit
+        let defaults = ResourceThresholds {
+            sustained_secs: 0,
+            ..Default::default()
+        };
+        assert!(tracker
+            .evaluate(&defaults, 1, &usage(100, 800.0), now)
+            .is_empty());
+    }
+}
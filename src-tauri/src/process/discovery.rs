@@ -0,0 +1,136 @@
+//! Identify Claude CLI processes by executable name and command line
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+fn process_refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::new()
+        .with_cmd(UpdateKind::Always)
+        .with_exe(UpdateKind::Always)
+        .with_cwd(UpdateKind::Always)
+}
+
+/// Whether a process looks like the Claude CLI (native binary or `node .../claude`)
+pub fn looks_like_claude(process: &Process) -> bool {
+    let name = process.name().to_string_lossy().to_lowercase();
+    if name == "claude" || name == "claude.exe" {
+        return true;
+    }
+
+    let is_node = name == "node" || name == "node.exe";
+    is_node
+        && process.cmd().iter().skip(1).any(|arg| {
+            let arg = arg.to_string_lossy().to_lowercase();
+            arg.contains("claude-code") || arg.ends_with("claude")
+        })
+}
+
+/// Check that `pid` is alive and still a Claude process (guards against PID reuse)
+pub fn is_claude_process(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        process_refresh_kind(),
+    );
+    system.process(pid).map(looks_like_claude).unwrap_or(false)
+}
+
+/// A Claude CLI process found on the system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalClaudeProcess {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub command_line: String,
+    /// Working directory, usually the project the session runs in
+    pub cwd: Option<String>,
+    /// Start time as a Unix timestamp in seconds
+    pub started_at: i64,
+    /// Session ID when started with `--resume`/`--session-id`
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Value following any of `flags` on a command line
+fn arg_value(args: &[String], flags: &[&str]) -> Option<String> {
+    args.iter()
+        .position(|arg| flags.contains(&arg.as_str()))
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+fn describe(pid: Pid, process: &Process) -> ExternalClaudeProcess {
+    let args: Vec<String> = process
+        .cmd()
+        .iter()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+
+    ExternalClaudeProcess {
+        pid: pid.as_u32(),
+        parent_pid: process.parent().map(|parent| parent.as_u32()),
+        name: process.name().to_string_lossy().to_string(),
+        command_line: args.join(" "),
+        cwd: process.cwd().map(|cwd| cwd.to_string_lossy().to_string()),
+        started_at: process.start_time() as i64,
+        session_id: arg_value(&args, &["--resume", "-r", "--session-id"]),
+        model: arg_value(&args, &["--model"]),
+    }
+}
+
+/// Scan for Claude CLI processes that are not tracked by the workbench
+///
+/// Processes in `tracked` and their descendants are skipped, as are Claude
+/// processes whose parent is itself a Claude process, so each session is
+/// reported once by its root process.
+pub fn scan_claude_processes(tracked: &HashSet<u32>) -> Vec<ExternalClaudeProcess> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, process_refresh_kind());
+
+    let is_tracked_or_nested = |pid: Pid| {
+        let mut current = system.process(pid).and_then(|process| process.parent());
+        while let Some(parent) = current {
+            if tracked.contains(&parent.as_u32()) {
+                return true;
+            }
+            match system.process(parent) {
+                Some(process) if looks_like_claude(process) => return true,
+                Some(process) => current = process.parent(),
+                None => break,
+            }
+        }
+        false
+    };
+
+    let mut found: Vec<ExternalClaudeProcess> = system
+        .processes()
+        .iter()
+        .filter(|(pid, process)| {
+            !tracked.contains(&pid.as_u32())
+                && process.thread_kind().is_none()
+                && looks_like_claude(process)
+                && !is_tracked_or_nested(**pid)
+        })
+        .map(|(pid, process)| describe(*pid, process))
+        .collect();
+
+    found.sort_by_key(|process| process.started_at);
+    found
+}
+
+/// Describe a single Claude process by PID, if it is one
+pub fn find_claude_process(pid: u32) -> Option<ExternalClaudeProcess> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        process_refresh_kind(),
+    );
+    system
+        .process(pid)
+        .filter(|process| looks_like_claude(process))
+        .map(|process| describe(pid, process))
+}
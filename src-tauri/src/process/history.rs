@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::registry::{ProcessInfo, ProcessType};
+
+/// Token and cost totals reported by the Claude CLI's final `result` message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunUsageSummary {
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_creation_tokens: Option<i64>,
+    pub cache_read_tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+}
+
+/// A finished process run as stored in `run_history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunHistoryEntry {
+    pub id: i64,
+    pub run_id: i64,
+    /// "claude_session", "agent_run", "external_claude" or "chain_step"
+    pub process_type: String,
+    pub session_id: Option<String>,
+    pub agent_id: Option<i64>,
+    pub agent_name: Option<String>,
+    pub project_path: String,
+    pub task: String,
+    pub model: String,
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration_ms: i64,
+    pub exit_code: Option<i32>,
+    /// "completed", "failed", "killed" or "interrupted"
+    pub status: String,
+    #[serde(flatten)]
+    pub usage: RunUsageSummary,
+}
+
+/// One page of run history, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunHistoryPage {
+    pub entries: Vec<RunHistoryEntry>,
+    pub total: i64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Derive usage totals from captured stream-json stdout, using the last `result` message
+pub fn summarize_usage<'a>(lines: impl DoubleEndedIterator<Item = &'a str>) -> RunUsageSummary {
+    for line in lines.rev() {
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if msg["type"] != "result" {
+            continue;
+        }
+
+        let usage = &msg["usage"];
+        return RunUsageSummary {
+            input_tokens: usage["input_tokens"].as_i64(),
+            output_tokens: usage["output_tokens"].as_i64(),
+            cache_creation_tokens: usage["cache_creation_input_tokens"].as_i64(),
+            cache_read_tokens: usage["cache_read_input_tokens"].as_i64(),
+            cost_usd: msg["total_cost_usd"]
+                .as_f64()
+                .or_else(|| msg["cost_usd"].as_f64()),
+        };
+    }
+    RunUsageSummary::default()
+}
+
+/// Insert a finished run into `run_history`
+pub fn record_run(
+    conn: &Connection,
+    info: &ProcessInfo,
+    ended_at: DateTime<Utc>,
+    exit_code: Option<i32>,
+    status: &str,
+    usage: &RunUsageSummary,
+) -> Result<(), String> {
+    let (process_type, session_id, agent_id, agent_name) = match &info.process_type {
+        ProcessType::ClaudeSession { session_id } => {
+            ("claude_session", Some(session_id.clone()), None, None)
+        }
+        ProcessType::AgentRun {
+            agent_id,
+            agent_name,
+        } => ("agent_run", None, Some(*agent_id), Some(agent_name.clone())),
+        ProcessType::ExternalClaude { session_id, .. } => {
+            ("external_claude", session_id.clone(), None, None)
+        }
+        ProcessType::ChainStep { .. } => ("chain_step", None, None, None),
+    };
+    let duration_ms = (ended_at - info.started_at).num_milliseconds().max(0);
+
+    conn.execute(
+        "INSERT INTO run_history (
+            run_id, process_type, session_id, agent_id, agent_name, project_path, task, model,
+            started_at, ended_at, duration_ms, exit_code, status,
+            input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost_usd
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            info.run_id,
+            process_type,
+            session_id,
+            agent_id,
+            agent_name,
+            info.project_path,
+            info.task,
+            info.model,
+            info.started_at.to_rfc3339(),
+            ended_at.to_rfc3339(),
+            duration_ms,
+            exit_code,
+            status,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_creation_tokens,
+            usage.cache_read_tokens,
+            usage.cost_usd,
+        ],
+    )
+    .map_err(|e| format!("Failed to record run history: {}", e))?;
+    Ok(())
+}
+
+/// Read a page of run history (1-based `page`), optionally filtered by project path
+pub fn query_runs(
+    conn: &Connection,
+    page: u32,
+    page_size: u32,
+    project_path: Option<&str>,
+) -> Result<RunHistoryPage, String> {
+    let page = page.max(1);
+    let page_size = page_size.clamp(1, 500);
+    let offset = (page as i64 - 1) * page_size as i64;
+
+    let total: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM run_history WHERE (?1 IS NULL OR project_path = ?1)",
+            params![project_path],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, run_id, process_type, session_id, agent_id, agent_name, project_path, task, model,
+                    started_at, ended_at, duration_ms, exit_code, status,
+                    input_tokens, output_tokens, cache_creation_tokens, cache_read_tokens, cost_usd
+             FROM run_history
+             WHERE (?1 IS NULL OR project_path = ?1)
+             ORDER BY ended_at DESC, id DESC
+             LIMIT ?2 OFFSET ?3",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let entries = stmt
+        .query_map(params![project_path, page_size, offset], |row| {
+            Ok(RunHistoryEntry {
+                id: row.get(0)?,
+                run_id: row.get(1)?,
+                process_type: row.get(2)?,
+                session_id: row.get(3)?,
+                agent_id: row.get(4)?,
+                agent_name: row.get(5)?,
+                project_path: row.get(6)?,
+                task: row.get(7)?,
+                model: row.get(8)?,
+                started_at: row.get(9)?,
+                ended_at: row.get(10)?,
+                duration_ms: row.get(11)?,
+                exit_code: row.get(12)?,
+                status: row.get(13)?,
+                usage: RunUsageSummary {
+                    input_tokens: row.get(14)?,
+                    output_tokens: row.get(15)?,
+                    cache_creation_tokens: row.get(16)?,
+                    cache_read_tokens: row.get(17)?,
+                    cost_usd: row.get(18)?,
+                },
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read run history: {}", e))?;
+
+    Ok(RunHistoryPage {
+        entries,
+        total,
+        page,
+        page_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `claude -p --output-format stream-json --verbose`, one tool call
+    const STREAM: &str = r#"{"type":"system","subtype":"init","cwd":"/tmp/app","session_id":"5f1c0d52-8a4e-4c1b-9d0e-2b7f3c6a1e90","tools":["Bash","Edit","Read"],"model":"claude-sonnet-4-5-20250929","permissionMode":"default","apiKeySource":"none"}
+{"type":"assistant","message":{"id":"msg_01","type":"message","role":"assistant","model":"claude-sonnet-4-5-20250929","content":[{"type":"tool_use","id":"toolu_01","name":"Bash","input":{"command":"ls"}}],"stop_reason":null,"usage":{"input_tokens":4,"cache_creation_input_tokens":1520,"cache_read_input_tokens":13012,"output_tokens":61}},"parent_tool_use_id":null,"session_id":"5f1c0d52-8a4e-4c1b-9d0e-2b7f3c6a1e90"}
+{"type":"user","message":{"role":"user","content":[{"tool_use_id":"toolu_01","type":"tool_result","content":"Cargo.toml\nsrc","is_error":false}]},"parent_tool_use_id":null,"session_id":"5f1c0d52-8a4e-4c1b-9d0e-2b7f3c6a1e90"}
+{"type":"assistant","message":{"id":"msg_02","type":"message","role":"assistant","model":"claude-sonnet-4-5-20250929","content":[{"type":"text","text":"The project has a Cargo.toml and a src directory."}],"stop_reason":null,"usage":{"input_tokens":4,"cache_creation_input_tokens":98,"cache_read_input_tokens":14532,"output_tokens":17}},"parent_tool_use_id":null,"session_id":"5f1c0d52-8a4e-4c1b-9d0e-2b7f3c6a1e90"}
+{"type":"result","subtype":"success","is_error":false,"duration_ms":8421,"duration_api_ms":7310,"num_turns":3,"result":"The project has a Cargo.toml and a src directory.","session_id":"5f1c0d52-8a4e-4c1b-9d0e-2b7f3c6a1e90","total_cost_usd":0.0312105,"usage":{"input_tokens":8,"cache_creation_input_tokens":1618,"cache_read_input_tokens":27544,"output_tokens":78,"server_tool_use":{"web_search_requests":0},"service_tier":"standard"}}"#;
+
+    #[test]
+    fn test_summarize_usage() {
+        let usage = summarize_usage(STREAM.lines());
+        assert_eq!(usage.input_tokens, Some(8));
+        assert_eq!(usage.output_tokens, Some(78));
+        assert_eq!(usage.cache_creation_tokens, Some(1618));
+        assert_eq!(usage.cache_read_tokens, Some(27544));
+        assert_eq!(usage.cost_usd, Some(0.0312105));
+
+        // Older CLIs reported `cost_usd`; stray stderr lines are skipped
+        let legacy = r#"{"type":"result","subtype":"success","cost_usd":0.5,"usage":{"input_tokens":1,"output_tokens":2}}
+npm WARN config production Use `--omit=dev` instead."#;
+        let usage = summarize_usage(legacy.lines());
+        assert_eq!(usage.cost_usd, Some(0.5));
+        assert_eq!(usage.cache_read_tokens, None);
+
+        // A run killed before its result message has no totals
+        let interrupted: Vec<&str> = STREAM.lines().take(3).collect();
+        let usage = summarize_usage(interrupted.into_iter());
+        assert_eq!(usage.input_tokens, None);
+        assert_eq!(usage.cost_usd, None);
+    }
+}
@@ -1,3 +1,16 @@
+pub mod alerts;
+pub mod discovery;
+pub mod history;
+pub mod monitor;
+pub mod pty;
+pub mod queue;
 pub mod registry;
+pub mod schedule;
+pub mod shutdown;
+pub mod tree;
 
+pub use alerts::*;
+pub use history::*;
+pub use monitor::*;
+pub use queue::*;
 pub use registry::*;
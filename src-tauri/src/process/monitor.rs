@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// Live resource usage of a managed process (including its descendants)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    /// CPU usage in percent, may exceed 100 on multi-core machines
+    pub cpu_usage: f32,
+    /// Resident memory in bytes
+    pub memory_bytes: u64,
+    /// Number of descendant processes included in the totals
+    pub child_count: usize,
+}
+
+/// Samples CPU and memory usage for tracked PIDs via sysinfo
+///
+/// The `System` is kept between calls so CPU usage is measured over the
+/// interval since the previous sample rather than being reported as zero.
+pub struct ProcessMonitor {
+    system: Mutex<System>,
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        Self {
+            system: Mutex::new(System::new()),
+        }
+    }
+
+    fn refresh(&self) -> Result<(), String> {
+        let mut system = self.system.lock().map_err(|e| e.to_string())?;
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::new().with_cpu().with_memory(),
+        );
+        Ok(())
+    }
+
+    /// Sample usage for the given PIDs, skipping any that are no longer alive
+    pub async fn sample(&self, pids: &[u32]) -> Result<HashMap<u32, ProcessUsage>, String> {
+        let needs_baseline = {
+            let system = self.system.lock().map_err(|e| e.to_string())?;
+            pids.iter()
+                .any(|pid| system.process(Pid::from_u32(*pid)).is_none())
+        };
+
+        self.refresh()?;
+
+        // A process seen for the first time has no CPU baseline yet, take a second sample
+        if needs_baseline {
+            tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+            self.refresh()?;
+        }
+
+        let system = self.system.lock().map_err(|e| e.to_string())?;
+
+        // Build parent -> children index once for the descendant walk
+        let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        for (pid, process) in system.processes() {
+            if let Some(parent) = process.parent() {
+                children.entry(parent).or_default().push(*pid);
+            }
+        }
+
+        let mut usage = HashMap::new();
+        for &pid in pids {
+            let root = Pid::from_u32(pid);
+            let Some(process) = system.process(root) else {
+                continue;
+            };
+
+            let mut total = ProcessUsage {
+                pid,
+                cpu_usage: process.cpu_usage(),
+                memory_bytes: process.memory(),
+                child_count: 0,
+            };
+
+            let mut stack = children.get(&root).cloned().unwrap_or_default();
+            while let Some(child_pid) = stack.pop() {
+                if let Some(child) = system.process(child_pid) {
+                    total.cpu_usage += child.cpu_usage();
+                    total.memory_bytes += child.memory();
+                    total.child_count += 1;
+                }
+                if let Some(grandchildren) = children.get(&child_pid) {
+                    stack.extend(grandchildren.iter().copied());
+                }
+            }
+
+            usage.insert(pid, total);
+        }
+
+        Ok(usage)
+    }
+}
+
+impl Default for ProcessMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
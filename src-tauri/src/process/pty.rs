@@ -0,0 +1,306 @@
+//! Pseudo-terminal backed Claude sessions for the interactive terminal view
+//! Piped stdio makes the CLI fall back to plain output; a PTY lets prompts,
+//! spinners and cursor control sequences render as they do in a real terminal.
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+use super::registry::{ProcessRegistry, SessionSlot};
+use crate::commands::notifications::{
+    notify_session_event, PermissionPromptDetector, SessionEvent,
+};
+
+/// Raw output kept per session so a re-opened terminal view can be repainted
+const MAX_SCROLLBACK_BYTES: usize = 512 * 1024;
+
+/// Payload of the `pty-exit:{run_id}` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyExit {
+    pub run_id: i64,
+    pub exit_code: Option<u32>,
+}
+
+struct PtySession {
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    scrollback: Arc<Mutex<String>>,
+}
+
+/// Options for starting a PTY session
+pub struct PtyLaunch {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: String,
+    pub env: HashMap<String, String>,
+    pub rows: u16,
+    pub cols: u16,
+    /// Claude session ID the CLI was started with (`--session-id`/`--resume`)
+    pub session_id: String,
+    pub task: String,
+    pub model: String,
+}
+
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Split off the longest valid UTF-8 prefix, keeping an incomplete trailing
+/// sequence in `pending` for the next read
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Genuinely invalid bytes: emit everything lossily
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+    pending.drain(..valid);
+    text
+}
+
+/// Interactive Claude sessions running inside pseudo-terminals, keyed by run ID
+#[derive(Default)]
+pub struct PtySessions {
+    sessions: Mutex<HashMap<i64, Arc<PtySession>>>,
+}
+
+impl PtySessions {
+    /// Spawn the CLI in a new PTY and register it as a Claude session in `slot`
+    ///
+    /// Output is emitted as `pty-output:{run_id}` and the exit as `pty-exit:{run_id}`.
+    pub fn spawn(
+        self: &Arc<Self>,
+        app: AppHandle,
+        registry: Arc<ProcessRegistry>,
+        launch: PtyLaunch,
+        slot: SessionSlot,
+    ) -> Result<i64, String> {
+        let pair = native_pty_system()
+            .openpty(pty_size(launch.rows, launch.cols))
+            .map_err(|e| format!("Failed to open pseudo-terminal: {}", e))?;
+
+        let mut cmd = CommandBuilder::new(&launch.program);
+        cmd.args(&launch.args);
+        cmd.cwd(&launch.cwd);
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        for (key, value) in &launch.env {
+            cmd.env(key, value);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to spawn Claude in pseudo-terminal: {}", e))?;
+        // The child holds its own handle; keeping ours would prevent EOF on exit
+        drop(pair.slave);
+
+        let reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to read from pseudo-terminal: {}", e))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to write to pseudo-terminal: {}", e))?;
+
+        let pid = child.process_id().unwrap_or(0);
+        let run_id = match registry.register_claude_session(
+            launch.session_id.clone(),
+            pid,
+            launch.cwd.clone(),
+            launch.task.clone(),
+            launch.model.clone(),
+            // The PTY child is not a tokio process; `PtySessions` owns it
+            Default::default(),
+            slot,
+        ) {
+            Ok(run_id) => run_id,
+            Err(e) => {
+                let _ = child.kill();
+                return Err(e);
+            }
+        };
+        registry.set_env_overrides(run_id, launch.env.clone());
+        log::info!(
+            "Started interactive Claude session {} in PTY (run_id: {}, PID: {})",
+            launch.session_id,
+            run_id,
+            pid
+        );
+
+        let session = Arc::new(PtySession {
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            killer: Mutex::new(child.clone_killer()),
+            scrollback: Arc::new(Mutex::new(String::new())),
+        });
+        self.sessions
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(run_id, session.clone());
+
+        Self::spawn_reader(
+            app.clone(),
+            run_id,
+            reader,
+            session.scrollback.clone(),
+            launch.cwd.clone(),
+        );
+
+        let sessions = Arc::clone(self);
+        std::thread::spawn(move || {
+            let status = child.wait();
+            let exit_code = status.as_ref().ok().map(|status| status.exit_code());
+            log::info!("Interactive Claude session {} exited: {:?}", run_id, exit_code);
+
+            if let Ok(mut map) = sessions.sessions.lock() {
+                map.remove(&run_id);
+            }
+            let outcome = match &status {
+                Ok(status) if status.success() => "completed",
+                _ => "failed",
+            };
+            if let Err(e) =
+                registry.finish_process(run_id, exit_code.map(|code| code as i32), outcome)
+            {
+                log::warn!("Failed to finish PTY session {}: {}", run_id, e);
+            }
+            let _ = app.emit(
+                &format!("pty-exit:{}", run_id),
+                &PtyExit { run_id, exit_code },
+            );
+            // The session's slot is free again
+            tauri::async_runtime::spawn(crate::commands::claude::start_queued_runs(app));
+        });
+
+        Ok(run_id)
+    }
+
+    fn spawn_reader(
+        app: AppHandle,
+        run_id: i64,
+        mut reader: Box<dyn Read + Send>,
+        scrollback: Arc<Mutex<String>>,
+        project_path: String,
+    ) {
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            let mut pending = Vec::new();
+            let mut prompts = PermissionPromptDetector::default();
+            loop {
+                let read = match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                pending.extend_from_slice(&buf[..read]);
+                let text = take_utf8(&mut pending);
+                if text.is_empty() {
+                    continue;
+                }
+
+                if let Ok(mut scrollback) = scrollback.lock() {
+                    scrollback.push_str(&text);
+                    if scrollback.len() > MAX_SCROLLBACK_BYTES {
+                        let mut cut = scrollback.len() - MAX_SCROLLBACK_BYTES;
+                        while !scrollback.is_char_boundary(cut) {
+                            cut += 1;
+                        }
+                        scrollback.drain(..cut);
+                    }
+                }
+                let _ = app.emit(&format!("pty-output:{}", run_id), &text);
+
+                if prompts.observe(&text) {
+                    notify_session_event(
+                        &app,
+                        SessionEvent::PermissionRequested,
+                        &project_path,
+                        None,
+                    );
+                }
+            }
+        });
+    }
+
+    fn get(&self, run_id: i64) -> Result<Arc<PtySession>, String> {
+        self.sessions
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(&run_id)
+            .cloned()
+            .ok_or_else(|| format!("No interactive session with run ID {}", run_id))
+    }
+
+    /// Send keystrokes (or pasted text) to the session
+    pub fn write(&self, run_id: i64, data: &str) -> Result<(), String> {
+        let session = self.get(run_id)?;
+        let mut writer = session.writer.lock().map_err(|e| e.to_string())?;
+        writer
+            .write_all(data.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write to pseudo-terminal: {}", e))
+    }
+
+    /// Resize the terminal after the frontend view changed size
+    pub fn resize(&self, run_id: i64, rows: u16, cols: u16) -> Result<(), String> {
+        let session = self.get(run_id)?;
+        let master = session.master.lock().map_err(|e| e.to_string())?;
+        master
+            .resize(pty_size(rows, cols))
+            .map_err(|e| format!("Failed to resize pseudo-terminal: {}", e))
+    }
+
+    /// Output captured so far, for repainting a terminal view
+    pub fn scrollback(&self, run_id: i64) -> Result<String, String> {
+        let session = self.get(run_id)?;
+        let scrollback = session.scrollback.lock().map_err(|e| e.to_string())?;
+        Ok(scrollback.clone())
+    }
+
+    /// Kill the session; the exit is reported through `pty-exit:{run_id}`
+    pub fn kill(&self, run_id: i64) -> Result<(), String> {
+        let session = self.get(run_id)?;
+        let mut killer = session.killer.lock().map_err(|e| e.to_string())?;
+        killer
+            .kill()
+            .map_err(|e| format!("Failed to kill interactive session: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_utf8_keeps_split_sequences() {
+        // "é" is 0xC3 0xA9 and "😀" is four bytes; split both across reads
+        let mut pending = b"caf\xC3".to_vec();
+        assert_eq!(take_utf8(&mut pending), "caf");
+        assert_eq!(pending, b"\xC3");
+
+        pending.extend_from_slice(b"\xA9 \xF0\x9F");
+        assert_eq!(take_utf8(&mut pending), "é ");
+        assert_eq!(pending, b"\xF0\x9F");
+
+        pending.extend_from_slice(b"\x98");
+        assert_eq!(take_utf8(&mut pending), "");
+        pending.extend_from_slice(b"\x80!");
+        assert_eq!(take_utf8(&mut pending), "😀!");
+        assert!(pending.is_empty());
+
+        // Invalid bytes are emitted lossily instead of stalling the stream
+        let mut pending = b"a\xFFb".to_vec();
+        assert_eq!(take_utf8(&mut pending), "a\u{FFFD}b");
+        assert!(pending.is_empty());
+    }
+}
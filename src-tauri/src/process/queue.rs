@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// How a queued Claude run should be started once a slot frees up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum QueuedRunKind {
+    Execute,
+    Continue,
+    Resume { session_id: String },
+}
+
+/// A Claude run request waiting for a free session slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedRun {
+    pub id: String,
+    pub kind: QueuedRunKind,
+    pub project_path: String,
+    pub prompt: String,
+    pub model: String,
+    /// Per-launch environment overrides
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Maximum number of concurrent Claude sessions, `None` meaning unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConcurrencyLimits {
+    pub max_global: Option<usize>,
+    pub max_per_project: Option<usize>,
+}
+
+impl ConcurrencyLimits {
+    /// Whether another session may start given the current global and per-project counts
+    pub fn allows(&self, running_global: usize, running_in_project: usize) -> bool {
+        self.max_global.is_none_or(|max| running_global < max)
+            && self
+                .max_per_project
+                .is_none_or(|max| running_in_project < max)
+    }
+}
+
+/// FIFO queue of Claude runs that could not start because a limit was reached
+#[derive(Default)]
+pub struct RunQueue {
+    runs: Mutex<VecDeque<QueuedRun>>,
+    limits: Mutex<ConcurrencyLimits>,
+}
+
+impl RunQueue {
+    pub fn limits(&self) -> ConcurrencyLimits {
+        self.limits
+            .lock()
+            .map(|limits| limits.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_limits(&self, limits: ConcurrencyLimits) {
+        if let Ok(mut current) = self.limits.lock() {
+            *current = limits;
+        }
+    }
+
+    /// Append a run and return its 0-based position in the queue
+    pub fn push(&self, run: QueuedRun) -> Result<usize, String> {
+        let mut runs = self.runs.lock().map_err(|e| e.to_string())?;
+        runs.push_back(run);
+        Ok(runs.len() - 1)
+    }
+
+    pub fn list(&self) -> Result<Vec<QueuedRun>, String> {
+        let runs = self.runs.lock().map_err(|e| e.to_string())?;
+        Ok(runs.iter().cloned().collect())
+    }
+
+    pub fn has_queued_for_project(&self, project_path: &str) -> bool {
+        self.runs
+            .lock()
+            .map(|runs| runs.iter().any(|run| run.project_path == project_path))
+            .unwrap_or(false)
+    }
+
+    /// Remove a queued run, returning whether it was found
+    pub fn remove(&self, id: &str) -> Result<bool, String> {
+        let mut runs = self.runs.lock().map_err(|e| e.to_string())?;
+        let before = runs.len();
+        runs.retain(|run| run.id != id);
+        Ok(runs.len() != before)
+    }
+
+    /// Move a queued run to `position` (clamped to the end of the queue)
+    pub fn move_to(&self, id: &str, position: usize) -> Result<(), String> {
+        let mut runs = self.runs.lock().map_err(|e| e.to_string())?;
+        let index = runs
+            .iter()
+            .position(|run| run.id == id)
+            .ok_or_else(|| format!("Queued run {} not found", id))?;
+        let run = runs.remove(index).expect("index from position");
+        let position = position.min(runs.len());
+        runs.insert(position, run);
+        Ok(())
+    }
+
+    /// Take the first run whose project still has a free slot
+    ///
+    /// Runs for projects at their per-project limit are skipped so they do not
+    /// block other projects, but keep their place in the queue.
+    pub fn take_next_runnable(
+        &self,
+        running_global: usize,
+        running_per_project: &HashMap<String, usize>,
+    ) -> Option<QueuedRun> {
+        let limits = self.limits();
+        let mut runs = self.runs.lock().ok()?;
+        let index = runs.iter().position(|run| {
+            let in_project = running_per_project
+                .get(&run.project_path)
+                .copied()
+                .unwrap_or(0);
+            limits.allows(running_global, in_project)
+        })?;
+        runs.remove(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(id: &str, project_path: &str) -> QueuedRun {
+        QueuedRun {
+            id: id.to_string(),
+            kind: QueuedRunKind::Execute,
+            project_path: project_path.to_string(),
+            prompt: "fix the build".to_string(),
+            model: "sonnet".to_string(),
+            env: HashMap::new(),
+            queued_at: Utc::now(),
+        }
+    }
+
+    fn queue_of(runs: &[(&str, &str)]) -> RunQueue {
+        let queue = RunQueue::default();
+        for (id, project_path) in runs {
+            queue.push(queued(id, project_path)).unwrap();
+        }
+        queue
+    }
+
+    #[test]
+    fn test_take_next_runnable_in_order() {
+        let queue = queue_of(&[("q1", "/work/a"), ("q2", "/work/b"), ("q3", "/work/a")]);
+        let running = HashMap::new();
+        let ids: Vec<String> = std::iter::from_fn(|| queue.take_next_runnable(0, &running))
+            .map(|run| run.id)
+            .collect();
+        assert_eq!(ids, vec!["q1", "q2", "q3"]);
+
+        // Reordered runs start in their new order
+        let queue = queue_of(&[("q1", "/work/a"), ("q2", "/work/b")]);
+        queue.move_to("q2", 0).unwrap();
+        assert_eq!(queue.take_next_runnable(0, &running).unwrap().id, "q2");
+        assert!(queue.move_to("missing", 0).is_err());
+    }
+
+    #[test]
+    fn test_take_next_runnable_respects_limits() {
+        let queue = queue_of(&[("q1", "/work/a"), ("q2", "/work/b"), ("q3", "/work/a")]);
+        queue.set_limits(ConcurrencyLimits {
+            max_global: Some(3),
+            max_per_project: Some(1),
+        });
+
+        // A project at its limit is skipped without losing its place
+        let running = HashMap::from([("/work/a".to_string(), 1)]);
+        assert_eq!(queue.take_next_runnable(1, &running).unwrap().id, "q2");
+        assert!(queue.take_next_runnable(2, &running).is_none());
+        assert_eq!(queue.list().unwrap().len(), 2);
+
+        // Nothing starts while the global limit is reached
+        let idle = HashMap::new();
+        assert!(queue.take_next_runnable(3, &idle).is_none());
+        assert_eq!(queue.take_next_runnable(0, &idle).unwrap().id, "q1");
+
+        assert!(queue.remove("q3").unwrap());
+        assert!(!queue.remove("q3").unwrap());
+        assert!(!queue.has_queued_for_project("/work/a"));
+    }
+}
@@ -1,9 +1,18 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
 use tokio::process::Child;
 
+use super::alerts::{ResourceAlert, ResourceAlertTracker, ResourceThresholds};
+use super::history::{record_run, summarize_usage, RunUsageSummary};
+use super::monitor::ProcessMonitor;
+use super::queue::{QueuedRun, RunQueue};
+use super::tree::ProcessTree;
+
 /// Type of process being tracked
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ProcessType {
@@ -14,6 +23,17 @@ pub enum ProcessType {
     ClaudeSession {
         session_id: String,
     },
+    /// Claude CLI started outside the workbench and adopted for monitoring
+    ExternalClaude {
+        command_line: String,
+        session_id: Option<String>,
+    },
+    /// One step of a scheduled or chained task run
+    ChainStep {
+        chain_id: i64,
+        chain_run_id: i64,
+        step_index: usize,
+    },
 }
 
 /// Information about a running agent process
@@ -28,18 +48,235 @@ pub struct ProcessInfo {
     pub model: String,
 }
 
+/// Registry entry enriched with uptime and live resource usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedProcessInfo {
+    #[serde(flatten)]
+    pub info: ProcessInfo,
+    pub uptime_seconds: i64,
+    /// Whether the PID was found alive when sampling
+    pub is_alive: bool,
+    pub cpu_usage: Option<f32>,
+    pub memory_bytes: Option<u64>,
+    pub child_count: Option<usize>,
+    /// Paused via `suspend_process`
+    pub is_suspended: bool,
+}
+
+/// Maximum number of captured output lines kept per process
+const MAX_OUTPUT_LINES: usize = 10_000;
+
+/// Default time a process gets to exit after an interrupt before it is killed
+pub const DEFAULT_SHUTDOWN_GRACE_MS: u64 = 5_000;
+
+/// Auto-restart policy for a Claude session that exits with a non-zero status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    /// Maximum number of relaunches before giving up
+    pub max_restarts: u32,
+    /// Delay before the first relaunch, doubled for each further attempt
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the backoff delay
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            initial_backoff_ms: 2_000,
+            max_backoff_ms: 60_000,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Backoff delay before the given (1-based) restart attempt
+    pub fn backoff_ms(&self, attempt: u32) -> u64 {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        self.initial_backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms)
+    }
+}
+
+/// Stream a captured output line came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A single captured line of process output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessOutputLine {
+    pub run_id: i64,
+    /// Absolute line offset since the process started
+    pub offset: usize,
+    pub stream: OutputStream,
+    pub line: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A page of captured output used to backfill the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessOutputChunk {
+    pub run_id: i64,
+    pub lines: Vec<ProcessOutputLine>,
+    /// Offset to request next to continue where this chunk ended
+    pub next_offset: usize,
+    /// True if earlier lines were discarded because the buffer was full
+    pub truncated: bool,
+}
+
+/// Bounded line buffer that keeps absolute offsets stable after trimming
+#[derive(Default)]
+pub struct OutputBuffer {
+    lines: VecDeque<ProcessOutputLine>,
+    first_offset: usize,
+}
+
+impl OutputBuffer {
+    fn next_offset(&self) -> usize {
+        self.first_offset + self.lines.len()
+    }
+
+    fn push(&mut self, run_id: i64, stream: OutputStream, line: &str) -> ProcessOutputLine {
+        let entry = ProcessOutputLine {
+            run_id,
+            offset: self.next_offset(),
+            stream,
+            line: line.to_string(),
+            timestamp: Utc::now(),
+        };
+        self.lines.push_back(entry.clone());
+        if self.lines.len() > MAX_OUTPUT_LINES {
+            self.lines.pop_front();
+            self.first_offset += 1;
+        }
+        entry
+    }
+
+    fn usage_summary(&self) -> RunUsageSummary {
+        summarize_usage(
+            self.lines
+                .iter()
+                .filter(|l| l.stream == OutputStream::Stdout)
+                .map(|l| l.line.as_str()),
+        )
+    }
+
+    fn read_from(&self, run_id: i64, offset: usize, limit: usize) -> ProcessOutputChunk {
+        let start = offset.max(self.first_offset) - self.first_offset;
+        let lines: Vec<ProcessOutputLine> =
+            self.lines.iter().skip(start).take(limit).cloned().collect();
+        let next_offset = lines
+            .last()
+            .map(|l| l.offset + 1)
+            .unwrap_or_else(|| offset.max(self.first_offset).min(self.next_offset()));
+        ProcessOutputChunk {
+            run_id,
+            lines,
+            next_offset,
+            truncated: offset < self.first_offset,
+        }
+    }
+}
+
+/// A child process handle shared between the registry and the task awaiting its exit
+pub type SharedChild = Arc<Mutex<Option<Child>>>;
+
+/// Wait until a shared child exits
+///
+/// Returns `None` once the handle has been taken, e.g. by `kill_process`.
+pub async fn wait_for_child(
+    child: &SharedChild,
+) -> Option<std::io::Result<std::process::ExitStatus>> {
+    loop {
+        {
+            let mut child_guard = child.lock().ok()?;
+            match child_guard.as_mut()?.try_wait() {
+                Ok(Some(status)) => return Some(Ok(status)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+}
+
+/// A session slot held by a Claude run that has not registered yet
+struct Reservation {
+    project_path: String,
+    /// PID and handle of the spawned process, once there is one
+    child: Option<(u32, SharedChild)>,
+}
+
+#[derive(Default)]
+struct Reservations {
+    next_id: u64,
+    slots: HashMap<u64, Reservation>,
+}
+
+/// A reserved Claude session slot
+///
+/// It counts against the concurrency limits until it is handed to
+/// `register_claude_session`, and is released when dropped before that, e.g.
+/// because the spawn failed.
+pub struct SessionSlot {
+    id: u64,
+    reservations: Arc<Mutex<Reservations>>,
+}
+
+impl SessionSlot {
+    /// Record the process started in this slot so it can be killed before it registers
+    pub fn set_child(&self, pid: u32, child: SharedChild) {
+        if let Ok(mut reservations) = self.reservations.lock() {
+            if let Some(reservation) = reservations.slots.get_mut(&self.id) {
+                reservation.child = Some((pid, child));
+            }
+        }
+    }
+}
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        if let Ok(mut reservations) = self.reservations.lock() {
+            reservations.slots.remove(&self.id);
+        }
+    }
+}
+
 /// Information about a running process with handle
 #[allow(dead_code)]
 pub struct ProcessHandle {
     pub info: ProcessInfo,
-    pub child: Arc<Mutex<Option<Child>>>,
+    pub child: SharedChild,
     pub live_output: Arc<Mutex<String>>,
+    pub output: Arc<Mutex<OutputBuffer>>,
+    pub tree: Option<Arc<ProcessTree>>, // Containment for the process and its children
 }
 
 /// Registry for tracking active agent processes
 pub struct ProcessRegistry {
     processes: Arc<Mutex<HashMap<i64, ProcessHandle>>>, // run_id -> ProcessHandle
     next_id: Arc<Mutex<i64>>, // Auto-incrementing ID for non-agent processes
+    monitor: Arc<ProcessMonitor>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>, // Used to emit per-run output events
+    shutdown_grace_ms: Arc<Mutex<u64>>,
+    restart_policies: Arc<Mutex<HashMap<String, RestartPolicy>>>, // session_id -> policy
+    restart_attempts: Arc<Mutex<HashMap<String, u32>>>, // session_id -> restarts so far
+    stop_requested: Arc<Mutex<HashSet<i64>>>, // Sessions terminated on purpose, not crashed
+    reservations: Arc<Mutex<Reservations>>, // Session slots of runs starting but not yet registered
+    run_queue: Arc<RunQueue>,
+    persistence: Arc<Mutex<Option<Connection>>>, // Mirrors entries to `managed_processes`
+    orphans: Arc<Mutex<Vec<ProcessInfo>>>, // Still-running processes left by a previous launch
+    recorded_runs: Arc<Mutex<HashSet<i64>>>, // Runs already written to `run_history`
+    suspended: Arc<Mutex<HashSet<i64>>>, // Runs paused via `suspend_process`
+    env_overrides: Arc<Mutex<HashMap<i64, HashMap<String, String>>>>, // Per-launch env of sessions
+    resource_thresholds: Arc<Mutex<ResourceThresholds>>,
+    alert_tracker: Arc<Mutex<ResourceAlertTracker>>,
 }
 
 impl ProcessRegistry {
@@ -47,17 +284,502 @@ impl ProcessRegistry {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1000000)), // Start at high number to avoid conflicts
+            monitor: Arc::new(ProcessMonitor::new()),
+            app_handle: Arc::new(Mutex::new(None)),
+            shutdown_grace_ms: Arc::new(Mutex::new(DEFAULT_SHUTDOWN_GRACE_MS)),
+            restart_policies: Arc::new(Mutex::new(HashMap::new())),
+            restart_attempts: Arc::new(Mutex::new(HashMap::new())),
+            stop_requested: Arc::new(Mutex::new(HashSet::new())),
+            reservations: Arc::new(Mutex::new(Reservations::default())),
+            run_queue: Arc::new(RunQueue::default()),
+            persistence: Arc::new(Mutex::new(None)),
+            orphans: Arc::new(Mutex::new(Vec::new())),
+            recorded_runs: Arc::new(Mutex::new(HashSet::new())),
+            suspended: Arc::new(Mutex::new(HashSet::new())),
+            env_overrides: Arc::new(Mutex::new(HashMap::new())),
+            resource_thresholds: Arc::new(Mutex::new(ResourceThresholds::default())),
+            alert_tracker: Arc::new(Mutex::new(ResourceAlertTracker::default())),
+        }
+    }
+
+    /// Grace period between the interrupt and the hard kill in `shutdown_process`
+    pub fn shutdown_grace_period_ms(&self) -> u64 {
+        self.shutdown_grace_ms
+            .lock()
+            .map(|ms| *ms)
+            .unwrap_or(DEFAULT_SHUTDOWN_GRACE_MS)
+    }
+
+    pub fn set_shutdown_grace_period_ms(&self, grace_ms: u64) {
+        if let Ok(mut ms) = self.shutdown_grace_ms.lock() {
+            *ms = grace_ms;
+        }
+    }
+
+    /// Enable (`Some`) or disable (`None`) auto-restart for a Claude session
+    pub fn set_restart_policy(
+        &self,
+        session_id: &str,
+        policy: Option<RestartPolicy>,
+    ) -> Result<(), String> {
+        let mut policies = self.restart_policies.lock().map_err(|e| e.to_string())?;
+        match policy {
+            Some(policy) => {
+                policies.insert(session_id.to_string(), policy);
+            }
+            None => {
+                policies.remove(session_id);
+                drop(policies);
+                self.clear_restart_attempts(session_id);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_restart_policy(&self, session_id: &str) -> Option<RestartPolicy> {
+        self.restart_policies
+            .lock()
+            .ok()
+            .and_then(|policies| policies.get(session_id).cloned())
+    }
+
+    /// Reserve the next restart attempt for a crashed session
+    ///
+    /// Returns the 1-based attempt number, or `None` once the policy's limit is reached.
+    pub fn next_restart_attempt(&self, session_id: &str) -> Option<u32> {
+        let policy = self.get_restart_policy(session_id)?;
+        let mut attempts = self.restart_attempts.lock().ok()?;
+        let count = attempts.entry(session_id.to_string()).or_insert(0);
+        if *count >= policy.max_restarts {
+            return None;
+        }
+        *count += 1;
+        Some(*count)
+    }
+
+    pub fn clear_restart_attempts(&self, session_id: &str) {
+        if let Ok(mut attempts) = self.restart_attempts.lock() {
+            attempts.remove(session_id);
+        }
+    }
+
+    /// Move policy and attempt count to the session ID reported by a relaunched process
+    pub fn transfer_restart_state(&self, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+        if let Ok(mut policies) = self.restart_policies.lock() {
+            if let Some(policy) = policies.remove(from) {
+                policies.insert(to.to_string(), policy);
+            }
+        }
+        if let Ok(mut attempts) = self.restart_attempts.lock() {
+            if let Some(count) = attempts.remove(from) {
+                attempts.insert(to.to_string(), count);
+            }
+        }
+    }
+
+    /// Remember that a Claude session is being stopped on purpose so its
+    /// non-zero exit is not treated as a crash
+    fn mark_stop_requested(&self, run_id: i64) {
+        let is_session = self
+            .processes
+            .lock()
+            .map(|processes| {
+                processes.get(&run_id).map_or(false, |handle| {
+                    matches!(handle.info.process_type, ProcessType::ClaudeSession { .. })
+                })
+            })
+            .unwrap_or(false);
+
+        if is_session {
+            if let Ok(mut stopped) = self.stop_requested.lock() {
+                stopped.insert(run_id);
+            }
+        }
+    }
+
+    /// Check (and clear) whether a session's exit was requested
+    pub fn take_stop_requested(&self, run_id: i64) -> bool {
+        self.stop_requested
+            .lock()
+            .map(|mut stopped| stopped.remove(&run_id))
+            .unwrap_or(false)
+    }
+
+    /// Queue of Claude runs waiting for a free session slot
+    pub fn run_queue(&self) -> &RunQueue {
+        &self.run_queue
+    }
+
+    /// Kill Claude processes that were spawned but have not registered a session yet,
+    /// returning how many were signalled
+    pub fn kill_launching_sessions(&self) -> usize {
+        let Ok(reservations) = self.reservations.lock() else {
+            return 0;
+        };
+        let mut killed = 0;
+        for (pid, child) in reservations.slots.values().filter_map(|slot| slot.child.as_ref()) {
+            let Ok(mut child_guard) = child.lock() else {
+                continue;
+            };
+            if let Some(child) = child_guard.as_mut() {
+                match child.start_kill() {
+                    Ok(()) => killed += 1,
+                    Err(e) => log::warn!("Failed to kill launching Claude process {}: {}", pid, e),
+                }
+            }
+        }
+        killed
+    }
+
+    /// Running (or reserved) Claude sessions, globally and per project path
+    ///
+    /// Takes the locked reservations so checking and reserving a slot is one step.
+    fn session_counts(&self, reservations: &Reservations) -> (usize, HashMap<String, usize>) {
+        let mut per_project: HashMap<String, usize> = HashMap::new();
+        let mut total = 0;
+
+        if let Ok(processes) = self.processes.lock() {
+            for handle in processes.values() {
+                if let ProcessType::ClaudeSession { .. } = handle.info.process_type {
+                    *per_project.entry(handle.info.project_path.clone()).or_default() += 1;
+                    total += 1;
+                }
+            }
+        }
+        for slot in reservations.slots.values() {
+            *per_project.entry(slot.project_path.clone()).or_default() += 1;
+            total += 1;
+        }
+
+        (total, per_project)
+    }
+
+    fn reserve(&self, reservations: &mut Reservations, project_path: &str) -> SessionSlot {
+        reservations.next_id += 1;
+        let id = reservations.next_id;
+        reservations.slots.insert(
+            id,
+            Reservation {
+                project_path: project_path.to_string(),
+                child: None,
+            },
+        );
+        SessionSlot {
+            id,
+            reservations: self.reservations.clone(),
+        }
+    }
+
+    /// Reserve a slot for a new Claude session in `project_path`, or `None`
+    /// when the configured limits are reached
+    pub fn try_reserve_session_slot(&self, project_path: &str) -> Option<SessionSlot> {
+        let mut reservations = self.reservations.lock().ok()?;
+        let (total, per_project) = self.session_counts(&reservations);
+        let in_project = per_project.get(project_path).copied().unwrap_or(0);
+        if !self.run_queue.limits().allows(total, in_project) {
+            return None;
+        }
+        Some(self.reserve(&mut reservations, project_path))
+    }
+
+    /// Pop the next queued run that fits within the limits, with the slot reserved for it
+    pub fn take_next_queued_run(&self) -> Option<(QueuedRun, SessionSlot)> {
+        let mut reservations = self.reservations.lock().ok()?;
+        let (total, per_project) = self.session_counts(&reservations);
+        let run = self.run_queue.take_next_runnable(total, &per_project)?;
+        let slot = self.reserve(&mut reservations, &run.project_path);
+        Some((run, slot))
+    }
+
+    /// Attach the app handle so captured output can be streamed to the frontend
+    pub fn set_app_handle(&self, app: AppHandle) {
+        if let Ok(mut handle) = self.app_handle.lock() {
+            *handle = Some(app);
         }
     }
 
     /// Generate a unique ID for non-agent processes
     pub fn generate_id(&self) -> Result<i64, String> {
         let mut next_id = self.next_id.lock().map_err(|e| e.to_string())?;
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        // Adopted processes keep their original IDs, skip any still in use
+        while processes.contains_key(&*next_id) {
+            *next_id += 1;
+        }
         let id = *next_id;
         *next_id += 1;
         Ok(id)
     }
 
+    /// Mirror registry entries to the `managed_processes` table in `db_path`
+    pub fn enable_persistence(&self, db_path: &Path) -> Result<(), String> {
+        let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+        let mut persistence = self.persistence.lock().map_err(|e| e.to_string())?;
+        *persistence = Some(conn);
+        Ok(())
+    }
+
+    fn persist_process(&self, info: &ProcessInfo) {
+        let Ok(persistence) = self.persistence.lock() else {
+            return;
+        };
+        let Some(conn) = persistence.as_ref() else {
+            return;
+        };
+
+        let process_type = serde_json::to_string(&info.process_type).unwrap_or_default();
+        if let Err(e) = conn.execute(
+            "INSERT OR REPLACE INTO managed_processes (run_id, process_type, pid, started_at, project_path, task, model)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                info.run_id,
+                process_type,
+                info.pid,
+                info.started_at.to_rfc3339(),
+                info.project_path,
+                info.task,
+                info.model
+            ],
+        ) {
+            log::warn!("Failed to persist process {}: {}", info.run_id, e);
+        }
+    }
+
+    fn forget_persisted_process(&self, run_id: i64) {
+        if let Ok(persistence) = self.persistence.lock() {
+            if let Some(conn) = persistence.as_ref() {
+                let _ = conn.execute(
+                    "DELETE FROM managed_processes WHERE run_id = ?1",
+                    params![run_id],
+                );
+            }
+        }
+    }
+
+    /// Record a run in `run_history` once, keeping it registered
+    ///
+    /// `status` is "completed", "failed", "killed" or "interrupted". Later calls
+    /// for the same run (e.g. a kill after the exit was already recorded) are
+    /// ignored.
+    pub fn record_history(
+        &self,
+        run_id: i64,
+        exit_code: Option<i32>,
+        status: &str,
+    ) -> Result<(), String> {
+        let finished = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            processes.get(&run_id).map(|handle| {
+                let usage = handle
+                    .output
+                    .lock()
+                    .map(|output| output.usage_summary())
+                    .unwrap_or_default();
+                (handle.info.clone(), usage)
+            })
+        };
+        let Some((info, usage)) = finished else {
+            return Ok(());
+        };
+
+        {
+            let mut recorded = self.recorded_runs.lock().map_err(|e| e.to_string())?;
+            if !recorded.insert(run_id) {
+                return Ok(());
+            }
+        }
+
+        let persistence = self.persistence.lock().map_err(|e| e.to_string())?;
+        if let Some(conn) = persistence.as_ref() {
+            record_run(conn, &info, Utc::now(), exit_code, status, &usage)?;
+        }
+        Ok(())
+    }
+
+    /// Record a run in `run_history` and remove it from the registry
+    ///
+    /// Does nothing if the run was already removed (e.g. killed before its exit was observed).
+    pub fn finish_process(
+        &self,
+        run_id: i64,
+        exit_code: Option<i32>,
+        status: &str,
+    ) -> Result<(), String> {
+        if let Err(e) = self.record_history(run_id, exit_code, status) {
+            log::warn!("{}", e);
+        }
+        self.unregister_process(run_id)
+    }
+
+    /// Wait briefly for a registered child to exit and return its exit code
+    pub async fn collect_exit_code(&self, run_id: i64) -> Option<i32> {
+        let child_arc = {
+            let processes = self.processes.lock().ok()?;
+            processes.get(&run_id)?.child.clone()
+        };
+
+        for _ in 0..50 {
+            {
+                let mut child_guard = child_arc.lock().ok()?;
+                match child_guard.as_mut()?.try_wait() {
+                    Ok(Some(status)) => return status.code(),
+                    Ok(None) => {}
+                    Err(_) => return None,
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        None
+    }
+
+    /// Wait until a registered child exits
+    ///
+    /// Returns `None` if the run is removed from the registry first (e.g. killed)
+    /// or has no child handle.
+    pub async fn wait_for_exit(&self, run_id: i64) -> Option<std::process::ExitStatus> {
+        loop {
+            let child_arc = {
+                let processes = self.processes.lock().ok()?;
+                processes.get(&run_id)?.child.clone()
+            };
+            {
+                let mut child_guard = child_arc.lock().ok()?;
+                match child_guard.as_mut()?.try_wait() {
+                    Ok(Some(status)) => return Some(status),
+                    Ok(None) => {}
+                    Err(_) => return None,
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Find processes persisted by a previous launch that are still running
+    ///
+    /// Must run before anything is registered in this launch. Entries whose PID is
+    /// gone (or now belongs to something other than Claude) are dropped.
+    pub fn scan_orphans(&self) -> Result<Vec<ProcessInfo>, String> {
+        let stale: Vec<ProcessInfo> = {
+            let persistence = self.persistence.lock().map_err(|e| e.to_string())?;
+            let Some(conn) = persistence.as_ref() else {
+                return Ok(Vec::new());
+            };
+
+            let mut stmt = conn
+                .prepare("SELECT run_id, process_type, pid, started_at, project_path, task, model FROM managed_processes")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, u32>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                })
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+
+            rows.into_iter()
+                .filter_map(|(run_id, process_type, pid, started_at, project_path, task, model)| {
+                    Some(ProcessInfo {
+                        run_id,
+                        process_type: serde_json::from_str(&process_type).ok()?,
+                        pid,
+                        started_at: DateTime::parse_from_rfc3339(&started_at)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                        project_path,
+                        task,
+                        model,
+                    })
+                })
+                .collect()
+        };
+
+        let mut orphans = Vec::new();
+        for info in stale {
+            if info.pid != 0 && super::discovery::is_claude_process(info.pid) {
+                log::warn!(
+                    "Found orphaned process {} (PID: {}) from a previous launch",
+                    info.run_id,
+                    info.pid
+                );
+                orphans.push(info);
+            } else {
+                self.forget_persisted_process(info.run_id);
+            }
+        }
+
+        let mut stored = self.orphans.lock().map_err(|e| e.to_string())?;
+        *stored = orphans.clone();
+        Ok(orphans)
+    }
+
+    /// Orphaned processes that have not been adopted or killed yet
+    pub fn get_orphans(&self) -> Result<Vec<ProcessInfo>, String> {
+        let orphans = self.orphans.lock().map_err(|e| e.to_string())?;
+        Ok(orphans.clone())
+    }
+
+    fn take_orphan(&self, run_id: i64) -> Result<Option<ProcessInfo>, String> {
+        let mut orphans = self.orphans.lock().map_err(|e| e.to_string())?;
+        Ok(orphans
+            .iter()
+            .position(|info| info.run_id == run_id)
+            .map(|index| orphans.remove(index)))
+    }
+
+    /// Track an orphaned process again so it can be monitored and stopped
+    pub fn adopt_orphan(&self, run_id: i64) -> Result<bool, String> {
+        let Some(info) = self.take_orphan(run_id)? else {
+            return Ok(false);
+        };
+        if !super::discovery::is_claude_process(info.pid) {
+            self.forget_persisted_process(run_id);
+            return Ok(false);
+        }
+
+        log::info!("Adopting orphaned process {} (PID: {})", run_id, info.pid);
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let tree = Self::attach_tree(info.pid);
+        processes.insert(
+            run_id,
+            ProcessHandle {
+                info,
+                child: Arc::new(Mutex::new(None)),
+                live_output: Arc::new(Mutex::new(String::new())),
+                output: Arc::new(Mutex::new(OutputBuffer::default())),
+                tree,
+            },
+        );
+        Ok(true)
+    }
+
+    /// Kill an orphaned process together with its children
+    pub fn kill_orphan(&self, run_id: i64) -> Result<bool, String> {
+        let Some(info) = self.take_orphan(run_id)? else {
+            return Ok(false);
+        };
+        self.forget_persisted_process(run_id);
+
+        // Never signal a PID that has since been reused by another program
+        if !super::discovery::is_claude_process(info.pid) {
+            return Ok(false);
+        }
+
+        log::info!("Killing orphaned process {} (PID: {})", run_id, info.pid);
+        super::tree::kill_tree_by_pid(info.pid)?;
+        Ok(true)
+    }
+
     /// Register a new running agent process
     pub fn register_process(
         &self,
@@ -83,7 +805,42 @@ impl ProcessRegistry {
         self.register_process_internal(run_id, process_info, child)
     }
 
-    /// Register a new Claude session (without child process - handled separately)
+    /// Register a running step of a task chain, returning its run ID
+    pub fn register_chain_step(
+        &self,
+        chain_id: i64,
+        chain_run_id: i64,
+        step_index: usize,
+        pid: u32,
+        project_path: String,
+        task: String,
+        model: String,
+        child: Child,
+    ) -> Result<i64, String> {
+        let run_id = self.generate_id()?;
+        let process_info = ProcessInfo {
+            run_id,
+            process_type: ProcessType::ChainStep {
+                chain_id,
+                chain_run_id,
+                step_index,
+            },
+            pid,
+            started_at: Utc::now(),
+            project_path,
+            task,
+            model,
+        };
+
+        self.register_process_internal(run_id, process_info, child)?;
+        Ok(run_id)
+    }
+
+    /// Register a new Claude session in the slot reserved for it
+    ///
+    /// The child stays shared with the task awaiting its exit, so every session
+    /// keeps its own handle and can be stopped on its own.
+    #[allow(clippy::too_many_arguments)]
     pub fn register_claude_session(
         &self,
         session_id: String,
@@ -91,6 +848,8 @@ impl ProcessRegistry {
         project_path: String,
         task: String,
         model: String,
+        child: SharedChild,
+        slot: SessionSlot,
     ) -> Result<i64, String> {
         let run_id = self.generate_id()?;
         
@@ -104,16 +863,24 @@ impl ProcessRegistry {
             model,
         };
 
-        // Register without child - Claude sessions use ClaudeProcessState for process management
+        // The session takes over the slot without it ever being free
+        let mut reservations = self.reservations.lock().map_err(|e| e.to_string())?;
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
         
         let process_handle = ProcessHandle {
             info: process_info,
-            child: Arc::new(Mutex::new(None)), // No child handle for Claude sessions
+            child,
             live_output: Arc::new(Mutex::new(String::new())),
+            output: Arc::new(Mutex::new(OutputBuffer::default())),
+            tree: Self::attach_tree(pid),
         };
 
+        self.persist_process(&process_handle.info);
         processes.insert(run_id, process_handle);
+        reservations.slots.remove(&slot.id);
+        drop(processes);
+        drop(reservations);
+        drop(slot);
         Ok(run_id)
     }
 
@@ -126,16 +893,34 @@ impl ProcessRegistry {
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
+        let tree = Self::attach_tree(process_info.pid);
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
             live_output: Arc::new(Mutex::new(String::new())),
+            output: Arc::new(Mutex::new(OutputBuffer::default())),
+            tree,
         };
 
+        self.persist_process(&process_handle.info);
         processes.insert(run_id, process_handle);
         Ok(())
     }
 
+    /// Track the process tree rooted at `pid`, logging (not failing) when unavailable
+    fn attach_tree(pid: u32) -> Option<Arc<ProcessTree>> {
+        if pid == 0 {
+            return None;
+        }
+        match ProcessTree::attach(pid) {
+            Ok(tree) => Some(Arc::new(tree)),
+            Err(e) => {
+                log::warn!("Failed to track process tree for PID {}: {}", pid, e);
+                None
+            }
+        }
+    }
+
     /// Get all running Claude sessions
     pub fn get_running_claude_sessions(&self) -> Result<Vec<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -150,6 +935,58 @@ impl ProcessRegistry {
             .collect())
     }
 
+    /// Get all adopted external Claude processes
+    pub fn get_external_claude_processes(&self) -> Result<Vec<ProcessInfo>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .values()
+            .filter(|handle| matches!(handle.info.process_type, ProcessType::ExternalClaude { .. }))
+            .map(|handle| handle.info.clone())
+            .collect())
+    }
+
+    /// PIDs of every tracked process
+    pub fn tracked_pids(&self) -> Result<HashSet<u32>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes.values().map(|handle| handle.info.pid).collect())
+    }
+
+    /// Adopt a Claude process started outside the workbench, returning its run ID
+    pub fn adopt_external_process(&self, pid: u32) -> Result<i64, String> {
+        if self.tracked_pids()?.contains(&pid) {
+            return Err(format!("Process {} is already tracked", pid));
+        }
+        let external = super::discovery::find_claude_process(pid)
+            .ok_or_else(|| format!("Process {} is not a running Claude process", pid))?;
+
+        let run_id = self.generate_id()?;
+        let info = ProcessInfo {
+            run_id,
+            process_type: ProcessType::ExternalClaude {
+                command_line: external.command_line,
+                session_id: external.session_id,
+            },
+            pid,
+            started_at: DateTime::from_timestamp(external.started_at, 0).unwrap_or_else(Utc::now),
+            project_path: external.cwd.unwrap_or_default(),
+            task: String::new(),
+            model: external.model.unwrap_or_default(),
+        };
+
+        log::info!("Adopting external Claude process (PID: {}) as run {}", pid, run_id);
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let process_handle = ProcessHandle {
+            info,
+            child: Arc::new(Mutex::new(None)),
+            live_output: Arc::new(Mutex::new(String::new())),
+            output: Arc::new(Mutex::new(OutputBuffer::default())),
+            tree: Self::attach_tree(pid),
+        };
+        self.persist_process(&process_handle.info);
+        processes.insert(run_id, process_handle);
+        Ok(run_id)
+    }
+
     /// Get a specific Claude session by session ID
     pub fn get_claude_session_by_id(&self, session_id: &str) -> Result<Option<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -169,11 +1006,57 @@ impl ProcessRegistry {
     pub fn unregister_process(&self, run_id: i64) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
         processes.remove(&run_id);
+        drop(processes);
+        self.forget_persisted_process(run_id);
+        if let Ok(mut recorded) = self.recorded_runs.lock() {
+            recorded.remove(&run_id);
+        }
+        if let Ok(mut suspended) = self.suspended.lock() {
+            suspended.remove(&run_id);
+        }
+        if let Ok(mut overrides) = self.env_overrides.lock() {
+            overrides.remove(&run_id);
+        }
         Ok(())
     }
 
+    /// Remember the environment overrides a session was launched with
+    pub fn set_env_overrides(&self, run_id: i64, env: HashMap<String, String>) {
+        if env.is_empty() {
+            return;
+        }
+        if let Ok(mut overrides) = self.env_overrides.lock() {
+            overrides.insert(run_id, env);
+        }
+    }
+
+    pub fn get_env_overrides(&self, run_id: i64) -> HashMap<String, String> {
+        self.env_overrides
+            .lock()
+            .ok()
+            .and_then(|overrides| overrides.get(&run_id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Whether a session talks to its own relay instead of the global provider config
+    pub fn has_provider_override(&self, run_id: i64) -> bool {
+        const PROVIDER_KEYS: [&str; 3] = [
+            "ANTHROPIC_BASE_URL",
+            "ANTHROPIC_AUTH_TOKEN",
+            "ANTHROPIC_API_KEY",
+        ];
+        self.env_overrides
+            .lock()
+            .ok()
+            .and_then(|overrides| {
+                overrides
+                    .get(&run_id)
+                    .map(|env| PROVIDER_KEYS.iter().any(|key| env.contains_key(*key)))
+            })
+            .unwrap_or(false)
+    }
+
     /// Get all running processes
-    #[allow(dead_code)]
     pub fn get_running_processes(&self) -> Result<Vec<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         Ok(processes
@@ -182,6 +1065,137 @@ impl ProcessRegistry {
             .collect())
     }
 
+    /// Get every registry entry with uptime and live CPU/RAM usage
+    pub async fn list_managed_processes(&self) -> Result<Vec<ManagedProcessInfo>, String> {
+        let infos = self.get_running_processes()?;
+        let pids: Vec<u32> = infos.iter().map(|info| info.pid).filter(|pid| *pid != 0).collect();
+        let usage = self.monitor.sample(&pids).await?;
+        let now = Utc::now();
+
+        let mut managed: Vec<ManagedProcessInfo> = infos
+            .into_iter()
+            .map(|info| {
+                let sample = usage.get(&info.pid);
+                ManagedProcessInfo {
+                    uptime_seconds: (now - info.started_at).num_seconds().max(0),
+                    is_alive: sample.is_some(),
+                    cpu_usage: sample.map(|s| s.cpu_usage),
+                    memory_bytes: sample.map(|s| s.memory_bytes),
+                    child_count: sample.map(|s| s.child_count),
+                    is_suspended: self.is_suspended(info.run_id),
+                    info,
+                }
+            })
+            .collect();
+
+        managed.sort_by(|a, b| a.info.started_at.cmp(&b.info.started_at));
+        Ok(managed)
+    }
+
+    pub fn resource_thresholds(&self) -> ResourceThresholds {
+        self.resource_thresholds
+            .lock()
+            .map(|thresholds| thresholds.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_resource_thresholds(&self, thresholds: ResourceThresholds) {
+        if let Ok(mut current) = self.resource_thresholds.lock() {
+            *current = thresholds;
+        }
+    }
+
+    /// Sample every managed process and return alerts for sustained threshold breaches
+    ///
+    /// With `auto_kill` enabled the offending process tree is killed before returning.
+    pub async fn check_resource_usage(&self) -> Result<Vec<ResourceAlert>, String> {
+        let thresholds = self.resource_thresholds();
+        let infos = self.get_running_processes()?;
+        let pids: Vec<u32> = infos.iter().map(|info| info.pid).filter(|pid| *pid != 0).collect();
+        let usage = self.monitor.sample(&pids).await?;
+        let now = std::time::Instant::now();
+
+        let mut alerts = Vec::new();
+        {
+            let mut tracker = self.alert_tracker.lock().map_err(|e| e.to_string())?;
+            let sampled: Vec<i64> = infos
+                .iter()
+                .filter(|info| usage.contains_key(&info.pid))
+                .map(|info| info.run_id)
+                .collect();
+            tracker.retain_runs(&sampled);
+
+            for info in &infos {
+                let Some(sample) = usage.get(&info.pid) else {
+                    continue;
+                };
+                for (resource, value, threshold, elapsed) in
+                    tracker.evaluate(&thresholds, info.run_id, sample, now)
+                {
+                    alerts.push(ResourceAlert {
+                        run_id: info.run_id,
+                        pid: info.pid,
+                        project_path: info.project_path.clone(),
+                        resource,
+                        value,
+                        threshold,
+                        exceeded_for_secs: elapsed.as_secs(),
+                        killed: false,
+                    });
+                }
+            }
+        }
+
+        if thresholds.auto_kill {
+            for alert in alerts.iter_mut() {
+                log::warn!(
+                    "Killing process {} (PID: {}) for exceeding its {:?} limit",
+                    alert.run_id,
+                    alert.pid,
+                    alert.resource
+                );
+                alert.killed = matches!(self.kill_process(alert.run_id).await, Ok(true));
+            }
+        }
+        Ok(alerts)
+    }
+
+    /// Periodically check resource usage and emit `process-resource-alert` events
+    pub fn start_resource_watchdog(self: &Arc<Self>) {
+        let registry = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let interval = registry.resource_thresholds().check_interval_secs.max(1);
+                tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+                if !registry.resource_thresholds().enabled {
+                    continue;
+                }
+
+                let alerts = match registry.check_resource_usage().await {
+                    Ok(alerts) => alerts,
+                    Err(e) => {
+                        log::warn!("Failed to check process resource usage: {}", e);
+                        continue;
+                    }
+                };
+                let app = registry.app_handle.lock().ok().and_then(|app| app.clone());
+                for alert in alerts {
+                    log::warn!(
+                        "Process {} exceeded {:?} threshold: {:.1} > {:.1} for {}s",
+                        alert.run_id,
+                        alert.resource,
+                        alert.value,
+                        alert.threshold,
+                        alert.exceeded_for_secs
+                    );
+                    if let Some(app) = &app {
+                        let _ = app.emit("process-resource-alert", &alert);
+                    }
+                }
+            }
+        });
+    }
+
     /// Get all running agent processes
     pub fn get_running_agent_processes(&self) -> Result<Vec<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -197,7 +1211,6 @@ impl ProcessRegistry {
     }
 
     /// Get a specific running process
-    #[allow(dead_code)]
     pub fn get_process(&self, run_id: i64) -> Result<Option<ProcessInfo>, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         Ok(processes.get(&run_id).map(|handle| handle.info.clone()))
@@ -207,11 +1220,13 @@ impl ProcessRegistry {
     pub async fn kill_process(&self, run_id: i64) -> Result<bool, String> {
         use log::{error, info, warn};
 
+        self.mark_stop_requested(run_id);
+
         // First check if the process exists and get its PID
-        let (pid, child_arc) = {
+        let (pid, child_arc, tree) = {
             let processes = self.processes.lock().map_err(|e| e.to_string())?;
             if let Some(handle) = processes.get(&run_id) {
-                (handle.info.pid, handle.child.clone())
+                (handle.info.pid, handle.child.clone(), handle.tree.clone())
             } else {
                 warn!("Process {} not found in registry", run_id);
                 return Ok(false); // Process not found
@@ -223,6 +1238,14 @@ impl ProcessRegistry {
             run_id, pid
         );
 
+        // Take down child processes (node workers etc.) along with the root
+        if let Some(tree) = &tree {
+            match tree.terminate() {
+                Ok(()) => info!("Terminated process tree of {} (PID: {})", run_id, pid),
+                Err(e) => warn!("Failed to terminate process tree of {}: {}", run_id, e),
+            }
+        }
+
         // Send kill signal to the process
         let kill_sent = {
             let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
@@ -313,87 +1336,180 @@ impl ProcessRegistry {
         }
 
         // Remove from registry after killing
-        self.unregister_process(run_id)?;
+        self.finish_process(run_id, None, "killed")?;
 
         Ok(true)
     }
 
+    /// Shut a process down gracefully: interrupt it, wait for the grace period so
+    /// the Claude CLI can flush its session file, then escalate to `kill_process`
+    pub async fn shutdown_process(&self, run_id: i64) -> Result<bool, String> {
+        use log::{info, warn};
+
+        self.mark_stop_requested(run_id);
+
+        let (pid, child_arc) = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => (handle.info.pid, handle.child.clone()),
+                None => {
+                    warn!("Process {} not found in registry", run_id);
+                    return Ok(false);
+                }
+            }
+        };
+
+        if pid == 0 {
+            return self.kill_process(run_id).await;
+        }
+
+        // A suspended process cannot react to the interrupt
+        self.resume_if_suspended(run_id, pid);
+
+        info!("Sending interrupt to process {} (PID: {})", run_id, pid);
+        if let Err(e) = super::shutdown::send_interrupt(pid) {
+            warn!("Failed to interrupt process {} (PID: {}): {}", run_id, pid, e);
+            return self.kill_process(run_id).await;
+        }
+
+        let grace = tokio::time::Duration::from_millis(self.shutdown_grace_period_ms());
+        let deadline = tokio::time::Instant::now() + grace;
+        while tokio::time::Instant::now() < deadline {
+            let exited = {
+                let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+                match child_guard.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(Some(_)) => {
+                            *child_guard = None;
+                            true
+                        }
+                        Ok(None) => false,
+                        Err(_) => !super::shutdown::is_pid_alive(pid),
+                    },
+                    None => !super::shutdown::is_pid_alive(pid),
+                }
+            };
+
+            if exited {
+                info!("Process {} exited gracefully after interrupt", run_id);
+                self.finish_process(run_id, None, "killed")?;
+                return Ok(true);
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        }
+
+        warn!(
+            "Process {} did not exit within {}ms of interrupt, killing",
+            run_id,
+            grace.as_millis()
+        );
+        self.kill_process(run_id).await
+    }
+
     /// Kill a process by PID using system commands (fallback method)
     pub fn kill_process_by_pid(&self, run_id: i64, pid: u32) -> Result<bool, String> {
         use log::{error, info, warn};
 
         info!("Attempting to kill process {} by PID {}", run_id, pid);
+        self.mark_stop_requested(run_id);
+        self.resume_if_suspended(run_id, pid);
 
-        let kill_result = if cfg!(target_os = "windows") {
-            use std::os::windows::process::CommandExt;
-            std::process::Command::new("taskkill")
-                .args(["/F", "/PID", &pid.to_string()])
-                .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                .output()
-        } else {
-            // First try SIGTERM
-            let term_result = std::process::Command::new("kill")
-                .args(["-TERM", &pid.to_string()])
-                .output();
-
-            match &term_result {
-                Ok(output) if output.status.success() => {
-                    info!("Sent SIGTERM to PID {}", pid);
-                    // Give it 2 seconds to exit gracefully
-                    std::thread::sleep(std::time::Duration::from_secs(2));
-
-                    // Check if still running
-                    let check_result = std::process::Command::new("kill")
-                        .args(["-0", &pid.to_string()])
-                        .output();
-
-                    if let Ok(output) = check_result {
-                        if output.status.success() {
-                            // Still running, send SIGKILL
-                            warn!(
-                                "Process {} still running after SIGTERM, sending SIGKILL",
-                                pid
-                            );
-                            std::process::Command::new("kill")
-                                .args(["-KILL", &pid.to_string()])
-                                .output()
-                        } else {
-                            term_result
-                        }
-                    } else {
-                        term_result
-                    }
-                }
-                _ => {
-                    // SIGTERM failed, try SIGKILL directly
-                    warn!("SIGTERM failed for PID {}, trying SIGKILL", pid);
-                    std::process::Command::new("kill")
-                        .args(["-KILL", &pid.to_string()])
-                        .output()
+        // Signal the whole tree so child processes do not outlive the root
+        #[cfg(target_os = "windows")]
+        let kill_result = super::tree::kill_tree_by_pid(pid);
+
+        #[cfg(not(target_os = "windows"))]
+        let kill_result = match super::tree::signal_tree(pid, "TERM") {
+            Ok(()) => {
+                info!("Sent SIGTERM to PID {}", pid);
+                // Give it 2 seconds to exit gracefully
+                std::thread::sleep(std::time::Duration::from_secs(2));
+
+                if super::shutdown::is_pid_alive(pid) {
+                    warn!(
+                        "Process {} still running after SIGTERM, sending SIGKILL",
+                        pid
+                    );
+                    super::tree::signal_tree(pid, "KILL")
+                } else {
+                    Ok(())
                 }
             }
+            Err(e) => {
+                // SIGTERM failed, try SIGKILL directly
+                warn!("SIGTERM failed for PID {} ({}), trying SIGKILL", pid, e);
+                super::tree::signal_tree(pid, "KILL")
+            }
         };
 
         match kill_result {
-            Ok(output) => {
-                if output.status.success() {
-                    info!("Successfully killed process with PID {}", pid);
-                    // Remove from registry
-                    self.unregister_process(run_id)?;
-                    Ok(true)
-                } else {
-                    let error_msg = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to kill PID {}: {}", pid, error_msg);
-                    Ok(false)
-                }
+            Ok(()) => {
+                info!("Successfully killed process with PID {}", pid);
+                // Remove from registry
+                self.finish_process(run_id, None, "killed")?;
+                Ok(true)
             }
-            Err(e) => {
+            Err(e) if e.starts_with("Failed to execute") => {
                 error!("Failed to execute kill command for PID {}: {}", pid, e);
-                Err(format!("Failed to execute kill command: {}", e))
+                Err(e)
+            }
+            Err(e) => {
+                warn!("Failed to kill PID {}: {}", pid, e);
+                Ok(false)
             }
         }
     }
 
+    /// Pause a process and its children so it can be inspected without killing it
+    pub fn suspend_process(&self, run_id: i64) -> Result<bool, String> {
+        let Some(info) = self.get_process(run_id)? else {
+            return Ok(false);
+        };
+        if info.pid == 0 {
+            return Err(format!("Process {} has no PID", run_id));
+        }
+
+        super::tree::suspend_tree(info.pid)?;
+        log::info!("Suspended process {} (PID: {})", run_id, info.pid);
+        self.suspended
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(run_id);
+        Ok(true)
+    }
+
+    /// Continue a process paused by `suspend_process`
+    pub fn resume_process(&self, run_id: i64) -> Result<bool, String> {
+        let Some(info) = self.get_process(run_id)? else {
+            return Ok(false);
+        };
+
+        super::tree::resume_tree(info.pid)?;
+        log::info!("Resumed process {} (PID: {})", run_id, info.pid);
+        self.suspended
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(&run_id);
+        Ok(true)
+    }
+
+    /// Continue a suspended process so it can handle termination signals
+    fn resume_if_suspended(&self, run_id: i64, pid: u32) {
+        if self.is_suspended(run_id) {
+            if let Err(e) = super::tree::resume_tree(pid) {
+                log::warn!("Failed to resume suspended process {}: {}", run_id, e);
+            }
+        }
+    }
+
+    pub fn is_suspended(&self, run_id: i64) -> bool {
+        self.suspended
+            .lock()
+            .map(|suspended| suspended.contains(&run_id))
+            .unwrap_or(false)
+    }
+
     /// Check if a process is still running by trying to get its status
     #[allow(dead_code)]
     pub async fn is_process_running(&self, run_id: i64) -> Result<bool, String> {
@@ -431,15 +1547,44 @@ impl ProcessRegistry {
 
     /// Append to live output for a process
     pub fn append_live_output(&self, run_id: i64, output: &str) -> Result<(), String> {
-        let processes = self.processes.lock().map_err(|e| e.to_string())?;
-        if let Some(handle) = processes.get(&run_id) {
-            let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
-            live_output.push_str(output);
-            live_output.push('\n');
+        self.append_output(run_id, OutputStream::Stdout, output)
+    }
+
+    /// Capture a line of stdout/stderr and emit it as a `process-output:{run_id}` event
+    pub fn append_output(&self, run_id: i64, stream: OutputStream, line: &str) -> Result<(), String> {
+        let entry = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            let Some(handle) = processes.get(&run_id) else {
+                return Ok(());
+            };
+
+            // Keep the legacy stdout-only buffer for existing callers
+            if stream == OutputStream::Stdout {
+                let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+                live_output.push_str(line);
+                live_output.push('\n');
+            }
+
+            let mut output = handle.output.lock().map_err(|e| e.to_string())?;
+            output.push(run_id, stream, line)
+        };
+
+        if let Some(app) = self.app_handle.lock().map_err(|e| e.to_string())?.as_ref() {
+            let _ = app.emit(&format!("process-output:{}", run_id), &entry);
         }
         Ok(())
     }
 
+    /// Read captured output starting at `offset` (used for backfill after subscribing)
+    pub fn get_output(&self, run_id: i64, offset: usize, limit: usize) -> Result<ProcessOutputChunk, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let handle = processes
+            .get(&run_id)
+            .ok_or_else(|| format!("Process {} not found in registry", run_id))?;
+        let output = handle.output.lock().map_err(|e| e.to_string())?;
+        Ok(output.read_from(run_id, offset, limit))
+    }
+
     /// Get live output for a process
     pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -496,3 +1641,153 @@ impl Default for ProcessRegistryState {
         Self(Arc::new(ProcessRegistry::new()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::queue::{ConcurrencyLimits, QueuedRunKind};
+    use super::*;
+
+    fn queued(id: &str, project_path: &str) -> QueuedRun {
+        QueuedRun {
+            id: id.to_string(),
+            kind: QueuedRunKind::Execute,
+            project_path: project_path.to_string(),
+            prompt: "fix the build".to_string(),
+            model: "sonnet".to_string(),
+            env: HashMap::new(),
+            queued_at: Utc::now(),
+        }
+    }
+
+    fn register(registry: &ProcessRegistry, session_id: &str, slot: SessionSlot) -> i64 {
+        registry
+            .register_claude_session(
+                session_id.to_string(),
+                0,
+                "/work/a".to_string(),
+                "task".to_string(),
+                "sonnet".to_string(),
+                SharedChild::default(),
+                slot,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reservations_respect_limits() {
+        let registry = ProcessRegistry::new();
+        registry.run_queue().set_limits(ConcurrencyLimits {
+            max_global: Some(3),
+            max_per_project: Some(2),
+        });
+
+        let a1 = registry.try_reserve_session_slot("/work/a").unwrap();
+        let _a2 = registry.try_reserve_session_slot("/work/a").unwrap();
+        assert!(registry.try_reserve_session_slot("/work/a").is_none());
+        let _b1 = registry.try_reserve_session_slot("/work/b").unwrap();
+        assert!(registry.try_reserve_session_slot("/work/c").is_none());
+
+        // A dropped reservation, e.g. after a failed spawn, frees its slot
+        drop(a1);
+        let a1 = registry.try_reserve_session_slot("/work/a").unwrap();
+        assert!(registry.try_reserve_session_slot("/work/a").is_none());
+
+        // Registering keeps the slot taken
+        register(&registry, "s1", a1);
+        assert!(registry.try_reserve_session_slot("/work/a").is_none());
+        assert!(registry.try_reserve_session_slot("/work/c").is_none());
+    }
+
+    #[test]
+    fn test_pty_sessions_count_against_limits() {
+        let registry = ProcessRegistry::new();
+        registry.run_queue().set_limits(ConcurrencyLimits {
+            max_global: None,
+            max_per_project: Some(1),
+        });
+
+        // Registered like `PtySessions::spawn` does, without a tokio child
+        let slot = registry.try_reserve_session_slot("/work/a").unwrap();
+        let run_id = register(&registry, "pty", slot);
+        assert!(registry.try_reserve_session_slot("/work/a").is_none());
+        registry.run_queue().push(queued("q1", "/work/a")).unwrap();
+        assert!(registry.take_next_queued_run().is_none());
+
+        registry.finish_process(run_id, Some(0), "completed").unwrap();
+        assert_eq!(registry.take_next_queued_run().unwrap().0.id, "q1");
+    }
+
+    #[test]
+    fn test_queued_run_starts_when_a_session_finishes() {
+        let registry = ProcessRegistry::new();
+        registry.run_queue().set_limits(ConcurrencyLimits {
+            max_global: Some(1),
+            max_per_project: None,
+        });
+
+        // A starting session holds the only slot, so the next run queues
+        let slot = registry.try_reserve_session_slot("/work/a").unwrap();
+        assert!(registry.try_reserve_session_slot("/work/b").is_none());
+        registry.run_queue().push(queued("q1", "/work/b")).unwrap();
+        assert!(registry.take_next_queued_run().is_none());
+
+        // Registering moves it from starting to running without freeing the slot
+        let run_id = register(&registry, "s1", slot);
+        assert!(registry.take_next_queued_run().is_none());
+
+        registry.finish_process(run_id, Some(0), "completed").unwrap();
+        let (next, _slot) = registry.take_next_queued_run().unwrap();
+        assert_eq!(next.id, "q1");
+        assert!(registry.run_queue().list().unwrap().is_empty());
+        // The dequeued run already holds the freed slot
+        assert!(registry.try_reserve_session_slot("/work/a").is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sessions_keep_their_own_child() {
+        let registry = ProcessRegistry::new();
+        let mut run_ids = Vec::new();
+        let mut children = Vec::new();
+        for session_id in ["s1", "s2"] {
+            let child = tokio::process::Command::new("sleep")
+                .arg("30")
+                .kill_on_drop(true)
+                .spawn()
+                .unwrap();
+            let pid = child.id().unwrap();
+            let child: SharedChild = Arc::new(Mutex::new(Some(child)));
+            run_ids.push(
+                registry
+                    .register_claude_session(
+                        session_id.to_string(),
+                        pid,
+                        "/work/a".to_string(),
+                        "task".to_string(),
+                        "sonnet".to_string(),
+                        child.clone(),
+                        registry.try_reserve_session_slot("/work/a").unwrap(),
+                    )
+                    .unwrap(),
+            );
+            children.push(child);
+        }
+        assert_eq!(registry.get_running_claude_sessions().unwrap().len(), 2);
+
+        // Stopping one session leaves the other running
+        assert!(registry.kill_process(run_ids[0]).await.unwrap());
+        assert!(wait_for_child(&children[0]).await.is_none());
+        let still_running = children[1]
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .try_wait()
+            .unwrap()
+            .is_none();
+        assert!(still_running);
+        assert_eq!(registry.get_running_claude_sessions().unwrap().len(), 1);
+
+        assert!(registry.kill_process(run_ids[1]).await.unwrap());
+    }
+}
@@ -0,0 +1,174 @@
+//! Minimal five-field cron expressions (`minute hour day-of-month month day-of-week`)
+//! Each field accepts `*`, numbers, ranges (`1-5`), steps (`*/15`, `0-30/10`) and
+//! comma-separated lists. Times are evaluated in local time.
+use chrono::{DateTime, Datelike, Duration, Local, Timelike};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Standard cron: when both day fields are restricted either one may match
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("Invalid step '{}' in {} field", step, name))?;
+                if step == 0 {
+                    return Err(format!("Step must be at least 1 in {} field", name));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, name)?, parse_value(end, name)?)
+        } else {
+            let value = parse_value(range, name)?;
+            // `5/10` means "from 5 to the end in steps of 10"
+            (value, if step > 1 { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!(
+                "Value out of range in {} field: {} (allowed {}-{})",
+                name, range, min, max
+            ));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(value: &str, name: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' in {} field", value, name))
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Cron expression must have 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, "day-of-week")?;
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days_of_month: parse_field(fields[2], 1, 31, "day-of-month")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    fn matches_day(&self, time: &DateTime<Local>) -> bool {
+        if self.months & (1 << time.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << time.day()) != 0;
+        let dow = self.days_of_week & (1 << time.weekday().num_days_from_sunday()) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Whether the schedule fires in the minute containing `time`
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        self.matches_day(time)
+            && self.hours & (1 << time.hour()) != 0
+            && self.minutes & (1 << time.minute()) != 0
+    }
+
+    /// First minute strictly after `after` at which the schedule fires (searches up to ~4 years)
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = *after + Duration::days(366 * 4);
+
+        while time <= limit {
+            if !self.matches_day(&time) {
+                // Jump to the start of the next day
+                time = (time + Duration::days(1))
+                    .with_hour(0)
+                    .and_then(|t| t.with_minute(0))
+                    .unwrap_or(time + Duration::hours(1));
+                continue;
+            }
+            if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0).unwrap_or(time) + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << time.minute()) != 0 {
+                return Some(time);
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 9 * * 1-5").is_ok());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_15 = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            every_15.next_after(&local(2024, 3, 4, 10, 7)),
+            Some(local(2024, 3, 4, 10, 15))
+        );
+
+        // 2024-03-08 is a Friday, so the next weekday 09:00 is Monday
+        let weekdays = CronSchedule::parse("0 9 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_after(&local(2024, 3, 8, 9, 0)),
+            Some(local(2024, 3, 11, 9, 0))
+        );
+
+        let sunday = CronSchedule::parse("30 8 * * 7").unwrap();
+        assert!(sunday.matches(&local(2024, 3, 10, 8, 30)));
+    }
+}
@@ -0,0 +1,67 @@
+//! Platform helpers for graceful process shutdown
+//! Sends an interrupt (SIGINT / console CTRL+C) so the Claude CLI can flush its
+//! session transcript before the registry escalates to a hard kill.
+#[cfg(target_os = "windows")]
+use log::debug;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+
+/// Check whether a PID still refers to a live (non-zombie) process
+pub fn is_pid_alive(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::new(),
+    );
+    system
+        .process(pid)
+        .map(|process| process.status() != ProcessStatus::Zombie)
+        .unwrap_or(false)
+}
+
+/// Ask a process (and the process group it leads) to shut down cleanly
+#[cfg(not(target_os = "windows"))]
+pub fn send_interrupt(pid: u32) -> Result<(), String> {
+    super::tree::signal_tree(pid, "INT")
+}
+
+/// Ask a process to shut down cleanly
+///
+/// Windows has no signals; instead we briefly attach to the target's (hidden)
+/// console and raise CTRL+C there, which node delivers as SIGINT. Only one
+/// console can be attached at a time, so calls are serialized.
+#[cfg(target_os = "windows")]
+pub fn send_interrupt(pid: u32) -> Result<(), String> {
+    use std::sync::Mutex;
+    use windows_sys::Win32::System::Console::{
+        AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, SetConsoleCtrlHandler, CTRL_C_EVENT,
+    };
+
+    static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = CONSOLE_LOCK.lock().map_err(|e| e.to_string())?;
+
+    unsafe {
+        FreeConsole();
+        if AttachConsole(pid) == 0 {
+            return Err(format!("Failed to attach to console of PID {}", pid));
+        }
+
+        // Ignore the event ourselves while it is broadcast to the console group
+        SetConsoleCtrlHandler(None, 1);
+        let sent = GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0) != 0;
+
+        // Give the event time to be delivered before detaching
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        FreeConsole();
+        SetConsoleCtrlHandler(None, 0);
+
+        if sent {
+            debug!("Sent CTRL+C to console of PID {}", pid);
+            Ok(())
+        } else {
+            log::warn!("GenerateConsoleCtrlEvent failed for PID {}", pid);
+            Err(format!("Failed to send CTRL+C to PID {}", pid))
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Process tree containment so terminating a session takes its children with it
+//! Claude spawns node child processes that outlive a plain kill of the parent PID.
+//! On Windows every tracked process is placed in a Job Object; on Unix processes
+//! are started as their own process group and signalled as a group.
+use log::debug;
+
+/// Prepare a command so its children can be terminated together with it
+pub fn configure_command(cmd: &mut tokio::process::Command) {
+    // New process group led by the child, so `kill -- -<pid>` reaches the whole tree
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    // On Windows containment happens after spawn via `ProcessTree::attach`
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Handle to the tree of processes rooted at a tracked PID
+pub struct ProcessTree {
+    pid: u32,
+    #[cfg(target_os = "windows")]
+    job: JobHandle,
+}
+
+#[cfg(target_os = "windows")]
+struct JobHandle(windows_sys::Win32::Foundation::HANDLE);
+
+// The job handle is only used through thread-safe kernel calls
+#[cfg(target_os = "windows")]
+unsafe impl Send for JobHandle {}
+#[cfg(target_os = "windows")]
+unsafe impl Sync for JobHandle {}
+
+#[cfg(target_os = "windows")]
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+impl ProcessTree {
+    /// Start tracking the tree rooted at `pid`
+    ///
+    /// On Windows this creates a kill-on-close Job Object and assigns the process
+    /// to it; children spawned afterwards join the job automatically.
+    #[cfg(target_os = "windows")]
+    pub fn attach(pid: u32) -> Result<Self, String> {
+        use std::ptr;
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        };
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+        };
+
+        unsafe {
+            let job = CreateJobObjectW(ptr::null(), ptr::null());
+            if job.is_null() {
+                return Err("Failed to create job object".to_string());
+            }
+            let job = JobHandle(job);
+
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if SetInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const core::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            ) == 0
+            {
+                return Err("Failed to configure job object".to_string());
+            }
+
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+            if process.is_null() {
+                return Err(format!("Failed to open process {}", pid));
+            }
+            let assigned = AssignProcessToJobObject(job.0, process) != 0;
+            CloseHandle(process);
+
+            if !assigned {
+                return Err(format!("Failed to assign process {} to job object", pid));
+            }
+
+            debug!("Attached PID {} to job object", pid);
+            Ok(Self { pid, job })
+        }
+    }
+
+    /// Start tracking the tree rooted at `pid` (the process group it leads)
+    #[cfg(not(target_os = "windows"))]
+    pub fn attach(pid: u32) -> Result<Self, String> {
+        Ok(Self { pid })
+    }
+
+    /// Forcefully terminate every process in the tree
+    ///
+    /// Children spawned before the process joined the job are not members of it,
+    /// so walk the tree with `taskkill /T` first and then terminate the job to
+    /// catch anything that was reparented.
+    #[cfg(target_os = "windows")]
+    pub fn terminate(&self) -> Result<(), String> {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        let by_pid = kill_tree_by_pid(self.pid);
+        let job_terminated = unsafe { TerminateJobObject(self.job.0, 1) } != 0;
+
+        if job_terminated {
+            debug!("Terminated job object for PID {}", self.pid);
+            Ok(())
+        } else {
+            log::warn!("TerminateJobObject failed for PID {}", self.pid);
+            by_pid
+        }
+    }
+
+    /// Forcefully terminate every process in the tree
+    #[cfg(not(target_os = "windows"))]
+    pub fn terminate(&self) -> Result<(), String> {
+        kill_tree_by_pid(self.pid)
+    }
+}
+
+/// Kill a process and all of its descendants without a tracked tree handle
+#[cfg(target_os = "windows")]
+pub fn kill_tree_by_pid(pid: u32) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    let output = std::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map_err(|e| format!("Failed to execute taskkill: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Kill a process and all of its descendants without a tracked tree handle
+#[cfg(not(target_os = "windows"))]
+pub fn kill_tree_by_pid(pid: u32) -> Result<(), String> {
+    signal_tree(pid, "KILL")
+}
+
+/// Send a signal to the process group led by `pid`, falling back to the PID
+/// alone when it is not a group leader (e.g. processes spawned elsewhere)
+#[cfg(not(target_os = "windows"))]
+pub fn signal_tree(pid: u32, signal: &str) -> Result<(), String> {
+    let group = std::process::Command::new("kill")
+        .args([&format!("-{}", signal), "--", &format!("-{}", pid)])
+        .output()
+        .map_err(|e| format!("Failed to execute kill command: {}", e))?;
+
+    if group.status.success() {
+        debug!("Sent SIG{} to process group {}", signal, pid);
+        return Ok(());
+    }
+
+    let single = std::process::Command::new("kill")
+        .args([&format!("-{}", signal), &pid.to_string()])
+        .output()
+        .map_err(|e| format!("Failed to execute kill command: {}", e))?;
+
+    if single.status.success() {
+        debug!("Sent SIG{} to PID {}", signal, pid);
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&single.stderr).trim().to_string())
+    }
+}
+
+/// PIDs of a process and all of its descendants, root first
+#[cfg(target_os = "windows")]
+fn tree_pids(pid: u32) -> Vec<u32> {
+    use std::collections::HashMap;
+    use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::new());
+
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (child, process) in system.processes() {
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*child);
+        }
+    }
+
+    let mut pids = vec![pid];
+    let mut index = 0;
+    while index < pids.len() {
+        if let Some(descendants) = children.get(&Pid::from_u32(pids[index])) {
+            pids.extend(descendants.iter().map(|child| child.as_u32()));
+        }
+        index += 1;
+    }
+    pids
+}
+
+/// Pause a process and its descendants (SIGSTOP to the group)
+#[cfg(not(target_os = "windows"))]
+pub fn suspend_tree(pid: u32) -> Result<(), String> {
+    signal_tree(pid, "STOP")
+}
+
+/// Continue a process and its descendants paused by `suspend_tree`
+#[cfg(not(target_os = "windows"))]
+pub fn resume_tree(pid: u32) -> Result<(), String> {
+    signal_tree(pid, "CONT")
+}
+
+/// Pause a process and its descendants
+#[cfg(target_os = "windows")]
+pub fn suspend_tree(pid: u32) -> Result<(), String> {
+    set_tree_suspended(pid, true)
+}
+
+/// Continue a process and its descendants paused by `suspend_tree`
+#[cfg(target_os = "windows")]
+pub fn resume_tree(pid: u32) -> Result<(), String> {
+    set_tree_suspended(pid, false)
+}
+
+/// Windows has no process-wide stop signal; NtSuspendProcess/NtResumeProcess
+/// (ntdll) suspend every thread of a process, applied to each process in the tree.
+#[cfg(target_os = "windows")]
+fn set_tree_suspended(pid: u32, suspend: bool) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SUSPEND_RESUME};
+
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn NtSuspendProcess(process: HANDLE) -> i32;
+        fn NtResumeProcess(process: HANDLE) -> i32;
+    }
+
+    let mut root_result = Ok(());
+    for target in tree_pids(pid) {
+        let result = unsafe {
+            let handle = OpenProcess(PROCESS_SUSPEND_RESUME, 0, target);
+            if handle.is_null() {
+                Err(format!("Failed to open process {}", target))
+            } else {
+                let status = if suspend {
+                    NtSuspendProcess(handle)
+                } else {
+                    NtResumeProcess(handle)
+                };
+                CloseHandle(handle);
+                if status < 0 {
+                    Err(format!("NTSTATUS {:#x} for process {}", status, target))
+                } else {
+                    Ok(())
+                }
+            }
+        };
+
+        match result {
+            Err(e) if target == pid => root_result = Err(e),
+            Err(e) => debug!("Skipping descendant: {}", e),
+            Ok(()) => {}
+        }
+    }
+    root_result
+}
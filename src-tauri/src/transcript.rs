@@ -0,0 +1,363 @@
+//! Parser for Claude Code session transcripts (`~/.claude/projects/<project>/<session>.jsonl`)
+//! that turns records into renderable messages and reads them a page at a time
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Text longer than this (e.g. a tool result holding a whole file) is cut
+pub const MAX_TEXT_BYTES: usize = 64 * 1024;
+
+/// Attachments larger than this are sent without their data
+pub const MAX_INLINE_ATTACHMENT_BYTES: usize = 512 * 1024;
+
+/// An image or document sent to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptAttachment {
+    /// "image" or "document"
+    pub kind: String,
+    pub media_type: Option<String>,
+    /// Size of the base64 data
+    pub size: usize,
+    /// Base64 data, left out above `MAX_INLINE_ATTACHMENT_BYTES`
+    pub data: Option<String>,
+    pub url: Option<String>,
+}
+
+/// One content block of a transcript message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptBlock {
+    Text {
+        text: String,
+        truncated: bool,
+    },
+    Thinking {
+        thinking: String,
+        /// The thinking was encrypted by the API and has no readable text
+        redacted: bool,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        truncated: bool,
+        is_error: bool,
+        attachments: Vec<TranscriptAttachment>,
+    },
+    Attachment(TranscriptAttachment),
+    /// A block type this parser doesn't know yet
+    Unknown {
+        block_type: String,
+    },
+}
+
+/// A user, assistant, system or summary record of a transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    /// Position among the messages of the transcript
+    pub index: usize,
+    /// "user", "assistant", "system" or "summary"
+    pub kind: String,
+    pub uuid: Option<String>,
+    pub parent_uuid: Option<String>,
+    pub timestamp: Option<String>,
+    pub model: Option<String>,
+    /// Generated by Claude Code rather than typed by the user
+    pub is_meta: bool,
+    /// Part of a subagent (Task tool) conversation
+    pub is_sidechain: bool,
+    pub blocks: Vec<TranscriptBlock>,
+    pub usage: Option<Value>,
+}
+
+/// A window of transcript messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptPage {
+    pub messages: Vec<TranscriptMessage>,
+    pub offset: usize,
+    /// Messages in the whole transcript
+    pub total: usize,
+    pub has_more: bool,
+    /// Lines that weren't valid JSON, e.g. a partially written last line
+    pub skipped_lines: usize,
+}
+
+/// Record kinds that become messages; everything else (file snapshots, queue
+/// operations, ...) is bookkeeping
+const MESSAGE_KINDS: &[&str] = &["user", "assistant", "system", "summary"];
+
+/// Just the record type, so records outside the requested window are cheap to skip
+#[derive(Deserialize)]
+struct RecordKind {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+fn truncate(text: &str) -> (String, bool) {
+    if text.len() <= MAX_TEXT_BYTES {
+        return (text.to_string(), false);
+    }
+    let mut end = MAX_TEXT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (text[..end].to_string(), true)
+}
+
+fn text_block(text: &str) -> TranscriptBlock {
+    let (text, truncated) = truncate(text);
+    TranscriptBlock::Text { text, truncated }
+}
+
+fn parse_attachment(block: &Value, kind: &str) -> TranscriptAttachment {
+    let source = &block["source"];
+    let data = source["data"].as_str();
+    let size = data.map(str::len).unwrap_or(0);
+    TranscriptAttachment {
+        kind: kind.to_string(),
+        media_type: source["media_type"].as_str().map(str::to_string),
+        size,
+        data: data
+            .filter(|_| size <= MAX_INLINE_ATTACHMENT_BYTES)
+            .map(str::to_string),
+        url: source["url"].as_str().map(str::to_string),
+    }
+}
+
+fn parse_tool_result(block: &Value) -> TranscriptBlock {
+    let mut texts = Vec::new();
+    let mut attachments = Vec::new();
+    match &block["content"] {
+        Value::String(text) => texts.push(text.as_str()),
+        Value::Array(items) => {
+            for item in items {
+                match item["type"].as_str() {
+                    Some("text") => texts.extend(item["text"].as_str()),
+                    Some(kind @ ("image" | "document")) => {
+                        attachments.push(parse_attachment(item, kind))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    let (content, truncated) = truncate(&texts.join("\n"));
+    TranscriptBlock::ToolResult {
+        tool_use_id: block["tool_use_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        content,
+        truncated,
+        is_error: block["is_error"].as_bool().unwrap_or(false),
+        attachments,
+    }
+}
+
+fn parse_block(block: &Value) -> TranscriptBlock {
+    match block["type"].as_str().unwrap_or_default() {
+        "text" => text_block(block["text"].as_str().unwrap_or_default()),
+        "thinking" => TranscriptBlock::Thinking {
+            thinking: block["thinking"].as_str().unwrap_or_default().to_string(),
+            redacted: false,
+        },
+        "redacted_thinking" => TranscriptBlock::Thinking {
+            thinking: String::new(),
+            redacted: true,
+        },
+        "tool_use" | "server_tool_use" => TranscriptBlock::ToolUse {
+            id: block["id"].as_str().unwrap_or_default().to_string(),
+            name: block["name"].as_str().unwrap_or_default().to_string(),
+            input: block["input"].clone(),
+        },
+        "tool_result" => parse_tool_result(block),
+        kind @ ("image" | "document") => TranscriptBlock::Attachment(parse_attachment(block, kind)),
+        other => TranscriptBlock::Unknown {
+            block_type: other.to_string(),
+        },
+    }
+}
+
+/// Turn one transcript record into a message; `None` for bookkeeping records
+pub fn parse_record(record: &Value, index: usize) -> Option<TranscriptMessage> {
+    let kind = record["type"].as_str()?;
+    let blocks = match kind {
+        "user" | "assistant" => match &record["message"]["content"] {
+            Value::String(text) => vec![text_block(text)],
+            Value::Array(blocks) => blocks.iter().map(parse_block).collect(),
+            _ => Vec::new(),
+        },
+        "system" => record["content"]
+            .as_str()
+            .map(|text| vec![text_block(text)])
+            .unwrap_or_default(),
+        "summary" => vec![text_block(record["summary"].as_str().unwrap_or_default())],
+        _ => return None,
+    };
+    let message = &record["message"];
+
+    Some(TranscriptMessage {
+        index,
+        kind: kind.to_string(),
+        uuid: record["uuid"].as_str().map(str::to_string),
+        parent_uuid: record["parentUuid"].as_str().map(str::to_string),
+        timestamp: record["timestamp"].as_str().map(str::to_string),
+        model: message["model"].as_str().map(str::to_string),
+        is_meta: record["isMeta"].as_bool().unwrap_or(false),
+        is_sidechain: record["isSidechain"].as_bool().unwrap_or(false),
+        blocks,
+        usage: message.get("usage").cloned(),
+    })
+}
+
+/// Read `limit` messages starting at message `offset` of a transcript
+///
+/// Only the records in the window are fully parsed; the rest are just counted.
+pub fn read_page(path: &Path, offset: usize, limit: usize) -> Result<TranscriptPage, String> {
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    let mut messages = Vec::new();
+    let mut total = 0;
+    let mut skipped_lines = 0;
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read session file: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(record) = serde_json::from_str::<RecordKind>(&line) else {
+            skipped_lines += 1;
+            continue;
+        };
+        if !record
+            .kind
+            .as_deref()
+            .is_some_and(|kind| MESSAGE_KINDS.contains(&kind))
+        {
+            continue;
+        }
+
+        if total >= offset && messages.len() < limit {
+            let message = serde_json::from_str::<Value>(&line)
+                .ok()
+                .and_then(|record| parse_record(&record, total));
+            messages.extend(message);
+        }
+        total += 1;
+    }
+
+    Ok(TranscriptPage {
+        has_more: offset + messages.len() < total,
+        messages,
+        offset,
+        total,
+        skipped_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_record_blocks() {
+        let record = json!({
+            "type": "assistant",
+            "uuid": "a1",
+            "parentUuid": "u1",
+            "message": {
+                "model": "claude-sonnet-4-5",
+                "content": [
+                    {"type": "thinking", "thinking": "Let me look"},
+                    {"type": "redacted_thinking", "data": "xyz"},
+                    {"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "a.rs"}},
+                    {"type": "citations_delta"}
+                ],
+                "usage": {"input_tokens": 10}
+            }
+        });
+        let message = parse_record(&record, 3).unwrap();
+        assert_eq!(message.index, 3);
+        assert_eq!(message.parent_uuid.as_deref(), Some("u1"));
+        assert_eq!(message.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert!(matches!(
+            &message.blocks[1],
+            TranscriptBlock::Thinking { redacted: true, .. }
+        ));
+        assert!(
+            matches!(&message.blocks[2], TranscriptBlock::ToolUse { name, .. } if name == "Read")
+        );
+        assert!(matches!(
+            &message.blocks[3],
+            TranscriptBlock::Unknown { .. }
+        ));
+
+        let record = json!({
+            "type": "user",
+            "message": {"content": [{
+                "type": "tool_result",
+                "tool_use_id": "t1",
+                "is_error": true,
+                "content": [
+                    {"type": "text", "text": "x".repeat(MAX_TEXT_BYTES + 10)},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBO"}}
+                ]
+            }]}
+        });
+        let message = parse_record(&record, 0).unwrap();
+        let TranscriptBlock::ToolResult {
+            content,
+            truncated,
+            is_error,
+            attachments,
+            ..
+        } = &message.blocks[0]
+        else {
+            panic!("expected a tool result");
+        };
+        assert_eq!(content.len(), MAX_TEXT_BYTES);
+        assert!(*truncated && *is_error);
+        assert_eq!(attachments[0].data.as_deref(), Some("iVBO"));
+
+        assert!(parse_record(&json!({"type": "file-history-snapshot"}), 0).is_none());
+    }
+
+    #[test]
+    fn test_read_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let mut lines: Vec<String> = (0..5)
+            .map(|i| {
+                json!({"type": "user", "message": {"content": format!("prompt {}", i)}}).to_string()
+            })
+            .collect();
+        lines.insert(2, json!({"type": "file-history-snapshot"}).to_string());
+        lines.push("{\"type\": \"assist".to_string());
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let page = read_page(&path, 1, 2).unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(page.skipped_lines, 1);
+        assert!(page.has_more);
+        let indexes: Vec<usize> = page.messages.iter().map(|m| m.index).collect();
+        assert_eq!(indexes, vec![1, 2]);
+        assert_eq!(
+            page.messages[1].blocks,
+            vec![TranscriptBlock::Text {
+                text: "prompt 2".to_string(),
+                truncated: false
+            }]
+        );
+
+        let page = read_page(&path, 4, 10).unwrap();
+        assert_eq!(page.messages.len(), 1);
+        assert!(!page.has_more);
+    }
+}
@@ -0,0 +1,284 @@
+//! System tray icon whose menu switches the upstream Claude Code uses
+//!
+//! The menu lists the provider configs and the enabled relay stations; picking
+//! one applies it through the same backend logic as the provider and relay
+//! station pages, so the main window does not have to be opened.
+use crate::commands::app_lock::{self, ProtectedAction};
+use crate::commands::provider;
+use crate::commands::relay_stations::{self, ConfigUsageStatus, RelayStation, RelayStationManager};
+use crate::t;
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Menu, MenuBuilder, MenuItem, SubmenuBuilder};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager};
+
+const TRAY_ID: &str = "main";
+
+const SHOW_ID: &str = "show";
+const QUIT_ID: &str = "quit";
+const PROVIDER_PREFIX: &str = "provider:";
+const STATION_PREFIX: &str = "station:";
+
+/// What a tray menu item does when clicked
+#[derive(Debug, PartialEq)]
+enum TrayAction<'a> {
+    ShowWindow,
+    Quit,
+    SwitchProvider(&'a str),
+    SwitchStation(&'a str),
+}
+
+fn parse_action(id: &str) -> Option<TrayAction<'_>> {
+    match id {
+        SHOW_ID => Some(TrayAction::ShowWindow),
+        QUIT_ID => Some(TrayAction::Quit),
+        _ => {
+            if let Some(provider_id) = id.strip_prefix(PROVIDER_PREFIX) {
+                Some(TrayAction::SwitchProvider(provider_id))
+            } else {
+                id.strip_prefix(STATION_PREFIX)
+                    .map(TrayAction::SwitchStation)
+            }
+        }
+    }
+}
+
+/// Enabled relay stations with the configuration last applied from each,
+/// which is what the tray re-applies
+fn stations_with_usage(app: &AppHandle) -> Vec<(RelayStation, Option<ConfigUsageStatus>)> {
+    let state = app.state::<Mutex<Option<RelayStationManager>>>();
+    let Ok(manager_lock) = state.lock() else {
+        return Vec::new();
+    };
+    let Some(manager) = manager_lock.as_ref() else {
+        return Vec::new();
+    };
+
+    let stations = manager.list_stations().unwrap_or_else(|e| {
+        log::warn!("Failed to list relay stations for the tray: {}", e);
+        Vec::new()
+    });
+    let usage = manager.get_config_usage_status().unwrap_or_default();
+    stations
+        .into_iter()
+        .filter(|station| station.enabled)
+        .map(|station| {
+            let applied = usage
+                .iter()
+                .find(|status| status.station_id == station.id)
+                .cloned();
+            (station, applied)
+        })
+        .collect()
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let providers = provider::load_providers_from_file().unwrap_or_else(|e| {
+        log::warn!("Failed to load providers for the tray: {}", e);
+        Vec::new()
+    });
+    let current_provider = provider::get_current_provider_id().ok().flatten();
+    let current = provider::read_current_config().ok();
+
+    let mut providers_menu = SubmenuBuilder::new(app, t!("tray.providers"));
+    if providers.is_empty() {
+        providers_menu = providers_menu.item(&MenuItem::new(
+            app,
+            t!("tray.no_providers"),
+            false,
+            None::<&str>,
+        )?);
+    }
+    for config in &providers {
+        providers_menu = providers_menu.item(&CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROVIDER_PREFIX, config.id),
+            &config.name,
+            true,
+            current_provider.as_deref() == Some(config.id.as_str()),
+            None::<&str>,
+        )?);
+    }
+
+    let stations = stations_with_usage(app);
+    let mut stations_menu = SubmenuBuilder::new(app, t!("tray.stations"));
+    if stations.is_empty() {
+        stations_menu = stations_menu.item(&MenuItem::new(
+            app,
+            t!("tray.no_stations"),
+            false,
+            None::<&str>,
+        )?);
+    }
+    for (station, applied) in &stations {
+        let active = match (applied, &current) {
+            (Some(applied), Some(current)) => {
+                current.anthropic_base_url.as_deref() == Some(applied.base_url.as_str())
+                    && current.anthropic_auth_token.as_deref() == Some(applied.token.as_str())
+            }
+            _ => false,
+        };
+        // A station can only be switched to once it was configured in the app
+        let label = match applied {
+            Some(_) => station.name.clone(),
+            None => t!("tray.station_not_configured", "name" => &station.name),
+        };
+        stations_menu = stations_menu.item(&CheckMenuItem::with_id(
+            app,
+            format!("{}{}", STATION_PREFIX, station.id),
+            label,
+            applied.is_some(),
+            active,
+            None::<&str>,
+        )?);
+    }
+
+    MenuBuilder::new(app)
+        .item(&providers_menu.build()?)
+        .item(&stations_menu.build()?)
+        .separator()
+        .text(SHOW_ID, t!("tray.show"))
+        .text(QUIT_ID, t!("tray.quit"))
+        .build()
+}
+
+pub(crate) fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Apply the configuration last used with a relay station again
+pub(crate) async fn switch_station(app: &AppHandle, station_id: &str) -> Result<String, String> {
+    let (config, applied) = {
+        let state = app.state::<Mutex<Option<RelayStationManager>>>();
+        let manager_lock = state
+            .lock()
+            .map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        relay_stations::station_provider_config(manager, station_id)?
+    };
+    let message = provider::switch_provider(app.clone(), config).await?;
+    relay_stations::record_config_usage(
+        applied.station_id,
+        applied.base_url,
+        applied.token,
+        app.clone(),
+    )
+    .await?;
+    Ok(message)
+}
+
+/// Switch to the provider or station of a tray menu item
+async fn switch_upstream(app: &AppHandle, id: &str) -> Result<String, String> {
+    // The passcode is asked for in the window
+    if let Err(e) = app_lock::require_unlocked(app, ProtectedAction::SwitchConfig).await {
+        show_main_window(app);
+        return Err(e);
+    }
+    match parse_action(id) {
+        Some(TrayAction::SwitchProvider(provider_id)) => {
+            let config = provider::find_provider(provider_id)?;
+            provider::switch_provider(app.clone(), config).await
+        }
+        Some(TrayAction::SwitchStation(station_id)) => switch_station(app, station_id).await,
+        _ => Err(format!("Not a switch menu item: {}", id)),
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    match parse_action(id) {
+        Some(TrayAction::ShowWindow) => show_main_window(app),
+        Some(TrayAction::Quit) => app.exit(0),
+        Some(TrayAction::SwitchProvider(_) | TrayAction::SwitchStation(_)) => {
+            let app = app.clone();
+            let id = id.to_string();
+            tauri::async_runtime::spawn(async move {
+                match switch_upstream(&app, &id).await {
+                    Ok(message) => {
+                        log::info!("Switched upstream from the tray: {}", message);
+                        let _ = app.emit("provider-changed", &id);
+                    }
+                    Err(e) => {
+                        log::error!("Tray switch {} failed: {}", id, e);
+                        let _ = app.emit("provider-switch-failed", &e);
+                    }
+                }
+                // Put the check mark back on the actual upstream
+                refresh_menu(&app);
+            });
+        }
+        None => log::warn!("Unknown tray menu item: {}", id),
+    }
+}
+
+/// Create the tray icon; called once from the app setup
+pub fn setup(app: &AppHandle) -> tauri::Result<()> {
+    let menu = build_menu(app)?;
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Claude Workbench")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu after providers, stations, the active upstream or
+/// the locale changed
+pub fn refresh_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("Failed to update the tray menu: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to build the tray menu: {}", e),
+    }
+}
+
+/// Rebuild the tray menu, for changes the backend does not see itself
+#[tauri::command]
+pub fn refresh_tray_menu(app: AppHandle) {
+    refresh_menu(&app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(parse_action("show"), Some(TrayAction::ShowWindow));
+        assert_eq!(parse_action("quit"), Some(TrayAction::Quit));
+        assert_eq!(
+            parse_action("provider:anyrouter"),
+            Some(TrayAction::SwitchProvider("anyrouter"))
+        );
+        assert_eq!(
+            parse_action("station:1b2c:3d"),
+            Some(TrayAction::SwitchStation("1b2c:3d"))
+        );
+        assert_eq!(parse_action("other"), None);
+    }
+}
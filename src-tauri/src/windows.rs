@@ -0,0 +1,439 @@
+//! Window size and position kept across restarts, and extra windows for a
+//! Claude session or a relay station dashboard
+//!
+//! A window's state is saved when it is closed and when the app quits, keyed
+//! by its label, and applied again the next time a window with that label
+//! opens. Extra windows load the same frontend; an initialization script
+//! tells it which view to show.
+use crate::commands::agents::AgentDb;
+use crate::commands::relay_stations::RelayStationManager;
+use crate::t;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, LogicalPosition, LogicalSize, Manager, State, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, Window, WindowEvent,
+};
+
+pub const MAIN_WINDOW: &str = "main";
+const SESSION_PREFIX: &str = "session-";
+const STATION_PREFIX: &str = "station-";
+
+/// Saved states kept; the ones of windows not opened for longest go first
+const MAX_SAVED_STATES: i64 = 100;
+
+/// Size of an extra window opened for the first time, in logical pixels
+const DEFAULT_SIZE: (f64, f64) = (900.0, 700.0);
+const MIN_SIZE: (f64, f64) = (480.0, 360.0);
+
+/// Part of a window that has to be on a monitor for its position to be
+/// restored, in logical pixels
+const VISIBLE_MARGIN: f64 = 48.0;
+
+/// Outer position and inner size of a window, in logical pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub maximized: bool,
+}
+
+/// An open window of the app
+#[derive(Debug, Clone, Serialize)]
+pub struct AppWindowInfo {
+    pub label: String,
+    /// "main", "session" or "station"
+    pub kind: String,
+    pub title: String,
+    pub focused: bool,
+}
+
+/// What an extra window shows, handed to its frontend as
+/// `window.__WORKBENCH_WINDOW__`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WindowView {
+    #[serde(rename_all = "camelCase")]
+    Session {
+        session_id: String,
+        project_path: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Station { station_id: String },
+}
+
+/// Create the `window_states` table
+pub fn create_window_states_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS window_states (
+            label TEXT PRIMARY KEY,
+            x REAL NOT NULL,
+            y REAL NOT NULL,
+            width REAL NOT NULL,
+            height REAL NOT NULL,
+            maximized INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn save_state(conn: &Connection, state: &WindowState, now: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO window_states (label, x, y, width, height, maximized, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            state.label,
+            state.x,
+            state.y,
+            state.width,
+            state.height,
+            state.maximized,
+            now
+        ],
+    )
+    .map_err(|e| format!("Failed to save window state: {}", e))?;
+    conn.execute(
+        "DELETE FROM window_states WHERE label NOT IN (
+            SELECT label FROM window_states ORDER BY updated_at DESC LIMIT ?1
+         )",
+        params![MAX_SAVED_STATES],
+    )
+    .map_err(|e| format!("Failed to prune window states: {}", e))?;
+    Ok(())
+}
+
+pub fn load_state(conn: &Connection, label: &str) -> Result<Option<WindowState>, String> {
+    conn.query_row(
+        "SELECT label, x, y, width, height, maximized FROM window_states WHERE label = ?1",
+        params![label],
+        |row| {
+            Ok(WindowState {
+                label: row.get(0)?,
+                x: row.get(1)?,
+                y: row.get(2)?,
+                width: row.get(3)?,
+                height: row.get(4)?,
+                maximized: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read window state: {}", e))
+}
+
+/// Window label for a session or station id; labels allow only a few characters
+pub fn window_label(prefix: &str, id: &str) -> String {
+    let id: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", prefix, id)
+}
+
+fn window_kind(label: &str) -> &'static str {
+    if label.starts_with(SESSION_PREFIX) {
+        "session"
+    } else if label.starts_with(STATION_PREFIX) {
+        "station"
+    } else {
+        "main"
+    }
+}
+
+/// Whether enough of the window's top edge lies on one of the monitors,
+/// given as logical `(x, y, width, height)`
+pub fn is_on_screen(state: &WindowState, monitors: &[(f64, f64, f64, f64)]) -> bool {
+    monitors.iter().any(|&(x, y, width, height)| {
+        state.x + state.width - VISIBLE_MARGIN >= x
+            && state.x + VISIBLE_MARGIN <= x + width
+            && state.y >= y - VISIBLE_MARGIN / 2.0
+            && state.y + VISIBLE_MARGIN <= y + height
+    })
+}
+
+fn monitor_rects(app: &AppHandle) -> Vec<(f64, f64, f64, f64)> {
+    app.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| {
+            let scale = monitor.scale_factor();
+            let position = monitor.position().to_logical::<f64>(scale);
+            let size = monitor.size().to_logical::<f64>(scale);
+            (position.x, position.y, size.width, size.height)
+        })
+        .collect()
+}
+
+/// State of a window as it is now; a maximized window keeps the size and
+/// position it had before, so it returns there when restored
+fn capture_state(window: &WebviewWindow, previous: Option<WindowState>) -> Option<WindowState> {
+    if window.is_minimized().unwrap_or(false) {
+        return None;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    if maximized {
+        if let Some(previous) = previous {
+            return Some(WindowState {
+                maximized,
+                ..previous
+            });
+        }
+    }
+    let scale = window.scale_factor().ok()?;
+    let position = window.outer_position().ok()?.to_logical::<f64>(scale);
+    let size = window.inner_size().ok()?.to_logical::<f64>(scale);
+    Some(WindowState {
+        label: window.label().to_string(),
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized,
+    })
+}
+
+fn persist(window: &WebviewWindow) {
+    let db = window.app_handle().state::<AgentDb>();
+    let Ok(conn) = db.0.lock() else {
+        return;
+    };
+    let previous = load_state(&conn, window.label()).ok().flatten();
+    if let Some(state) = capture_state(window, previous) {
+        if let Err(e) = save_state(&conn, &state, chrono::Utc::now().timestamp()) {
+            log::warn!(
+                "Failed to save the state of window {}: {}",
+                window.label(),
+                e
+            );
+        }
+    }
+}
+
+/// Save the state of every open window, before the app quits
+pub fn persist_all(app: &AppHandle) {
+    for window in app.webview_windows().values() {
+        persist(window);
+    }
+}
+
+/// Remember a window's size and position when it is closed
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { .. } = event {
+        if let Some(window) = window.app_handle().get_webview_window(window.label()) {
+            persist(&window);
+        }
+    }
+}
+
+fn saved_state(app: &AppHandle, label: &str) -> Option<WindowState> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().ok()?;
+    load_state(&conn, label).unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        None
+    })
+}
+
+/// Give the main window the size and position it had when last closed
+pub fn restore_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW) else {
+        return;
+    };
+    let Some(state) = saved_state(app, MAIN_WINDOW) else {
+        return;
+    };
+    let _ = window.set_size(LogicalSize::new(
+        state.width.max(MIN_SIZE.0),
+        state.height.max(MIN_SIZE.1),
+    ));
+    if is_on_screen(&state, &monitor_rects(app)) {
+        let _ = window.set_position(LogicalPosition::new(state.x, state.y));
+    }
+    if state.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Focus the window with `label`, or open it showing `view`
+fn open_window(
+    app: &AppHandle,
+    label: &str,
+    title: &str,
+    view: &WindowView,
+) -> Result<String, String> {
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(label.to_string());
+    }
+
+    let view = serde_json::to_string(view).map_err(|e| e.to_string())?;
+    let mut builder = WebviewWindowBuilder::new(app, label, WebviewUrl::App("index.html".into()))
+        .title(title)
+        .initialization_script(format!("window.__WORKBENCH_WINDOW__ = {};", view))
+        .min_inner_size(MIN_SIZE.0, MIN_SIZE.1)
+        .resizable(true);
+    match saved_state(app, label) {
+        Some(state) => {
+            builder = builder.inner_size(state.width, state.height);
+            if is_on_screen(&state, &monitor_rects(app)) {
+                builder = builder.position(state.x, state.y);
+            }
+            builder = builder.maximized(state.maximized);
+        }
+        None => builder = builder.inner_size(DEFAULT_SIZE.0, DEFAULT_SIZE.1),
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to open window {}: {}", label, e))?;
+    log::info!("Opened window {}", label);
+    Ok(label.to_string())
+}
+
+/// Open a window following one Claude session, or focus it if it is open;
+/// returns the window label
+#[tauri::command]
+pub async fn open_session_window(
+    app: AppHandle,
+    session_id: String,
+    project_path: Option<String>,
+    title: Option<String>,
+) -> Result<String, String> {
+    if session_id.trim().is_empty() {
+        return Err("A session id is required".to_string());
+    }
+    let label = window_label(SESSION_PREFIX, &session_id);
+    let name = title
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| session_id.clone());
+    open_window(
+        &app,
+        &label,
+        &t!("windows.session_title", "name" => &name),
+        &WindowView::Session {
+            session_id,
+            project_path,
+        },
+    )
+}
+
+/// Open the dashboard of a relay station in its own window, or focus it if
+/// it is open; returns the window label
+#[tauri::command]
+pub async fn open_station_window(
+    app: AppHandle,
+    relay_manager: State<'_, Mutex<Option<RelayStationManager>>>,
+    station_id: String,
+) -> Result<String, String> {
+    let station = {
+        let manager_lock = relay_manager
+            .lock()
+            .map_err(|e| t!("relay.lock_error", "error" => &e.to_string()))?;
+        let manager = manager_lock
+            .as_ref()
+            .ok_or_else(|| t!("relay.manager_not_initialized"))?;
+        manager
+            .get_station(&station_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Relay station not found: {}", station_id))?
+    };
+    open_window(
+        &app,
+        &window_label(STATION_PREFIX, &station_id),
+        &t!("windows.station_title", "name" => &station.name),
+        &WindowView::Station { station_id },
+    )
+}
+
+/// The app's open windows, main window first
+#[tauri::command]
+pub fn list_app_windows(app: AppHandle) -> Vec<AppWindowInfo> {
+    let mut windows: Vec<AppWindowInfo> = app
+        .webview_windows()
+        .values()
+        .map(|window| AppWindowInfo {
+            label: window.label().to_string(),
+            kind: window_kind(window.label()).to_string(),
+            title: window.title().unwrap_or_default(),
+            focused: window.is_focused().unwrap_or(false),
+        })
+        .collect();
+    windows.sort_by(|a, b| {
+        (a.label != MAIN_WINDOW)
+            .cmp(&(b.label != MAIN_WINDOW))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(label: &str, x: f64, y: f64) -> WindowState {
+        WindowState {
+            label: label.to_string(),
+            x,
+            y,
+            width: 800.0,
+            height: 600.0,
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn test_window_states() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_window_states_table(&conn).unwrap();
+        assert_eq!(load_state(&conn, MAIN_WINDOW).unwrap(), None);
+
+        let main = state(MAIN_WINDOW, 100.0, 80.0);
+        save_state(&conn, &main, 1).unwrap();
+        let moved = WindowState {
+            maximized: true,
+            ..state(MAIN_WINDOW, 10.0, 20.0)
+        };
+        save_state(&conn, &moved, 2).unwrap();
+        assert_eq!(load_state(&conn, MAIN_WINDOW).unwrap(), Some(moved));
+
+        for n in 0..MAX_SAVED_STATES {
+            save_state(&conn, &state(&format!("session-{}", n), 0.0, 0.0), 10 + n).unwrap();
+        }
+        // The least recently saved state made room
+        assert_eq!(load_state(&conn, MAIN_WINDOW).unwrap(), None);
+        assert!(load_state(&conn, "session-0").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_labels_and_screen_check() {
+        assert_eq!(
+            window_label(SESSION_PREFIX, "3f2a-11ef.b/c"),
+            "session-3f2a-11ef_b_c"
+        );
+        assert_eq!(window_kind("station-abc"), "station");
+        assert_eq!(window_kind(MAIN_WINDOW), "main");
+
+        let monitors = [(0.0, 0.0, 1920.0, 1080.0), (1920.0, 0.0, 1280.0, 1024.0)];
+        assert!(is_on_screen(&state(MAIN_WINDOW, 100.0, 100.0), &monitors));
+        assert!(is_on_screen(&state(MAIN_WINDOW, 2500.0, 300.0), &monitors));
+        // Mostly off the left edge, but its right part is still reachable
+        assert!(is_on_screen(&state(MAIN_WINDOW, -700.0, 100.0), &monitors));
+        // On a monitor that was unplugged
+        assert!(!is_on_screen(&state(MAIN_WINDOW, 3500.0, 100.0), &monitors));
+        assert!(!is_on_screen(&state(MAIN_WINDOW, 100.0, -500.0), &monitors));
+        assert!(!is_on_screen(&state(MAIN_WINDOW, 100.0, 1070.0), &monitors));
+    }
+}
@@ -1388,11 +1388,12 @@ export const api = {
     order?: "asc" | "desc"
   ): Promise<ProjectUsage[]> {
     try {
-      return await invoke<ProjectUsage[]>("get_session_stats", {
+      const result = await invoke<{ items: ProjectUsage[] }>("get_session_stats", {
         since,
         until,
         order,
       });
+      return result.items;
     } catch (error) {
       console.error("Failed to get session stats:", error);
       throw error;
@@ -1418,7 +1419,8 @@ export const api = {
    */
   async getUsageByApiBaseUrl(): Promise<ApiBaseUrlUsage[]> {
     try {
-      return await invoke<ApiBaseUrlUsage[]>("get_usage_by_api_base_url");
+      const result = await invoke<{ items: ApiBaseUrlUsage[] }>("get_usage_by_api_base_url");
+      return result.items;
     } catch (error) {
       console.error("Failed to get usage by API Base URL:", error);
       throw error;
@@ -1432,7 +1434,8 @@ export const api = {
    */
   async getUsageDetails(limit?: number): Promise<UsageEntry[]> {
     try {
-      return await invoke<UsageEntry[]>("get_usage_details", { limit });
+      const result = await invoke<{ items: UsageEntry[] }>("get_usage_details", { limit });
+      return result.items;
     } catch (error) {
       console.error("Failed to get usage details:", error);
       throw error;